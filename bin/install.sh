@@ -75,7 +75,7 @@ while ! command -v tinychain &> /dev/null
 do
     # install TinyChain
     echo "installing TinyChain"
-    cargo install tinychain --features=tensor
+    cargo install tinychain --features=arrayfire
 done
 
 echo 'TinyChain installed successfully--remember to run source $HOME/.cargo/env before running the tinychain command'
@@ -8,6 +8,7 @@ import tinychain.ml.cnn
 import tinychain.ml.dnn
 import tinychain.op
 import tinychain.ref
+import tinychain.txn
 
 from tinychain.cluster import Cluster, write_cluster
 from tinychain.collection import Column
@@ -16,6 +17,6 @@ from tinychain.decorators import closure, delete_method, get_method, post_method
 from tinychain.ml import linalg
 from tinychain.ref import After, Case, If, While
 from tinychain.reflect import Meta, Object
-from tinychain.state import Class, Instance, Map, State, Scalar, Stream, Tuple
+from tinychain.state import Class, Closure, Instance, Map, State, Scalar, Stream, Tuple
 from tinychain.value import *
 from tinychain.util import form_of, print_json, to_json, uri, use, Context, URI
@@ -31,6 +31,34 @@ class Chain(State):
 
         return ref.Put(uri(self), None, value)
 
+    def history(self):
+        """Return the ID of each past transaction recorded by this `Chain`, in order."""
+
+        from .state import Tuple
+        return Tuple(ref.Get(uri(self).append("chain/history")))
+
+    def as_of(self, txn_id):
+        """Return a copy of the subject of this `Chain` as of the given past transaction ID."""
+
+        return State(ref.Get(uri(self).append("chain/history"), txn_id))
+
+    def changes(self, since=None):
+        """
+        Return a :class:`Stream` of each change to a row of the subject of this `Chain` committed after the given
+        past transaction ID (or every change, if `since` is `None`), in order.
+
+        Each change is a tuple of (transaction ID, "insert" | "update" | "delete", key, values).
+        """
+
+        from .state import Stream
+        return Stream(ref.Get(uri(self).append("chain/changes"), since))
+
+    def revert(self, txn_id):
+        """Revert the subject of this `Chain` to its state as of the given past transaction ID."""
+
+        from .value import Nil
+        return Nil(ref.Put(uri(self).append("chain/history"), None, txn_id))
+
     # TODO: delete these overrides and make MethodSubject compatible with Chain
     def _get(self, name, key=None, rtype=State):
         from .value import Nil
@@ -54,7 +82,11 @@ class Chain(State):
 
 
 class Block(Chain):
-    """A :class:`Chain` which keeps track of the entire update history of its subject."""
+    """
+    A :class:`Chain` which keeps track of the entire update history of its subject.
+
+    Use a `Block` chain for data which must be auditable. A new replica of a `Block` chain copies its entire history.
+    """
 
     __uri__ = uri(Chain) + "/block"
 
@@ -63,6 +95,9 @@ class Sync(Chain):
     """
     A :class:`Chain` which keeps track of only the current transaction's operations,
     in order to recover from a transaction failure (e.g. if the host crashes).
+
+    Use a `Sync` chain for bulk data whose history is not needed. A new replica of a `Sync` chain copies only the
+    latest state of its subject.
     """
 
     __uri__ = uri(Chain) + "/sync"
@@ -28,6 +28,28 @@ class Collection(State):
 
         return cls(Post(uri(cls) + "/copy_from", Map(schema=schema, source=source)))
 
+    @classmethod
+    def import_from(cls, source, target=None):
+        """
+        Copy the :class:`Collection` at the given `source` :class:`URI`, which may be on another host.
+
+        If a `target` :class:`URI` is given, the source is copied into the existing (e.g. hosted) `Collection` there,
+        within the current transaction. Otherwise, the result is a new copy of the source.
+
+        Example:
+            .. highlight:: python
+            .. code-block:: python
+
+                users = tc.table.Table.import_from(tc.URI("http://example.com/app/users"))
+        """
+
+        params = {"source": source if isinstance(source, URI) else URI(source)}
+
+        if target is not None:
+            params["target"] = target if isinstance(target, URI) else URI(target)
+
+        return cls(Post(uri(Collection) + "/copy_from", Map(params)))
+
     @classmethod
     def load(cls, schema, data):
         """
@@ -1,13 +1,16 @@
+import re
+
 from tinychain.collection.btree import BTree
 from tinychain.decorators import closure, delete_op, get_op, post_op
 from tinychain.error import BadRequest
-from tinychain.ref import Delete, If, Ref
+from tinychain.ref import Delete, If, Post, Ref
 from tinychain.state import Map, Tuple, State, Stream
 from tinychain.util import form_of, to_json, uri, Context, URI
-from tinychain.value import Bool, UInt, Nil
+from tinychain.value import Bool, Number, UInt, Nil
 
 from .collection import Collection
 from .bound import Range
+from .tensor import Dense
 
 
 class Schema(object):
@@ -17,28 +20,191 @@ class Schema(object):
         self.key = key
         self.values = values
         self.indices = []
+        self.unique = []
+        self.references = []
+        self.access = []
+        self.privacy = None
+        self.validation = []
+        self.defaults = {}
+        self.required = set()
 
     def __json__(self):
-        return to_json([[self.key, self.values], Tuple(self.indices)])
+        # the order of these options matches the order in which a host encodes them
+        options = [("access", Tuple(self.access))] if self.access else []
+
+        if self.privacy:
+            options.append(("privacy", self.privacy))
+
+        options += [
+            (name, Tuple(option)) for name, option in [
+                ("validation", self.validation),
+                ("unique", self.unique),
+                ("references", self.references),
+                ("defaults", list(self.defaults.items())),
+                ("not_null", sorted(self.required)),
+            ] if option]
+
+        if options:
+            schema = [("primary", [self.key, self.values]), ("indices", Tuple(self.indices))] + options
+            return to_json(schema)
+        else:
+            return to_json([[self.key, self.values], Tuple(self.indices)])
 
     def columns(self):
         return self.key + self.values
 
-    def create_index(self, name, columns):
+    def create_index(self, name, columns, unique=False, references=None):
+        """
+        Create an index called `name` on the given `columns`.
+
+        If `unique` is `True`, no two rows may have the same (non-`None`) values in these columns.
+        If `references` is the `URI` of another hosted `Table`, the (non-`None`) values in these columns
+        must be the key of a row in that `Table`. A violation of either constraint is a `Conflict` error.
+        """
+
         self.indices.append((name, columns))
+
+        if unique:
+            self.unique.append(name)
+
+        if references is not None:
+            self.references.append((name, references if isinstance(references, URI) else URI(references)))
+
+        return self
+
+    def set_default(self, column, default):
+        """Fill in the given `default` value for the given value `column` when a row is inserted without it."""
+
+        self.defaults[column] = default
         return self
 
+    def validate(self, *rules):
+        """Require each row ingested in bulk to satisfy the given validation :class:`Rule` s."""
+
+        self.validation.extend(rules)
+        return self
+
+    def not_null(self, column):
+        """Reject any write of `None` to the given `column`."""
+
+        self.required.add(column)
+        return self
+
+    def restrict(self, column, scope, omit=False):
+        """
+        Restrict reads of the given `column` of a hosted `Table` to requests with the given auth `scope`.
+
+        Other requests will see `None` in place of each value in the column (or, if `omit` is `True`,
+        will not see the column at all), and will not be able to filter or order by the column.
+        """
+
+        self.access.append((column, str(scope), "omit" if omit else "mask"))
+        return self
+
+    def private(self, scope, epsilon, threshold, bounds={}):
+        """
+        Expose only noisy aggregates of a hosted `Table` to requests which lack the given auth `scope`.
+
+        Other requests can only read the `count`, `sum`, and `mean` of the `Table`, with noise calibrated to the
+        privacy budget `epsilon`, and cannot aggregate fewer than `threshold` rows. Only the columns in `bounds`
+        can be summed or averaged, and their values are clamped to the given `(min, max)` range.
+        """
+
+        bounds = [(column, lo, hi) for column, (lo, hi) in bounds.items()]
+        self.privacy = (str(scope), epsilon, threshold, bounds)
+        return self
+
+
+class Rule(object):
+    """A validation rule which each row ingested in bulk into a `Table` must satisfy."""
+
+    @staticmethod
+    def regex(column, pattern):
+        """Require the (string) value of `column` to match the given regular expression."""
+
+        return ("regex", column, pattern)
+
+    @staticmethod
+    def range(column, lo, hi):
+        """Require the (numeric) value of `column` to fall within the inclusive range `[lo, hi]`."""
+
+        return ("range", column, lo, hi)
+
+    @staticmethod
+    def compare(left, op, right):
+        """Require the value of the `left` column to compare to the `right` column according to `op` (e.g. "lt")."""
+
+        assert op in ["eq", "ne", "lt", "le", "gt", "ge"]
+        return (op, left, right)
+
 
 class Table(Collection):
     """A `Table` defined by a primary key, values, and optional indices."""
 
     __uri__ = uri(Collection) + "/table"
 
+    @classmethod
+    def infer(cls, source, key=None, dtypes=None, sample=None, rules=None):
+        """
+        Create a new `Table` from the given rows, inferring its schema from a sample of them.
+
+        `source` may be a CSV string with a header line or a :class:`Stream` of :class:`Map` s.
+        If no `key` is given, the first column is used as the primary key.
+        `dtypes` may be given to override the inferred type of any column.
+        A numeric CSV cell with a leading zero (e.g. "007") is read as a string.
+        A column with no fixed size (e.g. a string) has a maximum length of at least 256.
+
+        If validation `rules` are given, each row which violates a rule is written to a quarantine `Table` instead,
+        and the result is a :class:`Map` with the new `table`, the `quarantine` table, and the number of rows
+        `loaded` and `quarantined`.
+
+        Example: `Table.infer("name,views\none,1\ntwo,2", key=["name"])`
+        """
+
+        params = {"source": source}
+
+        if key is not None:
+            params["key"] = key
+
+        if dtypes is not None:
+            params["dtypes"] = dtypes
+
+        if sample is not None:
+            params["sample"] = sample
+
+        if rules:
+            params["rules"] = Tuple(rules)
+            return Map(Post(uri(cls) + "/load", Map(params)))
+
+        return cls(Post(uri(cls) + "/load", Map(params)))
+
+    @classmethod
+    def ingest(cls, schema, source, rules):
+        """
+        Copy a `Table` with the given `schema` from a :class:`Stream` of rows, validating each row.
+
+        Each row which violates one of the given validation `rules` (or the `schema`) is written to a quarantine
+        `Table` along with the reason, instead of aborting the copy. Returns a :class:`Map` with the new `table`,
+        the `quarantine` table, and the number of rows `loaded` and `quarantined`.
+        """
+
+        params = {"schema": schema, "source": source, "rules": Tuple(rules)}
+        return Map(Post(uri(cls) + "/copy_from", Map(params)))
+
     def __getitem__(self, key):
         """Return the row with the given key, or a :class:`NotFound` error."""
 
         return self._get("", key, rtype=Map)
 
+    def add_column(self, column, default=None):
+        """
+        Return a copy of this `Table` with the given :class:`Column` added to its values.
+
+        Each existing row is given the `default` value of the new column.
+        """
+
+        return self._post("alter", Map(add=column, default=default), Table)
+
     def aggregate(self, columns, fn):
         """
         Apply the given callback to slices of this `Table` grouped by the given columns.
@@ -57,6 +223,16 @@ class Table(Collection):
 
         return self.group_by(columns).map(group)
 
+    def as_tensor(self, columns):
+        """
+        Return a read-only view of the given numeric `columns` of this `Table` as a 2-D :class:`Dense` tensor.
+
+        The tensor has one row per row of this `Table` and one column per column given, and reads its values directly
+        from this `Table`, without copying them.
+        """
+
+        return self._get("as_tensor", columns, Dense)
+
     def contains(self, key):
         """Return `True` if this `Table` contains the given key."""
 
@@ -73,7 +249,18 @@ class Table(Collection):
         if where is None:
             return self._get("count", rtype=UInt)
         else:
-            return self.where(where).count()
+            return self._get("count", where, rtype=UInt)
+
+    def count_distinct(self, column, where=None):
+        """
+        Estimate the number of distinct values of the given `column` in the given slice of this `Table`.
+
+        The estimate is computed with a HyperLogLog sketch, so it's exact for a small number of distinct values
+        and otherwise has a relative error of about 1%. `None` values are not counted.
+        """
+
+        key = column if where is None else (column, where)
+        return self._get("count_distinct", key, rtype=UInt)
 
     def delete(self, where={}):
         """
@@ -91,6 +278,15 @@ class Table(Collection):
 
         return self._delete("", key)
 
+    def drop_column(self, name):
+        """
+        Return a copy of this `Table` without the value column with the given `name`.
+
+        A column can't be dropped while an index or a validation rule depends on it.
+        """
+
+        return self._post("alter", Map(drop=name), Table)
+
     def group_by(self, columns):
         """Return a :class:`Stream` of the unique values of the given columns."""
 
@@ -128,10 +324,40 @@ class Table(Collection):
 
         return self._get("key_names", rtype=Tuple)
 
-    def limit(self, limit):
-        """Limit the number of rows returned from this `Table`."""
+    def limit(self, limit, offset=None):
+        """
+        Limit the number of rows returned from this `Table`.
 
-        return self._get("limit", limit, Table)
+        If an `offset` is given, the first `offset` rows are skipped by the host.
+        To read a page in descending order, call `order_by(columns, reverse=True)` first.
+        """
+
+        key = limit if offset is None else (limit, offset)
+        return self._get("limit", key, Table)
+
+    def load_ndjson(self, source, batch_size=None):
+        """
+        Insert the rows in the given newline-delimited JSON `source` into this `Table`.
+
+        Each line must be a JSON object which maps column names to values, or a JSON list of the values of every
+        column. A line which can't be parsed or inserted doesn't abort the load. Returns a :class:`Map` with the
+        number of rows `loaded` and the `errors`, a :class:`Tuple` of `(line, reason)` tuples.
+
+        Example: `table.load_ndjson('{"name": "one", "views": 1}\n{"name": "two", "views": 2}')`
+        """
+
+        params = {"source": source}
+
+        if batch_size is not None:
+            params["batch_size"] = batch_size
+
+        return self._post("load_ndjson", Map(params), Map)
+
+    def mean(self, column, where=None):
+        """Return the mean of the values of the given `column` in the given slice of this `Table`."""
+
+        key = column if where is None else (column, where)
+        return self._get("mean", key, rtype=Number)
 
     def order_by(self, columns, reverse=False):
         """
@@ -142,17 +368,63 @@ class Table(Collection):
 
         return self._get("order", (columns, reverse), Table)
 
+    def page(self, limit, cursor=None):
+        """
+        Read one page of at most `limit` rows of this `Table`, in order of its key.
+
+        The result is a :class:`Map` with the `rows` of the page and an opaque `cursor`, which is
+        `None` if this is the last page. To read the next page, call `page` again with the same
+        `limit` and the `cursor` of the previous page.
+        """
+
+        key = limit if cursor is None else (limit, cursor)
+        return self._get("page", key, Map)
+
+    def query(self, query):
+        """
+        Query this `Table` using a compact textual syntax.
+
+        If the query has a GROUP BY clause, the result is a :class:`Stream` of unique group keys.
+        Otherwise, the result is a `Table`.
+
+        Example: `users.query("SELECT name WHERE age >= 18 ORDER BY name LIMIT 10 OFFSET 20")`
+        """
+
+        rtype = Stream if isinstance(query, str) and _GROUP_BY.search(query) else Table
+        return self._post("query", Map(query=query), rtype)
+
+    def rename_column(self, name, new_name):
+        """Return a copy of this `Table` with the column `name` renamed to `new_name`."""
+
+        return self._post("alter", Map(rename=(name, new_name)), Table)
+
     def rows(self, where={}):
         """Return a :class:`Stream` of the rows in this `Table`."""
 
         where = _handle_bounds(where)
         return self._post("rows", where, Stream)
 
+    def sample(self, n, seed=None):
+        """
+        Return a uniform random sample of up to `n` rows of this `Table`, in order.
+
+        The same `seed` always selects the same rows from the same `Table`.
+        """
+
+        key = n if seed is None else (n, seed)
+        return self._get("sample", key, rtype=Tuple)
+
     def select(self, columns):
         """Return a `Table` containing only the specified columns."""
 
         return self._get("select", columns, Table)
 
+    def sum(self, column, where=None):
+        """Return the sum of the values of the given `column` in the given slice of this `Table`."""
+
+        key = column if where is None else (column, where)
+        return self._get("sum", key, rtype=Number)
+
     def update(self, values, where={}):
         """Update the specified rows of this table with the given `values`."""
 
@@ -184,6 +456,42 @@ class Table(Collection):
         return self._post("", Map(bounds=bounds), Table)
 
 
+class View(Table):
+    """
+    A materialized view of a hosted `Table`, which is updated as each transaction which writes to the `Table` commits.
+
+    A `View` can be read like a `Table`, but not written to.
+    """
+
+    __uri__ = uri(Table) + "/view"
+
+    @classmethod
+    def define(cls, source, schema, op, aggregate=False):
+        """
+        Define a `View` of the `Table` called `source` in the same :class:`Cluster`, with the given `schema`.
+
+        The `op` is a POST :class:`Op` which maps the columns of a row of the `source` to a :class:`Map` of the columns
+        of a row of the `View`, or to `None` to leave the row out. If `aggregate` is `True`, the value columns of all
+        the rows which map to the same key are summed.
+
+        Example:
+            .. highlight:: python
+            .. code-block:: python
+
+                @tc.post_op
+                def by_views(name: tc.String, views: tc.UInt):
+                    return tc.Map(views=views, count=1)
+
+                schema = tc.table.Schema([tc.Column("views", tc.UInt)], [tc.Column("count", tc.UInt)])
+                self.by_views = tc.table.View.define("posts", schema, by_views, aggregate=True)
+        """
+
+        return cls((source, schema, op, aggregate))
+
+
+_GROUP_BY = re.compile(r"\bgroup\s+by\b", re.IGNORECASE)
+
+
 def _handle_bounds(bounds):
     if bounds is None:
         return {}
@@ -1,9 +1,11 @@
 """An n-dimensional array of numbers."""
 
+import base64
+
 from tinychain import ref
 from tinychain.state import Map, State, Stream, Tuple
 from tinychain.util import form_of, to_json, uri, URI
-from tinychain.value import Bool, I16, F32, Number, UInt
+from tinychain.value import Bool, Bytes, I16, F32, F64, Number, UInt
 
 from .bound import Range
 from .collection import Collection
@@ -127,6 +129,11 @@ class Tensor(Collection):
 
         return self._post("add", Map(r=other), Tensor)
 
+    def add_assign(self, other):
+        """Add another `Tensor` or `Number` to this `Tensor` in place."""
+
+        return self._post("add_assign", Map(r=other), None)
+
     def all(self):
         """Return `True` if all elements in this `Tensor` are nonzero."""
 
@@ -137,6 +144,11 @@ class Tensor(Collection):
 
         return self._get("any", rtype=Bool)
 
+    def arg(self):
+        """Return the element-wise argument (phase angle) of this `Tensor`."""
+
+        return self._get("arg", rtype=self.__class__)
+
     def asin(self):
         """Return the element-wise arcsine of this `Tensor`."""
 
@@ -157,11 +169,33 @@ class Tensor(Collection):
 
         return self._get("atanh", rtype=self.__class__)
 
+    def batch_norm(self, mean, var, gamma, beta, epsilon=None):
+        """
+        Return `gamma * (self - mean) / sqrt(var + epsilon) + beta`, computed in a single pass.
+
+        Each parameter is broadcast to the shape of this `Tensor`. `epsilon` defaults to 1e-5.
+        """
+
+        return self._post("batch_norm", Map(mean=mean, var=var, gamma=gamma, beta=beta, epsilon=epsilon), Dense)
+
     def cast(self, number_type):
         """Cast the data type of `Tensor` into the given `number_type`."""
 
         return self._get("cast", number_type, self.__class__)
 
+    def cholesky(self):
+        """
+        Return the lower-triangular Cholesky factor `L` of this symmetric, positive-definite matrix,
+        such that `self = L * L^T`.
+        """
+
+        return self._get("cholesky", rtype=Dense)
+
+    def conj(self):
+        """Return the element-wise complex conjugate of this `Tensor`."""
+
+        return self._get("conj", rtype=self.__class__)
+
     def copy(self):
         """Return a copy of this `Tensor`"""
 
@@ -177,11 +211,31 @@ class Tensor(Collection):
 
         return self._get("cosh", rtype=self.__class__)
 
+    def cumprod(self, axis):
+        """Return the cumulative product of this `Tensor` along the given `axis`."""
+
+        return self._get("cumprod", axis, Dense)
+
+    def cumsum(self, axis):
+        """Return the cumulative sum of this `Tensor` along the given `axis`."""
+
+        return self._get("cumsum", axis, Dense)
+
+    def det(self):
+        """Return the determinant of this square matrix."""
+
+        return self._get("det", rtype=Number)
+
     def div(self, other):
         """Divide this `Tensor` by another `Tensor` or `Number`, broadcasting if necessary."""
 
         return self._post("div", Map(r=other), Tensor)
 
+    def div_assign(self, other):
+        """Divide this `Tensor` by another `Tensor` or `Number` in place, or raise an error if dividing by zero."""
+
+        return self._post("div_assign", Map(r=other), None)
+
     def flip(self, axis):
         """Flip the elements in this `Tensor` along the specified `axis`."""
 
@@ -212,6 +266,15 @@ class Tensor(Collection):
 
         return self._post("gte", Map(r=other), Tensor)
 
+    def histogram(self, bins=None):
+        """
+        Return a histogram of the elements of this `Tensor` in `bins` equal-width bins (10 by default).
+
+        Returns a `Tuple` of the count of elements in each bin and the edges of the bins, like `numpy.histogram`.
+        """
+
+        return self._get("histogram", bins, Tuple)
+
     def lt(self, other):
         """Return a boolean `Tensor` with element-wise less-than values."""
 
@@ -222,6 +285,26 @@ class Tensor(Collection):
 
         return self._post("lte", Map(r=other), Tensor)
 
+    def im(self):
+        """Return the element-wise imaginary component of this `Tensor`."""
+
+        return self._get("im", rtype=self.__class__)
+
+    def im2col(self, kernel, stride=1):
+        """
+        Gather every window of the given `kernel` shape, moved `stride` elements at a time, into a new `Dense` tensor.
+
+        This `Tensor` must have the shape `[batch, spatial..., channels]`, with one spatial axis per axis of `kernel`.
+        The result has the shape `[batch, windows..., kernel..., channels]`.
+        """
+
+        return self._post("im2col", Map(kernel=kernel, stride=stride), Dense)
+
+    def inv(self):
+        """Return the inverse of this square matrix."""
+
+        return self._get("inv", rtype=Dense)
+
     def logical_and(self, other):
         """Return a boolean `Tensor` with element-wise logical and values."""
 
@@ -242,6 +325,21 @@ class Tensor(Collection):
 
         return self._post("xor", Map(r=other), Tensor)
 
+    def mask_assign(self, mask, value):
+        """Overwrite each element of this `Tensor` where the boolean `mask` is `True` with the given `value`."""
+
+        return self._post("mask_assign", Map(mask=mask, value=value), None)
+
+    def mask_select(self, mask):
+        """Return a flat `Dense` vector of the elements of this `Tensor` where the boolean `mask` is `True`."""
+
+        return self._post("mask_select", Map(mask=mask), Dense)
+
+    def materialize(self):
+        """Return a writable copy of this `Tensor`, e.g. to write to a broadcast or a slice."""
+
+        return self._get("materialize", rtype=self.__class__)
+
     @property
     def ndim(self):
         """Return the number of dimensions of this `Tensor`."""
@@ -253,6 +351,22 @@ class Tensor(Collection):
 
         return self._post("mul", Map(r=other), self.__class__)
 
+    def mul_assign(self, other):
+        """Multiply this `Tensor` by another `Tensor` or `Number` in place."""
+
+        return self._post("mul_assign", Map(r=other), None)
+
+    def norm(self, ord=None, axis=None):
+        """
+        Return the norm of the given order of this `Tensor` along the given `axis`, or of all its elements if no axis
+        is given.
+
+        `ord` may be 1, 2, or "inf", and defaults to 2.
+        """
+
+        rtype = Number if axis is None else Tensor
+        return self._post("norm", Map(ord=ord, axis=axis), rtype)
+
     def ne(self, other):
         """Return a boolean `Tensor` with element-wise not-equal values."""
 
@@ -269,6 +383,30 @@ class Tensor(Collection):
         rtype = Number if axis is None else self.__class__
         return self._get("product", axis, rtype)
 
+    def qr(self):
+        """
+        Return the QR decomposition of this matrix, computed in memory.
+
+        Returns a `Tuple` of the orthogonal matrix `Q` and the upper-triangular matrix `R`.
+        """
+
+        return self._get("qr", rtype=Tuple)
+
+    def quantile(self, q):
+        """
+        Estimate the `q` quantile of the elements of this `Tensor`, or each quantile if `q` is a list.
+
+        The estimate is computed in a single pass over the elements of this `Tensor` using a t-digest.
+        """
+
+        rtype = Tuple if isinstance(q, (list, tuple)) else Number
+        return self._get("quantile", q, rtype)
+
+    def re(self):
+        """Return the element-wise real component of this `Tensor`."""
+
+        return self._get("re", rtype=self.__class__)
+
     def reshape(self, shape):
         """Return a view of this `Tensor` with the given `shape`."""
 
@@ -291,17 +429,45 @@ class Tensor(Collection):
 
         return self._get("sinh", rtype=self.__class__)
 
+    def softmax(self, axis=None):
+        """Return the softmax of this `Tensor` along the given `axis` (by default, the last axis)."""
+
+        return self._get("softmax", axis, Dense)
+
+    def sort(self, axis=None, ascending=True):
+        """
+        Sort this `Tensor` along the given `axis` (by default, the last axis).
+
+        Returns a `Tuple` of the sorted values and, for each, its index along `axis` in this `Tensor`.
+        """
+
+        return self._post("sort", Map(axis=axis, ascending=ascending), Tuple)
+
     def sub(self, other):
         """Subtract another `Tensor` or `Number` from this one, broadcasting if necessary."""
 
         return self._post("sub", Map(r=other), Tensor)
 
+    def sub_assign(self, other):
+        """Subtract another `Tensor` or `Number` from this `Tensor` in place."""
+
+        return self._post("sub_assign", Map(r=other), None)
+
     def sum(self, axis=None):
         """Calculate the sum of this `Tensor` along the given `axis`, or the total sum if no axis is given."""
 
         rtype = Number if axis is None else self.__class__
         return self._get("sum", axis, rtype)
 
+    def svd(self):
+        """
+        Return the singular value decomposition `U * S * V^T` of this matrix, computed in memory.
+
+        Returns a `Tuple` of `U`, the singular values `S` in descending order, and `V^T`.
+        """
+
+        return self._get("svd", rtype=Tuple)
+
     def tan(self):
         """Return the element-wise tangent of this `Tensor`."""
 
@@ -312,6 +478,15 @@ class Tensor(Collection):
 
         return self._get("tanh", rtype=self.__class__)
 
+    def topk(self, k, axis=None):
+        """
+        Return the `k` greatest elements of this `Tensor` along the given `axis` (by default, the last axis).
+
+        Returns a `Tuple` of the values, in descending order, and for each its index along `axis` in this `Tensor`.
+        """
+
+        return self._post("topk", Map(k=k, axis=axis), Tuple)
+
     def transpose(self, permutation=None):
         """
         Return a view of this `Tensor` with its axes transposed according to the given permutation.
@@ -363,6 +538,27 @@ class Dense(Tensor):
 
         return cls(ref.Get(uri(cls) + "/constant", (shape, value)))
 
+    @classmethod
+    def load_hdf5(cls, data, dest=None):
+        """
+        Import each numeric dataset in the given HDF5 file as a `Dense` tensor.
+
+        `data` is the contents of the file, as `bytes`. If `dest` is given, each tensor is also written to
+        `dest` plus the path of its dataset. The host must be built with the `hdf5-import` feature.
+
+        Returns a :class:`Tuple` with a :class:`Map` for each group and dataset in the file,
+        with its `path`, `attributes`, and (for a numeric dataset) `tensor`.
+        """
+
+        if isinstance(data, bytes):
+            data = {str(uri(Bytes)): base64.b64encode(data).decode()}
+
+        params = {"data": data}
+        if dest is not None:
+            params["dest"] = dest
+
+        return Tuple(ref.Post(uri(cls) + "/load_hdf5", params))
+
     @classmethod
     def ones(cls, shape, dtype=F32):
         """
@@ -425,12 +621,34 @@ class Sparse(Tensor):
         bounds = _handle_bounds(bounds)
         return self._get("elements", bounds, Stream)
 
+    def filled_count(self, bounds=None):
+        """Return the number of nonzero elements of this `Sparse` tensor, optionally within the given `bounds`."""
+
+        bounds = _handle_bounds(bounds)
+        return self._get("filled_count", bounds, UInt)
+
+    def density(self, bounds=None):
+        """Return the fraction of the elements of this `Sparse` tensor which are nonzero."""
+
+        bounds = _handle_bounds(bounds)
+        return self._get("density", bounds, F64)
+
     def as_dense(self):
         """Return a :class:`Dense` view of this `Sparse` tensor."""
 
         return self._get("dense", rtype=Dense)
 
 
+def cdist(a, b, metric="euclidean"):
+    """
+    Return the distance between each row of the matrix `a` and each row of the matrix `b`, as a `Dense` matrix.
+
+    `metric` may be "euclidean", "cityblock", or "chebyshev".
+    """
+
+    return Dense(ref.Post(uri(Tensor) + "/cdist", {"a": a, "b": b, "metric": metric}))
+
+
 # TODO: allow eliding batch dimensions
 def einsum(format, tensors):
     """
@@ -445,6 +663,54 @@ def einsum(format, tensors):
     return Tensor(ref.Post(uri(Tensor) + "/einsum", {"format": format, "tensors": tensors}))
 
 
+def where(cond, then, or_else):
+    """
+    Return a `Tensor` with the elements of `then` where `cond` is `True` and the elements of `or_else` elsewhere.
+
+    All three tensors are broadcast to a common shape if necessary. The result is `Sparse` only if both `then`
+    and `or_else` are `Sparse`.
+    """
+
+    return Tensor(ref.Post(uri(Tensor) + "/where", {"cond": cond, "then": then, "or_else": or_else}))
+
+
+def solve(a, b):
+    """
+    Return the solution `x` of the system of linear equations `a * x = b`.
+
+    `a` must be a square matrix and `b` a vector or matrix with the same number of rows. Like the other linear algebra
+    methods of `Tensor`, this loads both operands into memory, so each must have at most 64 blocks' worth of elements.
+    """
+
+    return Dense(ref.Post(uri(Tensor) + "/solve", {"a": a, "b": b}))
+
+
+def watch(tensor):
+    """
+    Watch the given `tensor` on the gradient tape of the current transaction, starting the tape if necessary.
+
+    After this, each `add`, `sub`, `mul`, `div`, `exp`, or `sum` of a watched `Tensor` (or of the result of such an
+    op) is recorded, as long as both the op and its operands are named states in the same `Context`.
+    """
+
+    rtype = type(tensor) if isinstance(tensor, Tensor) else Tensor
+    return rtype(ref.Post(uri(Tensor) + "/watch", {"tensor": tensor}))
+
+
+def backward(loss):
+    """
+    Return a `Map` of the gradient of the given `loss` with respect to each watched `Tensor`, by name.
+
+    The `loss` must be a named state in the current `Context`, like `cxt.loss`.
+    """
+
+    name = form_of(loss)
+    if not isinstance(name, URI) or not name.is_id():
+        raise ValueError(f"backward requires a named state in the current Context, not {loss}")
+
+    return Map(ref.After(loss, ref.Post(uri(Tensor) + "/backward", {"loss": str(name)[1:]})))
+
+
 def _handle_bounds(bounds):
     if bounds is None or isinstance(bounds, ref.Ref) or isinstance(bounds, URI):
         return bounds
@@ -464,3 +730,4 @@ def _handle_bounds(bounds):
     return [
         Range.from_slice(x) if isinstance(x, slice)
         else x for x in bounds]
+
@@ -47,9 +47,18 @@ def put_op(form):
     return op.Put(form)
 
 
-def post_op(form):
-    """Annotation for a callable function specifying that it is a POST :class:`Op`."""
-    return op.Post(form)
+def post_op(form=None, typed=False):
+    """
+    Annotation for a callable function specifying that it is a POST :class:`Op`.
+
+    If `typed` is `True`, e.g. `@post_op(typed=True)`, the host will validate the params of each call
+    against the annotated type and default of each parameter before executing the :class:`Op`.
+    """
+
+    if form is None:
+        return lambda form: op.Post(form, typed)
+
+    return op.Post(form, typed)
 
 
 def delete_op(form):
@@ -15,6 +15,28 @@ class TinyChainError(Exception):
 
         params = kwargs if kwargs else params
         self.message = String(message).render(params) if params else message
+        self.code = None
+        self.path = None
+        self.txn_id = None
+        self.causes = []
+
+    @classmethod
+    def from_report(cls, report):
+        """
+        Construct an error from the error report in the response of a TinyChain host.
+
+        The report's `code`, `path`, `txn_id`, and `causes` (each itself an error) are available as attributes.
+        """
+
+        if not isinstance(report, dict) or "message" not in report:
+            return cls(report)
+
+        error = cls(report["message"])
+        error.code = report.get("code")
+        error.path = report.get("path")
+        error.txn_id = report.get("txn_id")
+        error.causes = [_error_class(cause.get("code")).from_report(cause) for cause in report.get("causes", [])]
+        return error
 
     def __json__(self):
         return {str(uri(self)): [to_json(self.message)]}
@@ -71,6 +93,12 @@ class Timeout(TinyChainError):
     __uri__ = uri(TinyChainError) + "/timeout"
 
 
+class TooManyRequests(TinyChainError):
+    """Error indicating that the host is too busy, or that the requestor has exceeded its rate limit."""
+
+    __uri__ = uri(TinyChainError) + "/too_many_requests"
+
+
 class Unauthorized(TinyChainError):
     """Error indicating that the requestor's credentials are missing or invalid."""
 
@@ -82,3 +110,11 @@ class UnknownError(TinyChainError):
 
     __uri__ = uri(TinyChainError) + "/unknown"
 
+
+
+def _error_class(code):
+    for error_class in TinyChainError.__subclasses__():
+        if str(uri(error_class)) == f"/error/{code}":
+            return error_class
+
+    return TinyChainError
@@ -5,12 +5,15 @@ import requests
 import urllib.parse
 
 from tinychain.error import *
-from tinychain.util import to_json, uri, URI
+from tinychain.util import form_of, to_json, uri, URI
 from tinychain.value import Nil
 
 
 DEFAULT_PORT = 8702
 ENCODING = "utf-8"
+NPY = "application/x-npy"
+NPZ = "application/x-npz"
+ARROW = "application/vnd.apache.arrow.stream"
 
 
 class Host(object):
@@ -43,21 +46,23 @@ class Host(object):
         elif status == 204:
             return None
         elif status == 400:
-            raise BadRequest(response)
+            raise BadRequest.from_report(response)
         elif status == 401:
-            raise Unauthorized(response)
+            raise Unauthorized.from_report(response)
         elif status == 403:
-            raise Forbidden(response)
+            raise Forbidden.from_report(response)
         elif status == 404:
-            raise NotFound(response)
+            raise NotFound.from_report(response)
         elif status == 405:
-            raise MethodNotAllowed(response)
+            raise MethodNotAllowed.from_report(response)
         elif status == 408:
-            raise Timeout(response)
+            raise Timeout.from_report(response)
         elif status == 409:
-            raise Conflict(response)
+            raise Conflict.from_report(response)
+        elif status == 429:
+            raise TooManyRequests.from_report(response)
         elif status == 501:
-            raise NotImplemented(response)
+            raise NotImplemented.from_report(response)
         else:
             raise UnknownError(f"HTTP error code {status}: {response}")
 
@@ -118,6 +123,85 @@ class Host(object):
 
         return self._handle(request)
 
+    def get_array(self, path, key=None, sparse=False, auth=None):
+        """
+        Execute a GET request for a `Tensor` and decode the response as a NumPy array.
+
+        If `sparse` is `True`, the tensor is encoded as an `.npz` archive, and decoded as a `dict` of the arrays
+        "coords" (with shape `[ndim, nnz]`), "data", "shape", and "fill_value".
+        """
+
+        import io
+        import numpy as np
+
+        data = self._get_encoded(path, key, NPZ if sparse else NPY, auth)
+        data = np.load(io.BytesIO(data))
+        return dict(data) if sparse else data
+
+    def get_arrow(self, path, key=None, auth=None):
+        """
+        Execute a GET request for a `Table` and decode the response as a `pyarrow.Table`.
+
+        The host must be built with the `arrow-export` feature.
+        """
+
+        import pyarrow
+
+        data = self._get_encoded(path, key, ARROW, auth)
+        return pyarrow.ipc.open_stream(data).read_all()
+
+    def _get_encoded(self, path, key, encoding, auth):
+        url = self.link(path)
+        headers = auth_header(auth)
+        headers["Accept-Encoding"] = encoding
+        if key and not isinstance(key, Nil):
+            params = {"key": json.dumps(to_json(key)).encode(ENCODING)}
+        else:
+            params = {}
+
+        response = requests.get(url, params=params, headers=headers)
+        if response.status_code == 200:
+            return response.content
+        else:
+            return self._handle(lambda: response)
+
+    def put_array(self, path, key=None, value=None, auth=None):
+        """
+        Execute a PUT request whose `value` is a NumPy array, or a `dict` of the arrays of a sparse tensor
+        in the format returned by `get_array(..., sparse=True)`.
+        """
+
+        import io
+        import numpy as np
+
+        url = self.link(path)
+        headers = auth_header(auth)
+        if key and not isinstance(key, Nil):
+            params = {"key": json.dumps(to_json(key)).encode(ENCODING)}
+        else:
+            params = {}
+
+        buffer = io.BytesIO()
+        if isinstance(value, dict):
+            headers["Content-Type"] = NPZ
+            np.savez(buffer, **value)
+        else:
+            headers["Content-Type"] = NPY
+            np.save(buffer, np.ascontiguousarray(value))
+
+        data = buffer.getvalue()
+        request = lambda: requests.put(url, params=params, data=data, headers=headers)
+        return self._handle(request)
+
+    def install(self, cluster, auth=None):
+        """
+        Install the given :class:`Cluster` on this host, replacing the version it already hosts (if any).
+
+        The installed cluster is only hosted until the host restarts, unless its config is also passed to `--cluster`.
+        """
+
+        return self.put("/host/cluster", uri(cluster), form_of(cluster), auth)
+
 
 class Local(Host):
     """A local TinyChain host."""
@@ -28,11 +28,7 @@ def conv_1d(input_shape, filters, bias=None, stride=1, activation=Sigmoid()):
             padded = Dense.zeros([inputs.shape[0], length + padding, input_channels])
             l, r = padding // 2, padding // 2 if length % 2 == 0 else math.ceil(padding / 2)
 
-            strides = []
-            for x in range(length):
-                strides.append(padded[:, x:(x + stride)].expand_dims(1))
-
-            strides = After(padded[:, l:-r].write(inputs), Dense.concatenate(strides, 1))
+            strides = After(padded[:, l:-r].write(inputs), padded.im2col([stride]))
 
             # einsum dimensions:
             #   b = batch dim
@@ -66,15 +62,7 @@ def conv_2d(input_shape, filters, bias=None, stride=1, activation=Sigmoid()):
             l, r = padding // 2, padding // 2 if width % 2 == 0 else math.ceil(padding / 2)
             t, b = padding // 2, padding // 2 if height % 2 == 0 else math.ceil(padding / 2)
 
-            strides = []
-            for x in range(width):
-                x_strides = []
-                for y in range(height):
-                    x_strides.append(padded[:, x:(x + stride), y:(y + stride)].expand_dims(1))
-
-                strides.append(Dense.concatenate(x_strides, 1).expand_dims(1))
-
-            strides = After(padded[:, l:-r, t:-b].write(inputs), Dense.concatenate(strides, 1))
+            strides = After(padded[:, l:-r, t:-b].write(inputs), padded.im2col([stride, stride]))
 
             # einsum dimensions:
             #   b = batch dim
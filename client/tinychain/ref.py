@@ -136,37 +136,46 @@ class While(Ref):
     A flow control operator to execute a closure repeatedly until a condition is met.
 
     Args:
-        cond (Bool or Ref): The condition which determines which branch to execute.
+        cond (Op or Ref): The condition to check, with the current `state`, before each iteration.
 
-        then (State or Ref): The State to resolve if `cond` resolves to `True`.
+        op (Op or Ref): The Op to call with the current `state` to compute the next `state`.
 
-        or_else (State or Ref): The State to resolve if `cond` resolves to `False`.
+        state (State or Ref): The initial state of the loop.
+
+        limit (Number or Ref): The maximum number of iterations (the host's default limit is used if none is given).
 
     Raises:
-        `BadRequestError` if `cond` does not resolve to a :class:`Bool` or in case of a nested conditional
+        `BadRequestError` if `cond` does not resolve to a :class:`Bool`, in case of a nested conditional,
+        or if the loop exceeds its iteration `limit`
     """
 
     __uri__ = uri(Ref) + "/while"
 
-    def __init__(self, cond, op, state=None):
+    def __init__(self, cond, op, state=None, limit=None):
         self.cond = cond
         self.op = op
         self.state = state
+        self.limit = limit
 
     def __deps__(self):
         deps = set()
         deps.update(requires(self.cond))
         deps.update(requires(self.op))
         deps.update(requires(self.state))
+        deps.update(requires(self.limit))
         return deps
 
     def __json__(self):
-        return {str(uri(self)): to_json([self.cond, self.op, self.state])}
+        if self.limit is None:
+            return {str(uri(self)): to_json([self.cond, self.op, self.state])}
+        else:
+            return {str(uri(self)): to_json([self.cond, self.op, self.state, self.limit])}
 
     def __ns__(self, cxt):
         deanonymize(self.cond, cxt)
         deanonymize(self.op, cxt)
         deanonymize(self.state, cxt)
+        deanonymize(self.limit, cxt)
 
 
 class With(Ref):
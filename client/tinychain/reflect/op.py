@@ -128,8 +128,9 @@ class Put(Op):
 class Post(Op):
     __uri__ = uri(op.Post)
 
-    def __init__(self, form):
+    def __init__(self, form, typed=False):
         self.rtype = _get_rtype(form, State)
+        self.typed = typed
         Op.__init__(self, form)
 
     def __call__(self, *args, **kwargs):
@@ -145,8 +146,14 @@ class Post(Op):
         cxt, args = _maybe_first_arg(self)
 
         sig = inspect.signature(self.form)
+        params = list(sig.parameters.items())[len(args):]
+
+        if self.typed:
+            # the host validates the params of a call against this signature
+            cxt._params = {name: _param_spec(self.form, param) for name, param in params}
+
         kwargs = {}
-        for name, param in list(sig.parameters.items())[len(args):]:
+        for name, param in params:
             dtype = resolve_class(self.form, param.annotation, State)
             kwargs[name] = dtype(URI(name))
 
@@ -186,6 +193,14 @@ class Delete(Op):
         return f"DELETE Op with form {self.form}"
 
 
+def _param_spec(form, param):
+    dtype = str(uri(resolve_class(form, param.annotation, State)))
+    if param.default is EMPTY:
+        return dtype
+    else:
+        return [dtype, param.default]
+
+
 def _maybe_first_arg(op):
     sig = inspect.signature(op.form)
     param_names = list(sig.parameters.keys())
@@ -98,6 +98,46 @@ class State(object):
         return self.is_none().logical_not()
 
 
+class Closure(State):
+    """
+    An :class:`Op` packaged together with a captured environment, which can be stored and called later.
+
+    Construct a `Closure` explicitly with a `Map` of captured `State` s and an `Op`, e.g. `Closure({"x": x}, op)`,
+    or use the `closure` decorator to capture referenced states automatically.
+    """
+
+    __uri__ = uri(State) + "/closure"
+
+    def __init__(self, form, op=None):
+        if op is None:
+            State.__init__(self, form)
+        else:
+            State.__init__(self, (form, op))
+
+    def __json__(self):
+        form = form_of(self)
+
+        if isinstance(form, tuple):
+            return {str(uri(self)): to_json(list(form))}
+        else:
+            return to_json(form)
+
+    def bind(self, **params):
+        """Return a new `Closure` with the given `params` added to its captured environment (partial application)."""
+
+        return self._post("bind", params, Closure)
+
+    def get(self, key=None, rtype=State):
+        """Call this `Closure` with the given `key`, if it closes over a GET `Op`."""
+
+        return self._get("", key, rtype)
+
+    def post(self, rtype=State, **params):
+        """Call this `Closure` with the given `params`, if it closes over a POST `Op`."""
+
+        return self._post("", params, rtype)
+
+
 class Map(State):
     """A key-value map whose keys are `Id`s and whose values are `State` s."""
 
@@ -127,6 +167,11 @@ class Map(State):
     def __json__(self):
         return to_json(form_of(self))
 
+    def get_path(self, *path, rtype=State):
+        """Return the element of this (possibly nested) `Map` at the given key `path`, e.g. `m.get_path("a", 0)`."""
+
+        return self._get("", path, rtype)
+
     def __ne__(self, other):
         return self.ne(other)
 
@@ -141,12 +186,28 @@ class Map(State):
 
         return self.eq(other).logical_not()
 
+    def contains(self, key):
+        """Return a `Bool` indicating whether this `Map` contains the given `key`."""
+
+        from .value import Bool
+        return self._get("contains", key, Bool)
+
+    def filter(self, op):
+        """Construct a new `Map` with only the entries whose values satisfy the given `op`."""
+
+        return self._post("filter", {"op": op}, Map)
+
     def len(self):
         """Return the number of elements in this `Map`."""
 
         from .value import UInt
         return self._get("len", rtype=UInt)
 
+    def map(self, op):
+        """Construct a new `Map` with the same keys, by mapping the values in this `Map` with the given `op`."""
+
+        return self._post("map", {"op": op}, Map)
+
 
 class Tuple(State):
     """A tuple of `State` s."""
@@ -170,6 +231,11 @@ class Tuple(State):
         else:
             return self._get("", i)
 
+    def get_path(self, *path, rtype=State):
+        """Return the element of this (possibly nested) `Tuple` at the given key `path`, e.g. `t.get_path(0, "a")`."""
+
+        return self._get("", path, rtype)
+
     def __ne__(self, other):
         return self.ne(other)
 
@@ -184,6 +250,12 @@ class Tuple(State):
 
         return self.eq(other).logical_not()
 
+    def contains(self, value):
+        """Return a `Bool` indicating whether this `Tuple` contains the given `value`."""
+
+        from .value import Bool
+        return self._get("contains", value, Bool)
+
     def extend(self, other):
         """Construct a new `Tuple` which is the concatenation of this `Tuple` and the given `other`."""
 
@@ -195,6 +267,11 @@ class Tuple(State):
         from .value import UInt
         return self._get("len", rtype=UInt)
 
+    def filter(self, op):
+        """Construct a new `Tuple` with only the elements in this `Tuple` which satisfy the given `op`."""
+
+        return self._post("filter", {"op": op}, Tuple)
+
     # TODO: update this method signature to match `Stream.fold`
     def fold(self, initial_state, op):
         """Iterate over the elements in this `Tuple` with the given `op`, accumulating the results."""
@@ -208,6 +285,21 @@ class Tuple(State):
         rtype = op.rtype if hasattr(op, "rtype") else State
         return self._post("map", {"op": op}, rtype)
 
+    def reduce(self, op):
+        """
+        Iterate over the elements in this `Tuple` with the given `op`, accumulating the results.
+
+        The first element is the initial state, so the `Tuple` must not be empty.
+        """
+
+        rtype = op.rtype if hasattr(op, "rtype") else State
+        return self._post("reduce", {"op": op}, rtype)
+
+    def slice(self, start=None, stop=None):
+        """Return the elements of this `Tuple` from `start` up to (but not including) `stop`, like a Python slice."""
+
+        return self._get("slice", (start, stop), Tuple)
+
     def unpack(self, length):
         """A Python convenience method which yields an iterator over the first `length` elements in this `Tuple`."""
 
@@ -0,0 +1,21 @@
+"""Savepoints within a transaction, to recover from a late error without aborting the whole transaction."""
+
+from tinychain import ref
+from tinychain.util import URI
+
+SAVEPOINT = URI("/transact/savepoint")
+ROLLBACK = URI("/transact/rollback")
+
+
+def savepoint(name):
+    """Save the state of the data which the current transaction has mutated on this host as `name`."""
+
+    from .value import Nil
+    return Nil(ref.Put(SAVEPOINT, name, None))
+
+
+def rollback(name):
+    """Undo every mutation the current transaction has made on this host since the savepoint `name`."""
+
+    from .value import Nil
+    return Nil(ref.Post(ROLLBACK, {"savepoint": name}))
@@ -1,7 +1,10 @@
 """:class:`Value` types such as :class:`Nil`, :class:`Number`, and :class:`String`."""
 
-from tinychain.ref import If, Ref
-from tinychain.state import Scalar
+import datetime
+import decimal
+
+from tinychain.ref import Get, If, Ref
+from tinychain.state import Scalar, Tuple
 from tinychain.util import form_of, to_json, uri, URI
 
 
@@ -41,6 +44,19 @@ class Value(Scalar):
     def __le__(self, other):
         return self.lte(other)
 
+    def cast(self, dtype, lossy=True):
+        """
+        Cast this `Value` into the given `dtype`.
+
+        If `lossy` is `False`, the cast will raise an error instead of losing information,
+        e.g. when casting `3.5` into an `Int`.
+        """
+
+        if lossy:
+            return Scalar.cast(self, dtype)
+        else:
+            return dtype(Get(uri(dtype) + "/cast/strict", self))
+
     def eq(self, other):
         """Returns `true` if `self` is equal to `other`."""
 
@@ -91,6 +107,101 @@ class Bytes(Value):
 
     __uri__ = uri(Value) + "/bytes"
 
+    @classmethod
+    def from_base64(cls, encoded):
+        """Decode the given base64-`encoded` string."""
+
+        return cls(Get(uri(cls) + "/from_base64", encoded))
+
+    @classmethod
+    def from_hex(cls, encoded):
+        """Decode the given hex-`encoded` string."""
+
+        return cls(Get(uri(cls) + "/from_hex", encoded))
+
+    def blake3(self):
+        """Return the BLAKE3 hash of these `Bytes`."""
+
+        return self._get("blake3", rtype=Bytes)
+
+    def len(self):
+        """Return the number of bytes in this `Bytes`."""
+
+        return self._get("len", rtype=U64)
+
+    def sha256(self):
+        """Return the SHA-256 hash of these `Bytes`."""
+
+        return self._get("sha256", rtype=Bytes)
+
+    def to_base64(self):
+        """Encode these `Bytes` as a base64 :class:`String`."""
+
+        return self._get("to_base64", rtype=String)
+
+    def to_hex(self):
+        """Encode these `Bytes` as a hexadecimal :class:`String`."""
+
+        return self._get("to_hex", rtype=String)
+
+
+class DateTime(Value):
+    """
+    A UTC timestamp with nanosecond precision, which collates in chronological order.
+
+    Construct a `DateTime` from an RFC 3339 string or a :class:`datetime.datetime` (which is assumed to be in UTC
+    if it has no time zone).
+    """
+
+    __uri__ = uri(Value) + "/datetime"
+
+    @classmethod
+    def now(cls):
+        """Return the current time."""
+
+        return cls(Get(uri(cls) + "/now"))
+
+    @classmethod
+    def parse(cls, datetime):
+        """Parse an RFC 3339 `datetime` string."""
+
+        return cls(Get(uri(cls) + "/parse", datetime))
+
+    def __json__(self):
+        form = form_of(self)
+
+        if isinstance(form, datetime.datetime):
+            if form.tzinfo is None:
+                form = form.replace(tzinfo=datetime.timezone.utc)
+
+            return {str(uri(self)): [form.isoformat()]}
+        elif isinstance(form, str):
+            return {str(uri(self)): [form]}
+        else:
+            return Value.__json__(self)
+
+    def __add__(self, other):
+        return self.add_duration(other)
+
+    def __sub__(self, other):
+        return self.sub(other)
+
+    def add_duration(self, seconds):
+        """Return the time `seconds` after this one (or before, if `seconds` is negative)."""
+
+        return self._get("add_duration", seconds, DateTime)
+
+    def sub(self, other):
+        """
+        Return the number of seconds elapsed since the given `DateTime`,
+        or the time `other` seconds before this one, if `other` is a :class:`Number`.
+        """
+
+        if isinstance(other, (DateTime, datetime.datetime)):
+            return self._get("sub", other, F64)
+        else:
+            return self._get("sub", other, DateTime)
+
 
 class Id(Value):
     """An identifier"""
@@ -122,6 +233,75 @@ class String(Value):
         else:
             return self._post("render", kwargs, String)
 
+    def contains(self, pattern):
+        """Return `True` if this string contains the given `pattern`."""
+
+        return self._get("contains", pattern, Bool)
+
+    def ends_with(self, suffix):
+        """Return `True` if this string ends with the given `suffix`."""
+
+        return self._get("ends_with", suffix, Bool)
+
+    def format(self, *args):
+        """
+        Replace each `{}` placeholder in this string with the next of the given `args`,
+        and each `{n}` placeholder with the `n`th of the given `args`.
+        """
+
+        return self._get("format", args, String)
+
+    def join(self, strings):
+        """Concatenate the given `strings`, separated by this string."""
+
+        return self._get("join", strings, String)
+
+    def lower(self):
+        """Return a lowercase copy of this string."""
+
+        return self._get("lowercase", rtype=String)
+
+    def split(self, separator=None):
+        """Split this string on the given `separator` (or whitespace, if none is given) into a :class:`Tuple`."""
+
+        return self._get("split", separator, Tuple)
+
+    def starts_with(self, prefix):
+        """Return `True` if this string starts with the given `prefix`."""
+
+        return self._get("starts_with", prefix, Bool)
+
+    def strip(self):
+        """Return a copy of this string without leading or trailing whitespace."""
+
+        return self._get("trim", rtype=String)
+
+    def upper(self):
+        """Return an uppercase copy of this string."""
+
+        return self._get("uppercase", rtype=String)
+
+    def matches(self, pattern):
+        """Return `True` if this string contains a match for the given regular expression `pattern`."""
+
+        return self._get("matches", pattern, Bool)
+
+    def extract(self, pattern, group=0):
+        """
+        Return the given capture `group` (a position or a name) of the first match of the regular expression
+        `pattern` in this string, or `None` if there is no match.
+        """
+
+        return self._post("extract", {"pattern": pattern, "group": group}, String)
+
+    def replace(self, pattern, template):
+        """
+        Replace every match of the regular expression `pattern` in this string with the given `template`,
+        which may refer to capture groups like `$1` or `${name}`.
+        """
+
+        return self._post("replace", {"pattern": pattern, "template": template}, String)
+
 
 class Version(Value):
     """
@@ -156,6 +336,9 @@ class Number(Value):
     def __div__(self, other):
         return self.div(other)
 
+    def __mod__(self, other):
+        return self.rem(other)
+
     def __mul__(self, other):
         return self.mul(other)
 
@@ -233,6 +416,11 @@ class Number(Value):
 
         return self._get("cosh", rtype=self._trig_rtype())
 
+    def exp(self):
+        """Raise `e` to the power of this `Number`."""
+
+        return self._get("exp", rtype=self.__class__)
+
     def div(self, other):
         """Return the quotient of `self` and `other`."""
 
@@ -248,6 +436,11 @@ class Number(Value):
 
         return self._get("pow", other, self.__class__)
 
+    def rem(self, other):
+        """Return the remainder of `self` divided by `other`."""
+
+        return self._get("rem", other, self.__class__)
+
     def sin(self):
         """Return the sine of this `Number`."""
 
@@ -304,33 +497,108 @@ class Bool(Number):
         return self._get("xor", other, Bool)
 
 
+class BigInt(Number):
+    """An arbitrary-precision integer, which is encoded as a string to avoid any loss of precision."""
+
+    __uri__ = uri(Number) + "/bigint"
+
+    def __json__(self):
+        form = form_of(self)
+
+        if isinstance(form, int):
+            return {str(uri(self)): [str(form)]}
+        else:
+            return Number.__json__(self)
+
+
 class Complex(Number):
     """A complex number."""
 
     __uri__ = uri(Number) + "/complex"
 
+    @classmethod
+    def _real_rtype(cls):
+        return Float
+
     def abs(self):
         """Return the linear norm of this complex number."""
 
         return Number.abs(self)
 
+    def arg(self):
+        """Return the argument (phase angle) of this complex number."""
+
+        return self._get("arg", rtype=self._real_rtype())
+
+    def conj(self):
+        """Return the complex conjugate of this complex number."""
+
+        return self._get("conj", rtype=self.__class__)
+
+    def im(self):
+        """Return the imaginary component of this complex number."""
+
+        return self._get("im", rtype=self._real_rtype())
+
     def norm(self):
         """Return the linear norm of this complex number."""
 
         return self.abs()
 
+    def re(self):
+        """Return the real component of this complex number."""
+
+        return self._get("re", rtype=self._real_rtype())
+
 
 class C32(Complex):
     """A complex 32-bit floating point number."""
 
     __uri__ = uri(Complex) + "/32"
 
+    @classmethod
+    def _real_rtype(cls):
+        return F32
+
 
 class C64(Complex):
     """A complex 64-bit floating point number."""
 
     __uri__ = uri(Complex) + "/64"
 
+    @classmethod
+    def _real_rtype(cls):
+        return F64
+
+
+class Decimal(Number):
+    """
+    An arbitrary-precision decimal number, e.g. a currency amount, which is never rounded
+    like a :class:`Float` (except by division, which rounds to 28 decimal places).
+
+    Construct a `Decimal` from a string or a :class:`decimal.Decimal` to avoid any loss of precision.
+    """
+
+    __uri__ = uri(Number) + "/decimal"
+
+    def __json__(self):
+        form = form_of(self)
+
+        if isinstance(form, (int, str, decimal.Decimal)):
+            return {str(uri(self)): [str(form)]}
+        else:
+            return Number.__json__(self)
+
+    def round(self, places=0):
+        """Round this `Decimal` to the given number of decimal `places`, rounding half to even."""
+
+        return self._get("round", places, Decimal)
+
+    def trunc(self):
+        """Return the integer part of this `Decimal`, rounding toward zero."""
+
+        return self._get("trunc", rtype=BigInt)
+
 
 class Float(Number):
     """A floating-point decimal number."""
@@ -12,14 +12,14 @@ use collate::Collate;
 use destream::{de, en};
 use futures::future::{self, Future, TryFutureExt};
 use futures::join;
-use futures::stream::{self, FuturesOrdered, FuturesUnordered, TryStreamExt};
+use futures::stream::{self, FuturesOrdered, FuturesUnordered, Stream, TryStreamExt};
 use log::debug;
 use uuid::Uuid;
 
 use tc_error::*;
 use tc_transact::fs::*;
 use tc_transact::lock::TxnLock;
-use tc_transact::{Transact, Transaction, TxnId};
+use tc_transact::{Isolation, Transact, Transaction, TxnId};
 use tc_value::{Value, ValueCollator};
 use tcgeneric::{Instance, TCBoxTryFuture, TCBoxTryStream, Tuple};
 
@@ -220,6 +220,7 @@ struct Inner<F, D, T> {
 #[derive(Clone)]
 pub struct BTreeFile<F, D, T> {
     inner: Arc<Inner<F, D, T>>,
+    isolation: Isolation,
 }
 
 impl<F: File<Node>, D: Dir, T: Transaction<D>> BTreeFile<F, D, T>
@@ -237,6 +238,7 @@ where
                 dir: PhantomData,
                 txn: PhantomData,
             }),
+            isolation: Isolation::default(),
         }
     }
 
@@ -259,6 +261,54 @@ where
         Ok(BTreeFile::new(file, schema, order, root))
     }
 
+    /// Create a new `BTreeFile` from a stream of `keys` which is already sorted in ascending order.
+    ///
+    /// Unlike inserting each key into an empty `BTreeFile`, this fills each node from left to right
+    /// and writes each block exactly once. Duplicate keys are ignored.
+    pub async fn bulk_load<S>(
+        file: F,
+        schema: RowSchema,
+        txn_id: TxnId,
+        mut keys: S,
+    ) -> TCResult<Self>
+    where
+        S: Stream<Item = TCResult<Key>> + Send + Unpin,
+    {
+        if !file.is_empty(txn_id).await? {
+            return Err(TCError::internal(
+                "Tried to bulk-load a new BTree without a new File",
+            ));
+        }
+
+        let order = validate_schema(&schema)?;
+        let collator = ValueCollator::default();
+
+        let mut loader = BulkLoad::new(&file, txn_id, (2 * order) - 1);
+        let mut last: Option<Key> = None;
+        while let Some(key) = keys.try_next().await? {
+            let key = validate_key(&schema, key)?;
+
+            if let Some(last) = &last {
+                match collator.compare_slice(last, &key) {
+                    Ordering::Less => {}
+                    Ordering::Equal => continue,
+                    Ordering::Greater => {
+                        return Err(TCError::bad_request(
+                            "cannot bulk-load a BTree from keys out of order at",
+                            Tuple::from(key),
+                        ))
+                    }
+                }
+            }
+
+            loader.push(key.to_vec()).await?;
+            last = Some(key);
+        }
+
+        let root = loader.finish().await?;
+        Ok(BTreeFile::new(file, schema, order, root))
+    }
+
     fn _delete_range<'a>(
         &'a self,
         txn_id: TxnId,
@@ -404,7 +454,11 @@ where
 
                 let this = self.clone();
                 let selection = Box::pin(async move {
-                    let node = this.inner.file.read_block(txn_id, child_id).await?;
+                    let node = this
+                        .inner
+                        .file
+                        .read_block_isolated(txn_id, child_id, this.isolation)
+                        .await?;
                     this._slice(txn_id, node, range_clone)
                 });
                 selected.push(Box::pin(selection));
@@ -421,7 +475,11 @@ where
             let last_child_id = node.children[r].clone();
 
             let selection = Box::pin(async move {
-                let node = self.inner.file.read_block(txn_id, last_child_id).await?;
+                let node = self
+                    .inner
+                    .file
+                    .read_block_isolated(txn_id, last_child_id, self.isolation)
+                    .await?;
                 self._slice(txn_id, node, range)
             });
             selected.push(Box::pin(selection));
@@ -461,7 +519,11 @@ where
             let range_clone = range.clone();
             let this = self.clone();
             let selection = Box::pin(async move {
-                let node = this.inner.file.read_block(txn_id, last_child).await?;
+                let node = this
+                    .inner
+                    .file
+                    .read_block_isolated(txn_id, last_child, this.isolation)
+                    .await?;
                 this._slice_reverse(txn_id, node, range_clone)
             });
             selected.push(Box::pin(selection));
@@ -472,7 +534,11 @@ where
 
                 let this = self.clone();
                 let selection = Box::pin(async move {
-                    let node = this.inner.file.read_block(txn_id, child_id).await?;
+                    let node = this
+                        .inner
+                        .file
+                        .read_block_isolated(txn_id, child_id, this.isolation)
+                        .await?;
                     this._slice_reverse(txn_id, node, range_clone)
                 });
 
@@ -500,12 +566,16 @@ where
     where
         Self: 'a,
     {
-        let root_id = self.inner.root.read(txn_id).await?;
+        let root_id = self
+            .inner
+            .root
+            .read_isolated(txn_id, self.isolation)
+            .await?;
 
         let root = self
             .inner
             .file
-            .read_block(txn_id, (*root_id).clone())
+            .read_block_isolated(txn_id, (*root_id).clone(), self.isolation)
             .await?;
 
         if reverse {
@@ -589,12 +659,21 @@ where
         BTreeSlice::new(BTree::File(self), range, reverse)
     }
 
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        Self { isolation, ..self }
+    }
+
     async fn is_empty(&self, txn_id: TxnId) -> TCResult<bool> {
-        let root_id = self.inner.root.read(txn_id).await?;
+        let root_id = self
+            .inner
+            .root
+            .read_isolated(txn_id, self.isolation)
+            .await?;
+
         let root = self
             .inner
             .file
-            .read_block(txn_id, (*root_id).clone())
+            .read_block_isolated(txn_id, (*root_id).clone(), self.isolation)
             .await?;
 
         Ok(root.keys.is_empty())
@@ -605,17 +684,7 @@ where
     }
 
     fn validate_key(&self, key: Key) -> TCResult<Key> {
-        if key.len() != self.inner.schema.len() {
-            return Err(TCError::bad_request("invalid key length", Tuple::from(key)));
-        }
-
-        key.into_iter()
-            .zip(&self.inner.schema)
-            .map(|(val, col)| {
-                val.into_type(col.dtype)
-                    .ok_or_else(|| TCError::bad_request("invalid value for column", &col.name))
-            })
-            .collect()
+        validate_key(&self.inner.schema, key)
     }
 }
 
@@ -774,10 +843,8 @@ impl<F: File<Node>, D: Dir, T: Transaction<D>, I: BTreeInstance + 'static> CopyF
     async fn copy_from(source: I, file: F, txn: &T) -> TCResult<Self> {
         let txn_id = *txn.id();
         let schema = source.schema().clone();
-        let dest = Self::create(file, schema, txn_id).await?;
         let keys = source.keys(txn_id).await?;
-        dest.try_insert_from(txn_id, keys).await?;
-        Ok(dest)
+        Self::bulk_load(file, schema, txn_id, keys).await
     }
 }
 
@@ -787,6 +854,101 @@ impl<F, D, T> fmt::Display for BTreeFile<F, D, T> {
     }
 }
 
+/// The state of a [`BTreeFile::bulk_load`], with one open node per level of the tree.
+///
+/// A node is only written to its block once it's full (or the load is finished),
+/// so its ID is assigned in advance in order for its children to reference it as their parent.
+struct BulkLoad<'a, F> {
+    file: &'a F,
+    txn_id: TxnId,
+    max_keys: usize,
+    levels: Vec<(NodeId, Node)>,
+}
+
+impl<'a, F: File<Node>> BulkLoad<'a, F> {
+    fn new(file: &'a F, txn_id: TxnId, max_keys: usize) -> Self {
+        Self {
+            file,
+            txn_id,
+            max_keys,
+            levels: vec![],
+        }
+    }
+
+    fn open(&mut self, level: usize) {
+        if self.levels.len() == level {
+            self.levels
+                .push((Uuid::new_v4().into(), Node::new(level == 0, None)));
+        }
+    }
+
+    async fn push(&mut self, key: Key) -> TCResult<()> {
+        let mut level = 0;
+
+        loop {
+            self.open(level);
+
+            let (_, node) = &mut self.levels[level];
+            if node.keys.len() < self.max_keys {
+                node.keys.push(NodeKey::new(key));
+                return Ok(());
+            }
+
+            // the node at this level is full, so the key belongs in its parent
+            self.close(level).await?;
+            level += 1;
+        }
+    }
+
+    async fn close(&mut self, level: usize) -> TCResult<()> {
+        self.open(level + 1);
+
+        let parent_id = self.levels[level + 1].0.clone();
+        let next = (Uuid::new_v4().into(), Node::new(level == 0, None));
+        let (node_id, mut node) = std::mem::replace(&mut self.levels[level], next);
+        node.parent = Some(parent_id);
+
+        self.file
+            .create_block(self.txn_id, node_id.clone(), node, DEFAULT_BLOCK_SIZE)
+            .await?;
+
+        self.levels[level + 1].1.children.push(node_id);
+        Ok(())
+    }
+
+    async fn finish(mut self) -> TCResult<NodeId> {
+        self.open(0);
+
+        let root_level = self.levels.len() - 1;
+        for level in 0..root_level {
+            self.close(level).await?;
+        }
+
+        let (root_id, root) = self.levels.pop().expect("BTree root");
+        debug_assert_eq!(root.children.is_empty(), root.leaf);
+
+        self.file
+            .create_block(self.txn_id, root_id.clone(), root, DEFAULT_BLOCK_SIZE)
+            .await?;
+
+        Ok(root_id)
+    }
+}
+
+fn validate_key(schema: &RowSchema, key: Key) -> TCResult<Key> {
+    if key.len() != schema.len() {
+        return Err(TCError::bad_request("invalid key length", Tuple::from(key)));
+    }
+
+    key.into_iter()
+        .zip(schema)
+        .map(|(val, col)| {
+            val.into_type(col.dtype)
+                .ok_or_else(|| TCError::bad_request("invalid value for column", &col.name))
+        })
+        .collect()
+}
+
 fn validate_schema(schema: &RowSchema) -> TCResult<usize> {
     let mut key_size = 0;
     for col in schema {
@@ -12,7 +12,7 @@ use safecast::*;
 
 use tc_error::*;
 use tc_transact::fs::{Dir, File, Hash};
-use tc_transact::{IntoView, Transaction, TxnId};
+use tc_transact::{IntoView, Isolation, Transaction, TxnId};
 use tc_value::{NumberType, Value, ValueCollator, ValueType};
 use tcgeneric::*;
 
@@ -48,6 +48,11 @@ pub trait BTreeInstance: Clone + Instance {
     /// Return a slice of this `BTree` with the given range.
     fn slice(self, range: Range, reverse: bool) -> TCResult<Self::Slice>;
 
+    /// Return a handle to this `BTree` which reads with the given [`Isolation`] level.
+    ///
+    /// Writes are not affected by the isolation level.
+    fn with_isolation(self, isolation: Isolation) -> Self;
+
     /// Return the number of [`Key`]s in this `BTree`.
     async fn count(&self, txn_id: TxnId) -> TCResult<u64> {
         // TODO: reimplement this more efficiently
@@ -386,6 +391,13 @@ where
         }
     }
 
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        match self {
+            Self::File(file) => Self::File(file.with_isolation(isolation)),
+            Self::Slice(slice) => Self::Slice(slice.with_isolation(isolation)),
+        }
+    }
+
     async fn is_empty(&self, txn_id: TxnId) -> TCResult<bool> {
         match self {
             Self::File(file) => file.is_empty(txn_id).await,
@@ -572,7 +584,7 @@ where
 
     async fn into_view(self, txn: T) -> TCResult<BTreeView<'en>> {
         let schema = self.schema().to_vec();
-        let keys = self.keys(*txn.id()).await?;
+        let keys = self.with_isolation(txn.isolation()).keys(*txn.id()).await?;
         Ok(BTreeView { schema, keys })
     }
 }
@@ -3,7 +3,7 @@ use futures::{TryFutureExt, TryStreamExt};
 
 use tc_error::{TCError, TCResult};
 use tc_transact::fs::{Dir, File};
-use tc_transact::{Transaction, TxnId};
+use tc_transact::{Isolation, Transaction, TxnId};
 use tc_value::ValueCollator;
 use tcgeneric::{Instance, TCBoxTryStream};
 
@@ -98,6 +98,13 @@ where
         }
     }
 
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        Self {
+            source: self.source.with_isolation(isolation),
+            ..self
+        }
+    }
+
     async fn is_empty(&self, txn_id: TxnId) -> TCResult<bool> {
         let mut rows = self
             .source
@@ -4,7 +4,7 @@
 
 use std::fmt;
 
-use destream::{en, EncodeMap, Encoder};
+use destream::{en, EncodeMap, EncodeSeq, Encoder};
 
 pub type TCResult<T> = Result<T, TCError>;
 
@@ -20,27 +20,32 @@ pub enum ErrorType {
     NotFound,
     NotImplemented,
     Timeout,
+    TooManyRequests,
     Unauthorized,
 }
 
+impl ErrorType {
+    /// The machine-readable name of this `ErrorType`, like `not_found`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::BadGateway => "bad_gateway",
+            Self::BadRequest => "bad_request",
+            Self::Conflict => "conflict",
+            Self::Forbidden => "forbidden",
+            Self::Internal => "internal",
+            Self::MethodNotAllowed => "method_not_allowed",
+            Self::NotFound => "not_found",
+            Self::NotImplemented => "not_implemented",
+            Self::Timeout => "timeout",
+            Self::TooManyRequests => "too_many_requests",
+            Self::Unauthorized => "unauthorized",
+        }
+    }
+}
+
 impl<'en> en::IntoStream<'en> for ErrorType {
     fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
-        format!(
-            "/error/{}",
-            match self {
-                Self::BadGateway => "bad_gateway",
-                Self::BadRequest => "bad_request",
-                Self::Conflict => "conflict",
-                Self::Forbidden => "forbidden",
-                Self::Internal => "internal",
-                Self::MethodNotAllowed => "method_not_allowed",
-                Self::NotFound => "not_found",
-                Self::NotImplemented => "not_implemented",
-                Self::Timeout => "timeout",
-                Self::Unauthorized => "unauthorized",
-            }
-        )
-        .into_stream(encoder)
+        format!("/error/{}", self.name()).into_stream(encoder)
     }
 }
 
@@ -62,6 +67,7 @@ impl fmt::Display for ErrorType {
             Self::NotFound => f.write_str("not found"),
             Self::NotImplemented => f.write_str("not implemented"),
             Self::Timeout => f.write_str("request timeout"),
+            Self::TooManyRequests => f.write_str("too many requests"),
             Self::Unauthorized => f.write_str("unauthorized"),
         }
     }
@@ -71,12 +77,17 @@ impl fmt::Display for ErrorType {
 pub struct TCError {
     code: ErrorType,
     message: String,
+    causes: Vec<TCError>,
 }
 
 impl TCError {
     /// Returns a new error with the given code and message.
     pub fn new(code: ErrorType, message: String) -> Self {
-        Self { code, message }
+        Self {
+            code,
+            message,
+            causes: vec![],
+        }
     }
 
     /// Error indicating that the an upstream server send an invalid response.
@@ -84,6 +95,7 @@ impl TCError {
         Self {
             code: ErrorType::BadGateway,
             message: cause.to_string(),
+            causes: vec![],
         }
     }
 
@@ -92,6 +104,7 @@ impl TCError {
         Self {
             code: ErrorType::BadRequest,
             message: format!("{}: {}", message, cause),
+            causes: vec![],
         }
     }
 
@@ -101,6 +114,7 @@ impl TCError {
         Self {
             code: ErrorType::Conflict,
             message: String::default(),
+            causes: vec![],
         }
     }
 
@@ -110,6 +124,7 @@ impl TCError {
         Self {
             code: ErrorType::Forbidden,
             message: format!("{}: {}", message, id),
+            causes: vec![],
         }
     }
 
@@ -123,6 +138,7 @@ impl TCError {
         Self {
             code: ErrorType::Internal,
             message: info.to_string(),
+            causes: vec![],
         }
     }
 
@@ -135,6 +151,7 @@ impl TCError {
         Self {
             code: ErrorType::MethodNotAllowed,
             message: format!("{} endpoint {} does not support {}", subject, path, method),
+            causes: vec![],
         }
     }
 
@@ -143,6 +160,7 @@ impl TCError {
         Self {
             code: ErrorType::NotFound,
             message: locator.to_string(),
+            causes: vec![],
         }
     }
 
@@ -151,6 +169,7 @@ impl TCError {
         Self {
             code: ErrorType::NotImplemented,
             message: feature.to_string(),
+            causes: vec![],
         }
     }
 
@@ -159,6 +178,17 @@ impl TCError {
         Self {
             code: ErrorType::Timeout,
             message: info.to_string(),
+            causes: vec![],
+        }
+    }
+
+    /// Error indicating that the host is too busy to accept the request, or that the requestor
+    /// has exceeded its rate limit, so the request should be retried later.
+    pub fn too_many_requests<I: fmt::Display>(info: I) -> Self {
+        Self {
+            code: ErrorType::TooManyRequests,
+            message: info.to_string(),
+            causes: vec![],
         }
     }
 
@@ -167,6 +197,7 @@ impl TCError {
         Self {
             code: ErrorType::Unauthorized,
             message: format!("invalid credentials: {}", info),
+            causes: vec![],
         }
     }
 
@@ -175,6 +206,7 @@ impl TCError {
         Self {
             code: ErrorType::BadRequest,
             message: info.to_string(),
+            causes: vec![],
         }
     }
 
@@ -186,10 +218,23 @@ impl TCError {
         &self.message
     }
 
+    /// The errors which caused this error, if any.
+    pub fn causes(&self) -> &[TCError] {
+        &self.causes
+    }
+
+    /// Record that this error was caused by the given `cause`.
+    pub fn with_cause(mut self, cause: TCError) -> Self {
+        self.causes.push(cause);
+        self
+    }
+
+    /// Prefix the message of this error with the given `info`, keeping the original error as its cause.
     pub fn consume<I: fmt::Display>(self, info: I) -> Self {
         Self {
             code: self.code,
             message: format!("{}: {}", info, self.message),
+            causes: vec![self],
         }
     }
 }
@@ -212,6 +257,97 @@ impl<'en> en::IntoStream<'en> for TCError {
     }
 }
 
+/// A machine-readable report of a [`TCError`], for the response to a request which failed.
+///
+/// A report is encoded as a map with the error's `code`, `message`, and `causes` (each encoded the
+/// same way), plus the `path` and `txn_id` of the failed request, if known. For compatibility with
+/// clients which expect the legacy encoding `{"/error/<code>": "<message>"}`, it also includes the
+/// legacy entry.
+pub struct ErrorReport {
+    error: TCError,
+    path: Option<String>,
+    txn_id: Option<String>,
+}
+
+impl ErrorReport {
+    /// Construct a new report of the given `error`.
+    pub fn new(error: TCError) -> Self {
+        Self {
+            error,
+            path: None,
+            txn_id: None,
+        }
+    }
+
+    /// Report the `path` of the request which failed.
+    pub fn at<P: fmt::Display>(mut self, path: P) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Report the ID of the transaction which failed.
+    pub fn in_txn<T: fmt::Display>(mut self, txn_id: T) -> Self {
+        self.txn_id = Some(txn_id.to_string());
+        self
+    }
+
+    /// The error reported.
+    pub fn error(&self) -> &TCError {
+        &self.error
+    }
+}
+
+impl<'en> en::IntoStream<'en> for ErrorReport {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let TCError {
+            code,
+            message,
+            causes,
+        } = self.error;
+
+        let mut map = encoder.encode_map(None)?;
+        map.encode_entry(code, message.clone())?;
+        map.encode_entry("code", code.name())?;
+        map.encode_entry("message", message)?;
+
+        if let Some(path) = self.path {
+            map.encode_entry("path", path)?;
+        }
+
+        if let Some(txn_id) = self.txn_id {
+            map.encode_entry("txn_id", txn_id)?;
+        }
+
+        map.encode_entry("causes", Causes(causes))?;
+        map.end()
+    }
+}
+
+struct Causes(Vec<TCError>);
+
+impl<'en> en::IntoStream<'en> for Causes {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let mut seq = encoder.encode_seq(Some(self.0.len()))?;
+        for cause in self.0 {
+            seq.encode_element(Cause(cause))?;
+        }
+
+        seq.end()
+    }
+}
+
+struct Cause(TCError);
+
+impl<'en> en::IntoStream<'en> for Cause {
+    fn into_stream<E: Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let mut map = encoder.encode_map(Some(3))?;
+        map.encode_entry("code", self.0.code.name())?;
+        map.encode_entry("message", self.0.message)?;
+        map.encode_entry("causes", Causes(self.0.causes))?;
+        map.end()
+    }
+}
+
 #[cfg(feature = "tensor")]
 impl From<afarray::ArrayError> for TCError {
     fn from(cause: afarray::ArrayError) -> Self {
@@ -222,6 +358,7 @@ impl From<afarray::ArrayError> for TCError {
         Self {
             code: ErrorType::Internal,
             message: format!("tensor error: {}", cause),
+            causes: vec![],
         }
     }
 }
@@ -0,0 +1,218 @@
+"use strict";
+
+const OVERVIEW = "/admin/api/overview";
+const BROWSE = "/admin/api/browse";
+
+function element(tag, text) {
+  const elem = document.createElement(tag);
+  if (text !== undefined) {
+    elem.textContent = text;
+  }
+
+  return elem;
+}
+
+function table(columns, rows) {
+  const table = element("table");
+  const header = element("tr");
+  for (const column of columns) {
+    header.appendChild(element("th", column));
+  }
+
+  table.appendChild(header);
+
+  for (const row of rows) {
+    const tr = element("tr");
+    for (const cell of row) {
+      tr.appendChild(element("td", typeof cell === "string" ? cell : JSON.stringify(cell)));
+    }
+
+    table.appendChild(tr);
+  }
+
+  return table;
+}
+
+function pathLink(path) {
+  const link = element("a", path);
+  link.className = "path";
+  link.addEventListener("click", () => {
+    const form = document.getElementById("browse");
+    form.path.value = path;
+    form.key.value = "";
+    browse();
+  });
+
+  return link;
+}
+
+function authHeaders() {
+  const token = sessionStorage.getItem("token");
+  return token ? { Authorization: `Bearer ${token}` } : {};
+}
+
+async function fetchJson(url) {
+  const response = await fetch(url, { headers: authHeaders() });
+  const body = await response.text();
+  if (!response.ok) {
+    throw new Error(body);
+  }
+
+  return JSON.parse(body);
+}
+
+function showError(cause) {
+  document.getElementById("error").textContent = cause ? cause.message : "";
+}
+
+function describeSubject(subject) {
+  if (subject.key !== undefined) {
+    return `${subject.key.length + subject.values.length} columns`;
+  } else if (subject.schema !== undefined) {
+    return `${subject.schema.length} columns`;
+  } else if (subject.shape !== undefined) {
+    return `${subject.dtype} [${subject.shape.join(", ")}]`;
+  } else if (subject.members !== undefined) {
+    return `${Object.keys(subject.members).length} members`;
+  } else {
+    return "";
+  }
+}
+
+function schema(subject) {
+  if (subject.key !== undefined) {
+    return subject.key.map((col) => `${col.name}: ${col.dtype} (key)`)
+      .concat(subject.values.map((col) => `${col.name}: ${col.dtype}`))
+      .join(", ");
+  } else if (subject.schema !== undefined) {
+    return subject.schema.map((col) => `${col.name}: ${col.dtype}`).join(", ");
+  } else {
+    return "";
+  }
+}
+
+function collectSubjects(subject, rows) {
+  rows.push(subject);
+
+  if (subject.members !== undefined) {
+    for (const member of Object.values(subject.members)) {
+      collectSubjects(member, rows);
+    }
+  }
+
+  return rows;
+}
+
+function renderCluster(cluster) {
+  const div = element("div");
+  div.className = "cluster";
+
+  div.appendChild(element("h3", cluster.path));
+  div.appendChild(element("p", `link: ${cluster.link}`));
+  div.appendChild(element("p", `status: ${cluster.online ? "online" : "catching up"}`));
+
+  if (cluster.replicas.length) {
+    div.appendChild(element("p", `replicas: ${cluster.replicas.join(", ")}`));
+  }
+
+  const chains = table(["path", "chain", "class", "size", "schema"], []);
+  for (const chain of Object.values(cluster.chains)) {
+    for (const subject of collectSubjects(chain.subject, [])) {
+      const tr = element("tr");
+      const path = element("td");
+      path.appendChild(pathLink(subject.path));
+      tr.appendChild(path);
+      tr.appendChild(element("td", chain.class));
+      tr.appendChild(element("td", subject.class));
+      tr.appendChild(element("td", describeSubject(subject)));
+      tr.appendChild(element("td", schema(subject)));
+      chains.appendChild(tr);
+    }
+  }
+
+  div.appendChild(chains);
+
+  if (cluster.classes.length) {
+    div.appendChild(element("p", `classes: ${cluster.classes.join(", ")}`));
+  }
+
+  if (cluster.methods.length) {
+    const methods = table(["method", "path"], []);
+    for (const method of cluster.methods) {
+      const tr = element("tr");
+      tr.appendChild(element("td", method.method));
+      const path = element("td");
+      path.appendChild(method.method === "GET" ? pathLink(method.path) : element("span", method.path));
+      tr.appendChild(path);
+      methods.appendChild(tr);
+    }
+
+    div.appendChild(methods);
+  }
+
+  return div;
+}
+
+async function refresh() {
+  try {
+    const overview = await fetchJson(OVERVIEW);
+    document.getElementById("host").textContent = `${overview.host} (v${overview.version})`;
+
+    const metrics = overview.metrics;
+    document.getElementById("metrics").replaceChildren(table(
+      ["active", "pending versions", "finalized"],
+      [[metrics.active, metrics.pending_versions, metrics.finalized]]));
+
+    const clusters = document.getElementById("clusters");
+    clusters.replaceChildren(...overview.clusters.map(renderCluster));
+
+    showError(null);
+  } catch (cause) {
+    showError(cause);
+  }
+}
+
+async function browse() {
+  const form = document.getElementById("browse");
+  const params = new URLSearchParams({ path: form.path.value, limit: form.limit.value });
+  if (form.key.value) {
+    params.set("key", form.key.value);
+  }
+
+  const output = document.getElementById("data");
+
+  try {
+    const data = await fetchJson(`${BROWSE}?${params}`);
+    const heading = element("p", `${data.path} (${data.class})`);
+
+    if (data.rows !== undefined) {
+      const summary = data.truncated ? `first ${data.rows.length} rows` : `${data.rows.length} rows`;
+      heading.textContent += `: ${summary}`;
+      output.replaceChildren(heading, table(data.columns, data.rows));
+    } else if (data.shape !== undefined) {
+      heading.textContent += `: ${data.dtype} [${data.shape.join(", ")}], ${data.size} elements`;
+      output.replaceChildren(heading);
+    } else {
+      output.replaceChildren(heading, element("pre", JSON.stringify(data.value, null, 2)));
+    }
+
+    showError(null);
+  } catch (cause) {
+    showError(cause);
+  }
+}
+
+const token = document.getElementById("token");
+token.value = sessionStorage.getItem("token") || "";
+token.addEventListener("change", () => {
+  sessionStorage.setItem("token", token.value);
+  refresh();
+});
+
+document.getElementById("refresh").addEventListener("click", refresh);
+document.getElementById("browse").addEventListener("submit", (event) => {
+  event.preventDefault();
+  browse();
+});
+
+refresh();
@@ -0,0 +1,356 @@
+//! An optional, browser-facing admin dashboard, served by the host at [`PREFIX`].
+//!
+//! The dashboard is a static page embedded in the host binary. It reads an overview of this host
+//! (its hosted clusters and their chains, the schema of each collection, and transaction metrics)
+//! from [`OVERVIEW`], and browses the data at a given path via [`BROWSE`], which reads the path
+//! through the same routes as an ordinary GET request. The dashboard is read-only.
+//!
+//! Only a host administrator (see the `--host_admin` option) can read [`OVERVIEW`] or [`BROWSE`],
+//! so the dashboard page sends the bearer token entered by its user with each request. The static
+//! assets of the page itself contain no data, so they're served to any client.
+
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use serde_json::{json, Map as JsonMap, Value as Json};
+
+use tc_btree::{BTreeInstance, Column};
+use tc_error::*;
+use tc_table::{TableInstance, TableStream};
+#[cfg(feature = "tc-tensor")]
+use tc_tensor::TensorAccess;
+use tc_transact::Transaction;
+use tc_value::{LinkHost, Value};
+use tcgeneric::{Id, Instance, NativeClass, TCBoxTryFuture, TCPath, TCPathBuf};
+
+use crate::chain::{ChainInstance, Subject};
+use crate::cluster::Cluster;
+use crate::collection::Collection;
+use crate::graphql::{state_to_json, value_to_json};
+use crate::kernel::Kernel;
+use crate::object::InstanceExt;
+use crate::scalar::{OpDef, Scalar};
+use crate::state::State;
+use crate::txn::{Txn, TxnServer};
+
+/// The path prefix of the admin dashboard.
+pub const PREFIX: &str = "/admin";
+
+/// The path of the JSON overview of this host.
+pub const OVERVIEW: &str = "/admin/api/overview";
+
+/// The path of the JSON endpoint used to browse the data at a given path.
+pub const BROWSE: &str = "/admin/api/browse";
+
+/// The number of rows returned by [`BROWSE`] if no `limit` is given.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// The maximum number of rows returned by [`BROWSE`].
+pub const MAX_LIMIT: usize = 1000;
+
+const ASSETS: [(&str, &str, &str); 4] = [
+    (
+        PREFIX,
+        "text/html; charset=utf-8",
+        include_str!("index.html"),
+    ),
+    (
+        "/admin/index.html",
+        "text/html; charset=utf-8",
+        include_str!("index.html"),
+    ),
+    (
+        "/admin/admin.js",
+        "application/javascript; charset=utf-8",
+        include_str!("admin.js"),
+    ),
+    (
+        "/admin/admin.css",
+        "text/css; charset=utf-8",
+        include_str!("admin.css"),
+    ),
+];
+
+/// Return `true` if the given request `path` belongs to the admin dashboard.
+pub fn is_admin_path(path: &str) -> bool {
+    path == PREFIX || path.starts_with("/admin/")
+}
+
+/// Return the content type and body of the static asset at the given `path`, if any.
+pub fn asset(path: &str) -> Option<(&'static str, Bytes)> {
+    let path = if path == "/admin/" { PREFIX } else { path };
+
+    ASSETS
+        .iter()
+        .find(|(asset_path, _, _)| *asset_path == path)
+        .map(|(_, content_type, body)| (*content_type, Bytes::from_static(body.as_bytes())))
+}
+
+/// The data sources of the admin dashboard.
+pub struct Dashboard<'a> {
+    root: &'a LinkHost,
+    kernel: &'a Kernel,
+    txn_server: &'a TxnServer,
+}
+
+impl<'a> Dashboard<'a> {
+    /// Construct a new `Dashboard` of the host at `root`.
+    pub fn new(root: &'a LinkHost, kernel: &'a Kernel, txn_server: &'a TxnServer) -> Self {
+        Self {
+            root,
+            kernel,
+            txn_server,
+        }
+    }
+
+    /// Describe this host, its hosted clusters, and its active transactions.
+    pub async fn overview(&self, txn: &Txn) -> TCResult<Json> {
+        self.kernel
+            .authorize_admin(txn, "viewing the admin dashboard")?;
+
+        let metrics = self.txn_server.metrics().await;
+
+        let mut clusters = Vec::new();
+        for cluster in self.kernel.visible(txn).await {
+            clusters.push(describe_cluster(txn, &cluster).await?);
+        }
+
+        Ok(json!({
+            "host": self.root.to_string(),
+            "version": env!("CARGO_PKG_VERSION"),
+            "metrics": {
+                "active": metrics.active,
+                "pending_versions": metrics.pending_versions,
+                "finalized": metrics.finalized,
+            },
+            "clusters": clusters,
+        }))
+    }
+
+    /// Read up to `limit` rows (or the whole value) of the state at `path` with the given `key`.
+    pub async fn browse(
+        &self,
+        txn: &Txn,
+        path: TCPathBuf,
+        key: Value,
+        limit: Option<usize>,
+    ) -> TCResult<Json> {
+        self.kernel
+            .authorize_admin(txn, "viewing the admin dashboard")?;
+
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if limit > MAX_LIMIT {
+            return Err(TCError::bad_request(
+                format!("the maximum browse limit is {}, not", MAX_LIMIT),
+                limit,
+            ));
+        }
+
+        let txn_id = *txn.id();
+        let state = txn.get(txn.link(path.clone()), key).await?;
+        let class = state.class().path().to_string();
+
+        let mut data = match state {
+            State::Collection(Collection::Table(table)) => {
+                let columns = column_names(table.key().iter().chain(table.values()));
+                let rows = table.rows(txn_id).await?;
+                let rows = rows.take(limit + 1).try_collect().await?;
+                table_json(columns, rows, limit)
+            }
+            State::Collection(Collection::BTree(btree)) => {
+                let columns = column_names(btree.schema().iter());
+                let rows = btree.keys(txn_id).await?;
+                let rows = rows.take(limit + 1).try_collect().await?;
+                table_json(columns, rows, limit)
+            }
+            #[cfg(feature = "tc-tensor")]
+            State::Collection(Collection::Tensor(tensor)) => json!({
+                "shape": tensor.shape().to_vec(),
+                "dtype": tensor.dtype().to_string(),
+                "size": tensor.size(),
+            }),
+            other => json!({ "value": state_to_json(txn, other).await? }),
+        };
+
+        data["path"] = Json::String(path.to_string());
+        data["class"] = Json::String(class);
+        Ok(data)
+    }
+}
+
+async fn describe_cluster(txn: &Txn, cluster: &InstanceExt<Cluster>) -> TCResult<Json> {
+    let root = TCPathBuf::from(cluster.path().to_vec());
+
+    let mut replicas: Vec<String> = cluster
+        .replicas(*txn.id())
+        .await?
+        .into_iter()
+        .map(|replica| replica.to_string())
+        .collect();
+
+    replicas.sort();
+
+    let mut chains = JsonMap::new();
+    let mut classes = Vec::new();
+    for name in cluster.ns() {
+        if let Some(chain) = cluster.chain(name) {
+            let path = root.clone().append(name.clone());
+            let subject = describe_subject(txn, path, chain.subject()).await?;
+
+            chains.insert(
+                name.to_string(),
+                json!({
+                    "class": chain.class().path().to_string(),
+                    "subject": subject,
+                }),
+            );
+        } else if Cluster::class(cluster, name).is_some() {
+            classes.push(name.to_string());
+        }
+    }
+
+    let mut methods = Vec::new();
+    for (name, attr) in cluster.proto().iter() {
+        if let Scalar::Op(op_def) = attr {
+            let method = match op_def {
+                OpDef::Get(_) => "GET",
+                OpDef::Put(_) => "PUT",
+                OpDef::Post(_) => "POST",
+                OpDef::Delete(_) => "DELETE",
+            };
+
+            methods.push(json!({
+                "path": root.clone().append(name.clone()).to_string(),
+                "method": method,
+            }));
+        }
+    }
+
+    Ok(json!({
+        "path": TCPath::from(cluster.path()).to_string(),
+        "link": cluster.link().to_string(),
+        "online": cluster.is_online(),
+        "replicas": replicas,
+        "chains": chains,
+        "classes": classes,
+        "methods": methods,
+    }))
+}
+
+fn describe_subject<'a>(
+    txn: &'a Txn,
+    path: TCPathBuf,
+    subject: &'a Subject,
+) -> TCBoxTryFuture<'a, Json> {
+    Box::pin(async move {
+        let class = subject.class().path().to_string();
+
+        let mut description = match subject {
+            Subject::Table(table) => json!({
+                "key": columns_json(table.key()),
+                "values": columns_json(table.values()),
+            }),
+            Subject::BTree(btree) => json!({
+                "schema": columns_json(btree.schema()),
+            }),
+            Subject::Map(map) => {
+                let mut members = JsonMap::new();
+                for (name, member) in map.iter() {
+                    let path = path.clone().append(name.clone());
+                    let member = describe_subject(txn, path, member).await?;
+                    members.insert(name.to_string(), member);
+                }
+
+                json!({ "members": members })
+            }
+            Subject::Tuple(tuple) => {
+                let mut members = Vec::with_capacity(tuple.len());
+                for (i, member) in tuple.iter().enumerate() {
+                    let path = path.clone().append(Id::from(i));
+                    members.push(describe_subject(txn, path, member).await?);
+                }
+
+                json!({ "members": members })
+            }
+            #[cfg(feature = "tc-tensor")]
+            Subject::Dense(dense) => json!({
+                "shape": dense.shape().to_vec(),
+                "dtype": dense.dtype().to_string(),
+                "size": dense.size(),
+            }),
+            #[cfg(feature = "tc-tensor")]
+            Subject::Sparse(sparse) => json!({
+                "shape": sparse.shape().to_vec(),
+                "dtype": sparse.dtype().to_string(),
+                "size": sparse.size(),
+            }),
+        };
+
+        description["path"] = Json::String(path.to_string());
+        description["class"] = Json::String(class);
+        Ok(description)
+    })
+}
+
+fn columns_json(columns: &[Column]) -> Json {
+    let columns = columns
+        .iter()
+        .map(|col| {
+            json!({
+                "name": col.name.to_string(),
+                "dtype": col.dtype.to_string(),
+                "max_len": col.max_len,
+            })
+        })
+        .collect();
+
+    Json::Array(columns)
+}
+
+fn column_names<'a, I: Iterator<Item = &'a Column>>(columns: I) -> Vec<String> {
+    columns.map(|col| col.name.to_string()).collect()
+}
+
+fn table_json(columns: Vec<String>, mut rows: Vec<Vec<Value>>, limit: usize) -> Json {
+    let truncated = rows.len() > limit;
+    rows.truncate(limit);
+
+    let rows: Vec<Json> = rows
+        .into_iter()
+        .map(|row| Json::Array(row.into_iter().map(value_to_json).collect()))
+        .collect();
+
+    json!({
+        "columns": columns,
+        "rows": rows,
+        "truncated": truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset() {
+        assert!(is_admin_path(PREFIX));
+        assert!(is_admin_path(OVERVIEW));
+        assert!(!is_admin_path("/administrator"));
+
+        let (content_type, index) = asset("/admin/").expect("index");
+        assert!(content_type.starts_with("text/html"));
+        assert_eq!(Some(index), asset(PREFIX).map(|(_, body)| body));
+
+        assert!(asset("/admin/missing.js").is_none());
+    }
+
+    #[test]
+    fn test_table_json() {
+        let columns = vec!["key".to_string()];
+        let rows = (0..3u64).map(|i| vec![Value::from(i)]).collect();
+
+        let page = table_json(columns, rows, 2);
+        assert_eq!(page["columns"], json!(["key"]));
+        assert_eq!(page["rows"].as_array().expect("rows").len(), 2);
+        assert_eq!(page["truncated"], Json::Bool(true));
+    }
+}
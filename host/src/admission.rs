@@ -0,0 +1,209 @@
+//! Admission control of inbound requests, to keep a single client from exhausting this host.
+//!
+//! An [`Admission`] controller limits the number of requests which execute concurrently, queueing
+//! any others until a slot is available, and rejects a request with a "too many requests" error
+//! if the queue is full. It can also limit the sustained rate of requests from each client with a
+//! token bucket, which allows a burst of requests up to a configured size.
+//!
+//! Requests which belong to a transaction already claimed by a cluster (e.g. replication and
+//! commit messages from another host) are not subject to admission control, since that
+//! transaction was already admitted where it began, and delaying it would hold its locks longer.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use log::debug;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use tc_error::*;
+
+/// The maximum number of idle clients to track before discarding their rate limit state.
+const MAX_CLIENTS: usize = 10_000;
+
+/// The policy by which a host admits inbound requests.
+#[derive(Clone, Copy)]
+pub struct AdmissionPolicy {
+    /// The maximum number of requests to execute concurrently (unlimited if `None`)
+    pub max_concurrent: Option<usize>,
+
+    /// The maximum number of requests to queue awaiting admission before rejecting a request
+    pub max_queued: usize,
+
+    /// The maximum sustained number of requests per second from a single client
+    /// (unlimited if `None`)
+    pub rate_limit: Option<f64>,
+
+    /// The maximum number of requests a client can make at once without being rate-limited
+    pub burst: u32,
+}
+
+/// Permission to execute a request, which is released when dropped.
+pub struct Permit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant, rate: f64, burst: f64) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.updated = now;
+    }
+}
+
+struct Queued<'a>(&'a AtomicUsize);
+
+impl<'a> Drop for Queued<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Controls the admission of inbound requests according to an [`AdmissionPolicy`].
+pub struct Admission {
+    policy: AdmissionPolicy,
+    permits: Option<Arc<Semaphore>>,
+    queued: AtomicUsize,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Admission {
+    /// Construct a new admission controller with the given `policy`.
+    pub fn new(policy: AdmissionPolicy) -> Self {
+        Self {
+            policy,
+            permits: policy
+                .max_concurrent
+                .map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            queued: AtomicUsize::new(0),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Admit a request from the given `client`, waiting for a slot to execute it if necessary.
+    ///
+    /// Returns a "too many requests" error if the `client` has exceeded its rate limit
+    /// or if the queue of requests awaiting admission is full.
+    pub async fn admit(&self, client: &str) -> TCResult<Permit> {
+        self.check_rate(client)?;
+
+        let permits = if let Some(permits) = &self.permits {
+            permits
+        } else {
+            return Ok(Permit { _permit: None });
+        };
+
+        if let Ok(permit) = permits.clone().try_acquire_owned() {
+            return Ok(Permit {
+                _permit: Some(permit),
+            });
+        }
+
+        if self.queued.fetch_add(1, Ordering::SeqCst) >= self.policy.max_queued {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            debug!(
+                "rejected a request from {} because the queue is full",
+                client
+            );
+            return Err(TCError::too_many_requests(
+                "this host is too busy to accept the request, please try again later",
+            ));
+        }
+
+        // leave the queue even if this request is cancelled (e.g. because it timed out)
+        let _queued = Queued(&self.queued);
+
+        let permit = permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(TCError::internal)?;
+
+        Ok(Permit {
+            _permit: Some(permit),
+        })
+    }
+
+    fn check_rate(&self, client: &str) -> TCResult<()> {
+        let rate = if let Some(rate) = self.policy.rate_limit {
+            rate
+        } else {
+            return Ok(());
+        };
+
+        let burst = self.policy.burst.max(1) as f64;
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("rate limit buckets");
+
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(client) {
+            // forget any client whose bucket has refilled, since its state is the same as a new one
+            buckets.retain(|_, bucket| {
+                bucket.refill(now, rate, burst);
+                bucket.tokens < burst
+            });
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+
+        bucket.refill(now, rate, burst);
+
+        if bucket.tokens >= 1. {
+            bucket.tokens -= 1.;
+            Ok(())
+        } else {
+            debug!(
+                "rejected a request from {} which exceeded its rate limit",
+                client
+            );
+            Err(TCError::too_many_requests(format!(
+                "rate limit of {} requests per second exceeded",
+                rate
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_concurrent: Option<usize>, rate_limit: Option<f64>) -> AdmissionPolicy {
+        AdmissionPolicy {
+            max_concurrent,
+            max_queued: 0,
+            rate_limit,
+            burst: 2,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent() {
+        let admission = Admission::new(policy(Some(1), None));
+
+        let permit = admission.admit("a").await.expect("permit");
+        let err = admission.admit("b").await.err().expect("error");
+        assert!(err.code() == ErrorType::TooManyRequests);
+
+        std::mem::drop(permit);
+        assert!(admission.admit("b").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let admission = Admission::new(policy(None, Some(0.001)));
+
+        assert!(admission.admit("a").await.is_ok());
+        assert!(admission.admit("a").await.is_ok());
+        assert!(admission.admit("a").await.is_err());
+        assert!(admission.admit("b").await.is_ok());
+    }
+}
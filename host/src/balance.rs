@@ -0,0 +1,290 @@
+//! Balance read requests to a remote [`Cluster`](crate::cluster::Cluster) across its replicas.
+//!
+//! A [`Balancer`] learns the replica set of a cluster whenever this host reads it (e.g. when a
+//! hosted replica joins its cluster), and thereafter routes GET requests addressed to that cluster's
+//! canonical link to each of its healthy replicas in turn. A periodic health check refreshes each
+//! replica set and removes any replica which does not respond, until it responds again.
+//!
+//! Only reads of the data in a cluster are balanced. Requests to the endpoints a cluster uses to
+//! coordinate with its replicas (e.g. `/replicas` or `/journal`) always go to the link given.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use futures::future::join_all;
+use log::{debug, info, warn};
+use safecast::TryCastFrom;
+
+use tc_error::*;
+use tc_value::{Link, Value};
+use tcgeneric::{PathSegment, TCPathBuf, Tuple};
+
+use crate::cluster::REPLICAS;
+use crate::gateway::{Client, Gateway};
+use crate::state::State;
+use crate::txn::TxnId;
+
+/// The endpoints of a cluster which must be addressed to a specific replica.
+const UNBALANCED: [&str; 7] = [
+    "authorize",
+    "grant",
+    "install",
+    "journal",
+    "replicas",
+    "replicate",
+    "verify",
+];
+
+/// The policy by which a host balances reads across the replicas of a cluster.
+#[derive(Clone, Copy)]
+pub struct BalancePolicy {
+    /// How often to check the health of each known replica
+    pub interval: Duration,
+}
+
+/// A replica chosen to serve a read request.
+pub struct Selection {
+    /// The canonical link to the cluster.
+    pub cluster: Link,
+
+    /// The link to the chosen replica of the cluster.
+    pub replica: Link,
+
+    /// The link to request from the chosen replica.
+    pub target: Link,
+}
+
+#[derive(Default)]
+struct Replicas {
+    healthy: Vec<Link>,
+    unhealthy: HashSet<Link>,
+    next: usize,
+}
+
+impl Replicas {
+    fn update(&mut self, replicas: HashSet<Link>) {
+        self.healthy.retain(|replica| replicas.contains(replica));
+        self.unhealthy.retain(|replica| replicas.contains(replica));
+
+        for replica in replicas {
+            if !self.unhealthy.contains(&replica) && !self.healthy.contains(&replica) {
+                self.healthy.push(replica);
+            }
+        }
+    }
+
+    fn set_health(&mut self, replica: &Link, healthy: bool) {
+        if healthy {
+            if self.unhealthy.remove(replica) {
+                self.healthy.push(replica.clone());
+            }
+        } else if let Some(i) = self.healthy.iter().position(|r| r == replica) {
+            let replica = self.healthy.remove(i);
+            self.unhealthy.insert(replica);
+        }
+    }
+
+    fn all(&self) -> Vec<Link> {
+        self.healthy
+            .iter()
+            .chain(self.unhealthy.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+/// Routes reads of a remote cluster to its healthy replicas, in round-robin order.
+#[derive(Default)]
+pub struct Balancer {
+    clusters: RwLock<HashMap<Link, Replicas>>,
+}
+
+impl Balancer {
+    /// Construct a new `Balancer` with no known clusters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the replica set of the `cluster` at the given canonical link.
+    pub fn register<I: IntoIterator<Item = Link>>(&self, cluster: Link, replicas: I) {
+        let mut replicas: HashSet<Link> = replicas.into_iter().collect();
+        replicas.insert(cluster.clone());
+
+        debug!("{} has {} replicas", cluster, replicas.len());
+
+        let mut clusters = self.clusters.write().expect("balancer");
+        clusters.entry(cluster).or_default().update(replicas);
+    }
+
+    /// Record the replica set of a cluster if `link` refers to its `/replicas` endpoint.
+    pub fn observe(&self, link: &Link, response: &State) {
+        let path = link.path();
+        let host = match link.host() {
+            Some(host) if path.last() == Some(&REPLICAS.into()) => host,
+            _ => return,
+        };
+
+        match Tuple::<Link>::opt_cast_from(response.clone()) {
+            Some(replicas) => {
+                let path = TCPathBuf::from(path[..path.len() - 1].to_vec());
+                self.register(Link::from((host.clone(), path)), replicas)
+            }
+            None => debug!("{} is not a replica set: {}", link, response),
+        }
+    }
+
+    /// Choose a healthy replica to serve a read of the given `link`, if it belongs to a known
+    /// cluster other than one of its coordination endpoints.
+    pub fn select(&self, link: &Link) -> Option<Selection> {
+        let mut clusters = self.clusters.write().expect("balancer");
+
+        let (cluster, replicas) = clusters
+            .iter_mut()
+            .filter(|(cluster, _)| {
+                cluster.host() == link.host() && link.path().starts_with(cluster.path())
+            })
+            .max_by_key(|(cluster, _)| cluster.path().len())?;
+
+        let suffix = &link.path()[cluster.path().len()..];
+        if !is_balanced(suffix) || replicas.healthy.is_empty() {
+            return None;
+        }
+
+        let replica = replicas.healthy[replicas.next % replicas.healthy.len()].clone();
+        replicas.next = replicas.next.wrapping_add(1);
+
+        let mut target = replica.clone();
+        target.extend(suffix.iter().cloned());
+
+        Some(Selection {
+            cluster: cluster.clone(),
+            replica,
+            target,
+        })
+    }
+
+    /// Stop routing reads of the given `cluster` to the given `replica`,
+    /// until it passes a health check.
+    pub fn mark_unhealthy(&self, cluster: &Link, replica: &Link) {
+        let mut clusters = self.clusters.write().expect("balancer");
+        if let Some(replicas) = clusters.get_mut(cluster) {
+            replicas.set_health(replica, false);
+        }
+    }
+
+    /// Refresh the replica set of each known cluster, and check whether each replica responds.
+    pub async fn check_health<C: Client + Sync>(&self, client: &C) {
+        let txn_id = TxnId::new(Gateway::time());
+
+        let clusters: Vec<Link> = {
+            let clusters = self.clusters.read().expect("balancer");
+            clusters.keys().cloned().collect()
+        };
+
+        for cluster in clusters {
+            let link = cluster.clone().append(REPLICAS.into());
+            match client.fetch::<Value>(&txn_id, &link, &Value::None).await {
+                Ok(replicas) => self.observe(&link, &State::from(replicas)),
+                Err(cause) => warn!("unable to list the replicas of {}: {}", cluster, cause),
+            }
+
+            let replicas = {
+                let clusters = self.clusters.read().expect("balancer");
+                match clusters.get(&cluster) {
+                    Some(replicas) => replicas.all(),
+                    None => continue,
+                }
+            };
+
+            let responses = join_all(
+                replicas
+                    .iter()
+                    .map(|replica| client.fetch::<Value>(&txn_id, replica, &Value::None)),
+            )
+            .await;
+
+            let mut clusters = self.clusters.write().expect("balancer");
+            if let Some(status) = clusters.get_mut(&cluster) {
+                for (replica, response) in replicas.iter().zip(responses) {
+                    let healthy = response.is_ok();
+                    if healthy && status.unhealthy.contains(replica) {
+                        info!("replica {} of {} is available again", replica, cluster);
+                    } else if !healthy && !status.unhealthy.contains(replica) {
+                        info!("replica {} of {} is unavailable", replica, cluster);
+                    }
+
+                    status.set_health(replica, healthy);
+                }
+            }
+        }
+    }
+}
+
+/// Return `true` if the given `cause` means that a replica could not serve a request
+/// which another replica may be able to serve.
+pub fn is_unavailable(cause: &TCError) -> bool {
+    matches!(
+        cause.code(),
+        ErrorType::BadGateway
+            | ErrorType::Conflict
+            | ErrorType::Timeout
+            | ErrorType::TooManyRequests
+    )
+}
+
+fn is_balanced(suffix: &[PathSegment]) -> bool {
+    match suffix.first() {
+        Some(name) => !UNBALANCED.contains(&name.as_str()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(s: &str) -> Link {
+        s.parse().expect("link")
+    }
+
+    #[test]
+    fn test_select() {
+        let balancer = Balancer::new();
+        let cluster = link("http://127.0.0.1:8702/app");
+        let replica = link("http://127.0.0.1:8703/app");
+        balancer.register(cluster.clone(), vec![replica.clone()]);
+
+        assert!(balancer
+            .select(&link("http://127.0.0.1:8702/app"))
+            .is_none());
+        assert!(balancer
+            .select(&link("http://127.0.0.1:8702/app/replicas"))
+            .is_none());
+        assert!(balancer
+            .select(&link("http://127.0.0.1:8704/app/table"))
+            .is_none());
+
+        let mut targets = HashSet::new();
+        for _ in 0..2 {
+            let selected = balancer
+                .select(&link("http://127.0.0.1:8702/app/table"))
+                .expect("selection");
+
+            assert_eq!(selected.cluster, cluster);
+            targets.insert(selected.target.to_string());
+        }
+
+        assert_eq!(targets.len(), 2);
+        assert!(targets.contains("http://127.0.0.1:8703/app/table"));
+
+        balancer.mark_unhealthy(&cluster, &replica);
+        for _ in 0..2 {
+            let selected = balancer
+                .select(&link("http://127.0.0.1:8702/app/table"))
+                .expect("selection");
+
+            assert_eq!(selected.replica, cluster);
+        }
+    }
+}
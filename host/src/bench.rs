@@ -0,0 +1,361 @@
+//! Built-in benchmark workloads, used to measure the performance of a host on its own hardware.
+//!
+//! Each workload sets up its data in the transaction workspace, then measures only the operation
+//! under test. Results are reported as one JSON object per workload, so that they can be compared
+//! across releases.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use destream::de::FromStream;
+use futures::future::{self, TryFutureExt};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde_json::{json, Value as Json};
+
+use tc_btree::{BTreeWrite, Column};
+use tc_error::*;
+use tc_table::{Bounds, ColumnBound, IndexSchema, TableRead, TableSlice, TableStream, TableWrite};
+#[cfg(feature = "tc-tensor")]
+use tc_tensor::TensorReduce;
+use tc_transact::fs::Dir;
+use tc_transact::{IntoView, Transaction};
+use tc_value::{Bound, FloatType, Number, NumberType, UIntType, Value, ValueType};
+use tcgeneric::{label, Label, TCPathBuf};
+
+use crate::chain::{self, BlockChain, Chain, ChainInstance, ChainType, Subject};
+use crate::collection::{BTreeFile, BTreeType, TableIndex};
+#[cfg(feature = "tc-tensor")]
+use crate::collection::{DenseAccess, DenseTensor, TensorType};
+use crate::state::State;
+use crate::txn::Txn;
+
+const KEY: Label = label("key");
+const VALUE: Label = label("value");
+
+// a prime used to scatter point reads across the key range
+const SCATTER: u64 = 1_000_003;
+
+/// A built-in benchmark workload.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Workload {
+    /// Insert rows into a `Table`.
+    TableInsert,
+    /// Read rows from a `Table` by primary key, in a scattered order.
+    TableRead,
+    /// Read half the rows of a `Table` by slicing a range of its primary key.
+    TableScan,
+    /// Load a sorted stream of keys into an empty `BTree`.
+    BTreeLoad,
+    /// Encode the history of a `BlockChain` and replay it onto a new subject,
+    /// as a new replica does.
+    ChainReplay,
+    /// Sum all the elements of a dense `Tensor`.
+    #[cfg(feature = "tc-tensor")]
+    TensorReduce,
+    /// Stream all the elements of a dense `Tensor`.
+    #[cfg(feature = "tc-tensor")]
+    TensorScan,
+}
+
+impl Workload {
+    /// List every built-in workload.
+    pub fn all() -> Vec<Self> {
+        let mut all = vec![
+            Self::TableInsert,
+            Self::TableRead,
+            Self::TableScan,
+            Self::BTreeLoad,
+            Self::ChainReplay,
+        ];
+
+        #[cfg(feature = "tc-tensor")]
+        all.extend_from_slice(&[Self::TensorReduce, Self::TensorScan]);
+
+        all
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::TableInsert => "table_insert",
+            Self::TableRead => "table_read",
+            Self::TableScan => "table_scan",
+            Self::BTreeLoad => "btree_load",
+            Self::ChainReplay => "chain_replay",
+            #[cfg(feature = "tc-tensor")]
+            Self::TensorReduce => "tensor_reduce",
+            #[cfg(feature = "tc-tensor")]
+            Self::TensorScan => "tensor_scan",
+        }
+    }
+}
+
+impl FromStr for Workload {
+    type Err = TCError;
+
+    fn from_str(s: &str) -> TCResult<Self> {
+        Self::all()
+            .into_iter()
+            .find(|workload| workload.name() == s)
+            .ok_or_else(|| TCError::bad_request("unknown benchmark workload", s))
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The result of running a single [`Workload`].
+pub struct Measurement {
+    /// The workload measured.
+    pub workload: Workload,
+    /// The number of elements in the workload's data set.
+    pub size: u64,
+    /// The number of operations measured, e.g. the number of rows read.
+    pub ops: u64,
+    /// The time taken to perform the measured operations.
+    pub elapsed: Duration,
+}
+
+impl Measurement {
+    /// Encode this `Measurement` as a JSON object.
+    pub fn to_json(&self) -> Json {
+        let secs = self.elapsed.as_secs_f64();
+        let ops_per_sec = if secs > 0. {
+            self.ops as f64 / secs
+        } else {
+            0.
+        };
+
+        json!({
+            "workload": self.workload.name(),
+            "version": env!("CARGO_PKG_VERSION"),
+            "size": self.size,
+            "ops": self.ops,
+            "elapsed_ms": secs * 1000.,
+            "ops_per_sec": ops_per_sec,
+        })
+    }
+}
+
+/// Run the given `workload` with a data set of the given `size`.
+pub async fn run(txn: &Txn, workload: Workload, size: u64) -> TCResult<Measurement> {
+    if size == 0 {
+        return Err(TCError::bad_request(
+            "benchmark size must be positive, not",
+            size,
+        ));
+    }
+
+    let (ops, elapsed) = match workload {
+        Workload::TableInsert => table_insert(txn, size).await?,
+        Workload::TableRead => table_read(txn, size).await?,
+        Workload::TableScan => table_scan(txn, size).await?,
+        Workload::BTreeLoad => btree_load(txn, size).await?,
+        Workload::ChainReplay => chain_replay(txn, size).await?,
+        #[cfg(feature = "tc-tensor")]
+        Workload::TensorReduce => tensor_reduce(txn, size).await?,
+        #[cfg(feature = "tc-tensor")]
+        Workload::TensorScan => tensor_scan(txn, size).await?,
+    };
+
+    Ok(Measurement {
+        workload,
+        size,
+        ops,
+        elapsed,
+    })
+}
+
+async fn table_insert(txn: &Txn, size: u64) -> TCResult<(u64, Duration)> {
+    let table = create_table(txn).await?;
+
+    let start = Instant::now();
+    fill_table(txn, &table, size).await?;
+    Ok((size, start.elapsed()))
+}
+
+async fn table_read(txn: &Txn, size: u64) -> TCResult<(u64, Duration)> {
+    let txn_id = *txn.id();
+    let table = create_table(txn).await?;
+    fill_table(txn, &table, size).await?;
+
+    let start = Instant::now();
+    for i in 0..size {
+        let i = (i * SCATTER) % size;
+        if table.read(&txn_id, &vec![Value::from(i)]).await?.is_none() {
+            return Err(TCError::internal(format!(
+                "benchmark table is missing row {}",
+                i
+            )));
+        }
+    }
+
+    Ok((size, start.elapsed()))
+}
+
+async fn table_scan(txn: &Txn, size: u64) -> TCResult<(u64, Duration)> {
+    let txn_id = *txn.id();
+    let table = create_table(txn).await?;
+    fill_table(txn, &table, size).await?;
+
+    let mut bounds = Bounds::default();
+    bounds.insert(
+        KEY.into(),
+        ColumnBound::from((
+            Bound::In(Value::from(size / 4)),
+            Bound::Ex(Value::from(size - (size / 4))),
+        )),
+    );
+
+    let start = Instant::now();
+    let rows = table.slice(bounds)?.rows(txn_id).await?;
+    let ops = rows
+        .try_fold(0u64, |count, _| future::ready(Ok(count + 1)))
+        .await?;
+
+    Ok((ops, start.elapsed()))
+}
+
+async fn btree_load(txn: &Txn, size: u64) -> TCResult<(u64, Duration)> {
+    let txn_id = *txn.id();
+    let file = txn
+        .context()
+        .create_file_unique(txn_id, BTreeType::default())
+        .await?;
+
+    let btree = BTreeFile::create(file, row_schema(), txn_id).await?;
+
+    let start = Instant::now();
+    let keys = stream::iter(0..size).map(|i| Ok(row(i)));
+    btree.try_insert_from(txn_id, keys).await?;
+    Ok((size, start.elapsed()))
+}
+
+async fn chain_replay(txn: &Txn, size: u64) -> TCResult<(u64, Duration)> {
+    let txn_id = *txn.id();
+    let dir = txn.context().create_dir_unique(txn_id).await?;
+    let schema = chain::Schema::BTree(row_schema());
+    let chain = match chain::load(txn, ChainType::Block, schema, dir).await? {
+        Chain::Block(chain) => chain,
+        Chain::Sync(_) => unreachable!("load a BlockChain"),
+    };
+
+    let btree = match chain.subject() {
+        Subject::BTree(btree) => btree.clone(),
+        _ => unreachable!("BlockChain with a BTree schema"),
+    };
+
+    for i in 0..size {
+        let key = row(i);
+        let value = State::from(Value::Tuple(key.clone().into()));
+        chain
+            .append_put(txn, TCPathBuf::default(), Value::None, value)
+            .await?;
+
+        btree.insert(txn_id, key).await?;
+    }
+
+    let start = Instant::now();
+
+    let view = chain.into_view(txn.clone()).await?;
+    let encoded = destream_json::encode(view).map_err(TCError::internal)?;
+    let encoded = encoded
+        .map_err(TCError::internal)
+        .try_fold(Vec::new(), |mut encoded, chunk| {
+            encoded.extend_from_slice(&chunk);
+            future::ready(Ok(encoded))
+        })
+        .await?;
+
+    let mut decoder = destream_json::de::Decoder::from_stream(stream::once(future::ready(Ok(
+        Bytes::from(encoded),
+    ))));
+
+    BlockChain::from_stream(txn.clone(), &mut decoder)
+        .map_err(|e| TCError::internal(format!("error replaying benchmark chain: {}", e)))
+        .await?;
+
+    Ok((size, start.elapsed()))
+}
+
+#[cfg(feature = "tc-tensor")]
+async fn tensor_reduce(txn: &Txn, size: u64) -> TCResult<(u64, Duration)> {
+    let tensor = create_tensor(txn, size).await?;
+
+    let start = Instant::now();
+    tensor.sum_all(txn.clone()).await?;
+    Ok((size, start.elapsed()))
+}
+
+#[cfg(feature = "tc-tensor")]
+async fn tensor_scan(txn: &Txn, size: u64) -> TCResult<(u64, Duration)> {
+    let tensor = create_tensor(txn, size).await?;
+
+    let start = Instant::now();
+    let values = tensor.into_inner().value_stream(txn.clone()).await?;
+    let ops = values
+        .try_fold(0u64, |count, _| future::ready(Ok(count + 1)))
+        .await?;
+
+    Ok((ops, start.elapsed()))
+}
+
+#[cfg(feature = "tc-tensor")]
+async fn create_tensor(
+    txn: &Txn,
+    size: u64,
+) -> TCResult<DenseTensor<crate::collection::DenseTensorFile>> {
+    let txn_id = *txn.id();
+    let file = txn
+        .context()
+        .create_file_unique(txn_id, TensorType::Dense)
+        .await?;
+
+    let stop = Number::from(size as f64);
+    DenseTensor::range(file, txn_id, vec![size], Number::from(0f64), stop).await
+}
+
+async fn create_table(txn: &Txn) -> TCResult<TableIndex> {
+    let txn_id = *txn.id();
+    let mut columns = row_schema();
+    let values = columns.split_off(1);
+    let schema = IndexSchema::from((columns, values));
+
+    let dir = txn.context().create_dir_unique(txn_id).await?;
+    TableIndex::create(&dir, schema.into(), txn_id).await
+}
+
+async fn fill_table(txn: &Txn, table: &TableIndex, size: u64) -> TCResult<()> {
+    let txn_id = *txn.id();
+
+    stream::iter(0..size)
+        .map(|i| {
+            let mut key = row(i);
+            let values = key.split_off(1);
+            Ok(table.upsert(txn_id, key, values))
+        })
+        .try_buffer_unordered(num_cpus::get())
+        .try_fold((), |(), ()| future::ready(Ok(())))
+        .await
+}
+
+fn row_schema() -> Vec<Column> {
+    vec![
+        Column::from((
+            KEY.into(),
+            ValueType::Number(NumberType::UInt(UIntType::U64)),
+        )),
+        Column::from((
+            VALUE.into(),
+            ValueType::Number(NumberType::Float(FloatType::F64)),
+        )),
+    ]
+}
+
+fn row(i: u64) -> Vec<Value> {
+    vec![Value::from(i), Value::Number(Number::from(i as f64))]
+}
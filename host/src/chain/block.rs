@@ -1,27 +1,27 @@
 //! A [`Chain`] which stores every mutation of its [`Subject`] in a series of `ChainBlock`s.
 //!
-//! Each block in the chain begins with the hash of the previous block.
+//! Each block in the chain begins with the hash of the previous block. A long chain can be
+//! compacted, so that its first block holds a snapshot of its subject in place of the blocks
+//! before it.
 
 use async_trait::async_trait;
 use destream::de;
 use futures::future::TryFutureExt;
 use futures::join;
-use log::debug;
 
 use tc_error::*;
-use tc_transact::fs::{Persist, Store};
+use tc_transact::fs::{Dir, Persist, Store};
 use tc_transact::{IntoView, Transact};
-use tc_value::{Link, Value};
-use tcgeneric::TCPathBuf;
+use tc_value::Value;
+use tcgeneric::{TCBoxTryStream, TCPathBuf};
 
 use crate::fs;
-use crate::route::Public;
 use crate::state::State;
 use crate::transact::Transaction;
 use crate::txn::{Txn, TxnId};
 
-use super::data::History;
-use super::{Chain, ChainInstance, ChainType, Schema, Subject, CHAIN};
+use super::data::{Change, History, Mutation};
+use super::{Chain, ChainInstance, ChainType, Schema, Subject};
 
 /// A [`Chain`] which stores every mutation of its [`Subject`] in a series of `ChainBlock`s
 #[derive(Clone)]
@@ -65,19 +65,19 @@ impl ChainInstance for BlockChain {
         &self.subject
     }
 
-    async fn replicate(&self, txn: &Txn, source: Link) -> TCResult<()> {
-        let chain = match txn.get(source.append(CHAIN.into()), Value::None).await? {
-            State::Chain(Chain::Block(chain)) => chain,
+    async fn replicate(&self, txn: &Txn, source: Chain) -> TCResult<()> {
+        let chain = match source {
+            Chain::Block(chain) => chain,
             other => {
                 return Err(TCError::bad_request(
-                    "cannot replicate with a blockchain",
+                    "a BlockChain can only replicate from another BlockChain, not",
                     other,
                 ))
             }
         };
 
         self.history
-            .replicate(txn, &self.subject, chain.history)
+            .replicate(txn, &self.schema, &self.subject, chain.history)
             .await
     }
 
@@ -99,6 +99,60 @@ impl ChainInstance for BlockChain {
 
         self.history.commit(txn_id).await
     }
+
+    async fn prepare(&self, txn_id: TxnId) -> TCResult<Vec<Mutation>> {
+        self.history.prepare(txn_id).await
+    }
+
+    async fn recover(&self, txn: &Txn, prepared: TxnId, mutations: Vec<Mutation>) -> TCResult<()> {
+        self.history
+            .recover(txn, &self.subject, prepared, mutations)
+            .await
+    }
+
+    async fn compact(&self, txn: &Txn, retain: u64) -> TCResult<bool> {
+        self.history.compact(txn, &self.schema, retain).await
+    }
+
+    async fn history(&self, txn_id: TxnId) -> TCResult<Vec<TxnId>> {
+        self.history.txn_ids(txn_id).await
+    }
+
+    async fn as_of(&self, txn: &Txn, past_txn_id: TxnId) -> TCResult<Subject> {
+        let txn_id = *txn.id();
+        if past_txn_id >= txn_id {
+            return Err(TCError::bad_request(
+                "cannot read the state of a Chain as of a transaction which is not in the past",
+                past_txn_id,
+            ));
+        }
+
+        let scratch = txn.context().create_dir_unique(txn_id).await?;
+        let past = Subject::create(self.schema.clone(), &scratch, txn_id).await?;
+        self.history.replay_until(txn, &past, past_txn_id).await?;
+        Ok(past)
+    }
+
+    async fn changes(
+        &self,
+        txn: &Txn,
+        since: Option<TxnId>,
+    ) -> TCResult<TCBoxTryStream<'static, Change>> {
+        let txn_id = *txn.id();
+        let scratch = txn.context().create_dir_unique(txn_id).await?;
+        let past = Subject::create(self.schema.clone(), &scratch, txn_id).await?;
+        self.history.changes(txn.clone(), past, since).await
+    }
+
+    async fn revert(&self, txn: &Txn, past_txn_id: TxnId) -> TCResult<()> {
+        let past = State::from(self.as_of(txn, past_txn_id).await?);
+
+        self.history
+            .append_revert(txn, past_txn_id, past.clone())
+            .await?;
+
+        self.subject.restore(txn, past).await
+    }
 }
 
 #[async_trait]
@@ -189,43 +243,14 @@ impl<'en> IntoView<'en, fs::Dir> for BlockChain {
 }
 
 async fn validate(txn: Txn, schema: Schema, history: History) -> TCResult<BlockChain> {
-    use super::data::Mutation;
-
-    let txn_id = txn.id();
-
-    let subject = Subject::create(schema.clone(), txn.context(), *txn.id()).await?;
-
-    let mut i = 0u64;
-    while history.contains_block(*txn_id, i).await? {
-        let block = history.read_block(*txn_id, i.into()).await?;
-
-        for (_, ops) in block.mutations() {
-            for mutation in ops.iter().cloned() {
-                let result = match mutation {
-                    Mutation::Delete(path, key) => {
-                        debug!("replay DELETE op: {}: {}", path, key);
-                        subject.delete(&txn, &path, key).await
-                    }
-                    Mutation::Put(path, key, value) => {
-                        debug!("replay PUT op: {}: {} <- {}", path, key, value);
-                        history
-                            .resolve(&txn, value)
-                            .and_then(|value| subject.put(&txn, &path, key, value))
-                            .await
-                    }
-                };
-
-                if let Err(cause) = result {
-                    return Err(TCError::bad_request(
-                        format!("error replaying block {}", i),
-                        cause,
-                    ));
-                }
-            }
-        }
+    let txn_id = *txn.id();
 
-        i += 1;
-    }
+    let subject = Subject::create(schema.clone(), txn.context(), txn_id).await?;
+
+    let latest = history.latest_block_id(txn_id).await?;
+    history
+        .replay_blocks(&txn, &subject, 0..(latest + 1))
+        .await?;
 
     Ok(BlockChain::new(schema, subject, history))
 }
@@ -21,6 +21,11 @@ use crate::scalar::Scalar;
 pub enum Mutation {
     Delete(TCPathBuf, Value),
     Put(TCPathBuf, Value, Scalar),
+
+    /// Restore the whole subject to the given saved state, as of the given past [`TxnId`].
+    ///
+    /// Encoded like a PUT of the whole subject, followed by the [`TxnId`] reverted to.
+    Revert(TxnId, Scalar),
 }
 
 #[async_trait]
@@ -39,6 +44,9 @@ impl<'en> en::ToStream<'en> for Mutation {
         match self {
             Self::Delete(path, key) => (path, key).into_stream(encoder),
             Self::Put(path, key, value) => (path, key, value).into_stream(encoder),
+            Self::Revert(txn_id, value) => {
+                (TCPathBuf::default(), Value::None, value, txn_id).into_stream(encoder)
+            }
         }
     }
 }
@@ -48,6 +56,9 @@ impl<'en> en::IntoStream<'en> for Mutation {
         match self {
             Self::Delete(path, key) => (path, key).into_stream(encoder),
             Self::Put(path, key, value) => (path, key, value).into_stream(encoder),
+            Self::Revert(txn_id, value) => {
+                (TCPathBuf::default(), Value::None, value, txn_id).into_stream(encoder)
+            }
         }
     }
 }
@@ -57,6 +68,7 @@ impl fmt::Debug for Mutation {
         match self {
             Self::Delete(path, key) => write!(f, "DELETE {}: {:?}", path, key),
             Self::Put(path, key, value) => write!(f, "PUT {}: {:?} <- {:?}", path, key, value),
+            Self::Revert(txn_id, value) => write!(f, "REVERT to {}: {:?}", txn_id, value),
         }
     }
 }
@@ -66,6 +78,7 @@ impl fmt::Display for Mutation {
         match self {
             Self::Delete(path, key) => write!(f, "DELETE {}: {}", path, key),
             Self::Put(path, key, value) => write!(f, "PUT {}: {} <- {}", path, key, value),
+            Self::Revert(txn_id, value) => write!(f, "REVERT to {}: {}", txn_id, value),
         }
     }
 }
@@ -92,7 +105,11 @@ impl de::Visitor for MutationVisitor {
             .ok_or_else(|| de::Error::invalid_length(0, Self::expecting()))?;
 
         if let Some(value) = seq.next_element(()).await? {
-            Ok(Mutation::Put(path, key, value))
+            if let Some(txn_id) = seq.next_element(()).await? {
+                Ok(Mutation::Revert(txn_id, value))
+            } else {
+                Ok(Mutation::Put(path, key, value))
+            }
         } else {
             Ok(Mutation::Delete(path, key))
         }
@@ -156,6 +173,12 @@ impl ChainBlock {
         self.append(txn_id, Mutation::Put(path, key, value))
     }
 
+    /// Append a REVERT op to the contents of this `ChainBlock`.
+    pub fn append_revert(&mut self, txn_id: TxnId, past_txn_id: TxnId, value: Scalar) {
+        debug!("ChainBlock::append_revert to {}: {}", past_txn_id, value);
+        self.append(txn_id, Mutation::Revert(past_txn_id, value))
+    }
+
     /// Delete all mutations listed in this `ChainBlock` prior to the given `TxnId`.
     pub fn clear_until(&mut self, txn_id: &TxnId) {
         let old_txn_ids: Vec<TxnId> = self
@@ -180,6 +203,11 @@ impl ChainBlock {
         &self.last_hash
     }
 
+    /// Replace the hash of the previous block in the chain, e.g. after the chain is compacted.
+    pub fn set_last_hash<H: Into<Bytes>>(&mut self, hash: H) {
+        self.last_hash = hash.into();
+    }
+
     /// The current hash of this block.
     pub async fn hash(&self) -> TCResult<Bytes> {
         let mut hasher = Sha256::default();
@@ -1,9 +1,11 @@
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::fmt;
 use std::iter::FromIterator;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use collate::Collate;
 use destream::{de, en};
 use futures::stream::{self, StreamExt};
 use futures::{join, try_join, TryFutureExt, TryStreamExt};
@@ -12,18 +14,19 @@ use safecast::*;
 
 use tc_btree::BTreeInstance;
 use tc_error::*;
-use tc_table::TableInstance;
-#[cfg(feature = "tensor")]
+use tc_table::{Key, TableInstance, TableRead, TableStream};
+#[cfg(feature = "tc-tensor")]
 use tc_tensor::TensorAccess;
 use tc_transact::fs::*;
 use tc_transact::lock::TxnLock;
 use tc_transact::{IntoView, Transact, Transaction, TxnId};
-use tc_value::Value;
+use tc_value::{TCString, Value, ValueCollator};
 use tcgeneric::{
-    label, Id, Instance, Label, Map, NativeClass, TCBoxStream, TCBoxTryStream, TCPathBuf, Tuple,
+    label, Id, Instance, Label, Map, NativeClass, TCBoxStream, TCBoxTryFuture, TCBoxTryStream,
+    TCPathBuf, Tuple,
 };
 
-use crate::chain::{ChainType, Subject, BLOCK_SIZE, CHAIN, NULL_HASH};
+use crate::chain::{ChainType, Schema, Subject, BLOCK_SIZE, CHAIN, NULL_HASH};
 use crate::collection::*;
 use crate::fs;
 use crate::route::Public;
@@ -35,6 +38,80 @@ use super::{ChainBlock, Mutation};
 
 const DATA: Label = label("data");
 
+/// The kind of a [`Change`] to a row of a `Table`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Insert => "insert",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        })
+    }
+}
+
+/// A change to a single row of a `Table`, committed as part of the transaction with the given
+/// [`TxnId`]. The `values` of a deleted row are empty.
+pub struct Change {
+    pub txn_id: TxnId,
+    pub kind: ChangeKind,
+    pub key: Key,
+    pub values: Vec<Value>,
+}
+
+impl Change {
+    fn diff(
+        txn_id: TxnId,
+        key_len: usize,
+        before: Option<Vec<Value>>,
+        after: Option<Vec<Value>>,
+    ) -> Option<Self> {
+        let (kind, mut row) = match (before, after) {
+            (None, Some(after)) => (ChangeKind::Insert, after),
+            (Some(before), Some(after)) if before != after => (ChangeKind::Update, after),
+            (Some(mut before), None) => {
+                before.truncate(key_len);
+                (ChangeKind::Delete, before)
+            }
+            _ => return None,
+        };
+
+        let values = row.split_off(key_len);
+        Some(Self {
+            txn_id,
+            kind,
+            key: row,
+            values,
+        })
+    }
+}
+
+impl From<Change> for Value {
+    fn from(change: Change) -> Self {
+        let values = if change.kind == ChangeKind::Delete {
+            Value::None
+        } else {
+            Value::Tuple(change.values.into())
+        };
+
+        Value::Tuple(
+            vec![
+                Value::from(change.txn_id.to_string()),
+                Value::from(change.kind.to_string()),
+                Value::Tuple(change.key.into()),
+                values,
+            ]
+            .into(),
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct History {
     dir: fs::Dir,
@@ -87,111 +164,143 @@ impl History {
         Ok(())
     }
 
-    async fn save_state(&self, txn: &Txn, state: State) -> TCResult<Scalar> {
-        if state.is_ref() {
-            return Err(TCError::bad_request(
-                "cannot update Chain with reference: {}",
-                state,
-            ));
-        }
-
+    /// Append a REVERT of the subject of this `History` to the given `past` state, as of the given
+    /// `past_txn_id`, to the latest block.
+    pub async fn append_revert(&self, txn: &Txn, past_txn_id: TxnId, past: State) -> TCResult<()> {
         let txn_id = *txn.id();
-        match state {
-            State::Collection(collection) => match collection {
-                Collection::BTree(btree) => {
-                    let hash: Id = btree.hash_hex(&txn).await?.parse()?;
-                    let schema = btree.schema().to_vec();
-                    let classpath = BTreeType::default().path();
-
-                    if self.dir.contains(txn_id, &hash).await? {
-                        debug!("BTree with hash {} is already saved", hash);
-                    } else {
-                        let file = self
-                            .dir
-                            .create_file(txn_id, hash.clone(), btree.class())
-                            .await?;
+        let past = self.save_state(txn, past).await?;
 
-                        BTreeFile::copy_from(btree, file, txn).await?;
-                        debug!("saved BTree with hash {}", hash);
-                    }
+        debug!("History::append_revert {} to {}", txn_id, past_txn_id);
 
-                    Ok(OpRef::Get((
-                        (hash.into(), classpath).into(),
-                        Value::from_iter(schema).into(),
-                    ))
-                    .into())
-                }
-                Collection::Table(table) => {
-                    let hash: Id = table.hash_hex(&txn).await?.parse()?;
-                    let schema = table.schema().clone();
-                    let classpath = TableType::default().path();
+        let mut block = self.write_latest(txn_id).await?;
+        block.append_revert(txn_id, past_txn_id, past);
 
-                    if self.dir.contains(txn_id, &hash).await? {
-                        debug!("Table with hash {} is already saved", hash);
-                    } else {
-                        let dir = self.dir.create_dir(txn_id, hash.clone()).await?;
-                        TableIndex::copy_from(table, dir, txn).await?;
-                        debug!("saved Table with hash {}", hash);
-                    }
+        Ok(())
+    }
 
-                    Ok(OpRef::Get((
-                        (hash.into(), classpath).into(),
-                        Value::cast_from(schema).into(),
-                    ))
-                    .into())
-                }
+    fn save_state<'a>(&'a self, txn: &'a Txn, state: State) -> TCBoxTryFuture<'a, Scalar> {
+        Box::pin(async move {
+            if state.is_ref() {
+                return Err(TCError::bad_request(
+                    "cannot update Chain with reference: {}",
+                    state,
+                ));
+            }
 
-                #[cfg(feature = "tensor")]
-                Collection::Tensor(tensor) => {
-                    let shape = tensor.shape().clone();
-                    let dtype = tensor.dtype();
-                    let schema = tc_tensor::Schema { shape, dtype };
-                    let classpath = tensor.class().path();
-
-                    let hash = match tensor {
-                        Tensor::Dense(dense) => {
-                            let hash = dense.hash_hex(&txn).await?.parse()?;
-
-                            if self.dir.contains(txn_id, &hash).await? {
-                                debug!("Tensor with hash {} is already saved", hash);
-                            } else {
-                                let file = self
-                                    .dir
-                                    .create_file(txn_id, hash.clone(), TensorType::Dense)
-                                    .await?;
-
-                                DenseTensor::copy_from(dense, file, txn).await?;
-                                debug!("saved Tensor with hash {}", hash);
-                            }
+            let txn_id = *txn.id();
+            match state {
+                State::Collection(collection) => match collection {
+                    Collection::BTree(btree) => {
+                        let hash: Id = btree.hash_hex(&txn).await?.parse()?;
+                        let schema = btree.schema().to_vec();
+                        let classpath = BTreeType::default().path();
+
+                        if self.dir.contains(txn_id, &hash).await? {
+                            debug!("BTree with hash {} is already saved", hash);
+                        } else {
+                            let file = self
+                                .dir
+                                .create_file(txn_id, hash.clone(), btree.class())
+                                .await?;
+
+                            BTreeFile::copy_from(btree, file, txn).await?;
+                            debug!("saved BTree with hash {}", hash);
+                        }
 
-                            hash
+                        Ok(OpRef::Get((
+                            (hash.into(), classpath).into(),
+                            Value::from_iter(schema).into(),
+                        ))
+                        .into())
+                    }
+                    Collection::Table(table) => {
+                        let hash: Id = table.hash_hex(&txn).await?.parse()?;
+                        let schema = table.schema().clone();
+                        let classpath = TableType::default().path();
+
+                        if self.dir.contains(txn_id, &hash).await? {
+                            debug!("Table with hash {} is already saved", hash);
+                        } else {
+                            let dir = self.dir.create_dir(txn_id, hash.clone()).await?;
+                            TableIndex::copy_from(table, dir, txn).await?;
+                            debug!("saved Table with hash {}", hash);
                         }
-                        Tensor::Sparse(sparse) => {
-                            let hash = sparse.hash_hex(&txn).await?.parse()?;
-
-                            if self.dir.contains(txn_id, &hash).await? {
-                                debug!("Tensor with hash {} is already saved", hash);
-                            } else {
-                                let dir = self.dir.create_dir(txn_id, hash.clone()).await?;
-                                SparseTensor::copy_from(sparse, dir, txn).await?;
-                                debug!("saved Tensor with hash {}", hash);
+
+                        Ok(OpRef::Get((
+                            (hash.into(), classpath).into(),
+                            Value::cast_from(schema).into(),
+                        ))
+                        .into())
+                    }
+
+                    #[cfg(feature = "tc-tensor")]
+                    Collection::Tensor(tensor) => {
+                        let shape = tensor.shape().clone();
+                        let dtype = tensor.dtype();
+                        let schema = tc_tensor::Schema { shape, dtype };
+                        let classpath = tensor.class().path();
+
+                        let hash = match tensor {
+                            Tensor::Dense(dense) => {
+                                let hash = dense.hash_hex(&txn).await?.parse()?;
+
+                                if self.dir.contains(txn_id, &hash).await? {
+                                    debug!("Tensor with hash {} is already saved", hash);
+                                } else {
+                                    let file = self
+                                        .dir
+                                        .create_file(txn_id, hash.clone(), TensorType::Dense)
+                                        .await?;
+
+                                    DenseTensor::copy_from(dense, file, txn).await?;
+                                    debug!("saved Tensor with hash {}", hash);
+                                }
+
+                                hash
+                            }
+                            Tensor::Sparse(sparse) => {
+                                let hash = sparse.hash_hex(&txn).await?.parse()?;
+
+                                if self.dir.contains(txn_id, &hash).await? {
+                                    debug!("Tensor with hash {} is already saved", hash);
+                                } else {
+                                    let dir = self.dir.create_dir(txn_id, hash.clone()).await?;
+                                    SparseTensor::copy_from(sparse, dir, txn).await?;
+                                    debug!("saved Tensor with hash {}", hash);
+                                }
+
+                                hash
                             }
+                        };
 
-                            hash
-                        }
-                    };
+                        let schema: Value = schema.cast_into();
+                        Ok(OpRef::Get(((hash.into(), classpath).into(), schema.into())).into())
+                    }
+                },
+                State::Scalar(value) => Ok(value),
+                other if Scalar::can_cast_from(&other) => Ok(other.opt_cast_into().unwrap()),
+                State::Map(map) => {
+                    let mut saved = Map::new();
+                    for (name, state) in map {
+                        saved.insert(name, self.save_state(txn, state).await?);
+                    }
 
-                    let schema: Value = schema.cast_into();
-                    Ok(OpRef::Get(((hash.into(), classpath).into(), schema.into())).into())
+                    Ok(Scalar::Map(saved))
                 }
-            },
-            State::Scalar(value) => Ok(value),
-            other if Scalar::can_cast_from(&other) => Ok(other.opt_cast_into().unwrap()),
-            other => Err(TCError::bad_request(
-                "Chain does not support value",
-                other.class(),
-            )),
-        }
+                State::Tuple(tuple) => {
+                    let mut saved = Vec::with_capacity(tuple.len());
+                    for state in tuple {
+                        saved.push(self.save_state(txn, state).await?);
+                    }
+
+                    Ok(Scalar::Tuple(saved.into()))
+                }
+                other => Err(TCError::bad_request(
+                    "Chain does not support value",
+                    other.class(),
+                )),
+            }
+        })
     }
 
     pub async fn last_commit(&self, txn_id: TxnId) -> TCResult<Option<TxnId>> {
@@ -261,68 +370,594 @@ impl History {
         let latest = *self.latest.read(*txn.id()).await?;
         let block = self.read_block(*txn.id(), latest.into()).await?;
 
-        let last_block = if latest > 0 && block.mutations().is_empty() {
-            self.read_block(*txn.id(), (latest - 1).into()).await?
+        let (last_block_id, last_block) = if latest > 0 && block.mutations().is_empty() {
+            let block = self.read_block(*txn.id(), (latest - 1).into()).await?;
+            (latest - 1, block)
         } else {
-            block
+            (latest, block)
         };
 
-        if let Some((last_txn_id, ops)) = last_block.mutations().iter().last() {
-            for op in ops {
-                let result = match op {
-                    Mutation::Delete(path, key) => {
-                        debug!("replay DELETE {}{}: {}", subject, path, key);
+        if is_snapshot(last_block_id, &last_block) {
+            self.restore_snapshot(txn, subject, &last_block).await?;
+        } else if let Some((last_txn_id, ops)) = last_block.mutations().iter().last() {
+            self.replay(txn, subject, last_txn_id, ops).await?;
+        }
 
-                        subject.delete(txn, path, key.clone()).await
-                    }
-                    Mutation::Put(path, key, value) => {
-                        debug!("replay PUT {}{}: {} <- {}", subject, path, key, value);
+        Ok(())
+    }
 
-                        self.resolve(txn, value.clone())
-                            .and_then(|value| subject.put(txn, path, key.clone(), value))
-                            .await
+    /// Replay the blocks of this `History` with the given `block_ids` onto the given `subject`.
+    pub async fn replay_blocks<I: IntoIterator<Item = u64>>(
+        &self,
+        txn: &Txn,
+        subject: &Subject,
+        block_ids: I,
+    ) -> TCResult<()> {
+        for block_id in block_ids {
+            let block = self.read_block(*txn.id(), block_id).await?;
+
+            let result = if is_snapshot(block_id, &block) {
+                self.restore_snapshot(txn, subject, &block).await
+            } else {
+                let mut result = Ok(());
+                for (past_txn_id, ops) in block.mutations() {
+                    result = self.replay(txn, subject, past_txn_id, ops).await;
+                    if result.is_err() {
+                        break;
                     }
-                };
+                }
+
+                result
+            };
+
+            if let Err(cause) = result {
+                return Err(TCError::bad_request(
+                    format!("error replaying block {}", block_id),
+                    cause,
+                ));
+            }
+        }
+
+        Ok(())
+    }
 
-                if let Err(cause) = result {
-                    return Err(TCError::internal(format!(
-                        "error replaying last transaction {}: {}",
-                        last_txn_id, cause
-                    )));
+    /// Replay this `History` onto the given (empty) `subject`, up to and including the mutations
+    /// committed at the given [`TxnId`].
+    pub async fn replay_until(&self, txn: &Txn, subject: &Subject, until: TxnId) -> TCResult<()> {
+        let txn_id = *txn.id();
+        let latest = self.latest_block_id(txn_id).await?;
+
+        for block_id in 0..(latest + 1) {
+            let block = self.read_block(txn_id, block_id).await?;
+
+            if is_snapshot(block_id, &block) {
+                let (snapshot_txn_id, _) = snapshot(&block)?;
+                if snapshot_txn_id > until {
+                    return Err(TCError::bad_request(
+                        "chain history was compacted after",
+                        until,
+                    ));
+                }
+
+                self.restore_snapshot(txn, subject, &block).await?;
+                continue;
+            }
+
+            for (past_txn_id, ops) in block.mutations() {
+                if past_txn_id > &until {
+                    return Ok(());
                 }
+
+                self.replay(txn, subject, past_txn_id, ops).await?;
             }
         }
 
         Ok(())
     }
 
-    pub async fn replicate(&self, txn: &Txn, subject: &Subject, other: Self) -> TCResult<()> {
-        debug!("replicate chain history");
+    /// List the [`TxnId`] of each transaction committed to this `History`, in order.
+    pub async fn txn_ids(&self, txn_id: TxnId) -> TCResult<Vec<TxnId>> {
+        let latest = self.latest_block_id(txn_id).await?;
+
+        let mut txn_ids = Vec::new();
+        for block_id in 0..(latest + 1) {
+            let block = self.read_block(txn_id, block_id).await?;
+            txn_ids.extend(
+                block
+                    .mutations()
+                    .keys()
+                    .filter(|past_txn_id| *past_txn_id < &txn_id)
+                    .cloned(),
+            );
+        }
+
+        Ok(txn_ids)
+    }
 
+    /// Replay this `History` onto the given empty `subject`, which must be a `Table`, and stream
+    /// each change to a row committed after the given `since` [`TxnId`] (or ever, if `None`).
+    ///
+    /// Changes are captured starting from the block which contains `since`. The prior state of
+    /// each row which they change is looked up in the preceding blocks, so the whole history is
+    /// only replayed if a bulk write or a revert could have changed one of those rows.
+    pub async fn changes(
+        &self,
+        txn: Txn,
+        subject: Subject,
+        since: Option<TxnId>,
+    ) -> TCResult<TCBoxTryStream<'static, Change>> {
+        let table = match &subject {
+            Subject::Table(table) => table.clone(),
+            other => {
+                return Err(TCError::unsupported(format!(
+                    "change data capture is only supported for a Table, not {}",
+                    other
+                )))
+            }
+        };
+
+        let txn_id = *txn.id();
+        let latest = self.latest_block_id(txn_id).await?;
+
+        let (start, replay_prior) = if let Some(since) = since {
+            let start = self.start_block(txn_id, since, latest).await?;
+            let replay_prior = self
+                .seed(&txn, &subject, &table, since, start, latest)
+                .await?;
+            (start, replay_prior)
+        } else {
+            (0, true)
+        };
+
+        let history = self.clone();
+        let changes = stream::iter(start..(latest + 1))
+            .then(move |block_id| {
+                let history = history.clone();
+                let txn = txn.clone();
+                let subject = subject.clone();
+                let table = table.clone();
+
+                Box::pin(async move {
+                    let replay_prior = replay_prior || block_id > start;
+                    history
+                        .capture_block(&txn, &subject, &table, block_id, since, replay_prior)
+                        .await
+                })
+            })
+            .map_ok(|changes| stream::iter(changes.into_iter().map(TCResult::Ok)))
+            .try_flatten();
+
+        Ok(Box::pin(changes))
+    }
+
+    // find the first block which contains a transaction committed after `since`
+    async fn start_block(&self, txn_id: TxnId, since: TxnId, latest: u64) -> TCResult<u64> {
+        let mut block_id = latest;
+        while block_id > 0 {
+            let block = self.read_block(txn_id, block_id).await?;
+            match block.mutations().keys().next() {
+                Some(first) if first <= &since => return Ok(block_id),
+                _ => block_id -= 1,
+            }
+        }
+
+        Ok(0)
+    }
+
+    // replay onto the given empty `subject` the state as of `since` of each row changed after
+    // `since`, by looking back from the block `start` for the last mutation of each row,
+    // and return `true` if the mutations before `since` in the block `start` must still be replayed
+    async fn seed(
+        &self,
+        txn: &Txn,
+        subject: &Subject,
+        table: &TableIndex,
+        since: TxnId,
+        start: u64,
+        latest: u64,
+    ) -> TCResult<bool> {
+        let txn_id = *txn.id();
+        let collator = ValueCollator::default();
+
+        // the key of each row changed after `since`, in order, with its last prior mutation
+        let mut rows: Vec<(Key, Option<(TxnId, Mutation)>)> = Vec::new();
+        for block_id in start..(latest + 1) {
+            let block = self.read_block(txn_id, block_id).await?;
+            for (past_txn_id, ops) in block.mutations() {
+                if past_txn_id <= &since || past_txn_id >= &txn_id {
+                    continue;
+                }
+
+                for op in ops {
+                    let key = match row_key(table, op) {
+                        Some(key) => key,
+                        None => return self.replay_prior(txn, subject, start).await,
+                    };
+
+                    if let Err(i) = rows.binary_search_by(|(k, _)| collator.compare_slice(k, &key))
+                    {
+                        rows.insert(i, (key, None));
+                    }
+                }
+            }
+        }
+
+        let mut unresolved = rows.len();
+        let mut restored = false;
+        let mut block_id = start + 1;
+        while unresolved > 0 && block_id > 0 {
+            block_id -= 1;
+            let block = self.read_block(txn_id, block_id).await?;
+
+            if is_snapshot(block_id, &block) {
+                // the snapshot has the prior state of every row not mutated since
+                self.restore_snapshot(txn, subject, &block).await?;
+                restored = true;
+                break;
+            }
+
+            let prior = block
+                .mutations()
+                .iter()
+                .rev()
+                .filter(|(past_txn_id, _)| *past_txn_id <= &since);
+
+            'prior: for (past_txn_id, ops) in prior {
+                for op in ops.iter().rev() {
+                    if unresolved == 0 {
+                        break 'prior;
+                    }
+
+                    let key = match row_key(table, op) {
+                        Some(key) => key,
+                        None => return self.replay_prior(txn, subject, start).await,
+                    };
+
+                    if let Ok(i) = rows.binary_search_by(|(k, _)| collator.compare_slice(k, &key)) {
+                        if rows[i].1.is_none() {
+                            rows[i].1 = Some((*past_txn_id, op.clone()));
+                            unresolved -= 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (_key, prior) in rows {
+            if let Some((past_txn_id, op)) = prior {
+                // a deleted row is already absent unless it was restored from a snapshot
+                if restored || matches!(op, Mutation::Put(..)) {
+                    let ops = std::slice::from_ref(&op);
+                    self.replay(txn, subject, &past_txn_id, ops).await?;
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    // replay every block before `start` onto the given empty `subject`
+    async fn replay_prior(&self, txn: &Txn, subject: &Subject, start: u64) -> TCResult<bool> {
+        debug!("replay {} chain blocks to capture changes", start);
+        self.replay_blocks(txn, subject, 0..start).await?;
+        Ok(true)
+    }
+
+    async fn capture_block(
+        &self,
+        txn: &Txn,
+        subject: &Subject,
+        table: &TableIndex,
+        block_id: u64,
+        since: Option<TxnId>,
+        replay_prior: bool,
+    ) -> TCResult<Vec<Change>> {
+        let txn_id = *txn.id();
+        let block = self.read_block(txn_id, block_id).await?;
+
+        if is_snapshot(block_id, &block) {
+            let (snapshot_txn_id, _) = snapshot(&block)?;
+            if since.map(|since| snapshot_txn_id > since).unwrap_or(true) {
+                return Err(TCError::bad_request(
+                    "cannot list the changes to a chain from before it was compacted at",
+                    snapshot_txn_id,
+                ));
+            }
+
+            if replay_prior {
+                self.restore_snapshot(txn, subject, &block).await?;
+            }
+
+            return Ok(vec![]);
+        }
+
+        let mut changes = Vec::new();
+        for (past_txn_id, ops) in block.mutations() {
+            if past_txn_id >= &txn_id {
+                break;
+            } else if since.map(|since| past_txn_id <= &since).unwrap_or(false) {
+                if replay_prior {
+                    self.replay(txn, subject, past_txn_id, ops).await?;
+                }
+            } else {
+                for op in ops {
+                    self.capture(txn, subject, table, past_txn_id, op, &mut changes)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+
+    async fn capture(
+        &self,
+        txn: &Txn,
+        subject: &Subject,
+        table: &TableIndex,
+        past_txn_id: &TxnId,
+        op: &Mutation,
+        changes: &mut Vec<Change>,
+    ) -> TCResult<()> {
         let txn_id = *txn.id();
+        let key_len = table.key().len();
+        let ops = std::slice::from_ref(op);
+
+        if let Some(key) = row_key(table, op) {
+            let before = table.read(&txn_id, &key).await?;
+            self.replay(txn, subject, past_txn_id, ops).await?;
+            let after = table.read(&txn_id, &key).await?;
+
+            changes.extend(Change::diff(*past_txn_id, key_len, before, after));
+        } else {
+            // a bulk write or a revert can change any row, so compare the whole table
+            let before = table.clone().rows(txn_id).await?;
+            let before: Vec<Vec<Value>> = before.try_collect().await?;
+            self.replay(txn, subject, past_txn_id, ops).await?;
+            let after = table.clone().rows(txn_id).await?;
+            let after: Vec<Vec<Value>> = after.try_collect().await?;
+
+            diff_rows(*past_txn_id, key_len, before, after, changes);
+        }
 
-        let (latest, other_latest) =
-            try_join!(self.latest.read(txn_id), other.latest.read(txn_id))?;
+        Ok(())
+    }
 
-        if (*latest) > (*other_latest) {
+    /// Fold every block of this `History` except the latest `retain` blocks into a snapshot of
+    /// the state of its subject at that point, so that this `History` begins with the snapshot.
+    ///
+    /// Returns `false` if there is nothing to compact.
+    ///
+    /// Collections saved by the folded blocks are not deleted from the data directory.
+    pub async fn compact(&self, txn: &Txn, schema: &Schema, retain: u64) -> TCResult<bool> {
+        if retain == 0 {
             return Err(TCError::bad_request(
-                "cannot replicate from chain with fewer blocks",
-                *latest,
+                "chain compaction must retain at least one block, not",
+                retain,
             ));
         }
 
-        const ERR_DIVERGENT: &str = "chain to replicate diverges at block";
-        for i in 0u64..*latest {
-            let block = self.read_block(txn_id, i.into()).await?;
+        let txn_id = *txn.id();
+        let mut latest = self.latest.write(txn_id).await?;
+
+        if *latest < retain {
+            return Ok(false);
+        }
+
+        let fold = *latest + 1 - retain;
+        if fold == 1 {
+            let first = self.read_block(txn_id, 0).await?;
+            if is_snapshot(0, &first) {
+                return Ok(false);
+            }
+        }
+
+        let (hash, snapshot_txn_id) = {
+            let last_folded = self.read_block(txn_id, fold - 1).await?;
+            let hash = last_folded.hash().await?;
+
+            let mut snapshot_txn_id = last_folded.mutations().keys().last().cloned();
+            let mut block_id = fold - 1;
+            while snapshot_txn_id.is_none() && block_id > 0 {
+                block_id -= 1;
+                let block = self.read_block(txn_id, block_id).await?;
+                snapshot_txn_id = block.mutations().keys().last().cloned();
+            }
+
+            match snapshot_txn_id {
+                Some(snapshot_txn_id) => (hash, snapshot_txn_id),
+                None => return Ok(false),
+            }
+        };
+
+        debug!(
+            "compact {} chain blocks into a snapshot as of {}",
+            fold, snapshot_txn_id
+        );
+
+        let scratch = txn.context().create_dir_unique(txn_id).await?;
+        let snapshot = Subject::create(schema.clone(), &scratch, txn_id).await?;
+        self.replay_blocks(txn, &snapshot, 0..fold).await?;
+
+        let snapshot = self.save_state(txn, State::from(snapshot)).await?;
+
+        let first = snapshot_block(hash, snapshot_txn_id, snapshot);
+        let mut last_hash = first.hash().await?;
+        *self.write_block(txn_id, 0).await? = first;
+
+        for i in 0..retain {
+            let mut block = ChainBlock::clone(&*self.read_block(txn_id, fold + i).await?);
+            block.set_last_hash(last_hash);
+            last_hash = block.hash().await?;
+
+            *self.write_block(txn_id, i + 1).await? = block;
+        }
+
+        for block_id in (retain + 1)..(*latest + 1) {
+            self.file.delete_block(txn_id, block_id.into()).await?;
+        }
+
+        *latest = retain;
+
+        Ok(true)
+    }
+
+    async fn restore_snapshot(
+        &self,
+        txn: &Txn,
+        subject: &Subject,
+        block: &ChainBlock,
+    ) -> TCResult<()> {
+        let (snapshot_txn_id, snapshot) = snapshot(block)?;
+        debug!(
+            "restore {} from snapshot as of {}",
+            subject, snapshot_txn_id
+        );
+
+        let snapshot = self.resolve(txn, snapshot.clone()).await?;
+        subject.restore(txn, snapshot).await
+    }
+
+    /// Save any data referenced by the mutations pending at the given [`TxnId`] to disk,
+    /// and return the mutations themselves, without committing them to this `History`.
+    pub async fn prepare(&self, txn_id: TxnId) -> TCResult<Vec<Mutation>> {
+        // saved collections are content-addressed, so committing them early is harmless
+        self.dir.commit(&txn_id).await;
+
+        let block = self.read_latest(txn_id).await?;
+        Ok(block.mutations().get(&txn_id).cloned().unwrap_or_default())
+    }
+
+    /// Append the given `mutations`, prepared at the given `prepared` [`TxnId`], to the latest
+    /// block of this `History` and apply them to the given `subject`,
+    /// unless they have already been committed.
+    pub async fn recover(
+        &self,
+        txn: &Txn,
+        subject: &Subject,
+        prepared: TxnId,
+        mutations: Vec<Mutation>,
+    ) -> TCResult<()> {
+        let txn_id = *txn.id();
+
+        let committed = {
+            let latest = *self.latest.read(txn_id).await?;
+            let block = self.read_block(txn_id, latest.into()).await?;
+            let last_commit = if latest > 0 && block.mutations().is_empty() {
+                let block = self.read_block(txn_id, (latest - 1).into()).await?;
+                block.mutations().keys().last().cloned()
+            } else {
+                block.mutations().keys().last().cloned()
+            };
+
+            last_commit.map(|last| last >= prepared).unwrap_or(false)
+        };
+
+        if committed {
+            debug!("transaction {} is already committed", prepared);
+            return Ok(());
+        }
+
+        {
+            let mut block = self.write_latest(txn_id).await?;
+            for mutation in &mutations {
+                block.append(txn_id, mutation.clone());
+            }
+        }
+
+        self.replay(txn, subject, &prepared, &mutations).await
+    }
+
+    async fn replay(
+        &self,
+        txn: &Txn,
+        subject: &Subject,
+        txn_id: &TxnId,
+        ops: &[Mutation],
+    ) -> TCResult<()> {
+        for op in ops {
+            let result = match op {
+                Mutation::Delete(path, key) => {
+                    debug!("replay DELETE {}{}: {}", subject, path, key);
+
+                    subject.delete(txn, path, key.clone()).await
+                }
+                Mutation::Put(path, key, value) => {
+                    debug!("replay PUT {}{}: {} <- {}", subject, path, key, value);
+
+                    self.resolve(txn, value.clone())
+                        .and_then(|value| subject.put(txn, path, key.clone(), value))
+                        .await
+                }
+                Mutation::Revert(past_txn_id, value) => {
+                    debug!("replay REVERT {} to {}", subject, past_txn_id);
 
-            let other = other.read_block(txn_id, i.into()).await?;
+                    self.resolve(txn, value.clone())
+                        .and_then(|value| subject.restore(txn, value))
+                        .await
+                }
+            };
 
-            if &*block != &*other {
-                return Err(TCError::bad_request(ERR_DIVERGENT, i));
+            if let Err(cause) = result {
+                return Err(TCError::internal(format!(
+                    "error replaying transaction {}: {}",
+                    txn_id, cause
+                )));
             }
         }
 
-        let mut i = *latest;
+        Ok(())
+    }
+
+    pub async fn replicate(
+        &self,
+        txn: &Txn,
+        schema: &Schema,
+        subject: &Subject,
+        other: Self,
+    ) -> TCResult<()> {
+        debug!("replicate chain history");
+
+        let txn_id = *txn.id();
+
+        let compacted = {
+            let (first, other_first) =
+                try_join!(self.read_block(txn_id, 0), other.read_block(txn_id, 0))?;
+
+            *first != *other_first && (is_snapshot(0, &first) || is_snapshot(0, &other_first))
+        };
+
+        let mut i = if compacted {
+            // the two histories were compacted at different points, so start over from the source
+            match self.reset(txn, schema, subject, &other).await? {
+                Some(i) => i,
+                None => return Ok(()),
+            }
+        } else {
+            let (latest, other_latest) =
+                try_join!(self.latest.read(txn_id), other.latest.read(txn_id))?;
+
+            if (*latest) > (*other_latest) {
+                return Err(TCError::bad_request(
+                    "cannot replicate from chain with fewer blocks",
+                    *latest,
+                ));
+            }
+
+            const ERR_DIVERGENT: &str = "chain to replicate diverges at block";
+            for i in 0u64..*latest {
+                let block = self.read_block(txn_id, i.into()).await?;
+
+                let other = other.read_block(txn_id, i.into()).await?;
+
+                if &*block != &*other {
+                    return Err(TCError::bad_request(ERR_DIVERGENT, i));
+                }
+            }
+
+            *latest
+        };
+
         loop {
             debug!("copy history from block {}", i);
 
@@ -353,6 +988,16 @@ impl History {
 
                             subject.put(txn, &path, key, value).await
                         }
+                        Mutation::Revert(reverted_to, value) => {
+                            let value = other.resolve(txn, value).await?;
+                            let value_ref = self.save_state(txn, value.clone()).await?;
+
+                            if append {
+                                dest.append_revert(*past_txn_id, reverted_to, value_ref);
+                            }
+
+                            subject.restore(txn, value).await
+                        }
                     };
 
                     if let Err(cause) = result {
@@ -384,33 +1029,102 @@ impl History {
         Ok(())
     }
 
-    pub async fn resolve(&self, txn: &Txn, scalar: Scalar) -> TCResult<State> {
-        debug!("History::resolve {}", scalar);
-
-        type OpSubject = crate::scalar::Subject;
+    /// Reset this `History` and its `subject` to the beginning of the `other` history,
+    /// and return the ordinal of the first block of `other` which remains to be copied, if any.
+    async fn reset(
+        &self,
+        txn: &Txn,
+        schema: &Schema,
+        subject: &Subject,
+        other: &Self,
+    ) -> TCResult<Option<u64>> {
+        let txn_id = *txn.id();
+        let other_first = ChainBlock::clone(&*other.read_block(txn_id, 0).await?);
 
-        if let Scalar::Ref(tc_ref) = scalar {
-            if let TCRef::Op(OpRef::Get((OpSubject::Ref(hash, classpath), schema))) = *tc_ref {
-                let class = CollectionType::from_path(&classpath).ok_or_else(|| {
-                    TCError::internal(format!("invalid Collection type: {}", classpath))
-                })?;
+        let (first, start) = if is_snapshot(0, &other_first) {
+            let (snapshot_txn_id, snapshot) = snapshot(&other_first)?;
+            let snapshot = other.resolve(txn, snapshot.clone()).await?;
+            let saved = self.save_state(txn, snapshot.clone()).await?;
+            subject.restore(txn, snapshot).await?;
 
-                self.resolve_inner(txn, hash.into(), schema, class)
-                    .map_ok(State::from)
-                    .await
-            } else {
-                error!("invalid subject for historical Chain state {}", tc_ref);
+            let hash = other_first.last_hash().clone();
+            (snapshot_block(hash, snapshot_txn_id, saved), 1)
+        } else {
+            let scratch = txn.context().create_dir_unique(txn_id).await?;
+            let empty = Subject::create(schema.clone(), &scratch, txn_id).await?;
+            subject.restore(txn, State::from(empty)).await?;
+            (ChainBlock::new(NULL_HASH), 0)
+        };
 
-                Err(TCError::internal(format!(
-                    "invalid subject for historical Chain state {}",
-                    tc_ref
-                )))
+        {
+            let mut latest = self.latest.write(txn_id).await?;
+            for block_id in 1..(*latest + 1) {
+                self.file.delete_block(txn_id, block_id.into()).await?;
             }
+
+            *latest = 0;
+        }
+
+        *self.write_block(txn_id, 0).await? = first;
+
+        if start == 0 {
+            Ok(Some(0))
+        } else if other.contains_block(txn_id, start).await? {
+            self.create_next_block(txn_id).await?;
+            Ok(Some(start))
         } else {
-            Ok(scalar.into())
+            Ok(None)
         }
     }
 
+    pub fn resolve<'a>(&'a self, txn: &'a Txn, scalar: Scalar) -> TCBoxTryFuture<'a, State> {
+        Box::pin(async move {
+            debug!("History::resolve {}", scalar);
+
+            type OpSubject = crate::scalar::Subject;
+
+            match scalar {
+                Scalar::Ref(tc_ref) => {
+                    if let TCRef::Op(OpRef::Get((OpSubject::Ref(hash, classpath), schema))) =
+                        *tc_ref
+                    {
+                        let class = CollectionType::from_path(&classpath).ok_or_else(|| {
+                            TCError::internal(format!("invalid Collection type: {}", classpath))
+                        })?;
+
+                        self.resolve_inner(txn, hash.into(), schema, class)
+                            .map_ok(State::from)
+                            .await
+                    } else {
+                        error!("invalid subject for historical Chain state {}", tc_ref);
+
+                        Err(TCError::internal(format!(
+                            "invalid subject for historical Chain state {}",
+                            tc_ref
+                        )))
+                    }
+                }
+                Scalar::Map(map) if map.values().any(Scalar::is_ref) => {
+                    let mut resolved = Map::new();
+                    for (name, scalar) in map {
+                        resolved.insert(name, self.resolve(txn, scalar).await?);
+                    }
+
+                    Ok(State::Map(resolved))
+                }
+                Scalar::Tuple(tuple) if tuple.iter().any(Scalar::is_ref) => {
+                    let mut resolved = Vec::with_capacity(tuple.len());
+                    for scalar in tuple {
+                        resolved.push(self.resolve(txn, scalar).await?);
+                    }
+
+                    Ok(State::Tuple(resolved.into()))
+                }
+                scalar => Ok(scalar.into()),
+            }
+        })
+    }
+
     async fn resolve_inner(
         &self,
         txn: &Txn,
@@ -459,7 +1173,7 @@ impl History {
                 Ok(Collection::Table(table.into()))
             }
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             CollectionType::Tensor(tt) => {
                 let schema: Value = schema.try_cast_into(|s| {
                     TCError::internal(format!("invalid Tensor schema: {}", s))
@@ -517,14 +1231,19 @@ impl Persist<fs::Dir> for History {
         // so just create a new one in memory
         let dir = dir.get_or_create_dir(*txn_id, DATA.into()).await?;
 
-        let mut last_hash = Bytes::from(NULL_HASH);
+        // the first block of a compacted history begins with the hash of the last folded block
+        let mut last_hash = file
+            .read_block(*txn_id, 0u64.into())
+            .await?
+            .last_hash()
+            .clone();
         let mut latest = 0;
 
         loop {
             let block = file.read_block(*txn_id, latest.into()).await?;
 
             if block.last_hash() == &last_hash {
-                last_hash = block.last_hash().clone();
+                last_hash = block.hash().await?;
             } else {
                 return Err(TCError::internal(format!(
                     "block {} hash does not match previous block",
@@ -606,15 +1325,7 @@ impl de::Visitor for HistoryVisitor {
                 .try_cast_into(|s| TCError::bad_request("invalid Chain block", s))
                 .map_err(de::Error::custom)?;
 
-            if hash != NULL_HASH {
-                let hash = hex::encode(hash);
-                let null_hash = hex::encode(NULL_HASH);
-                return Err(de::Error::invalid_value(
-                    format!("initial block hash {}", hash),
-                    format!("null hash {}", null_hash),
-                ));
-            }
-
+            // a history which does not start with the null hash starts with a snapshot
             let mutations = parse_block_state(&history, &txn, block_data)
                 .map_err(de::Error::custom)
                 .await?;
@@ -660,6 +1371,87 @@ impl de::Visitor for HistoryVisitor {
     }
 }
 
+/// Return `true` if the block with the given ordinal is the snapshot of a compacted history.
+fn is_snapshot(block_id: u64, block: &ChainBlock) -> bool {
+    block_id == 0 && block.last_hash()[..] != NULL_HASH[..]
+}
+
+/// Return the key of the single row of the given `table` changed by the given `op`, if any.
+fn row_key(table: &TableIndex, op: &Mutation) -> Option<Key> {
+    let (path, key) = match op {
+        Mutation::Delete(path, key) => (path, key),
+        Mutation::Put(path, key, _) => (path, key),
+        Mutation::Revert(_, _) => return None,
+    };
+
+    if !path.is_empty() || key.is_none() {
+        return None;
+    }
+
+    let key = Vec::<Value>::try_cast_from(key.clone(), |_| ()).ok()?;
+    table.schema().primary().validate_key(key).ok()
+}
+
+/// Compare the `before` and `after` rows of a table, both in order of their key, and append a
+/// [`Change`] for each row which differs.
+fn diff_rows(
+    txn_id: TxnId,
+    key_len: usize,
+    before: Vec<Vec<Value>>,
+    after: Vec<Vec<Value>>,
+    changes: &mut Vec<Change>,
+) {
+    let collator = ValueCollator::default();
+    let mut before = before.into_iter().peekable();
+    let mut after = after.into_iter().peekable();
+
+    loop {
+        let order = match (before.peek(), after.peek()) {
+            (Some(l), Some(r)) => collator.compare_slice(&l[..key_len], &r[..key_len]),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+
+        let change = match order {
+            Ordering::Less => Change::diff(txn_id, key_len, before.next(), None),
+            Ordering::Greater => Change::diff(txn_id, key_len, None, after.next()),
+            Ordering::Equal => Change::diff(txn_id, key_len, before.next(), after.next()),
+        };
+
+        changes.extend(change);
+    }
+}
+
+/// Construct the first block of a compacted history.
+fn snapshot_block(hash: Bytes, txn_id: TxnId, snapshot: Scalar) -> ChainBlock {
+    let mut contents = BTreeMap::new();
+    contents.insert(
+        txn_id,
+        vec![Mutation::Put(TCPathBuf::default(), Value::None, snapshot)],
+    );
+
+    ChainBlock::with_mutations(hash, contents)
+}
+
+/// Borrow the [`TxnId`] and saved subject state of the given snapshot block.
+fn snapshot(block: &ChainBlock) -> TCResult<(TxnId, &Scalar)> {
+    let mut entries = block.mutations().iter();
+
+    if let (Some((txn_id, ops)), None) = (entries.next(), entries.next()) {
+        if let [Mutation::Put(path, key, snapshot)] = &ops[..] {
+            if path.is_empty() && key.is_none() {
+                return Ok((*txn_id, snapshot));
+            }
+        }
+    }
+
+    Err(TCError::internal(format!(
+        "invalid chain snapshot {}",
+        block
+    )))
+}
+
 async fn parse_block_state(
     history: &History,
     txn: &Txn,
@@ -673,7 +1465,14 @@ async fn parse_block_state(
         let mut parsed = Vec::with_capacity(ops.len());
 
         for op in ops.into_iter() {
-            if op.matches::<(TCPathBuf, Value)>() {
+            if op.matches::<(TCPathBuf, Value, State, TCString)>() {
+                let (_path, _key, value, reverted_to): (TCPathBuf, Value, State, TCString) =
+                    op.opt_cast_into().unwrap();
+
+                let reverted_to = reverted_to.as_str().parse()?;
+                let value = history.save_state(txn, value).await?;
+                parsed.push(Mutation::Revert(reverted_to, value));
+            } else if op.matches::<(TCPathBuf, Value)>() {
                 let (path, key) = op.opt_cast_into().unwrap();
                 parsed.push(Mutation::Delete(path, key));
             } else if op.matches::<(TCPathBuf, Value, State)>() {
@@ -762,6 +1561,11 @@ async fn load_history<'a>(history: History, op: Mutation, txn: Txn) -> TCResult<
 
             Ok(MutationView::Put(path, key, value))
         }
+        Mutation::Revert(past_txn_id, value) => {
+            let value = history.resolve(&txn, value).await?;
+            let value = value.into_view(txn).await?;
+            Ok(MutationView::Revert(past_txn_id, value))
+        }
     }
 }
 
@@ -780,6 +1584,7 @@ type HistoryBlockView<'en> = (
 pub enum MutationView<'en> {
     Delete(TCPathBuf, Value),
     Put(TCPathBuf, Value, StateView<'en>),
+    Revert(TxnId, StateView<'en>),
 }
 
 impl<'en> en::IntoStream<'en> for MutationView<'en> {
@@ -787,6 +1592,9 @@ impl<'en> en::IntoStream<'en> for MutationView<'en> {
         match self {
             Self::Delete(path, key) => (path, key).into_stream(encoder),
             Self::Put(path, key, value) => (path, key, value).into_stream(encoder),
+            Self::Revert(txn_id, value) => {
+                (TCPathBuf::default(), Value::None, value, txn_id).into_stream(encoder)
+            }
         }
     }
 }
@@ -1,5 +1,5 @@
 pub use block::{ChainBlock, Mutation};
-pub use history::{History, HistoryView};
+pub use history::{Change, ChangeKind, History, HistoryView};
 
 mod block;
 mod history;
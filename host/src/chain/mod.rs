@@ -3,6 +3,7 @@
 use std::convert::TryFrom;
 use std::fmt;
 use std::iter::FromIterator;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use destream::{de, en};
@@ -12,17 +13,18 @@ use safecast::{TryCastFrom, TryCastInto};
 
 use tc_btree::{BTreeType, Column};
 use tc_error::*;
-#[cfg(feature = "tensor")]
+use tc_table::{ColumnAccess, PrivacyPolicy, TableInstance};
+#[cfg(feature = "tc-tensor")]
 use tc_tensor::TensorPersist;
 use tc_transact::fs::{Dir, Persist, Restore, Store};
 use tc_transact::{IntoView, Transact, Transaction, TxnId};
-use tc_value::{Link, Value};
+use tc_value::Value;
 use tcgeneric::*;
 
 use crate::collection::{
     BTree, BTreeFile, Collection, CollectionType, Table, TableIndex, TableType,
 };
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 use crate::collection::{
     DenseTensor, DenseTensorFile, SparseTable, SparseTensor, Tensor, TensorType,
 };
@@ -32,7 +34,7 @@ use crate::state::{State, StateType, StateView};
 use crate::txn::Txn;
 
 pub use block::BlockChain;
-pub use data::ChainBlock;
+pub use data::{ChainBlock, Change, ChangeKind, Mutation};
 pub use sync::SyncChain;
 
 mod block;
@@ -47,6 +49,16 @@ const PREFIX: PathLabel = path_label(&["state", "chain"]);
 
 const SUBJECT: Label = label("subject");
 
+/// The policy by which a host compacts the history of its [`BlockChain`]s.
+#[derive(Clone, Copy)]
+pub struct CompactionPolicy {
+    /// The number of most recent blocks of each chain to keep as-is
+    pub retain: u64,
+
+    /// How often to compact
+    pub interval: Duration,
+}
+
 /// The schema of a [`Chain`], used when constructing a new `Chain` or loading a `Chain` from disk.
 #[derive(Clone)]
 pub enum Schema {
@@ -55,9 +67,9 @@ pub enum Schema {
     Table(tc_table::TableSchema),
     Tuple(Tuple<Schema>),
 
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Dense(tc_tensor::Schema),
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Sparse(tc_tensor::Schema),
 }
 
@@ -85,14 +97,31 @@ impl Schema {
                                 Ok(Self::BTree(schema))
                             }
                             CollectionType::Table(_) => {
-                                let schema = schema.try_cast_into(|s| {
+                                let schema: tc_table::TableSchema = schema.try_cast_into(|s| {
                                     TCError::bad_request("invalid Table schema", s)
                                 })?;
 
+                                for access in schema.access() {
+                                    if !schema
+                                        .primary()
+                                        .column_names()
+                                        .any(|name| name == &access.column)
+                                    {
+                                        return Err(TCError::bad_request(
+                                            "cannot restrict access to nonexistent column",
+                                            &access.column,
+                                        ));
+                                    }
+                                }
+
+                                if let Some(privacy) = schema.privacy() {
+                                    privacy.validate(schema.primary().column_names())?;
+                                }
+
                                 Ok(Self::Table(schema))
                             }
 
-                            #[cfg(feature = "tensor")]
+                            #[cfg(feature = "tc-tensor")]
                             CollectionType::Tensor(tt) => {
                                 let schema: Value = schema.try_cast_into(|s| {
                                     TCError::bad_request("invalid Tensor schema", s)
@@ -158,7 +187,7 @@ impl<'en> en::IntoStream<'en> for Schema {
             }
             Self::Tuple(tuple) => tuple.into_stream(encoder),
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Dense(schema) | Self::Sparse(schema) => {
                 let mut map = encoder.encode_map(Some(1))?;
                 map.encode_entry(TensorType::Dense.path(), (schema,))?;
@@ -176,9 +205,9 @@ impl fmt::Display for Schema {
             Self::Table(schema) => fmt::Display::fmt(schema, f),
             Self::Tuple(schema) => fmt::Display::fmt(schema, f),
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Dense(schema) => fmt::Display::fmt(schema, f),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Sparse(schema) => fmt::Display::fmt(schema, f),
         }
     }
@@ -192,9 +221,9 @@ pub enum Subject {
     Table(TableIndex),
     Tuple(Tuple<Subject>),
 
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Dense(DenseTensor<DenseTensorFile>),
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Sparse(SparseTensor<SparseTable>),
 }
 
@@ -203,7 +232,7 @@ impl Subject {
     pub fn create<'a>(schema: Schema, dir: &'a fs::Dir, txn_id: TxnId) -> TCBoxTryFuture<'a, Self> {
         Box::pin(async move {
             match schema {
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 Schema::Dense(schema) => {
                     let file = dir
                         .create_file(txn_id, SUBJECT.into(), TensorType::Dense)
@@ -213,7 +242,7 @@ impl Subject {
                         .map_ok(Self::Dense)
                         .await
                 }
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 Schema::Sparse(schema) => {
                     let dir = dir.create_dir(txn_id, SUBJECT.into()).await?;
                     let tensor = SparseTensor::create(&dir, schema, txn_id)
@@ -265,6 +294,58 @@ impl Subject {
         })
     }
 
+    /// Return the [`ColumnAccess`] rules of each `Table` in this `Subject`.
+    pub fn column_access(&self) -> Vec<ColumnAccess> {
+        match self {
+            Self::Map(map) => map
+                .iter()
+                .flat_map(|(_, subject)| subject.column_access())
+                .collect(),
+            Self::Table(table) => TableInstance::schema(table).access().to_vec(),
+            Self::Tuple(tuple) => tuple
+                .iter()
+                .flat_map(|subject| subject.column_access())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Return the [`PrivacyPolicy`] of each `Table` in this `Subject`.
+    pub fn privacy(&self) -> Vec<PrivacyPolicy> {
+        match self {
+            Self::Map(map) => map
+                .iter()
+                .flat_map(|(_, subject)| subject.privacy())
+                .collect(),
+            Self::Table(table) => TableInstance::schema(table)
+                .privacy()
+                .into_iter()
+                .cloned()
+                .collect(),
+            Self::Tuple(tuple) => tuple.iter().flat_map(|subject| subject.privacy()).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Find the member of this `Subject` addressed by the given `path`,
+    /// and return it with the rest of the `path` (the path of the request to the member).
+    pub fn member<'a>(&'a self, path: &'a [PathSegment]) -> (&'a Self, &'a [PathSegment]) {
+        let member = match self {
+            Self::Map(map) if !path.is_empty() => map.get(&path[0]),
+            Self::Tuple(tuple) if !path.is_empty() => path[0]
+                .as_str()
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| tuple.get(i)),
+            _ => None,
+        };
+
+        match member {
+            Some(member) => member.member(&path[1..]),
+            None => (self, path),
+        }
+    }
+
     fn load<'a>(txn: &'a Txn, schema: Schema, dir: &'a fs::Dir) -> TCBoxTryFuture<'a, Self> {
         Box::pin(async move {
             match schema {
@@ -322,7 +403,7 @@ impl Subject {
                     .await
                 }
 
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 Schema::Dense(schema) => {
                     if let Some(file) = dir.get_file(*txn.id(), &SUBJECT.into()).await? {
                         DenseTensor::load(txn, schema, file)
@@ -332,7 +413,7 @@ impl Subject {
                         Self::create(Schema::Dense(schema), dir, *txn.id()).await
                     }
                 }
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 Schema::Sparse(schema) => {
                     if let Some(dir) = dir.get_dir(*txn.id(), &SUBJECT.into()).await? {
                         SparseTensor::load(txn, schema, dir)
@@ -415,7 +496,7 @@ impl Subject {
                     ))),
                 },
 
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 Self::Dense(tensor) => match backup {
                     State::Collection(Collection::Tensor(Tensor::Dense(backup))) => {
                         let file = txn
@@ -433,7 +514,7 @@ impl Subject {
                         other,
                     )),
                 },
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 Self::Sparse(tensor) => match backup {
                     State::Collection(Collection::Tensor(Tensor::Sparse(backup))) => {
                         let dir = txn.context().create_dir_unique(txn_id).await?;
@@ -459,9 +540,9 @@ impl Instance for Subject {
             Self::Map(_) => StateType::Map,
             Self::Table(table) => CollectionType::Table(table.class()).into(),
             Self::Tuple(_) => StateType::Tuple,
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Dense(dense) => CollectionType::Tensor(dense.class()).into(),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Sparse(sparse) => CollectionType::Tensor(sparse.class()).into(),
         }
     }
@@ -490,9 +571,9 @@ impl Transact for Subject {
                 )
                 .await;
             }
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Dense(tensor) => tensor.commit(txn_id).await,
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Sparse(tensor) => tensor.commit(txn_id).await,
         }
     }
@@ -515,9 +596,9 @@ impl Transact for Subject {
                 }))
                 .await;
             }
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Dense(tensor) => tensor.finalize(txn_id).await,
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Sparse(tensor) => tensor.finalize(txn_id).await,
         }
     }
@@ -541,7 +622,7 @@ fn from_state<E: de::Error>(state: State) -> Result<Subject, E> {
         State::Collection(collection) => match collection {
             Collection::BTree(BTree::File(btree)) => Ok(Subject::BTree(btree)),
             Collection::Table(Table::Table(table)) => Ok(Subject::Table(table)),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Collection::Tensor(tensor) => match tensor {
                 Tensor::Dense(dense) => dense
                     .as_persistent()
@@ -601,9 +682,9 @@ impl<'en> IntoView<'en, fs::Dir> for Subject {
                 try_join_all(views).map_ok(StateView::Tuple).await
             }
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Dense(tensor) => State::from(Tensor::from(tensor)).into_view(txn).await,
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Sparse(tensor) => State::from(Tensor::from(tensor)).into_view(txn).await,
         }
     }
@@ -621,9 +702,9 @@ impl From<Subject> for State {
             Subject::Table(table) => State::Collection(table.into()),
             Subject::Tuple(tuple) => State::Tuple(tuple.into_iter().map(State::from).collect()),
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Subject::Dense(dense) => State::Collection(dense.into()),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Subject::Sparse(sparse) => State::Collection(sparse.into()),
         }
     }
@@ -637,9 +718,9 @@ impl fmt::Display for Subject {
             Self::Table(table) => write!(f, "chain Subject, {}", table.class()),
             Self::Tuple(tuple) => fmt::Display::fmt(tuple, f),
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Dense(_) => write!(f, "chain Subject, {}", TensorType::Dense),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Sparse(_) => write!(f, "chain Subject, {}", TensorType::Sparse),
         }
     }
@@ -665,10 +746,40 @@ pub trait ChainInstance {
     /// Borrow the [`Subject`] of this [`Chain`] immutably.
     fn subject(&self) -> &Subject;
 
-    /// Replicate this [`Chain`] from the [`Chain`] at the given [`Link`].
-    async fn replicate(&self, txn: &Txn, source: Link) -> TCResult<()>;
+    /// Replicate this [`Chain`] from the given `source`, read from another replica.
+    async fn replicate(&self, txn: &Txn, source: Chain) -> TCResult<()>;
 
     async fn write_ahead(&self, txn_id: &TxnId);
+
+    /// Save the data needed to commit the given [`TxnId`] later, even after a restart,
+    /// and return its pending mutations.
+    async fn prepare(&self, txn_id: TxnId) -> TCResult<Vec<Mutation>>;
+
+    /// Apply the given `mutations`, prepared at the given [`TxnId`], as part of the given [`Txn`],
+    /// unless they have already been committed.
+    async fn recover(&self, txn: &Txn, prepared: TxnId, mutations: Vec<Mutation>) -> TCResult<()>;
+
+    /// Fold all but the latest `retain` blocks of this [`Chain`] into a snapshot of its
+    /// [`Subject`], as part of the given [`Txn`]. Returns `false` if there is nothing to compact.
+    async fn compact(&self, txn: &Txn, retain: u64) -> TCResult<bool>;
+
+    /// List the [`TxnId`] of each past transaction recorded in the history of this [`Chain`].
+    async fn history(&self, txn_id: TxnId) -> TCResult<Vec<TxnId>>;
+
+    /// Construct a copy of the [`Subject`] of this [`Chain`] as of the given past [`TxnId`].
+    async fn as_of(&self, txn: &Txn, past_txn_id: TxnId) -> TCResult<Subject>;
+
+    /// Stream each change to a row of the [`Subject`] of this [`Chain`] committed after the given
+    /// past [`TxnId`], or every change in its history if `since` is `None`.
+    async fn changes(
+        &self,
+        txn: &Txn,
+        since: Option<TxnId>,
+    ) -> TCResult<TCBoxTryStream<'static, Change>>;
+
+    /// Revert the [`Subject`] of this [`Chain`] to its state as of the given past [`TxnId`],
+    /// as part of the given [`Txn`].
+    async fn revert(&self, txn: &Txn, past_txn_id: TxnId) -> TCResult<()>;
 }
 
 /// The type of a [`Chain`].
@@ -778,7 +889,7 @@ impl ChainInstance for Chain {
         }
     }
 
-    async fn replicate(&self, txn: &Txn, source: Link) -> TCResult<()> {
+    async fn replicate(&self, txn: &Txn, source: Chain) -> TCResult<()> {
         match self {
             Self::Block(chain) => chain.replicate(txn, source).await,
             Self::Sync(chain) => chain.replicate(txn, source).await,
@@ -791,6 +902,59 @@ impl ChainInstance for Chain {
             Self::Sync(chain) => chain.write_ahead(txn_id).await,
         }
     }
+
+    async fn prepare(&self, txn_id: TxnId) -> TCResult<Vec<Mutation>> {
+        match self {
+            Self::Block(chain) => chain.prepare(txn_id).await,
+            Self::Sync(chain) => chain.prepare(txn_id).await,
+        }
+    }
+
+    async fn recover(&self, txn: &Txn, prepared: TxnId, mutations: Vec<Mutation>) -> TCResult<()> {
+        match self {
+            Self::Block(chain) => chain.recover(txn, prepared, mutations).await,
+            Self::Sync(chain) => chain.recover(txn, prepared, mutations).await,
+        }
+    }
+
+    async fn compact(&self, txn: &Txn, retain: u64) -> TCResult<bool> {
+        match self {
+            Self::Block(chain) => chain.compact(txn, retain).await,
+            Self::Sync(chain) => chain.compact(txn, retain).await,
+        }
+    }
+
+    async fn history(&self, txn_id: TxnId) -> TCResult<Vec<TxnId>> {
+        match self {
+            Self::Block(chain) => chain.history(txn_id).await,
+            Self::Sync(chain) => chain.history(txn_id).await,
+        }
+    }
+
+    async fn as_of(&self, txn: &Txn, past_txn_id: TxnId) -> TCResult<Subject> {
+        match self {
+            Self::Block(chain) => chain.as_of(txn, past_txn_id).await,
+            Self::Sync(chain) => chain.as_of(txn, past_txn_id).await,
+        }
+    }
+
+    async fn changes(
+        &self,
+        txn: &Txn,
+        since: Option<TxnId>,
+    ) -> TCResult<TCBoxTryStream<'static, Change>> {
+        match self {
+            Self::Block(chain) => chain.changes(txn, since).await,
+            Self::Sync(chain) => chain.changes(txn, since).await,
+        }
+    }
+
+    async fn revert(&self, txn: &Txn, past_txn_id: TxnId) -> TCResult<()> {
+        match self {
+            Self::Block(chain) => chain.revert(txn, past_txn_id).await,
+            Self::Sync(chain) => chain.revert(txn, past_txn_id).await,
+        }
+    }
 }
 
 #[async_trait]
@@ -9,15 +9,17 @@ use futures::join;
 use tc_error::*;
 use tc_transact::fs::{Persist, Store};
 use tc_transact::{IntoView, Transact, Transaction, TxnId};
-use tc_value::{Link, Value};
-use tcgeneric::TCPathBuf;
+use tc_value::Value;
+use tcgeneric::{TCBoxTryStream, TCPathBuf};
 
 use crate::fs;
 use crate::state::{State, StateView};
 use crate::txn::Txn;
 
-use super::data::History;
-use super::{ChainBlock, ChainInstance, ChainType, Schema, Subject, NULL_HASH};
+use super::data::{Change, History, Mutation};
+use super::{Chain, ChainBlock, ChainInstance, ChainType, Schema, Subject, NULL_HASH};
+
+const ERR_NO_HISTORY: &str = "a SyncChain does not keep the history of its subject";
 
 /// A [`super::Chain`] which keeps only the data needed to recover the state of its subject in the
 /// event of a transaction failure.
@@ -63,8 +65,9 @@ impl ChainInstance for SyncChain {
         &self.subject
     }
 
-    async fn replicate(&self, txn: &Txn, source: Link) -> TCResult<()> {
-        let subject = txn.get(source, Value::None).await?;
+    async fn replicate(&self, txn: &Txn, source: Chain) -> TCResult<()> {
+        // there is no history to replicate, so copy only the latest state of the subject
+        let subject = State::from(source.subject().clone());
         self.subject.restore(txn, subject).await?;
 
         let mut block = self.history.write_latest(*txn.id()).await?;
@@ -77,6 +80,46 @@ impl ChainInstance for SyncChain {
     async fn write_ahead(&self, txn_id: &TxnId) {
         self.history.commit(txn_id).await
     }
+
+    async fn prepare(&self, txn_id: TxnId) -> TCResult<Vec<Mutation>> {
+        self.history.prepare(txn_id).await
+    }
+
+    async fn recover(&self, txn: &Txn, prepared: TxnId, mutations: Vec<Mutation>) -> TCResult<()> {
+        {
+            let mut block = self.history.write_latest(*txn.id()).await?;
+            block.clear_until(txn.id());
+        }
+
+        self.history
+            .recover(txn, &self.subject, prepared, mutations)
+            .await
+    }
+
+    async fn compact(&self, _txn: &Txn, _retain: u64) -> TCResult<bool> {
+        // a SyncChain only ever has one block
+        Ok(false)
+    }
+
+    async fn history(&self, _txn_id: TxnId) -> TCResult<Vec<TxnId>> {
+        Err(TCError::unsupported(ERR_NO_HISTORY))
+    }
+
+    async fn as_of(&self, _txn: &Txn, _past_txn_id: TxnId) -> TCResult<Subject> {
+        Err(TCError::unsupported(ERR_NO_HISTORY))
+    }
+
+    async fn changes(
+        &self,
+        _txn: &Txn,
+        _since: Option<TxnId>,
+    ) -> TCResult<TCBoxTryStream<'static, Change>> {
+        Err(TCError::unsupported(ERR_NO_HISTORY))
+    }
+
+    async fn revert(&self, _txn: &Txn, _past_txn_id: TxnId) -> TCResult<()> {
+        Err(TCError::unsupported(ERR_NO_HISTORY))
+    }
 }
 
 #[async_trait]
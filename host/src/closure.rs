@@ -16,7 +16,7 @@ use tcgeneric::{Id, Instance, Map, PathSegment, TCPathBuf, Tuple};
 
 use crate::fs;
 use crate::route::{DeleteHandler, GetHandler, Handler, PostHandler, PutHandler};
-use crate::scalar::{Executor, OpDef, OpDefType, OpRef, Scalar, SELF};
+use crate::scalar::{Executor, OpDef, OpDefType, OpRef, Scalar, Signature, SELF};
 use crate::state::{State, StateView};
 use crate::txn::Txn;
 
@@ -38,6 +38,21 @@ impl Closure {
         (self.context, self.op)
     }
 
+    /// Bind the given `params` into the context of this `Closure`, for partial application.
+    pub fn bind(self, params: Map<State>) -> TCResult<Self> {
+        if params.contains_key(&SELF.into()) {
+            return Err(TCError::bad_request("cannot bind a parameter called", SELF));
+        }
+
+        let mut context = self.context;
+        context.extend(params);
+
+        Ok(Self {
+            context,
+            op: self.op,
+        })
+    }
+
     /// Replace references to `$self` with the given `path`.
     pub fn dereference_self(self, path: &TCPathBuf) -> Self {
         let mut context = self.context;
@@ -102,7 +117,22 @@ impl Closure {
                     .await
             }
             OpDef::Post(op_def) => {
-                let params: Map<State> = args.try_into()?;
+                let mut params: Map<State> = args.try_into()?;
+                let (signature, op_def) = Signature::split(op_def)?;
+
+                if let Some(signature) = signature {
+                    // a parameter may already be bound in the context of this closure
+                    for param in signature.params() {
+                        if !params.contains_key(param.name()) {
+                            if let Some(bound) = context.remove(param.name()) {
+                                params.insert(param.name().clone(), bound);
+                            }
+                        }
+                    }
+
+                    params = signature.destructure(params)?;
+                }
+
                 context.extend(params);
 
                 Executor::with_context(txn, subject.as_ref(), context, op_def)
@@ -0,0 +1,481 @@
+//! A durable record of the two-phase commit protocol, used to resolve in-doubt transactions
+//! after a restart.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+use bytes::Bytes;
+use freqfs::{DirEntry, DirLock, FileReadGuard, FileWriteGuard};
+use futures::TryFutureExt;
+use log::debug;
+use safecast::TryCastFrom;
+use tokio::sync::RwLock;
+
+use tc_error::*;
+use tc_value::{Link, Value};
+use tcgeneric::{label, Id, Label, Map, Tuple};
+
+use crate::chain::{ChainBlock, Mutation};
+use crate::fs::{self, io_err, CacheBlock};
+use crate::txn::TxnId;
+
+const DIR_NAME: &str = ".journal";
+const STATE: &str = "state.value";
+const BLOCK_EXT: &str = ".chain_block";
+
+const PREPARED: Label = label("prepared");
+const COMMIT: Label = label("commit");
+
+/// The state of a transaction in the two-phase commit protocol, from the perspective of one
+/// [`super::Cluster`].
+#[derive(Clone)]
+pub enum Entry {
+    /// This cluster is coordinating the transaction but has not yet decided to commit it.
+    Deciding,
+
+    /// This cluster is prepared to commit the transaction, pending the decision of `coordinator`.
+    Prepared {
+        coordinator: Link,
+        participants: HashSet<Link>,
+    },
+
+    /// The transaction is committed, pending acknowledgment by the listed `participants`.
+    Commit { participants: HashSet<Link> },
+}
+
+impl Entry {
+    fn participants(&self) -> Option<&HashSet<Link>> {
+        match self {
+            Self::Deciding => None,
+            Self::Prepared { participants, .. } => Some(participants),
+            Self::Commit { participants } => Some(participants),
+        }
+    }
+}
+
+impl From<&Entry> for Value {
+    fn from(entry: &Entry) -> Value {
+        let (state, coordinator) = match entry {
+            Entry::Deciding => unreachable!("write an undecided transaction to the journal"),
+            Entry::Prepared { coordinator, .. } => (PREPARED, Value::from(coordinator.clone())),
+            Entry::Commit { .. } => (COMMIT, Value::None),
+        };
+
+        let participants = entry.participants().expect("participants").iter().cloned();
+
+        Value::Tuple(
+            vec![
+                Id::from(state).into(),
+                coordinator,
+                Value::from_iter(participants),
+            ]
+            .into(),
+        )
+    }
+}
+
+impl TryFrom<Value> for Entry {
+    type Error = TCError;
+
+    fn try_from(value: Value) -> TCResult<Self> {
+        let err = |v: &Value| TCError::internal(format!("invalid journal entry: {}", v));
+
+        let mut fields = match value {
+            Value::Tuple(fields) if fields.len() == 3 => fields.into_inner(),
+            other => return Err(err(&other)),
+        };
+
+        let participants = fields.pop().expect("participants");
+        let coordinator = fields.pop().expect("coordinator");
+        let state = Id::try_cast_from(fields.pop().expect("state"), err)?;
+
+        let participants = Tuple::<Link>::try_cast_from(participants, err)?;
+        let participants = participants.into_iter().collect();
+
+        if state == PREPARED {
+            let coordinator = Link::try_cast_from(coordinator, err)?;
+            Ok(Self::Prepared {
+                coordinator,
+                participants,
+            })
+        } else if state == COMMIT {
+            Ok(Self::Commit { participants })
+        } else {
+            Err(TCError::internal(format!(
+                "invalid journal entry state: {}",
+                state
+            )))
+        }
+    }
+}
+
+/// A durable record of the transactions which a [`super::Cluster`] has prepared or committed
+/// but which have not yet been acknowledged by every participant.
+pub struct Journal {
+    dir: DirLock<CacheBlock>,
+    entries: RwLock<HashMap<TxnId, Entry>>,
+}
+
+impl Journal {
+    /// Load the journal of the cluster whose data is stored in the given `dir`.
+    pub async fn load(dir: &fs::Dir) -> TCResult<Self> {
+        let dir = dir.get_or_create_hidden(DIR_NAME).await?;
+
+        let mut entries = HashMap::new();
+        {
+            let contents = dir.read().await;
+            for (name, entry) in contents.iter() {
+                let txn_id: TxnId = name.parse()?;
+                let entry = match entry {
+                    DirEntry::Dir(entry) => entry,
+                    DirEntry::File(_) => {
+                        return Err(TCError::internal(format!(
+                            "invalid journal entry: {}",
+                            name
+                        )))
+                    }
+                };
+
+                let state = entry.read().await.get_file(STATE);
+                if let Some(state) = state {
+                    let state = state
+                        .read()
+                        .map_ok(|state: FileReadGuard<CacheBlock, Value>| Value::clone(&*state))
+                        .map_err(io_err)
+                        .await?;
+
+                    entries.insert(txn_id, Entry::try_from(state)?);
+                } else {
+                    // the process stopped before this entry was complete
+                    debug!("journal entry {} has no state, ignoring", txn_id);
+                }
+            }
+        }
+
+        Ok(Self {
+            dir,
+            entries: RwLock::new(entries),
+        })
+    }
+
+    /// Return the state of the given transaction, if there is a record of it.
+    pub async fn get(&self, txn_id: &TxnId) -> Option<Entry> {
+        self.entries.read().await.get(txn_id).cloned()
+    }
+
+    /// List the transactions in this journal which are not yet resolved.
+    pub async fn in_doubt(&self) -> Vec<(TxnId, Entry)> {
+        let entries = self.entries.read().await;
+        let mut in_doubt: Vec<(TxnId, Entry)> = entries
+            .iter()
+            .filter(|(_, entry)| !matches!(entry, Entry::Deciding))
+            .map(|(txn_id, entry)| (*txn_id, entry.clone()))
+            .collect();
+
+        in_doubt.sort_by(|(l, _), (r, _)| l.cmp(r));
+        in_doubt
+    }
+
+    /// Record that this cluster is coordinating the given transaction.
+    ///
+    /// This is not written to disk; a coordinator which stops before making a decision
+    /// will answer that the transaction was rolled back.
+    pub async fn begin(&self, txn_id: TxnId) {
+        self.entries.write().await.insert(txn_id, Entry::Deciding);
+    }
+
+    /// Durably record that this cluster is prepared to commit the given `mutations`.
+    pub async fn prepare(
+        &self,
+        txn_id: TxnId,
+        coordinator: Link,
+        participants: HashSet<Link>,
+        mutations: Map<Vec<Mutation>>,
+    ) -> TCResult<()> {
+        let entry = Entry::Prepared {
+            coordinator,
+            participants,
+        };
+
+        self.write(txn_id, entry, Some(mutations)).await
+    }
+
+    /// Durably record the decision to commit the given transaction.
+    ///
+    /// The `mutations` to this cluster must be provided unless they are already prepared.
+    pub async fn commit(
+        &self,
+        txn_id: TxnId,
+        participants: HashSet<Link>,
+        mutations: Option<Map<Vec<Mutation>>>,
+    ) -> TCResult<()> {
+        self.write(txn_id, Entry::Commit { participants }, mutations)
+            .await
+    }
+
+    /// Record that the given `participant` has committed the given transaction,
+    /// discarding the entry once every participant has done so.
+    pub async fn acknowledge(&self, txn_id: TxnId, participant: &Link) -> TCResult<()> {
+        let mut entries = self.entries.write().await;
+
+        let participants = match entries.get_mut(&txn_id) {
+            Some(Entry::Commit { participants }) => {
+                participants.remove(participant);
+                participants.is_empty()
+            }
+            Some(_) => return Err(TCError::conflict()),
+            None => {
+                debug!("no journal entry for {}", txn_id);
+                return Ok(());
+            }
+        };
+
+        if participants {
+            entries.remove(&txn_id);
+            self.delete(txn_id).await
+        } else {
+            let entry = entries.get(&txn_id).expect("journal entry");
+            self.persist(txn_id, entry, None).await
+        }
+    }
+
+    /// Read the mutations prepared for the given transaction, by chain name.
+    pub async fn mutations(&self, txn_id: TxnId) -> TCResult<Map<Vec<Mutation>>> {
+        let entry = self.dir.read().await.get_dir(&txn_id.to_string()).cloned();
+        let entry = entry.ok_or_else(|| TCError::not_found(format!("journal entry {}", txn_id)))?;
+
+        let entry = entry.read().await;
+        let mut mutations = Map::new();
+        for (name, block) in entry.iter() {
+            if !name.ends_with(BLOCK_EXT) {
+                continue;
+            }
+
+            let block = match block {
+                DirEntry::File(block) => block,
+                DirEntry::Dir(_) => {
+                    return Err(TCError::internal(format!(
+                        "invalid journal entry: {}",
+                        name
+                    )))
+                }
+            };
+
+            let chain_name: Id = name[..(name.len() - BLOCK_EXT.len())].parse()?;
+            let block = block
+                .read()
+                .map_ok(|block: FileReadGuard<CacheBlock, ChainBlock>| ChainBlock::clone(&*block))
+                .map_err(io_err)
+                .await?;
+
+            let ops = block.mutations().get(&txn_id).cloned().unwrap_or_default();
+            mutations.insert(chain_name, ops);
+        }
+
+        Ok(mutations)
+    }
+
+    /// Delete the record of the given transaction.
+    pub async fn discard(&self, txn_id: TxnId) -> TCResult<()> {
+        let mut entries = self.entries.write().await;
+        if entries.remove(&txn_id).is_some() {
+            self.delete(txn_id).await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn write(
+        &self,
+        txn_id: TxnId,
+        entry: Entry,
+        mutations: Option<Map<Vec<Mutation>>>,
+    ) -> TCResult<()> {
+        let mut entries = self.entries.write().await;
+        self.persist(txn_id, &entry, mutations).await?;
+        entries.insert(txn_id, entry);
+        Ok(())
+    }
+
+    async fn delete(&self, txn_id: TxnId) -> TCResult<()> {
+        self.dir.write().await.delete(txn_id.to_string());
+        self.dir.sync(false).map_err(io_err).await
+    }
+
+    async fn persist(
+        &self,
+        txn_id: TxnId,
+        entry: &Entry,
+        mutations: Option<Map<Vec<Mutation>>>,
+    ) -> TCResult<()> {
+        debug!("write journal entry for {}", txn_id);
+
+        let record = self
+            .dir
+            .write()
+            .await
+            .get_or_create_dir(txn_id.to_string())
+            .map_err(io_err)?;
+
+        {
+            let mut contents = record.write().await;
+
+            if let Some(mutations) = mutations {
+                for (chain_name, ops) in mutations {
+                    if ops.is_empty() {
+                        continue;
+                    }
+
+                    let mut block = ChainBlock::new(Bytes::new());
+                    for op in ops {
+                        block.append(txn_id, op);
+                    }
+
+                    let name = format!("{}{}", chain_name, BLOCK_EXT);
+                    let file = contents.create_file(name, block, None).map_err(io_err)?;
+
+                    file.sync(true).map_err(io_err).await?;
+                }
+            }
+
+            // the state is written last, so that its presence marks the entry as complete
+            let state = Value::from(entry);
+            let file = if let Some(file) = contents.get_file(STATE) {
+                {
+                    let mut guard: FileWriteGuard<CacheBlock, Value> =
+                        file.write().map_err(io_err).await?;
+
+                    *guard = state;
+                }
+
+                file
+            } else {
+                contents
+                    .create_file(STATE.to_string(), state, None)
+                    .map_err(io_err)?
+            };
+
+            file.sync(true).map_err(io_err).await?;
+        }
+
+        record.sync(false).map_err(io_err).await?;
+        self.dir.sync(false).map_err(io_err).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use tcgeneric::{NetworkTime, TCPathBuf};
+
+    use crate::scalar::Scalar;
+
+    use super::*;
+
+    fn txn_id(nanos: u64) -> TxnId {
+        TxnId::new(NetworkTime::from_nanos(nanos))
+    }
+
+    fn link(port: u16) -> Link {
+        let link = format!("http://127.0.0.1:{}/app", port);
+        link.parse().expect("link")
+    }
+
+    // open the journal stored under `path` with a new cache, the way a restarted host would
+    async fn open(path: &Path) -> Journal {
+        let cache = freqfs::Cache::new(1_000_000, Duration::from_secs(1));
+        let dir = cache.load(path.to_path_buf()).await.expect("cache dir");
+        let dir = fs::Dir::load(dir, txn_id(0)).await.expect("dir");
+        Journal::load(&dir).await.expect("journal")
+    }
+
+    #[tokio::test]
+    async fn test_recover_prepared() {
+        let path = std::env::temp_dir().join(format!("tc-journal-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&path).expect("test dir");
+
+        let prepared = txn_id(1);
+        let coordinator = link(8702);
+        let participant = link(8703);
+        let participants: HashSet<Link> = vec![participant.clone()].into_iter().collect();
+
+        let chain: Id = label("data").into();
+        let mutation = Mutation::Put(
+            TCPathBuf::default(),
+            Value::from(1u64),
+            Scalar::Value(Value::from(2u64)),
+        );
+
+        let mutations: Map<Vec<Mutation>> = vec![(chain.clone(), vec![mutation.clone()])]
+            .into_iter()
+            .collect();
+
+        {
+            let journal = open(&path).await;
+            journal
+                .prepare(
+                    prepared,
+                    coordinator.clone(),
+                    participants.clone(),
+                    mutations,
+                )
+                .await
+                .expect("prepare");
+
+            // the host stops here, before the coordinator's decision arrives
+        }
+
+        // and another transaction was interrupted before its prepared state was written
+        let interrupted = txn_id(2);
+        std::fs::create_dir_all(path.join(DIR_NAME).join(interrupted.to_string()))
+            .expect("incomplete entry");
+
+        let journal = open(&path).await;
+        let in_doubt = journal.in_doubt().await;
+        assert_eq!(in_doubt.len(), 1);
+
+        let (txn, entry) = &in_doubt[0];
+        assert_eq!(txn, &prepared);
+        match entry {
+            Entry::Prepared {
+                coordinator: c,
+                participants: p,
+            } => {
+                assert_eq!(c, &coordinator);
+                assert_eq!(p, &participants);
+            }
+            _ => panic!("expected a prepared journal entry"),
+        }
+
+        let recovered = journal.mutations(prepared).await.expect("mutations");
+        assert!(recovered.get(&chain) == Some(&vec![mutation.clone()]));
+
+        // the coordinator decided to commit
+        journal
+            .commit(prepared, participants, None)
+            .await
+            .expect("commit");
+
+        let journal = open(&path).await;
+        assert!(matches!(
+            journal.get(&prepared).await,
+            Some(Entry::Commit { .. })
+        ));
+
+        let recovered = journal.mutations(prepared).await.expect("mutations");
+        assert!(recovered.get(&chain) == Some(&vec![mutation]));
+
+        journal
+            .acknowledge(prepared, &participant)
+            .await
+            .expect("acknowledge");
+
+        let journal = open(&path).await;
+        assert!(journal.in_doubt().await.is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+//! Dependencies of one [`Cluster`] on the class definitions and ops of another.
+//!
+//! A cluster declares a dependency by defining a member whose value is a [`Link`] to another
+//! cluster. When the dependent cluster is installed, the class definitions exported by the other
+//! cluster at its [`CLASSES`] endpoint are copied into the dependent cluster, so that constructing
+//! an instance of one of them doesn't require a network request. Any other request to a path
+//! under the dependency's name is forwarded to the other cluster, so that an `OpDef` can call the
+//! other cluster's ops by a link relative to its own cluster (e.g. `$self/lib/op`).
+
+use std::fmt;
+
+use log::debug;
+use safecast::TryCastFrom;
+
+use tc_error::*;
+use tc_transact::Transaction;
+use tc_value::{Link, Value};
+use tcgeneric::{label, Id, Label, Map};
+
+use crate::object::InstanceClass;
+use crate::txn::Txn;
+
+/// The name of the endpoint which exports the class definitions of a [`Cluster`](super::Cluster).
+pub const CLASSES: Label = label("classes");
+
+/// A dependency of a [`Cluster`](super::Cluster) on another cluster.
+pub struct Library {
+    link: Link,
+    classes: Map<InstanceClass>,
+}
+
+impl Library {
+    /// Resolve the class definitions of the cluster at the given `link`.
+    pub async fn resolve(txn: &Txn, link: Link) -> TCResult<Self> {
+        debug!("resolve the class definitions of {}", link);
+
+        let source = link.clone().append(CLASSES.into());
+
+        let classes = if link.host().is_none() {
+            let classes = txn.get(source, Value::None).await?;
+            let classes =
+                classes.try_into_map(|s| TCError::bad_request("expected classes but found", s))?;
+
+            classes
+                .into_iter()
+                .map(|(name, class)| {
+                    InstanceClass::try_cast_from(class, |s| {
+                        TCError::bad_request("expected a class but found", s)
+                    })
+                    .map(|class| (name, class))
+                })
+                .collect::<TCResult<Map<InstanceClass>>>()?
+        } else {
+            txn.gateway().fetch(txn.id(), &source, &Value::None).await?
+        };
+
+        Ok(Self { link, classes })
+    }
+
+    /// Borrow the cached definition of the class with the given `name`, if there is one.
+    pub fn class(&self, name: &Id) -> Option<&InstanceClass> {
+        self.classes.get(name)
+    }
+
+    /// Return the [`Link`] to the cluster which this `Library` depends on.
+    pub fn link(&self) -> &Link {
+        &self.link
+    }
+}
+
+impl fmt::Display for Library {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "library {}", self.link)
+    }
+}
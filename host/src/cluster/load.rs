@@ -1,8 +1,10 @@
 use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use log::debug;
+use safecast::TryCastFrom;
 use tokio::sync::RwLock;
 
 use tc_error::*;
@@ -11,13 +13,15 @@ use tc_transact::Transaction;
 use tc_value::{Link, LinkHost, Value};
 use tcgeneric::*;
 
-use crate::chain::{self, Chain, ChainType, Schema};
+use crate::chain::{self, Chain, ChainInstance, ChainType, Schema, Subject};
 use crate::fs;
+use crate::kernel::REGISTRY;
 use crate::object::{InstanceClass, InstanceExt};
-use crate::scalar::{OpRef, Refer, Scalar};
+use crate::scalar::{OpRef, Refer, Scalar, TCRef};
 use crate::txn::{Actor, Txn, TxnId};
 
-use super::Cluster;
+use super::view::{View, ViewDef, VIEW};
+use super::{Cluster, Journal, Library, Status};
 
 /// Load a cluster from the filesystem, or instantiate a new one.
 pub async fn instantiate(
@@ -34,6 +38,8 @@ pub async fn instantiate(
     let mut chain_schema = Map::new();
     let mut cluster_proto = Map::new();
     let mut classes = Map::new();
+    let mut libraries = Map::new();
+    let mut views = Map::new();
 
     for (id, scalar) in proto.into_iter() {
         debug!("Cluster member: {}", scalar);
@@ -44,10 +50,21 @@ pub async fn instantiate(
                 match op_ref {
                     OpRef::Get((class, schema)) => {
                         let classpath = TCPathBuf::try_from(class)?;
-                        let ct = ChainType::from_path(&classpath)
-                            .ok_or_else(|| TCError::bad_request("not a Chain", classpath))?;
+                        if classpath == TCPathBuf::from(VIEW) {
+                            debug!("a materialized view defined by {}", schema);
+                            views.insert(id, ViewDef::from_scalar(schema)?);
+                            continue;
+                        }
+
+                        let ct = ChainType::from_path(&classpath).ok_or_else(|| {
+                            TCError::bad_request(
+                                "expected a BlockChain or SyncChain but found",
+                                classpath,
+                            )
+                        })?;
 
                         debug!("an instance of {} with schema {}", ct, schema);
+                        let schema = resolve_schema(txn, schema).await?;
                         let schema = Schema::from_scalar(schema)?;
                         chain_schema.insert(id, (ct, schema));
                     }
@@ -58,6 +75,18 @@ pub async fn instantiate(
                     other => return Err(TCError::bad_request("expected a Chain but found", other)),
                 }
             }
+            Scalar::Value(Value::Link(dependency)) => {
+                if dependency.path() == link.path() {
+                    return Err(TCError::bad_request(
+                        "a cluster cannot depend on itself",
+                        dependency,
+                    ));
+                }
+
+                debug!("a dependency on {}", dependency);
+                let library = Library::resolve(txn, dependency).await?;
+                libraries.insert(id, library);
+            }
             Scalar::Op(op_def) => {
                 let op_def = if op_def.is_write() {
                     // make sure not to replicate ops internal to this OpDef
@@ -84,7 +113,7 @@ pub async fn instantiate(
             }
             other => {
                 return Err(TCError::bad_request(
-                    "Cluster member must be a Chain (for mutable data), or an immutable OpDef, not",
+                    "Cluster member must be a Chain (for mutable data), a materialized View, an immutable OpDef, or a Link to a dependency, not",
                     other,
                 ))
             }
@@ -92,7 +121,7 @@ pub async fn instantiate(
     }
 
     let txn_id = *txn.id();
-    let dir = get_or_create_dir(data_dir, txn_id, link.path()).await?;
+    let dir = get_or_create_dir(data_dir.clone(), txn_id, link.path()).await?;
     let mut replicas = HashSet::new();
     replicas.insert((host, link.path().clone()).into());
 
@@ -105,17 +134,52 @@ pub async fn instantiate(
         chains.insert(id, chain);
     }
 
+    let mut materialized = Map::<View>::new();
+    for (id, def) in views.into_iter() {
+        debug!("load materialized view {} of {}", id, def.source());
+
+        let source = match chains.get(def.source()).map(|chain| chain.subject()) {
+            Some(Subject::Table(table)) => table,
+            _ => {
+                return Err(TCError::bad_request(
+                    "a materialized view requires a Table chain as its source, not",
+                    def.source(),
+                ))
+            }
+        };
+
+        let dir = dir.get_or_create_dir(txn_id, id.clone()).await?;
+        let view = View::load(txn, def, dir, source).await?;
+        materialized.insert(id, view);
+    }
+
+    let journal = Journal::load(&dir).await?;
+
     let actor_id = Value::from(Link::default());
 
+    // a cluster with no canonical host has no other replicas to catch up with
+    let status = if link.host().is_none() {
+        Status::Online
+    } else {
+        Status::Offline
+    };
+
     let cluster = Cluster {
         link: link.clone(),
         actor: Arc::new(Actor::new(actor_id)),
         chains,
+        views: materialized,
         classes,
+        libraries,
         confirmed: RwLock::new(txn_id),
+        created: txn_id,
+        data_dir,
+        journal,
         owned: RwLock::new(HashMap::new()),
         installed: TxnLock::new(format!("Cluster {} installed deps", link), HashMap::new()),
         replicas: TxnLock::new(format!("Cluster {} replicas", link), replicas),
+        status: std::sync::RwLock::new(status),
+        deprecated: AtomicBool::new(false),
     };
 
     let class = InstanceClass::new(Some(link), cluster_proto.into());
@@ -123,6 +187,37 @@ pub async fn instantiate(
     Ok(InstanceExt::new(cluster, class))
 }
 
+/// If the given chain `schema` references a schema in a [`Registry`](crate::kernel::Registry)
+/// by link, replace the link with the registered schema.
+async fn resolve_schema(txn: &Txn, schema: Scalar) -> TCResult<Scalar> {
+    let (class, link) = match schema {
+        Scalar::Ref(tc_ref) => match *tc_ref {
+            TCRef::Op(OpRef::Get((class, Scalar::Value(Value::Link(link)))))
+                if link.path().len() > 1 && link.path()[0] == REGISTRY[0] =>
+            {
+                (class, link)
+            }
+            tc_ref => return Ok(Scalar::Ref(Box::new(tc_ref))),
+        },
+        other => return Ok(other),
+    };
+
+    debug!("resolve schema {}", link);
+
+    let schema = if link.host().is_none() {
+        let schema = txn.get(link, Value::None).await?;
+        Scalar::try_cast_from(schema, |s| {
+            TCError::bad_request("expected a schema but found", s)
+        })?
+    } else {
+        txn.gateway().fetch(txn.id(), &link, &Value::None).await?
+    };
+
+    Ok(Scalar::Ref(Box::new(TCRef::Op(OpRef::Get((
+        class, schema,
+    ))))))
+}
+
 async fn get_or_create_dir(
     data_dir: fs::Dir,
     txn_id: TxnId,
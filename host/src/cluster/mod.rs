@@ -5,38 +5,65 @@ use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::iter::FromIterator;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use futures::future::{join_all, try_join_all, Future, FutureExt};
+use futures::future::{join_all, try_join_all, Future, FutureExt, TryFutureExt};
 use futures::{join, StreamExt};
-use log::{debug, info, warn};
+use log::{debug, error, info, warn};
 use safecast::TryCastFrom;
 use tokio::sync::RwLock;
 
 use tc_error::*;
+use tc_table::{ColumnAccess, ColumnMask, PrivacyPolicy};
 use tc_transact::lock::TxnLock;
 use tc_transact::{Transact, Transaction};
 use tc_value::{Link, Value};
 use tcgeneric::*;
 
-use crate::chain::{Chain, ChainInstance};
+use crate::chain::{Chain, ChainInstance, Mutation, Subject};
+use crate::collection::TableIndex;
+use crate::fs;
+use crate::metrics;
 use crate::object::InstanceClass;
 use crate::scalar::{Executor, OpDef, Scalar};
 use crate::state::{State, ToState};
 use crate::txn::{Actor, Scope, Txn, TxnId};
 
+use journal::{Entry, Journal};
 use owner::Owner;
 
 use futures::stream::FuturesUnordered;
+pub use library::{Library, CLASSES};
 pub use load::instantiate;
+pub use openapi::{openapi, openapi_host, META, OPENAPI};
+pub use verify::{BOUNDS, VERIFY};
+pub use view::View;
 
+mod journal;
+mod library;
 mod load;
+mod openapi;
 mod owner;
+mod verify;
+mod view;
+
+/// The name of the endpoint which serves the commit decision of a [`Cluster`] to the
+/// participants in a distributed transaction.
+pub const JOURNAL: Label = label("journal");
 
 /// The name of the endpoint which serves a [`Link`] to each of this [`Cluster`]'s replicas.
 pub const REPLICAS: Label = label("replicas");
 
+/// The name of the endpoint which serves a consistent copy of every [`Chain`] in a [`Cluster`],
+/// used by a new replica to catch up with the others.
+pub const REPLICATE: Label = label("replicate");
+
+/// The name of the commit parameter which asks a participant to prepare a distributed transaction.
+pub const PREPARE: Label = label("prepare");
+
 /// The [`Class`] of a [`Cluster`].
 pub struct ClusterType;
 
@@ -48,16 +75,43 @@ impl fmt::Display for ClusterType {
     }
 }
 
+/// Whether a replica of a [`Cluster`] is ready to serve requests.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Status {
+    /// This replica has not yet started to catch up with the others.
+    Offline,
+
+    /// This replica is copying the state of the others in the given transaction.
+    CatchingUp(TxnId),
+
+    /// This replica is caught up with the others.
+    Online,
+}
+
+/// The in-memory state of a [`Cluster`] at a savepoint within a transaction.
+#[derive(Clone)]
+pub struct ClusterSavepoint {
+    installed: Option<HashMap<Link, HashSet<Scope>>>,
+    replicas: Option<HashSet<Link>>,
+}
+
 /// The data structure responsible for maintaining consensus per-transaction.
 pub struct Cluster {
     link: Link,
     actor: Arc<Actor>,
     chains: Map<Chain>,
+    views: Map<View>,
     classes: Map<InstanceClass>,
+    libraries: Map<Library>,
     confirmed: RwLock<TxnId>,
+    created: TxnId,
+    data_dir: fs::Dir,
+    journal: Journal,
     owned: RwLock<HashMap<TxnId, Owner>>,
     installed: TxnLock<HashMap<Link, HashSet<Scope>>>,
     replicas: TxnLock<HashSet<Link>>,
+    status: std::sync::RwLock<Status>,
+    deprecated: AtomicBool,
 }
 
 impl Cluster {
@@ -66,11 +120,26 @@ impl Cluster {
         self.chains.get(name)
     }
 
+    /// Borrow one of this cluster's materialized [`View`]s.
+    pub fn view(&self, name: &Id) -> Option<&View> {
+        self.views.get(name)
+    }
+
     /// Borrow an [`InstanceClass`], if there is one defined with the given name.
     pub fn class(&self, name: &Id) -> Option<&InstanceClass> {
         self.classes.get(name)
     }
 
+    /// Iterate over the [`InstanceClass`]es defined by this cluster.
+    pub fn classes(&self) -> impl Iterator<Item = (&Id, &InstanceClass)> {
+        self.classes.iter()
+    }
+
+    /// Borrow a [`Library`], if this cluster depends on one with the given name.
+    pub fn library(&self, name: &Id) -> Option<&Library> {
+        self.libraries.get(name)
+    }
+
     /// Borrow the public key of this cluster.
     pub fn public_key(&self) -> &[u8] {
         self.actor.public_key().as_bytes()
@@ -88,7 +157,60 @@ impl Cluster {
 
     /// Return the names of the members of this cluster.
     pub fn ns(&self) -> impl Iterator<Item = &Id> {
-        self.chains.keys().chain(self.classes.keys())
+        self.chains
+            .keys()
+            .chain(self.views.keys())
+            .chain(self.classes.keys())
+            .chain(self.libraries.keys())
+    }
+
+    /// Return `true` if this replica is caught up with the others and ready to serve requests.
+    pub fn is_online(&self) -> bool {
+        *self.status.read().expect("cluster status") == Status::Online
+    }
+
+    /// Return how far the data of this replica may lag behind the other replicas: zero if it's
+    /// online, otherwise the time elapsed since the last transaction it committed.
+    pub async fn replication_lag(&self) -> Duration {
+        if self.is_online() {
+            return Duration::default();
+        }
+
+        let last_commit = self.confirmed.read().await.time();
+        let now = NetworkTime::now();
+        Duration::from_nanos(now.as_nanos().saturating_sub(last_commit.as_nanos()))
+    }
+
+    /// Return `true` if this version of the cluster is deprecated in favor of another version.
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated.load(Ordering::Relaxed)
+    }
+
+    /// Mark this version of the cluster as deprecated, or not.
+    pub fn set_deprecated(&self, deprecated: bool) {
+        self.deprecated.store(deprecated, Ordering::Relaxed)
+    }
+
+    /// Return `true` if this cluster owns a transaction which has not yet committed or rolled back.
+    pub async fn has_pending_txns(&self) -> bool {
+        !self.owned.read().await.is_empty()
+    }
+
+    /// Return an error if this replica cannot yet serve a request in the given transaction,
+    /// because it has not yet caught up with the others.
+    pub fn check_online(&self, txn_id: &TxnId) -> TCResult<()> {
+        match *self.status.read().expect("cluster status") {
+            Status::Online => Ok(()),
+            Status::CatchingUp(catch_up) if &catch_up == txn_id => Ok(()),
+            _ => Err(TCError::new(
+                ErrorType::Conflict,
+                format!("{} is still catching up with its replicas", self),
+            )),
+        }
+    }
+
+    fn set_status(&self, status: Status) {
+        *self.status.write().expect("cluster status") = status;
     }
 
     /// Iterate over a list of replicas of this cluster.
@@ -147,6 +269,97 @@ impl Cluster {
         )))
     }
 
+    /// Return `Unauthorized` unless the given [`Txn`] was claimed by a replica of this cluster.
+    pub async fn authorize_replica(&self, txn: &Txn) -> TCResult<()> {
+        let replicas = self.replicas.read(*txn.id()).await?;
+
+        for (host, actor_id, _scopes) in txn.request().scopes().iter() {
+            if actor_id.is_none() && host.path() == self.link.path() && replicas.contains(host) {
+                debug!("{} is a replica of {}", host, self);
+                return Ok(());
+            }
+        }
+
+        Err(TCError::unauthorized(format!(
+            "only a replica of {} can read its state",
+            self
+        )))
+    }
+
+    /// Return `Unauthorized` unless the request may read every column and every row of the given
+    /// [`Chain`].
+    pub async fn authorize_chain(&self, txn: &Txn, chain: &Chain) -> TCResult<()> {
+        let access = chain.subject().column_access();
+        self.authorize_columns(txn, &access).await?;
+
+        let policies = chain.subject().privacy();
+        self.authorize_privacy(txn, &policies).await
+    }
+
+    /// Return `true` if a trusted caller authorized the given `scope`.
+    pub async fn is_authorized(&self, txn: &Txn, scope: &Scope) -> TCResult<bool> {
+        match self.authorize(txn, scope).await {
+            Ok(()) => Ok(true),
+            Err(cause) if cause.code() == ErrorType::Unauthorized => Ok(false),
+            Err(cause) => Err(cause),
+        }
+    }
+
+    /// Return the columns among the given [`ColumnAccess`] rules which the request may not read,
+    /// and how to hide each of them.
+    pub async fn column_masks(
+        &self,
+        txn: &Txn,
+        access: &[ColumnAccess],
+    ) -> TCResult<HashMap<Id, ColumnMask>> {
+        let mut authorized: HashMap<&Scope, bool> = HashMap::new();
+        let mut masks = HashMap::new();
+
+        for rule in access {
+            let allowed = if let Some(allowed) = authorized.get(&rule.scope) {
+                *allowed
+            } else {
+                let allowed = self.is_authorized(txn, &rule.scope).await?;
+
+                authorized.insert(&rule.scope, allowed);
+                allowed
+            };
+
+            if !allowed {
+                debug!("mask column {} ({})", rule.column, rule.mask);
+                masks.insert(rule.column.clone(), rule.mask);
+            }
+        }
+
+        Ok(masks)
+    }
+
+    /// Return `Unauthorized` unless the request may read every column restricted by the given
+    /// [`ColumnAccess`] rules (e.g. in order to read a whole [`Chain`] rather than one of its tables).
+    pub async fn authorize_columns(&self, txn: &Txn, access: &[ColumnAccess]) -> TCResult<()> {
+        let masks = self.column_masks(txn, access).await?;
+
+        if masks.is_empty() {
+            Ok(())
+        } else {
+            let columns: Vec<String> = masks.keys().map(|name| name.to_string()).collect();
+            Err(TCError::unauthorized(format!(
+                "not authorized to read column(s) {}",
+                columns.join(", ")
+            )))
+        }
+    }
+
+    /// Return an error if the request lacks the scope required to read the individual rows of a
+    /// table with any of the given [`PrivacyPolicy`]s.
+    pub async fn authorize_privacy(&self, txn: &Txn, policies: &[PrivacyPolicy]) -> TCResult<()> {
+        for policy in policies {
+            self.authorize(txn, &policy.scope).await?;
+        }
+
+        Ok(())
+    }
+
     /// Grant the given `scope` to the `txn` and use it to resolve the given `OpRef`.
     pub async fn grant(
         &self,
@@ -211,9 +424,12 @@ impl Cluster {
         if replica == self_link {
             if self.link.host().is_none() || self.link == self_link {
                 debug!("{} cannot replicate itself", self);
+                self.set_status(Status::Online);
                 return Ok(());
             }
 
+            self.set_status(Status::CatchingUp(*txn.id()));
+
             debug!(
                 "{} replica at {} got add request for self: {}",
                 self, replica, self_link
@@ -248,6 +464,9 @@ impl Cluster {
             }
 
             self.replicate(txn).await?;
+
+            // replicated writes may arrive as soon as the other replicas commit this transaction
+            self.set_status(Status::Online);
         } else {
             debug!("add replica {}", replica);
             (*self.replicas.write(*txn.id()).await?).insert(replica);
@@ -284,16 +503,57 @@ impl Cluster {
         }
     }
 
+    /// Return every [`Chain`] in this cluster by name, to be read as of the requesting transaction.
+    ///
+    /// The chains are not masked, so only a replica of this cluster may read them.
+    pub async fn state(&self, txn: &Txn) -> TCResult<Map<State>> {
+        self.authorize_replica(txn).await?;
+
+        let state = self
+            .chains
+            .iter()
+            .map(|(name, chain)| (name.clone(), State::from(chain.clone())))
+            .collect();
+
+        Ok(state)
+    }
+
     async fn replicate(&self, txn: &Txn) -> TCResult<()> {
-        let replication = self.chains.iter().map(|(name, chain)| {
-            let mut path = self.link.path().to_vec();
-            path.push(name.clone());
+        let source = self.link.clone().append(REPLICATE.into());
+        let mut state = txn
+            .get(source, Value::None)
+            .await?
+            .try_into_map(|s| TCError::bad_gateway(format!("invalid cluster state: {}", s)))?;
+
+        let mut replication = Vec::with_capacity(self.chains.len());
+        for (name, chain) in self.chains.iter() {
+            let source = match state.remove(name) {
+                Some(State::Chain(source)) => source,
+                Some(other) => {
+                    return Err(TCError::bad_gateway(format!(
+                        "expected the Chain {} but found {}",
+                        name, other
+                    )))
+                }
+                None => {
+                    return Err(TCError::bad_gateway(format!(
+                        "{} has no Chain {}",
+                        self.link, name
+                    )))
+                }
+            };
 
-            chain.replicate(txn, self.link.clone().append(name.clone()))
-        });
+            replication.push(chain.replicate(txn, source));
+        }
 
         try_join_all(replication).await?;
 
+        // the chains were copied wholesale, so there are no mutations to refresh the views with
+        for view in self.views.values() {
+            let source = self.view_source(view)?;
+            view.rebuild(txn, source).await?;
+        }
+
         Ok(())
     }
 
@@ -375,38 +635,121 @@ impl Cluster {
         Ok(())
     }
 
+    /// Commit the given [`Txn`] as its coordinator.
+    ///
+    /// If there are other participants in the transaction, this uses a two-phase commit protocol:
+    /// every participant must prepare to commit durably before the decision to commit is written
+    /// to this cluster's journal, so that a participant which stops before committing can recover.
     pub async fn distribute_commit(&self, txn: &Txn) -> TCResult<()> {
-        let replicas = self.replicas.read(*txn.id()).await?;
+        let txn_id = *txn.id();
 
-        if let Some(owner) = self.owned.read().await.get(txn.id()) {
-            owner.commit(txn).await?;
+        if let Err(cause) = self.refresh_views(txn).await {
+            warn!(
+                "{} failed to refresh its views in {}: {}",
+                self, txn_id, cause
+            );
+            self.distribute_rollback(txn).await;
+            return Err(cause);
         }
 
-        self.write_ahead(txn.id()).await;
+        let participants = self.participants(txn).await?;
 
-        let self_link = txn.link(self.link.path().clone());
-        let mut replica_commits = FuturesUnordered::from_iter(
-            replicas
-                .iter()
-                .filter(|replica| *replica != &self_link)
-                .map(|replica| {
-                    debug!("commit replica {}...", replica);
-                    txn.post(replica.clone(), State::Map(Map::default()))
-                }),
-        );
+        if participants.is_empty() {
+            self.write_ahead(&txn_id).await;
+            self.commit(&txn_id).await;
+            return Ok(());
+        }
 
-        while let Some(result) = replica_commits.next().await {
-            match result {
-                Ok(_) => {}
-                Err(cause) => log::error!("commit failure: {}", cause),
-            }
+        self.journal.begin(txn_id).await;
+
+        let decision = async {
+            self.prepare_participants(txn, &participants).await?;
+
+            let mutations = self.pending(txn_id).await?;
+            self.journal
+                .commit(txn_id, participants.clone(), Some(mutations))
+                .await
+        };
+
+        if let Err(cause) = decision.await {
+            warn!("{} failed to prepare {}: {}", self, txn_id, cause);
+            self.distribute_rollback(txn).await;
+            return Err(cause);
         }
 
-        self.commit(txn.id()).await;
+        self.complete(txn, participants).await;
 
         Ok(())
     }
 
+    /// Prepare to commit the given [`Txn`] at the direction of the given `coordinator`.
+    pub async fn prepare(&self, txn: &Txn, coordinator: Link) -> TCResult<()> {
+        let txn_id = *txn.id();
+        debug!("{} preparing {} for {}", self, txn_id, coordinator);
+
+        self.refresh_views(txn).await?;
+
+        let participants = self.participants(txn).await?;
+        let mutations = self.pending(txn_id).await?;
+
+        self.journal
+            .prepare(txn_id, coordinator, participants.clone(), mutations)
+            .await?;
+
+        self.prepare_participants(txn, &participants).await
+    }
+
+    /// Return `true` if this cluster has prepared to commit the given transaction.
+    pub async fn is_prepared(&self, txn_id: &TxnId) -> bool {
+        let entry = self.journal.get(txn_id).await;
+        matches!(entry, Some(Entry::Prepared { .. }))
+    }
+
+    /// Commit a prepared [`Txn`] at the direction of its coordinator.
+    pub async fn commit_prepared(&self, txn: &Txn) -> TCResult<()> {
+        let txn_id = *txn.id();
+
+        let participants = match self.journal.get(&txn_id).await {
+            Some(Entry::Prepared { participants, .. }) => participants,
+            _ => {
+                return Err(TCError::bad_request(
+                    format!("{} has not prepared transaction", self),
+                    txn_id,
+                ))
+            }
+        };
+
+        if participants.is_empty() {
+            self.write_ahead(&txn_id).await;
+            self.commit(&txn_id).await;
+            self.journal.discard(txn_id).await
+        } else {
+            self.journal
+                .commit(txn_id, participants.clone(), None)
+                .await?;
+
+            self.complete(txn, participants).await;
+            Ok(())
+        }
+    }
+
+    /// Return `true` if the coordinator of the given transaction decided to commit it.
+    ///
+    /// Returns a conflict error if the decision has not been made yet.
+    pub async fn decision(&self, txn_id: &TxnId) -> TCResult<bool> {
+        match self.journal.get(txn_id).await {
+            Some(Entry::Commit { .. }) => Ok(true),
+            Some(Entry::Deciding) | Some(Entry::Prepared { .. }) => Err(TCError::conflict()),
+            None => Ok(false),
+        }
+    }
+
+    /// Record that the given `participant` has committed the given transaction.
+    pub async fn acknowledge(&self, txn_id: TxnId, participant: &Link) -> TCResult<()> {
+        debug!("{} acknowledged commit of {}", participant, txn_id);
+        self.journal.acknowledge(txn_id, participant).await
+    }
+
     pub async fn distribute_rollback(&self, txn: &Txn) {
         let replicas = self.replicas.read(*txn.id()).await;
 
@@ -425,12 +768,282 @@ impl Cluster {
             .await;
         }
 
-        self.finalize(txn.id()).await;
+        self.rollback(txn.id()).await;
+    }
+
+    /// Roll back the given transaction at the direction of its coordinator.
+    pub async fn rollback(&self, txn_id: &TxnId) {
+        if let Err(cause) = self.journal.discard(*txn_id).await {
+            error!(
+                "{} failed to discard journal entry for {}: {}",
+                self, txn_id, cause
+            );
+        }
+
+        self.finalize(txn_id).await;
+    }
+
+    /// Save the in-memory state of this `Cluster` as of the given [`TxnId`],
+    /// to roll back to with [`Cluster::rollback_to`].
+    pub fn savepoint(&self, txn_id: TxnId) -> TCResult<ClusterSavepoint> {
+        let installed = if self.installed.is_pending(&txn_id) {
+            self.installed.savepoint(txn_id)?
+        } else {
+            None
+        };
+
+        let replicas = if self.replicas.is_pending(&txn_id) {
+            self.replicas.savepoint(txn_id)?
+        } else {
+            None
+        };
+
+        Ok(ClusterSavepoint {
+            installed,
+            replicas,
+        })
+    }
+
+    /// Discard any changes to the in-memory state of this `Cluster` made at the given [`TxnId`]
+    /// since the `savepoint`, without rolling back the transaction.
+    pub fn rollback_to(&self, txn_id: TxnId, savepoint: ClusterSavepoint) -> TCResult<()> {
+        if self.installed.is_pending(&txn_id) {
+            self.installed.rollback(txn_id, savepoint.installed)?;
+        }
+
+        if self.replicas.is_pending(&txn_id) {
+            self.replicas.rollback(txn_id, savepoint.replicas)?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolve any transactions left in doubt when this host last stopped,
+    /// by asking their coordinators for a decision, and commit any recovered data.
+    pub async fn recover(&self, txn: &Txn) -> TCResult<()> {
+        let in_doubt = self.journal.in_doubt().await;
+        if in_doubt.is_empty() {
+            return Ok(());
+        }
+
+        let txn_id = *txn.id();
+        let self_link = txn.link(self.link.path().clone());
+
+        for (prepared, entry) in in_doubt {
+            match entry {
+                Entry::Prepared {
+                    coordinator,
+                    participants,
+                } => {
+                    let source = coordinator.clone().append(JOURNAL.into());
+                    let key = Value::String(prepared.to_string().into());
+
+                    let decision = txn.get(source, key).await.and_then(|decision| {
+                        bool::try_cast_from(decision, |s| {
+                            TCError::bad_request("invalid commit decision", s)
+                        })
+                    });
+
+                    match decision {
+                        Ok(true) => {
+                            info!("{} recovering committed transaction {}", self, prepared);
+
+                            self.replay(txn, prepared).await?;
+
+                            if participants.is_empty() {
+                                self.journal.discard(prepared).await?;
+                            } else {
+                                self.journal.commit(prepared, participants, None).await?;
+                            }
+
+                            let ack = Value::Tuple(
+                                vec![
+                                    Value::String(prepared.to_string().into()),
+                                    self_link.clone().into(),
+                                ]
+                                .into(),
+                            );
+
+                            let target = coordinator.clone().append(JOURNAL.into());
+                            if let Err(cause) = txn.delete(target, ack).await {
+                                warn!(
+                                    "{} could not acknowledge commit of {} to {}: {}",
+                                    self, prepared, coordinator, cause
+                                );
+                            }
+                        }
+                        Ok(false) => {
+                            info!("{} discarding rolled-back transaction {}", self, prepared);
+                            self.journal.discard(prepared).await?;
+                        }
+                        Err(cause) => warn!(
+                            "{} could not resolve in-doubt transaction {} with {}: {}",
+                            self, prepared, coordinator, cause
+                        ),
+                    }
+                }
+                Entry::Commit { .. } => {
+                    // the participants will ask for this decision when they recover
+                    info!("{} recovering committed transaction {}", self, prepared);
+                    self.replay(txn, prepared).await?;
+                }
+                Entry::Deciding => unreachable!("undecided transaction in journal"),
+            }
+        }
+
+        self.write_ahead(&txn_id).await;
+        self.commit(&txn_id).await;
+
+        Ok(())
+    }
+
+    /// Fold all but the latest `retain` blocks of each chain in this cluster into a snapshot.
+    pub async fn compact(&self, txn: &Txn, retain: u64) -> TCResult<()> {
+        let txn_id = *txn.id();
+
+        let compacted = try_join_all(self.chains.values().map(|chain| chain.compact(txn, retain)));
+        let compacted = match compacted.await {
+            Ok(compacted) => compacted,
+            Err(cause) => {
+                self.rollback(&txn_id).await;
+                return Err(cause);
+            }
+        };
+
+        if compacted.into_iter().any(|compacted| compacted) {
+            info!("{} compacted its chains", self);
+            self.write_ahead(&txn_id).await;
+            self.commit(&txn_id).await;
+        }
+
+        Ok(())
     }
 
     pub async fn write_ahead(&self, txn_id: &TxnId) {
         join_all(self.chains.values().map(|chain| chain.write_ahead(txn_id))).await;
     }
+
+    /// Update each materialized [`View`] in this cluster to reflect the writes to its source
+    /// table in the given [`Txn`]. This must be called before the transaction commits.
+    pub async fn refresh_views(&self, txn: &Txn) -> TCResult<()> {
+        for view in self.views.values() {
+            let chain = self.chains.get(view.source()).expect("view source");
+            let source = self.view_source(view)?;
+            let mutations = chain.prepare(*txn.id()).await?;
+            view.refresh(txn, source, &mutations).await?;
+        }
+
+        Ok(())
+    }
+
+    fn view_source(&self, view: &View) -> TCResult<&TableIndex> {
+        match self.chains.get(view.source()).map(|chain| chain.subject()) {
+            Some(Subject::Table(table)) => Ok(table),
+            _ => Err(TCError::internal(format!(
+                "{} has no Table {} to materialize",
+                self,
+                view.source()
+            ))),
+        }
+    }
+
+    async fn participants(&self, txn: &Txn) -> TCResult<HashSet<Link>> {
+        let mut participants = HashSet::new();
+
+        if txn.is_leader(self.path()) {
+            let self_link = txn.link(self.link.path().clone());
+            let replicas = self.replicas.read(*txn.id()).await?;
+            participants.extend(
+                replicas
+                    .iter()
+                    .filter(|replica| *replica != &self_link)
+                    .cloned(),
+            );
+        }
+
+        if let Some(owner) = self.owned.read().await.get(txn.id()) {
+            participants.extend(owner.mutated().await);
+        }
+
+        Ok(participants)
+    }
+
+    async fn pending(&self, txn_id: TxnId) -> TCResult<Map<Vec<Mutation>>> {
+        let pending = try_join_all(self.chains.iter().map(|(name, chain)| {
+            chain
+                .prepare(txn_id)
+                .map_ok(move |mutations| (name.clone(), mutations))
+        }))
+        .await?;
+
+        Ok(pending.into_iter().collect())
+    }
+
+    async fn prepare_participants(&self, txn: &Txn, participants: &HashSet<Link>) -> TCResult<()> {
+        let self_link = txn.link(self.link.path().clone());
+        let params: Map<State> = vec![(PREPARE.into(), State::from(Value::from(self_link)))]
+            .into_iter()
+            .collect();
+
+        try_join_all(participants.iter().map(|participant| {
+            debug!("prepare participant {}...", participant);
+            txn.post(participant.clone(), State::Map(params.clone()))
+        }))
+        .await?;
+
+        Ok(())
+    }
+
+    async fn complete(&self, txn: &Txn, participants: HashSet<Link>) {
+        let txn_id = *txn.id();
+
+        self.write_ahead(&txn_id).await;
+
+        let mut commits =
+            FuturesUnordered::from_iter(participants.into_iter().map(|participant| {
+                debug!("commit participant {}...", participant);
+                txn.post(participant.clone(), State::Map(Map::default()))
+                    .map(|result| (participant, result))
+            }));
+
+        while let Some((participant, result)) = commits.next().await {
+            match result {
+                Ok(_) => {
+                    if let Err(cause) = self.journal.acknowledge(txn_id, &participant).await {
+                        error!("failed to update journal for {}: {}", txn_id, cause);
+                    }
+                }
+                Err(cause) => warn!(
+                    "participant {} failed to commit {} and must recover: {}",
+                    participant, txn_id, cause
+                ),
+            }
+        }
+
+        self.commit(&txn_id).await;
+    }
+
+    async fn replay(&self, txn: &Txn, prepared: TxnId) -> TCResult<()> {
+        let mutations = self.journal.mutations(prepared).await?;
+
+        for (name, mutations) in mutations {
+            let chain = self.chains.get(&name).ok_or_else(|| {
+                TCError::internal(format!(
+                    "journal entry for {} has mutations of unknown chain {}",
+                    prepared, name
+                ))
+            })?;
+
+            chain.recover(txn, prepared, mutations.to_vec()).await?;
+
+            for view in self.views.values().filter(|view| view.source() == &name) {
+                let source = self.view_source(view)?;
+                view.refresh(txn, source, &mutations).await?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Eq for Cluster {}
@@ -458,6 +1071,10 @@ impl Instance for Cluster {
 #[async_trait]
 impl Transact for Cluster {
     async fn commit(&self, txn_id: &TxnId) {
+        // don't write to disk while a backup is in progress
+        let _permit = fs::commit_permit().await;
+
+        let start = Instant::now();
         let mut confirmed = self.confirmed.write().await;
         {
             debug!(
@@ -467,8 +1084,14 @@ impl Transact for Cluster {
         }
 
         join_all(self.chains.values().map(|chain| chain.commit(txn_id))).await;
+        join_all(self.views.values().map(|view| view.commit(txn_id))).await;
         join!(self.installed.commit(txn_id), self.replicas.commit(txn_id));
 
+        if txn_id == &self.created {
+            // this cluster was installed in this transaction, so its directory is new
+            self.data_dir.commit_subdir(txn_id, self.link.path()).await;
+        }
+
         {
             debug!(
                 "replicas after commit: {}",
@@ -479,10 +1102,13 @@ impl Transact for Cluster {
         if txn_id > &*confirmed {
             *confirmed = *txn_id;
         }
+
+        metrics::record_commit(start.elapsed());
     }
 
     async fn finalize(&self, txn_id: &TxnId) {
         join_all(self.chains.values().map(|chain| chain.finalize(txn_id))).await;
+        join_all(self.views.values().map(|view| view.finalize(txn_id))).await;
         self.owned.write().await.remove(txn_id);
         join!(
             self.installed.finalize(txn_id),
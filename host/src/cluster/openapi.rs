@@ -0,0 +1,505 @@
+//! Describe a hosted [`Cluster`] as an OpenAPI document, for use with standard API tooling.
+//!
+//! Each cluster serves its own document at `<cluster path>/-/openapi.json`, and the host serves a
+//! document describing all of its clusters at `/-/openapi.json`. The data of each chain is
+//! described as a JSON schema in the `components` of the document, and referenced from the chain's
+//! path by its `x-tc-schema` extension.
+
+use serde_json::{json, Map as JsonMap, Value as Json};
+
+use tc_btree::{BTreeInstance, Column};
+use tc_table::TableInstance;
+#[cfg(feature = "tc-tensor")]
+use tc_transact::fs::Persist;
+use tc_value::{LinkHost, NumberType, ValueType};
+use tcgeneric::*;
+
+use crate::chain::{ChainInstance, Subject};
+use crate::object::InstanceExt;
+use crate::scalar::{OpDef, Scalar, ScalarType, Signature};
+use crate::state::StateType;
+
+use super::{Cluster, JOURNAL, REPLICAS, REPLICATE, VERIFY};
+
+/// The name of the endpoint which serves the OpenAPI description of a [`Cluster`].
+pub const OPENAPI: Label = label("openapi.json");
+
+/// The path segment under which a [`Cluster`], or the host itself, serves its metadata.
+pub const META: Label = label("-");
+
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Construct an OpenAPI (JSON) document describing the routes of the given `cluster`.
+///
+/// Clusters without a host of their own are described as served by `host`.
+pub fn openapi(host: &LinkHost, cluster: &InstanceExt<Cluster>) -> Json {
+    let root = TCPath::from(cluster.path()).to_string();
+    let server = cluster.link().host().as_ref().unwrap_or(host);
+
+    let mut paths = JsonMap::new();
+    let mut schemas = JsonMap::new();
+    describe(cluster, &mut paths, &mut schemas);
+    document(&root, server, paths, schemas)
+}
+
+/// Construct an OpenAPI (JSON) document describing the routes of every one of the given
+/// `clusters`, as served by `host`.
+pub fn openapi_host<'a, I>(host: &LinkHost, clusters: I) -> Json
+where
+    I: IntoIterator<Item = &'a InstanceExt<Cluster>>,
+{
+    let mut paths = JsonMap::new();
+    let mut schemas = JsonMap::new();
+    for cluster in clusters {
+        describe(cluster, &mut paths, &mut schemas);
+    }
+
+    document(&host.to_string(), host, paths, schemas)
+}
+
+fn document(
+    title: &str,
+    server: &LinkHost,
+    paths: JsonMap<String, Json>,
+    schemas: JsonMap<String, Json>,
+) -> Json {
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": title,
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": server.to_string() }],
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+fn describe(
+    cluster: &InstanceExt<Cluster>,
+    described: &mut JsonMap<String, Json>,
+    schemas: &mut JsonMap<String, Json>,
+) {
+    let root = TCPath::from(cluster.path()).to_string();
+    let mut paths = JsonMap::new();
+
+    paths.insert(
+        root.clone(),
+        json!({
+            "get": operation(
+                "cluster",
+                "Read the public key of this cluster, or one of its chains by name",
+                vec![key_param("key", false)],
+                None,
+            ),
+        }),
+    );
+
+    paths.insert(
+        format!("{}/authorize", root),
+        json!({
+            "get": operation(
+                "cluster",
+                "Check that the request is authorized with the given scope",
+                vec![key_param("key", true)],
+                None,
+            ),
+        }),
+    );
+
+    paths.insert(
+        format!("{}/grant", root),
+        json!({
+            "post": operation(
+                "cluster",
+                "Execute an op with the given authorization scope",
+                vec![],
+                Some(params_body(&["scope", "op"], &["context"])),
+            ),
+        }),
+    );
+
+    paths.insert(
+        format!("{}/install", root),
+        json!({
+            "put": operation(
+                "cluster",
+                "Install a dependency with the given authorization scopes",
+                vec![key_param("key", true)],
+                Some(value_body("scopes")),
+            ),
+        }),
+    );
+
+    paths.insert(
+        format!("{}/{}", root, REPLICAS),
+        json!({
+            "get": operation("cluster", "List the replicas of this cluster", vec![], None),
+            "put": operation(
+                "cluster",
+                "Add a replica of this cluster",
+                vec![],
+                Some(value_body("link")),
+            ),
+            "delete": operation(
+                "cluster",
+                "Remove replicas of this cluster",
+                vec![key_param("key", true)],
+                None,
+            ),
+        }),
+    );
+
+    paths.insert(
+        format!("{}/{}", root, REPLICATE),
+        json!({
+            "get": operation(
+                "cluster",
+                "Read every chain in this cluster, to bring a new replica up to date",
+                vec![],
+                None,
+            ),
+        }),
+    );
+
+    paths.insert(
+        format!("{}/{}", root, VERIFY),
+        json!({
+            "post": operation(
+                "cluster",
+                "Compare the hash of each collection with every other replica of this cluster",
+                vec![],
+                Some(params_body(&[], &["bounds"])),
+            ),
+        }),
+    );
+
+    paths.insert(
+        format!("{}/{}", root, JOURNAL),
+        json!({
+            "get": operation(
+                "cluster",
+                "Check whether this cluster decided to commit a distributed transaction",
+                vec![key_param("transaction ID", true)],
+                None,
+            ),
+            "delete": operation(
+                "cluster",
+                "Acknowledge the commit of a distributed transaction by a participant",
+                vec![key_param("transaction ID and participant link", true)],
+                None,
+            ),
+        }),
+    );
+
+    for name in cluster.ns() {
+        let path = format!("{}/{}", root, name);
+
+        if let Some(chain) = cluster.chain(name) {
+            let summary = format!("{} of {}", chain.class(), chain.subject().class());
+
+            let mut item = json!({
+                "get": operation("chain", &summary, vec![key_param("key", false)], None),
+                "put": operation(
+                    "chain",
+                    &summary,
+                    vec![key_param("key", false)],
+                    Some(value_body("value")),
+                ),
+                "delete": operation("chain", &summary, vec![key_param("key", false)], None),
+            });
+
+            item["x-tc-class"] = Json::String(chain.class().path().to_string());
+
+            let schema_name = cluster
+                .path()
+                .iter()
+                .chain(Some(name))
+                .map(|segment| segment.as_str())
+                .collect::<Vec<&str>>()
+                .join(".");
+
+            schemas.insert(schema_name.clone(), subject_schema(chain.subject()));
+            item["x-tc-schema"] =
+                json!({ "$ref": format!("#/components/schemas/{}", schema_name) });
+
+            paths.insert(path, item);
+        } else if Cluster::class(cluster, name).is_some() {
+            let summary = format!("Construct a new instance of {}", name);
+
+            paths.insert(
+                path,
+                json!({
+                    "get": operation("class", &summary, vec![key_param("key", false)], None),
+                }),
+            );
+        }
+    }
+
+    for (name, attr) in cluster.proto().iter() {
+        if let Scalar::Op(op_def) = attr {
+            let path = format!("{}/{}", root, name);
+            paths.insert(path, method(name, op_def));
+        }
+    }
+
+    if cluster.is_deprecated() {
+        for item in paths.values_mut() {
+            if let Some(item) = item.as_object_mut() {
+                for operation in item.values_mut().filter(|op| op.is_object()) {
+                    operation["deprecated"] = Json::Bool(true);
+                }
+            }
+        }
+    }
+
+    described.extend(paths);
+}
+
+fn method(name: &Id, op_def: &OpDef) -> Json {
+    let summary = format!("Call the method {}", name);
+
+    match op_def {
+        OpDef::Get((key_name, _)) => json!({
+            "get": operation(
+                "method",
+                &summary,
+                vec![key_param(key_name.as_str(), false)],
+                None,
+            ),
+        }),
+        OpDef::Put((key_name, value_name, _)) => json!({
+            "put": operation(
+                "method",
+                &summary,
+                vec![key_param(key_name.as_str(), false)],
+                Some(value_body(value_name.as_str())),
+            ),
+        }),
+        OpDef::Post(_) => {
+            let body = if let Some(signature) = op_def.signature() {
+                signature_body(&signature)
+            } else {
+                let params = op_def.params();
+                let params: Vec<&str> = params.iter().map(|id| id.as_str()).collect();
+                params_body(&[], &params)
+            };
+
+            json!({
+                "post": operation("method", &summary, vec![], Some(body)),
+            })
+        }
+        OpDef::Delete((key_name, _)) => json!({
+            "delete": operation(
+                "method",
+                &summary,
+                vec![key_param(key_name.as_str(), false)],
+                None,
+            ),
+        }),
+    }
+}
+
+fn operation(tag: &str, summary: &str, parameters: Vec<Json>, request_body: Option<Json>) -> Json {
+    let mut operation = json!({
+        "tags": [tag],
+        "summary": summary,
+        "parameters": parameters,
+        "responses": {
+            "200": {
+                "description": "OK",
+                "content": { "application/json": { "schema": {} } },
+            },
+            "default": {
+                "description": "an error",
+                "content": { "application/json": { "schema": { "type": "string" } } },
+            },
+        },
+    });
+
+    if let Some(request_body) = request_body {
+        operation["requestBody"] = request_body;
+    }
+
+    operation
+}
+
+fn key_param(name: &str, required: bool) -> Json {
+    json!({
+        "name": "key",
+        "in": "query",
+        "description": format!("the {} (a JSON-encoded Value)", name),
+        "required": required,
+        "schema": { "type": "string" },
+    })
+}
+
+fn value_body(name: &str) -> Json {
+    json!({
+        "description": format!("the {}", name),
+        "required": true,
+        "content": { "application/json": { "schema": {} } },
+    })
+}
+
+fn params_body(required: &[&str], optional: &[&str]) -> Json {
+    let properties: JsonMap<String, Json> = required
+        .iter()
+        .chain(optional)
+        .map(|name| (name.to_string(), json!({})))
+        .collect();
+
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+    });
+
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+
+    json!({
+        "required": !required.is_empty(),
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+/// Describe the declared parameters of a POST op as a request body,
+/// where each parameter without a default is required.
+fn signature_body(signature: &Signature) -> Json {
+    let properties: JsonMap<String, Json> = signature
+        .params()
+        .iter()
+        .map(|param| {
+            let schema = match param.dtype() {
+                StateType::Scalar(ScalarType::Value(dtype)) => value_schema(dtype),
+                StateType::Map | StateType::Scalar(ScalarType::Map) => {
+                    json!({ "type": "object", "x-tc-class": param.dtype().path().to_string() })
+                }
+                StateType::Tuple | StateType::Scalar(ScalarType::Tuple) => {
+                    json!({ "type": "array", "x-tc-class": param.dtype().path().to_string() })
+                }
+                dtype => json!({ "x-tc-class": dtype.path().to_string() }),
+            };
+
+            (param.name().to_string(), schema)
+        })
+        .collect();
+
+    let required: Vec<&str> = signature
+        .params()
+        .iter()
+        .filter(|param| param.default().is_none())
+        .map(|param| param.name().as_str())
+        .collect();
+
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    });
+
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+
+    json!({
+        "required": !required.is_empty(),
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+/// Describe the data of the given chain [`Subject`] as a JSON schema: a row of a `BTree` or
+/// `Table` as an object with a property for each column, and a tensor as an array of numbers.
+fn subject_schema(subject: &Subject) -> Json {
+    let mut schema = match subject {
+        Subject::BTree(btree) => row_schema(BTreeInstance::schema(btree), &[]),
+        Subject::Map(map) => {
+            let properties: JsonMap<String, Json> = map
+                .iter()
+                .map(|(name, member)| (name.to_string(), subject_schema(member)))
+                .collect();
+
+            json!({ "type": "object", "properties": properties })
+        }
+        Subject::Table(table) => {
+            row_schema(TableInstance::key(table), TableInstance::values(table))
+        }
+        Subject::Tuple(tuple) => json!({
+            "type": "array",
+            "items": { "oneOf": tuple.iter().map(subject_schema).collect::<Vec<Json>>() },
+        }),
+        #[cfg(feature = "tc-tensor")]
+        Subject::Dense(dense) => tensor_schema(Persist::schema(dense)),
+        #[cfg(feature = "tc-tensor")]
+        Subject::Sparse(sparse) => tensor_schema(Persist::schema(sparse)),
+    };
+
+    schema["x-tc-class"] = Json::String(subject.class().path().to_string());
+    schema
+}
+
+fn row_schema(key: &[Column], values: &[Column]) -> Json {
+    let properties: JsonMap<String, Json> = key
+        .iter()
+        .chain(values)
+        .map(|column| {
+            let mut schema = value_schema(column.dtype);
+            if let Some(max_len) = column.max_len {
+                schema["maxLength"] = json!(max_len);
+            }
+
+            (column.name.to_string(), schema)
+        })
+        .collect();
+
+    let required: Vec<String> = key.iter().map(|column| column.name.to_string()).collect();
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+#[cfg(feature = "tc-tensor")]
+fn tensor_schema(schema: &tc_tensor::Schema) -> Json {
+    json!({
+        "type": "array",
+        "items": value_schema(ValueType::Number(schema.dtype)),
+        "x-tc-shape": schema.shape.to_vec(),
+    })
+}
+
+/// Describe a [`Value`] of the given type as a JSON schema.
+fn value_schema(dtype: ValueType) -> Json {
+    let mut schema = match dtype {
+        ValueType::Number(NumberType::Bool) => json!({ "type": "boolean" }),
+        ValueType::Number(NumberType::Complex(_)) => json!({
+            "type": "array",
+            "items": { "type": "number" },
+            "minItems": 2,
+            "maxItems": 2,
+        }),
+        ValueType::Number(NumberType::Float(_)) | ValueType::Number(NumberType::Number) => {
+            json!({ "type": "number" })
+        }
+        ValueType::Number(NumberType::Int(_)) | ValueType::Number(NumberType::UInt(_)) => {
+            json!({ "type": "integer" })
+        }
+        ValueType::Bytes => json!({ "type": "string", "format": "byte" }),
+        ValueType::DateTime => json!({ "type": "string", "format": "date-time" }),
+        ValueType::Link => json!({ "type": "string", "format": "uri" }),
+        ValueType::BigInt
+        | ValueType::Decimal
+        | ValueType::Id
+        | ValueType::String
+        | ValueType::Version => json!({ "type": "string" }),
+        ValueType::None => json!({ "nullable": true }),
+        ValueType::Tuple => json!({ "type": "array", "items": {} }),
+        ValueType::Value => json!({}),
+    };
+
+    schema["x-tc-class"] = Json::String(dtype.path().to_string());
+    schema
+}
@@ -2,17 +2,14 @@ use std::collections::HashSet;
 use std::iter::FromIterator;
 use std::sync::Arc;
 
-use futures::future::{try_join_all, FutureExt};
+use futures::future::FutureExt;
 use futures::stream::{FuturesUnordered, StreamExt};
 use log::{debug, warn};
 use tokio::sync::RwLock;
 
-use tc_error::*;
 use tc_transact::Transaction;
 use tc_value::{Link, Value};
-use tcgeneric::Map;
 
-use crate::state::State;
 use crate::txn::Txn;
 
 #[derive(Clone)]
@@ -32,21 +29,8 @@ impl Owner {
         mutated.insert(participant);
     }
 
-    pub async fn commit(&self, txn: &Txn) -> TCResult<()> {
-        let mut mutated = self.mutated.write().await;
-
-        if mutated.is_empty() {
-            debug!("no dependencies to commit");
-        }
-
-        let mutated = mutated.drain();
-        try_join_all(mutated.into_iter().map(|link| {
-            debug!("sending commit message to dependency at {}", link);
-            txn.post(link, Map::<State>::default().into())
-        }))
-        .await?;
-
-        Ok(())
+    pub async fn mutated(&self) -> HashSet<Link> {
+        self.mutated.read().await.clone()
     }
 
     pub async fn rollback(&self, txn: &Txn) {
@@ -0,0 +1,435 @@
+//! Verify that the replicas of a [`Cluster`] are consistent with one another.
+//!
+//! Each collection in a cluster is divided into ranges of its key, and each range is hashed
+//! separately, so that a mismatch between two replicas can be narrowed down to the ranges where
+//! they differ. The verifying replica chooses the range bounds from its own data, then asks every
+//! other replica to hash the same ranges in the same transaction, so every replica is compared as
+//! of the same [`TxnId`](crate::txn::TxnId).
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use collate::Collate;
+use futures::future::join_all;
+use futures::{TryFutureExt, TryStreamExt};
+use log::debug;
+use safecast::{TryCastFrom, TryCastInto};
+use sha2::{Digest, Sha256};
+
+use tc_btree::BTreeInstance;
+use tc_error::*;
+use tc_table::{TableInstance, TableStream};
+#[cfg(feature = "tc-tensor")]
+use tc_tensor::TensorAccess;
+#[cfg(feature = "tc-tensor")]
+use tc_transact::fs::Hash;
+use tc_transact::Transaction;
+use tc_value::{Link, TCString, Value, ValueCollator};
+use tcgeneric::*;
+
+use crate::chain::{ChainInstance, Subject};
+use crate::state::State;
+use crate::txn::Txn;
+
+use super::Cluster;
+
+/// The name of the endpoint which verifies the consistency of the replicas of a [`Cluster`].
+pub const VERIFY: Label = label("verify");
+
+/// The name of the parameter which gives the range bounds to hash, when verifying a replica.
+pub const BOUNDS: Label = label("bounds");
+
+/// The maximum number of rows hashed as a single range, when choosing range bounds.
+pub const RANGE_SIZE: u64 = 1000;
+
+type Key = Vec<Value>;
+
+/// The hash of a single range of a collection.
+struct RangeHash {
+    start: Option<Key>,
+    count: u64,
+    hash: String,
+}
+
+impl RangeHash {
+    fn into_value(self) -> Value {
+        let start = self.start.map(Value::from).unwrap_or_default();
+        let hash = Value::String(TCString::from(self.hash));
+        Value::Tuple(vec![start, Value::from(self.count), hash].into())
+    }
+}
+
+impl TryCastFrom<Value> for RangeHash {
+    fn can_cast_from(value: &Value) -> bool {
+        <(Value, u64, TCString)>::can_cast_from(value)
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        let (start, count, hash): (Value, u64, TCString) = value.opt_cast_into()?;
+
+        let start = if start.is_none() {
+            None
+        } else {
+            Some(start.opt_cast_into()?)
+        };
+
+        Some(Self {
+            start,
+            count,
+            hash: hash.to_string(),
+        })
+    }
+}
+
+/// Accumulates the hash of a range of rows.
+struct RangeHasher {
+    start: Option<Key>,
+    count: u64,
+    hasher: Sha256,
+}
+
+impl RangeHasher {
+    fn new(start: Option<Key>) -> Self {
+        Self {
+            start,
+            count: 0,
+            hasher: Sha256::default(),
+        }
+    }
+
+    async fn update(&mut self, row: Vec<Value>) -> TCResult<()> {
+        let mut encoded = tbon::en::encode(row).map_err(TCError::internal)?;
+        while let Some(chunk) = encoded.try_next().map_err(TCError::internal).await? {
+            self.hasher.update(&chunk);
+        }
+
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> RangeHash {
+        RangeHash {
+            start: self.start,
+            count: self.count,
+            hash: hex::encode(self.hasher.finalize()),
+        }
+    }
+}
+
+/// Hash the given `rows`, which must be sorted by their keys of length `key_len`.
+///
+/// If `bounds` is given, the rows are hashed in ranges starting at each bound, after an initial
+/// range with no lower bound. Otherwise a new range is started every [`RANGE_SIZE`] rows.
+async fn hash_rows<'a>(
+    mut rows: TCBoxTryStream<'a, Vec<Value>>,
+    key_len: usize,
+    bounds: Option<Vec<Key>>,
+) -> TCResult<Vec<RangeHash>> {
+    let collator = ValueCollator::default();
+
+    let mut ranges = Vec::new();
+    let mut range = RangeHasher::new(None);
+    let mut next = 0;
+
+    while let Some(row) = rows.try_next().await? {
+        let key = &row[..key_len.min(row.len())];
+
+        if let Some(bounds) = &bounds {
+            while next < bounds.len()
+                && collator.compare_slice(key, &bounds[next]) != Ordering::Less
+            {
+                ranges.push(range.finish());
+                range = RangeHasher::new(Some(bounds[next].to_vec()));
+                next += 1;
+            }
+        } else if range.count == RANGE_SIZE {
+            ranges.push(range.finish());
+            range = RangeHasher::new(Some(key.to_vec()));
+        }
+
+        range.update(row).await?;
+    }
+
+    ranges.push(range.finish());
+
+    if let Some(bounds) = bounds {
+        for bound in bounds.into_iter().skip(next) {
+            ranges.push(RangeHasher::new(Some(bound)).finish());
+        }
+    }
+
+    Ok(ranges)
+}
+
+/// Hash every collection in the given `subject`, by its path relative to the cluster.
+fn hash_subject<'a>(
+    txn: &'a Txn,
+    path: TCPathBuf,
+    subject: &'a Subject,
+    bounds: &'a HashMap<String, Vec<Key>>,
+    digest: &'a mut Vec<(TCPathBuf, Vec<RangeHash>)>,
+) -> TCBoxTryFuture<'a, ()> {
+    Box::pin(async move {
+        let txn_id = *txn.id();
+        let range_bounds = bounds.get(&path.to_string()).cloned();
+
+        let ranges = match subject {
+            Subject::BTree(btree) => {
+                let key_len = btree.schema().len();
+                let keys = btree.clone().keys(txn_id).await?;
+                hash_rows(keys, key_len, range_bounds).await?
+            }
+            Subject::Table(table) => {
+                let key_len = table.key().len();
+                let rows = table.clone().rows(txn_id).await?;
+                hash_rows(rows, key_len, range_bounds).await?
+            }
+            Subject::Map(map) => {
+                for (name, member) in map.iter() {
+                    let path = path.clone().append(name.clone());
+                    hash_subject(txn, path, member, bounds, digest).await?;
+                }
+
+                return Ok(());
+            }
+            Subject::Tuple(tuple) => {
+                for (i, member) in tuple.iter().enumerate() {
+                    let path = path.clone().append(Id::from(i));
+                    hash_subject(txn, path, member, bounds, digest).await?;
+                }
+
+                return Ok(());
+            }
+            #[cfg(feature = "tc-tensor")]
+            Subject::Dense(dense) => vec![RangeHash {
+                start: None,
+                count: dense.size(),
+                hash: dense.hash_hex(txn).await?,
+            }],
+            #[cfg(feature = "tc-tensor")]
+            Subject::Sparse(sparse) => vec![RangeHash {
+                start: None,
+                count: sparse.size(),
+                hash: sparse.hash_hex(txn).await?,
+            }],
+        };
+
+        digest.push((path, ranges));
+        Ok(())
+    })
+}
+
+impl Cluster {
+    /// Hash each range of each collection in this cluster, as a [`Value`] to send to another
+    /// replica.
+    ///
+    /// The `bounds` give the start of each range after the first, by collection path; the range
+    /// bounds of any collection not listed are chosen by this replica.
+    pub async fn digest(&self, txn: &Txn, bounds: Value) -> TCResult<Value> {
+        let bounds = cast_bounds(bounds)?;
+
+        let digest = self
+            .hash_collections(txn, &bounds)
+            .await?
+            .into_iter()
+            .map(|(path, ranges)| {
+                let ranges = ranges.into_iter().map(RangeHash::into_value).collect();
+                Value::Tuple(vec![Value::from(path), Value::Tuple(ranges)].into())
+            })
+            .collect();
+
+        Ok(Value::Tuple(digest))
+    }
+
+    async fn hash_collections(
+        &self,
+        txn: &Txn,
+        bounds: &HashMap<String, Vec<Key>>,
+    ) -> TCResult<Vec<(TCPathBuf, Vec<RangeHash>)>> {
+        let mut digest = Vec::new();
+        for (name, chain) in self.chains.iter() {
+            let path = TCPathBuf::from(name.clone());
+            hash_subject(txn, path, chain.subject(), bounds, &mut digest).await?;
+        }
+
+        Ok(digest)
+    }
+
+    /// Compare the hash of each collection in this cluster with every other replica,
+    /// as of the current transaction, and report the ranges where they differ.
+    pub async fn verify(&self, txn: &Txn) -> TCResult<State> {
+        let self_link = txn.link(self.link.path().clone());
+
+        let expected = self.hash_collections(txn, &HashMap::new()).await?;
+
+        let bounds = expected
+            .iter()
+            .map(|(path, ranges)| {
+                let starts = ranges
+                    .iter()
+                    .filter_map(|range| range.start.clone())
+                    .map(Value::from)
+                    .collect();
+
+                Value::Tuple(vec![Value::from(path.clone()), Value::Tuple(starts)].into())
+            })
+            .collect::<Tuple<Value>>();
+
+        let mut replicas = self.replicas(*txn.id()).await?;
+        replicas.remove(&self_link);
+
+        let mut replicas = replicas.into_iter().collect::<Vec<Link>>();
+        replicas.sort_by_key(|replica| replica.to_string());
+
+        debug!("{} verifying {} replicas", self, replicas.len());
+
+        let digests = join_all(replicas.iter().map(|replica| {
+            let mut params = Map::new();
+            params.insert(BOUNDS.into(), State::from(Value::Tuple(bounds.clone())));
+
+            txn.post(replica.clone().append(VERIFY.into()), State::Map(params))
+                .map_ok(|digest| {
+                    Value::try_cast_from(digest, |s| {
+                        TCError::bad_gateway(format!("invalid replica digest: {}", s))
+                    })
+                })
+        }))
+        .await;
+
+        let mut mismatches = Vec::new();
+        let mut unavailable = Vec::new();
+
+        for (replica, digest) in replicas.iter().zip(digests) {
+            let mut actual = match digest.and_then(|digest| digest).and_then(cast_digest) {
+                Ok(digest) => digest,
+                Err(cause) => {
+                    debug!("unable to verify replica {}: {}", replica, cause);
+
+                    let mut failure = Map::new();
+                    failure.insert(label("replica").into(), Value::from(replica.clone()).into());
+                    failure.insert(label("error").into(), State::from(error_message(cause)));
+                    unavailable.push(State::Map(failure));
+                    continue;
+                }
+            };
+
+            for (path, ranges) in &expected {
+                let found_ranges = actual.remove(path).unwrap_or_default();
+
+                for (i, range) in ranges.iter().enumerate() {
+                    let found = found_ranges.get(i);
+                    if let Some(found) = found {
+                        if found.count == range.count && found.hash == range.hash {
+                            continue;
+                        }
+                    }
+
+                    let end = ranges
+                        .get(i + 1)
+                        .and_then(|range| range.start.clone())
+                        .map(Value::from)
+                        .unwrap_or_default();
+
+                    mismatches.push(mismatch(
+                        replica,
+                        path,
+                        range_start(range),
+                        end,
+                        range_summary(Some(range)),
+                        range_summary(found),
+                    ));
+                }
+            }
+
+            for (path, ranges) in actual {
+                mismatches.push(mismatch(
+                    replica,
+                    &path,
+                    Value::None,
+                    Value::None,
+                    Value::None,
+                    range_summary(ranges.first()),
+                ));
+            }
+        }
+
+        let consistent = mismatches.is_empty() && unavailable.is_empty();
+        let txn_id = TCString::from(txn.id().to_string());
+        let replicas = replicas
+            .into_iter()
+            .map(Value::from)
+            .collect::<Vec<Value>>();
+
+        let mut report = Map::new();
+        report.insert(label("txn_id").into(), Value::String(txn_id).into());
+        report.insert(label("verified_by").into(), Value::from(self_link).into());
+        report.insert(label("replicas").into(), Value::from(replicas).into());
+        report.insert(label("consistent").into(), Value::from(consistent).into());
+        report.insert(label("mismatches").into(), State::Tuple(mismatches.into()));
+        report.insert(
+            label("unavailable").into(),
+            State::Tuple(unavailable.into()),
+        );
+
+        Ok(State::Map(report))
+    }
+}
+
+fn cast_bounds(bounds: Value) -> TCResult<HashMap<String, Vec<Key>>> {
+    if bounds.is_none() {
+        return Ok(HashMap::new());
+    }
+
+    let bounds = Vec::<(TCPathBuf, Vec<Key>)>::try_cast_from(bounds, |v| {
+        TCError::bad_request("invalid range bounds", v)
+    })?;
+
+    Ok(bounds
+        .into_iter()
+        .map(|(path, bounds)| (path.to_string(), bounds))
+        .collect())
+}
+
+fn cast_digest(digest: Value) -> TCResult<HashMap<TCPathBuf, Vec<RangeHash>>> {
+    let digest = Vec::<(TCPathBuf, Vec<RangeHash>)>::try_cast_from(digest, |v| {
+        TCError::bad_gateway(format!("invalid collection digest: {}", v))
+    })?;
+
+    Ok(digest.into_iter().collect())
+}
+
+fn mismatch(
+    replica: &Link,
+    path: &TCPathBuf,
+    start: Value,
+    end: Value,
+    expected: Value,
+    found: Value,
+) -> State {
+    let mut mismatch = Map::new();
+    mismatch.insert(label("replica").into(), Value::from(replica.clone()).into());
+    mismatch.insert(label("collection").into(), Value::from(path.clone()).into());
+    mismatch.insert(label("start").into(), start.into());
+    mismatch.insert(label("end").into(), end.into());
+    mismatch.insert(label("expected").into(), expected.into());
+    mismatch.insert(label("found").into(), found.into());
+    State::Map(mismatch)
+}
+
+fn range_start(range: &RangeHash) -> Value {
+    range.start.clone().map(Value::from).unwrap_or_default()
+}
+
+fn range_summary(range: Option<&RangeHash>) -> Value {
+    match range {
+        Some(range) => {
+            let hash = Value::String(TCString::from(range.hash.to_string()));
+            Value::Tuple(vec![Value::from(range.count), hash].into())
+        }
+        None => Value::None,
+    }
+}
+
+fn error_message(cause: TCError) -> Value {
+    Value::String(TCString::from(cause.to_string()))
+}
@@ -0,0 +1,470 @@
+//! A materialized [`View`] of a `Table` hosted by a [`Cluster`](super::Cluster), which is kept up
+//! to date as each transaction which writes to its source table commits.
+//!
+//! A view is defined by the name of its source chain, the [`TableSchema`] of its rows, and a POST
+//! [`OpDef`] which maps each row of the source table (as a `Map` of its columns) to a row of the
+//! view, or to `None` to leave the source row out. An aggregate view sums the value columns of
+//! every source row which maps to the same view key, like a `GROUP BY` query.
+//!
+//! The view remembers which view row each source row contributed to, and how much, so that when a
+//! source row changes only its old and new contributions need to be recomputed. A change which
+//! can't be traced to individual source rows (like a bulk delete or a revert) rebuilds the view.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::FromIterator;
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use futures::stream::TryStreamExt;
+use log::debug;
+use safecast::{TryCastFrom, TryCastInto};
+
+use tc_error::*;
+use tc_table::{
+    Bounds, Column, ColumnBound, IndexSchema, Key, TableInstance, TableRead, TableSchema,
+    TableSlice, TableStream, TableWrite,
+};
+use tc_transact::fs::{Dir, Persist};
+use tc_transact::{Transact, Transaction, TxnId};
+use tc_value::{Number, Value, ValueType};
+use tcgeneric::*;
+
+use crate::chain::Mutation;
+use crate::closure::Closure;
+use crate::collection::TableIndex;
+use crate::fs;
+use crate::scalar::{OpDef, Scalar};
+use crate::state::State;
+use crate::txn::Txn;
+
+/// The class path used to declare a [`View`] as a member of a cluster.
+pub const VIEW: PathLabel = path_label(&["state", "collection", "table", "view"]);
+
+const ROWS: Label = label("rows");
+const LINEAGE: Label = label("lineage");
+const VIEW_KEY: Label = label("view_key");
+const CONTRIBUTION: Label = label("contribution");
+
+/// The definition of a materialized [`View`].
+pub struct ViewDef {
+    source: Id,
+    schema: TableSchema,
+    op: OpDef,
+    aggregate: bool,
+}
+
+impl ViewDef {
+    /// Parse a view definition of the form `(source, schema, op, aggregate)`.
+    pub fn from_scalar(scalar: Scalar) -> TCResult<Self> {
+        let (source, schema, op, aggregate): (Id, Value, OpDef, bool) =
+            scalar.try_cast_into(|s| {
+                TCError::bad_request(
+                    "expected a view definition (source, schema, op, aggregate) but found",
+                    s,
+                )
+            })?;
+
+        let schema: TableSchema =
+            schema.try_cast_into(|v| TCError::bad_request("invalid view schema", v))?;
+
+        if !matches!(op, OpDef::Post(_)) {
+            return Err(TCError::bad_request(
+                "a view must be defined by a POST Op, not",
+                op,
+            ));
+        }
+
+        if aggregate {
+            for column in schema.primary().values() {
+                if !matches!(column.dtype(), ValueType::Number(_)) {
+                    return Err(TCError::bad_request(
+                        "an aggregate view can only sum numeric columns, not",
+                        column.name(),
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            source,
+            schema,
+            op,
+            aggregate,
+        })
+    }
+
+    /// The name of the chain whose `Table` this view is computed from.
+    pub fn source(&self) -> &Id {
+        &self.source
+    }
+}
+
+/// A materialized view of a `Table`, kept up to date when each transaction commits.
+pub struct View {
+    source: Id,
+    op: OpDef,
+    aggregate: bool,
+    rows: TableIndex,
+    lineage: TableIndex,
+}
+
+impl View {
+    /// Load the view with the given definition from the given `dir`, or create it and compute
+    /// its rows from the given `source` table if it doesn't exist yet.
+    pub async fn load(
+        txn: &Txn,
+        def: ViewDef,
+        dir: fs::Dir,
+        source: &TableIndex,
+    ) -> TCResult<Self> {
+        let source_schema = source.schema();
+        if !source_schema.access().is_empty() || source_schema.privacy().is_some() {
+            return Err(TCError::unsupported(format!(
+                "cannot materialize a view of {}, which has restricted columns or a privacy policy",
+                def.source
+            )));
+        }
+
+        let lineage_schema = lineage_schema(source_schema.primary());
+
+        let txn_id = *txn.id();
+        let view = if dir.is_empty(txn_id).await? {
+            debug!("create materialized view of {}", def.source);
+
+            let rows = dir.create_dir(txn_id, ROWS.into()).await?;
+            let rows = TableIndex::create(&rows, def.schema.clone(), txn_id).await?;
+
+            let lineage = dir.create_dir(txn_id, LINEAGE.into()).await?;
+            let lineage = TableIndex::create(&lineage, lineage_schema, txn_id).await?;
+
+            let view = Self::new(def, rows, lineage);
+            view.rebuild(txn, source).await?;
+            view
+        } else {
+            let rows = require_dir(&dir, txn_id, ROWS).await?;
+            let rows = TableIndex::load(txn, def.schema.clone(), rows).await?;
+
+            let lineage = require_dir(&dir, txn_id, LINEAGE).await?;
+            let lineage = TableIndex::load(txn, lineage_schema, lineage).await?;
+
+            Self::new(def, rows, lineage)
+        };
+
+        Ok(view)
+    }
+
+    fn new(def: ViewDef, rows: TableIndex, lineage: TableIndex) -> Self {
+        Self {
+            source: def.source,
+            op: def.op,
+            aggregate: def.aggregate,
+            rows,
+            lineage,
+        }
+    }
+
+    /// The name of the chain whose `Table` this view is computed from.
+    pub fn source(&self) -> &Id {
+        &self.source
+    }
+
+    /// Borrow the rows of this view, as a `Table`.
+    pub fn table(&self) -> &TableIndex {
+        &self.rows
+    }
+
+    /// Update this view to reflect the given `mutations` of its `source` table in the given
+    /// transaction.
+    pub async fn refresh(
+        &self,
+        txn: &Txn,
+        source: &TableIndex,
+        mutations: &[Mutation],
+    ) -> TCResult<()> {
+        if mutations.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(keys) = mutated_keys(source.schema().primary(), mutations) {
+            debug!("refresh {} rows of the view of {}", keys.len(), self.source);
+
+            for key in keys {
+                self.reconcile(txn, source, key).await?;
+            }
+
+            Ok(())
+        } else {
+            self.rebuild(txn, source).await
+        }
+    }
+
+    /// Recompute every row of this view from its `source` table.
+    pub async fn rebuild(&self, txn: &Txn, source: &TableIndex) -> TCResult<()> {
+        debug!("rebuild the view of {}", self.source);
+
+        let txn_id = *txn.id();
+        let key_columns: Vec<Id> = source
+            .schema()
+            .primary()
+            .key()
+            .iter()
+            .map(|col| col.name().clone())
+            .collect();
+
+        let mut keys = source
+            .clone()
+            .select(key_columns.to_vec())?
+            .rows(txn_id)
+            .await?;
+
+        while let Some(key) = keys.try_next().await? {
+            self.reconcile(txn, source, key).await?;
+        }
+
+        // remove the contributions of any source rows which no longer exist
+        let stale = self
+            .lineage
+            .clone()
+            .select(key_columns)?
+            .rows(txn_id)
+            .await?
+            .try_collect::<Vec<Key>>()
+            .await?;
+
+        for key in stale {
+            self.reconcile(txn, source, key).await?;
+        }
+
+        Ok(())
+    }
+
+    // bring the contribution of the source row with the given `key` up to date
+    async fn reconcile(&self, txn: &Txn, source: &TableIndex, key: Key) -> TCResult<()> {
+        let txn_id = *txn.id();
+        let key_len = key.len();
+
+        let old = self
+            .lineage
+            .read(&txn_id, &key)
+            .await?
+            .map(|row| contribution_from_lineage(row, key_len))
+            .transpose()?;
+
+        let new = match source.read(&txn_id, &key).await? {
+            Some(row) => self.apply(txn, source, row).await?,
+            None => None,
+        };
+
+        if old == new {
+            return Ok(());
+        }
+
+        if let Some((view_key, values)) = old {
+            self.lineage.delete(txn_id, key.to_vec()).await?;
+            self.remove(txn_id, view_key, values).await?;
+        }
+
+        if let Some((view_key, values)) = new {
+            self.insert(txn_id, view_key.to_vec(), values.to_vec())
+                .await?;
+
+            let lineage = vec![Value::Tuple(view_key.into()), Value::Tuple(values.into())];
+            self.lineage.upsert(txn_id, key, lineage).await?;
+        }
+
+        Ok(())
+    }
+
+    // compute the view key and values of the given source row, if it's part of this view
+    async fn apply(
+        &self,
+        txn: &Txn,
+        source: &TableIndex,
+        row: Vec<Value>,
+    ) -> TCResult<Option<(Key, Vec<Value>)>> {
+        let row = source.schema().primary().row_from_values(row)?;
+        let params = row
+            .into_iter()
+            .map(|(name, value)| (name, State::from(value)))
+            .collect();
+
+        let closure = Closure::new(Map::default(), self.op.clone());
+        let result = closure.call(txn, State::Map(params)).await?;
+        if result.is_none() {
+            return Ok(None);
+        }
+
+        let result = result.try_into_map(|s| {
+            TCError::bad_request("a view Op must return a Map or None, not", s)
+        })?;
+
+        let row = result
+            .into_iter()
+            .map(|(name, state)| {
+                Value::try_cast_from(state, |s| {
+                    TCError::bad_request(format!("invalid value for view column {}", name), s)
+                })
+                .map(|value| (name, value))
+            })
+            .collect::<TCResult<Map<Value>>>()?;
+
+        let schema = self.rows.schema().primary();
+        let (key, values) = schema.key_values_from_row(row, true)?;
+        let key = schema.validate_key(key)?;
+        let values = schema.validate_values(values)?;
+
+        Ok(Some((key, values)))
+    }
+
+    async fn insert(&self, txn_id: TxnId, view_key: Key, values: Vec<Value>) -> TCResult<()> {
+        let existing = self.rows.read(&txn_id, &view_key).await?;
+
+        let values = match existing {
+            Some(existing) if self.aggregate => {
+                let existing = &existing[view_key.len()..];
+                combine(existing, &values, |l, r| l + r)?
+            }
+            Some(_) => {
+                return Err(TCError::new(
+                    ErrorType::Conflict,
+                    format!(
+                        "more than one source row maps to the view key {}",
+                        Value::Tuple(view_key.into())
+                    ),
+                ))
+            }
+            None => values,
+        };
+
+        self.rows.upsert(txn_id, view_key, values).await
+    }
+
+    async fn remove(&self, txn_id: TxnId, view_key: Key, values: Vec<Value>) -> TCResult<()> {
+        if self.aggregate && self.has_contributors(txn_id, &view_key).await? {
+            if let Some(existing) = self.rows.read(&txn_id, &view_key).await? {
+                let existing = &existing[view_key.len()..];
+                let values = combine(existing, &values, |l, r| l - r)?;
+                return self.rows.upsert(txn_id, view_key, values).await;
+            }
+        }
+
+        self.rows.delete(txn_id, view_key).await
+    }
+
+    async fn has_contributors(&self, txn_id: TxnId, view_key: &[Value]) -> TCResult<bool> {
+        let bound = ColumnBound::Is(Value::Tuple(view_key.to_vec().into()));
+        let bounds = Bounds::from(HashMap::from_iter(vec![(VIEW_KEY.into(), bound)]));
+
+        let count = self
+            .lineage
+            .clone()
+            .slice(bounds)?
+            .limit(1)
+            .count(txn_id)
+            .await?;
+
+        Ok(count > 0)
+    }
+}
+
+#[async_trait]
+impl Transact for View {
+    async fn commit(&self, txn_id: &TxnId) {
+        join_all(vec![self.rows.commit(txn_id), self.lineage.commit(txn_id)]).await;
+    }
+
+    async fn finalize(&self, txn_id: &TxnId) {
+        join_all(vec![
+            self.rows.finalize(txn_id),
+            self.lineage.finalize(txn_id),
+        ])
+        .await;
+    }
+}
+
+impl fmt::Display for View {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "materialized view of {}", self.source)
+    }
+}
+
+// the lineage of a view maps the key of each source row to the view key and values it contributed
+fn lineage_schema(source: &IndexSchema) -> TableSchema {
+    let values = vec![
+        Column::from((Id::from(VIEW_KEY), ValueType::Tuple)),
+        Column::from((Id::from(CONTRIBUTION), ValueType::Tuple)),
+    ];
+
+    let primary = IndexSchema::from((source.key().to_vec(), values));
+    TableSchema::new(primary, vec![(VIEW_KEY.into(), vec![VIEW_KEY.into()])])
+}
+
+fn contribution_from_lineage(mut row: Vec<Value>, key_len: usize) -> TCResult<(Key, Vec<Value>)> {
+    let mut values = row.split_off(key_len).into_iter();
+
+    let view_key = values.next().unwrap_or_default();
+    let view_key = Tuple::<Value>::try_cast_from(view_key, |v| {
+        TCError::internal(format!("invalid view key in lineage: {}", v))
+    })?;
+
+    let contribution = values.next().unwrap_or_default();
+    let contribution = Tuple::<Value>::try_cast_from(contribution, |v| {
+        TCError::internal(format!("invalid contribution in lineage: {}", v))
+    })?;
+
+    Ok((view_key.into_inner(), contribution.into_inner()))
+}
+
+// return the key of each source row written by the given `mutations`,
+// or `None` if they can't all be traced to individual rows
+fn mutated_keys(schema: &IndexSchema, mutations: &[Mutation]) -> Option<Vec<Key>> {
+    let mut keys = Vec::with_capacity(mutations.len());
+
+    for mutation in mutations {
+        let (path, key) = match mutation {
+            Mutation::Delete(path, key) => (path, key),
+            Mutation::Put(path, key, _) => (path, key),
+            Mutation::Revert(_, _) => return None,
+        };
+
+        if !path.is_empty() || key.is_none() {
+            return None;
+        }
+
+        let key = Vec::<Value>::try_cast_from(key.clone(), |_| ()).ok()?;
+        let key = schema.validate_key(key).ok()?;
+        keys.push(key);
+    }
+
+    Some(keys)
+}
+
+fn combine<Op>(left: &[Value], right: &[Value], op: Op) -> TCResult<Vec<Value>>
+where
+    Op: Fn(Number, Number) -> Number,
+{
+    left.iter()
+        .zip(right)
+        .map(|(l, r)| {
+            let l = Number::try_cast_from(l.clone(), |v| {
+                TCError::bad_request("an aggregate view can only sum numbers, not", v)
+            })?;
+
+            let r = Number::try_cast_from(r.clone(), |v| {
+                TCError::bad_request("an aggregate view can only sum numbers, not", v)
+            })?;
+
+            Ok(Value::Number(op(l, r)))
+        })
+        .collect()
+}
+
+async fn require_dir(dir: &fs::Dir, txn_id: TxnId, name: Label) -> TCResult<fs::Dir> {
+    dir.get_dir(txn_id, &name.into()).await?.ok_or_else(|| {
+        TCError::internal(format!(
+            "materialized view is missing its {} directory",
+            name
+        ))
+    })
+}
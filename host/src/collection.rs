@@ -11,7 +11,7 @@ use log::debug;
 use tc_btree::BTreeView;
 use tc_error::*;
 use tc_table::TableView;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 use tc_tensor::{Array, TensorView};
 use tc_transact::fs::Dir;
 use tc_transact::{IntoView, Transaction};
@@ -25,7 +25,7 @@ use crate::txn::Txn;
 pub use tc_btree::BTreeType;
 pub use tc_table::TableType;
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub use tc_tensor::{DenseAccess, SparseAccess, TensorType};
 
 pub type BTree = tc_btree::BTree<fs::File<tc_btree::Node>, fs::Dir, Txn>;
@@ -34,26 +34,28 @@ pub type BTreeFile = tc_btree::BTreeFile<fs::File<tc_btree::Node>, fs::Dir, Txn>
 pub type Table = tc_table::Table<fs::File<tc_btree::Node>, fs::Dir, Txn>;
 pub type TableIndex = tc_table::TableIndex<fs::File<tc_btree::Node>, fs::Dir, Txn>;
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub type Tensor = tc_tensor::Tensor<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub type DenseAccessor =
     tc_tensor::DenseAccessor<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub type DenseTensor<B> =
     tc_tensor::DenseTensor<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn, B>;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub type DenseTensorFile =
     tc_tensor::BlockListFile<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub type SparseAccessor =
     tc_tensor::SparseAccessor<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub type SparseTensor<A> =
     tc_tensor::SparseTensor<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn, A>;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub type SparseTable =
     tc_tensor::SparseTable<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>;
+#[cfg(feature = "tc-tensor")]
+pub type TensorTape = tc_tensor::Tape<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>;
 
 pub const PREFIX: PathLabel = path_label(&["state", "collection"]);
 
@@ -62,7 +64,7 @@ pub const PREFIX: PathLabel = path_label(&["state", "collection"]);
 pub enum CollectionType {
     BTree(BTreeType),
     Table(TableType),
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Tensor(TensorType),
 }
 
@@ -76,7 +78,7 @@ impl NativeClass for CollectionType {
             match path[2].as_str() {
                 "btree" => BTreeType::from_path(path).map(Self::BTree),
                 "table" => TableType::from_path(path).map(Self::Table),
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 "tensor" => TensorType::from_path(path).map(Self::Tensor),
                 _ => None,
             }
@@ -89,7 +91,7 @@ impl NativeClass for CollectionType {
         match self {
             Self::BTree(btt) => btt.path(),
             Self::Table(tt) => tt.path(),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tt) => tt.path(),
         }
     }
@@ -107,7 +109,7 @@ impl From<TableType> for CollectionType {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl From<TensorType> for CollectionType {
     fn from(tt: TensorType) -> Self {
         Self::Tensor(tt)
@@ -119,7 +121,7 @@ impl fmt::Display for CollectionType {
         match self {
             Self::BTree(btt) => fmt::Display::fmt(btt, f),
             Self::Table(tt) => fmt::Display::fmt(tt, f),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tt) => fmt::Display::fmt(tt, f),
         }
     }
@@ -130,7 +132,7 @@ impl fmt::Display for CollectionType {
 pub enum Collection {
     BTree(BTree),
     Table(Table),
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Tensor(Tensor),
 }
 
@@ -141,7 +143,7 @@ impl Instance for Collection {
         match self {
             Self::BTree(btree) => CollectionType::BTree(btree.class()),
             Self::Table(table) => CollectionType::Table(table.class()),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tensor) => CollectionType::Tensor(tensor.class()),
         }
     }
@@ -171,14 +173,14 @@ impl From<TableIndex> for Collection {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl From<Tensor> for Collection {
     fn from(tensor: Tensor) -> Self {
         Self::Tensor(tensor)
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl<B: DenseAccess<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>> From<DenseTensor<B>>
     for Collection
 {
@@ -187,7 +189,7 @@ impl<B: DenseAccess<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>> Fr
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl<A: SparseAccess<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>> From<SparseTensor<A>>
     for Collection
 {
@@ -196,7 +198,7 @@ impl<A: SparseAccess<fs::File<Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>> F
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl safecast::TryCastFrom<Collection> for Tensor {
     fn can_cast_from(collection: &Collection) -> bool {
         match collection {
@@ -247,7 +249,7 @@ impl CollectionVisitor {
 
             CollectionType::Table(_) => access.next_value(self.txn).map_ok(Collection::Table).await,
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             CollectionType::Tensor(tt) => match tt {
                 TensorType::Dense => {
                     let tensor: DenseTensor<DenseTensorFile> = access.next_value(self.txn).await?;
@@ -301,7 +303,7 @@ impl<'en> IntoView<'en, fs::Dir> for Collection {
         match self {
             Self::BTree(btree) => btree.into_view(txn).map_ok(CollectionView::BTree).await,
             Self::Table(table) => table.into_view(txn).map_ok(CollectionView::Table).await,
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tensor) => tensor.into_view(txn).map_ok(CollectionView::Tensor).await,
         }
     }
@@ -312,7 +314,7 @@ impl fmt::Debug for Collection {
         match self {
             Self::BTree(btree) => fmt::Debug::fmt(btree, f),
             Self::Table(table) => fmt::Debug::fmt(table, f),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tensor) => fmt::Debug::fmt(tensor, f),
         }
     }
@@ -323,7 +325,7 @@ impl fmt::Display for Collection {
         match self {
             Self::BTree(btree) => fmt::Display::fmt(btree, f),
             Self::Table(table) => fmt::Display::fmt(table, f),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tensor) => fmt::Display::fmt(tensor, f),
         }
     }
@@ -333,7 +335,7 @@ impl fmt::Display for Collection {
 pub enum CollectionView<'en> {
     BTree(BTreeView<'en>),
     Table(TableView<'en>),
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Tensor(TensorView<'en>),
 }
 
@@ -345,7 +347,7 @@ impl<'en> en::IntoStream<'en> for CollectionView<'en> {
         match self {
             Self::BTree(btree) => map.encode_entry(BTreeType::default().path(), btree),
             Self::Table(table) => map.encode_entry(TableType::default().path(), table),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tensor) => match tensor {
                 TensorView::Dense(dense) => map.encode_entry(TensorType::Dense.path(), dense),
                 TensorView::Sparse(sparse) => map.encode_entry(TensorType::Sparse.path(), sparse),
@@ -354,3 +356,109 @@ impl<'en> en::IntoStream<'en> for CollectionView<'en> {
         map.end()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::TryStreamExt;
+    use uuid::Uuid;
+
+    use tc_btree::{BTreeInstance, BTreeWrite, Column, Key, Node};
+    use tc_table::{IndexSchema, TableInstance, TableRead, TableSchema, TableStream, TableWrite};
+    use tc_transact::{Isolation, Transact, TxnId};
+    use tc_value::{NumberType, UIntType, Value};
+    use tcgeneric::{label, NetworkTime};
+
+    use super::*;
+
+    fn txn_id(nanos: u64) -> TxnId {
+        TxnId::new(NetworkTime::from_nanos(nanos))
+    }
+
+    fn key(n: u64) -> Key {
+        vec![Value::from(n)]
+    }
+
+    async fn workspace() -> freqfs::DirLock<fs::CacheBlock> {
+        let path = std::env::temp_dir().join(format!("tc-isolation-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&path).expect("test dir");
+
+        let cache = freqfs::Cache::new(1_000_000, Duration::from_secs(1));
+        cache.load(path).await.expect("cache dir")
+    }
+
+    fn schema() -> Vec<Column> {
+        vec![Column::from((label("id"), NumberType::UInt(UIntType::U64)))]
+    }
+
+    async fn keys(btree: &BTreeFile, txn_id: TxnId) -> Vec<Key> {
+        let keys = btree.clone().keys(txn_id).await.expect("keys");
+        keys.try_collect().await.expect("keys")
+    }
+
+    #[tokio::test]
+    async fn test_btree_read_committed() {
+        let committed = txn_id(1);
+        let file = fs::File::<Node>::new(workspace().await)
+            .await
+            .expect("file");
+
+        let btree = BTreeFile::create(file, schema(), committed)
+            .await
+            .expect("btree");
+
+        btree.insert(committed, key(1)).await.expect("insert");
+        btree.commit(&committed).await;
+
+        // leave a write pending at a transaction which never commits
+        let pending = txn_id(2);
+        btree.insert(pending, key(2)).await.expect("insert");
+
+        let reader = txn_id(3);
+
+        let read_committed = btree.clone().with_isolation(Isolation::ReadCommitted);
+        assert_eq!(keys(&read_committed, reader).await, vec![key(1)]);
+        assert_eq!(read_committed.count(reader).await.expect("count"), 1);
+
+        // a snapshot read has to wait for the pending write to resolve
+        let snapshot = tokio::time::timeout(Duration::from_millis(100), keys(&btree, reader));
+        assert!(snapshot.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_table_read_committed() {
+        let committed = txn_id(1);
+        let dir = fs::Dir::new(workspace().await).await.expect("dir");
+
+        let schema = TableSchema::from(IndexSchema::from((schema(), vec![])));
+        let table = TableIndex::create(&dir, schema, committed)
+            .await
+            .expect("table");
+
+        table
+            .upsert(committed, key(1), vec![])
+            .await
+            .expect("upsert");
+        dir.commit(&committed).await;
+        table.commit(&committed).await;
+
+        let pending = txn_id(2);
+        table.upsert(pending, key(2), vec![]).await.expect("upsert");
+
+        let reader = txn_id(3);
+
+        let read_committed = Table::from(table.clone()).with_isolation(Isolation::ReadCommitted);
+        assert_eq!(
+            read_committed.clone().count(reader).await.expect("count"),
+            1
+        );
+
+        let read_committed = table.clone().with_isolation(Isolation::ReadCommitted);
+        let row = read_committed.read(&reader, &key(2)).await.expect("read");
+        assert!(row.is_none());
+
+        let snapshot = tokio::time::timeout(Duration::from_millis(100), table.count(reader));
+        assert!(snapshot.await.is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Online backup and restore of the data directory.
+//!
+//! A backup quiesces commits, so that the canonical version of every file on disk reflects the same
+//! set of committed transactions, then hard-links (or, failing that, copies) each file into the
+//! target directory. This is safe because a block is never modified in place: it's written to a
+//! temporary file which is then renamed over the old one, so a hard link keeps the old contents.
+//!
+//! Each backup includes a manifest of the files it contains, which is checked when it's restored.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use log::{debug, info};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use tc_error::*;
+use tc_transact::TxnId;
+use tcgeneric::TCBoxTryFuture;
+
+/// The name of the manifest file of a backup.
+pub const MANIFEST: &str = "manifest.json";
+
+static COMMITS: RwLock<()> = RwLock::const_new(());
+
+/// Acquire a permit to commit a transaction, which prevents a backup from starting until it's
+/// dropped.
+pub async fn commit_permit() -> RwLockReadGuard<'static, ()> {
+    COMMITS.read().await
+}
+
+/// A list of the files in a backup and their sizes, as of a given transaction.
+pub struct Manifest {
+    pub txn_id: TxnId,
+    pub files: BTreeMap<PathBuf, u64>,
+}
+
+impl Manifest {
+    /// The total size in bytes of the files in the backup.
+    pub fn size(&self) -> u64 {
+        self.files.values().sum()
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let files = self
+            .files
+            .iter()
+            .map(|(path, size)| (path.to_string_lossy().into_owned(), (*size).into()))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+
+        serde_json::json!({
+            "txn_id": self.txn_id.to_string(),
+            "files": files,
+        })
+    }
+
+    fn from_json(json: serde_json::Value) -> Option<Self> {
+        let txn_id = json.get("txn_id")?.as_str()?.parse().ok()?;
+
+        let files = json
+            .get("files")?
+            .as_object()?
+            .iter()
+            .map(|(path, size)| size.as_u64().map(|size| (PathBuf::from(path), size)))
+            .collect::<Option<_>>()?;
+
+        Some(Self { txn_id, files })
+    }
+
+    /// Read the manifest of the backup in the given directory.
+    pub async fn read(dir: &Path) -> TCResult<Self> {
+        let path = dir.join(MANIFEST);
+        let json = tokio::fs::read(&path).await.map_err(backup_err)?;
+
+        serde_json::from_slice(&json)
+            .ok()
+            .and_then(Self::from_json)
+            .ok_or_else(|| TCError::internal(format!("invalid backup manifest {:?}", path)))
+    }
+}
+
+/// Back up the contents of the `source` directory to a new `target` directory, as of `txn_id`.
+///
+/// New commits wait until the backup is complete.
+pub async fn backup(source: &Path, target: &Path, txn_id: TxnId) -> TCResult<Manifest> {
+    if tokio::fs::metadata(target).await.is_ok() {
+        return Err(TCError::bad_request(
+            "backup already exists",
+            target.display(),
+        ));
+    }
+
+    // write to a temporary directory first so that a partial backup is never mistaken for a whole
+    let tmp = target.with_extension("tmp");
+    if tokio::fs::metadata(&tmp).await.is_ok() {
+        tokio::fs::remove_dir_all(&tmp).await.map_err(backup_err)?;
+    }
+
+    let manifest = {
+        let _commits = COMMITS.write().await;
+
+        // write back any committed block which so far is only in the write-ahead log
+        super::wal::checkpoint().await?;
+
+        info!("backing up {:?} to {:?} at {}", source, target, txn_id);
+
+        let mut files = BTreeMap::new();
+        link_dir(source, &tmp, Path::new(""), &mut files).await?;
+        Manifest { txn_id, files }
+    };
+
+    let json = serde_json::to_vec_pretty(&manifest.to_json()).map_err(TCError::internal)?;
+    tokio::fs::write(tmp.join(MANIFEST), json)
+        .await
+        .map_err(backup_err)?;
+
+    tokio::fs::rename(&tmp, target).await.map_err(backup_err)?;
+
+    info!(
+        "backed up {} files ({} bytes) to {:?}",
+        manifest.files.len(),
+        manifest.size(),
+        target
+    );
+
+    Ok(manifest)
+}
+
+/// Restore the backup in the `source` directory to the `target` directory, which must be empty.
+pub async fn restore(source: &Path, target: &Path) -> TCResult<Manifest> {
+    let manifest = Manifest::read(source).await?;
+
+    let mut entries = tokio::fs::read_dir(target).await.map_err(backup_err)?;
+    if entries.next_entry().await.map_err(backup_err)?.is_some() {
+        return Err(TCError::bad_request(
+            "cannot restore a backup to a directory which is not empty",
+            target.display(),
+        ));
+    }
+
+    info!(
+        "restoring {:?} to {:?} as of {}",
+        source, target, manifest.txn_id
+    );
+
+    for (path, size) in &manifest.files {
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(TCError::internal(format!(
+                "invalid path in backup manifest: {:?}",
+                path
+            )));
+        }
+
+        let from = source.join(path);
+        let actual = tokio::fs::metadata(&from).await.map_err(backup_err)?.len();
+
+        if actual != *size {
+            return Err(TCError::internal(format!(
+                "backup file {:?} should have {} bytes but has {}",
+                from, size, actual
+            )));
+        }
+
+        let to = target.join(path);
+        if let Some(parent) = to.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(backup_err)?;
+        }
+
+        tokio::fs::copy(&from, &to).await.map_err(backup_err)?;
+    }
+
+    Ok(manifest)
+}
+
+fn link_dir<'a>(
+    source: &'a Path,
+    target: &'a Path,
+    prefix: &'a Path,
+    files: &'a mut BTreeMap<PathBuf, u64>,
+) -> TCBoxTryFuture<'a, ()> {
+    Box::pin(async move {
+        tokio::fs::create_dir_all(target)
+            .await
+            .map_err(backup_err)?;
+
+        let mut entries = tokio::fs::read_dir(source).await.map_err(backup_err)?;
+        while let Some(entry) = entries.next_entry().await.map_err(backup_err)? {
+            let name = entry.file_name();
+            let name_str = name.to_string_lossy();
+            if name_str.starts_with('.')
+                || name_str.ends_with("_freqfs")
+                || name_str.ends_with(super::dedup::PARTIAL_LINK)
+            {
+                // skip transaction-specific versions and partially-written blocks
+                continue;
+            }
+
+            let path = prefix.join(&name);
+            let metadata = entry.metadata().await.map_err(backup_err)?;
+            if metadata.is_dir() {
+                link_dir(&entry.path(), &target.join(&name), &path, files).await?;
+            } else {
+                let to = target.join(&name);
+                if let Err(cause) = tokio::fs::hard_link(entry.path(), &to).await {
+                    debug!("unable to hard-link {:?} ({}), copying", path, cause);
+                    tokio::fs::copy(entry.path(), &to)
+                        .await
+                        .map_err(backup_err)?;
+                }
+
+                files.insert(path, metadata.len());
+            }
+        }
+
+        Ok(())
+    })
+}
+
+fn backup_err(cause: io::Error) -> TCError {
+    TCError::internal(format!("backup I/O error: {}", cause))
+}
@@ -8,10 +8,11 @@ use destream::en;
 use futures::{TryFutureExt, TryStreamExt};
 use safecast::AsType;
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWrite};
 use tokio_util::io::StreamReader;
 
 use tc_btree::Node;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 use tc_tensor::Array;
 use tc_value::Value;
 
@@ -23,39 +24,42 @@ use super::file_ext;
 pub enum CacheBlock {
     BTree(Node),
     Chain(ChainBlock),
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Tensor(Array),
     Value(Value),
 }
 
-#[async_trait]
-impl freqfs::FileLoad for CacheBlock {
-    async fn load(path: &Path, file: fs::File, _metadata: Metadata) -> Result<Self, io::Error> {
+impl CacheBlock {
+    /// Decode a block from the given `reader`, according to the file extension of its `path`.
+    pub(super) async fn decode<R>(path: &Path, reader: R) -> Result<Self, io::Error>
+    where
+        R: AsyncReadExt + Send + Unpin,
+    {
         match file_ext(path) {
             Some("node") => {
-                tbon::de::read_from((), file)
+                tbon::de::read_from((), reader)
                     .map_ok(Self::BTree)
                     .map_err(|cause| io::Error::new(io::ErrorKind::InvalidData, cause))
                     .await
             }
 
             Some("chain_block") => {
-                tbon::de::read_from((), file)
+                tbon::de::read_from((), reader)
                     .map_ok(Self::Chain)
                     .map_err(|cause| io::Error::new(io::ErrorKind::InvalidData, cause))
                     .await
             }
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Some("array") => {
-                tbon::de::read_from((), file)
+                tbon::de::read_from((), reader)
                     .map_ok(Self::Tensor)
                     .map_err(|cause| io::Error::new(io::ErrorKind::InvalidData, cause))
                     .await
             }
 
             Some("value") => {
-                tbon::de::read_from((), file)
+                tbon::de::read_from((), reader)
                     .map_ok(Self::Value)
                     .map_err(|cause| io::Error::new(io::ErrorKind::InvalidData, cause))
                     .await
@@ -72,11 +76,32 @@ impl freqfs::FileLoad for CacheBlock {
         }
     }
 
+    /// Encode this block.
+    pub(super) async fn encode(&self) -> Result<Bytes, io::Error> {
+        let mut encoded = Vec::new();
+        match self {
+            Self::BTree(node) => persist(node, &mut encoded).await,
+            Self::Chain(block) => persist(block, &mut encoded).await,
+            #[cfg(feature = "tc-tensor")]
+            Self::Tensor(array) => persist(array, &mut encoded).await,
+            Self::Value(value) => persist(value, &mut encoded).await,
+        }?;
+
+        Ok(encoded.into())
+    }
+}
+
+#[async_trait]
+impl freqfs::FileLoad for CacheBlock {
+    async fn load(path: &Path, file: fs::File, _metadata: Metadata) -> Result<Self, io::Error> {
+        Self::decode(path, file).await
+    }
+
     async fn save(&self, file: &mut fs::File) -> Result<u64, io::Error> {
         match self {
             Self::BTree(node) => persist(node, file).await,
             Self::Chain(block) => persist(block, file).await,
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(array) => persist(array, file).await,
             Self::Value(value) => persist(value, file).await,
         }
@@ -135,7 +160,7 @@ impl AsType<ChainBlock> for CacheBlock {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl AsType<Array> for CacheBlock {
     fn as_type(&self) -> Option<&Array> {
         if let Self::Tensor(array) = self {
@@ -188,6 +213,20 @@ impl AsType<Value> for CacheBlock {
     }
 }
 
+impl AsType<CacheBlock> for CacheBlock {
+    fn as_type(&self) -> Option<&CacheBlock> {
+        Some(self)
+    }
+
+    fn as_type_mut(&mut self) -> Option<&mut CacheBlock> {
+        Some(self)
+    }
+
+    fn into_type(self) -> Option<CacheBlock> {
+        Some(self)
+    }
+}
+
 impl From<Node> for CacheBlock {
     fn from(node: Node) -> Self {
         Self::BTree(node)
@@ -200,7 +239,7 @@ impl From<ChainBlock> for CacheBlock {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl From<Array> for CacheBlock {
     fn from(array: Array) -> Self {
         Self::Tensor(array)
@@ -213,10 +252,11 @@ impl From<Value> for CacheBlock {
     }
 }
 
-async fn persist<'en, T: en::ToStream<'en>>(
-    data: &'en T,
-    file: &mut fs::File,
-) -> Result<u64, io::Error> {
+async fn persist<'en, T, W>(data: &'en T, file: &mut W) -> Result<u64, io::Error>
+where
+    T: en::ToStream<'en>,
+    W: AsyncWrite + Send + Unpin,
+{
     let encoded = tbon::en::encode(data)
         .map_err(|cause| io::Error::new(io::ErrorKind::InvalidData, cause))?;
 
@@ -0,0 +1,287 @@
+//! A hard memory budget for the block cache.
+//!
+//! [`freqfs`] evicts blocks in the background once the cache grows past its capacity, but it does
+//! not stop new blocks from being loaded in the meantime, so a burst of reads (e.g. a large tensor
+//! scan) can grow the cache without bound. The budget here admits a block which is not already
+//! cached only while the cache is within its limit. Under pressure, it writes back the
+//! least-recently-used dirty blocks so that the background cleanup can evict them without waiting
+//! on disk I/O.
+//!
+//! The budget is process-wide, like the [`freqfs::Cache`] it guards.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use freqfs::FileLock;
+use log::{debug, warn};
+
+use tc_error::*;
+
+use super::{io_err, CacheBlock};
+
+/// The interval at which to check whether the cache has made room for a pending load.
+const PRESSURE_INTERVAL: Duration = Duration::from_millis(10);
+
+static BUDGET: Budget = Budget::new();
+
+/// Set the maximum size in bytes of the blocks loaded in the cache, and the maximum time to wait
+/// for room in the cache before failing a load.
+///
+/// A `limit` of zero (the default) disables the budget.
+pub fn set_cache_budget(limit: usize, timeout: Duration) {
+    BUDGET.set(limit, timeout)
+}
+
+/// Record an access to the given `block`, which is now loaded in the cache.
+pub(super) async fn touch(block: &FileLock<CacheBlock>) {
+    BUDGET.touch(block).await
+}
+
+/// Stop tracking the blocks at or under the given `path`, e.g. because they have been deleted.
+pub(super) fn forget(path: &Path) {
+    BUDGET.forget(path)
+}
+
+/// Wait until the cache is within its budget, so that a block which is not cached can be loaded.
+pub(super) async fn reserve() -> TCResult<()> {
+    BUDGET.reserve().await
+}
+
+struct Budget {
+    limit: AtomicUsize,
+    timeout: AtomicU64,
+    lru: Mutex<Lru>,
+}
+
+impl Budget {
+    const fn new() -> Self {
+        Self {
+            limit: AtomicUsize::new(0),
+            timeout: AtomicU64::new(0),
+            lru: Mutex::new(Lru::new()),
+        }
+    }
+
+    fn set(&self, limit: usize, timeout: Duration) {
+        self.limit.store(limit, Ordering::Relaxed);
+        self.timeout
+            .store(timeout.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    async fn touch(&self, block: &FileLock<CacheBlock>) {
+        if self.limit.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+
+        if let Some(size) = block.size_hint().await {
+            let mut lru = self.lru.lock().expect("block cache LRU");
+            lru.touch(block, size);
+        }
+    }
+
+    fn forget(&self, path: &Path) {
+        let mut lru = self.lru.lock().expect("block cache LRU");
+        lru.forget(path);
+    }
+
+    async fn reserve(&self) -> TCResult<()> {
+        let limit = self.limit.load(Ordering::Relaxed);
+        if limit == 0 {
+            return Ok(());
+        }
+
+        let timeout = Duration::from_millis(self.timeout.load(Ordering::Relaxed));
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let (occupancy, blocks) = {
+                let lru = self.lru.lock().expect("block cache LRU");
+                if lru.occupancy <= limit {
+                    return Ok(());
+                }
+
+                (lru.occupancy, lru.least_recent())
+            };
+
+            debug!(
+                "block cache occupancy {} exceeds its budget of {}",
+                occupancy, limit
+            );
+
+            let mut over = occupancy - limit;
+            let mut evicted = Vec::new();
+            for block in blocks {
+                if over == 0 {
+                    break;
+                }
+
+                if let Some(size) = block.size_hint().await {
+                    match block.sync(true).await {
+                        Ok(()) => over = over.saturating_sub(size),
+                        Err(cause) if cause.kind() == io::ErrorKind::WouldBlock => {}
+                        Err(cause) => return Err(io_err(cause)),
+                    }
+                } else {
+                    evicted.push(block.path().to_path_buf());
+                }
+            }
+
+            {
+                let mut lru = self.lru.lock().expect("block cache LRU");
+                for path in evicted {
+                    lru.forget(&path);
+                }
+            }
+
+            if Instant::now() >= deadline {
+                warn!("timed out waiting for room in the block cache");
+
+                return Err(TCError::timeout(format!(
+                    "the block cache is over its budget of {} bytes",
+                    limit
+                )));
+            }
+
+            tokio::time::sleep(PRESSURE_INTERVAL).await;
+        }
+    }
+}
+
+struct Lru {
+    tick: u64,
+    occupancy: usize,
+    order: BTreeMap<u64, PathBuf>,
+    blocks: BTreeMap<PathBuf, (u64, usize, FileLock<CacheBlock>)>,
+}
+
+impl Lru {
+    const fn new() -> Self {
+        Self {
+            tick: 0,
+            occupancy: 0,
+            order: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+        }
+    }
+
+    fn touch(&mut self, block: &FileLock<CacheBlock>, size: usize) {
+        self.tick += 1;
+
+        let path = block.path().to_path_buf();
+        if let Some((tick, old_size, _)) = self.blocks.remove(&path) {
+            self.order.remove(&tick);
+            self.occupancy -= old_size;
+        }
+
+        self.order.insert(self.tick, path.clone());
+        self.blocks.insert(path, (self.tick, size, block.clone()));
+        self.occupancy += size;
+    }
+
+    fn forget(&mut self, prefix: &Path) {
+        let paths: Vec<PathBuf> = self
+            .blocks
+            .range(prefix.to_path_buf()..)
+            .map(|(path, _)| path)
+            .take_while(|path| path.starts_with(prefix))
+            .cloned()
+            .collect();
+
+        for path in paths {
+            let (tick, size, _) = self.blocks.remove(&path).expect("cached block");
+            self.order.remove(&tick);
+            self.occupancy -= size;
+        }
+    }
+
+    fn least_recent(&self) -> Vec<FileLock<CacheBlock>> {
+        self.order
+            .values()
+            .map(|path| self.blocks[path].2.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use tc_value::Value;
+
+    use super::*;
+
+    const BLOCK_SIZE: usize = 1_000;
+
+    // create `count` blocks of `BLOCK_SIZE` bytes in a new cache of the given `capacity`
+    async fn create_blocks(capacity: usize, count: u64) -> Vec<FileLock<CacheBlock>> {
+        let path = std::env::temp_dir().join(format!("tc-cache-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&path).expect("test dir");
+
+        let cache = freqfs::Cache::new(capacity, Duration::from_millis(50));
+        let dir = cache.load(path).await.expect("cache dir");
+        let mut dir = dir.write().await;
+
+        (0..count)
+            .map(|i| {
+                let name = format!("{}.value", i);
+                dir.create_file(name, Value::from(i), Some(BLOCK_SIZE))
+                    .expect("block")
+            })
+            .collect()
+    }
+
+    fn occupancy(budget: &Budget) -> usize {
+        budget.lru.lock().expect("block cache LRU").occupancy
+    }
+
+    #[tokio::test]
+    async fn test_evict_over_limit() {
+        let budget = Budget::new();
+        budget.set(2 * BLOCK_SIZE, Duration::from_secs(5));
+
+        let blocks = create_blocks(2 * BLOCK_SIZE, 4).await;
+        for block in &blocks {
+            budget.touch(block).await;
+        }
+
+        assert_eq!(occupancy(&budget), 4 * BLOCK_SIZE);
+
+        budget.reserve().await.expect("reserve");
+        assert!(occupancy(&budget) <= 2 * BLOCK_SIZE);
+
+        let mut evicted = 0;
+        for (i, block) in blocks.into_iter().enumerate() {
+            if block.size_hint().await.is_none() {
+                evicted += 1;
+            }
+
+            // an evicted block was written back, so it can be loaded again
+            let value: freqfs::FileReadGuard<CacheBlock, Value> =
+                block.read().await.expect("block");
+
+            assert_eq!(*value, Value::from(i as u64));
+        }
+
+        assert!(evicted >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_admission_timeout() {
+        let budget = Budget::new();
+        budget.set(BLOCK_SIZE, Duration::from_millis(100));
+
+        // the cache itself has plenty of room, so it never evicts anything
+        let blocks = create_blocks(10 * BLOCK_SIZE, 2).await;
+        for block in &blocks {
+            budget.touch(block).await;
+        }
+
+        let err = budget.reserve().await.expect_err("over budget");
+        assert!(err.code() == ErrorType::Timeout);
+        assert_eq!(occupancy(&budget), 2 * BLOCK_SIZE);
+    }
+}
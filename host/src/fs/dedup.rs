@@ -0,0 +1,257 @@
+//! Optional deduplication of identical blocks in the data directory.
+//!
+//! When enabled, each block written back by a commit is hashed, and a block with the same contents
+//! as one already stored is replaced by a hard link to a single copy in the hidden [`BLOCKS`]
+//! directory of the data directory, named by its BLAKE3 hash. The number of links to a stored copy
+//! is its reference count.
+//!
+//! Every block is written back by writing a temporary file and renaming it into place, so writing
+//! to a deduplicated block replaces its link rather than modifying the shared copy, which makes
+//! deduplication transparent to the `File` API. A stored copy which no block links to any longer
+//! is deleted by [`collect_garbage`], which runs at startup and then every [`GC_INTERVAL`].
+//!
+//! Deduplication requires hard links, so it's only supported on Unix.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use freqfs::FileLock;
+use log::{debug, info, warn};
+use tokio::sync::RwLock;
+
+use tc_error::*;
+
+use super::CacheBlock;
+
+/// The name of the directory of deduplicated blocks in the data directory.
+pub const BLOCKS: &str = ".blocks";
+
+/// The interval at which to delete stored blocks which are no longer referenced.
+pub const GC_INTERVAL: Duration = Duration::from_secs(600);
+
+/// The suffix of a link to a stored block which was not yet renamed into place.
+pub(super) const PARTIAL_LINK: &str = "_dedup";
+
+// the directory of stored blocks, if deduplication is enabled--
+// deduplicating a block holds a read lock, and garbage collection holds the write lock
+static STORE: RwLock<Option<PathBuf>> = RwLock::const_new(None);
+
+/// Enable deduplication of the blocks committed to the given `data_dir`.
+#[cfg(unix)]
+pub async fn enable_dedup(data_dir: &Path) -> TCResult<()> {
+    let store = data_dir.join(BLOCKS);
+    tokio::fs::create_dir_all(&store).await.map_err(dedup_err)?;
+
+    {
+        let mut enabled = STORE.write().await;
+        if enabled.is_some() {
+            return Err(TCError::internal("block deduplication is already enabled"));
+        }
+
+        *enabled = Some(store);
+    }
+
+    collect_garbage().await?;
+
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(GC_INTERVAL);
+        ticks.tick().await;
+
+        loop {
+            ticks.tick().await;
+
+            if let Err(cause) = collect_garbage().await {
+                warn!("failed to delete unreferenced blocks: {}", cause);
+            }
+        }
+    });
+
+    info!("enabled block deduplication in {:?}", data_dir);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn enable_dedup(_data_dir: &Path) -> TCResult<()> {
+    Err(TCError::unsupported(
+        "block deduplication requires a filesystem with hard links",
+    ))
+}
+
+/// Delete each stored block which no block in the data directory links to,
+/// and return the number of stored blocks deleted.
+#[cfg(unix)]
+pub async fn collect_garbage() -> TCResult<usize> {
+    let store = STORE.write().await;
+    if let Some(store) = &*store {
+        collect(store).await
+    } else {
+        Ok(0)
+    }
+}
+
+#[cfg(unix)]
+async fn collect(store: &Path) -> TCResult<usize> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut deleted = 0;
+    let mut entries = tokio::fs::read_dir(store).await.map_err(dedup_err)?;
+    while let Some(entry) = entries.next_entry().await.map_err(dedup_err)? {
+        let metadata = entry.metadata().await.map_err(dedup_err)?;
+        if metadata.is_file() && metadata.nlink() == 1 {
+            tokio::fs::remove_file(entry.path())
+                .await
+                .map_err(dedup_err)?;
+
+            deleted += 1;
+        }
+    }
+
+    debug!("deleted {} unreferenced blocks", deleted);
+    Ok(deleted)
+}
+
+#[cfg(not(unix))]
+pub async fn collect_garbage() -> TCResult<usize> {
+    Ok(0)
+}
+
+/// Replace the given canonical `block`, which must already have been written back,
+/// with a link to a stored block with the same contents, if deduplication is enabled.
+pub(super) async fn dedup(block: &FileLock<CacheBlock>) -> Result<(), io::Error> {
+    let store = STORE.read().await;
+    if let Some(store) = &*store {
+        link(store, block).await
+    } else {
+        Ok(())
+    }
+}
+
+// replace the given `block` with a link to the block with the same contents in the given `store`
+async fn link(store: &Path, block: &FileLock<CacheBlock>) -> Result<(), io::Error> {
+    // the block can't be written back while it's locked for reading
+    let _guard = block.read::<CacheBlock>().await?;
+    let path = block.path();
+
+    let contents = match tokio::fs::read(path).await {
+        Ok(contents) => contents,
+        Err(cause) if cause.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(cause) => return Err(cause),
+    };
+
+    let stored = store.join(blake3::hash(&contents).to_hex().as_str());
+
+    // if there's no stored block with these contents, this block becomes the stored block
+    match tokio::fs::hard_link(path, &stored).await {
+        Ok(()) => return Ok(()),
+        Err(cause) if cause.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(cause) => return Err(cause),
+    }
+
+    if is_same_file(path, &stored).await? {
+        return Ok(());
+    }
+
+    let mut link = path.as_os_str().to_owned();
+    link.push(PARTIAL_LINK);
+
+    match tokio::fs::remove_file(&link).await {
+        Ok(()) => {}
+        Err(cause) if cause.kind() == io::ErrorKind::NotFound => {}
+        Err(cause) => return Err(cause),
+    }
+
+    tokio::fs::hard_link(&stored, &link).await?;
+    tokio::fs::rename(&link, path).await
+}
+
+#[cfg(unix)]
+async fn is_same_file(left: &Path, right: &Path) -> Result<bool, io::Error> {
+    use std::os::unix::fs::MetadataExt;
+
+    let left = tokio::fs::metadata(left).await?;
+    let right = tokio::fs::metadata(right).await?;
+    Ok(left.dev() == right.dev() && left.ino() == right.ino())
+}
+
+#[cfg(not(unix))]
+async fn is_same_file(_left: &Path, _right: &Path) -> Result<bool, io::Error> {
+    Ok(false)
+}
+
+fn dedup_err(cause: io::Error) -> TCError {
+    TCError::internal(format!("block deduplication I/O error: {}", cause))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::MetadataExt;
+
+    use uuid::Uuid;
+
+    use tc_value::Value;
+
+    use super::*;
+
+    async fn links(path: &Path) -> u64 {
+        tokio::fs::metadata(path).await.expect("metadata").nlink()
+    }
+
+    async fn stored(store: &Path) -> Vec<PathBuf> {
+        let mut stored = Vec::new();
+        let mut entries = tokio::fs::read_dir(store).await.expect("block store");
+        while let Some(entry) = entries.next_entry().await.expect("stored block") {
+            stored.push(entry.path());
+        }
+
+        stored
+    }
+
+    #[tokio::test]
+    async fn test_dedup_rewrite() {
+        let path = std::env::temp_dir().join(format!("tc-dedup-{}", Uuid::new_v4()));
+        let store = path.join(BLOCKS);
+        std::fs::create_dir_all(&store).expect("test dir");
+
+        let cache = freqfs::Cache::new(1_000_000, Duration::from_millis(50));
+        let dir = cache.load(path.clone()).await.expect("cache dir");
+
+        let (a, b) = {
+            let mut dir = dir.write().await;
+            let a = dir.create_file("a.value".to_string(), Value::from(1u64), None);
+            let b = dir.create_file("b.value".to_string(), Value::from(1u64), None);
+            (a.expect("block"), b.expect("block"))
+        };
+
+        a.sync(false).await.expect("write back");
+        b.sync(false).await.expect("write back");
+
+        // identical blocks share a single stored copy
+        link(&store, &a).await.expect("dedup");
+        link(&store, &b).await.expect("dedup");
+
+        let copies = stored(&store).await;
+        assert_eq!(copies.len(), 1);
+        assert_eq!(links(&copies[0]).await, 3);
+
+        // rewriting a block replaces its link, rather than modifying the stored copy
+        *a.write::<Value>().await.expect("block") = Value::from(2u64);
+        a.sync(false).await.expect("write back");
+        assert_eq!(links(&copies[0]).await, 2);
+
+        link(&store, &a).await.expect("dedup");
+        assert_eq!(stored(&store).await.len(), 2);
+
+        // a stored copy is only deleted once no block links to it
+        assert_eq!(collect(&store).await.expect("garbage collection"), 0);
+
+        tokio::fs::remove_file(b.path())
+            .await
+            .expect("delete block");
+        assert_eq!(collect(&store).await.expect("garbage collection"), 1);
+        assert!(!copies[0].exists());
+        assert_eq!(stored(&store).await.len(), 1);
+
+        std::fs::remove_dir_all(path).expect("clean up");
+    }
+}
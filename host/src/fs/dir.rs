@@ -3,6 +3,8 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::ops::{Deref, DerefMut};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use freqfs::DirLock;
@@ -13,20 +15,20 @@ use uuid::Uuid;
 
 use tc_btree::{BTreeType, Node};
 use tc_error::*;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 use tc_tensor::{Array, TensorType};
 use tc_transact::fs;
 use tc_transact::lock::TxnLock;
 use tc_transact::{Transact, TxnId};
 use tc_value::{Value, ValueType};
-use tcgeneric::{Id, PathSegment, TCBoxTryFuture};
+use tcgeneric::{Id, PathSegment, TCBoxFuture, TCBoxTryFuture};
 
 use crate::chain::{ChainBlock, ChainType};
 use crate::collection::CollectionType;
 use crate::scalar::ScalarType;
 use crate::state::StateType;
 
-use super::{io_err, CacheBlock, File};
+use super::{io_err, CacheBlock, File, FileSavepoint, Quota, Usage};
 
 #[derive(Clone)]
 pub enum FileEntry {
@@ -34,7 +36,7 @@ pub enum FileEntry {
     Chain(File<ChainBlock>),
     Value(File<Value>),
 
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     Tensor(File<Array>),
 }
 
@@ -52,7 +54,7 @@ impl FileEntry {
                 CollectionType::BTree(_) => File::new(cache).map_ok(Self::BTree).await,
                 CollectionType::Table(tt) => Err(err(tt)),
 
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 CollectionType::Tensor(tt) => match tt {
                     TensorType::Dense => File::new(cache).map_ok(Self::Tensor).await,
                     TensorType::Sparse => Err(err(TensorType::Sparse)),
@@ -80,7 +82,7 @@ impl FileEntry {
                 CollectionType::BTree(_) => File::load(cache, txn_id).map_ok(Self::BTree).await,
                 CollectionType::Table(tt) => Err(err(tt)),
 
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 CollectionType::Tensor(tt) => match tt {
                     TensorType::Dense => File::load(cache, txn_id).map_ok(Self::Tensor).await,
                     TensorType::Sparse => Err(err(TensorType::Sparse)),
@@ -94,6 +96,18 @@ impl FileEntry {
             other => Err(err(other)),
         }
     }
+
+    /// Return `true` if this file has been modified at the given [`TxnId`].
+    pub async fn is_touched(&self, txn_id: TxnId) -> bool {
+        match self {
+            Self::BTree(file) => file.is_touched(txn_id).await,
+            Self::Chain(file) => file.is_touched(txn_id).await,
+            Self::Value(file) => file.is_touched(txn_id).await,
+
+            #[cfg(feature = "tc-tensor")]
+            Self::Tensor(file) => file.is_touched(txn_id).await,
+        }
+    }
 }
 
 impl AsType<File<Node>> for FileEntry {
@@ -148,7 +162,7 @@ impl AsType<File<ChainBlock>> for FileEntry {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl AsType<File<Array>> for FileEntry {
     fn as_type(&self) -> Option<&File<Array>> {
         if let Self::Tensor(file) = self {
@@ -213,7 +227,7 @@ impl From<File<ChainBlock>> for FileEntry {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl From<File<Array>> for FileEntry {
     fn from(file: File<Array>) -> Self {
         Self::Tensor(file)
@@ -233,7 +247,7 @@ impl fmt::Display for FileEntry {
             Self::Chain(chain) => fmt::Display::fmt(chain, f),
             Self::Value(value) => fmt::Display::fmt(value, f),
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tensor) => fmt::Display::fmt(tensor, f),
         }
     }
@@ -353,6 +367,287 @@ impl Dir {
             fs::Dir::create_dir(self, txn_id, name).await
         }
     }
+
+    /// Get or create a hidden subdirectory which is not versioned by transaction,
+    /// for data which must be written to disk independently of any transaction.
+    pub async fn get_or_create_hidden(&self, name: &str) -> TCResult<DirLock<CacheBlock>> {
+        if !name.starts_with('.') {
+            return Err(TCError::internal(format!(
+                "hidden directory name must begin with '.', not {}",
+                name
+            )));
+        }
+
+        let mut cache = self.cache.write().await;
+        cache.get_or_create_dir(name.to_string()).map_err(io_err)
+    }
+
+    /// Save the state of this `Dir` and its contents as of the given [`TxnId`].
+    ///
+    /// Only the entries modified at the given [`TxnId`] are saved, since the rest are unchanged.
+    pub fn savepoint<'a>(&'a self, txn_id: TxnId) -> TCBoxTryFuture<'a, DirSavepoint> {
+        Box::pin(async move {
+            // a Dir with no version at this txn_id has never been read by it
+            let contents = match self.contents.savepoint(txn_id)? {
+                Some(contents) => contents,
+                None => return Ok(DirSavepoint::default()),
+            };
+
+            let mut entries = HashMap::new();
+            for (name, entry) in contents.iter() {
+                if let DirEntry::File(file) = entry {
+                    if !file.is_touched(txn_id).await {
+                        continue;
+                    }
+                }
+
+                let savepoint = match entry {
+                    DirEntry::Dir(dir) => dir.savepoint(txn_id).map_ok(EntrySavepoint::Dir).await,
+                    DirEntry::File(file) => match file {
+                        FileEntry::BTree(file) => {
+                            file.savepoint(txn_id).map_ok(EntrySavepoint::BTree).await
+                        }
+                        FileEntry::Chain(file) => {
+                            file.savepoint(txn_id).map_ok(EntrySavepoint::Chain).await
+                        }
+                        FileEntry::Value(file) => {
+                            file.savepoint(txn_id).map_ok(EntrySavepoint::Value).await
+                        }
+
+                        #[cfg(feature = "tc-tensor")]
+                        FileEntry::Tensor(file) => {
+                            file.savepoint(txn_id).map_ok(EntrySavepoint::Tensor).await
+                        }
+                    },
+                }?;
+
+                entries.insert(name.clone(), savepoint);
+            }
+
+            let contents = if self.contents.is_pending(&txn_id) {
+                Some(contents)
+            } else {
+                None
+            };
+
+            Ok(DirSavepoint { contents, entries })
+        })
+    }
+
+    /// Discard any changes to this `Dir` made at the given [`TxnId`] since the `savepoint`.
+    pub fn rollback<'a>(
+        &'a self,
+        txn_id: TxnId,
+        savepoint: DirSavepoint,
+    ) -> TCBoxTryFuture<'a, ()> {
+        Box::pin(async move {
+            debug!("Dir::rollback at {}", txn_id);
+
+            let DirSavepoint {
+                contents,
+                mut entries,
+            } = savepoint;
+
+            let current = match self.contents.savepoint(txn_id)? {
+                Some(current) => current,
+                None => return Ok(()),
+            };
+
+            if self.contents.is_pending(&txn_id) {
+                self.contents.rollback(txn_id, contents)?;
+            }
+
+            // roll back every entry present now, including entries created since the savepoint
+            for (name, entry) in current.iter() {
+                if let DirEntry::File(file) = entry {
+                    if !file.is_touched(txn_id).await {
+                        continue;
+                    }
+                }
+
+                rollback_entry(entry, txn_id, entries.remove(name)).await?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Commit the subdirectory at the given `path`, and its entry in each of its ancestors,
+    /// at the given [`TxnId`] without committing any other entry of this `Dir`.
+    pub fn commit_subdir<'a>(
+        &'a self,
+        txn_id: &'a TxnId,
+        path: &'a [PathSegment],
+    ) -> TCBoxFuture<'a, ()> {
+        Box::pin(async move {
+            let (name, suffix) = match path.split_first() {
+                Some(split) => split,
+                None => return self.commit(txn_id).await,
+            };
+
+            let subdir = fs::Dir::get_dir(self, *txn_id, name)
+                .await
+                .expect("subdirectory to commit");
+
+            self.contents.commit(txn_id).await;
+
+            if let Some(subdir) = subdir {
+                subdir.commit_subdir(txn_id, suffix).await;
+            }
+        })
+    }
+
+    /// Finalize the given [`TxnId`]s without acquiring a read lock on the contents of this `Dir`,
+    /// so that expired transactions which never committed can be finalized in a single walk.
+    pub fn reclaim<'a>(&'a self, txn_ids: &'a [TxnId]) -> TCBoxFuture<'a, ()> {
+        Box::pin(async move {
+            debug!("Dir::reclaim {:?}", txn_ids);
+
+            let mut entries: HashMap<PathSegment, DirEntry> = self.contents.canon().inner;
+
+            // a transaction with an active write lock on this Dir will have no version to save
+            for txn_id in txn_ids {
+                if let Ok(Some(version)) = self.contents.savepoint(*txn_id) {
+                    entries.extend(version.inner);
+                }
+            }
+
+            join_all(entries.values().map(|entry| async move {
+                match entry {
+                    DirEntry::Dir(dir) => dir.reclaim(txn_ids).await,
+                    DirEntry::File(file) => {
+                        for txn_id in txn_ids {
+                            match file {
+                                FileEntry::BTree(file) => file.finalize(txn_id).await,
+                                FileEntry::Chain(file) => file.finalize(txn_id).await,
+                                FileEntry::Value(file) => file.finalize(txn_id).await,
+
+                                #[cfg(feature = "tc-tensor")]
+                                FileEntry::Tensor(file) => file.finalize(txn_id).await,
+                            }
+                        }
+                    }
+                }
+            }))
+            .await;
+
+            for txn_id in txn_ids {
+                self.contents.finalize(txn_id).await;
+            }
+        })
+    }
+
+    /// Return the total size of the files in this `Dir` which are currently loaded in the cache.
+    pub fn cache_occupancy(&self) -> TCBoxFuture<'_, usize> {
+        super::cache_occupancy(&self.cache)
+    }
+
+    /// Return the resource [`Quota`] of this `Dir`, if any.
+    pub async fn quota(&self) -> Option<Quota> {
+        super::quota::get(&self.cache).await
+    }
+
+    /// Limit the resources used by this `Dir` to the given [`Quota`],
+    /// or remove its quota if the given `quota` has no limits.
+    pub async fn set_quota(&self, quota: Quota) {
+        super::quota::set(&self.cache, quota).await
+    }
+
+    /// Measure the resources currently used by this `Dir`.
+    pub async fn usage(&self) -> TCResult<Usage> {
+        super::quota::usage(&self.cache).await
+    }
+
+    /// Count the transaction-specific block versions in this `Dir` as they're created and
+    /// finalized, starting from a single walk, so that they can be reported without walking it again.
+    pub async fn track_pending_versions(&self) -> Arc<AtomicUsize> {
+        let path = self.cache.read().await.path().to_path_buf();
+        let count = self.pending_versions().await;
+        super::pending::track(path, count).await
+    }
+
+    /// Return the number of transaction-specific block versions in this `Dir` not yet finalized.
+    pub fn pending_versions<'a>(&'a self) -> TCBoxFuture<'a, usize> {
+        Box::pin(async move {
+            let mut pending = 0;
+            for entry in self.contents.canon().values() {
+                pending += match entry {
+                    DirEntry::Dir(dir) => dir.pending_versions().await,
+                    DirEntry::File(file) => match file {
+                        FileEntry::BTree(file) => file.pending_versions().await,
+                        FileEntry::Chain(file) => file.pending_versions().await,
+                        FileEntry::Value(file) => file.pending_versions().await,
+
+                        #[cfg(feature = "tc-tensor")]
+                        FileEntry::Tensor(file) => file.pending_versions().await,
+                    },
+                };
+            }
+
+            pending
+        })
+    }
+}
+
+/// The state of a [`Dir`] at a savepoint within a transaction.
+#[derive(Clone, Default)]
+pub struct DirSavepoint {
+    contents: Option<Contents>,
+    entries: HashMap<PathSegment, EntrySavepoint>,
+}
+
+#[derive(Clone)]
+enum EntrySavepoint {
+    Dir(DirSavepoint),
+    BTree(FileSavepoint<Node>),
+    Chain(FileSavepoint<ChainBlock>),
+    Value(FileSavepoint<Value>),
+
+    #[cfg(feature = "tc-tensor")]
+    Tensor(FileSavepoint<Array>),
+}
+
+async fn rollback_entry(
+    entry: &DirEntry,
+    txn_id: TxnId,
+    savepoint: Option<EntrySavepoint>,
+) -> TCResult<()> {
+    match (entry, savepoint) {
+        (DirEntry::Dir(dir), Some(EntrySavepoint::Dir(savepoint))) => {
+            dir.rollback(txn_id, savepoint).await
+        }
+        (DirEntry::Dir(dir), None) => dir.rollback(txn_id, DirSavepoint::default()).await,
+        (DirEntry::File(file), savepoint) => match (file, savepoint) {
+            (FileEntry::BTree(file), Some(EntrySavepoint::BTree(savepoint))) => {
+                file.rollback(txn_id, savepoint).await
+            }
+            (FileEntry::BTree(file), None) => file.rollback(txn_id, Default::default()).await,
+            (FileEntry::Chain(file), Some(EntrySavepoint::Chain(savepoint))) => {
+                file.rollback(txn_id, savepoint).await
+            }
+            (FileEntry::Chain(file), None) => file.rollback(txn_id, Default::default()).await,
+            (FileEntry::Value(file), Some(EntrySavepoint::Value(savepoint))) => {
+                file.rollback(txn_id, savepoint).await
+            }
+            (FileEntry::Value(file), None) => file.rollback(txn_id, Default::default()).await,
+
+            #[cfg(feature = "tc-tensor")]
+            (FileEntry::Tensor(file), Some(EntrySavepoint::Tensor(savepoint))) => {
+                file.rollback(txn_id, savepoint).await
+            }
+            #[cfg(feature = "tc-tensor")]
+            (FileEntry::Tensor(file), None) => file.rollback(txn_id, Default::default()).await,
+
+            (file, Some(_)) => Err(TCError::internal(format!(
+                "savepoint does not match {}",
+                file
+            ))),
+        },
+        (dir, Some(_)) => Err(TCError::internal(format!(
+            "savepoint does not match {}",
+            dir
+        ))),
+    }
 }
 
 #[async_trait]
@@ -506,7 +801,7 @@ impl Transact for Dir {
                 FileEntry::Chain(file) => file.commit(txn_id),
                 FileEntry::Value(file) => file.commit(txn_id),
 
-                #[cfg(feature = "tensor")]
+                #[cfg(feature = "tc-tensor")]
                 FileEntry::Tensor(file) => file.commit(txn_id),
             },
         }))
@@ -523,7 +818,7 @@ impl Transact for Dir {
                     FileEntry::Chain(file) => file.finalize(txn_id),
                     FileEntry::Value(file) => file.finalize(txn_id),
 
-                    #[cfg(feature = "tensor")]
+                    #[cfg(feature = "tc-tensor")]
                     FileEntry::Tensor(file) => file.finalize(txn_id),
                 },
             }))
@@ -572,7 +867,7 @@ async fn is_file(name: &str, fs_cache: &DirLock<CacheBlock>) -> bool {
     true
 }
 
-fn file_class(name: &str) -> TCResult<(PathSegment, StateType)> {
+pub(super) fn file_class(name: &str) -> TCResult<(PathSegment, StateType)> {
     let i = name
         .rfind('.')
         .ok_or_else(|| TCError::internal(format!("invalid file name {}", name)))?;
@@ -583,7 +878,7 @@ fn file_class(name: &str) -> TCResult<(PathSegment, StateType)> {
     Ok((stem, class))
 }
 
-fn ext_class(name: &str) -> Option<StateType> {
+pub(super) fn ext_class(name: &str) -> Option<StateType> {
     if name.ends_with('.') {
         return None;
     }
@@ -593,7 +888,7 @@ fn ext_class(name: &str) -> Option<StateType> {
     match &name[i..] {
         "node" => Some(BTreeType::default().into()),
         "chain_block" => Some(ChainType::default().into()),
-        #[cfg(feature = "tensor")]
+        #[cfg(feature = "tc-tensor")]
         "array" => Some(TensorType::Dense.into()),
         "value" => Some(ValueType::default().into()),
         _ => None,
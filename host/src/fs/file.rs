@@ -17,9 +17,11 @@ use uuid::Uuid;
 use tc_error::*;
 use tc_transact::fs;
 use tc_transact::lock::{TxnLock, TxnLockReadGuard, TxnLockWriteGuard};
-use tc_transact::{Transact, TxnId};
+use tc_transact::{Isolation, Transact, TxnId};
 
-use super::{io_err, CacheBlock, VERSION};
+use crate::metrics;
+
+use super::{cache, dedup, flush, io_err, pending, quota, wal, CacheBlock, VERSION};
 
 type Blocks = HashMap<fs::BlockId, TxnLock<TxnId>>;
 
@@ -50,6 +52,24 @@ impl<B> Clone for File<B> {
     }
 }
 
+/// The state of a [`File`] at a savepoint within a transaction.
+#[derive(Clone)]
+pub struct FileSavepoint<B> {
+    present: Option<HashSet<fs::BlockId>>,
+    mutations: HashMap<fs::BlockId, TxnId>,
+    versions: HashMap<String, (B, Option<usize>)>,
+}
+
+impl<B> Default for FileSavepoint<B> {
+    fn default() -> Self {
+        Self {
+            present: None,
+            mutations: HashMap::new(),
+            versions: HashMap::new(),
+        }
+    }
+}
+
 impl<B: fs::BlockData> File<B>
 where
     CacheBlock: AsType<B>,
@@ -77,13 +97,14 @@ where
 
         let mut blocks = HashMap::new();
         let mut present = HashSet::new();
-        let mut version = versions
-            .write()
-            .await
-            .create_dir(txn_id.to_string())
-            .map_err(io_err)?
-            .write()
-            .await;
+        let mut version = {
+            let mut versions = versions.write().await;
+            let version = versions.create_dir(txn_id.to_string()).map_err(io_err)?;
+            pending::created(&versions.path().join(txn_id.to_string())).await;
+            version
+        }
+        .write()
+        .await;
 
         for (name, block) in fs_dir.iter() {
             if name.starts_with('.') {
@@ -138,6 +159,96 @@ where
         })
     }
 
+    /// Return `true` if this `File` has been modified at the given [`TxnId`].
+    pub async fn is_touched(&self, txn_id: TxnId) -> bool {
+        if self.present.is_pending(&txn_id) {
+            return true;
+        }
+
+        let blocks = self.blocks.read().await;
+        blocks
+            .values()
+            .any(|last_mutation| last_mutation.is_pending(&txn_id))
+    }
+
+    /// Save the state of this `File` as of the given [`TxnId`].
+    pub async fn savepoint(&self, txn_id: TxnId) -> TCResult<FileSavepoint<B>> {
+        let present = self.present.savepoint(txn_id)?;
+
+        let mut mutations = HashMap::new();
+        for (block_id, last_mutation) in self.blocks.read().await.iter() {
+            if let Some(last_mutation) = last_mutation.savepoint(txn_id)? {
+                mutations.insert(block_id.clone(), last_mutation);
+            }
+        }
+
+        let mut versions = HashMap::new();
+        let version = self
+            .versions
+            .read()
+            .await
+            .get_dir(&txn_id.to_string())
+            .cloned();
+        if let Some(version) = version {
+            for (name, block) in version.read().await.iter() {
+                if let DirEntry::File(block) = block {
+                    let size_hint = block.size_hint().await;
+                    let contents = block
+                        .read()
+                        .map_ok(|contents| B::clone(&*contents))
+                        .map_err(io_err)
+                        .await?;
+
+                    versions.insert(name.clone(), (contents, size_hint));
+                }
+            }
+        }
+
+        Ok(FileSavepoint {
+            present,
+            mutations,
+            versions,
+        })
+    }
+
+    /// Discard any changes to this `File` made at the given [`TxnId`] since the `savepoint`.
+    pub async fn rollback(&self, txn_id: TxnId, savepoint: FileSavepoint<B>) -> TCResult<()> {
+        debug!("File::rollback at {}", txn_id);
+
+        let FileSavepoint {
+            present,
+            mut mutations,
+            versions,
+        } = savepoint;
+
+        self.present.rollback(txn_id, present)?;
+
+        for (block_id, last_mutation) in self.blocks.read().await.iter() {
+            last_mutation.rollback(txn_id, mutations.remove(block_id))?;
+        }
+
+        let mut version = self.version_write(&txn_id).await?;
+        flush::forget(version.path()).await;
+
+        let names: Vec<String> = version.iter().map(|(name, _)| name.clone()).collect();
+        for name in names {
+            version.delete(name);
+        }
+
+        for (name, (contents, size_hint)) in versions {
+            version
+                .create_file(name, contents, size_hint)
+                .map_err(io_err)?;
+        }
+
+        Ok(())
+    }
+
+    /// Return the number of transaction-specific block versions of this `File` not yet finalized.
+    pub async fn pending_versions(&self) -> usize {
+        self.versions.read().await.len()
+    }
+
     async fn block_read(
         &self,
         txn_id: TxnId,
@@ -187,6 +298,7 @@ where
         let name = Self::file_name(&block_id);
         if let Some(block) = self.version_read(&txn_id).await?.get_file(&name) {
             debug!("read existing version of block {} at {}", block_id, txn_id);
+            admit(&block).await?;
             return Ok(block);
         }
 
@@ -204,14 +316,15 @@ where
                 .await;
 
             let block_version = last_version.get_file(&name).expect("block prior value");
-            let size_hint = block_version.size_hint().await;
+            admit(&block_version).await?;
 
             let value = {
                 let value = block_version.read().map_err(io_err).await?;
                 B::clone(&*value)
             };
 
-            (size_hint, value)
+            cache::touch(&block_version).await;
+            (block_version.size_hint().await, value)
         };
 
         let block = self
@@ -222,6 +335,7 @@ where
 
         debug!("created new version of block {} at {}", block_id, txn_id);
 
+        cache::touch(&block).await;
         Ok(block)
     }
 
@@ -234,6 +348,7 @@ where
 
         if let Some(block) = self.version_read(&txn_id).await?.get_file(&name) {
             debug!("read existing version of block {} at {}", block_id, txn_id);
+            admit(&block).await?;
             return Ok(block);
         }
 
@@ -247,12 +362,16 @@ where
             .get_file(&name)
             .expect("canonical block");
 
-        let size_hint = block_canon.size_hint().await;
+        admit(&block_canon).await?;
+
         let value = {
             let value = block_canon.read().map_err(io_err).await?;
             B::clone(&*value)
         };
 
+        cache::touch(&block_canon).await;
+        let size_hint = block_canon.size_hint().await;
+
         let block = self
             .version_write(&txn_id)
             .await?
@@ -261,25 +380,31 @@ where
 
         debug!("created new version of block {} at {}", block_id, txn_id);
 
+        cache::touch(&block).await;
         Ok(block)
     }
 
     async fn version_read(&self, txn_id: &TxnId) -> TCResult<DirReadGuard<CacheBlock>> {
-        let mut versions = self.versions.write().await;
-        let version = versions
-            .get_or_create_dir(txn_id.to_string())
-            .map_err(io_err)?;
-
+        let version = self.version(txn_id).await?;
         version.read().map(Ok).await
     }
 
     async fn version_write(&self, txn_id: &TxnId) -> TCResult<DirWriteGuard<CacheBlock>> {
+        let version = self.version(txn_id).await?;
+        version.write().map(Ok).await
+    }
+
+    async fn version(&self, txn_id: &TxnId) -> TCResult<DirLock<CacheBlock>> {
         let mut versions = self.versions.write().await;
-        let version = versions
-            .get_or_create_dir(txn_id.to_string())
-            .map_err(io_err)?;
+        let name = txn_id.to_string();
 
-        version.write().map(Ok).await
+        if let Some(version) = versions.get_dir(&name) {
+            return Ok(version.clone());
+        }
+
+        let version = versions.create_dir(name).map_err(io_err)?;
+        pending::created(&versions.path().join(txn_id.to_string())).await;
+        Ok(version)
     }
 
     fn file_name(block_id: &fs::BlockId) -> String {
@@ -366,6 +491,8 @@ where
         size_hint: usize,
     ) -> TCResult<Self::Write> {
         debug!("File::create_block {}", block_id);
+        quota::allocate(&self.canon, size_hint).await?;
+        cache::reserve().await?;
 
         let present = self.present.write(txn_id).await?;
         if present.contains(&block_id) {
@@ -386,6 +513,8 @@ where
         )
         .await?;
 
+        cache::touch(&block).await;
+        flush::mark_dirty(&block);
         block.write().map_err(io_err).await
     }
 
@@ -396,6 +525,8 @@ where
         size_hint: usize,
     ) -> TCResult<(fs::BlockId, Self::Write)> {
         debug!("File::create_block_tmp");
+        quota::allocate(&self.canon, size_hint).await?;
+        cache::reserve().await?;
 
         let present = self.present.write(txn_id).await?;
         let block_id = loop {
@@ -419,6 +550,8 @@ where
         )
         .await?;
 
+        cache::touch(&block).await;
+        flush::mark_dirty(&block);
         let lock = block.write().map_err(io_err).await?;
         Ok((block_id, lock))
     }
@@ -434,13 +567,16 @@ where
             // keep the version directory in sync in case create_block is called later
             // with the same block_id
             let mut version = self.version_write(&txn_id).await?;
-            version.delete(Self::file_name(&block_id));
+            let name = Self::file_name(&block_id);
+            flush::forget(&version.path().join(&name)).await;
+            version.delete(name);
         }
 
         present.remove(&block_id);
         Ok(())
     }
 
+    #[tracing::instrument(level = "trace", skip_all, fields(%txn_id, %block_id, ext = B::ext()))]
     async fn read_block(
         &self,
         txn_id: TxnId,
@@ -452,7 +588,53 @@ where
             .read_block_inner(txn_id, block_id, &*last_mutation)
             .await?;
 
-        block.read().map_err(io_err).await
+        metrics::record_block_io(B::ext(), false);
+        let guard = block.read().map_err(io_err).await?;
+        cache::touch(&block).await;
+        Ok(guard)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all, fields(%txn_id, %block_id, ext = B::ext()))]
+    async fn read_block_isolated(
+        &self,
+        txn_id: TxnId,
+        block_id: fs::BlockId,
+        isolation: Isolation,
+    ) -> TCResult<FileReadGuard<CacheBlock, B>> {
+        if isolation == Isolation::Snapshot {
+            return self.read_block(txn_id, block_id).await;
+        }
+
+        debug!("File::read_block {} ({})", block_id, isolation);
+
+        {
+            let present = self.present.read_isolated(txn_id, isolation).await?;
+            if !present.contains(&block_id) {
+                return Err(TCError::not_found(block_id));
+            }
+        }
+
+        let name = Self::file_name(&block_id);
+
+        // read this transaction's own writes, if any
+        let version = self.version_read(&txn_id).await?.get_file(&name);
+
+        let block = if let Some(block) = version {
+            block
+        } else {
+            // otherwise read the latest commit, without waiting on writes pending elsewhere
+            self.canon
+                .read()
+                .await
+                .get_file(&name)
+                .ok_or_else(TCError::conflict)?
+        };
+
+        admit(&block).await?;
+        metrics::record_block_io(B::ext(), false);
+        let guard = block.read().map_err(io_err).await?;
+        cache::touch(&block).await;
+        Ok(guard)
     }
 
     async fn read_block_owned(
@@ -463,6 +645,7 @@ where
         self.read_block(txn_id, block_id).await
     }
 
+    #[tracing::instrument(level = "trace", skip_all, fields(%txn_id, %block_id, ext = B::ext()))]
     async fn write_block(
         &self,
         txn_id: TxnId,
@@ -473,12 +656,17 @@ where
         *last_mutation = txn_id;
 
         let block = self.write_block_inner(txn_id, block_id.clone()).await?;
-        block.write().map_err(io_err).await
+        metrics::record_block_io(B::ext(), true);
+        let guard = block.write().map_err(io_err).await?;
+        cache::touch(&block).await;
+        flush::mark_dirty(&block);
+        Ok(guard)
     }
 
     async fn truncate(&self, txn_id: TxnId) -> TCResult<()> {
         let mut contents = self.present.write(txn_id).await?;
         let mut version = self.version_write(&txn_id).await?;
+        flush::forget(version.path()).await;
         for block_id in contents.drain() {
             version.delete(Self::file_name(&block_id));
         }
@@ -514,13 +702,48 @@ where
                 .await
                 .expect("file block versions");
 
+            // the block versions are copied to the canonical blocks below,
+            // so there's no need to write them back separately
+            flush::forget(version.path()).await;
+
             let mut canon = self.canon.write().await;
+
+            // if this file is covered by the write-ahead log, log the whole commit before
+            // updating any canonical block, and defer writing the blocks back to a checkpoint
+            let logged = if wal::is_logged(canon.path()).await {
+                let mut entries = Vec::with_capacity(blocks.len());
+                for (block_id, last_mutation) in blocks.iter() {
+                    let name = Self::file_name(block_id);
+                    let path = canon.path().join(&name);
+                    if !present.contains(block_id) {
+                        entries.push((path, None));
+                    } else if last_mutation.canon() == *txn_id {
+                        if let Some(version) = version.get_file(&name) {
+                            let block = version.read().await.expect("block version");
+                            entries.push((path, Some(block.clone())));
+                        }
+                    }
+                }
+
+                let logged = wal::log_commit(txn_id, entries)
+                    .await
+                    .expect("write-ahead log");
+
+                Some(logged)
+            } else {
+                None
+            };
+
             let mut deleted = Vec::with_capacity(blocks.len());
             let mut synchronize = Vec::with_capacity(present.len());
-            for block_id in blocks.keys() {
+            let mut deferred = Vec::with_capacity(present.len());
+            for (block_id, last_mutation) in blocks.iter() {
                 let name = Self::file_name(block_id);
                 if present.contains(block_id) {
-                    if let Some(version) = version.get_file(&name) {
+                    if last_mutation.canon() != *txn_id {
+                        // a version created only to read the block is identical to the canon
+                        continue;
+                    } else if let Some(version) = version.get_file(&name) {
                         let block = version.read().await.expect("block version");
                         let canon = if let Some(canon) = canon.get_file(&name) {
                             *canon.write().await.expect("canonical block") = block.clone();
@@ -532,11 +755,19 @@ where
                                 .expect("new canonical block")
                         };
 
-                        synchronize.push(async move { canon.sync(true).await });
+                        if logged.is_some() {
+                            deferred.push(canon);
+                        } else {
+                            synchronize.push(async move {
+                                canon.sync(true).await?;
+                                dedup::dedup(&canon).await
+                            });
+                        }
                     } else {
                         debug!("block {} has no version to commit at {}", block_id, txn_id);
                     }
                 } else {
+                    cache::forget(&canon.path().join(&name));
                     canon.delete(name);
                     deleted.push(block_id.clone());
                 }
@@ -549,6 +780,12 @@ where
             try_join_all(synchronize)
                 .await
                 .expect("sync block contents to disk");
+
+            if let Some(logged) = logged {
+                wal::defer(logged, deferred)
+                    .await
+                    .expect("defer block write-back to a checkpoint");
+            }
         }
 
         try_join!(self.canon.sync(false), self.versions.sync(false))
@@ -557,7 +794,14 @@ where
 
     async fn finalize(&self, txn_id: &TxnId) {
         let mut versions = self.versions.write().await;
-        versions.delete(txn_id.to_string());
+        let version = versions.path().join(txn_id.to_string());
+        flush::forget(&version).await;
+        cache::forget(&version);
+
+        if versions.get_dir(&txn_id.to_string()).is_some() {
+            versions.delete(txn_id.to_string());
+            pending::deleted(&version).await;
+        }
 
         let blocks = self.blocks.read().await;
         join_all(
@@ -577,6 +821,18 @@ impl<B: Send + Sync + 'static> fmt::Display for File<B> {
     }
 }
 
+/// Record an access to the given `block`, and make room for it in the cache if it's not loaded.
+async fn admit(block: &FileLock<CacheBlock>) -> TCResult<()> {
+    let cached = block.size_hint().await.is_some();
+    metrics::record_cache_access(cached);
+
+    if cached {
+        Ok(())
+    } else {
+        cache::reserve().await
+    }
+}
+
 async fn create_block_inner<'a, B: fs::BlockData + 'a>(
     mut present: TxnLockWriteGuard<HashSet<fs::BlockId>>,
     mut blocks: RwLockWriteGuard<'a, Blocks>,
@@ -0,0 +1,141 @@
+//! Background write-back of dirty blocks.
+//!
+//! A transaction which writes to a block only modifies its own version of the block, in the cache,
+//! so repeated writes to the same block within a transaction already coalesce in memory. The
+//! flusher keeps a queue of the block versions which have been written to since they were last
+//! persisted, keyed by path so that a block is queued at most once however many times it's
+//! written, and writes them back to disk in the background while the transaction is still running.
+//!
+//! This means that a large transaction does not accumulate an unbounded amount of dirty data in the
+//! cache, which would otherwise have to be written back synchronously when it's evicted.
+//!
+//! The flusher is process-wide, like the [`freqfs::Cache`] whose blocks it writes back.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use freqfs::FileLock;
+use futures::future;
+use futures::stream::{self, StreamExt};
+use log::{debug, warn};
+
+use super::CacheBlock;
+
+/// The maximum number of blocks to write back concurrently.
+const CONCURRENCY: usize = 16;
+
+static FLUSHER: Flusher = Flusher::new();
+
+/// Start writing back dirty blocks in the background at the given `interval`.
+///
+/// An `interval` of zero (the default) disables background write-back, in which case dirty blocks
+/// are only written to disk when they're committed or evicted.
+pub fn start_flusher(interval: Duration) {
+    let millis = interval.as_millis() as u64;
+    if millis == 0 || FLUSHER.interval.swap(millis, Ordering::Relaxed) != 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            flush().await;
+        }
+    });
+}
+
+/// Queue the given `block` to be written back to disk.
+pub(super) fn mark_dirty(block: &FileLock<CacheBlock>) {
+    if FLUSHER.interval.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+
+    let mut queue = FLUSHER.queue.lock().expect("block flush queue");
+    queue.insert(block.path().to_path_buf(), block.clone());
+}
+
+/// Stop writing back the blocks at or under the given `path`, e.g. because they're about to be
+/// deleted, waiting for any write-back already in progress to finish.
+pub(super) async fn forget(prefix: &Path) {
+    if FLUSHER.interval.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+
+    // a block which is written back after its file is deleted would be re-created on disk
+    let _flushing = FLUSHER.flushing.lock().await;
+
+    let mut queue = FLUSHER.queue.lock().expect("block flush queue");
+    let paths: Vec<PathBuf> = queue
+        .range(prefix.to_path_buf()..)
+        .map(|(path, _)| path)
+        .take_while(|path| path.starts_with(prefix))
+        .cloned()
+        .collect();
+
+    for path in paths {
+        queue.remove(&path);
+    }
+}
+
+/// Write back the queued blocks which are not currently locked.
+async fn flush() {
+    let _flushing = FLUSHER.flushing.lock().await;
+
+    let blocks = {
+        let mut queue = FLUSHER.queue.lock().expect("block flush queue");
+        mem::take(&mut *queue)
+    };
+
+    if blocks.is_empty() {
+        return;
+    }
+
+    debug!("writing back {} dirty blocks", blocks.len());
+
+    let busy: Vec<(PathBuf, FileLock<CacheBlock>)> = stream::iter(blocks)
+        .map(|(path, block)| async move {
+            match block.sync(true).await {
+                Ok(()) => None,
+                Err(cause) if cause.kind() == io::ErrorKind::WouldBlock => Some((path, block)),
+                Err(cause) => {
+                    warn!("failed to write back block {:?}: {}", path, cause);
+                    Some((path, block))
+                }
+            }
+        })
+        .buffer_unordered(CONCURRENCY)
+        .filter_map(future::ready)
+        .collect()
+        .await;
+
+    if !busy.is_empty() {
+        // a block which is still locked will be written back at the next interval,
+        // together with any further writes made to it in the meantime
+        let mut queue = FLUSHER.queue.lock().expect("block flush queue");
+        for (path, block) in busy {
+            queue.entry(path).or_insert(block);
+        }
+    }
+}
+
+struct Flusher {
+    interval: AtomicU64,
+    flushing: tokio::sync::Mutex<()>,
+    queue: Mutex<BTreeMap<PathBuf, FileLock<CacheBlock>>>,
+}
+
+impl Flusher {
+    const fn new() -> Self {
+        Self {
+            interval: AtomicU64::new(0),
+            flushing: tokio::sync::Mutex::const_new(()),
+            queue: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
@@ -3,17 +3,35 @@
 use std::io;
 use std::path::Path;
 
+use freqfs::{DirEntry, DirLock};
+
 use tc_error::*;
-use tcgeneric::{label, Label};
+use tcgeneric::{label, Label, TCBoxFuture};
 
+pub use backup::{backup, commit_permit, restore, Manifest, MANIFEST};
 pub use block::*;
+pub use cache::set_cache_budget;
+pub use dedup::{collect_garbage, enable_dedup, BLOCKS};
 pub use dir::*;
 pub use file::*;
+pub use flush::start_flusher;
+pub use quota::{Quota, Usage};
+pub use recover::{recover, Recovery};
+pub use wal::{checkpoint, open_wal, SyncPolicy, WAL};
 
+mod backup;
 mod block;
+mod cache;
+mod dedup;
 mod dir;
 #[allow(unused)]
 mod file;
+mod flush;
+pub mod object;
+mod pending;
+mod quota;
+mod recover;
+mod wal;
 
 const VERSION: Label = label(".version");
 
@@ -22,6 +40,23 @@ fn file_ext(path: &'_ Path) -> Option<&'_ str> {
     path.extension().and_then(|ext| ext.to_str())
 }
 
+/// Return the total size of the files under the given directory which are loaded in the cache.
+pub fn cache_occupancy(dir: &DirLock<CacheBlock>) -> TCBoxFuture<'_, usize> {
+    Box::pin(async move {
+        let dir = dir.read().await;
+
+        let mut occupancy = 0;
+        for (_name, entry) in dir.iter() {
+            occupancy += match entry {
+                DirEntry::Dir(dir) => cache_occupancy(dir).await,
+                DirEntry::File(file) => file.size_hint().await.unwrap_or(0),
+            };
+        }
+
+        occupancy
+    })
+}
+
 pub fn io_err(err: io::Error) -> TCError {
     match err.kind() {
         io::ErrorKind::NotFound => TCError::not_found(err),
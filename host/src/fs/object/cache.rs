@@ -0,0 +1,94 @@
+//! A local cache of the most recently used blocks in an object store.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use super::super::CacheBlock;
+
+/// A least-recently-used cache of committed blocks, keyed by object key, so that hot blocks
+/// don't have to be fetched from the object store each time they're read.
+pub struct BlockCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+}
+
+impl BlockCache {
+    /// Construct a new `BlockCache` which holds up to `capacity` bytes of (encoded) blocks.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Return the total encoded size of the blocks in this cache.
+    pub fn occupancy(&self) -> usize {
+        self.lru.lock().expect("object block cache").occupancy
+    }
+
+    pub(super) fn get(&self, key: &str) -> Option<CacheBlock> {
+        let mut lru = self.lru.lock().expect("object block cache");
+        lru.touch(key)
+    }
+
+    pub(super) fn insert(&self, key: String, block: CacheBlock, size: usize) {
+        if size > self.capacity {
+            return;
+        }
+
+        let mut lru = self.lru.lock().expect("object block cache");
+        lru.remove(&key);
+        lru.insert(key, block, size);
+
+        while lru.occupancy > self.capacity {
+            lru.evict();
+        }
+    }
+
+    pub(super) fn remove(&self, key: &str) {
+        let mut lru = self.lru.lock().expect("object block cache");
+        lru.remove(key);
+    }
+}
+
+#[derive(Default)]
+struct Lru {
+    tick: u64,
+    occupancy: usize,
+    order: BTreeMap<u64, String>,
+    blocks: HashMap<String, (u64, usize, CacheBlock)>,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &str) -> Option<CacheBlock> {
+        self.tick += 1;
+
+        let (tick, _, block) = self.blocks.get_mut(key)?;
+        self.order.remove(tick);
+        self.order.insert(self.tick, key.to_string());
+        *tick = self.tick;
+
+        Some(block.clone())
+    }
+
+    fn insert(&mut self, key: String, block: CacheBlock, size: usize) {
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.blocks.insert(key, (self.tick, size, block));
+        self.occupancy += size;
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((tick, size, _)) = self.blocks.remove(key) {
+            self.order.remove(&tick);
+            self.occupancy -= size;
+        }
+    }
+
+    fn evict(&mut self) {
+        let tick = *self.order.keys().next().expect("least recently used block");
+        let key = self.order.remove(&tick).expect("block key");
+        let (_, size, _) = self.blocks.remove(&key).expect("cached block");
+        self.occupancy -= size;
+    }
+}
@@ -0,0 +1,387 @@
+//! A minimal client for an S3-compatible object store, signed with AWS Signature Version 4.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use hyper::client::HttpConnector;
+use hyper::{Body, Method, Request, StatusCode};
+use log::debug;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use tc_error::*;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const SERVICE: &str = "s3";
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+/// The result of listing the keys in an [`ObjectStore`] under a prefix.
+#[derive(Default)]
+pub struct Listing {
+    /// The keys of the objects directly under the prefix.
+    pub keys: Vec<String>,
+
+    /// The common prefixes of the keys one level below the prefix, ending with "/".
+    pub prefixes: Vec<String>,
+}
+
+/// A bucket in an S3-compatible object store (e.g. Amazon S3, GCS in interoperability mode,
+/// or MinIO), addressed by path.
+///
+/// The store is accessed over plain HTTP, like the rest of the host, so a remote store should be
+/// accessed through a local TLS-terminating proxy.
+pub struct ObjectStore {
+    client: hyper::Client<HttpConnector, Body>,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl ObjectStore {
+    /// Construct a client for the given `bucket` at the given `endpoint`.
+    pub fn new(
+        endpoint: Url,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    ) -> TCResult<Self> {
+        if endpoint.scheme() != "http" || endpoint.host_str().is_none() {
+            return Err(TCError::bad_request(
+                "unsupported object store endpoint",
+                endpoint,
+            ));
+        }
+
+        Ok(Self {
+            client: hyper::Client::new(),
+            endpoint,
+            bucket,
+            region,
+            access_key,
+            secret_key,
+        })
+    }
+
+    /// Read the object with the given `key`, if it exists.
+    pub async fn get(&self, key: &str) -> TCResult<Option<Bytes>> {
+        let (status, body) = self.send(Method::GET, key, &[], Bytes::new()).await?;
+        match status {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(body)),
+            status => Err(store_err(status, body)),
+        }
+    }
+
+    /// Write the object with the given `key`.
+    pub async fn put(&self, key: &str, body: Bytes) -> TCResult<()> {
+        let (status, body) = self.send(Method::PUT, key, &[], body).await?;
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(store_err(status, body))
+        }
+    }
+
+    /// Delete the object with the given `key`, if it exists.
+    pub async fn delete(&self, key: &str) -> TCResult<()> {
+        let (status, body) = self.send(Method::DELETE, key, &[], Bytes::new()).await?;
+        if status.is_success() || status == StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(store_err(status, body))
+        }
+    }
+
+    /// Delete every object whose key starts with the given `prefix`.
+    pub async fn delete_all(&self, prefix: &str) -> TCResult<()> {
+        let listing = self.list(prefix, false).await?;
+        for key in listing.keys {
+            self.delete(&key).await?;
+        }
+
+        Ok(())
+    }
+
+    /// List the keys under the given `prefix`, grouped one level below the prefix if `shallow`.
+    pub async fn list(&self, prefix: &str, shallow: bool) -> TCResult<Listing> {
+        let mut listing = Listing::default();
+        let mut continuation = None;
+
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), prefix.to_string()),
+            ];
+
+            if shallow {
+                query.push(("delimiter".to_string(), "/".to_string()));
+            }
+
+            if let Some(token) = continuation.take() {
+                query.push(("continuation-token".to_string(), token));
+            }
+
+            let (status, body) = self.send(Method::GET, "", &query, Bytes::new()).await?;
+            if !status.is_success() {
+                return Err(store_err(status, body));
+            }
+
+            let xml = String::from_utf8_lossy(&body);
+
+            for contents in elements(&xml, "Contents") {
+                if let Some(key) = elements(contents, "Key").next() {
+                    listing.keys.push(unescape(key));
+                }
+            }
+
+            for prefixes in elements(&xml, "CommonPrefixes") {
+                if let Some(prefix) = elements(prefixes, "Prefix").next() {
+                    listing.prefixes.push(unescape(prefix));
+                }
+            }
+
+            if elements(&xml, "IsTruncated").next() == Some("true") {
+                continuation = elements(&xml, "NextContinuationToken").next().map(unescape);
+            }
+
+            if continuation.is_none() {
+                break;
+            }
+        }
+
+        Ok(listing)
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(String, String)],
+        body: Bytes,
+    ) -> TCResult<(StatusCode, Bytes)> {
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{}", self.endpoint.host_str().expect("host"), port),
+            None => self.endpoint.host_str().expect("host").to_string(),
+        };
+
+        let path = format!(
+            "{}/{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            self.bucket,
+            key
+        );
+
+        let path = uri_encode(&path, false);
+
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(name, value)| (uri_encode(name, true), uri_encode(value, true)))
+            .collect();
+
+        query.sort();
+
+        let query = query
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let (date, timestamp) = now();
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, query, host, payload_hash, timestamp, SIGNED_HEADERS, payload_hash
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, SERVICE);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            timestamp,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = format!("AWS4{}", self.secret_key);
+        let key = hmac(key.as_bytes(), date.as_bytes());
+        let key = hmac(&key, self.region.as_bytes());
+        let key = hmac(&key, SERVICE.as_bytes());
+        let key = hmac(&key, b"aws4_request");
+        let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, self.access_key, scope, SIGNED_HEADERS, signature
+        );
+
+        let uri = if query.is_empty() {
+            format!("{}://{}{}", self.endpoint.scheme(), host, path)
+        } else {
+            format!("{}://{}{}?{}", self.endpoint.scheme(), host, path, query)
+        };
+
+        debug!("{} {}", method, uri);
+
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(hyper::header::HOST, host)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", timestamp)
+            .header(hyper::header::AUTHORIZATION, authorization)
+            .body(Body::from(body))
+            .map_err(|cause| {
+                TCError::internal(format!("invalid object store request: {}", cause))
+            })?;
+
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|cause| TCError::internal(format!("object store error: {}", cause)))?;
+
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|cause| TCError::internal(format!("object store error: {}", cause)))?;
+
+        Ok((status, body))
+    }
+}
+
+impl fmt::Display for ObjectStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "object store bucket {} at {}",
+            self.bucket, self.endpoint
+        )
+    }
+}
+
+fn store_err(status: StatusCode, body: Bytes) -> TCError {
+    TCError::internal(format!(
+        "object store responded {}: {}",
+        status,
+        String::from_utf8_lossy(&body)
+    ))
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut padded = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        padded[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        padded[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(padded.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    inner.update(message);
+
+    let mut outer = Sha256::new();
+    outer.update(padded.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+/// Return the current UTC date as `YYYYMMDD` and time as `YYYYMMDDTHHMMSSZ`.
+fn now() -> (String, String) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time")
+        .as_secs();
+
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (hour, minute, second) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+
+    // convert days since the Unix epoch to a civil date
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let timestamp = format!("{}T{:02}{:02}{:02}Z", date, hour, minute, second);
+    (date, timestamp)
+}
+
+fn uri_encode(input: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// Iterate over the contents of each XML element with the given `name` in `xml`.
+fn elements<'a>(xml: &'a str, name: &str) -> impl Iterator<Item = &'a str> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+
+    let mut rest = xml;
+    std::iter::from_fn(move || {
+        let start = rest.find(&open)? + open.len();
+        let end = start + rest[start..].find(&close)?;
+        let contents = &rest[start..end];
+        rest = &rest[end + close.len()..];
+        Some(contents)
+    })
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac() {
+        // RFC 4231, test case 2
+        let mac = hmac(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            hex::encode(mac),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_list_response() {
+        let xml = "<ListBucketResult><Contents><Key>a/b.node</Key></Contents>\
+            <Contents><Key>a/c&amp;d.node</Key></Contents>\
+            <CommonPrefixes><Prefix>a/e/</Prefix></CommonPrefixes></ListBucketResult>";
+
+        let keys: Vec<String> = elements(xml, "Contents")
+            .filter_map(|contents| elements(contents, "Key").next())
+            .map(unescape)
+            .collect();
+
+        assert_eq!(keys, vec!["a/b.node", "a/c&d.node"]);
+        assert_eq!(elements(xml, "Prefix").collect::<Vec<_>>(), vec!["a/e/"]);
+    }
+}
@@ -0,0 +1,610 @@
+//! A transactional directory whose files are stored in a [`BlockStore`]
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use log::{debug, error};
+use safecast::AsType;
+use uuid::Uuid;
+
+use tc_btree::Node;
+use tc_error::*;
+#[cfg(feature = "tc-tensor")]
+use tc_tensor::{Array, TensorType};
+use tc_transact::fs;
+use tc_transact::lock::TxnLock;
+use tc_transact::{Transact, TxnId};
+use tc_value::Value;
+use tcgeneric::{Id, PathSegment, TCBoxTryFuture};
+
+use crate::chain::ChainBlock;
+use crate::collection::CollectionType;
+use crate::scalar::ScalarType;
+use crate::state::StateType;
+
+use super::super::dir::{ext_class, file_class};
+use super::store::retry;
+use super::{BlockStore, MemoryStore, ObjectFile};
+
+/// A file stored in an [`ObjectDir`].
+#[derive(Clone)]
+pub enum ObjectFileEntry {
+    BTree(ObjectFile<Node>),
+    Chain(ObjectFile<ChainBlock>),
+    Value(ObjectFile<Value>),
+
+    #[cfg(feature = "tc-tensor")]
+    Tensor(ObjectFile<Array>),
+}
+
+impl ObjectFileEntry {
+    async fn new<C>(
+        store: Arc<dyn BlockStore>,
+        prefix: String,
+        class: C,
+        txn_id: Option<TxnId>,
+    ) -> TCResult<Self>
+    where
+        StateType: From<C>,
+    {
+        fn err<T: fmt::Display>(class: T) -> TCError {
+            TCError::bad_request("cannot create file for", class)
+        }
+
+        macro_rules! file {
+            ($variant:ident) => {
+                match txn_id {
+                    Some(txn_id) => ObjectFile::load(store, prefix, txn_id)
+                        .await
+                        .map(Self::$variant),
+                    None => Ok(Self::$variant(ObjectFile::new(store, prefix))),
+                }
+            };
+        }
+
+        match StateType::from(class) {
+            StateType::Collection(ct) => match ct {
+                CollectionType::BTree(_) => file!(BTree),
+                CollectionType::Table(tt) => Err(err(tt)),
+
+                #[cfg(feature = "tc-tensor")]
+                CollectionType::Tensor(tt) => match tt {
+                    TensorType::Dense => file!(Tensor),
+                    TensorType::Sparse => Err(err(TensorType::Sparse)),
+                },
+            },
+            StateType::Chain(_) => file!(Chain),
+            StateType::Scalar(st) => match st {
+                ScalarType::Value(_) => file!(Value),
+                other => Err(err(other)),
+            },
+            other => Err(err(other)),
+        }
+    }
+}
+
+impl AsType<ObjectFile<Node>> for ObjectFileEntry {
+    fn as_type(&self) -> Option<&ObjectFile<Node>> {
+        if let Self::BTree(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn as_type_mut(&mut self) -> Option<&mut ObjectFile<Node>> {
+        if let Self::BTree(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn into_type(self) -> Option<ObjectFile<Node>> {
+        if let Self::BTree(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+}
+
+impl AsType<ObjectFile<ChainBlock>> for ObjectFileEntry {
+    fn as_type(&self) -> Option<&ObjectFile<ChainBlock>> {
+        if let Self::Chain(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn as_type_mut(&mut self) -> Option<&mut ObjectFile<ChainBlock>> {
+        if let Self::Chain(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn into_type(self) -> Option<ObjectFile<ChainBlock>> {
+        if let Self::Chain(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "tc-tensor")]
+impl AsType<ObjectFile<Array>> for ObjectFileEntry {
+    fn as_type(&self) -> Option<&ObjectFile<Array>> {
+        if let Self::Tensor(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn as_type_mut(&mut self) -> Option<&mut ObjectFile<Array>> {
+        if let Self::Tensor(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn into_type(self) -> Option<ObjectFile<Array>> {
+        if let Self::Tensor(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+}
+
+impl AsType<ObjectFile<Value>> for ObjectFileEntry {
+    fn as_type(&self) -> Option<&ObjectFile<Value>> {
+        if let Self::Value(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn as_type_mut(&mut self) -> Option<&mut ObjectFile<Value>> {
+        if let Self::Value(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn into_type(self) -> Option<ObjectFile<Value>> {
+        if let Self::Value(file) = self {
+            Some(file)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<ObjectFile<Node>> for ObjectFileEntry {
+    fn from(file: ObjectFile<Node>) -> Self {
+        Self::BTree(file)
+    }
+}
+
+impl From<ObjectFile<ChainBlock>> for ObjectFileEntry {
+    fn from(file: ObjectFile<ChainBlock>) -> Self {
+        Self::Chain(file)
+    }
+}
+
+#[cfg(feature = "tc-tensor")]
+impl From<ObjectFile<Array>> for ObjectFileEntry {
+    fn from(file: ObjectFile<Array>) -> Self {
+        Self::Tensor(file)
+    }
+}
+
+impl From<ObjectFile<Value>> for ObjectFileEntry {
+    fn from(file: ObjectFile<Value>) -> Self {
+        Self::Value(file)
+    }
+}
+
+impl fmt::Display for ObjectFileEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BTree(btree) => fmt::Display::fmt(btree, f),
+            Self::Chain(chain) => fmt::Display::fmt(chain, f),
+            Self::Value(value) => fmt::Display::fmt(value, f),
+
+            #[cfg(feature = "tc-tensor")]
+            Self::Tensor(tensor) => fmt::Display::fmt(tensor, f),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum DirEntry {
+    Dir(ObjectDir),
+    File(ObjectFileEntry),
+}
+
+impl DirEntry {
+    async fn commit(&self, txn_id: &TxnId) {
+        match self {
+            Self::Dir(dir) => dir.commit(txn_id).await,
+            Self::File(file) => match file {
+                ObjectFileEntry::BTree(file) => file.commit(txn_id).await,
+                ObjectFileEntry::Chain(file) => file.commit(txn_id).await,
+                ObjectFileEntry::Value(file) => file.commit(txn_id).await,
+
+                #[cfg(feature = "tc-tensor")]
+                ObjectFileEntry::Tensor(file) => file.commit(txn_id).await,
+            },
+        }
+    }
+
+    async fn finalize(&self, txn_id: &TxnId) {
+        match self {
+            Self::Dir(dir) => dir.finalize(txn_id).await,
+            Self::File(file) => match file {
+                ObjectFileEntry::BTree(file) => file.finalize(txn_id).await,
+                ObjectFileEntry::Chain(file) => file.finalize(txn_id).await,
+                ObjectFileEntry::Value(file) => file.finalize(txn_id).await,
+
+                #[cfg(feature = "tc-tensor")]
+                ObjectFileEntry::Tensor(file) => file.finalize(txn_id).await,
+            },
+        }
+    }
+}
+
+impl fmt::Display for DirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Dir(dir) => fmt::Display::fmt(dir, f),
+            Self::File(file) => fmt::Display::fmt(file, f),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Contents {
+    inner: HashMap<PathSegment, (String, DirEntry)>,
+}
+
+impl PartialEq for Contents {
+    fn eq(&self, other: &Self) -> bool {
+        let this: HashSet<_> = self.inner.keys().collect();
+        let that: HashSet<_> = other.inner.keys().collect();
+        this == that
+    }
+}
+
+impl Eq for Contents {}
+
+/// A transactional directory whose files are stored in a [`BlockStore`].
+///
+/// Entries are laid out in the store the same way as on disk: the blocks of a file named `name`
+/// with blocks of type `B` are stored under the prefix `name.<B::ext()>/`. Since a key-value store
+/// has no concept of an empty directory, each directory and file is marked by a `.keep` entry.
+#[derive(Clone)]
+pub struct ObjectDir {
+    store: Arc<dyn BlockStore>,
+    prefix: String,
+    contents: TxnLock<Contents>,
+
+    // the entry prefixes which are known to exist in the store
+    stored: Arc<Mutex<HashSet<String>>>,
+}
+
+impl ObjectDir {
+    /// Construct a new, empty `ObjectDir` under the given `prefix` in the given `store`.
+    pub fn new(store: Arc<dyn BlockStore>, prefix: String) -> Self {
+        let contents = Contents {
+            inner: HashMap::new(),
+        };
+
+        Self {
+            contents: TxnLock::new(format!("contents of {}", prefix), contents),
+            store,
+            prefix,
+            stored: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Load the `ObjectDir` under the given `prefix` in the given `store`.
+    pub fn load<'a>(
+        store: Arc<dyn BlockStore>,
+        prefix: String,
+        txn_id: TxnId,
+    ) -> TCBoxTryFuture<'a, Self> {
+        Box::pin(async move {
+            debug!("ObjectDir::load {}", prefix);
+
+            let listing = store.list(&prefix).await?;
+
+            let mut inner = HashMap::new();
+            let mut stored = HashSet::new();
+            for entry_prefix in listing.prefixes {
+                let name = entry_prefix[prefix.len()..].trim_end_matches('/');
+                if name.starts_with('.') {
+                    debug!("ObjectDir::load skipping hidden entry {}", name);
+                    continue;
+                }
+
+                let (name, entry) = if ext_class(name).is_some() {
+                    let (name, class) = file_class(name)?;
+                    let file = ObjectFileEntry::new(
+                        store.clone(),
+                        entry_prefix.clone(),
+                        class,
+                        Some(txn_id),
+                    )
+                    .await?;
+
+                    (name, DirEntry::File(file))
+                } else {
+                    let subdir = Self::load(store.clone(), entry_prefix.clone(), txn_id).await?;
+
+                    (name.parse()?, DirEntry::Dir(subdir))
+                };
+
+                stored.insert(entry_prefix.clone());
+                inner.insert(name, (entry_prefix, entry));
+            }
+
+            Ok(Self {
+                contents: TxnLock::new(format!("contents of {}", prefix), Contents { inner }),
+                store,
+                prefix,
+                stored: Arc::new(Mutex::new(stored)),
+            })
+        })
+    }
+
+    /// Construct a new, empty `ObjectDir` which keeps its contents in memory only.
+    ///
+    /// Nothing is written to disk, so the contents are lost when the `ObjectDir` is dropped. This
+    /// is useful for tests and for scratch data which doesn't need to survive a restart.
+    pub fn memory() -> Self {
+        Self::new(Arc::new(MemoryStore::new()), String::new())
+    }
+
+    fn create_entry(&self, name: &str) -> String {
+        format!("{}{}/", self.prefix, name)
+    }
+}
+
+#[async_trait]
+impl fs::Store for ObjectDir {
+    async fn is_empty(&self, txn_id: TxnId) -> TCResult<bool> {
+        self.contents
+            .read(txn_id)
+            .await
+            .map(|contents| contents.inner.is_empty())
+    }
+}
+
+#[async_trait]
+impl fs::Dir for ObjectDir {
+    type File = ObjectFileEntry;
+    type FileClass = StateType;
+
+    async fn contains(&self, txn_id: TxnId, name: &PathSegment) -> TCResult<bool> {
+        self.contents
+            .read(txn_id)
+            .await
+            .map(|contents| contents.inner.contains_key(name))
+    }
+
+    async fn create_dir(&self, txn_id: TxnId, name: PathSegment) -> TCResult<Self> {
+        let mut contents = self.contents.write(txn_id).await?;
+        if contents.inner.contains_key(&name) {
+            return Err(TCError::bad_request(
+                "filesystem entry already exists",
+                name,
+            ));
+        }
+
+        let prefix = self.create_entry(name.as_str());
+        let subdir = Self::new(self.store.clone(), prefix.clone());
+        contents
+            .inner
+            .insert(name, (prefix, DirEntry::Dir(subdir.clone())));
+
+        Ok(subdir)
+    }
+
+    async fn create_dir_unique(&self, txn_id: TxnId) -> TCResult<Self> {
+        let name = {
+            let contents = self.contents.read(txn_id).await?;
+            loop {
+                let name = Uuid::new_v4().into();
+                if !contents.inner.contains_key(&name) {
+                    break name;
+                }
+            }
+        };
+
+        self.create_dir(txn_id, name).await
+    }
+
+    async fn create_file<C, F, B>(&self, txn_id: TxnId, file_id: Id, class: C) -> TCResult<F>
+    where
+        C: Copy + Send + fmt::Display,
+        StateType: From<C>,
+        ObjectFileEntry: AsType<F>,
+        F: fs::File<B>,
+        B: fs::BlockData,
+    {
+        let mut contents = self.contents.write(txn_id).await?;
+        if contents.inner.contains_key(&file_id) {
+            return Err(TCError::bad_request(
+                "filesystem entry already exists",
+                file_id,
+            ));
+        }
+
+        let prefix = self.create_entry(&format!("{}.{}", file_id, B::ext()));
+        debug!("create file at {}", prefix);
+
+        let file = ObjectFileEntry::new(self.store.clone(), prefix.clone(), class, None).await?;
+
+        contents
+            .inner
+            .insert(file_id, (prefix, DirEntry::File(file.clone())));
+
+        file.into_type()
+            .ok_or_else(|| TCError::bad_request("expected file type", class))
+    }
+
+    async fn create_file_unique<C, F, B>(&self, txn_id: TxnId, class: C) -> TCResult<F>
+    where
+        C: Copy + Send + fmt::Display,
+        StateType: From<C>,
+        ObjectFileEntry: AsType<F>,
+        F: fs::File<B>,
+        B: fs::BlockData,
+    {
+        let file_id = {
+            let contents = self.contents.read(txn_id).await?;
+            loop {
+                let name = Uuid::new_v4().into();
+                if !contents.inner.contains_key(&name) {
+                    break name;
+                }
+            }
+        };
+
+        self.create_file(txn_id, file_id, class).await
+    }
+
+    async fn get_dir(&self, txn_id: TxnId, name: &PathSegment) -> TCResult<Option<Self>> {
+        let contents = self.contents.read(txn_id).await?;
+        match contents.inner.get(name) {
+            Some((_, DirEntry::Dir(dir))) => Ok(Some(dir.clone())),
+            Some((_, other)) => Err(TCError::bad_request("expected a directory, not", other)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_file<F, B>(&self, txn_id: TxnId, file_id: &Id) -> TCResult<Option<F>>
+    where
+        ObjectFileEntry: AsType<F>,
+        F: fs::File<B>,
+        B: fs::BlockData,
+    {
+        let contents = self.contents.read(txn_id).await?;
+        match contents.inner.get(file_id) {
+            Some((_, DirEntry::File(file))) => file
+                .clone()
+                .into_type()
+                .map(Some)
+                .ok_or_else(|| TCError::bad_request("unexpected file type", file)),
+
+            Some((_, other)) => Err(TCError::bad_request("expected a file, not", other)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl Transact for ObjectDir {
+    async fn commit(&self, txn_id: &TxnId) {
+        let contents = self.contents.write(*txn_id).await.expect("dir contents");
+        self.contents.commit(txn_id).await;
+
+        let (created, deleted) = {
+            let stored = self.stored.lock().expect("stored entries");
+            let current: HashSet<&String> = contents.inner.values().map(|(p, _)| p).collect();
+
+            let created: Vec<String> = current
+                .iter()
+                .filter(|prefix| !stored.contains(**prefix))
+                .map(|prefix| prefix.to_string())
+                .collect();
+
+            let deleted: Vec<String> = stored
+                .iter()
+                .filter(|prefix| !current.contains(prefix))
+                .cloned()
+                .collect();
+
+            (created, deleted)
+        };
+
+        // an entry which can't be created or deleted now is retried on the next commit,
+        // since it's only marked as stored once the store is updated
+        let created = join_all(created.into_iter().map(|prefix| async move {
+            let description = format!("create entry {} in {}", prefix, self.store);
+            let result = retry(&description, || self.store.create(&prefix)).await;
+            (prefix, result)
+        }))
+        .await;
+
+        let deleted = join_all(deleted.into_iter().map(|prefix| async move {
+            let description = format!("delete entry {} from {}", prefix, self.store);
+            let result = retry(&description, || self.store.delete_all(&prefix)).await;
+            (prefix, result)
+        }))
+        .await;
+
+        {
+            let mut stored = self.stored.lock().expect("stored entries");
+
+            for (prefix, result) in created {
+                match result {
+                    Ok(()) => {
+                        stored.insert(prefix);
+                    }
+                    Err(cause) => error!("unable to create entry {}: {}", prefix, cause),
+                }
+            }
+
+            for (prefix, result) in deleted {
+                match result {
+                    Ok(()) => {
+                        stored.remove(&prefix);
+                    }
+                    Err(cause) => error!("unable to delete entry {}: {}", prefix, cause),
+                }
+            }
+        }
+
+        join_all(
+            contents
+                .inner
+                .values()
+                .map(|(_, entry)| entry.commit(txn_id)),
+        )
+        .await;
+    }
+
+    async fn finalize(&self, txn_id: &TxnId) {
+        {
+            let contents = self.contents.read(*txn_id).await.expect("dir contents");
+            join_all(
+                contents
+                    .inner
+                    .values()
+                    .map(|(_, entry)| entry.finalize(txn_id)),
+            )
+            .await;
+        }
+
+        self.contents.finalize(txn_id).await
+    }
+}
+
+impl fmt::Display for ObjectDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a transactional directory in {}", self.store)
+    }
+}
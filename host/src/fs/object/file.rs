@@ -0,0 +1,710 @@
+//! A transactional file whose blocks are stored in a [`BlockStore`]
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::{join_all, BoxFuture};
+use log::{debug, error, warn};
+use safecast::AsType;
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+use uuid::Uuid;
+
+use tc_error::*;
+use tc_transact::fs;
+use tc_transact::lock::{TxnLock, TxnLockReadGuard, TxnLockWriteGuard};
+use tc_transact::{Isolation, Transact, TxnId};
+
+use super::super::CacheBlock;
+use super::store::retry;
+use super::BlockStore;
+
+type Blocks = HashMap<fs::BlockId, TxnLock<TxnId>>;
+type Versions<B> = HashMap<TxnId, HashMap<fs::BlockId, Arc<RwLock<B>>>>;
+
+// the committed blocks which could not be written to the store, by the ID of the committed version
+type Unsynced = HashMap<fs::BlockId, TxnId>;
+
+/// A transactional file whose blocks are stored in a [`BlockStore`].
+///
+/// Like a [`super::super::File`], the blocks written by a transaction are kept as
+/// transaction-specific versions until it commits, but the versions are held in memory and the
+/// canonical blocks are written to the store on commit. If a committed block can't be written to
+/// the store, its version is kept in memory (and served to readers) until a later commit writes it.
+pub struct ObjectFile<B> {
+    store: Arc<dyn BlockStore>,
+    prefix: String,
+    blocks: Arc<RwLock<Blocks>>,
+    present: TxnLock<HashSet<fs::BlockId>>,
+    versions: Arc<RwLock<Versions<B>>>,
+    unsynced: Arc<RwLock<Unsynced>>,
+    phantom: PhantomData<B>,
+}
+
+impl<B> Clone for ObjectFile<B> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            prefix: self.prefix.clone(),
+            blocks: self.blocks.clone(),
+            present: self.present.clone(),
+            versions: self.versions.clone(),
+            unsynced: self.unsynced.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<B> ObjectFile<B>
+where
+    B: fs::BlockData,
+    CacheBlock: AsType<B> + From<B>,
+{
+    pub(super) fn new(store: Arc<dyn BlockStore>, prefix: String) -> Self {
+        Self {
+            present: TxnLock::new(format!("block listing of {}", prefix), HashSet::new()),
+            store,
+            prefix,
+            blocks: Arc::new(RwLock::new(HashMap::new())),
+            versions: Arc::new(RwLock::new(HashMap::new())),
+            unsynced: Arc::new(RwLock::new(HashMap::new())),
+            phantom: PhantomData,
+        }
+    }
+
+    pub(super) async fn load(
+        store: Arc<dyn BlockStore>,
+        prefix: String,
+        txn_id: TxnId,
+    ) -> TCResult<Self> {
+        let listing = store.list(&prefix).await?;
+
+        let mut blocks = HashMap::new();
+        let mut present = HashSet::new();
+        for key in listing.keys {
+            let name = &key[prefix.len()..];
+            if name.starts_with('.') {
+                debug!("ObjectFile::load skipping hidden object {}", key);
+                continue;
+            }
+
+            let block_id = name
+                .strip_suffix(B::ext())
+                .and_then(|name| name.strip_suffix('.'))
+                .ok_or_else(|| {
+                    TCError::internal(format!("block has invalid extension: {}", key))
+                })?;
+
+            let block_id: fs::BlockId = block_id.parse()?;
+            let lock_name = format!("block {}", block_id);
+            blocks.insert(block_id.clone(), TxnLock::new(lock_name, txn_id));
+            present.insert(block_id);
+        }
+
+        Ok(Self {
+            present: TxnLock::new(format!("block listing of {}", prefix), present),
+            store,
+            prefix,
+            blocks: Arc::new(RwLock::new(blocks)),
+            versions: Arc::new(RwLock::new(HashMap::new())),
+            unsynced: Arc::new(RwLock::new(HashMap::new())),
+            phantom: PhantomData,
+        })
+    }
+
+    fn key(&self, block_id: &fs::BlockId) -> String {
+        format!("{}{}.{}", self.prefix, block_id, B::ext())
+    }
+
+    async fn read_canon(&self, block_id: &fs::BlockId) -> TCResult<B> {
+        // a committed version which is not yet in the store is still held in memory
+        if let Some(committed) = self.unsynced.read().await.get(block_id) {
+            let versions = self.versions.read().await;
+            if let Some(version) = versions
+                .get(committed)
+                .and_then(|version| version.get(block_id))
+            {
+                return Ok(B::clone(&*version.read().await));
+            }
+        }
+
+        let key = self.key(block_id);
+
+        let block = self
+            .store
+            .read(&key)
+            .await?
+            .ok_or_else(|| TCError::internal(format!("missing block {}", key)))?;
+
+        block
+            .into_type()
+            .ok_or_else(|| TCError::internal(format!("block {} has the wrong type", key)))
+    }
+
+    /// Return the version of the given block at `txn_id`, creating it if necessary by copying
+    /// the version at `source`, or the canonical version if `source` has no version.
+    async fn version(
+        &self,
+        txn_id: TxnId,
+        block_id: &fs::BlockId,
+        source: Option<&TxnId>,
+    ) -> TCResult<Arc<RwLock<B>>> {
+        let prior = {
+            let versions = self.versions.read().await;
+            if let Some(version) = versions
+                .get(&txn_id)
+                .and_then(|version| version.get(block_id))
+            {
+                return Ok(version.clone());
+            }
+
+            source
+                .and_then(|source| versions.get(source))
+                .and_then(|version| version.get(block_id))
+                .cloned()
+        };
+
+        let value = if let Some(prior) = prior {
+            B::clone(&*prior.read().await)
+        } else {
+            self.read_canon(block_id).await?
+        };
+
+        let mut versions = self.versions.write().await;
+        let version = versions
+            .entry(txn_id)
+            .or_insert_with(HashMap::new)
+            .entry(block_id.clone())
+            .or_insert_with(|| Arc::new(RwLock::new(value)));
+
+        Ok(version.clone())
+    }
+
+    async fn block_read(
+        &self,
+        txn_id: TxnId,
+        block_id: &fs::BlockId,
+    ) -> TCResult<TxnLockReadGuard<TxnId>> {
+        let present = self.present.read(txn_id).await?;
+        if !present.contains(block_id) {
+            return Err(TCError::not_found(block_id));
+        }
+
+        let blocks = self.blocks.read().await;
+        let block = blocks
+            .get(block_id)
+            .cloned()
+            .expect("block last mutation ID");
+        block.read(txn_id).await
+    }
+
+    async fn block_write(
+        &self,
+        txn_id: TxnId,
+        block_id: &fs::BlockId,
+    ) -> TCResult<TxnLockWriteGuard<TxnId>> {
+        let present = self.present.read(txn_id).await?;
+        if !present.contains(block_id) {
+            return Err(TCError::not_found(block_id));
+        }
+
+        let blocks = self.blocks.read().await;
+        let block = blocks
+            .get(block_id)
+            .cloned()
+            .expect("block last mutation ID");
+        block.write(txn_id).await
+    }
+
+    async fn create_version(
+        &self,
+        txn_id: TxnId,
+        block_id: fs::BlockId,
+        value: B,
+    ) -> TCResult<OwnedRwLockWriteGuard<B>> {
+        let mut present = self.present.write(txn_id).await?;
+        if present.contains(&block_id) {
+            return Err(TCError::bad_request("block already exists", block_id));
+        }
+
+        let mut blocks = self.blocks.write().await;
+        if let Some(last_mutation) = blocks.get(&block_id) {
+            // the block was deleted earlier, possibly by this same transaction
+            *last_mutation.write(txn_id).await? = txn_id;
+        } else {
+            let lock_name = format!("block {}", block_id);
+            blocks.insert(block_id.clone(), TxnLock::new(lock_name, txn_id));
+        }
+
+        let version = Arc::new(RwLock::new(value));
+        self.versions
+            .write()
+            .await
+            .entry(txn_id)
+            .or_insert_with(HashMap::new)
+            .insert(block_id.clone(), version.clone());
+
+        present.insert(block_id);
+
+        Ok(version.write_owned().await)
+    }
+}
+
+#[async_trait]
+impl<B> fs::Store for ObjectFile<B>
+where
+    B: fs::BlockData,
+    CacheBlock: AsType<B> + From<B>,
+{
+    async fn is_empty(&self, txn_id: TxnId) -> TCResult<bool> {
+        self.present
+            .read(txn_id)
+            .await
+            .map(|present| present.is_empty())
+    }
+}
+
+#[async_trait]
+impl<B> fs::File<B> for ObjectFile<B>
+where
+    B: fs::BlockData,
+    CacheBlock: AsType<B> + From<B>,
+{
+    type Read = OwnedRwLockReadGuard<B>;
+    type Write = OwnedRwLockWriteGuard<B>;
+
+    async fn block_ids(&self, txn_id: TxnId) -> TCResult<HashSet<fs::BlockId>> {
+        self.present
+            .read(txn_id)
+            .await
+            .map(|present| present.clone())
+    }
+
+    async fn contains_block(&self, txn_id: TxnId, name: &fs::BlockId) -> TCResult<bool> {
+        self.present
+            .read(txn_id)
+            .await
+            .map(|present| present.contains(name))
+    }
+
+    async fn copy_from(&self, other: &Self, txn_id: TxnId) -> TCResult<()> {
+        for block_id in other.block_ids(txn_id).await? {
+            let value = B::clone(&*other.read_block(txn_id, block_id.clone()).await?);
+
+            if self.contains_block(txn_id, &block_id).await? {
+                *self.write_block(txn_id, block_id).await? = value;
+            } else {
+                self.create_version(txn_id, block_id, value).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn create_block(
+        &self,
+        txn_id: TxnId,
+        block_id: fs::BlockId,
+        initial_value: B,
+        _size_hint: usize,
+    ) -> TCResult<Self::Write> {
+        debug!("ObjectFile::create_block {}", block_id);
+        self.create_version(txn_id, block_id, initial_value).await
+    }
+
+    async fn create_block_unique(
+        &self,
+        txn_id: TxnId,
+        initial_value: B,
+        _size_hint: usize,
+    ) -> TCResult<(fs::BlockId, Self::Write)> {
+        let block_id = {
+            let present = self.present.read(txn_id).await?;
+            loop {
+                let name = Uuid::new_v4().into();
+                if !present.contains(&name) {
+                    break name;
+                }
+            }
+        };
+
+        let lock = self
+            .create_version(txn_id, block_id.clone(), initial_value)
+            .await?;
+
+        Ok((block_id, lock))
+    }
+
+    async fn delete_block(&self, txn_id: TxnId, block_id: fs::BlockId) -> TCResult<()> {
+        debug!("ObjectFile::delete_block {}", block_id);
+
+        let mut present = self.present.write(txn_id).await?;
+        let blocks = self.blocks.read().await;
+        if let Some(last_mutation) = blocks.get(&block_id) {
+            *last_mutation.write(txn_id).await? = txn_id;
+
+            let mut versions = self.versions.write().await;
+            if let Some(version) = versions.get_mut(&txn_id) {
+                version.remove(&block_id);
+            }
+        }
+
+        present.remove(&block_id);
+        Ok(())
+    }
+
+    async fn read_block(&self, txn_id: TxnId, block_id: fs::BlockId) -> TCResult<Self::Read> {
+        let last_mutation = self.block_read(txn_id, &block_id).await?;
+        let version = self
+            .version(txn_id, &block_id, Some(&*last_mutation))
+            .await?;
+
+        Ok(version.read_owned().await)
+    }
+
+    async fn read_block_isolated(
+        &self,
+        txn_id: TxnId,
+        block_id: fs::BlockId,
+        isolation: Isolation,
+    ) -> TCResult<Self::Read> {
+        if isolation == Isolation::Snapshot {
+            return self.read_block(txn_id, block_id).await;
+        }
+
+        {
+            let present = self.present.read_isolated(txn_id, isolation).await?;
+            if !present.contains(&block_id) {
+                return Err(TCError::not_found(block_id));
+            }
+        }
+
+        // read this transaction's own writes, if any
+        let version = {
+            let versions = self.versions.read().await;
+            versions
+                .get(&txn_id)
+                .and_then(|version| version.get(&block_id))
+                .cloned()
+        };
+
+        let version = if let Some(version) = version {
+            version
+        } else {
+            // otherwise read the latest commit, without waiting on writes pending elsewhere
+            let value = self.read_canon(&block_id).await?;
+            Arc::new(RwLock::new(value))
+        };
+
+        Ok(version.read_owned().await)
+    }
+
+    async fn read_block_owned(self, txn_id: TxnId, block_id: fs::BlockId) -> TCResult<Self::Read> {
+        self.read_block(txn_id, block_id).await
+    }
+
+    async fn write_block(&self, txn_id: TxnId, block_id: fs::BlockId) -> TCResult<Self::Write> {
+        let mut last_mutation = self.block_write(txn_id, &block_id).await?;
+        let source = *last_mutation;
+        *last_mutation = txn_id;
+
+        let version = self.version(txn_id, &block_id, Some(&source)).await?;
+        Ok(version.write_owned().await)
+    }
+
+    async fn truncate(&self, txn_id: TxnId) -> TCResult<()> {
+        let mut present = self.present.write(txn_id).await?;
+        let blocks = self.blocks.read().await;
+        for block_id in present.drain() {
+            if let Some(last_mutation) = blocks.get(&block_id) {
+                *last_mutation.write(txn_id).await? = txn_id;
+            }
+        }
+
+        self.versions.write().await.remove(&txn_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<B> Transact for ObjectFile<B>
+where
+    B: fs::BlockData,
+    CacheBlock: AsType<B> + From<B>,
+{
+    async fn commit(&self, txn_id: &TxnId) {
+        debug!("ObjectFile::commit {}", self.prefix);
+
+        let mut blocks = self.blocks.write().await;
+        self.present.commit(txn_id).await;
+        join_all(
+            blocks
+                .values()
+                .map(|last_mutation| last_mutation.commit(txn_id)),
+        )
+        .await;
+
+        let present = self.present.read(*txn_id).await.expect("file block list");
+        let mut unsynced = self.unsynced.write().await;
+        let versions = self.versions.read().await;
+
+        let mut deletes: Vec<BoxFuture<(fs::BlockId, TCResult<()>)>> = Vec::new();
+        let mut writes: Vec<BoxFuture<(fs::BlockId, TxnId, TCResult<()>)>> = Vec::new();
+        for (block_id, last_mutation) in blocks.iter() {
+            let key = self.key(block_id);
+
+            if !present.contains(block_id) {
+                deletes.push(Box::pin(async move {
+                    let description = format!("delete block {} from {}", key, self.store);
+                    let result = retry(&description, || self.store.delete(&key)).await;
+                    (block_id.clone(), result)
+                }));
+
+                continue;
+            }
+
+            // write the version committed now, or retry a version which a prior commit couldn't
+            let committed = if last_mutation.canon() == *txn_id {
+                *txn_id
+            } else if let Some(committed) = unsynced.get(block_id) {
+                *committed
+            } else {
+                continue;
+            };
+
+            let block = match versions
+                .get(&committed)
+                .and_then(|version| version.get(block_id))
+            {
+                Some(block) => CacheBlock::from(B::clone(&*block.read().await)),
+                None => {
+                    debug!(
+                        "block {} has no version to commit at {}",
+                        block_id, committed
+                    );
+                    continue;
+                }
+            };
+
+            writes.push(Box::pin(async move {
+                let description = format!("write block {} to {}", key, self.store);
+                let result = retry(&description, || self.store.write(&key, block.clone())).await;
+                (block_id.clone(), committed, result)
+            }));
+        }
+
+        let (deleted, written) = futures::join!(join_all(deletes), join_all(writes));
+        std::mem::drop(versions);
+
+        // the versions of prior commits which no longer need to be held in memory
+        let mut released = Vec::new();
+
+        for (block_id, committed, result) in written {
+            if let Err(cause) = result {
+                error!(
+                    "unable to write block {} of {}, will retry on the next commit: {}",
+                    block_id, self.prefix, cause
+                );
+
+                match unsynced.insert(block_id.clone(), committed) {
+                    Some(prior) if prior != committed => released.push((block_id, prior)),
+                    _ => {}
+                }
+            } else if let Some(prior) = unsynced.remove(&block_id) {
+                released.push((block_id, prior));
+            }
+        }
+
+        for (block_id, result) in deleted {
+            if let Err(cause) = result {
+                error!(
+                    "unable to delete block {} of {}, will retry on the next commit: {}",
+                    block_id, self.prefix, cause
+                );
+            } else {
+                if let Some(prior) = unsynced.remove(&block_id) {
+                    released.push((block_id.clone(), prior));
+                }
+
+                blocks.remove(&block_id);
+            }
+        }
+
+        let mut versions = self.versions.write().await;
+        for (block_id, prior) in released {
+            if prior == *txn_id {
+                // the version of this transaction is released when it's finalized
+                continue;
+            }
+
+            if let Some(version) = versions.get_mut(&prior) {
+                version.remove(&block_id);
+                if version.is_empty() {
+                    versions.remove(&prior);
+                }
+            }
+        }
+    }
+
+    async fn finalize(&self, txn_id: &TxnId) {
+        {
+            // keep the versions of committed blocks which are not yet written to the store
+            let unsynced = self.unsynced.read().await;
+            let mut versions = self.versions.write().await;
+            if let Some(mut version) = versions.remove(txn_id) {
+                version.retain(|block_id, _| unsynced.get(block_id) == Some(txn_id));
+
+                if !version.is_empty() {
+                    warn!(
+                        "keeping {} blocks of {} at {} in memory until they're written",
+                        version.len(),
+                        self.prefix,
+                        txn_id
+                    );
+
+                    versions.insert(*txn_id, version);
+                }
+            }
+        }
+
+        let blocks = self.blocks.read().await;
+        join_all(
+            blocks
+                .values()
+                .map(|last_mutation| last_mutation.finalize(txn_id)),
+        )
+        .await;
+
+        self.present.finalize(txn_id).await
+    }
+}
+
+impl<B: Send + Sync + 'static> fmt::Display for ObjectFile<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "block store file of {} blocks",
+            std::any::type_name::<B>()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use tc_transact::fs::File;
+    use tc_value::Value;
+    use tcgeneric::NetworkTime;
+
+    use super::super::{Listing, MemoryStore};
+    use super::*;
+
+    // a store which fails every write while `offline` is set
+    #[derive(Default)]
+    struct FlakyStore {
+        blocks: MemoryStore,
+        offline: AtomicBool,
+    }
+
+    impl FlakyStore {
+        fn check(&self) -> TCResult<()> {
+            if self.offline.load(Ordering::SeqCst) {
+                Err(TCError::new(ErrorType::BadGateway, "offline".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[async_trait]
+    impl BlockStore for FlakyStore {
+        async fn read(&self, key: &str) -> TCResult<Option<CacheBlock>> {
+            self.blocks.read(key).await
+        }
+
+        async fn write(&self, key: &str, block: CacheBlock) -> TCResult<()> {
+            self.check()?;
+            self.blocks.write(key, block).await
+        }
+
+        async fn delete(&self, key: &str) -> TCResult<()> {
+            self.check()?;
+            self.blocks.delete(key).await
+        }
+
+        async fn create(&self, prefix: &str) -> TCResult<()> {
+            self.check()?;
+            self.blocks.create(prefix).await
+        }
+
+        async fn delete_all(&self, prefix: &str) -> TCResult<()> {
+            self.check()?;
+            self.blocks.delete_all(prefix).await
+        }
+
+        async fn list(&self, prefix: &str) -> TCResult<Listing> {
+            self.blocks.list(prefix).await
+        }
+    }
+
+    impl fmt::Display for FlakyStore {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("flaky store")
+        }
+    }
+
+    fn txn_id(nanos: u64) -> TxnId {
+        TxnId::new(NetworkTime::from_nanos(nanos))
+    }
+
+    #[tokio::test]
+    async fn test_commit_offline() {
+        let store = Arc::new(FlakyStore::default());
+        let file = ObjectFile::<Value>::new(store.clone(), "file/".to_string());
+        let block_id: fs::BlockId = "block".parse().expect("block ID");
+
+        store.offline.store(true, Ordering::SeqCst);
+
+        let first = txn_id(1);
+        let block = file
+            .create_block(first, block_id.clone(), Value::from(1u64), 0)
+            .await
+            .expect("create block");
+
+        std::mem::drop(block);
+        file.commit(&first).await;
+        file.finalize(&first).await;
+
+        // the committed block is still readable although the store is offline
+        assert!(store.blocks.is_empty());
+
+        let second = txn_id(2);
+        let block = file
+            .read_block_isolated(second, block_id.clone(), Isolation::ReadCommitted)
+            .await
+            .expect("read committed block");
+
+        assert_eq!(*block, Value::from(1u64));
+        std::mem::drop(block);
+
+        let block = file
+            .read_block(second, block_id.clone())
+            .await
+            .expect("read block");
+
+        assert_eq!(*block, Value::from(1u64));
+        std::mem::drop(block);
+
+        // the next commit writes the block once the store is back online
+        store.offline.store(false, Ordering::SeqCst);
+        file.commit(&second).await;
+        file.finalize(&second).await;
+
+        assert_eq!(store.blocks.len(), 1);
+        assert!(file.unsynced.read().await.is_empty());
+        assert!(file.versions.read().await.is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+//! A [`BlockStore`] which keeps its blocks in memory only.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+
+use tc_error::*;
+
+use super::super::CacheBlock;
+use super::store::MARKER;
+use super::{BlockStore, Listing};
+
+// a marker has no block
+type Entries = BTreeMap<String, Option<CacheBlock>>;
+
+/// A [`BlockStore`] which keeps its blocks in memory and never touches the disk, for tests and
+/// ephemeral data which doesn't need to survive a restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: RwLock<Entries>,
+}
+
+impl MemoryStore {
+    /// Construct a new, empty `MemoryStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of blocks in this store.
+    pub fn len(&self) -> usize {
+        let entries = self.entries.read().expect("memory store");
+        entries.values().filter(|block| block.is_some()).count()
+    }
+
+    /// Return `true` if this store contains no blocks.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl BlockStore for MemoryStore {
+    async fn read(&self, key: &str) -> TCResult<Option<CacheBlock>> {
+        let entries = self.entries.read().expect("memory store");
+        Ok(entries.get(key).cloned().flatten())
+    }
+
+    async fn write(&self, key: &str, block: CacheBlock) -> TCResult<()> {
+        let mut entries = self.entries.write().expect("memory store");
+        entries.insert(key.to_string(), Some(block));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> TCResult<()> {
+        let mut entries = self.entries.write().expect("memory store");
+        entries.remove(key);
+        Ok(())
+    }
+
+    async fn create(&self, prefix: &str) -> TCResult<()> {
+        let mut entries = self.entries.write().expect("memory store");
+        entries.insert(format!("{}{}", prefix, MARKER), None);
+        Ok(())
+    }
+
+    async fn delete_all(&self, prefix: &str) -> TCResult<()> {
+        let mut entries = self.entries.write().expect("memory store");
+        entries.retain(|key, _| !key.starts_with(prefix));
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> TCResult<Listing> {
+        let entries = self.entries.read().expect("memory store");
+        Ok(list(&entries, prefix))
+    }
+}
+
+impl fmt::Display for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("memory store")
+    }
+}
+
+fn list(entries: &Entries, prefix: &str) -> Listing {
+    let mut listing = Listing::default();
+
+    let keys = entries
+        .range(prefix.to_string()..)
+        .map(|(key, _)| key)
+        .take_while(|key| key.starts_with(prefix));
+
+    for key in keys {
+        let name = &key[prefix.len()..];
+        if let Some(i) = name.find('/') {
+            let entry_prefix = &key[..prefix.len() + i + 1];
+            if listing.prefixes.last().map(String::as_str) != Some(entry_prefix) {
+                listing.prefixes.push(entry_prefix.to_string());
+            }
+        } else {
+            listing.keys.push(key.to_string());
+        }
+    }
+
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list() {
+        let mut entries = Entries::new();
+        for key in &[
+            "a/.keep",
+            "a/b.value/.keep",
+            "a/b.value/0.value",
+            "a/b.value/1.value",
+            "a/c/.keep",
+            "a/c/d.node/.keep",
+            "ab/.keep",
+        ] {
+            entries.insert(key.to_string(), None);
+        }
+
+        let listing = list(&entries, "");
+        assert!(listing.keys.is_empty());
+        assert_eq!(listing.prefixes, vec!["a/", "ab/"]);
+
+        let listing = list(&entries, "a/");
+        assert_eq!(listing.keys, vec!["a/.keep"]);
+        assert_eq!(listing.prefixes, vec!["a/b.value/", "a/c/"]);
+
+        let listing = list(&entries, "a/b.value/");
+        assert_eq!(
+            listing.keys,
+            vec!["a/b.value/.keep", "a/b.value/0.value", "a/b.value/1.value"]
+        );
+        assert!(listing.prefixes.is_empty());
+    }
+}
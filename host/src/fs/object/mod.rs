@@ -0,0 +1,28 @@
+//! An implementation of the transactional filesystem traits over a flat, key-value
+//! [`BlockStore`], so that block data can live off-host or in memory only.
+//!
+//! An [`ObjectDir`] loads its listing from its store, and an [`ObjectFile`] fetches blocks on
+//! demand. Writes are held in memory until they're committed, at which point the affected blocks
+//! are written to the store.
+//!
+//! A [`RemoteStore`] keeps its blocks in an S3-compatible object store, with the most recently used
+//! blocks in a bounded [`BlockCache`], and a [`MemoryStore`] keeps its blocks in memory, for tests
+//! and ephemeral data which shouldn't touch the disk at all.
+//!
+//! These backends are only available to code which embeds this crate. The host itself always keeps
+//! its data in the `--data_dir`, since its chain and collection types are fixed to the on-disk
+//! [`Dir`](super::Dir), so neither backend can be selected at host startup.
+
+pub use cache::BlockCache;
+pub use client::{Listing, ObjectStore};
+pub use dir::{ObjectDir, ObjectFileEntry};
+pub use file::ObjectFile;
+pub use memory::MemoryStore;
+pub use store::{BlockStore, RemoteStore};
+
+mod cache;
+mod client;
+mod dir;
+mod file;
+mod memory;
+mod store;
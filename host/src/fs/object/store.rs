@@ -0,0 +1,152 @@
+//! The block storage interface of an [`ObjectDir`] and its implementations.
+
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::{debug, warn};
+
+use tc_error::*;
+
+use super::super::{io_err, CacheBlock};
+use super::{BlockCache, Listing, ObjectStore};
+
+/// The name of the (empty) object which marks the existence of a directory or file.
+pub(super) const MARKER: &str = ".keep";
+
+/// The number of times to retry a failed write to a [`BlockStore`] while committing.
+const RETRIES: u32 = 3;
+
+/// How long to wait before retrying a failed write, doubled after each retry.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Call `write` until it succeeds, up to [`RETRIES`] more times, backing off between attempts.
+///
+/// A commit can't fail, so a write which still fails is left for the caller to retry later.
+pub(super) async fn retry<F, W>(description: &str, write: W) -> TCResult<()>
+where
+    F: Future<Output = TCResult<()>>,
+    W: Fn() -> F,
+{
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=RETRIES {
+        match write().await {
+            Ok(()) => return Ok(()),
+            Err(cause) => {
+                warn!("{} failed (attempt {}): {}", description, attempt, cause);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+
+    write().await
+}
+
+/// A flat, key-value store of blocks in which an [`ObjectDir`] keeps its contents.
+///
+/// Keys are paths separated by "/", and an entry (i.e. a directory or file) with no blocks is
+/// represented by a marker under its prefix.
+#[async_trait]
+pub trait BlockStore: fmt::Display + Send + Sync + 'static {
+    /// Read the block with the given `key`, if it exists.
+    async fn read(&self, key: &str) -> TCResult<Option<CacheBlock>>;
+
+    /// Write the block with the given `key`.
+    async fn write(&self, key: &str, block: CacheBlock) -> TCResult<()>;
+
+    /// Delete the block with the given `key`, if it exists.
+    async fn delete(&self, key: &str) -> TCResult<()>;
+
+    /// Mark the existence of the (possibly empty) entry with the given `prefix`.
+    async fn create(&self, prefix: &str) -> TCResult<()>;
+
+    /// Delete the entry with the given `prefix` and all of its contents.
+    async fn delete_all(&self, prefix: &str) -> TCResult<()>;
+
+    /// List the keys and entry prefixes directly under the given `prefix`.
+    async fn list(&self, prefix: &str) -> TCResult<Listing>;
+}
+
+/// A [`BlockStore`] in an S3-compatible [`ObjectStore`], with a local cache of hot blocks.
+pub struct RemoteStore {
+    objects: ObjectStore,
+    cache: BlockCache,
+}
+
+impl RemoteStore {
+    /// Store blocks in the given [`ObjectStore`], caching up to `cache_size` bytes of them locally.
+    pub fn new(objects: ObjectStore, cache_size: usize) -> Self {
+        Self {
+            objects,
+            cache: BlockCache::new(cache_size),
+        }
+    }
+
+    /// Return the total encoded size of the blocks cached locally.
+    pub fn cache_occupancy(&self) -> usize {
+        self.cache.occupancy()
+    }
+}
+
+#[async_trait]
+impl BlockStore for RemoteStore {
+    async fn read(&self, key: &str) -> TCResult<Option<CacheBlock>> {
+        if let Some(block) = self.cache.get(key) {
+            return Ok(Some(block));
+        }
+
+        debug!("fetch block {} from {}", key, self.objects);
+
+        let encoded = match self.objects.get(key).await? {
+            Some(encoded) => encoded,
+            None => return Ok(None),
+        };
+
+        let block = CacheBlock::decode(Path::new(key), &encoded[..])
+            .await
+            .map_err(io_err)?;
+
+        self.cache
+            .insert(key.to_string(), block.clone(), encoded.len());
+
+        Ok(Some(block))
+    }
+
+    async fn write(&self, key: &str, block: CacheBlock) -> TCResult<()> {
+        let encoded = block.encode().await.map_err(io_err)?;
+        self.cache.insert(key.to_string(), block, encoded.len());
+        self.objects.put(key, encoded).await
+    }
+
+    async fn delete(&self, key: &str) -> TCResult<()> {
+        self.cache.remove(key);
+        self.objects.delete(key).await
+    }
+
+    async fn create(&self, prefix: &str) -> TCResult<()> {
+        let marker = format!("{}{}", prefix, MARKER);
+        self.objects.put(&marker, Bytes::new()).await
+    }
+
+    async fn delete_all(&self, prefix: &str) -> TCResult<()> {
+        for key in self.objects.list(prefix, false).await?.keys {
+            self.delete(&key).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> TCResult<Listing> {
+        self.objects.list(prefix, true).await
+    }
+}
+
+impl fmt::Display for RemoteStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.objects, f)
+    }
+}
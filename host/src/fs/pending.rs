@@ -0,0 +1,48 @@
+//! Count the transaction-specific block versions under a directory without walking it.
+//!
+//! A directory is tracked by [`track`], which starts from a count of its versions measured by
+//! walking it once. After that, each `File` under the directory adds to the count when it creates
+//! a version directory for a transaction, and subtracts from it when it finalizes that transaction.
+//! Tracking is process-wide and is not persisted.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+static COUNTS: RwLock<Vec<(PathBuf, Arc<AtomicUsize>)>> = RwLock::const_new(Vec::new());
+
+/// Start counting the pending block versions under the directory at `root`, from `count`.
+pub async fn track(root: PathBuf, count: usize) -> Arc<AtomicUsize> {
+    let mut counts = COUNTS.write().await;
+
+    if let Some((_, pending)) = counts.iter().find(|(path, _)| path == &root) {
+        pending.store(count, Ordering::Relaxed);
+        pending.clone()
+    } else {
+        let pending = Arc::new(AtomicUsize::new(count));
+        counts.push((root, pending.clone()));
+        pending
+    }
+}
+
+/// Record that a version directory was created at the given `path`.
+pub(super) async fn created(path: &Path) {
+    for (root, pending) in COUNTS.read().await.iter() {
+        if path.starts_with(root) {
+            pending.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Record that the version directory at the given `path` was deleted.
+pub(super) async fn deleted(path: &Path) {
+    for (root, pending) in COUNTS.read().await.iter() {
+        if path.starts_with(root) {
+            // a version created while its directory was being measured may not have been counted
+            let _ =
+                pending.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Resource quotas for a directory, typically the data directory of a `Cluster`.
+//!
+//! A [`Quota`] limits the bytes which a directory may occupy on disk and in the block cache. It's
+//! enforced when a block is created anywhere under the directory: if the directory's usage plus
+//! the size of the new block would exceed either limit, the block is not created.
+//!
+//! Measuring a directory means walking it, so a measurement is reused for [`USAGE_TTL`], adding the
+//! size of each block created in the meantime. Quotas are process-wide and are not persisted, so
+//! they must be set again when the host restarts.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use freqfs::DirLock;
+use log::debug;
+use tokio::sync::Mutex;
+
+use tc_error::*;
+use tcgeneric::TCBoxTryFuture;
+
+use super::{cache_occupancy, CacheBlock};
+
+/// How long to reuse a measurement of the usage of a directory with a quota.
+pub const USAGE_TTL: Duration = Duration::from_secs(1);
+
+static QUOTAS: Mutex<BTreeMap<PathBuf, Entry>> = Mutex::const_new(BTreeMap::new());
+
+/// The maximum resources which a directory may use.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Quota {
+    /// The maximum size in bytes of the directory on disk
+    pub disk: Option<u64>,
+
+    /// The maximum size in bytes of the blocks in the directory which are loaded in the cache
+    pub cache: Option<u64>,
+}
+
+impl Quota {
+    fn is_unlimited(&self) -> bool {
+        self.disk.is_none() && self.cache.is_none()
+    }
+}
+
+/// The resources which a directory uses.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Usage {
+    /// The size in bytes of the directory on disk
+    pub disk: u64,
+
+    /// The size in bytes of the blocks in the directory which are loaded in the cache
+    pub cache: u64,
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes on disk and {} bytes in the cache",
+            self.disk, self.cache
+        )
+    }
+}
+
+struct Entry {
+    dir: DirLock<CacheBlock>,
+    quota: Quota,
+    measured: Option<(Instant, Usage)>,
+}
+
+impl Entry {
+    async fn usage(&mut self, path: &Path) -> TCResult<Usage> {
+        if let Some((measured_at, usage)) = self.measured {
+            if measured_at.elapsed() < USAGE_TTL {
+                return Ok(usage);
+            }
+        }
+
+        let usage = Usage {
+            disk: disk_usage(path).await?,
+            cache: cache_occupancy(&self.dir).await as u64,
+        };
+
+        debug!("{:?} uses {}", path, usage);
+        self.measured = Some((Instant::now(), usage));
+        Ok(usage)
+    }
+
+    async fn allocate(&mut self, path: &Path, file: &Path, size: u64) -> TCResult<()> {
+        let usage = self.usage(path).await?;
+
+        if let Some(limit) = self.quota.disk {
+            if usage.disk + size > limit {
+                return Err(TCError::forbidden(
+                    format!(
+                        "creating a block in {:?} would exceed the disk quota of {} bytes of",
+                        file, limit
+                    ),
+                    format!("{:?}", path),
+                ));
+            }
+        }
+
+        if let Some(limit) = self.quota.cache {
+            if usage.cache + size > limit {
+                return Err(TCError::forbidden(
+                    format!(
+                        "creating a block in {:?} would exceed the cache quota of {} bytes of",
+                        file, limit
+                    ),
+                    format!("{:?}", path),
+                ));
+            }
+        }
+
+        if let Some((_, usage)) = &mut self.measured {
+            usage.disk += size;
+            usage.cache += size;
+        }
+
+        Ok(())
+    }
+}
+
+/// Return the quota of the given `dir`, if any.
+pub(super) async fn get(dir: &DirLock<CacheBlock>) -> Option<Quota> {
+    let path = dir.read().await.path().to_path_buf();
+    let quotas = QUOTAS.lock().await;
+    quotas.get(&path).map(|entry| entry.quota)
+}
+
+/// Set the quota of the given `dir`, or remove it if the given `quota` has no limits.
+pub(super) async fn set(dir: &DirLock<CacheBlock>, quota: Quota) {
+    let path = dir.read().await.path().to_path_buf();
+    let mut quotas = QUOTAS.lock().await;
+    if quota.is_unlimited() {
+        quotas.remove(&path);
+    } else if let Some(entry) = quotas.get_mut(&path) {
+        entry.quota = quota;
+    } else {
+        let entry = Entry {
+            dir: dir.clone(),
+            quota,
+            measured: None,
+        };
+
+        quotas.insert(path, entry);
+    }
+}
+
+/// Measure the resources used by the given `dir`.
+pub(super) async fn usage(dir: &DirLock<CacheBlock>) -> TCResult<Usage> {
+    let path = dir.read().await.path().to_path_buf();
+    let disk = disk_usage(&path).await?;
+    let cache = cache_occupancy(dir).await as u64;
+    Ok(Usage { disk, cache })
+}
+
+/// Check that creating a block of the given `size` in the given `file` would not exceed the quota
+/// of any directory which contains it.
+pub(super) async fn allocate(file: &DirLock<CacheBlock>, size: usize) -> TCResult<()> {
+    let mut quotas = QUOTAS.lock().await;
+    if quotas.is_empty() {
+        return Ok(());
+    }
+
+    let file = file.read().await.path().to_path_buf();
+    for (path, entry) in quotas.iter_mut() {
+        if file.starts_with(path) {
+            entry.allocate(path, &file, size as u64).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn disk_usage(path: &Path) -> TCBoxTryFuture<'_, u64> {
+    Box::pin(async move {
+        let mut size = 0;
+        let mut entries = match tokio::fs::read_dir(path).await {
+            Ok(entries) => entries,
+            Err(cause) if cause.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(cause) => return Err(quota_err(cause)),
+        };
+
+        while let Some(entry) = entries.next_entry().await.map_err(quota_err)? {
+            let metadata = entry.metadata().await.map_err(quota_err)?;
+            if metadata.is_dir() {
+                size += disk_usage(&entry.path()).await?;
+            } else {
+                size += metadata.len();
+            }
+        }
+
+        Ok(size)
+    })
+}
+
+fn quota_err(cause: io::Error) -> TCError {
+    TCError::internal(format!("quota I/O error: {}", cause))
+}
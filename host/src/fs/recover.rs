@@ -0,0 +1,209 @@
+//! Crash recovery of the data directory, run at startup before the data directory is loaded.
+//!
+//! A host which stops in the middle of a transaction can leave behind partially-written blocks
+//! (temporary files which were never renamed into place) and the pending versions of each file
+//! which the transaction read or wrote. Neither is part of the committed state of the data
+//! directory, so both are deleted, which rolls back any transaction which had not committed.
+//!
+//! If the data directory has a write-ahead log, it's replayed first, so that every commit which
+//! was logged is restored. Then the history of each chain is checked for a missing block. A missing
+//! block can't be repaired automatically, and loading the chain anyway would silently discard
+//! every block after it, so recovery fails instead, leaving the data directory to be restored from
+//! a backup.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use log::{debug, info};
+
+use tc_error::*;
+use tcgeneric::TCBoxTryFuture;
+
+use super::dedup::PARTIAL_LINK;
+use super::VERSION;
+
+// the suffix of a block which was not completely written to disk
+const PARTIAL: &str = "_freqfs";
+
+// the extension of a chain block, and of the file which contains the blocks of a chain
+const CHAIN_BLOCK: &str = "chain_block";
+
+/// A report of the recovery of a data directory.
+#[derive(Default)]
+pub struct Recovery {
+    /// The number of commits replayed from the write-ahead log
+    pub wal_records: usize,
+
+    /// The number of partially-written blocks deleted
+    pub partial_blocks: usize,
+
+    /// The number of pending transaction versions of a file deleted
+    pub pending_versions: usize,
+
+    /// The number of chains whose history was verified
+    pub chains: usize,
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "replayed {} logged commits, deleted {} partially-written blocks and {} pending versions, verified {} chains",
+            self.wal_records, self.partial_blocks, self.pending_versions, self.chains
+        )
+    }
+}
+
+/// Recover the data directory at the given `path` from an unclean shutdown.
+pub async fn recover(path: &Path) -> TCResult<Recovery> {
+    let mut recovery = Recovery {
+        wal_records: super::wal::replay(path).await?,
+        ..Default::default()
+    };
+
+    recover_dir(path, &mut recovery).await?;
+    info!("recovered data directory {:?}: {}", path, recovery);
+    Ok(recovery)
+}
+
+fn recover_dir<'a>(path: &'a Path, recovery: &'a mut Recovery) -> TCBoxTryFuture<'a, ()> {
+    Box::pin(async move {
+        let mut blocks = BTreeSet::new();
+        let is_chain = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.ends_with(&format!(".{}", CHAIN_BLOCK)))
+            .unwrap_or(false);
+
+        let mut entries = tokio::fs::read_dir(path).await.map_err(recovery_err)?;
+        while let Some(entry) = entries.next_entry().await.map_err(recovery_err)? {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let metadata = entry.metadata().await.map_err(recovery_err)?;
+
+            if metadata.is_dir() {
+                if name == &*VERSION {
+                    recovery.pending_versions += delete_versions(&entry.path()).await?;
+                } else if !name.starts_with('.') {
+                    recover_dir(&entry.path(), recovery).await?;
+                }
+            } else if name.ends_with(PARTIAL) || name.ends_with(PARTIAL_LINK) {
+                debug!("deleting partially-written block {:?}", entry.path());
+                tokio::fs::remove_file(entry.path())
+                    .await
+                    .map_err(recovery_err)?;
+
+                recovery.partial_blocks += 1;
+            } else if is_chain {
+                let ordinal = name
+                    .strip_suffix(&format!(".{}", CHAIN_BLOCK))
+                    .and_then(|ordinal| ordinal.parse::<u64>().ok())
+                    .ok_or_else(|| {
+                        TCError::internal(format!("invalid chain block {:?}", entry.path()))
+                    })?;
+
+                blocks.insert(ordinal);
+            }
+        }
+
+        if is_chain {
+            verify_chain(path, &blocks)?;
+            recovery.chains += 1;
+        }
+
+        Ok(())
+    })
+}
+
+async fn delete_versions(path: &Path) -> TCResult<usize> {
+    let mut deleted = 0;
+    let mut entries = tokio::fs::read_dir(path).await.map_err(recovery_err)?;
+    while let Some(entry) = entries.next_entry().await.map_err(recovery_err)? {
+        debug!("deleting pending version {:?}", entry.path());
+
+        let metadata = entry.metadata().await.map_err(recovery_err)?;
+        if metadata.is_dir() {
+            tokio::fs::remove_dir_all(entry.path()).await
+        } else {
+            tokio::fs::remove_file(entry.path()).await
+        }
+        .map_err(recovery_err)?;
+
+        deleted += 1;
+    }
+
+    Ok(deleted)
+}
+
+fn verify_chain(path: &Path, blocks: &BTreeSet<u64>) -> TCResult<()> {
+    // the blocks of a chain are numbered consecutively from zero
+    for (expected, ordinal) in blocks.iter().enumerate() {
+        if *ordinal != expected as u64 {
+            return Err(TCError::internal(format!(
+                "chain {:?} is missing block {} (found block {}), restore it from a backup",
+                path, expected, ordinal
+            )));
+        }
+    }
+
+    if blocks.is_empty() {
+        Err(TCError::internal(format!(
+            "chain {:?} has no blocks, restore it from a backup",
+            path
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+fn recovery_err(cause: io::Error) -> TCError {
+    TCError::internal(format!("recovery I/O error: {}", cause))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn write(path: &Path) {
+        std::fs::create_dir_all(path.parent().expect("parent dir")).expect("test dir");
+        std::fs::write(path, b"block").expect("test block");
+    }
+
+    #[tokio::test]
+    async fn test_recover() {
+        let path = std::env::temp_dir().join(format!("tc-recover-{}", Uuid::new_v4()));
+        let table = path.join("app").join("table");
+        let versions = table.join(&*VERSION);
+
+        write(&table.join("1.node"));
+        write(&table.join("2.node_freqfs"));
+        write(&table.join(format!("3.node{}", PARTIAL_LINK)));
+        write(&versions.join("1").join("1.node"));
+        write(&versions.join("2").join("2.node"));
+
+        let chain = path.join("app").join(format!("history.{}", CHAIN_BLOCK));
+        write(&chain.join(format!("0.{}", CHAIN_BLOCK)));
+        write(&chain.join(format!("1.{}", CHAIN_BLOCK)));
+
+        let recovery = recover(&path).await.expect("recovery");
+        assert_eq!(recovery.wal_records, 0);
+        assert_eq!(recovery.partial_blocks, 2);
+        assert_eq!(recovery.pending_versions, 2);
+        assert_eq!(recovery.chains, 1);
+
+        assert!(table.join("1.node").exists());
+        assert!(!table.join("2.node_freqfs").exists());
+        assert!(!table.join(format!("3.node{}", PARTIAL_LINK)).exists());
+        assert_eq!(std::fs::read_dir(&versions).expect("versions").count(), 0);
+
+        // a chain which is missing a block can't be recovered
+        std::fs::remove_file(chain.join(format!("0.{}", CHAIN_BLOCK))).expect("delete block");
+        assert!(recover(&path).await.is_err());
+
+        std::fs::remove_dir_all(path).expect("clean up");
+    }
+}
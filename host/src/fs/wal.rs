@@ -0,0 +1,549 @@
+//! An optional write-ahead log (WAL) of the blocks committed to the data directory.
+//!
+//! When the WAL is open, committing a file first appends a single record of all of its new,
+//! updated, and deleted blocks to the log, and only then updates its canonical blocks in the cache,
+//! without waiting for them to be written to disk. The log is synced to disk according to a
+//! [`SyncPolicy`]: after every record, at a fixed interval (so that the records of every commit in
+//! between share a single sync), or only when the operating system decides to.
+//!
+//! A checkpoint writes back every block committed since the last checkpoint and then truncates the
+//! log. A checkpoint runs when the log grows past [`CHECKPOINT_SIZE`], and before a backup.
+//!
+//! At startup, the log is replayed over the data directory, so that a block which was committed
+//! but not yet written back is restored. A record which was only partially written is detected by
+//! its checksum and discarded, together with any record after it. The commit of each file is
+//! atomic, but a transaction which spans several files is only recovered as far as it was logged.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use freqfs::FileLock;
+use futures::future;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use log::{debug, info, warn};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
+
+use tc_error::*;
+use tc_transact::TxnId;
+
+use super::CacheBlock;
+
+/// The name of the write-ahead log file in the data directory.
+pub const WAL: &str = ".wal";
+
+/// The size of the log, in bytes, at which to run a checkpoint.
+pub const CHECKPOINT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The maximum number of blocks to write back concurrently during a checkpoint.
+const CONCURRENCY: usize = 16;
+
+// the length of the body of a record, followed by its BLAKE3 hash
+const HEADER_LEN: usize = 8 + 32;
+
+const DELETE: u8 = 0;
+const PUT: u8 = 1;
+
+static LOG: Mutex<Option<Log>> = Mutex::const_new(None);
+
+// held by each commit from the time it's logged until its blocks are deferred to a checkpoint,
+// so that a checkpoint never truncates the record of a commit whose blocks it can't write back
+static APPLYING: RwLock<()> = RwLock::const_new(());
+
+/// When to sync the write-ahead log to disk.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SyncPolicy {
+    /// Sync the log after each commit
+    Always,
+
+    /// Sync the log at the given interval
+    Interval(Duration),
+
+    /// Leave it to the operating system to sync the log
+    Os,
+}
+
+impl FromStr for SyncPolicy {
+    type Err = TCError;
+
+    fn from_str(policy: &str) -> TCResult<Self> {
+        match policy {
+            "always" => Ok(Self::Always),
+            "os" => Ok(Self::Os),
+            interval => interval
+                .strip_suffix("ms")
+                .and_then(|millis| u64::from_str(millis).ok())
+                .map(Duration::from_millis)
+                .map(Self::Interval)
+                .ok_or_else(|| {
+                    TCError::bad_request(
+                        "expected a WAL sync policy of \"always\", \"os\", or an interval like \"10ms\", not",
+                        policy,
+                    )
+                }),
+        }
+    }
+}
+
+impl fmt::Display for SyncPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Always => f.write_str("always"),
+            Self::Interval(interval) => write!(f, "{}ms", interval.as_millis()),
+            Self::Os => f.write_str("os"),
+        }
+    }
+}
+
+struct Log {
+    data_dir: PathBuf,
+    file: tokio::fs::File,
+    policy: SyncPolicy,
+    size: u64,
+    dirty: bool,
+    unsynced: BTreeMap<PathBuf, FileLock<CacheBlock>>,
+}
+
+impl Log {
+    async fn append(&mut self, body: Bytes) -> TCResult<()> {
+        let record = frame(&body);
+        self.file.write_all(&record).await.map_err(wal_err)?;
+        self.size += record.len() as u64;
+        self.dirty = true;
+
+        if self.policy == SyncPolicy::Always {
+            self.sync().await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn sync(&mut self) -> TCResult<()> {
+        if self.dirty {
+            self.file.flush().await.map_err(wal_err)?;
+            self.file.sync_data().await.map_err(wal_err)?;
+            self.dirty = false;
+        }
+
+        Ok(())
+    }
+
+    async fn checkpoint(&mut self) -> TCResult<()> {
+        let blocks = mem::take(&mut self.unsynced);
+        debug!("WAL checkpoint: writing back {} blocks", blocks.len());
+
+        stream::iter(blocks)
+            .map(|(path, block)| async move {
+                // a block whose file has since been deleted must not be written back
+                if path.parent().map(Path::exists).unwrap_or(false) {
+                    block.sync(false).await?;
+                    super::dedup::dedup(&block).await
+                } else {
+                    Ok(())
+                }
+            })
+            .buffer_unordered(CONCURRENCY)
+            .try_for_each(|()| future::ready(Ok(())))
+            .await
+            .map_err(wal_err)?;
+
+        self.file.flush().await.map_err(wal_err)?;
+        self.file.set_len(0).await.map_err(wal_err)?;
+        self.file.sync_all().await.map_err(wal_err)?;
+        self.size = 0;
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+/// Open the write-ahead log of the given `data_dir`, which must already have been replayed.
+pub async fn open_wal(data_dir: &Path, policy: SyncPolicy) -> TCResult<()> {
+    let mut log = LOG.lock().await;
+    if log.is_some() {
+        return Err(TCError::internal("the write-ahead log is already open"));
+    }
+
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(data_dir.join(WAL))
+        .await
+        .map_err(wal_err)?;
+
+    let size = file.metadata().await.map_err(wal_err)?.len();
+
+    *log = Some(Log {
+        data_dir: data_dir.to_path_buf(),
+        file,
+        policy,
+        size,
+        dirty: false,
+        unsynced: BTreeMap::new(),
+    });
+
+    if let SyncPolicy::Interval(interval) = policy {
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+
+                if let Some(log) = LOG.lock().await.as_mut() {
+                    if let Err(cause) = log.sync().await {
+                        warn!("failed to sync the write-ahead log: {}", cause);
+                    }
+                }
+            }
+        });
+    }
+
+    info!(
+        "opened the write-ahead log of {:?} (sync {})",
+        data_dir, policy
+    );
+    Ok(())
+}
+
+/// A commit which has been logged, but whose blocks have not yet been deferred to a checkpoint.
+pub(super) struct Logged {
+    _applying: RwLockReadGuard<'static, ()>,
+}
+
+/// Write back every block committed since the last checkpoint, and truncate the log.
+pub async fn checkpoint() -> TCResult<()> {
+    let _applying = APPLYING.write().await;
+
+    if let Some(log) = LOG.lock().await.as_mut() {
+        log.checkpoint().await
+    } else {
+        Ok(())
+    }
+}
+
+/// Return `true` if commits to the file at the given `path` are logged.
+pub(super) async fn is_logged(path: &Path) -> bool {
+    LOG.lock()
+        .await
+        .as_ref()
+        .map(|log| path.starts_with(&log.data_dir))
+        .unwrap_or(false)
+}
+
+/// Log the commit of the given blocks at `txn_id`, each either with its new contents or `None` if
+/// it was deleted. The committed blocks must then be passed to [`defer`].
+pub(super) async fn log_commit(
+    txn_id: &TxnId,
+    blocks: Vec<(PathBuf, Option<CacheBlock>)>,
+) -> TCResult<Logged> {
+    let mut encoded = Vec::with_capacity(blocks.len());
+    for (path, block) in blocks {
+        let contents = match block {
+            Some(block) => Some(block.encode().await.map_err(wal_err)?),
+            None => None,
+        };
+
+        encoded.push((path, contents));
+    }
+
+    let applying = APPLYING.read().await;
+    let mut log = LOG.lock().await;
+    let log = log
+        .as_mut()
+        .ok_or_else(|| TCError::internal("the write-ahead log is not open"))?;
+
+    let mut body = BytesMut::new();
+    put_str(&mut body, &txn_id.to_string());
+    body.put_u32_le(encoded.len() as u32);
+
+    for (path, contents) in &encoded {
+        let name = path
+            .strip_prefix(&log.data_dir)
+            .map_err(|_| TCError::internal(format!("{:?} is not in the data directory", path)))?;
+
+        let name = name
+            .to_str()
+            .ok_or_else(|| TCError::internal(format!("invalid block path {:?}", name)))?;
+
+        if let Some(contents) = contents {
+            body.put_u8(PUT);
+            put_str(&mut body, name);
+            body.put_u64_le(contents.len() as u64);
+            body.put_slice(contents);
+        } else {
+            body.put_u8(DELETE);
+            put_str(&mut body, name);
+        }
+    }
+
+    log.append(body.freeze()).await?;
+
+    for (path, _) in encoded {
+        log.unsynced.remove(&path);
+    }
+
+    Ok(Logged {
+        _applying: applying,
+    })
+}
+
+/// Defer writing back the given canonical `blocks` of a [`Logged`] commit until a checkpoint.
+pub(super) async fn defer(logged: Logged, blocks: Vec<FileLock<CacheBlock>>) -> TCResult<()> {
+    let checkpoint_due = {
+        let mut log = LOG.lock().await;
+        let log = log
+            .as_mut()
+            .ok_or_else(|| TCError::internal("the write-ahead log is not open"))?;
+
+        for block in blocks {
+            log.unsynced.insert(block.path().to_path_buf(), block);
+        }
+
+        log.size >= CHECKPOINT_SIZE
+    };
+
+    mem::drop(logged);
+
+    if checkpoint_due {
+        checkpoint().await
+    } else {
+        Ok(())
+    }
+}
+
+/// Replay the write-ahead log of the given `data_dir`, if any, and truncate it.
+///
+/// Returns the number of records replayed.
+pub(super) async fn replay(data_dir: &Path) -> TCResult<usize> {
+    let path = data_dir.join(WAL);
+    let data = match tokio::fs::read(&path).await {
+        Ok(data) => Bytes::from(data),
+        Err(cause) if cause.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(cause) => return Err(wal_err(cause)),
+    };
+
+    let mut records = 0;
+    let mut remaining = &data[..];
+    while remaining.len() >= HEADER_LEN {
+        let len = (&remaining[..8]).get_u64_le() as usize;
+        if remaining.len() < HEADER_LEN + len {
+            break;
+        }
+
+        let body = &remaining[HEADER_LEN..(HEADER_LEN + len)];
+        if blake3::hash(body).as_bytes() != &remaining[8..HEADER_LEN] {
+            break;
+        }
+
+        apply(data_dir, body).await?;
+        remaining = &remaining[(HEADER_LEN + len)..];
+        records += 1;
+    }
+
+    if !remaining.is_empty() {
+        warn!(
+            "discarding {} bytes of a partially-written record at the end of the write-ahead log",
+            remaining.len()
+        );
+    }
+
+    let file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .await
+        .map_err(wal_err)?;
+
+    file.set_len(0).await.map_err(wal_err)?;
+    file.sync_all().await.map_err(wal_err)?;
+
+    Ok(records)
+}
+
+async fn apply(data_dir: &Path, mut body: &[u8]) -> TCResult<()> {
+    let txn_id = get_str(&mut body)?;
+    let len = get_u32(&mut body)?;
+    debug!("replay {} blocks committed at {}", len, txn_id);
+
+    for _ in 0..len {
+        let kind = if body.has_remaining() {
+            body.get_u8()
+        } else {
+            return Err(invalid_record());
+        };
+
+        let path = data_dir.join(get_str(&mut body)?);
+
+        match kind {
+            PUT => {
+                let len = get_u64(&mut body)? as usize;
+                if body.remaining() < len {
+                    return Err(invalid_record());
+                }
+
+                let (contents, rest) = body.split_at(len);
+                body = rest;
+
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await.map_err(wal_err)?;
+                }
+
+                // write to a temporary file first, so that the block is replaced atomically
+                let mut tmp = path.clone().into_os_string();
+                tmp.push("_freqfs");
+
+                let mut file = tokio::fs::File::create(&tmp).await.map_err(wal_err)?;
+                file.write_all(contents).await.map_err(wal_err)?;
+                file.sync_all().await.map_err(wal_err)?;
+                tokio::fs::rename(&tmp, &path).await.map_err(wal_err)?;
+            }
+            DELETE => match tokio::fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(cause) if cause.kind() == io::ErrorKind::NotFound => {}
+                Err(cause) => return Err(wal_err(cause)),
+            },
+            _ => return Err(invalid_record()),
+        }
+    }
+
+    Ok(())
+}
+
+// prefix the given record `body` with its length and BLAKE3 hash
+fn frame(body: &[u8]) -> BytesMut {
+    let mut record = BytesMut::with_capacity(HEADER_LEN + body.len());
+    record.put_u64_le(body.len() as u64);
+    record.put_slice(blake3::hash(body).as_bytes());
+    record.put_slice(body);
+    record
+}
+
+fn put_str(buf: &mut BytesMut, s: &str) {
+    buf.put_u16_le(s.len() as u16);
+    buf.put_slice(s.as_bytes());
+}
+
+fn get_str<'a>(buf: &mut &'a [u8]) -> TCResult<&'a str> {
+    if buf.remaining() < 2 {
+        return Err(invalid_record());
+    }
+
+    let len = buf.get_u16_le() as usize;
+    if buf.remaining() < len {
+        return Err(invalid_record());
+    }
+
+    let (s, rest) = buf.split_at(len);
+    *buf = rest;
+    std::str::from_utf8(s).map_err(|_| invalid_record())
+}
+
+fn get_u32(buf: &mut &[u8]) -> TCResult<u32> {
+    if buf.remaining() < 4 {
+        Err(invalid_record())
+    } else {
+        Ok(buf.get_u32_le())
+    }
+}
+
+fn get_u64(buf: &mut &[u8]) -> TCResult<u64> {
+    if buf.remaining() < 8 {
+        Err(invalid_record())
+    } else {
+        Ok(buf.get_u64_le())
+    }
+}
+
+fn invalid_record() -> TCError {
+    TCError::internal("invalid write-ahead log record")
+}
+
+fn wal_err(cause: io::Error) -> TCError {
+    TCError::internal(format!("write-ahead log I/O error: {}", cause))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    // encode a record of the given blocks, each with its new contents or `None` if deleted
+    fn record(blocks: &[(&str, Option<&[u8]>)]) -> BytesMut {
+        let mut body = BytesMut::new();
+        put_str(&mut body, "txn");
+        body.put_u32_le(blocks.len() as u32);
+
+        for (name, contents) in blocks {
+            if let Some(contents) = contents {
+                body.put_u8(PUT);
+                put_str(&mut body, name);
+                body.put_u64_le(contents.len() as u64);
+                body.put_slice(contents);
+            } else {
+                body.put_u8(DELETE);
+                put_str(&mut body, name);
+            }
+        }
+
+        frame(&body)
+    }
+
+    #[tokio::test]
+    async fn test_replay_torn_record() {
+        let path = std::env::temp_dir().join(format!("tc-wal-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&path).expect("test dir");
+
+        let mut log = BytesMut::new();
+        log.extend_from_slice(&record(&[
+            ("table/1.node", Some(&b"one"[..])),
+            ("table/2.node", Some(&b"two"[..])),
+        ]));
+        log.extend_from_slice(&record(&[("table/1.node", None)]));
+
+        // the host stopped while appending the last record
+        let torn = record(&[("table/3.node", Some(&b"three"[..]))]);
+        log.extend_from_slice(&torn[..(torn.len() - 2)]);
+
+        std::fs::write(path.join(WAL), &log).expect("write-ahead log");
+
+        assert_eq!(replay(&path).await.expect("replay"), 2);
+        assert!(!path.join("table").join("1.node").exists());
+        assert!(!path.join("table").join("3.node").exists());
+
+        let two = std::fs::read(path.join("table").join("2.node")).expect("block");
+        assert_eq!(&two[..], b"two");
+
+        // the log is truncated, so replaying it again is a no-op
+        assert_eq!(std::fs::metadata(path.join(WAL)).expect("log").len(), 0);
+        assert_eq!(replay(&path).await.expect("replay"), 0);
+
+        std::fs::remove_dir_all(path).expect("clean up");
+    }
+
+    #[tokio::test]
+    async fn test_replay_corrupt_record() {
+        let path = std::env::temp_dir().join(format!("tc-wal-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&path).expect("test dir");
+
+        let mut corrupt = record(&[("1.node", Some(&b"one"[..]))]);
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0xff;
+
+        // a record which fails its checksum is discarded with every record after it
+        let mut log = BytesMut::new();
+        log.extend_from_slice(&corrupt);
+        log.extend_from_slice(&record(&[("2.node", Some(&b"two"[..]))]));
+        std::fs::write(path.join(WAL), &log).expect("write-ahead log");
+
+        assert_eq!(replay(&path).await.expect("replay"), 0);
+        assert!(!path.join("1.node").exists());
+        assert!(!path.join("2.node").exists());
+
+        std::fs::remove_dir_all(path).expect("clean up");
+    }
+}
@@ -1,6 +1,7 @@
 //! [`Gateway`] handles network traffic.
 
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
@@ -12,22 +13,81 @@ use futures::try_join;
 use log::debug;
 
 use tc_error::*;
-use tc_value::{Link, LinkHost, LinkProtocol, Value};
-use tcgeneric::{NetworkTime, TCBoxTryFuture, TCPathBuf};
+use tc_value::{DateTime, Link, LinkHost, LinkProtocol, Value};
+use tcgeneric::{Map, NetworkTime, PathSegment, TCBoxTryFuture, TCPathBuf};
 
+use crate::admission::{Admission, AdmissionPolicy, Permit};
+use crate::balance::{self, BalancePolicy, Balancer};
+use crate::chain::CompactionPolicy;
+use crate::cluster::Cluster;
 use crate::http;
 use crate::kernel::Kernel;
+use crate::object::InstanceExt;
+use crate::scalar::{OpRefType, Refer, Scope};
 use crate::state::State;
+use crate::trace::Recorder;
 use crate::txn::*;
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 
+// check for scheduled jobs more than once per minute, so that no minute is skipped
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The class of an inbound request, which determines its [`RequestLimits`].
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum RouteClass {
+    /// A write of data to a collection (e.g. a bulk upload), which may be large and slow
+    Collection,
+
+    /// Any other request, such as a read or a scalar op, which is expected to be small and fast
+    Scalar,
+}
+
+impl RouteClass {
+    /// Classify a request with the given `method` to the given `path`.
+    ///
+    /// A PUT request, or a POST request to construct a new collection or chain, is a
+    /// [`RouteClass::Collection`] request. Any other request is a [`RouteClass::Scalar`] request.
+    pub fn of(method: OpRefType, path: &[PathSegment]) -> Self {
+        let constructs_collection =
+            path.len() > 1 && path[0] == "state" && (path[1] == "collection" || path[1] == "chain");
+
+        match method {
+            OpRefType::Put => Self::Collection,
+            OpRefType::Post if constructs_collection => Self::Collection,
+            _ => Self::Scalar,
+        }
+    }
+}
+
+/// The limits of a [`RouteClass`] of inbound requests.
+#[derive(Clone, Copy)]
+pub struct RequestLimits {
+    /// The maximum size of a request body, in bytes (unlimited if `None`)
+    pub max_size: Option<u64>,
+
+    /// The maximum duration of a request, which is also the deadline of its transaction
+    pub ttl: Duration,
+}
+
 /// Configuration for [`Gateway`].
 #[derive(Clone)]
 pub struct Config {
     pub addr: IpAddr,
     pub http_port: u16,
+    pub pg_port: Option<u16>,
+    pub graphql: bool,
+    pub admin: bool,
+    pub admission: Option<AdmissionPolicy>,
+    pub balance: Option<BalancePolicy>,
+    pub compaction: Option<CompactionPolicy>,
+    #[cfg(feature = "tc-tensor")]
+    pub representation: Option<tc_tensor::RepresentationPolicy>,
+    pub record: Option<PathBuf>,
     pub request_ttl: Duration,
+    pub max_request_size: Option<u64>,
+    pub collection_limits: RequestLimits,
+    pub cache_size: usize,
 }
 
 /// A client used by [`Gateway`]
@@ -70,6 +130,8 @@ pub struct Gateway {
     txn_server: TxnServer,
     root: LinkHost,
     client: http::Client,
+    admission: Option<Admission>,
+    balancer: Option<Balancer>,
     actor: Actor,
 }
 
@@ -87,12 +149,17 @@ impl Gateway {
             Some(config.http_port),
         ));
 
+        let admission = config.admission.map(Admission::new);
+        let balancer = config.balance.map(|_| Balancer::new());
+
         Arc::new(Self {
             config,
             kernel,
             txn_server,
             root,
             client: http::Client::new(),
+            admission,
+            balancer,
             actor: Actor::new(Link::default().into()),
         })
     }
@@ -102,11 +169,48 @@ impl Gateway {
         self.config.request_ttl
     }
 
+    /// Return the configured [`RequestLimits`] of the given [`RouteClass`].
+    pub fn limits(&self, class: RouteClass) -> RequestLimits {
+        match class {
+            RouteClass::Collection => self.config.collection_limits,
+            RouteClass::Scalar => RequestLimits {
+                max_size: self.config.max_request_size,
+                ttl: self.config.request_ttl,
+            },
+        }
+    }
+
+    /// Return the configured [`tc_tensor::RepresentationPolicy`], if any.
+    #[cfg(feature = "tc-tensor")]
+    pub fn representation_policy(&self) -> Option<&tc_tensor::RepresentationPolicy> {
+        self.config.representation.as_ref()
+    }
+
     /// Return the network address of this `Gateway`
     pub fn root(&self) -> &LinkHost {
         &self.root
     }
 
+    /// Return the configured capacity of this host's filesystem cache, in bytes.
+    pub fn cache_size(&self) -> usize {
+        self.config.cache_size
+    }
+
+    /// Return the total size of the files this host has loaded in its filesystem cache.
+    pub async fn cache_occupancy(&self) -> usize {
+        self.txn_server.cache_occupancy().await
+    }
+
+    /// Report the current [`TxnMetrics`] of this host.
+    pub async fn txn_metrics(&self) -> TxnMetrics {
+        self.txn_server.metrics().await
+    }
+
+    /// Return the [`Cluster`]s hosted by this `Gateway`.
+    pub fn hosted(&self) -> Vec<Arc<InstanceExt<Cluster>>> {
+        self.kernel.hosted()
+    }
+
     /// Return a [`Link`] to the given path at this host.
     pub fn link(&self, path: TCPathBuf) -> Link {
         Link::from((self.root.clone(), path))
@@ -114,10 +218,14 @@ impl Gateway {
 
     /// Return a new, signed auth token with no claims.
     pub fn new_token(&self, txn_id: &TxnId) -> TCResult<(String, Claims)> {
+        self.sign_token(txn_id, self.config.request_ttl)
+    }
+
+    fn sign_token(&self, txn_id: &TxnId, ttl: Duration) -> TCResult<(String, Claims)> {
         let token = Token::new(
             self.root.clone().into(),
             txn_id.time().into(),
-            self.config.request_ttl,
+            ttl,
             self.actor.id().clone(),
             vec![],
         );
@@ -129,6 +237,18 @@ impl Gateway {
 
     /// Authorize a transaction to execute on this host.
     pub async fn new_txn(self: &Arc<Self>, txn_id: TxnId, token: Option<String>) -> TCResult<Txn> {
+        self.new_txn_with_ttl(txn_id, token, self.config.request_ttl)
+            .await
+    }
+
+    /// Authorize a transaction to execute on this host, which expires after the given `ttl`
+    /// unless the given `token` specifies otherwise.
+    pub async fn new_txn_with_ttl(
+        self: &Arc<Self>,
+        txn_id: TxnId,
+        token: Option<String>,
+        ttl: Duration,
+    ) -> TCResult<Txn> {
         let token = if let Some(token) = token {
             use rjwt::Resolve;
             Resolver::new(self, &self.root().clone().into(), &txn_id)
@@ -136,7 +256,7 @@ impl Gateway {
                 .map_err(TCError::unauthorized)
                 .await?
         } else {
-            self.new_token(&txn_id)?
+            self.sign_token(&txn_id, ttl)?
         };
 
         self.txn_server.new_txn(self.clone(), txn_id, token).await
@@ -152,9 +272,86 @@ impl Gateway {
         self.client.fetch(txn_id, link, key).await
     }
 
+    /// Generate a GraphQL schema for the hosted clusters which the given [`Txn`] may see,
+    /// if the GraphQL endpoint is enabled.
+    pub async fn graphql(&self, txn: &Txn) -> Option<crate::graphql::Schema> {
+        if self.config.graphql {
+            let clusters = self.kernel.visible(txn).await;
+            Some(crate::graphql::Schema::new(
+                clusters.iter().map(|cluster| &**cluster),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Construct the data sources of the admin dashboard, if the dashboard is enabled.
+    pub fn admin(&self) -> Option<crate::admin::Dashboard<'_>> {
+        if self.config.admin {
+            Some(crate::admin::Dashboard::new(
+                &self.root,
+                &self.kernel,
+                &self.txn_server,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Describe the hosted cluster at the given `path` as an OpenAPI document.
+    pub fn openapi(&self, path: &[PathSegment]) -> TCResult<serde_json::Value> {
+        self.kernel.openapi(self.root(), path)
+    }
+
+    /// Return the route label of a request to the given `path` in the metrics of this host.
+    pub fn route_label(&self, path: &str) -> String {
+        match path.parse::<TCPathBuf>() {
+            Ok(path) => self.kernel.route_label(&path),
+            Err(_) => crate::metrics::OTHER.to_string(),
+        }
+    }
+
+    /// Admit a request in the given [`Txn`] from the given `remote` address according to the
+    /// [`AdmissionPolicy`] of this host, or return a "too many requests" error.
+    ///
+    /// The request is released when the returned [`Permit`] is dropped.
+    pub async fn admit(&self, txn: &Txn, remote: IpAddr) -> TCResult<Option<Permit>> {
+        let admission = if let Some(admission) = &self.admission {
+            admission
+        } else {
+            return Ok(None);
+        };
+
+        if txn.has_owner() {
+            // this transaction was already admitted by the host where it began
+            return Ok(None);
+        }
+
+        // identify the client by its credentials, unless it didn't provide any
+        let client = match txn.request().scopes().iter().next() {
+            Some((host, actor_id, _)) if host.host().as_ref() != Some(self.root()) => {
+                format!("{} {}", host, actor_id)
+            }
+            _ => remote.to_string(),
+        };
+
+        admission.admit(&client).await.map(Some)
+    }
+
     /// Read the [`State`] with the given `key` at `link`.
     pub async fn get(&self, txn: &Txn, link: Link, key: Value) -> TCResult<State> {
         debug!("GET {}: {}", link, key);
+
+        let is_local = link
+            .host()
+            .as_ref()
+            .map(|host| host == self.root())
+            .unwrap_or(true);
+        if is_local && link.path()[..] == METRICS[..] {
+            key.expect_none()?;
+            return Ok(self.txn_server.metrics().await.into());
+        }
+
         match link.host() {
             None if link.path().is_empty() && key.is_none() => {
                 let public_key = Bytes::from(self.actor.public_key().as_bytes().to_vec());
@@ -162,8 +359,42 @@ impl Gateway {
             }
             None => self.kernel.get(txn, link.path(), key).await,
             Some(host) if host == self.root() => self.kernel.get(txn, link.path(), key).await,
-            _ => self.client.get(txn.clone(), link, key).await,
+            _ => self.get_remote(txn, link, key).await,
+        }
+    }
+
+    async fn get_remote(&self, txn: &Txn, link: Link, key: Value) -> TCResult<State> {
+        let balancer = if let Some(balancer) = &self.balancer {
+            balancer
+        } else {
+            return self.client.get(txn.clone(), link, key).await;
+        };
+
+        if let Some(selected) = balancer.select(&link) {
+            debug!("GET {} from replica {}", link, selected.replica);
+
+            let result = if selected.replica.host().as_ref() == Some(self.root()) {
+                self.kernel
+                    .get(txn, selected.target.path(), key.clone())
+                    .await
+            } else {
+                self.client
+                    .get(txn.clone(), selected.target, key.clone())
+                    .await
+            };
+
+            match result {
+                Err(cause) if balance::is_unavailable(&cause) => {
+                    debug!("replica {} is unavailable: {}", selected.replica, cause);
+                    balancer.mark_unhealthy(&selected.cluster, &selected.replica);
+                }
+                result => return result,
+            }
         }
+
+        let state = self.client.get(txn.clone(), link.clone(), key).await?;
+        balancer.observe(&link, &state);
+        Ok(state)
     }
 
     /// Update the [`State`] with the given `key` at `link` to `value`.
@@ -215,13 +446,107 @@ impl Gateway {
     /// Start this `Gateway`'s server
     pub fn listen(self: Arc<Self>) -> Pin<Box<impl Future<Output = Result<(), Error>> + 'static>> {
         Box::pin(async move {
-            match try_join!(self.clone().http_listen(), self.clone().replicate()) {
+            self.clone().recover().await?;
+
+            if let Some(policy) = self.config.compaction {
+                spawn_compaction_thread(self.clone(), policy);
+            }
+
+            if let Some(policy) = self.config.balance {
+                spawn_health_check_thread(self.clone(), policy);
+            }
+
+            spawn_scheduler_thread(self.clone());
+
+            let listeners = try_join!(
+                self.clone().http_listen(),
+                self.clone().pg_listen(),
+                self.clone().replicate()
+            );
+
+            match listeners {
                 Ok(_) => Ok(()),
                 Err(cause) => Err(cause),
             }
         })
     }
 
+    async fn recover(self: Arc<Self>) -> Result<(), Error> {
+        for cluster in self.kernel.hosted() {
+            let txn = self.new_txn(TxnId::new(Self::time()), None).await?;
+
+            cluster.recover(&txn).await?;
+        }
+
+        if let Some(cluster) = self.kernel.scheduler().cluster() {
+            let txn = self.new_txn(TxnId::new(Self::time()), None).await?;
+
+            cluster.recover(&txn).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn compact(self: &Arc<Self>, retain: u64) {
+        for cluster in self.kernel.hosted() {
+            let result = async {
+                let txn = self.new_txn(TxnId::new(Self::time()), None).await?;
+                cluster.compact(&txn, retain).await
+            };
+
+            if let Err(cause) = result.await {
+                log::warn!("unable to compact {}: {}", cluster, cause);
+            }
+        }
+    }
+
+    async fn run_scheduled(self: &Arc<Self>) {
+        let now = DateTime::from(Self::time());
+
+        let due = async {
+            let txn = self.new_txn(TxnId::new(Self::time()), None).await?;
+            self.kernel.scheduler().due(&txn, now).await
+        };
+
+        let due = match due.await {
+            Ok(due) => due,
+            Err(cause) => {
+                log::warn!("unable to read the scheduled jobs: {}", cause);
+                return;
+            }
+        };
+
+        for (name, op, token) in due {
+            let gateway = self.clone();
+
+            // run each job in its own task so that a slow job doesn't delay any other job
+            tokio::spawn(async move {
+                debug!("running scheduled job {}", name);
+
+                let result = async {
+                    // a job runs with the authorization of the request which scheduled it
+                    let txn_id = TxnId::new(Self::time());
+                    let txn = gateway.new_txn(txn_id, Some(token)).await?;
+                    let scope = Scope::<State>::new(None, Map::<State>::default());
+                    State::Scalar(op).resolve(&scope, &txn).await
+                };
+
+                let result = result.await;
+                if let Err(cause) = &result {
+                    log::warn!("scheduled job {} failed: {}", name, cause);
+                }
+
+                gateway.kernel.scheduler().record(&name, now, result).await;
+            });
+        }
+    }
+
+    async fn check_health(&self) {
+        if let Some(balancer) = &self.balancer {
+            balancer.check_health(&self.client).await;
+        }
+    }
+
     async fn replicate(self: Arc<Self>) -> Result<(), Error> {
         let result = async move {
             for cluster in self.kernel.hosted() {
@@ -261,12 +586,71 @@ impl Gateway {
         self: Arc<Self>,
     ) -> std::pin::Pin<Box<impl futures::Future<Output = Result<(), Error>>>> {
         let http_addr = (self.config.addr, self.config.http_port).into();
-        let server = crate::http::HTTPServer::new(self);
-        let listener = server.listen(http_addr).map_err(|e| {
-            let e: Error = Box::new(e);
-            e
-        });
+        let listener = async move {
+            let recorder = if let Some(path) = &self.config.record {
+                Some(Recorder::create(path).await?)
+            } else {
+                None
+            };
+
+            let server = crate::http::HTTPServer::new(self, recorder);
+            server.listen(http_addr).await.map_err(|e| {
+                let e: Error = Box::new(e);
+                e
+            })
+        };
 
         Box::pin(listener)
     }
+
+    async fn pg_listen(self: Arc<Self>) -> Result<(), Error> {
+        let pg_port = if let Some(pg_port) = self.config.pg_port {
+            pg_port
+        } else {
+            return Ok(());
+        };
+
+        let pg_addr = (self.config.addr, pg_port).into();
+        let server = crate::pg::PGServer::new(self);
+        server.listen(pg_addr).await.map_err(|e| {
+            let e: Error = Box::new(e);
+            e
+        })
+    }
+}
+
+fn spawn_compaction_thread(gateway: Arc<Gateway>, policy: CompactionPolicy) {
+    let mut interval = tokio::time::interval(policy.interval);
+
+    tokio::spawn(async move {
+        // the first tick completes immediately
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            gateway.compact(policy.retain).await;
+        }
+    });
+}
+
+fn spawn_scheduler_thread(gateway: Arc<Gateway>) {
+    let mut interval = tokio::time::interval(SCHEDULE_INTERVAL);
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            gateway.run_scheduled().await;
+        }
+    });
+}
+
+fn spawn_health_check_thread(gateway: Arc<Gateway>, policy: BalancePolicy) {
+    let mut interval = tokio::time::interval(policy.interval);
+
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            gateway.check_health().await;
+        }
+    });
 }
@@ -0,0 +1,626 @@
+//! An optional, read-only GraphQL interface to the tables and GET methods of hosted clusters.
+//!
+//! Each hosted `Table` or `BTree` is a top-level query field which returns a list of rows,
+//! named after its path with `/` replaced by `_` (e.g. `/app/db/users` is `app_db_users`).
+//! Table fields accept an argument per column (to filter by equality) as well as `limit`,
+//! `offset`, `order_by`, and `reverse`; a field without a `limit` returns at most
+//! [`DEFAULT_LIMIT`] rows. Each GET method of a cluster is a top-level field which accepts an
+//! optional `key` and returns JSON. A column (or JSON field) whose value is a `Link` can be
+//! resolved with a nested selection set, up to a depth of [`MAX_DEPTH`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use futures::future::{self, try_join_all, BoxFuture, TryFutureExt};
+use futures::{StreamExt, TryStreamExt};
+use log::debug;
+use safecast::CastInto;
+use serde_json::{json, Map as JsonMap, Value as Json};
+
+use tc_btree::{BTreeInstance, Column, Range};
+use tc_error::*;
+use tc_table::{Bounds, ColumnBound, Query, TableInstance, TableStream};
+use tc_transact::{IntoView, Transaction};
+use tc_value::{Link, Number, NumberType, Value, ValueType};
+use tcgeneric::{Id, PathSegment, TCPathBuf, Tuple};
+
+use crate::chain::{ChainInstance, Subject};
+use crate::cluster::Cluster;
+use crate::collection::Collection;
+use crate::object::InstanceExt;
+use crate::scalar::{OpDef, Scalar};
+use crate::state::State;
+use crate::txn::Txn;
+
+use parse::{Field, Literal};
+
+mod parse;
+
+/// The path of the GraphQL endpoint.
+pub const PATH: &str = "/graphql";
+
+/// The maximum depth of nested `Link` resolution.
+pub const MAX_DEPTH: usize = 3;
+
+/// The maximum number of rows returned by a `Table` or `BTree` field which has no `limit`.
+pub const DEFAULT_LIMIT: u64 = 1_000;
+
+const TYPENAME: &str = "__typename";
+
+enum Kind {
+    Table(Vec<Column>, Vec<Column>),
+    BTree(Vec<Column>),
+    Method,
+}
+
+struct Entry {
+    path: TCPathBuf,
+    kind: Kind,
+}
+
+/// A GraphQL schema generated from a set of hosted clusters.
+pub struct Schema {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Schema {
+    /// Generate a GraphQL `Schema` from the given hosted clusters.
+    pub fn new<'a, I: IntoIterator<Item = &'a InstanceExt<Cluster>>>(clusters: I) -> Self {
+        let mut entries = BTreeMap::new();
+
+        for cluster in clusters {
+            let root = TCPathBuf::from(cluster.path().to_vec());
+
+            for name in cluster.ns() {
+                if let Some(chain) = cluster.chain(name) {
+                    let path = root.clone().append(name.clone());
+                    collect_subject(&mut entries, path, chain.subject());
+                }
+            }
+
+            for (name, attr) in cluster.proto().iter() {
+                if let Scalar::Op(OpDef::Get(_)) = attr {
+                    let path = root.clone().append(name.clone());
+                    let kind = Kind::Method;
+                    entries.insert(field_name(&path), Entry { path, kind });
+                }
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Describe this `Schema` in the GraphQL schema definition language.
+    pub fn sdl(&self) -> String {
+        let mut query = Vec::with_capacity(self.entries.len());
+        let mut types = Vec::new();
+
+        for (name, entry) in &self.entries {
+            match &entry.kind {
+                Kind::Table(key, values) => {
+                    let mut args: Vec<String> = key
+                        .iter()
+                        .chain(values)
+                        .map(|col| format!("{}: {}", col.name, scalar_type(col, key)))
+                        .collect();
+
+                    args.extend(vec![
+                        "limit: Int".to_string(),
+                        "offset: Int".to_string(),
+                        "order_by: [String!]".to_string(),
+                        "reverse: Boolean".to_string(),
+                    ]);
+
+                    let type_name = row_type(name);
+                    query.push(format!(
+                        "  {}({}): [{}!]!",
+                        name,
+                        args.join(", "),
+                        type_name
+                    ));
+
+                    types.push(object_type(&type_name, key.iter().chain(values), key));
+                }
+                Kind::BTree(schema) => {
+                    let type_name = row_type(name);
+                    query.push(format!(
+                        "  {}(limit: Int, offset: Int, reverse: Boolean): [{}!]!",
+                        name, type_name
+                    ));
+
+                    types.push(object_type(&type_name, schema.iter(), &[]));
+                }
+                Kind::Method => query.push(format!("  {}(key: JSON): JSON", name)),
+            }
+        }
+
+        let mut sdl = format!("scalar JSON\n\ntype Query {{\n{}\n}}\n", query.join("\n"));
+        for object_type in types {
+            sdl.push('\n');
+            sdl.push_str(&object_type);
+        }
+
+        sdl
+    }
+
+    /// Execute the GraphQL `request` (of the form `{"query": ..., "variables": {...}}`)
+    /// within the given [`Txn`], and construct a GraphQL response.
+    pub async fn execute(&self, txn: &Txn, request: Json) -> Json {
+        match self.execute_inner(txn, request).await {
+            Ok(data) => json!({ "data": data }),
+            Err(cause) => json!({
+                "data": null,
+                "errors": [{ "message": cause.to_string() }],
+            }),
+        }
+    }
+
+    async fn execute_inner(&self, txn: &Txn, request: Json) -> TCResult<Json> {
+        let mut request = match request {
+            Json::Object(request) => request,
+            other => return Err(TCError::bad_request("invalid GraphQL request", other)),
+        };
+
+        let query = match request.remove("query") {
+            Some(Json::String(query)) => query,
+            _ => return Err(TCError::bad_request("GraphQL request requires a", "query")),
+        };
+
+        let variables = match request.remove("variables") {
+            Some(Json::Object(variables)) => variables
+                .into_iter()
+                .map(|(name, value)| (name, json_to_literal(value)))
+                .collect(),
+            None | Some(Json::Null) => HashMap::new(),
+            Some(other) => return Err(TCError::bad_request("invalid GraphQL variables", other)),
+        };
+
+        let fields = parse::parse(&query)?.bind(variables)?;
+        debug!("GraphQL query with {} top-level fields", fields.len());
+
+        // resolve each top-level field concurrently, within the same transaction
+        let resolved = try_join_all(fields.iter().map(|field| self.resolve(txn, field))).await?;
+
+        let data = fields
+            .iter()
+            .map(|field| field.response_key().to_string())
+            .zip(resolved)
+            .collect::<JsonMap<String, Json>>();
+
+        Ok(Json::Object(data))
+    }
+
+    async fn resolve(&self, txn: &Txn, field: &Field) -> TCResult<Json> {
+        if field.name == TYPENAME {
+            return Ok(Json::String("Query".to_string()));
+        }
+
+        let entry = self
+            .entries
+            .get(&field.name)
+            .ok_or_else(|| TCError::not_found(format!("GraphQL field {}", field.name)))?;
+
+        let link = txn.link(entry.path.clone());
+
+        match &entry.kind {
+            Kind::Table(key, values) => {
+                let table = match txn.get(link, Value::None).await? {
+                    State::Collection(Collection::Table(table)) => table,
+                    other => return Err(TCError::bad_request("expected a Table but found", other)),
+                };
+
+                let columns: Vec<Column> = key.iter().chain(values).cloned().collect();
+                let query = table_query(field, &columns)?;
+                let selected = selected_columns(field, &columns)?;
+
+                let table = query.apply(table)?.with_isolation(txn.isolation());
+                let rows = table.rows(*txn.id()).await?;
+                let rows: Vec<Vec<Value>> = rows.try_collect().await?;
+
+                let type_name = row_type(&field.name);
+                resolve_rows(txn, &field.selection, &selected, &type_name, rows).await
+            }
+            Kind::BTree(schema) => {
+                let btree = match txn.get(link, Value::None).await? {
+                    State::Collection(Collection::BTree(btree)) => btree,
+                    other => return Err(TCError::bad_request("expected a BTree but found", other)),
+                };
+
+                let reverse = optional_bool(field, "reverse")?;
+                let limit = optional_count(field, "limit")?.unwrap_or(DEFAULT_LIMIT);
+                let offset = optional_count(field, "offset")?.unwrap_or(0);
+
+                for (name, _) in &field.arguments {
+                    if name != "reverse" && name != "limit" && name != "offset" {
+                        return Err(TCError::bad_request("unrecognized BTree argument", name));
+                    }
+                }
+
+                let all: Vec<Id> = schema.iter().map(|col| col.name.clone()).collect();
+                let keys = btree
+                    .with_isolation(txn.isolation())
+                    .slice(Range::default(), reverse)?
+                    .keys(*txn.id())
+                    .await?
+                    .skip(offset as usize);
+
+                let rows: Vec<Vec<Value>> = keys.take(limit as usize).try_collect().await?;
+
+                let selected = selected_columns(field, schema)?;
+                let rows = rows
+                    .into_iter()
+                    .map(|row| project_row(&all, &selected, row))
+                    .collect();
+
+                let type_name = row_type(&field.name);
+                resolve_rows(txn, &field.selection, &selected, &type_name, rows).await
+            }
+            Kind::Method => {
+                let key = match field.argument("key") {
+                    Some(key) => literal_to_value(key)?,
+                    None => Value::None,
+                };
+
+                for (name, _) in &field.arguments {
+                    if name != "key" {
+                        return Err(TCError::bad_request("unrecognized method argument", name));
+                    }
+                }
+
+                let state = txn.get(link, key).await?;
+                let json = state_to_json(txn, state).await?;
+                resolve_json(txn, &field.selection, json, 1).await
+            }
+        }
+    }
+}
+
+fn collect_subject(entries: &mut BTreeMap<String, Entry>, path: TCPathBuf, subject: &Subject) {
+    let kind = match subject {
+        Subject::Table(table) => Kind::Table(table.key().to_vec(), table.values().to_vec()),
+        Subject::BTree(btree) => Kind::BTree(btree.schema().to_vec()),
+        Subject::Map(map) => {
+            for (name, subject) in map.iter() {
+                collect_subject(entries, path.clone().append(name.clone()), subject);
+            }
+
+            return;
+        }
+        _ => return,
+    };
+
+    entries.insert(field_name(&path), Entry { path, kind });
+}
+
+fn field_name(path: &[PathSegment]) -> String {
+    let name = path
+        .iter()
+        .map(|segment| segment.as_str())
+        .collect::<Vec<&str>>()
+        .join("_")
+        .replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "_");
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+fn row_type(field_name: &str) -> String {
+    format!("{}_row", field_name)
+}
+
+fn object_type<'a, I: Iterator<Item = &'a Column>>(
+    name: &str,
+    columns: I,
+    key: &[Column],
+) -> String {
+    let fields: Vec<String> = columns
+        .map(|col| format!("  {}: {}", col.name, scalar_type(col, key)))
+        .collect();
+
+    format!("type {} {{\n{}\n}}\n", name, fields.join("\n"))
+}
+
+fn scalar_type(column: &Column, key: &[Column]) -> &'static str {
+    if key.len() == 1 && key[0].name == column.name {
+        return "ID";
+    }
+
+    match column.dtype {
+        ValueType::Number(NumberType::Bool) => "Boolean",
+        ValueType::Number(NumberType::Int(_)) | ValueType::Number(NumberType::UInt(_)) => "Int",
+        ValueType::Number(NumberType::Float(_)) => "Float",
+        ValueType::String | ValueType::Id => "String",
+        _ => "JSON",
+    }
+}
+
+fn table_query(field: &Field, columns: &[Column]) -> TCResult<Query> {
+    let mut bounds = Bounds::default();
+    let mut order_by = None;
+
+    for (name, value) in &field.arguments {
+        match name.as_str() {
+            "limit" | "offset" | "reverse" => {}
+            "order_by" => {
+                let order = match value {
+                    Literal::List(names) => names.iter().map(literal_to_id).collect(),
+                    name => literal_to_id(name).map(|name| vec![name]),
+                }?;
+
+                order_by = Some(order);
+            }
+            name => {
+                let column = columns
+                    .iter()
+                    .find(|col| col.name.as_str() == name)
+                    .ok_or_else(|| TCError::not_found(format!("column {}", name)))?;
+
+                let value = literal_to_value(value)?;
+                bounds.insert(column.name.clone(), ColumnBound::Is(value));
+            }
+        }
+    }
+
+    let reverse = optional_bool(field, "reverse")?;
+    let order_by = match order_by {
+        Some(order_by) => Some((order_by, reverse)),
+        None if reverse => Some((vec![columns[0].name.clone()], reverse)),
+        None => None,
+    };
+
+    Ok(Query {
+        select: Some(selected_columns(field, columns)?),
+        bounds,
+        group_by: None,
+        order_by,
+        limit: Some(optional_count(field, "limit")?.unwrap_or(DEFAULT_LIMIT)),
+        offset: optional_count(field, "offset")?,
+    })
+}
+
+fn selected_columns(field: &Field, columns: &[Column]) -> TCResult<Vec<Id>> {
+    if field.selection.is_empty() {
+        return Err(TCError::bad_request(
+            "GraphQL list of rows requires a selection set",
+            &field.name,
+        ));
+    }
+
+    let mut selected = Vec::new();
+    let mut visited = HashSet::new();
+    for subfield in &field.selection {
+        if subfield.name == TYPENAME || !visited.insert(&subfield.name) {
+            continue;
+        }
+
+        let column = columns
+            .iter()
+            .find(|col| col.name.as_str() == subfield.name)
+            .ok_or_else(|| TCError::not_found(format!("column {}", subfield.name)))?;
+
+        selected.push(column.name.clone());
+    }
+
+    if selected.is_empty() {
+        // a selection set of only __typename still needs a column to count rows by
+        selected.push(columns[0].name.clone());
+    }
+
+    Ok(selected)
+}
+
+fn project_row(all: &[Id], selected: &[Id], row: Vec<Value>) -> Vec<Value> {
+    let row: HashMap<&Id, Value> = all.iter().zip(row).collect();
+    selected
+        .iter()
+        .map(|name| row.get(name).cloned().unwrap_or_default())
+        .collect()
+}
+
+async fn resolve_rows(
+    txn: &Txn,
+    selection: &[Field],
+    columns: &[Id],
+    type_name: &str,
+    rows: Vec<Vec<Value>>,
+) -> TCResult<Json> {
+    let rows = rows.into_iter().map(|row| async move {
+        let row: HashMap<&str, Value> = columns.iter().map(|col| col.as_str()).zip(row).collect();
+
+        let mut object = JsonMap::new();
+        for field in selection {
+            let value = if field.name == TYPENAME {
+                Json::String(type_name.to_string())
+            } else {
+                let value = row.get(field.name.as_str()).cloned().unwrap_or_default();
+                resolve_value(txn, field, value).await?
+            };
+
+            object.insert(field.response_key().to_string(), value);
+        }
+
+        TCResult::Ok(Json::Object(object))
+    });
+
+    try_join_all(rows).map_ok(Json::Array).await
+}
+
+async fn resolve_value(txn: &Txn, field: &Field, value: Value) -> TCResult<Json> {
+    if field.selection.is_empty() {
+        Ok(value_to_json(value))
+    } else {
+        resolve_json(txn, &field.selection, value_to_json(value), 1).await
+    }
+}
+
+/// Resolve the given `selection` on `json`, fetching any `Link`s selected with a nested selection.
+fn resolve_json<'a>(
+    txn: &'a Txn,
+    selection: &'a [Field],
+    json: Json,
+    depth: usize,
+) -> BoxFuture<'a, TCResult<Json>> {
+    Box::pin(async move {
+        if selection.is_empty() {
+            return Ok(json);
+        }
+
+        match json {
+            Json::Null => Ok(Json::Null),
+            Json::Array(items) => {
+                let items = items
+                    .into_iter()
+                    .map(|item| resolve_json(txn, selection, item, depth));
+
+                try_join_all(items).map_ok(Json::Array).await
+            }
+            Json::Object(mut object) => {
+                let mut resolved = JsonMap::new();
+                for field in selection {
+                    let value = object.remove(&field.name).unwrap_or(Json::Null);
+                    let value = resolve_json(txn, &field.selection, value, depth).await?;
+                    resolved.insert(field.response_key().to_string(), value);
+                }
+
+                Ok(Json::Object(resolved))
+            }
+            Json::String(link) if depth <= MAX_DEPTH => {
+                let link: Link = link.parse().map_err(|cause| {
+                    TCError::bad_request("GraphQL cannot resolve a selection set on", cause)
+                })?;
+
+                debug!("GraphQL resolving nested link {}", link);
+
+                let state = txn.get(link, Value::None).await?;
+                let json = state_to_json(txn, state).await?;
+                resolve_json(txn, selection, json, depth + 1).await
+            }
+            Json::String(link) => Err(TCError::bad_request(
+                format!(
+                    "GraphQL link resolution exceeds the maximum depth {} at",
+                    MAX_DEPTH
+                ),
+                link,
+            )),
+            other => Err(TCError::bad_request(
+                "GraphQL cannot resolve a selection set on",
+                other,
+            )),
+        }
+    })
+}
+
+pub(crate) async fn state_to_json(txn: &Txn, state: State) -> TCResult<Json> {
+    if let State::Scalar(Scalar::Value(value)) = state {
+        return Ok(value_to_json(value));
+    }
+
+    let view = state.into_view(txn.clone()).await?;
+    let encoded = destream_json::encode(view).map_err(TCError::internal)?;
+    let encoded = encoded
+        .map_err(TCError::internal)
+        .try_fold(Vec::new(), |mut buffer, chunk| {
+            buffer.extend_from_slice(&chunk);
+            future::ready(Ok(buffer))
+        })
+        .await?;
+
+    serde_json::from_slice(&encoded).map_err(TCError::internal)
+}
+
+pub(crate) fn value_to_json(value: Value) -> Json {
+    match value {
+        Value::None => Json::Null,
+        Value::Number(Number::Bool(b)) => Json::Bool(b.into()),
+        Value::Number(Number::Complex(c)) => Json::String(c.to_string()),
+        Value::Number(n @ Number::Float(_)) => {
+            let n: f64 = n.cast_into();
+            serde_json::Number::from_f64(n)
+                .map(Json::Number)
+                .unwrap_or(Json::Null)
+        }
+        Value::Number(n @ Number::UInt(_)) => {
+            let n: u64 = n.cast_into();
+            Json::from(n)
+        }
+        Value::Number(n) => {
+            let n: i64 = n.cast_into();
+            Json::from(n)
+        }
+        Value::String(s) => Json::String(s.to_string()),
+        Value::Tuple(tuple) => Json::Array(tuple.into_iter().map(value_to_json).collect()),
+        other => Json::String(other.to_string()),
+    }
+}
+
+fn json_to_literal(json: Json) -> Literal {
+    match json {
+        Json::Null => Literal::Null,
+        Json::Bool(b) => Literal::Boolean(b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Literal::Int(i),
+            None => Literal::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(s) => Literal::String(s),
+        Json::Array(items) => Literal::List(items.into_iter().map(json_to_literal).collect()),
+        Json::Object(object) => Literal::Object(
+            object
+                .into_iter()
+                .map(|(name, value)| (name, json_to_literal(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn literal_to_value(literal: &Literal) -> TCResult<Value> {
+    match literal {
+        Literal::Null => Ok(Value::None),
+        Literal::Boolean(b) => Ok(Value::from(*b)),
+        Literal::Int(i) => Ok(Value::Number(Number::from(*i))),
+        Literal::Float(x) => Ok(Value::Number(Number::from(*x))),
+        Literal::String(s) | Literal::Enum(s) => Ok(Value::String(s.to_string().into())),
+        Literal::List(items) => items
+            .iter()
+            .map(literal_to_value)
+            .collect::<TCResult<Tuple<Value>>>()
+            .map(Value::Tuple),
+        other => Err(TCError::bad_request(
+            "GraphQL argument is not a valid Value",
+            other,
+        )),
+    }
+}
+
+fn literal_to_id(literal: &Literal) -> TCResult<Id> {
+    match literal {
+        Literal::String(name) | Literal::Enum(name) => name.parse(),
+        other => Err(TCError::bad_request(
+            "expected a column name but found",
+            other,
+        )),
+    }
+}
+
+fn optional_bool(field: &Field, name: &str) -> TCResult<bool> {
+    match field.argument(name) {
+        None | Some(Literal::Null) => Ok(false),
+        Some(Literal::Boolean(b)) => Ok(*b),
+        Some(other) => Err(TCError::bad_request(
+            format!("GraphQL argument {} must be a Boolean, not", name),
+            other,
+        )),
+    }
+}
+
+fn optional_count(field: &Field, name: &str) -> TCResult<Option<u64>> {
+    match field.argument(name) {
+        None | Some(Literal::Null) => Ok(None),
+        Some(Literal::Int(count)) if *count >= 0 => Ok(Some(*count as u64)),
+        Some(other) => Err(TCError::bad_request(
+            format!("invalid GraphQL {}", name),
+            other,
+        )),
+    }
+}
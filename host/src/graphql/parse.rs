@@ -0,0 +1,514 @@
+//! A parser for the read-only subset of the GraphQL query language supported by TinyChain.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use tc_error::*;
+
+/// A literal (or variable) argument value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Enum(String),
+    List(Vec<Literal>),
+    Object(Vec<(String, Literal)>),
+    Variable(String),
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Boolean(b) => fmt::Display::fmt(b, f),
+            Self::Int(i) => fmt::Display::fmt(i, f),
+            Self::Float(x) => fmt::Display::fmt(x, f),
+            Self::String(s) => write!(f, "{:?}", s),
+            Self::Enum(name) => f.write_str(name),
+            Self::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Self::Object(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect();
+
+                write!(f, "{{{}}}", fields.join(", "))
+            }
+            Self::Variable(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+/// A field to resolve, with its arguments and (possibly empty) selection set.
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub alias: Option<String>,
+    pub name: String,
+    pub arguments: Vec<(String, Literal)>,
+    pub selection: Vec<Field>,
+}
+
+impl Field {
+    /// The key of this field in a response object.
+    pub fn response_key(&self) -> &str {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
+
+    /// Look up the argument with the given `name`.
+    pub fn argument(&self, name: &str) -> Option<&Literal> {
+        self.arguments
+            .iter()
+            .find(|(arg_name, _)| arg_name == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// A parsed query operation.
+pub struct Operation {
+    /// The default values of this operation's declared variables
+    pub defaults: HashMap<String, Literal>,
+
+    /// The top-level selection set of this operation
+    pub selection: Vec<Field>,
+}
+
+impl Operation {
+    /// Replace each variable in this operation's arguments with its value.
+    pub fn bind(self, mut variables: HashMap<String, Literal>) -> TCResult<Vec<Field>> {
+        for (name, default) in self.defaults {
+            variables.entry(name).or_insert(default);
+        }
+
+        self.selection
+            .into_iter()
+            .map(|field| bind_field(field, &variables))
+            .collect()
+    }
+}
+
+/// Parse a GraphQL document containing a single query operation.
+pub fn parse(document: &str) -> TCResult<Operation> {
+    let tokens = tokenize(document)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+    };
+
+    let operation = parser.operation()?;
+
+    if let Some(token) = parser.next() {
+        return Err(TCError::bad_request(
+            "GraphQL document may only contain a single operation, found",
+            token,
+        ));
+    }
+
+    Ok(operation)
+}
+
+fn bind_field(field: Field, variables: &HashMap<String, Literal>) -> TCResult<Field> {
+    let arguments = field
+        .arguments
+        .into_iter()
+        .map(|(name, value)| bind_literal(value, variables).map(|value| (name, value)))
+        .collect::<TCResult<_>>()?;
+
+    let selection = field
+        .selection
+        .into_iter()
+        .map(|field| bind_field(field, variables))
+        .collect::<TCResult<_>>()?;
+
+    Ok(Field {
+        alias: field.alias,
+        name: field.name,
+        arguments,
+        selection,
+    })
+}
+
+fn bind_literal(value: Literal, variables: &HashMap<String, Literal>) -> TCResult<Literal> {
+    match value {
+        Literal::Variable(name) => variables
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| TCError::bad_request("missing value for GraphQL variable", name)),
+        Literal::List(items) => items
+            .into_iter()
+            .map(|item| bind_literal(item, variables))
+            .collect::<TCResult<_>>()
+            .map(Literal::List),
+        Literal::Object(fields) => fields
+            .into_iter()
+            .map(|(name, value)| bind_literal(value, variables).map(|value| (name, value)))
+            .collect::<TCResult<_>>()
+            .map(Literal::Object),
+        other => Ok(other),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Punctuator(char),
+    Spread,
+    Name(String),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Punctuator(c) => write!(f, "{}", c),
+            Self::Spread => f.write_str("..."),
+            Self::Name(name) => f.write_str(name),
+            Self::Int(i) => fmt::Display::fmt(i, f),
+            Self::Float(x) => fmt::Display::fmt(x, f),
+            Self::String(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        if token.is_some() {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    fn punctuator(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punctuator(c)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> TCResult<()> {
+        if self.punctuator(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("expected {}", c)))
+        }
+    }
+
+    fn name(&mut self) -> TCResult<String> {
+        if let Some(Token::Name(name)) = self.peek() {
+            self.position += 1;
+            Ok(name.to_string())
+        } else {
+            Err(self.unexpected("expected a name"))
+        }
+    }
+
+    fn unexpected(&self, message: &str) -> TCError {
+        match self.peek() {
+            Some(token) => TCError::bad_request(format!("{} but found", message), token),
+            None => TCError::bad_request(message, "end of GraphQL document"),
+        }
+    }
+
+    fn operation(&mut self) -> TCResult<Operation> {
+        let mut defaults = HashMap::new();
+
+        match self.peek() {
+            Some(Token::Name(keyword)) if keyword == "query" => {
+                self.position += 1;
+
+                if let Some(Token::Name(_)) = self.peek() {
+                    self.position += 1;
+                }
+
+                if self.punctuator('(') {
+                    while !self.punctuator(')') {
+                        self.expect('$')?;
+                        let name = self.name()?;
+                        self.expect(':')?;
+                        self.skip_type()?;
+
+                        if self.punctuator('=') {
+                            defaults.insert(name, self.literal(true)?);
+                        }
+                    }
+                }
+            }
+            Some(Token::Name(keyword)) if keyword == "mutation" || keyword == "subscription" => {
+                return Err(TCError::unsupported(format!(
+                    "GraphQL {} (the GraphQL endpoint is read-only)",
+                    keyword
+                )));
+            }
+            _ => {}
+        }
+
+        let selection = self.selection_set()?;
+        Ok(Operation {
+            defaults,
+            selection,
+        })
+    }
+
+    fn skip_type(&mut self) -> TCResult<()> {
+        if self.punctuator('[') {
+            self.skip_type()?;
+            self.expect(']')?;
+        } else {
+            self.name()?;
+        }
+
+        self.punctuator('!');
+        Ok(())
+    }
+
+    fn selection_set(&mut self) -> TCResult<Vec<Field>> {
+        self.expect('{')?;
+
+        let mut selection = Vec::new();
+        while !self.punctuator('}') {
+            if self.peek() == Some(&Token::Spread) {
+                return Err(TCError::unsupported("GraphQL fragments are not supported"));
+            }
+
+            selection.push(self.field()?);
+        }
+
+        if selection.is_empty() {
+            Err(TCError::bad_request(
+                "GraphQL selection set cannot be",
+                "empty",
+            ))
+        } else {
+            Ok(selection)
+        }
+    }
+
+    fn field(&mut self) -> TCResult<Field> {
+        let name = self.name()?;
+        let (alias, name) = if self.punctuator(':') {
+            (Some(name), self.name()?)
+        } else {
+            (None, name)
+        };
+
+        let mut arguments = Vec::new();
+        if self.punctuator('(') {
+            while !self.punctuator(')') {
+                let name = self.name()?;
+                self.expect(':')?;
+                arguments.push((name, self.literal(false)?));
+            }
+        }
+
+        if self.peek() == Some(&Token::Punctuator('@')) {
+            return Err(TCError::unsupported("GraphQL directives are not supported"));
+        }
+
+        let selection = if self.peek() == Some(&Token::Punctuator('{')) {
+            self.selection_set()?
+        } else {
+            vec![]
+        };
+
+        Ok(Field {
+            alias,
+            name,
+            arguments,
+            selection,
+        })
+    }
+
+    fn literal(&mut self, constant: bool) -> TCResult<Literal> {
+        let token = match self.peek() {
+            Some(Token::Punctuator('$')) if constant => None,
+            Some(Token::Punctuator(c)) if !matches!(c, '$' | '[' | '{') => None,
+            Some(Token::Spread) => None,
+            token => token,
+        };
+
+        let token = token.ok_or_else(|| self.unexpected("expected a value"))?;
+        self.position += 1;
+
+        match token {
+            Token::Punctuator('$') => self.name().map(Literal::Variable),
+            Token::Punctuator('[') => {
+                let mut items = Vec::new();
+                while !self.punctuator(']') {
+                    items.push(self.literal(constant)?);
+                }
+
+                Ok(Literal::List(items))
+            }
+            Token::Punctuator('{') => {
+                let mut fields = Vec::new();
+                while !self.punctuator('}') {
+                    let name = self.name()?;
+                    self.expect(':')?;
+                    fields.push((name, self.literal(constant)?));
+                }
+
+                Ok(Literal::Object(fields))
+            }
+            Token::Int(i) => Ok(Literal::Int(*i)),
+            Token::Float(x) => Ok(Literal::Float(*x)),
+            Token::String(s) => Ok(Literal::String(s.to_string())),
+            Token::Name(name) => match name.as_str() {
+                "null" => Ok(Literal::Null),
+                "true" => Ok(Literal::Boolean(true)),
+                "false" => Ok(Literal::Boolean(false)),
+                other => Ok(Literal::Enum(other.to_string())),
+            },
+            Token::Punctuator(_) | Token::Spread => unreachable!("GraphQL value token {}", token),
+        }
+    }
+}
+
+fn tokenize(document: &str) -> TCResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = document.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() || c == ',' || c == '\u{feff}' => {}
+            '#' => {
+                while let Some(c) = chars.next() {
+                    if c == '\n' || c == '\r' {
+                        break;
+                    }
+                }
+            }
+            '.' => {
+                if chars.next() == Some('.') && chars.next() == Some('.') {
+                    tokens.push(Token::Spread);
+                } else {
+                    return Err(TCError::bad_request("invalid GraphQL token", "."));
+                }
+            }
+            '!' | '$' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '|' | '}' => {
+                tokens.push(Token::Punctuator(c))
+            }
+            '"' => tokens.push(Token::String(string(&mut chars)?)),
+            c if c == '-' || c.is_ascii_digit() => tokens.push(number(c, &mut chars)?),
+            c if c == '_' || c.is_ascii_alphabetic() => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.peek() {
+                    if *c == '_' || c.is_ascii_alphanumeric() {
+                        name.push(*c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                tokens.push(Token::Name(name));
+            }
+            other => return Err(TCError::bad_request("invalid character in GraphQL", other)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn string(chars: &mut Peekable<Chars>) -> TCResult<String> {
+    let mut string = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('r') => string.push('\r'),
+                Some('t') => string.push('\t'),
+                Some('b') => string.push('\u{8}'),
+                Some('f') => string.push('\u{c}'),
+                Some('u') => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(std::char::from_u32)
+                        .ok_or_else(|| {
+                            TCError::bad_request("invalid unicode escape in GraphQL", &code)
+                        })?;
+
+                    string.push(c);
+                }
+                Some(c) => string.push(c),
+                None => break,
+            },
+            Some('\n') | None => break,
+            Some(c) => string.push(c),
+        }
+    }
+
+    Err(TCError::bad_request(
+        "unterminated string in GraphQL",
+        string,
+    ))
+}
+
+fn number(first: char, chars: &mut Peekable<Chars>) -> TCResult<Token> {
+    let mut number = first.to_string();
+    while let Some(c) = chars.peek() {
+        if c.is_ascii_digit() || *c == '.' || *c == 'e' || *c == 'E' || *c == '+' || *c == '-' {
+            number.push(*c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+
+    if let Ok(i) = number.parse() {
+        Ok(Token::Int(i))
+    } else {
+        number
+            .parse()
+            .map(Token::Float)
+            .map_err(|cause| TCError::bad_request("invalid number in GraphQL", cause))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let query = r#"
+            query Users($limit: Int = 10) {
+                users: app_users(active: true, limit: $limit) { id name }
+            }
+        "#;
+
+        let operation = parse(query).unwrap();
+        let fields = operation.bind(HashMap::new()).unwrap();
+        assert_eq!(fields.len(), 1);
+
+        let field = &fields[0];
+        assert_eq!(field.response_key(), "users");
+        assert_eq!(field.name, "app_users");
+        assert_eq!(field.argument("limit"), Some(&Literal::Int(10)));
+        assert_eq!(field.argument("active"), Some(&Literal::Boolean(true)));
+        assert_eq!(field.selection.len(), 2);
+    }
+}
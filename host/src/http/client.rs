@@ -12,7 +12,7 @@ use tc_transact::{IntoView, Transaction, TxnId};
 use tc_value::{Link, Value};
 use tcgeneric::label;
 
-use crate::http::Encoding;
+use crate::http::{Encoding, TRACE_ID};
 use crate::state::State;
 use crate::txn::Txn;
 
@@ -71,7 +71,7 @@ impl crate::gateway::Client for Client {
         }
 
         let uri = url(&link, txn.id(), &key)?;
-        let req = req_builder("GET", uri, Some(txn.request().token()));
+        let req = req_builder("GET", uri, Some(&txn));
 
         let txn = txn.subcontext_tmp().await?;
         let response = self
@@ -101,7 +101,7 @@ impl crate::gateway::Client for Client {
         }
 
         let uri = url(&link, txn.id(), &key)?;
-        let req = req_builder("PUT", uri, Some(txn.request().token()))
+        let req = req_builder("PUT", uri, Some(&txn))
             .header(hyper::header::CONTENT_TYPE, Encoding::Tbon.to_string());
 
         let txn = txn.subcontext_tmp().await?;
@@ -132,7 +132,7 @@ impl crate::gateway::Client for Client {
         }
 
         let uri = url(&link, txn.id(), &Value::default())?;
-        let req = req_builder("POST", uri, Some(txn.request().token()))
+        let req = req_builder("POST", uri, Some(&txn))
             .header(hyper::header::CONTENT_TYPE, Encoding::Tbon.to_string());
 
         let txn = txn.subcontext_tmp().await?;
@@ -171,7 +171,7 @@ impl crate::gateway::Client for Client {
         }
 
         let uri = url(&link, txn.id(), &key)?;
-        let req = req_builder("DELETE", uri, Some(txn.request().token()));
+        let req = req_builder("DELETE", uri, Some(txn));
 
         let response = self
             .client
@@ -205,14 +205,18 @@ fn url(link: &Link, txn_id: &TxnId, key: &Value) -> TCResult<Url> {
     Ok(url)
 }
 
-fn req_builder(method: &str, url: Url, auth: Option<&str>) -> http::request::Builder {
+fn req_builder(method: &str, url: Url, txn: Option<&Txn>) -> http::request::Builder {
+    tracing::debug!(method, %url, "outgoing request");
+
     let req = hyper::Request::builder()
         .method(method)
         .header(hyper::header::ACCEPT_ENCODING, Encoding::Tbon.to_string())
         .uri(url.to_string());
 
-    if let Some(token) = auth {
+    if let Some(txn) = txn {
+        let token = txn.request().token();
         req.header(hyper::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(TRACE_ID, txn.trace_id())
     } else {
         req
     }
@@ -251,6 +255,7 @@ async fn transform_error(source: &Link, response: hyper::Response<Body>) -> TCEr
         StatusCode::NOT_IMPLEMENTED => ErrorType::NotImplemented,
         StatusCode::UNAUTHORIZED => ErrorType::Unauthorized,
         StatusCode::REQUEST_TIMEOUT => ErrorType::Timeout,
+        StatusCode::TOO_MANY_REQUESTS => ErrorType::TooManyRequests,
         _ => ErrorType::BadGateway,
     };
 
@@ -13,6 +13,11 @@ mod server;
 pub use client::*;
 pub use server::*;
 
+/// The header which propagates the trace ID of a request to the hosts it calls.
+pub const TRACE_ID: &str = "x-trace-id";
+
+const MAX_TRACE_ID_LEN: usize = 128;
+
 trait Accept: Default + FromStr {
     fn parse_header(header: Option<&HeaderValue>) -> TCResult<Self> {
         let header = if let Some(header) = header {
@@ -67,6 +72,15 @@ trait Accept: Default + FromStr {
 enum Encoding {
     Json,
     Tbon,
+    /// A dense `Tensor` as a NumPy `.npy` array
+    #[cfg(feature = "tc-tensor")]
+    Npy,
+    /// A sparse `Tensor` as a NumPy `.npz` archive
+    #[cfg(feature = "tc-tensor")]
+    Npz,
+    /// A `Table` as an Apache Arrow IPC stream
+    #[cfg(feature = "arrow-export")]
+    Arrow,
 }
 
 impl Default for Encoding {
@@ -82,6 +96,12 @@ impl FromStr for Encoding {
         match s.trim() {
             "application/json" => Ok(Self::Json),
             "application/tbon" => Ok(Self::Tbon),
+            #[cfg(feature = "tc-tensor")]
+            "application/x-npy" => Ok(Self::Npy),
+            #[cfg(feature = "tc-tensor")]
+            "application/x-npz" => Ok(Self::Npz),
+            #[cfg(feature = "arrow-export")]
+            "application/vnd.apache.arrow.stream" => Ok(Self::Arrow),
             _ => Err(TCError::bad_request("encoding not supported", s)),
         }
     }
@@ -94,6 +114,12 @@ impl fmt::Display for Encoding {
         f.write_str(match self {
             Self::Json => "application/json",
             Self::Tbon => "application/tbon",
+            #[cfg(feature = "tc-tensor")]
+            Self::Npy => "application/x-npy",
+            #[cfg(feature = "tc-tensor")]
+            Self::Npz => "application/x-npz",
+            #[cfg(feature = "arrow-export")]
+            Self::Arrow => "application/vnd.apache.arrow.stream",
         })
     }
 }
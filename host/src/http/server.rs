@@ -1,99 +1,264 @@
 use std::collections::HashMap;
 use std::fmt;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::future::{self, TryFutureExt};
 use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use hyper::header::HeaderValue;
+use hyper::server::conn::AddrStream;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Response};
 use serde::de::DeserializeOwned;
+use serde_json::json;
+use tracing::Instrument;
 
 use tc_error::*;
-use tc_transact::{IntoView, TxnId};
+use tc_transact::{IntoView, Transaction, TxnId};
 use tcgeneric::{NetworkTime, TCPathBuf};
 
-use crate::gateway::Gateway;
+use crate::admin;
+use crate::gateway::{Gateway, RequestLimits, RouteClass};
+use crate::graphql;
+use crate::metrics;
+use crate::scalar::OpRefType;
 use crate::state::State;
+use crate::trace::{Envelope, Recorder};
 use crate::txn::*;
 
-use super::{Accept, Encoding};
+use super::{Accept, Encoding, MAX_TRACE_ID_LEN, TRACE_ID};
 
 type GetParams = HashMap<String, String>;
 
+const OPENAPI_SUFFIX: &str = "/openapi.json";
+
 /// TinyChain's HTTP server. Should only be used through a [`Gateway`].
 pub struct HTTPServer {
     gateway: Arc<Gateway>,
+    recorder: Option<Recorder>,
 }
 
 impl HTTPServer {
-    pub fn new(gateway: Arc<Gateway>) -> Self {
-        Self { gateway }
+    pub fn new(gateway: Arc<Gateway>, recorder: Option<Recorder>) -> Self {
+        Self { gateway, recorder }
     }
 
     async fn handle_timeout(
         self: Arc<Self>,
         request: hyper::Request<Body>,
+        remote: IpAddr,
     ) -> Result<Response<Body>, hyper::Error> {
-        match tokio::time::timeout(self.gateway.request_ttl(), self.handle(request)).await {
+        let envelope = self
+            .recorder
+            .as_ref()
+            .map(|recorder| envelope(recorder, &request));
+
+        let method = request.method().clone();
+        let path = request.uri().path().to_string();
+
+        let limits = self.gateway.limits(route_class(&request));
+        let handler = self.clone().handle(request, remote, limits);
+        let response = match tokio::time::timeout(limits.ttl, handler).await {
             Ok(result) => result,
             Err(cause) => Ok(transform_error(
                 TCError::timeout(cause),
                 Encoding::default(),
             )),
+        };
+
+        if let Ok(response) = &response {
+            let route = self.gateway.route_label(&path);
+            metrics::record_request(method.as_str(), &route, response.status().as_u16());
         }
+
+        if let (Some(recorder), Some(mut envelope)) = (&self.recorder, envelope) {
+            if let Ok(response) = &response {
+                envelope.status = response.status().as_u16();
+                envelope.duration = recorder.elapsed() - envelope.offset;
+                recorder.record(envelope);
+            }
+        }
+
+        response
     }
 
     async fn handle(
         self: Arc<Self>,
         request: hyper::Request<Body>,
+        remote: IpAddr,
+        limits: RequestLimits,
     ) -> Result<Response<Body>, hyper::Error> {
+        if request.method() == hyper::Method::GET && request.uri().path().ends_with(OPENAPI_SUFFIX)
+        {
+            return Ok(self.openapi(request.uri().path()));
+        }
+
+        if request.method() == hyper::Method::GET && request.uri().path() == metrics::PATH {
+            let mut response = Response::new(Body::from(metrics::render()));
+            response.headers_mut().insert(
+                hyper::header::CONTENT_TYPE,
+                metrics::CONTENT_TYPE.parse().expect("content type header"),
+            );
+
+            return Ok(response);
+        }
+
         let (params, txn, accept_encoding, request_encoding) =
-            match self.process_headers(&request).await {
+            match self.process_headers(&request, limits).await {
                 Ok(header_data) => header_data,
                 Err(cause) => return Ok(transform_error(cause, Encoding::default())),
             };
 
-        let state = match self.route(request_encoding, &txn, params, request).await {
-            Ok(state) => state,
+        let _permit = match self.gateway.admit(&txn, remote).await {
+            Ok(permit) => permit,
             Err(cause) => return Ok(transform_error(cause, accept_encoding)),
         };
 
+        let span = tracing::info_span!(
+            "request",
+            method = %request.method(),
+            path = request.uri().path(),
+            txn_id = %txn.id(),
+            trace_id = txn.trace_id(),
+        );
+
+        let trace_id = HeaderValue::from_str(txn.trace_id());
+
+        let mut response = self
+            .respond(
+                params,
+                txn,
+                accept_encoding,
+                request_encoding,
+                request,
+                limits,
+            )
+            .instrument(span.clone())
+            .await;
+
+        tracing::debug!(parent: &span, status = response.status().as_u16(), "response");
+
+        if let Ok(trace_id) = trace_id {
+            response.headers_mut().insert(TRACE_ID, trace_id);
+        }
+
+        Ok(response)
+    }
+
+    async fn respond(
+        &self,
+        params: GetParams,
+        txn: Txn,
+        accept_encoding: Encoding,
+        request_encoding: Encoding,
+        request: hyper::Request<Body>,
+        limits: RequestLimits,
+    ) -> Response<Body> {
+        let path = request.uri().path().to_string();
+        let txn_id = *txn.id();
+        let report = |cause| ErrorReport::new(cause).at(&path).in_txn(txn_id);
+
+        if request.uri().path() == graphql::PATH {
+            if let Some(schema) = self.gateway.graphql(&txn).await {
+                return graphql(schema, &txn, params, request, limits.max_size).await;
+            }
+        }
+
+        if admin::is_admin_path(request.uri().path()) {
+            if let Some(dashboard) = self.gateway.admin() {
+                return admin(dashboard, &txn, params, request).await;
+            }
+        }
+
+        let state = match self
+            .route(request_encoding, &txn, params, request, limits.max_size)
+            .await
+        {
+            Ok(state) => state,
+            Err(cause) => return error_response(report(cause), accept_encoding),
+        };
+
+        #[cfg(feature = "tc-tensor")]
+        if let Encoding::Npy | Encoding::Npz = accept_encoding {
+            let sparse = accept_encoding == Encoding::Npz;
+            return match crate::import::npy::encode(txn, state, sparse).await {
+                Ok(response) => encoded(Body::wrap_stream(response), accept_encoding),
+                Err(cause) => error_response(report(cause), accept_encoding),
+            };
+        }
+
+        #[cfg(feature = "arrow-export")]
+        if accept_encoding == Encoding::Arrow {
+            return match crate::import::arrow::encode(txn, state).await {
+                Ok(response) => encoded(Body::wrap_stream(response), accept_encoding),
+                Err(cause) => error_response(report(cause), accept_encoding),
+            };
+        }
+
         let view = match state.into_view(txn).await {
             Ok(view) => view,
-            Err(cause) => return Ok(transform_error(cause, accept_encoding)),
+            Err(cause) => return error_response(report(cause), accept_encoding),
         };
 
         let body = match accept_encoding {
-            Encoding::Json => match destream_json::encode(view) {
-                Ok(response) => Body::wrap_stream(response.chain(delimiter(b"\n"))),
-                Err(cause) => return Ok(transform_error(TCError::internal(cause), Encoding::Json)),
-            },
             Encoding::Tbon => match tbon::en::encode(view) {
                 Ok(response) => Body::wrap_stream(response.map_err(TCError::internal)),
-                Err(cause) => return Ok(transform_error(TCError::internal(cause), Encoding::Tbon)),
+                Err(cause) => {
+                    return error_response(report(TCError::internal(cause)), Encoding::Tbon)
+                }
+            },
+            _ => match destream_json::encode(view) {
+                Ok(response) => Body::wrap_stream(response.chain(delimiter(b"\n"))),
+                Err(cause) => {
+                    return error_response(report(TCError::internal(cause)), Encoding::Json)
+                }
             },
         };
 
-        let mut response = Response::new(body);
+        encoded(body, accept_encoding)
+    }
+
+    fn openapi(&self, path: &str) -> Response<Body> {
+        let document = path
+            .parse()
+            .and_then(|path: TCPathBuf| self.gateway.openapi(&path));
+
+        let document = match document {
+            Ok(document) => document,
+            Err(cause) => return transform_error(cause, Encoding::Json),
+        };
+
+        let mut response = Response::new(Body::from(document.to_string()));
 
         response.headers_mut().insert(
             hyper::header::CONTENT_TYPE,
-            accept_encoding
-                .to_string()
-                .parse()
-                .expect("content type header"),
+            "application/json".parse().expect("content type header"),
         );
 
-        Ok(response)
+        response
     }
 
     async fn process_headers(
         &self,
         http_request: &hyper::Request<Body>,
+        limits: RequestLimits,
     ) -> TCResult<(GetParams, Txn, Encoding, Encoding)> {
+        if let Some(max_size) = limits.max_size {
+            let content_length = http_request
+                .headers()
+                .get(hyper::header::CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok())
+                .and_then(|len| len.parse::<u64>().ok());
+
+            if let Some(content_length) = content_length {
+                if content_length > max_size {
+                    return Err(too_large(max_size));
+                }
+            }
+        }
+
         let content_type =
             if let Some(header) = http_request.headers().get(hyper::header::CONTENT_TYPE) {
                 header
@@ -140,7 +305,23 @@ impl HTTPServer {
             TxnId::new(NetworkTime::now())
         };
 
-        let txn = self.gateway.new_txn(txn_id, token).await?;
+        let txn = self
+            .gateway
+            .new_txn_with_ttl(txn_id, token, limits.ttl)
+            .await?;
+
+        let txn = if let Some(isolation) = params.remove("isolation") {
+            txn.with_isolation(isolation.parse()?)
+        } else {
+            txn
+        };
+
+        let txn = if let Some(trace_id) = http_request.headers().get(TRACE_ID) {
+            txn.with_trace_id(parse_trace_id(trace_id)?)
+        } else {
+            txn
+        };
+
         Ok((params, txn, accept_encoding, content_type))
     }
 
@@ -150,6 +331,7 @@ impl HTTPServer {
         txn: &Txn,
         mut params: GetParams,
         http_request: hyper::Request<Body>,
+        max_size: Option<u64>,
     ) -> TCResult<State> {
         let path: TCPathBuf = http_request.uri().path().parse()?;
 
@@ -161,7 +343,8 @@ impl HTTPServer {
 
             &hyper::Method::PUT => {
                 let key = get_param(&mut params, "key")?.unwrap_or_default();
-                let value = destream_body(http_request.into_body(), encoding, txn.clone()).await?;
+                let body = limit_body(http_request.into_body(), max_size);
+                let value = destream_body(body, encoding, txn.clone()).await?;
                 self.gateway
                     .put(txn, path.into(), key, value)
                     .map_ok(State::from)
@@ -169,7 +352,8 @@ impl HTTPServer {
             }
 
             &hyper::Method::POST => {
-                let data = destream_body(http_request.into_body(), encoding, txn.clone()).await?;
+                let body = limit_body(http_request.into_body(), max_size);
+                let data = destream_body(body, encoding, txn.clone()).await?;
                 self.gateway.post(txn, path.into(), data).await
             }
 
@@ -194,12 +378,13 @@ impl crate::gateway::Server for HTTPServer {
         println!("HTTP server listening on {}", &addr);
         let server = Arc::new(self);
 
-        let new_service = make_service_fn(move |_| {
+        let new_service = make_service_fn(move |conn: &AddrStream| {
             let server = server.clone();
-            async {
+            let remote = conn.remote_addr().ip();
+            async move {
                 Ok::<_, hyper::Error>(service_fn(move |req| {
                     let server = server.clone();
-                    HTTPServer::handle_timeout(server, req)
+                    HTTPServer::handle_timeout(server, req, remote)
                 }))
             }
         });
@@ -217,7 +402,197 @@ impl fmt::Display for HTTPServer {
     }
 }
 
-async fn destream_body(body: hyper::Body, encoding: Encoding, txn: Txn) -> TCResult<State> {
+async fn graphql(
+    schema: graphql::Schema,
+    txn: &Txn,
+    mut params: GetParams,
+    http_request: hyper::Request<Body>,
+    max_size: Option<u64>,
+) -> Response<Body> {
+    let request = match http_request.method() {
+        &hyper::Method::GET => match params.remove("query") {
+            Some(query) => {
+                let variables = match params.remove("variables") {
+                    Some(variables) => serde_json::from_str(&variables)
+                        .map_err(|e| TCError::bad_request("invalid GraphQL variables", e)),
+                    None => Ok(serde_json::Value::Null),
+                };
+
+                variables.map(|variables| json!({ "query": query, "variables": variables }))
+            }
+            None => {
+                let mut response = Response::new(Body::from(schema.sdl()));
+                response.headers_mut().insert(
+                    hyper::header::CONTENT_TYPE,
+                    "text/plain".parse().expect("content type header"),
+                );
+
+                return response;
+            }
+        },
+        &hyper::Method::POST => limit_body(http_request.into_body(), max_size)
+            .try_fold(Vec::new(), |mut body, chunk| {
+                body.extend_from_slice(&chunk);
+                future::ready(Ok(body))
+            })
+            .await
+            .and_then(|body| {
+                serde_json::from_slice(&body)
+                    .map_err(|e| TCError::bad_request("invalid GraphQL request", e))
+            }),
+        other => Err(TCError::method_not_allowed(other, "GraphQL", graphql::PATH)),
+    };
+
+    let response = match request {
+        Ok(request) => schema.execute(txn, request).await,
+        Err(cause) => return transform_error(cause, Encoding::Json),
+    };
+
+    let mut response = Response::new(Body::from(response.to_string()));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        "application/json".parse().expect("content type header"),
+    );
+
+    response
+}
+
+async fn admin(
+    dashboard: admin::Dashboard<'_>,
+    txn: &Txn,
+    mut params: GetParams,
+    http_request: hyper::Request<Body>,
+) -> Response<Body> {
+    if http_request.method() != hyper::Method::GET {
+        let cause = TCError::method_not_allowed(http_request.method(), "admin", admin::PREFIX);
+        return transform_error(cause, Encoding::Json);
+    }
+
+    let path = http_request.uri().path();
+
+    let page = match path {
+        admin::OVERVIEW => dashboard.overview(txn).await.map(json_page),
+        admin::BROWSE => {
+            let browse = async {
+                let path = params
+                    .remove("path")
+                    .ok_or_else(|| TCError::bad_request("missing required parameter", "path"))?;
+
+                let key = get_param(&mut params, "key")?.unwrap_or_default();
+                let limit = get_param(&mut params, "limit")?;
+                dashboard.browse(txn, path.parse()?, key, limit).await
+            };
+
+            browse.await.map(json_page)
+        }
+        path => admin::asset(path).ok_or_else(|| TCError::not_found(path)),
+    };
+
+    let (content_type, body) = match page {
+        Ok(page) => page,
+        Err(cause) => return transform_error(cause, Encoding::Json),
+    };
+
+    let mut response = Response::new(Body::from(body));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        content_type.parse().expect("content type header"),
+    );
+
+    response
+}
+
+/// Construct the sanitized [`Envelope`] of the given `request`, to be completed by its response.
+fn envelope(recorder: &Recorder, request: &hyper::Request<Body>) -> Envelope {
+    let key_len = request
+        .uri()
+        .query()
+        .and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(name, _)| name == "key")
+                .map(|(_, key)| key.len())
+        })
+        .unwrap_or_default();
+
+    let body_len = request
+        .headers()
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok())
+        .and_then(|len| len.parse().ok())
+        .unwrap_or_default();
+
+    Envelope {
+        offset: recorder.elapsed(),
+        method: request.method().to_string(),
+        path: request.uri().path().to_string(),
+        key_len,
+        body_len,
+        status: 0,
+        duration: Default::default(),
+    }
+}
+
+fn parse_trace_id(header: &HeaderValue) -> TCResult<String> {
+    let trace_id = header
+        .to_str()
+        .map_err(|e| TCError::bad_request("invalid trace ID header", e))?;
+
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+    if trace_id.is_empty() || trace_id.len() > MAX_TRACE_ID_LEN || !trace_id.chars().all(valid) {
+        Err(TCError::bad_request("invalid trace ID", trace_id))
+    } else {
+        Ok(trace_id.to_string())
+    }
+}
+
+fn json_page(json: serde_json::Value) -> (&'static str, Bytes) {
+    ("application/json", Bytes::from(json.to_string()))
+}
+
+/// Classify the given `request` in order to determine its [`RequestLimits`].
+fn route_class(request: &hyper::Request<Body>) -> RouteClass {
+    let method = match request.method() {
+        &hyper::Method::PUT => OpRefType::Put,
+        &hyper::Method::POST => OpRefType::Post,
+        &hyper::Method::DELETE => OpRefType::Delete,
+        _ => OpRefType::Get,
+    };
+
+    match request.uri().path().parse::<TCPathBuf>() {
+        Ok(path) => RouteClass::of(method, &path),
+        Err(_) => RouteClass::Scalar,
+    }
+}
+
+/// Stop reading the given request `body` if it exceeds `max_size` bytes.
+fn limit_body(
+    body: hyper::Body,
+    max_size: Option<u64>,
+) -> impl Stream<Item = TCResult<Bytes>> + Send + Unpin {
+    let mut size = 0u64;
+
+    body.map(move |chunk| {
+        let chunk = chunk.map_err(|e| TCError::bad_request("error reading request body", e))?;
+        size += chunk.len() as u64;
+
+        match max_size {
+            Some(max_size) if size > max_size => Err(too_large(max_size)),
+            _ => Ok(chunk),
+        }
+    })
+}
+
+fn too_large(max_size: u64) -> TCError {
+    TCError::bad_request(
+        "request body exceeds the maximum size in bytes of",
+        max_size,
+    )
+}
+
+async fn destream_body<S>(body: S, encoding: Encoding, txn: Txn) -> TCResult<State>
+where
+    S: Stream<Item = TCResult<Bytes>> + Send + Unpin,
+{
     const ERR_DESERIALIZE: &str = "error deserializing HTTP request body";
 
     match encoding {
@@ -231,9 +606,30 @@ async fn destream_body(body: hyper::Body, encoding: Encoding, txn: Txn) -> TCRes
                 .map_err(|e| TCError::bad_request(ERR_DESERIALIZE, e))
                 .await
         }
+        #[cfg(feature = "tc-tensor")]
+        Encoding::Npy | Encoding::Npz => {
+            let data: Vec<Bytes> = body.try_collect().await?;
+            let data = Bytes::from(data.concat());
+            crate::import::npy::decode(&txn, data, encoding == Encoding::Npz).await
+        }
+        #[cfg(feature = "arrow-export")]
+        Encoding::Arrow => Err(TCError::unsupported(
+            "an HTTP request body in the Arrow IPC format",
+        )),
     }
 }
 
+fn encoded(body: Body, encoding: Encoding) -> Response<Body> {
+    let mut response = Response::new(body);
+
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        encoding.to_string().parse().expect("content type header"),
+    );
+
+    response
+}
+
 fn get_param<T: DeserializeOwned>(
     params: &mut HashMap<String, String>,
     name: &str,
@@ -250,7 +646,11 @@ fn get_param<T: DeserializeOwned>(
 }
 
 fn transform_error(err: TCError, encoding: Encoding) -> hyper::Response<Body> {
-    let code = match err.code() {
+    error_response(ErrorReport::new(err), encoding)
+}
+
+fn error_response(report: ErrorReport, encoding: Encoding) -> hyper::Response<Body> {
+    let code = match report.error().code() {
         BadGateway => StatusCode::BAD_GATEWAY,
         BadRequest => StatusCode::BAD_REQUEST,
         Forbidden => StatusCode::FORBIDDEN,
@@ -260,16 +660,23 @@ fn transform_error(err: TCError, encoding: Encoding) -> hyper::Response<Body> {
         NotFound => StatusCode::NOT_FOUND,
         NotImplemented => StatusCode::NOT_IMPLEMENTED,
         Timeout => StatusCode::REQUEST_TIMEOUT,
+        TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
         Unauthorized => StatusCode::UNAUTHORIZED,
     };
 
+    // an error can't be encoded as a NumPy array, so it falls back to JSON
+    let encoding = match encoding {
+        Encoding::Tbon => Encoding::Tbon,
+        _ => Encoding::Json,
+    };
+
     let body = match encoding {
-        Encoding::Json => {
-            let encoded = destream_json::encode(err).expect("encode error");
+        Encoding::Tbon => Body::wrap_stream(tbon::en::encode(report).expect("encode error")),
+        _ => {
+            let encoded = destream_json::encode(report).expect("encode error");
             let encoded = encoded.chain(delimiter(b"\n"));
             Body::wrap_stream(encoded)
         }
-        Encoding::Tbon => Body::wrap_stream(tbon::en::encode(err).expect("encode error")),
     };
 
     let mut response = hyper::Response::new(body);
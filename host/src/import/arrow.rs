@@ -0,0 +1,212 @@
+//! Export [`Table`]s in the Apache Arrow IPC streaming format.
+//!
+//! The rows of a `Table` are streamed as a series of record batches of up to [`BATCH_SIZE`] rows
+//! each, so a large `Table` is never buffered in memory. Each column maps to the nearest Arrow
+//! type: a number to the Arrow number type of the same width, `Bytes` to binary, and a string,
+//! `Link`, or other scalar with a text representation to a UTF-8 string. A column of type
+//! `Complex`, `Tuple`, or `Value` can't be exported.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use ::arrow::array::{
+    ArrayRef, BinaryArray, BooleanArray, Float32Array, Float64Array, Int16Array, Int32Array,
+    Int64Array, Int8Array, StringArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+};
+use ::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use ::arrow::error::ArrowError;
+use ::arrow::ipc::writer::StreamWriter;
+use ::arrow::record_batch::RecordBatch;
+use bytes::Bytes;
+use futures::future;
+use futures::stream::{self, StreamExt};
+use safecast::{CastFrom, TryCastFrom};
+
+use tc_btree::Column;
+use tc_error::*;
+use tc_table::{TableInstance, TableStream};
+use tc_transact::Transaction;
+use tc_value::{FloatType, IntType, Number, NumberType, UIntType, Value, ValueType};
+use tcgeneric::TCBoxTryStream;
+
+use crate::collection::Collection;
+use crate::state::State;
+use crate::txn::Txn;
+
+/// The maximum number of rows in each record batch.
+pub const BATCH_SIZE: usize = 1024;
+
+// the end-of-stream marker of the Arrow IPC streaming format
+const END_OF_STREAM: [u8; 8] = [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+
+/// Encode the given `state`, which must be a `Table`, as an Arrow IPC stream.
+pub async fn encode(txn: Txn, state: State) -> TCResult<TCBoxTryStream<'static, Bytes>> {
+    let table = match state {
+        State::Collection(Collection::Table(table)) => table,
+        other => {
+            return Err(TCError::bad_request(
+                "only a Table can be encoded as an Arrow IPC stream, not",
+                other,
+            ))
+        }
+    };
+
+    let fields = table
+        .key()
+        .iter()
+        .chain(table.values())
+        .map(field)
+        .collect::<TCResult<Vec<Field>>>()?;
+
+    let schema = Arc::new(Schema::new(fields));
+
+    let buffer = Buffer::default();
+    let mut writer = StreamWriter::try_new(buffer.clone(), &schema).map_err(arrow_err)?;
+    let header = buffer.take();
+
+    let rows = table.rows(*txn.id()).await?;
+    let batches = rows.chunks(BATCH_SIZE).map(move |rows| {
+        let rows = rows.into_iter().collect::<TCResult<Vec<Vec<Value>>>>()?;
+        let batch = record_batch(&schema, &rows)?;
+        writer.write(&batch).map_err(arrow_err)?;
+        Ok(buffer.take())
+    });
+
+    let header = stream::once(future::ready(Ok(header)));
+    let end = stream::once(future::ready(Ok(Bytes::from_static(&END_OF_STREAM))));
+    Ok(Box::pin(header.chain(batches).chain(end)))
+}
+
+/// A buffer shared with a [`StreamWriter`], so that each message can be sent as it's written.
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Buffer {
+    fn take(&self) -> Bytes {
+        let mut buffer = self.0.lock().expect("Arrow IPC buffer");
+        Bytes::from(std::mem::take(&mut *buffer))
+    }
+}
+
+impl io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut buffer = self.0.lock().expect("Arrow IPC buffer");
+        buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn field(column: &Column) -> TCResult<Field> {
+    let dtype = match column.dtype {
+        ValueType::Number(NumberType::Bool) => DataType::Boolean,
+        ValueType::Number(NumberType::Float(FloatType::F32)) => DataType::Float32,
+        ValueType::Number(NumberType::Float(_)) | ValueType::Number(NumberType::Number) => {
+            DataType::Float64
+        }
+        ValueType::Number(NumberType::Int(IntType::I8)) => DataType::Int8,
+        ValueType::Number(NumberType::Int(IntType::I16)) => DataType::Int16,
+        ValueType::Number(NumberType::Int(IntType::I32)) => DataType::Int32,
+        ValueType::Number(NumberType::Int(_)) => DataType::Int64,
+        ValueType::Number(NumberType::UInt(UIntType::U8)) => DataType::UInt8,
+        ValueType::Number(NumberType::UInt(UIntType::U16)) => DataType::UInt16,
+        ValueType::Number(NumberType::UInt(UIntType::U32)) => DataType::UInt32,
+        ValueType::Number(NumberType::UInt(_)) => DataType::UInt64,
+        ValueType::Bytes => DataType::Binary,
+        ValueType::BigInt
+        | ValueType::DateTime
+        | ValueType::Decimal
+        | ValueType::Id
+        | ValueType::Link
+        | ValueType::String
+        | ValueType::Version => DataType::Utf8,
+        other => {
+            return Err(TCError::unsupported(format!(
+                "Arrow export of column {} of {}",
+                column.name, other
+            )))
+        }
+    };
+
+    Ok(Field::new(column.name.as_str(), dtype, true))
+}
+
+fn record_batch(schema: &SchemaRef, rows: &[Vec<Value>]) -> TCResult<RecordBatch> {
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| array(field.data_type(), rows, i))
+        .collect::<TCResult<Vec<ArrayRef>>>()?;
+
+    RecordBatch::try_new(schema.clone(), columns).map_err(arrow_err)
+}
+
+fn array(dtype: &DataType, rows: &[Vec<Value>], i: usize) -> TCResult<ArrayRef> {
+    match dtype {
+        DataType::Boolean => numbers::<_, BooleanArray>(rows, i, bool::cast_from),
+        DataType::Float32 => numbers::<_, Float32Array>(rows, i, f32::cast_from),
+        DataType::Float64 => numbers::<_, Float64Array>(rows, i, f64::cast_from),
+        DataType::Int8 => numbers::<_, Int8Array>(rows, i, |n| i16::cast_from(n) as i8),
+        DataType::Int16 => numbers::<_, Int16Array>(rows, i, i16::cast_from),
+        DataType::Int32 => numbers::<_, Int32Array>(rows, i, i32::cast_from),
+        DataType::Int64 => numbers::<_, Int64Array>(rows, i, i64::cast_from),
+        DataType::UInt8 => numbers::<_, UInt8Array>(rows, i, u8::cast_from),
+        DataType::UInt16 => numbers::<_, UInt16Array>(rows, i, u16::cast_from),
+        DataType::UInt32 => numbers::<_, UInt32Array>(rows, i, u32::cast_from),
+        DataType::UInt64 => numbers::<_, UInt64Array>(rows, i, u64::cast_from),
+        DataType::Binary => {
+            let values = rows
+                .iter()
+                .map(|row| match &row[i] {
+                    Value::None => Ok(None),
+                    Value::Bytes(bytes) => Ok(Some(&bytes[..])),
+                    other => Err(TCError::bad_request("expected Bytes, not", other)),
+                })
+                .collect::<TCResult<Vec<Option<&[u8]>>>>()?;
+
+            Ok(Arc::new(BinaryArray::from(values)))
+        }
+        DataType::Utf8 => {
+            let values = rows
+                .iter()
+                .map(|row| match &row[i] {
+                    Value::None => None,
+                    value => Some(value.to_string()),
+                })
+                .collect::<Vec<Option<String>>>();
+
+            Ok(Arc::new(StringArray::from(values)))
+        }
+        other => Err(TCError::internal(format!(
+            "no Arrow encoding for a column of type {:?}",
+            other
+        ))),
+    }
+}
+
+fn numbers<T, A>(rows: &[Vec<Value>], i: usize, cast: fn(Number) -> T) -> TCResult<ArrayRef>
+where
+    A: From<Vec<Option<T>>> + ::arrow::array::Array + 'static,
+{
+    let values = rows
+        .iter()
+        .map(|row| match &row[i] {
+            Value::None => Ok(None),
+            value => Number::try_cast_from(value.clone(), |v| {
+                TCError::bad_request("expected a Number, not", v)
+            })
+            .map(cast)
+            .map(Some),
+        })
+        .collect::<TCResult<Vec<Option<T>>>>()?;
+
+    Ok(Arc::new(A::from(values)))
+}
+
+fn arrow_err(cause: ArrowError) -> TCError {
+    TCError::internal(format!("Arrow IPC encoding error: {}", cause))
+}
@@ -0,0 +1,275 @@
+//! Import the datasets in an HDF5 file as dense [`Tensor`]s.
+//!
+//! Each numeric dataset becomes a dense `Tensor` with the same shape and the nearest
+//! [`NumberType`], and the attributes of each dataset and group are returned as a `Map` of
+//! `Value`s. Datasets of any other type (e.g. strings or compound types) are skipped.
+//!
+//! The HDF5 library can only read a file from the filesystem, so an uploaded file is first written
+//! to a temporary file, which is removed once it's been read.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ::hdf5::types::{FloatSize, IntSize, TypeDescriptor, VarLenUnicode};
+use ::hdf5::{Container, Group, Location};
+use bytes::Bytes;
+use futures::stream;
+use log::debug;
+
+use tc_error::*;
+use tc_tensor::{DenseAccess, TensorType};
+use tc_transact::fs::Dir;
+use tc_transact::Transaction;
+use tc_value::{FloatType, IntType, Link, Number, NumberType, TCString, UIntType, Value};
+use tcgeneric::{label, Map, TCPathBuf};
+
+use crate::collection::{Collection, DenseTensor, DenseTensorFile, Tensor};
+use crate::state::State;
+use crate::txn::Txn;
+
+static UPLOADS: AtomicU64 = AtomicU64::new(0);
+
+/// A dataset or group read from an HDF5 file.
+struct Entry {
+    path: TCPathBuf,
+    attributes: Map<Value>,
+    data: Option<(Vec<u64>, NumberType, Vec<Number>)>,
+}
+
+/// Import each dataset in the given HDF5 `file` as a dense [`Tensor`].
+///
+/// Returns one `Map` per group and dataset in the file, with its `path` in the file, its
+/// `attributes`, and (for a numeric dataset) its `tensor`. If `dest` is given, each `Tensor` is
+/// also written to `dest` plus the path of its dataset, in the current transaction.
+pub async fn import(txn: &Txn, file: Bytes, dest: Option<Link>) -> TCResult<State> {
+    let path = std::env::temp_dir().join(format!(
+        "tc-upload-{}-{}.h5",
+        txn.id(),
+        UPLOADS.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    tokio::fs::write(&path, &file)
+        .await
+        .map_err(|e| TCError::internal(format!("unable to write HDF5 upload: {}", e)))?;
+
+    let entries = {
+        let path = path.clone();
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || span.in_scope(|| read_file(&path))).await
+    };
+
+    if let Err(cause) = tokio::fs::remove_file(&path).await {
+        debug!("unable to remove HDF5 upload {:?}: {}", path, cause);
+    }
+
+    let entries = entries
+        .map_err(TCError::internal)
+        .and_then(|entries| entries)?;
+
+    let mut imported = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let tensor = if let Some((shape, dtype, data)) = entry.data {
+            let tensor = create_tensor(txn, shape, dtype, data).await?;
+
+            if let Some(dest) = &dest {
+                let mut link = dest.clone();
+                link.extend(entry.path.iter().cloned());
+
+                debug!("import HDF5 dataset {} to {}", entry.path, link);
+                txn.put(link, Value::None, tensor.clone().into()).await?;
+            }
+
+            tensor.into()
+        } else {
+            State::default()
+        };
+
+        let mut description = Map::new();
+        description.insert(label("path").into(), Value::from(entry.path).into());
+        description.insert(label("attributes").into(), to_state(entry.attributes));
+        description.insert(label("tensor").into(), tensor);
+        imported.push(State::Map(description));
+    }
+
+    Ok(State::Tuple(imported.into()))
+}
+
+async fn create_tensor(
+    txn: &Txn,
+    shape: Vec<u64>,
+    dtype: NumberType,
+    data: Vec<Number>,
+) -> TCResult<Collection> {
+    let txn_id = *txn.id();
+    let file = txn
+        .context()
+        .create_file_unique(txn_id, TensorType::Dense)
+        .await?;
+
+    let values = stream::iter(data.into_iter().map(Ok));
+    let blocks = DenseTensorFile::from_values(file, txn_id, shape.into(), dtype, values).await?;
+    let tensor = DenseTensor::from(blocks.accessor());
+    Ok(Collection::Tensor(Tensor::from(tensor)))
+}
+
+fn read_file(path: &Path) -> TCResult<Vec<Entry>> {
+    let file = ::hdf5::File::open(path)
+        .map_err(|e| TCError::bad_request("unable to read HDF5 file", e))?;
+
+    let mut entries = Vec::new();
+    read_group(&file, &mut entries)?;
+    Ok(entries)
+}
+
+fn read_group(group: &Group, entries: &mut Vec<Entry>) -> TCResult<()> {
+    entries.push(Entry {
+        path: parse_path(&group.name())?,
+        attributes: read_attributes(group)?,
+        data: None,
+    });
+
+    for dataset in group.datasets().map_err(hdf5_err)? {
+        let path = parse_path(&dataset.name())?;
+        let data = read_data(&dataset)?;
+        if data.is_none() {
+            debug!("skip non-numeric HDF5 dataset {}", path);
+        }
+
+        entries.push(Entry {
+            path,
+            attributes: read_attributes(&dataset)?,
+            data,
+        });
+    }
+
+    for group in group.groups().map_err(hdf5_err)? {
+        read_group(&group, entries)?;
+    }
+
+    Ok(())
+}
+
+fn read_data(dataset: &Container) -> TCResult<Option<(Vec<u64>, NumberType, Vec<Number>)>> {
+    let dtype = match number_type(dataset)? {
+        Some(dtype) => dtype,
+        None => return Ok(None),
+    };
+
+    let shape = dataset.shape().into_iter().map(|dim| dim as u64).collect();
+    let data = read_numbers(dataset, dtype)?;
+    Ok(Some((shape, dtype, data)))
+}
+
+fn read_attributes(location: &Location) -> TCResult<Map<Value>> {
+    let mut attributes = Map::new();
+
+    for name in location.attr_names().map_err(hdf5_err)? {
+        let id = match name.parse() {
+            Ok(id) => id,
+            Err(_) => {
+                debug!("skip HDF5 attribute with invalid name {}", name);
+                continue;
+            }
+        };
+
+        let attr = location.attr(&name).map_err(hdf5_err)?;
+        let descriptor = attr.dtype().and_then(|dtype| dtype.to_descriptor());
+
+        let value = match descriptor.map_err(hdf5_err)? {
+            TypeDescriptor::VarLenUnicode
+            | TypeDescriptor::VarLenAscii
+            | TypeDescriptor::FixedUnicode(_)
+            | TypeDescriptor::FixedAscii(_) => {
+                let value: VarLenUnicode = attr.read_scalar().map_err(hdf5_err)?;
+                Value::String(TCString::from(value.as_str().to_string()))
+            }
+            _ => match number_type(&attr)? {
+                Some(dtype) => {
+                    let mut numbers = read_numbers(&attr, dtype)?;
+                    if attr.is_scalar() && numbers.len() == 1 {
+                        Value::Number(numbers.remove(0))
+                    } else {
+                        Value::Tuple(numbers.into_iter().map(Value::Number).collect())
+                    }
+                }
+                None => {
+                    debug!("skip HDF5 attribute {} of unsupported type", name);
+                    continue;
+                }
+            },
+        };
+
+        attributes.insert(id, value);
+    }
+
+    Ok(attributes)
+}
+
+fn number_type(container: &Container) -> TCResult<Option<NumberType>> {
+    let descriptor = container
+        .dtype()
+        .and_then(|dtype| dtype.to_descriptor())
+        .map_err(hdf5_err)?;
+
+    let dtype = match descriptor {
+        TypeDescriptor::Boolean => NumberType::Bool,
+        TypeDescriptor::Integer(size) => NumberType::Int(match size {
+            IntSize::U1 => IntType::I8,
+            IntSize::U2 => IntType::I16,
+            IntSize::U4 => IntType::I32,
+            IntSize::U8 => IntType::I64,
+        }),
+        TypeDescriptor::Unsigned(size) => NumberType::UInt(match size {
+            IntSize::U1 => UIntType::U8,
+            IntSize::U2 => UIntType::U16,
+            IntSize::U4 => UIntType::U32,
+            IntSize::U8 => UIntType::U64,
+        }),
+        TypeDescriptor::Float(FloatSize::U4) => NumberType::Float(FloatType::F32),
+        TypeDescriptor::Float(_) => NumberType::Float(FloatType::F64),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(dtype))
+}
+
+fn read_numbers(container: &Container, dtype: NumberType) -> TCResult<Vec<Number>> {
+    fn read<T: ::hdf5::H5Type + Into<Number>>(container: &Container) -> TCResult<Vec<Number>> {
+        container
+            .read_raw::<T>()
+            .map(|data| data.into_iter().map(T::into).collect())
+            .map_err(hdf5_err)
+    }
+
+    match dtype {
+        NumberType::Bool => read::<bool>(container),
+        NumberType::Int(IntType::I8) => read::<i8>(container),
+        NumberType::Int(IntType::I16) => read::<i16>(container),
+        NumberType::Int(IntType::I32) => read::<i32>(container),
+        NumberType::Int(_) => read::<i64>(container),
+        NumberType::UInt(UIntType::U8) => read::<u8>(container),
+        NumberType::UInt(UIntType::U16) => read::<u16>(container),
+        NumberType::UInt(UIntType::U32) => read::<u32>(container),
+        NumberType::UInt(_) => read::<u64>(container),
+        NumberType::Float(FloatType::F32) => read::<f32>(container),
+        _ => read::<f64>(container),
+    }
+}
+
+fn parse_path(name: &str) -> TCResult<TCPathBuf> {
+    name.parse()
+        .map_err(|cause| TCError::bad_request(format!("invalid HDF5 path {}", name), cause))
+}
+
+fn to_state(attributes: Map<Value>) -> State {
+    State::Map(
+        attributes
+            .into_iter()
+            .map(|(name, value)| (name, State::from(value)))
+            .collect(),
+    )
+}
+
+fn hdf5_err(cause: ::hdf5::Error) -> TCError {
+    TCError::bad_request("error reading HDF5 file", cause)
+}
@@ -0,0 +1,8 @@
+//! Import and export data in common external file formats.
+
+#[cfg(feature = "arrow-export")]
+pub mod arrow;
+#[cfg(feature = "hdf5-import")]
+pub mod hdf5;
+#[cfg(feature = "tc-tensor")]
+pub mod npy;
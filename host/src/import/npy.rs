@@ -0,0 +1,672 @@
+//! Encode and decode [`Tensor`]s in NumPy's `.npy` and `.npz` formats.
+//!
+//! A dense `Tensor` is encoded as a single `.npy` array, streamed one block at a time after a
+//! header which gives its shape and data type.
+//!
+//! A sparse `Tensor` is encoded as an uncompressed `.npz` archive of the arrays `coords` (with
+//! shape `[ndim, nnz]`), `data`, `shape`, and `fill_value`, the same layout as `sparse.save_npz`
+//! writes for a `sparse.COO` array. The archive begins with the checksum and size of each array,
+//! so it's buffered in memory before it's sent.
+//!
+//! Only little-endian, C-ordered arrays of booleans and numbers can be decoded. An `.npz` archive
+//! must be written by `numpy.savez`, not `numpy.savez_compressed`.
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::iter;
+
+use bytes::Bytes;
+use futures::future;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use safecast::CastFrom;
+
+use tc_error::*;
+use tc_tensor::{
+    DenseAccess, Schema, SparseAccess, TensorAccess, TensorIO, TensorInstance, TensorType,
+};
+use tc_transact::fs::Dir;
+use tc_transact::Transaction;
+use tc_value::{
+    Complex, ComplexType, Float, FloatType, Int, IntType, Number, NumberClass, NumberInstance,
+    NumberType, UInt, UIntType,
+};
+use tcgeneric::TCBoxTryStream;
+
+use crate::collection::{Collection, DenseTensor, DenseTensorFile, SparseTensor, Tensor};
+use crate::state::State;
+use crate::txn::Txn;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+const ALIGN: usize = 64;
+const INDEX: NumberType = NumberType::Int(IntType::I64);
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_DIRECTORY: u32 = 0x06054b50;
+const ZIP64_EXTRA: u16 = 0x0001;
+const ZIP_VERSION: u16 = 20;
+const ZIP_DATE: u16 = 0x21; // 1980-01-01, the earliest date a zip archive can record
+
+/// Encode the given `state`, which must be a [`Tensor`], as a `.npy` array, or as an `.npz`
+/// archive if `sparse` is `true`.
+pub async fn encode(
+    txn: Txn,
+    state: State,
+    sparse: bool,
+) -> TCResult<TCBoxTryStream<'static, Bytes>> {
+    let tensor = match state {
+        State::Collection(Collection::Tensor(tensor)) => tensor,
+        other => {
+            return Err(TCError::bad_request(
+                "only a Tensor can be encoded as a NumPy array, not",
+                other,
+            ))
+        }
+    };
+
+    if sparse {
+        let archive = encode_sparse(txn, tensor).await?;
+        Ok(Box::pin(stream::once(future::ready(Ok(archive)))))
+    } else {
+        encode_dense(txn, tensor).await
+    }
+}
+
+/// Decode a new [`Tensor`] from the given `.npy` array, or from the given `.npz` archive if
+/// `sparse` is `true`.
+pub async fn decode(txn: &Txn, data: Bytes, sparse: bool) -> TCResult<State> {
+    let tensor = if sparse {
+        decode_sparse(txn, &data).await?
+    } else {
+        decode_dense(txn, &data).await?
+    };
+
+    Ok(State::Collection(Collection::Tensor(tensor)))
+}
+
+async fn encode_dense(txn: Txn, tensor: Tensor) -> TCResult<TCBoxTryStream<'static, Bytes>> {
+    let dtype = concrete(tensor.dtype());
+    let header = header(dtype, &tensor.shape().to_vec());
+
+    let blocks = match tensor {
+        Tensor::Dense(dense) => dense.into_inner().block_stream(txn).await?,
+        Tensor::Sparse(sparse) => sparse.into_dense().into_inner().block_stream(txn).await?,
+    };
+
+    let data = blocks.map_ok(move |block| {
+        let values = block.to_vec();
+        let mut buf = Vec::with_capacity(values.len() * element_size(dtype));
+        for n in values {
+            write_number(&mut buf, dtype, n);
+        }
+
+        Bytes::from(buf)
+    });
+
+    let header = stream::once(future::ready(Ok(Bytes::from(header))));
+    Ok(Box::pin(header.chain(data)))
+}
+
+async fn encode_sparse(txn: Txn, tensor: Tensor) -> TCResult<Bytes> {
+    let dtype = concrete(tensor.dtype());
+    let shape = tensor.shape().to_vec();
+
+    let filled = match tensor {
+        Tensor::Dense(dense) => dense.into_sparse().into_inner().filled(txn).await?,
+        Tensor::Sparse(sparse) => sparse.into_inner().filled(txn).await?,
+    };
+
+    let filled: Vec<(Vec<u64>, Number)> = filled.try_collect().await?;
+    let nnz = filled.len() as u64;
+
+    let mut coords = header(INDEX, &[shape.len() as u64, nnz]);
+    for axis in 0..shape.len() {
+        for (coord, _) in &filled {
+            write_number(&mut coords, INDEX, Number::from(coord[axis]));
+        }
+    }
+
+    let mut data = header(dtype, &[nnz]);
+    for (_, n) in filled {
+        write_number(&mut data, dtype, n);
+    }
+
+    let mut fill_value = header(dtype, &[]);
+    write_number(&mut fill_value, dtype, dtype.zero());
+
+    let mut dims = header(INDEX, &[shape.len() as u64]);
+    for dim in &shape {
+        write_number(&mut dims, INDEX, Number::from(*dim));
+    }
+
+    zip(&[
+        ("coords.npy", coords),
+        ("data.npy", data),
+        ("fill_value.npy", fill_value),
+        ("shape.npy", dims),
+    ])
+}
+
+async fn decode_dense(txn: &Txn, data: &[u8]) -> TCResult<Tensor> {
+    let array = NdArray::parse(data)?;
+    let dtype = array.dtype;
+    let values = array.values().map(Ok);
+
+    let txn_id = *txn.id();
+    let file = txn
+        .context()
+        .create_file_unique(txn_id, TensorType::Dense)
+        .await?;
+
+    let shape = array.shape.into();
+    let values = stream::iter(values);
+    let blocks = DenseTensorFile::from_values(file, txn_id, shape, dtype, values).await?;
+    Ok(Tensor::from(DenseTensor::from(blocks.accessor())))
+}
+
+async fn decode_sparse(txn: &Txn, data: &[u8]) -> TCResult<Tensor> {
+    let entries = unzip(data)?;
+    let entry = |name: &str| {
+        entries
+            .get(name)
+            .ok_or_else(|| TCError::bad_request("sparse .npz archive is missing the array", name))
+            .and_then(|data| NdArray::parse(*data))
+    };
+
+    let coords = entry("coords.npy")?;
+    let values = entry("data.npy")?;
+    let shape = entry("shape.npy")?.indices()?;
+
+    if entries.contains_key("fill_value.npy") {
+        let fill_value = entry("fill_value.npy")?;
+        if fill_value.values().any(|n| n != fill_value.dtype.zero()) {
+            return Err(TCError::unsupported(
+                "a sparse Tensor must have a fill value of zero",
+            ));
+        }
+    }
+
+    let nnz = values.shape.iter().product::<u64>();
+    if coords.shape != [shape.len() as u64, nnz] {
+        return Err(TCError::bad_request(
+            "sparse .npz coordinates should have shape [ndim, nnz], not",
+            format!("{:?}", coords.shape),
+        ));
+    }
+
+    let coords = coords.indices()?;
+    let nnz = nnz as usize;
+    for (i, coord) in coords.iter().enumerate() {
+        if *coord >= shape[i / nnz] {
+            return Err(TCError::bad_request(
+                "sparse .npz coordinate is out of bounds",
+                coord,
+            ));
+        }
+    }
+
+    let txn_id = *txn.id();
+    let dir = txn.context().create_dir_unique(txn_id).await?;
+    let schema = Schema {
+        shape: shape.into(),
+        dtype: values.dtype,
+    };
+
+    let tensor = SparseTensor::create(&dir, schema, txn_id).await?;
+
+    for (i, value) in values.values().enumerate() {
+        let coord = coords.iter().skip(i).step_by(nnz).copied().collect();
+        tensor.write_value_at(txn_id, coord, value).await?;
+    }
+
+    Ok(Tensor::from(tensor))
+}
+
+/// A NumPy array read from a `.npy` file.
+struct NdArray<'a> {
+    dtype: NumberType,
+    shape: Vec<u64>,
+    data: &'a [u8],
+}
+
+impl<'a> NdArray<'a> {
+    fn parse(bytes: &'a [u8]) -> TCResult<Self> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(TCError::bad_request(
+                "invalid NumPy array",
+                "missing the .npy magic string",
+            ));
+        }
+
+        let (header_len, offset) = match read(bytes, MAGIC.len(), 1)?[0] {
+            1 => (u16::from_le_bytes(array(read(bytes, 8, 2)?)) as usize, 10),
+            2 | 3 => (u32::from_le_bytes(array(read(bytes, 8, 4)?)) as usize, 12),
+            version => {
+                return Err(TCError::unsupported(format!(
+                    "NumPy file format version {}",
+                    version
+                )))
+            }
+        };
+
+        let header = std::str::from_utf8(read(bytes, offset, header_len)?)
+            .map_err(|e| TCError::bad_request("invalid NumPy array header", e))?;
+
+        let descr = header_entry(header, "descr")?;
+        let dtype = match descr.chars().next() {
+            Some(quote) if quote == '\'' || quote == '"' => descr[1..]
+                .split(quote)
+                .next()
+                .map(parse_descr)
+                .unwrap_or_else(|| Err(TCError::bad_request("invalid NumPy dtype", descr)))?,
+            _ => {
+                return Err(TCError::unsupported(format!(
+                    "NumPy array of structured type {}",
+                    descr
+                )))
+            }
+        };
+
+        if header_entry(header, "fortran_order")?.starts_with("True") {
+            return Err(TCError::unsupported(
+                "NumPy array in Fortran order (call numpy.ascontiguousarray first)",
+            ));
+        }
+
+        let shape = header_entry(header, "shape")?;
+        let shape = shape
+            .strip_prefix('(')
+            .and_then(|shape| shape.split(')').next())
+            .ok_or_else(|| TCError::bad_request("invalid NumPy array shape", shape))?;
+
+        let shape = shape
+            .split(',')
+            .map(|dim| dim.trim())
+            .filter(|dim| !dim.is_empty())
+            .map(|dim| {
+                dim.parse()
+                    .map_err(|e| TCError::bad_request("invalid NumPy array dimension", e))
+            })
+            .collect::<TCResult<Vec<u64>>>()?;
+
+        let size = shape.iter().product::<u64>() as usize * element_size(dtype);
+        let data = read(bytes, offset + header_len, size)?;
+
+        Ok(Self { dtype, shape, data })
+    }
+
+    fn values(&self) -> impl Iterator<Item = Number> + 'a {
+        let dtype = self.dtype;
+        self.data
+            .chunks_exact(element_size(dtype))
+            .map(move |bytes| read_number(dtype, bytes))
+    }
+
+    fn indices(&self) -> TCResult<Vec<u64>> {
+        self.values()
+            .map(|n| match n {
+                Number::UInt(u) => Ok(u64::from(u)),
+                Number::Int(i) if i64::from(i) >= 0 => Ok(i64::from(i) as u64),
+                other => Err(TCError::bad_request(
+                    "expected a non-negative integer index, not",
+                    other,
+                )),
+            })
+            .collect()
+    }
+}
+
+/// Find the value of the given `key` in a `.npy` header, which is a Python `dict` literal.
+fn header_entry<'a>(header: &'a str, key: &str) -> TCResult<&'a str> {
+    [format!("'{}':", key), format!("\"{}\":", key)]
+        .iter()
+        .find_map(|key| header.find(key.as_str()).map(|i| &header[i + key.len()..]))
+        .map(|value| value.trim_start())
+        .ok_or_else(|| TCError::bad_request("NumPy array header is missing", key))
+}
+
+/// Encode a `.npy` header for an array with the given `dtype` and `shape`.
+fn header(dtype: NumberType, shape: &[u64]) -> Vec<u8> {
+    let shape = match shape {
+        [dim] => format!("({},)", dim),
+        dims => {
+            let dims: Vec<String> = dims.iter().map(|dim| dim.to_string()).collect();
+            format!("({})", dims.join(", "))
+        }
+    };
+
+    let mut dict = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr(dtype),
+        shape
+    );
+
+    // the data must begin at a multiple of ALIGN bytes
+    let len = MAGIC.len() + 4 + dict.len() + 1;
+    dict.extend(iter::repeat(' ').take((ALIGN - len % ALIGN) % ALIGN));
+    dict.push('\n');
+
+    let mut header = Vec::with_capacity(MAGIC.len() + 4 + dict.len());
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&[1, 0]);
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+/// Return the concrete type in which to encode an element of the given `dtype`.
+fn concrete(dtype: NumberType) -> NumberType {
+    match dtype {
+        NumberType::Complex(ComplexType::Complex) => NumberType::Complex(ComplexType::C64),
+        NumberType::Float(FloatType::Float) | NumberType::Number => {
+            NumberType::Float(FloatType::F64)
+        }
+        NumberType::Int(IntType::Int) => NumberType::Int(IntType::I64),
+        NumberType::UInt(UIntType::UInt) => NumberType::UInt(UIntType::U64),
+        dtype => dtype,
+    }
+}
+
+fn descr(dtype: NumberType) -> &'static str {
+    match concrete(dtype) {
+        NumberType::Bool => "|b1",
+        NumberType::Complex(ComplexType::C32) => "<c8",
+        NumberType::Complex(_) => "<c16",
+        NumberType::Float(FloatType::F32) => "<f4",
+        NumberType::Float(_) => "<f8",
+        NumberType::Int(IntType::I8) => "|i1",
+        NumberType::Int(IntType::I16) => "<i2",
+        NumberType::Int(IntType::I32) => "<i4",
+        NumberType::Int(_) => "<i8",
+        NumberType::UInt(UIntType::U8) => "|u1",
+        NumberType::UInt(UIntType::U16) => "<u2",
+        NumberType::UInt(UIntType::U32) => "<u4",
+        NumberType::UInt(_) => "<u8",
+        NumberType::Number => "<f8",
+    }
+}
+
+fn parse_descr(descr: &str) -> TCResult<NumberType> {
+    let code = match descr.chars().next() {
+        Some('<') | Some('|') | Some('=') => &descr[1..],
+        Some('>') => {
+            return Err(TCError::unsupported(format!(
+                "big-endian NumPy array of type {}",
+                descr
+            )))
+        }
+        _ => descr,
+    };
+
+    let dtype = match code {
+        "b1" => NumberType::Bool,
+        "c8" => NumberType::Complex(ComplexType::C32),
+        "c16" => NumberType::Complex(ComplexType::C64),
+        "f4" => NumberType::Float(FloatType::F32),
+        "f8" => NumberType::Float(FloatType::F64),
+        "i1" => NumberType::Int(IntType::I8),
+        "i2" => NumberType::Int(IntType::I16),
+        "i4" => NumberType::Int(IntType::I32),
+        "i8" => NumberType::Int(IntType::I64),
+        "u1" => NumberType::UInt(UIntType::U8),
+        "u2" => NumberType::UInt(UIntType::U16),
+        "u4" => NumberType::UInt(UIntType::U32),
+        "u8" => NumberType::UInt(UIntType::U64),
+        _ => {
+            return Err(TCError::unsupported(format!(
+                "NumPy array of type {}",
+                descr
+            )))
+        }
+    };
+
+    Ok(dtype)
+}
+
+fn element_size(dtype: NumberType) -> usize {
+    match concrete(dtype) {
+        NumberType::Bool => 1,
+        NumberType::Complex(ComplexType::C32) => 8,
+        NumberType::Complex(_) => 16,
+        NumberType::Float(FloatType::F32) => 4,
+        NumberType::Float(_) => 8,
+        NumberType::Int(IntType::I8) => 1,
+        NumberType::Int(IntType::I16) => 2,
+        NumberType::Int(IntType::I32) => 4,
+        NumberType::Int(_) => 8,
+        NumberType::UInt(UIntType::U8) => 1,
+        NumberType::UInt(UIntType::U16) => 2,
+        NumberType::UInt(UIntType::U32) => 4,
+        NumberType::UInt(_) => 8,
+        NumberType::Number => 8,
+    }
+}
+
+/// Write the given number to `buf` as a little-endian element of the given concrete `dtype`.
+fn write_number(buf: &mut Vec<u8>, dtype: NumberType, n: Number) {
+    match n.into_type(dtype) {
+        Number::Bool(b) => buf.push(bool::from(b) as u8),
+        Number::Complex(Complex::C32(c)) => {
+            buf.extend_from_slice(&c.re.to_le_bytes());
+            buf.extend_from_slice(&c.im.to_le_bytes());
+        }
+        Number::Complex(Complex::C64(c)) => {
+            buf.extend_from_slice(&c.re.to_le_bytes());
+            buf.extend_from_slice(&c.im.to_le_bytes());
+        }
+        Number::Float(Float::F32(f)) => buf.extend_from_slice(&f.to_le_bytes()),
+        Number::Float(Float::F64(f)) => buf.extend_from_slice(&f.to_le_bytes()),
+        Number::Int(Int::I8(i)) => buf.extend_from_slice(&i.to_le_bytes()),
+        Number::Int(Int::I16(i)) => buf.extend_from_slice(&i.to_le_bytes()),
+        Number::Int(Int::I32(i)) => buf.extend_from_slice(&i.to_le_bytes()),
+        Number::Int(Int::I64(i)) => buf.extend_from_slice(&i.to_le_bytes()),
+        Number::UInt(UInt::U8(u)) => buf.push(u),
+        Number::UInt(UInt::U16(u)) => buf.extend_from_slice(&u.to_le_bytes()),
+        Number::UInt(UInt::U32(u)) => buf.extend_from_slice(&u.to_le_bytes()),
+        Number::UInt(UInt::U64(u)) => buf.extend_from_slice(&u.to_le_bytes()),
+    }
+}
+
+/// Read a little-endian element of the given concrete `dtype` from `bytes`, which must hold
+/// exactly one element.
+fn read_number(dtype: NumberType, bytes: &[u8]) -> Number {
+    match dtype {
+        NumberType::Bool => Number::from(bytes[0] != 0),
+        NumberType::Complex(ComplexType::C32) => {
+            let re = f32::from_le_bytes(array(&bytes[..4]));
+            let im = f32::from_le_bytes(array(&bytes[4..]));
+            Number::from(Complex::cast_from([re, im]))
+        }
+        NumberType::Complex(_) => {
+            let re = f64::from_le_bytes(array(&bytes[..8]));
+            let im = f64::from_le_bytes(array(&bytes[8..]));
+            Number::from(Complex::cast_from([re, im]))
+        }
+        NumberType::Float(FloatType::F32) => Number::from(f32::from_le_bytes(array(bytes))),
+        NumberType::Float(_) => Number::from(f64::from_le_bytes(array(bytes))),
+        NumberType::Int(IntType::I8) => Number::from(bytes[0] as i8),
+        NumberType::Int(IntType::I16) => Number::from(i16::from_le_bytes(array(bytes))),
+        NumberType::Int(IntType::I32) => Number::from(i32::from_le_bytes(array(bytes))),
+        NumberType::Int(_) => Number::from(i64::from_le_bytes(array(bytes))),
+        NumberType::UInt(UIntType::U8) => Number::from(bytes[0]),
+        NumberType::UInt(UIntType::U16) => Number::from(u16::from_le_bytes(array(bytes))),
+        NumberType::UInt(UIntType::U32) => Number::from(u32::from_le_bytes(array(bytes))),
+        NumberType::UInt(_) => Number::from(u64::from_le_bytes(array(bytes))),
+        NumberType::Number => Number::from(f64::from_le_bytes(array(bytes))),
+    }
+}
+
+/// Write an uncompressed zip archive with the given entries.
+fn zip(entries: &[(&str, Vec<u8>)]) -> TCResult<Bytes> {
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+
+    for (name, data) in entries {
+        let offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let crc = crc32(data);
+
+        for (buf, signature) in [
+            (&mut archive, LOCAL_HEADER),
+            (&mut directory, CENTRAL_HEADER),
+        ] {
+            buf.extend_from_slice(&signature.to_le_bytes());
+            if signature == CENTRAL_HEADER {
+                buf.extend_from_slice(&ZIP_VERSION.to_le_bytes()); // version made by
+            }
+
+            buf.extend_from_slice(&ZIP_VERSION.to_le_bytes()); // version needed to extract
+            buf.extend_from_slice(&0u16.to_le_bytes()); // flags
+            buf.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+            buf.extend_from_slice(&0u16.to_le_bytes()); // time
+            buf.extend_from_slice(&ZIP_DATE.to_le_bytes());
+            buf.extend_from_slice(&crc.to_le_bytes());
+            buf.extend_from_slice(&size.to_le_bytes()); // compressed size
+            buf.extend_from_slice(&size.to_le_bytes()); // uncompressed size
+            buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+            if signature == CENTRAL_HEADER {
+                buf.extend_from_slice(&0u16.to_le_bytes()); // comment length
+                buf.extend_from_slice(&0u16.to_le_bytes()); // disk number
+                buf.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+                buf.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+                buf.extend_from_slice(&offset.to_le_bytes());
+            }
+
+            buf.extend_from_slice(name.as_bytes());
+        }
+
+        archive.extend_from_slice(data);
+    }
+
+    let offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+    let count = entries.len() as u16;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&END_OF_DIRECTORY.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes()); // this disk
+    archive.extend_from_slice(&0u16.to_le_bytes()); // the disk where the directory starts
+    archive.extend_from_slice(&count.to_le_bytes()); // entries on this disk
+    archive.extend_from_slice(&count.to_le_bytes()); // total entries
+    archive.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes()); // comment length
+
+    Ok(Bytes::from(archive))
+}
+
+/// Read the entries of an uncompressed zip archive, including those with `zip64` extensions
+/// (which `numpy.savez` always writes).
+fn unzip(archive: &[u8]) -> TCResult<HashMap<String, &[u8]>> {
+    let end = (0..archive.len().saturating_sub(21))
+        .rev()
+        .find(|i| archive[*i..].starts_with(&END_OF_DIRECTORY.to_le_bytes()))
+        .ok_or_else(|| TCError::bad_request("invalid .npz archive", "missing zip directory"))?;
+
+    let count = u16::from_le_bytes(array(read(archive, end + 10, 2)?));
+    let mut at = u32::from_le_bytes(array(read(archive, end + 16, 4)?)) as usize;
+
+    let mut entries = HashMap::with_capacity(count as usize);
+    for _ in 0..count {
+        let header = read(archive, at, 46)?;
+        if !header.starts_with(&CENTRAL_HEADER.to_le_bytes()) {
+            return Err(TCError::bad_request(
+                "invalid .npz archive",
+                "missing zip directory entry",
+            ));
+        }
+
+        let method = u16::from_le_bytes(array(&header[10..]));
+        let mut size = u32::from_le_bytes(array(&header[20..])) as u64;
+        let name_len = u16::from_le_bytes(array(&header[28..])) as usize;
+        let extra_len = u16::from_le_bytes(array(&header[30..])) as usize;
+        let comment_len = u16::from_le_bytes(array(&header[32..])) as usize;
+        let mut offset = u32::from_le_bytes(array(&header[42..])) as u64;
+
+        let name = read(archive, at + 46, name_len)?;
+        let name = String::from_utf8(name.to_vec())
+            .map_err(|e| TCError::bad_request("invalid .npz entry name", e))?;
+
+        if method != 0 {
+            return Err(TCError::unsupported(format!(
+                "compressed .npz entry {} (use numpy.savez, not numpy.savez_compressed)",
+                name
+            )));
+        }
+
+        // a zip64 extra field holds each size or offset too large for its 32-bit field
+        let mut extra = read(archive, at + 46 + name_len, extra_len)?;
+        while extra.len() >= 4 {
+            let id = u16::from_le_bytes(array(extra));
+            let len = u16::from_le_bytes(array(&extra[2..])) as usize;
+            let field = read(extra, 4, len)?;
+
+            if id == ZIP64_EXTRA {
+                let mut values = field
+                    .chunks_exact(8)
+                    .map(|value| u64::from_le_bytes(array(value)));
+                let mut next = || {
+                    values.next().ok_or_else(|| {
+                        TCError::bad_request("invalid .npz archive", "truncated zip64 field")
+                    })
+                };
+
+                if u32::from_le_bytes(array(&header[24..])) == u32::MAX {
+                    next()?; // the uncompressed size, which is the same as the compressed size
+                }
+
+                if size == u32::MAX as u64 {
+                    size = next()?;
+                }
+
+                if offset == u32::MAX as u64 {
+                    offset = next()?;
+                }
+            }
+
+            extra = &extra[4 + len..];
+        }
+
+        let offset = offset as usize;
+        let local = read(archive, offset, 30)?;
+        let local_name_len = u16::from_le_bytes(array(&local[26..])) as usize;
+        let local_extra_len = u16::from_le_bytes(array(&local[28..])) as usize;
+        let start = offset + 30 + local_name_len + local_extra_len;
+
+        entries.insert(name, read(archive, start, size as usize)?);
+        at += 46 + name_len + extra_len + comment_len;
+    }
+
+    Ok(entries)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+
+    !crc
+}
+
+fn read(bytes: &[u8], start: usize, len: usize) -> TCResult<&[u8]> {
+    start
+        .checked_add(len)
+        .and_then(|end| bytes.get(start..end))
+        .ok_or_else(|| TCError::bad_request("unexpected end of NumPy data at byte", start))
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes[..N].try_into().expect("fixed-size byte array")
+}
+
+fn too_large() -> TCError {
+    TCError::bad_request(
+        "sparse Tensor is too large to encode as an .npz archive, max bytes",
+        u32::MAX,
+    )
+}
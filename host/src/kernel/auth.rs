@@ -0,0 +1,72 @@
+//! Authorization of the administrative routes of a host, like `PUT /host/cluster`.
+//!
+//! A host administrator is a request whose token includes a claim of the [`SCOPE_ADMIN`] scope by
+//! one of the issuers trusted with the `--host_admin` option, e.g. `http://auth.example.com/admin`
+//! or `/admin` (i.e. a cluster hosted on this host, which can grant the scope with its `grant`
+//! route). A host with no trusted issuer has no administrator, so its administrative routes always
+//! respond with `Unauthorized`.
+
+use log::debug;
+
+use tc_error::*;
+use tc_value::{Link, LinkHost};
+use tcgeneric::{path_label, PathLabel, TCPathBuf};
+
+use crate::txn::Txn;
+
+use super::Kernel;
+
+/// The auth scope required to administer a host.
+pub const SCOPE_ADMIN: PathLabel = path_label(&["host", "admin"]);
+
+impl Kernel {
+    /// Return `Unauthorized` unless the given [`Txn`] was granted [`SCOPE_ADMIN`] by a trusted
+    /// issuer, in order to perform the given `action`.
+    pub(crate) fn authorize_admin(&self, txn: &Txn, action: &str) -> TCResult<()> {
+        if self.is_admin(txn) {
+            Ok(())
+        } else {
+            Err(TCError::unauthorized(format!(
+                "{} requires the scope {}",
+                action,
+                TCPathBuf::from(SCOPE_ADMIN)
+            )))
+        }
+    }
+
+    /// Return `true` if the given [`Txn`] was granted [`SCOPE_ADMIN`] by a trusted issuer.
+    pub(super) fn is_admin(&self, txn: &Txn) -> bool {
+        let scope = TCPathBuf::from(SCOPE_ADMIN);
+        let root = txn.gateway().root();
+
+        for (host, actor_id, scopes) in txn.request().scopes().iter() {
+            if !scopes.contains(&scope) {
+                continue;
+            }
+
+            if self.admins.iter().any(|admin| is_issuer(admin, host, root)) {
+                debug!("{} is an administrator of this host", actor_id);
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+// return `true` if the `admin` issuer is the given `host`, resolving a relative `admin` link
+// against the `root` of this host
+fn is_issuer(admin: &Link, host: &Link, root: &LinkHost) -> bool {
+    if admin.path() != host.path() {
+        return false;
+    }
+
+    match admin.host() {
+        Some(admin) => host.host().as_ref() == Some(admin),
+        None => host
+            .host()
+            .as_ref()
+            .map(|host| host == root)
+            .unwrap_or(true),
+    }
+}
@@ -0,0 +1,48 @@
+//! A report of the ArrayFire backend which computes `Tensor` math on this host, served by the
+//! [`Kernel`] at [`BACKEND`].
+
+use tc_error::*;
+use tcgeneric::{path_label, PathLabel};
+
+use crate::state::State;
+
+use super::Kernel;
+
+/// The path at which to report the active ArrayFire backend.
+pub const BACKEND: PathLabel = path_label(&["host", "backend"]);
+
+impl Kernel {
+    /// Report the active ArrayFire backend and device, and the backends available on this host.
+    #[cfg(feature = "tc-tensor")]
+    pub(super) fn backend(&self) -> TCResult<State> {
+        use tc_value::Value;
+        use tcgeneric::{label, Id, Map};
+
+        let (backend, device) = tc_tensor::active_backend();
+
+        let available = tc_tensor::Backend::available()
+            .into_iter()
+            .map(|backend| State::from(Value::String(backend.to_string().into())))
+            .collect();
+
+        let mut report = Map::new();
+        report.insert(
+            Id::from(label("backend")),
+            State::from(Value::String(backend.to_string().into())),
+        );
+        report.insert(
+            Id::from(label("device")),
+            State::from(Value::from(device as u64)),
+        );
+        report.insert(Id::from(label("available")), State::Tuple(available));
+
+        Ok(State::Map(report))
+    }
+
+    #[cfg(not(feature = "tc-tensor"))]
+    pub(super) fn backend(&self) -> TCResult<State> {
+        Err(TCError::unsupported(
+            "this host was built without support for Tensors",
+        ))
+    }
+}
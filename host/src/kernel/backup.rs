@@ -0,0 +1,124 @@
+//! Online backups of the data directory of this host, served by the [`Kernel`] at [`BACKUP`].
+//!
+//! `PUT /backup/<name>` backs up the data directory as of the requesting transaction, `GET /backup`
+//! lists the available backups, and `GET /backup/<name>` describes a backup. Taking a backup
+//! requires the host admin scope. A backup is restored by starting a host with the `--restore`
+//! option and an empty data directory.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use tc_error::*;
+use tc_transact::Transaction;
+use tc_value::Value;
+use tcgeneric::{label, path_label, Id, Map, PathLabel, PathSegment, TCPath};
+
+use crate::fs;
+use crate::scalar::OpRefType;
+use crate::state::State;
+use crate::txn::Txn;
+
+/// The path of the backup API.
+pub const BACKUP: PathLabel = path_label(&["backup"]);
+
+/// The backups of the data directory of this host.
+pub struct Backups {
+    data_dir: PathBuf,
+    dir: PathBuf,
+}
+
+impl Backups {
+    /// Serve backups of the given `data_dir`, stored in the given `dir`.
+    pub fn new(data_dir: PathBuf, dir: PathBuf) -> Self {
+        Self { data_dir, dir }
+    }
+
+    /// Describe a backup, or list the available backups if the `path` is empty.
+    pub async fn get(&self, path: &[PathSegment], key: Value) -> TCResult<State> {
+        key.expect_none()?;
+
+        match path {
+            [] => {
+                let mut names = Vec::new();
+                let mut entries = tokio::fs::read_dir(&self.dir).await.map_err(io_err)?;
+                while let Some(entry) = entries.next_entry().await.map_err(io_err)? {
+                    let path = entry.path();
+                    if path.extension().is_none() && path.join(fs::MANIFEST).exists() {
+                        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                            names.push(Value::String(name.to_string().into()));
+                        }
+                    }
+                }
+
+                names.sort_by(|l, r| l.to_string().cmp(&r.to_string()));
+                Ok(State::from(Value::Tuple(names.into())))
+            }
+            [name] => {
+                let manifest = fs::Manifest::read(&self.dir.join(name.as_str())).await?;
+                Ok(describe(name, manifest))
+            }
+            _ => Err(TCError::not_found(TCPath::from(path))),
+        }
+    }
+
+    /// Back up the data directory as of the given [`Txn`].
+    pub async fn put(
+        &self,
+        txn: &Txn,
+        path: &[PathSegment],
+        key: Value,
+        value: State,
+    ) -> TCResult<()> {
+        let name = match path {
+            [name] => name,
+            _ => {
+                return Err(TCError::method_not_allowed(
+                    OpRefType::Put,
+                    self,
+                    TCPath::from(path),
+                ))
+            }
+        };
+
+        key.expect_none()?;
+        if !value.is_none() {
+            return Err(TCError::bad_request(
+                "a backup does not accept a value, but found",
+                value,
+            ));
+        }
+
+        let target = self.dir.join(name.as_str());
+        fs::backup(&self.data_dir, &target, *txn.id()).await?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Backups {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "backups of {:?}", self.data_dir)
+    }
+}
+
+fn describe(name: &Id, manifest: fs::Manifest) -> State {
+    let description: Map<State> = vec![
+        (label("name").into(), Value::from(name.clone()).into()),
+        (
+            label("txn_id").into(),
+            Value::String(manifest.txn_id.to_string().into()).into(),
+        ),
+        (
+            label("files").into(),
+            Value::from(manifest.files.len() as u64).into(),
+        ),
+        (label("size").into(), Value::from(manifest.size()).into()),
+    ]
+    .into_iter()
+    .collect();
+
+    State::Map(description)
+}
+
+fn io_err(cause: std::io::Error) -> TCError {
+    TCError::internal(format!("backup I/O error: {}", cause))
+}
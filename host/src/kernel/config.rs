@@ -0,0 +1,208 @@
+//! The configuration file of this host, served by the [`Kernel`] at [`CONFIG`].
+//!
+//! A configuration file is a TOML table whose keys are the names of command-line options, e.g.
+//! `data_dir = "/var/lib/tc"`, `http_port = 8702`, `balance_reads = true`, or
+//! `cluster = ["a.json", "b.json"]`. An option given on the command line overrides the file.
+//!
+//! The file is reloaded when the host receives a SIGHUP signal or a `PUT /config` request from a
+//! host administrator, and `GET /config` returns the options it sets. Only some options can be adjusted while the host is
+//! running (e.g. the log level and the cache limit); any other option takes effect at restart.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::{info, warn};
+
+use tc_error::*;
+use tc_value::{Number, Value};
+use tcgeneric::{path_label, Id, Map, PathLabel, PathSegment, TCPath};
+
+use crate::scalar::OpRefType;
+use crate::state::State;
+
+/// The path of the configuration API.
+pub const CONFIG: PathLabel = path_label(&["config"]);
+
+// applies the adjustable options of a reloaded configuration, given its command-line arguments
+type Apply = Box<dyn Fn(Vec<String>) -> TCResult<()> + Send + Sync>;
+
+/// The configuration file of this host.
+pub struct ConfigFile {
+    path: PathBuf,
+    cli: Vec<String>,
+    apply: Apply,
+}
+
+impl ConfigFile {
+    /// Reload the configuration file at `path`, overridden by the command-line arguments `cli`,
+    /// by calling `apply` with the merged arguments.
+    pub fn new<F>(path: PathBuf, cli: Vec<String>, apply: F) -> Self
+    where
+        F: Fn(Vec<String>) -> TCResult<()> + Send + Sync + 'static,
+    {
+        Self {
+            path,
+            cli,
+            apply: Box::new(apply),
+        }
+    }
+
+    /// Merge the options in the configuration file at `path` into the command-line arguments
+    /// `cli`. An option given in `cli` takes precedence over the same option in the file.
+    pub fn args(path: &Path, cli: &[String]) -> TCResult<Vec<String>> {
+        let mut args = cli.to_vec();
+
+        for (name, value) in read(path)? {
+            let flag = format!("--{}", name);
+            let prefix = format!("{}=", flag);
+            if cli
+                .iter()
+                .any(|arg| arg == &flag || arg.starts_with(&prefix))
+            {
+                continue;
+            }
+
+            match value {
+                toml::Value::Boolean(true) => args.push(flag),
+                toml::Value::Boolean(false) => {}
+                toml::Value::Array(values) => {
+                    for value in values {
+                        args.push(flag.clone());
+                        args.push(toml_to_arg(&name, value)?);
+                    }
+                }
+                value => {
+                    args.push(flag);
+                    args.push(toml_to_arg(&name, value)?);
+                }
+            }
+        }
+
+        Ok(args)
+    }
+
+    /// Read the file again and apply its adjustable options.
+    pub fn reload(&self) -> TCResult<()> {
+        let args = Self::args(&self.path, &self.cli)?;
+        (self.apply)(args)?;
+        info!("reloaded host configuration from {:?}", self.path);
+        Ok(())
+    }
+
+    /// Reload this configuration file each time this host receives a SIGHUP signal.
+    #[cfg(unix)]
+    pub fn reload_on_hangup(self: Arc<Self>) -> TCResult<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup())
+            .map_err(|e| TCError::internal(format!("unable to handle SIGHUP: {}", e)))?;
+
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                if let Err(cause) = self.reload() {
+                    warn!("unable to reload {}: {}", self, cause);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn reload_on_hangup(self: Arc<Self>) -> TCResult<()> {
+        Ok(())
+    }
+
+    /// Return the options set by the configuration file.
+    pub async fn get(&self, path: &[PathSegment], key: Value) -> TCResult<State> {
+        if !path.is_empty() {
+            return Err(TCError::not_found(TCPath::from(path)));
+        }
+
+        key.expect_none()?;
+
+        let options = read(&self.path)?
+            .into_iter()
+            .map(|(name, value)| {
+                let name: Id = name.parse()?;
+                toml_to_value(value).map(|value| (name, State::from(value)))
+            })
+            .collect::<TCResult<Map<State>>>()?;
+
+        Ok(State::Map(options))
+    }
+
+    /// Reload the configuration file.
+    pub async fn put(&self, path: &[PathSegment], key: Value, value: State) -> TCResult<()> {
+        if !path.is_empty() {
+            return Err(TCError::method_not_allowed(
+                OpRefType::Put,
+                self,
+                TCPath::from(path),
+            ));
+        }
+
+        key.expect_none()?;
+        if !value.is_none() {
+            return Err(TCError::bad_request(
+                "reloading the host configuration does not accept a value, but found",
+                value,
+            ));
+        }
+
+        self.reload()
+    }
+}
+
+impl fmt::Display for ConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "host configuration file {:?}", self.path)
+    }
+}
+
+fn read(path: &Path) -> TCResult<toml::value::Table> {
+    let config = std::fs::read_to_string(path)
+        .map_err(|e| TCError::bad_request(format!("unable to read config file {:?}", path), e))?;
+
+    config
+        .parse::<toml::Value>()
+        .map_err(|e| TCError::bad_request(format!("invalid config file {:?}", path), e))
+        .and_then(|config| match config {
+            toml::Value::Table(options) => Ok(options),
+            other => Err(TCError::bad_request(
+                "expected a table of options, not",
+                other,
+            )),
+        })
+}
+
+fn toml_to_arg(name: &str, value: toml::Value) -> TCResult<String> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        toml::Value::Datetime(dt) => Ok(dt.to_string()),
+        other => Err(TCError::bad_request(
+            format!("invalid value for option {}", name),
+            other,
+        )),
+    }
+}
+
+fn toml_to_value(value: toml::Value) -> TCResult<Value> {
+    match value {
+        toml::Value::String(s) => Ok(Value::String(s.into())),
+        toml::Value::Integer(i) => Ok(Value::Number(Number::from(i))),
+        toml::Value::Float(f) => Ok(Value::Number(Number::from(f))),
+        toml::Value::Boolean(b) => Ok(Value::from(b)),
+        toml::Value::Datetime(dt) => Ok(Value::String(dt.to_string().into())),
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(toml_to_value)
+            .collect::<TCResult<Vec<Value>>>()
+            .map(|values| Value::Tuple(values.into())),
+        other => Err(TCError::bad_request("invalid option value", other)),
+    }
+}
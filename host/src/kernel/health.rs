@@ -0,0 +1,98 @@
+//! Health and readiness probes of this host, served by the [`Kernel`] at [`HEALTH`] and [`READY`].
+//!
+//! Both probes report the uptime of this host, its active transactions, the occupancy of its
+//! filesystem cache, and the replication status of each hosted cluster. The readiness probe
+//! returns a `Conflict` error instead while any hosted cluster is still catching up with its
+//! other replicas, so that an orchestrator will not route requests to this host until it's ready.
+
+use std::time::Duration;
+
+use tc_error::*;
+use tc_value::Value;
+use tcgeneric::{label, path_label, Id, Label, Map, PathLabel, TCPath};
+
+use crate::state::State;
+use crate::txn::Txn;
+
+use super::Kernel;
+
+/// The path of the health probe.
+pub const HEALTH: PathLabel = path_label(&["health"]);
+
+/// The path of the readiness probe.
+pub const READY: PathLabel = path_label(&["ready"]);
+
+impl Kernel {
+    /// Report the health of this host.
+    pub(super) async fn health(&self, txn: &Txn) -> TCResult<State> {
+        let gateway = txn.gateway();
+        let metrics = gateway.txn_metrics().await;
+        let occupancy = gateway.cache_occupancy().await;
+
+        let mut ready = true;
+        let mut clusters = Vec::new();
+        for cluster in self.hosted() {
+            let online = cluster.is_online();
+            ready = ready && online;
+
+            let lag = cluster.replication_lag().await;
+            clusters.push(State::Map(map(vec![
+                (label("link"), Value::from(cluster.link().clone())),
+                (label("online"), Value::from(online)),
+                (label("replication_lag_ms"), millis(lag)),
+            ])));
+        }
+
+        let mut health = map(vec![
+            (label("ready"), Value::from(ready)),
+            (label("uptime_ms"), millis(self.started.elapsed())),
+            (label("active_txns"), Value::from(metrics.active as u64)),
+            (
+                label("pending_versions"),
+                Value::from(metrics.pending_versions as u64),
+            ),
+            (
+                label("cache_size"),
+                Value::from(gateway.cache_size() as u64),
+            ),
+            (label("cache_occupancy"), Value::from(occupancy as u64)),
+        ]);
+
+        health.insert(label("clusters").into(), State::Tuple(clusters.into()));
+
+        Ok(State::Map(health))
+    }
+
+    /// Report the health of this host, or a `Conflict` error if it's not ready to serve requests.
+    pub(super) async fn ready(&self, txn: &Txn) -> TCResult<State> {
+        let catching_up: Vec<String> = self
+            .hosted()
+            .into_iter()
+            .filter(|cluster| !cluster.is_online())
+            .map(|cluster| TCPath::from(cluster.path()).to_string())
+            .collect();
+
+        if catching_up.is_empty() {
+            self.health(txn).await
+        } else {
+            Err(TCError::new(
+                ErrorType::Conflict,
+                format!(
+                    "this host is not ready: {} still catching up with its replicas",
+                    catching_up.join(", ")
+                ),
+            ))
+        }
+    }
+}
+
+fn map(entries: Vec<(Label, Value)>) -> Map<State> {
+    entries
+        .into_iter()
+        .map(|(name, value)| (Id::from(name), State::from(value)))
+        .collect()
+}
+
+fn millis(duration: Duration) -> Value {
+    Value::from(duration.as_millis() as u64)
+}
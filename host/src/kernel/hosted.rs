@@ -1,12 +1,27 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::iter::FromIterator;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use log::{debug, info};
+use log::{debug, info, warn};
+use tokio::sync::Notify;
+use tokio::time::Instant;
 
-use tcgeneric::{label, Label, PathSegment, TCPath, TCPathBuf};
+use tc_error::*;
+use tc_transact::{Transaction, TxnId};
+use tcgeneric::{label, path_label, Label, PathLabel, PathSegment, TCPath, TCPathBuf};
 
 use crate::cluster::Cluster;
 use crate::object::InstanceExt;
+use crate::txn::Txn;
+
+/// The path at which to install a new cluster, or a new version of a hosted cluster
+pub const CLUSTER: PathLabel = path_label(&["host", "cluster"]);
+
+/// How often to check whether a cluster being replaced has finished its in-flight transactions
+const DRAIN_INTERVAL: Duration = Duration::from_millis(50);
 
 const RESERVED: [Label; 57] = [
     label("actor"),
@@ -73,62 +88,86 @@ struct HostedNode {
     children: HashMap<PathSegment, HostedNode>,
 }
 
-pub struct Hosted {
-    root: HostedNode,
-    hosted: HashMap<TCPathBuf, InstanceExt<Cluster>>,
+struct Slot {
+    cluster: Arc<InstanceExt<Cluster>>,
+    active: HashMap<TxnId, usize>,
 }
 
-impl Hosted {
-    fn new() -> Hosted {
-        Hosted {
-            root: HostedNode {
-                children: HashMap::new(),
-            },
-            hosted: HashMap::new(),
-        }
-    }
-
-    pub fn clusters(&self) -> impl Iterator<Item = &InstanceExt<Cluster>> {
-        self.hosted.values()
-    }
-
-    pub fn get<'a>(
-        &self,
-        path: &'a [PathSegment],
-    ) -> Option<(&'a [PathSegment], &InstanceExt<Cluster>)> {
-        debug!("checking for hosted cluster {}", TCPath::from(path));
+struct Inner {
+    root: HostedNode,
+    slots: HashMap<TCPathBuf, Slot>,
+    defaults: HashMap<TCPathBuf, u64>,
+    reloading: HashSet<TCPathBuf>,
+}
 
+impl Inner {
+    fn find<'a>(&self, path: &'a [PathSegment]) -> Option<(TCPathBuf, &'a [PathSegment])> {
         let mut node = &self.root;
         let mut found_path = &path[0..0];
         for i in 0..path.len() {
             if let Some(child) = node.children.get(&path[i]) {
                 found_path = &path[..i + 1];
                 node = child;
-            } else if let Some(cluster) = self.hosted.get(found_path) {
-                return Some((&path[found_path.len()..], cluster));
             } else {
-                return None;
+                break;
             }
         }
 
-        if let Some(cluster) = self.hosted.get(found_path) {
-            Some((&path[found_path.len()..], cluster))
+        let suffix = &path[found_path.len()..];
+        if self.slots.contains_key(found_path) {
+            Some((found_path.to_vec().into(), suffix))
         } else {
-            None
+            // if the path addresses a versioned cluster without a version, route to the default
+            let version = self.default_version(found_path, node)?;
+            Some((version, suffix))
         }
     }
 
-    fn push(&mut self, cluster: InstanceExt<Cluster>) {
-        if cluster.path().is_empty() {
-            panic!("Cannot host a cluster at /");
-        } else {
-            for id in &RESERVED {
-                if &cluster.path()[0] == id {
-                    panic!("Cannot host a cluster at reserved path /{}", id);
-                }
+    fn node(&self, path: &[PathSegment]) -> Option<&HostedNode> {
+        let mut node = &self.root;
+        for segment in path {
+            node = node.children.get(segment)?;
+        }
+
+        Some(node)
+    }
+
+    fn versions(&self, base: &[PathSegment], node: &HostedNode) -> Vec<(u64, TCPathBuf)> {
+        let mut versions: Vec<(u64, TCPathBuf)> = node
+            .children
+            .keys()
+            .filter_map(|segment| version_number(segment).map(|number| (number, segment)))
+            .map(|(number, segment)| {
+                let mut path = base.to_vec();
+                path.push(segment.clone());
+                (number, path.into())
+            })
+            .filter(|(_, path)| self.slots.contains_key(path))
+            .collect();
+
+        versions.sort_by_key(|(number, _)| *number);
+        versions
+    }
+
+    fn default_version(&self, base: &[PathSegment], node: &HostedNode) -> Option<TCPathBuf> {
+        let versions = self.versions(base, node);
+
+        if let Some(default) = self.defaults.get(base) {
+            if let Some((_, path)) = versions.iter().find(|(number, _)| number == default) {
+                return Some(path.clone());
             }
         }
 
+        let latest = versions
+            .iter()
+            .rev()
+            .find(|(_, path)| !self.slots[path].cluster.is_deprecated())
+            .or_else(|| versions.last());
+
+        latest.map(|(_, path)| path.clone())
+    }
+
+    fn insert(&mut self, cluster: Arc<InstanceExt<Cluster>>) {
         let mut node = &mut self.root;
         for segment in cluster.path().iter().cloned() {
             node = node.children.entry(segment).or_insert(HostedNode {
@@ -136,8 +175,228 @@ impl Hosted {
             });
         }
 
+        let path = TCPathBuf::from(cluster.path().to_vec());
+        let active = self
+            .slots
+            .remove(&path)
+            .map(|slot| slot.active)
+            .unwrap_or_default();
+
         info!("Hosted {}", cluster);
-        self.hosted.insert(cluster.path().to_vec().into(), cluster);
+        self.slots.insert(path, Slot { cluster, active });
+    }
+
+    fn remove(&mut self, path: &[PathSegment]) {
+        if let Some(slot) = self.slots.remove(path) {
+            info!("no longer hosting {}", slot.cluster);
+        }
+
+        // rebuild the routing tree so that no branch leads to the removed cluster
+        let mut root = HostedNode {
+            children: HashMap::new(),
+        };
+
+        for path in self.slots.keys() {
+            let mut node = &mut root;
+            for segment in path.iter().cloned() {
+                node = node.children.entry(segment).or_insert(HostedNode {
+                    children: HashMap::new(),
+                });
+            }
+        }
+
+        self.root = root;
+    }
+}
+
+/// The set of [`Cluster`]s served by this host, which can be replaced at runtime.
+pub struct Hosted {
+    inner: Mutex<Inner>,
+    reloaded: Notify,
+}
+
+impl Hosted {
+    fn new() -> Hosted {
+        Hosted {
+            inner: Mutex::new(Inner {
+                root: HostedNode {
+                    children: HashMap::new(),
+                },
+                slots: HashMap::new(),
+                defaults: HashMap::new(),
+                reloading: HashSet::new(),
+            }),
+            reloaded: Notify::new(),
+        }
+    }
+
+    pub fn clusters(&self) -> Vec<Arc<InstanceExt<Cluster>>> {
+        let inner = self.inner.lock().expect("hosted clusters");
+        inner
+            .slots
+            .values()
+            .map(|slot| slot.cluster.clone())
+            .collect()
+    }
+
+    /// Look up the hosted cluster at the given `path`, without waiting for any pending reload.
+    pub fn peek<'a>(
+        &self,
+        path: &'a [PathSegment],
+    ) -> Option<(&'a [PathSegment], Arc<InstanceExt<Cluster>>)> {
+        let inner = self.inner.lock().expect("hosted clusters");
+        let (prefix, suffix) = inner.find(path)?;
+        Some((suffix, inner.slots[&prefix].cluster.clone()))
+    }
+
+    /// Look up the hosted cluster at the given `path` in order to handle a request.
+    ///
+    /// If the cluster is being replaced, a request which starts a new transaction waits until
+    /// the new version is installed; requests belonging to a transaction already in progress
+    /// are served by the old version so that the transaction can complete.
+    pub async fn get<'a, 'b>(
+        &'b self,
+        path: &'a [PathSegment],
+        txn: &Txn,
+    ) -> Option<(&'a [PathSegment], HostedCluster<'b>)> {
+        debug!("checking for hosted cluster {}", TCPath::from(path));
+
+        let txn_id = *txn.id();
+
+        loop {
+            let reloaded = self.reloaded.notified();
+
+            {
+                let mut inner = self.inner.lock().expect("hosted clusters");
+                let (prefix, suffix) = inner.find(path)?;
+
+                let in_progress =
+                    txn.has_owner() || inner.slots[&prefix].active.contains_key(&txn_id);
+                if in_progress || !inner.reloading.contains(&prefix) {
+                    let slot = inner.slots.get_mut(&prefix).expect("hosted cluster");
+                    *slot.active.entry(txn_id).or_insert(0) += 1;
+
+                    if slot.cluster.is_deprecated() {
+                        warn!("request to deprecated {}", slot.cluster);
+                    }
+
+                    let cluster = HostedCluster {
+                        hosted: self,
+                        path: prefix,
+                        txn_id,
+                        cluster: slot.cluster.clone(),
+                    };
+
+                    return Some((suffix, cluster));
+                }
+            }
+
+            debug!("waiting for {} to be reloaded", TCPath::from(path));
+            reloaded.await;
+        }
+    }
+
+    /// Prepare to install a new version of the cluster at the given `path`, by waiting
+    /// (up to the given `timeout`) for any request or transaction in progress to finish.
+    ///
+    /// New transactions which address the cluster wait until the returned [`Reload`] is
+    /// either completed or dropped. If the `Reload` is dropped after installing a new version,
+    /// the old version (if any) is restored.
+    pub async fn reload(&self, path: TCPathBuf, timeout: Duration) -> TCResult<Reload<'_>> {
+        validate(&path)?;
+
+        let old = {
+            let mut inner = self.inner.lock().expect("hosted clusters");
+            if !inner.reloading.insert(path.clone()) {
+                return Err(TCError::new(
+                    ErrorType::Conflict,
+                    format!("{} is already being reloaded", path),
+                ));
+            }
+
+            inner.slots.get(&path).map(|slot| slot.cluster.clone())
+        };
+
+        let reload = Reload {
+            hosted: self,
+            path,
+            old: old.clone(),
+            installed: false,
+        };
+
+        if let Some(old) = old {
+            info!("draining {} before reloading it", old);
+
+            let deadline = Instant::now() + timeout;
+            loop {
+                let idle = {
+                    let inner = self.inner.lock().expect("hosted clusters");
+                    inner.slots[&reload.path].active.is_empty()
+                };
+
+                if idle && !old.has_pending_txns().await {
+                    break;
+                } else if Instant::now() >= deadline {
+                    return Err(TCError::new(
+                        ErrorType::Conflict,
+                        format!(
+                            "timed out waiting for the transactions in progress at {} to finish",
+                            reload.path
+                        ),
+                    ));
+                }
+
+                tokio::time::sleep(DRAIN_INTERVAL).await;
+            }
+        }
+
+        Ok(reload)
+    }
+
+    /// List the versions of the cluster at the given `base` path, and the default version.
+    pub fn versions(
+        &self,
+        base: &[PathSegment],
+    ) -> Option<(TCPathBuf, Vec<Arc<InstanceExt<Cluster>>>)> {
+        let inner = self.inner.lock().expect("hosted clusters");
+        let node = inner.node(base)?;
+        let default = inner.default_version(base, node)?;
+        let versions = inner
+            .versions(base, node)
+            .into_iter()
+            .map(|(_, path)| inner.slots[&path].cluster.clone())
+            .collect();
+
+        Some((default, versions))
+    }
+
+    /// Route requests which don't specify a version of a cluster to the version at `path`.
+    pub fn set_default(&self, path: &[PathSegment]) -> TCResult<()> {
+        let number = path.last().and_then(version_number).ok_or_else(|| {
+            TCError::bad_request(
+                "expected a versioned cluster path but found",
+                TCPath::from(path),
+            )
+        })?;
+
+        let mut inner = self.inner.lock().expect("hosted clusters");
+        if !inner.slots.contains_key(path) {
+            return Err(TCError::not_found(TCPath::from(path)));
+        }
+
+        let base = TCPathBuf::from(path[..path.len() - 1].to_vec());
+        info!("default version of {} is now {}", base, number);
+        inner.defaults.insert(base, number);
+        Ok(())
+    }
+
+    fn push(&mut self, cluster: InstanceExt<Cluster>) {
+        if let Err(cause) = validate(cluster.path()) {
+            panic!("{}", cause);
+        }
+
+        let inner = self.inner.get_mut().expect("hosted clusters");
+        inner.insert(Arc::new(cluster));
     }
 }
 
@@ -152,3 +411,126 @@ impl FromIterator<InstanceExt<Cluster>> for Hosted {
         hosted
     }
 }
+
+/// A hosted cluster in use by a request, which can't be replaced until the request finishes.
+pub struct HostedCluster<'a> {
+    hosted: &'a Hosted,
+    path: TCPathBuf,
+    txn_id: TxnId,
+    cluster: Arc<InstanceExt<Cluster>>,
+}
+
+impl<'a> Deref for HostedCluster<'a> {
+    type Target = InstanceExt<Cluster>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cluster
+    }
+}
+
+impl<'a> Drop for HostedCluster<'a> {
+    fn drop(&mut self) {
+        let mut inner = self.hosted.inner.lock().expect("hosted clusters");
+        if let Some(slot) = inner.slots.get_mut(&self.path) {
+            if let Some(count) = slot.active.get_mut(&self.txn_id) {
+                *count -= 1;
+                if *count == 0 {
+                    slot.active.remove(&self.txn_id);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for HostedCluster<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&*self.cluster, f)
+    }
+}
+
+/// A pending replacement of a hosted cluster, which is abandoned if dropped.
+pub struct Reload<'a> {
+    hosted: &'a Hosted,
+    path: TCPathBuf,
+    old: Option<Arc<InstanceExt<Cluster>>>,
+    installed: bool,
+}
+
+impl<'a> Reload<'a> {
+    /// Serve the given new version of the hosted cluster to the transaction which installs it.
+    ///
+    /// Other new transactions keep waiting until this `Reload` is completed, and the old version
+    /// (if any) is restored if this `Reload` is dropped without being completed.
+    pub fn install(
+        &mut self,
+        cluster: InstanceExt<Cluster>,
+    ) -> TCResult<Arc<InstanceExt<Cluster>>> {
+        if cluster.path() != &self.path[..] {
+            return Err(TCError::bad_request(
+                format!("cannot reload {} with a cluster at", self.path),
+                TCPath::from(cluster.path()),
+            ));
+        }
+
+        let cluster = Arc::new(cluster);
+        let mut inner = self.hosted.inner.lock().expect("hosted clusters");
+        inner.insert(cluster.clone());
+        self.installed = true;
+
+        Ok(cluster)
+    }
+
+    /// Keep the new version of the hosted cluster, and serve it to every new transaction.
+    pub fn complete(mut self) {
+        debug_assert!(self.installed);
+        self.installed = false;
+    }
+}
+
+impl<'a> Drop for Reload<'a> {
+    fn drop(&mut self) {
+        let mut inner = self.hosted.inner.lock().expect("hosted clusters");
+
+        if self.installed {
+            if let Some(old) = self.old.take() {
+                warn!("restoring {} since its new version was not installed", old);
+                inner.insert(old);
+            } else {
+                warn!("abandoning the new cluster at {}", self.path);
+                inner.remove(&self.path);
+            }
+        }
+
+        inner.reloading.remove(&self.path);
+        std::mem::drop(inner);
+
+        self.hosted.reloaded.notify_waiters();
+    }
+}
+
+fn validate(path: &[PathSegment]) -> TCResult<()> {
+    if path.is_empty() {
+        return Err(TCError::unsupported("cannot host a cluster at /"));
+    }
+
+    for id in &RESERVED {
+        if &path[0] == id {
+            return Err(TCError::unsupported(format!(
+                "cannot host a cluster at reserved path /{}",
+                id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the version number of a cluster from the last segment of its path, like "v2".
+fn version_number(segment: &PathSegment) -> Option<u64> {
+    let segment = segment.as_str();
+    if segment.starts_with('v') && segment.len() > 1 {
+        segment[1..].parse().ok()
+    } else {
+        None
+    }
+}
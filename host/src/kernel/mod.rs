@@ -3,48 +3,278 @@
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures::future::Future;
-use log::debug;
+use log::{debug, info};
 use safecast::*;
+use tracing::Instrument;
+
 use tc_error::*;
-use tc_value::{Link, Value};
+use tc_transact::{Transact, Transaction};
+use tc_value::{Link, LinkHost, Value};
 use tcgeneric::*;
 
-use crate::cluster::Cluster;
+use crate::cluster::{self, openapi, openapi_host, Cluster, JOURNAL, META, OPENAPI, PREPARE};
+use crate::fs;
+use crate::metrics;
 use crate::object::{InstanceClass, InstanceExt};
 use crate::route::{Public, Static};
 use crate::scalar::{OpRefType, Scalar, ScalarType};
 use crate::state::{State, StateType};
 use crate::txn::Txn;
 
-use hosted::Hosted;
+use hosted::{Hosted, CLUSTER};
 use hypothetical::Hypothetical;
 
+pub use auth::SCOPE_ADMIN;
+pub use backend::BACKEND;
+pub use backup::{Backups, BACKUP};
+pub use config::{ConfigFile, CONFIG};
+pub use health::{HEALTH, READY};
+pub use quota::QUOTA;
+pub use registry::{Compatibility, Registry, REGISTRY};
+pub use savepoint::{ROLLBACK, SAVEPOINT};
+pub use schedule::{Cron, Scheduler, SCHEDULE};
+pub use tenant::Namespace;
+pub use version::VERSION;
+
+mod auth;
+mod backend;
+mod backup;
+mod config;
+mod health;
 mod hosted;
 mod hypothetical;
+mod quota;
+mod registry;
+mod savepoint;
+mod schedule;
+mod tenant;
+mod version;
+
+/// How long to wait by default for the transactions in progress at a cluster to finish
+/// before reloading it
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// The host kernel, responsible for dispatching requests to the local host
 pub struct Kernel {
+    admins: Vec<Link>,
+    backups: Option<Backups>,
+    config: Option<Arc<ConfigFile>>,
+    data_dir: Option<fs::Dir>,
+    drain_timeout: Duration,
     hosted: Hosted,
     hypothetical: Hypothetical,
+    namespaces: Vec<Namespace>,
+    registry: Arc<Registry>,
+    scheduler: Arc<Scheduler>,
+    started: Instant,
 }
 
 impl Kernel {
     /// Construct a new `Kernel` to host the given [`Cluster`]s.
     pub fn new<I: IntoIterator<Item = InstanceExt<Cluster>>>(clusters: I) -> Self {
         Self {
+            admins: Vec::new(),
+            backups: None,
+            config: None,
+            data_dir: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
             hosted: clusters.into_iter().collect(),
             hypothetical: Hypothetical::new(),
+            namespaces: Vec::new(),
+            registry: Arc::new(Registry::default()),
+            scheduler: Arc::new(Scheduler::default()),
+            started: Instant::now(),
+        }
+    }
+
+    /// Serve the given schema [`Registry`] instead of an empty, in-memory registry.
+    pub fn with_registry(self, registry: Arc<Registry>) -> Self {
+        Self { registry, ..self }
+    }
+
+    /// Serve the given job [`Scheduler`] instead of an empty, in-memory scheduler.
+    pub fn with_scheduler(self, scheduler: Arc<Scheduler>) -> Self {
+        Self { scheduler, ..self }
+    }
+
+    /// Serve online backups of the data directory of this host.
+    pub fn with_backups(self, backups: Backups) -> Self {
+        Self {
+            backups: Some(backups),
+            ..self
+        }
+    }
+
+    /// Serve the configuration file of this host, so that it can be reloaded at runtime.
+    pub fn with_config(self, config: Arc<ConfigFile>) -> Self {
+        Self {
+            config: Some(config),
+            ..self
+        }
+    }
+
+    /// Store the data of any [`Cluster`] installed at runtime in the given data directory.
+    pub fn with_data_dir(self, data_dir: fs::Dir) -> Self {
+        Self {
+            data_dir: Some(data_dir),
+            ..self
         }
     }
 
+    /// Trust the given issuers to grant the [`SCOPE_ADMIN`] scope required to administer this host.
+    pub fn with_admins(self, admins: Vec<Link>) -> Self {
+        Self { admins, ..self }
+    }
+
+    /// Wait up to the given `drain_timeout` for the transactions in progress at a cluster
+    /// to finish before reloading it.
+    pub fn with_drain_timeout(self, drain_timeout: Duration) -> Self {
+        Self {
+            drain_timeout,
+            ..self
+        }
+    }
+
+    /// Isolate the clusters hosted in each of the given tenant [`Namespace`]s.
+    pub fn with_namespaces(self, namespaces: Vec<Namespace>) -> Self {
+        Self { namespaces, ..self }
+    }
+
     /// Return a list of hosted clusters
-    pub fn hosted(&self) -> impl Iterator<Item = &InstanceExt<Cluster>> {
+    pub fn hosted(&self) -> Vec<Arc<InstanceExt<Cluster>>> {
         self.hosted.clusters()
     }
 
+    /// Return the job [`Scheduler`] of this host
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// Install the [`Cluster`] at the given `link`, defined by the given `value`, replacing
+    /// the version currently hosted at that path (if any) once its in-flight transactions finish.
+    ///
+    /// The definition of an installed cluster is not persisted, so it is only hosted until the
+    /// host restarts; to keep hosting it, add its config to the `--cluster` option. Its data
+    /// directory is kept, so the cluster loads its data again once it's configured.
+    async fn install(&self, txn: &Txn, key: Value, value: State) -> TCResult<()> {
+        let link = Link::try_cast_from(key, |v| {
+            TCError::bad_request("expected a Link to the cluster to install, not", v)
+        })?;
+
+        self.authorize_install(txn, link.path()).await?;
+
+        let class = InstanceClass::try_cast_from(value, |s| {
+            TCError::bad_request("expected a cluster definition but found", s)
+        })?;
+
+        let (extends, proto) = class.into_inner();
+        if let Some(extends) = extends {
+            if extends != link {
+                return Err(TCError::bad_request(
+                    format!("cannot install {} at", extends),
+                    link,
+                ));
+            }
+        }
+
+        let data_dir = self.data_dir.as_ref().ok_or_else(|| {
+            TCError::unsupported("the --data_dir option is required to host a Cluster")
+        })?;
+
+        let mut reload = self
+            .hosted
+            .reload(link.path().clone(), self.drain_timeout)
+            .await?;
+
+        let host = txn.gateway().root().clone();
+        let class = InstanceClass::new(Some(link.clone()), proto);
+        let cluster = cluster::instantiate(txn, host, class, data_dir.clone()).await?;
+        cluster.recover(txn).await?;
+
+        // the new version has to be hosted in order to replicate it, but if it can't be
+        // committed then dropping the reload restores the old version
+        let cluster = reload.install(cluster)?;
+
+        // the new cluster commits its directory when it commits this transaction
+        let txn = cluster.claim(txn).await?;
+
+        if link.host().is_some() {
+            info!("replicating {}", cluster);
+
+            let self_link = txn.link(link.path().clone());
+            if let Err(cause) = cluster.add_replica(&txn, self_link).await {
+                cluster.distribute_rollback(&txn).await;
+                return Err(cause);
+            }
+        }
+
+        cluster.distribute_commit(&txn).await?;
+        reload.complete();
+
+        Ok(())
+    }
+
+    fn backups(&self) -> TCResult<&Backups> {
+        self.backups
+            .as_ref()
+            .ok_or_else(|| TCError::unsupported("this host is not configured to take backups"))
+    }
+
+    fn config(&self) -> TCResult<&ConfigFile> {
+        self.config
+            .as_deref()
+            .ok_or_else(|| TCError::unsupported("this host was not started with a config file"))
+    }
+
+    /// Describe the hosted [`Cluster`] at the given `path` as an OpenAPI document,
+    /// or every hosted cluster if the `path` is `/-/openapi.json`.
+    ///
+    /// A cluster in a tenant [`Namespace`] is not described, since the request is not authorized.
+    pub fn openapi(&self, host: &LinkHost, path: &[PathSegment]) -> TCResult<serde_json::Value> {
+        let is_openapi = |suffix: &[PathSegment]| match suffix {
+            [name] => name == &OPENAPI,
+            [meta, name] => meta == &META && name == &OPENAPI,
+            _ => false,
+        };
+
+        if path.len() == 2 && is_openapi(path) {
+            let clusters = self.hosted.clusters();
+            let clusters = clusters
+                .iter()
+                .filter(|cluster| self.namespace(cluster.path()).is_none())
+                .map(|cluster| &**cluster);
+
+            return Ok(openapi_host(host, clusters));
+        }
+
+        match self.hosted.peek(path) {
+            Some((suffix, cluster)) if is_openapi(suffix) && self.namespace(path).is_none() => {
+                Ok(openapi(host, &cluster))
+            }
+            _ => Err(TCError::not_found(TCPath::from(path))),
+        }
+    }
+
+    /// Return the route label of a request to the given `path` in the metrics of this host.
+    ///
+    /// A request to a hosted cluster is labelled with the path of the cluster, or the prefix of its
+    /// tenant [`Namespace`] so that the clusters of a tenant are not disclosed.
+    pub fn route_label(&self, path: &[PathSegment]) -> String {
+        match self.hosted.peek(path) {
+            Some((_, cluster)) => match self.namespace(cluster.path()) {
+                Some(namespace) => namespace.prefix().to_string(),
+                None => TCPath::from(cluster.path()).to_string(),
+            },
+            None => metrics::builtin_route(path).to_string(),
+        }
+    }
+
     /// Route a GET request.
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %TCPath::from(path)))]
     pub async fn get(&self, txn: &Txn, path: &[PathSegment], key: Value) -> TCResult<State> {
         if path.is_empty() {
             Err(TCError::not_found(format!(
@@ -60,7 +290,30 @@ impl Kernel {
                 .ok_or_else(|| TCError::unsupported(err))
         } else if path == &hypothetical::PATH[..] {
             self.hypothetical.get(txn, &path[..], key).await
-        } else if let Some((suffix, cluster)) = self.hosted.get(path) {
+        } else if path == &HEALTH[..] {
+            key.expect_none()?;
+            self.health(txn).await
+        } else if path == &READY[..] {
+            key.expect_none()?;
+            self.ready(txn).await
+        } else if path == &VERSION[..] {
+            self.versions(key)
+        } else if path == &QUOTA[..] {
+            self.quota(txn, key).await
+        } else if path == &BACKEND[..] {
+            key.expect_none()?;
+            self.backend()
+        } else if path[0] == REGISTRY[0] {
+            self.registry.get(&path[1..], key).await
+        } else if path[0] == SCHEDULE[0] {
+            self.scheduler.get(txn, &path[1..], key).await
+        } else if path[0] == BACKUP[0] {
+            self.backups()?.get(&path[1..], key).await
+        } else if path[0] == CONFIG[0] {
+            self.config()?.get(&path[1..], key).await
+        } else if !self.is_visible(txn, path).await {
+            Err(TCError::not_found(TCPath::from(path)))
+        } else if let Some((suffix, cluster)) = self.hosted.get(path, txn).await {
             debug!(
                 "GET {}: {} from cluster {}",
                 TCPath::from(suffix),
@@ -68,6 +321,7 @@ impl Kernel {
                 cluster
             );
 
+            check_online(&cluster, txn, suffix)?;
             cluster.get(&txn, suffix, key).await
         } else {
             Static.get(txn, path, key).await
@@ -75,6 +329,7 @@ impl Kernel {
     }
 
     /// Route a PUT request.
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %TCPath::from(path)))]
     pub async fn put(
         &self,
         txn: &Txn,
@@ -97,13 +352,35 @@ impl Kernel {
             ))
         } else if path == &hypothetical::PATH[..] {
             self.hypothetical.put(txn, &path[..], key, value).await
+        } else if path == &CLUSTER[..] {
+            self.install(txn, key, value).await
+        } else if path == &VERSION[..] {
+            self.set_version(txn, key, value)
+        } else if path == &QUOTA[..] {
+            self.set_quota(txn, key, value).await
+        } else if path == &SAVEPOINT[..] {
+            self.save(txn, key, value).await
+        } else if path[0] == REGISTRY[0] {
+            self.authorize_admin(txn, "publishing a schema")?;
+            self.registry.put(&path[1..], key, value).await
+        } else if path[0] == SCHEDULE[0] {
+            self.authorize_admin(txn, "scheduling a job")?;
+            self.scheduler.put(txn, &path[1..], key, value).await
+        } else if path[0] == BACKUP[0] {
+            self.authorize_admin(txn, "backing up the data directory")?;
+            self.backups()?.put(txn, &path[1..], key, value).await
+        } else if path[0] == CONFIG[0] {
+            self.authorize_admin(txn, "reloading the host configuration")?;
+            self.config()?.put(&path[1..], key, value).await
         } else if let Some(class) = StateType::from_path(path) {
             Err(TCError::method_not_allowed(
                 OpRefType::Put,
                 class,
                 TCPath::from(path),
             ))
-        } else if let Some((suffix, cluster)) = self.hosted.get(path) {
+        } else if !self.is_visible(txn, path).await {
+            Err(TCError::not_found(TCPath::from(path)))
+        } else if let Some((suffix, cluster)) = self.hosted.get(path, txn).await {
             debug!(
                 "PUT {}: {} <- {} to cluster {}",
                 TCPath::from(suffix),
@@ -112,9 +389,10 @@ impl Kernel {
                 cluster
             );
 
-            let txn = maybe_claim_leadership(cluster, txn).await?;
+            check_online(&cluster, txn, suffix)?;
+            let txn = maybe_claim_leadership(&cluster, txn).await?;
 
-            execute(txn, cluster, |txn, cluster| async move {
+            execute(txn, &cluster, |txn, cluster| async move {
                 cluster
                     .put(&txn, suffix, key.clone(), value.clone())
                     .await?;
@@ -155,6 +433,7 @@ impl Kernel {
     }
 
     /// Route a POST request.
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %TCPath::from(path)))]
     pub async fn post(&self, txn: &Txn, path: &[PathSegment], data: State) -> TCResult<State> {
         if path.is_empty() {
             if Map::try_from(data)?.is_empty() {
@@ -169,6 +448,13 @@ impl Kernel {
             }
         } else if path == &hypothetical::PATH[..] {
             self.hypothetical.execute(txn, data).await
+        } else if path == &ROLLBACK[..] {
+            let params = data.try_into()?;
+            self.rollback_to(txn, params).await?;
+            Ok(State::default())
+        } else if path[0] == SCHEDULE[0] {
+            let params = data.try_into()?;
+            self.scheduler.post(txn, &path[1..], params).await
         } else if StateType::from_path(path).is_some() {
             let extends = Link::from(TCPathBuf::from(path.to_vec()));
 
@@ -189,7 +475,9 @@ impl Kernel {
             Ok(State::Object(
                 InstanceClass::new(Some(extends), proto).into(),
             ))
-        } else if let Some((suffix, cluster)) = self.hosted.get(path) {
+        } else if !self.is_visible(txn, path).await {
+            Err(TCError::not_found(TCPath::from(path)))
+        } else if let Some((suffix, cluster)) = self.hosted.get(path, txn).await {
             let params: Map<State> = data.try_into()?;
 
             debug!(
@@ -199,12 +487,16 @@ impl Kernel {
                 cluster
             );
 
-            let txn = maybe_claim_leadership(cluster, txn).await?;
+            check_online(&cluster, txn, suffix)?;
+            let txn = maybe_claim_leadership(&cluster, txn).await?;
             if suffix.is_empty() && params.is_empty() {
                 // it's a "commit" instruction
                 cluster.post(&txn, suffix, params).await
+            } else if suffix.is_empty() && params.contains_key(&PREPARE.into()) {
+                // it's a "prepare" instruction
+                cluster.post(&txn, suffix, params).await
             } else {
-                execute(txn, cluster, |txn, cluster| async move {
+                execute(txn, &cluster, |txn, cluster| async move {
                     cluster.post(&txn, suffix, params).await
                 })
                 .await
@@ -216,6 +508,7 @@ impl Kernel {
     }
 
     /// Route a DELETE request.
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %TCPath::from(path)))]
     pub async fn delete(&self, txn: &Txn, path: &[PathSegment], key: Value) -> TCResult<()> {
         if path.is_empty() {
             Err(TCError::method_not_allowed(
@@ -225,16 +518,27 @@ impl Kernel {
             ))
         } else if path == &hypothetical::PATH[..] {
             self.hypothetical.delete(txn, &path[2..], key).await
+        } else if path[0] == SCHEDULE[0] {
+            if path.len() > 1 {
+                self.authorize_admin(txn, "cancelling a scheduled job")?;
+            }
+
+            self.scheduler.delete(txn, &path[1..], key).await
         } else if let Some(class) = StateType::from_path(path) {
             Err(TCError::method_not_allowed(
                 OpRefType::Post,
                 class,
                 TCPath::default(),
             ))
-        } else if let Some((suffix, cluster)) = self.hosted.get(path) {
+        } else if !self.is_visible(txn, path).await {
+            Err(TCError::not_found(TCPath::from(path)))
+        } else if let Some((suffix, cluster)) = self.hosted.get(path, txn).await {
             if suffix.is_empty() && key.is_none() {
                 // it's a rollback message
                 return cluster.delete(&txn, suffix, key).await;
+            } else if suffix.len() == 1 && suffix[0] == JOURNAL {
+                // it's an acknowledgment of a commit decision, which is specific to this host
+                return cluster.delete(&txn, suffix, key).await;
             }
 
             debug!(
@@ -244,8 +548,9 @@ impl Kernel {
                 cluster
             );
 
-            let txn = maybe_claim_leadership(cluster, txn).await?;
-            execute(txn, cluster, |txn, cluster| async move {
+            check_online(&cluster, txn, suffix)?;
+            let txn = maybe_claim_leadership(&cluster, txn).await?;
+            execute(txn, &cluster, |txn, cluster| async move {
                 cluster.delete(&txn, suffix, key.clone()).await?;
 
                 let txn = if !txn.has_leader(cluster.path()) {
@@ -296,6 +601,15 @@ impl fmt::Display for Kernel {
     }
 }
 
+fn check_online(cluster: &Cluster, txn: &Txn, suffix: &[PathSegment]) -> TCResult<()> {
+    if suffix.len() == 1 && suffix[0] == JOURNAL {
+        // the journal is specific to this host, so it's available before this replica is online
+        Ok(())
+    } else {
+        cluster.check_online(txn.id())
+    }
+}
+
 fn execute<
     'a,
     R: Send,
@@ -330,10 +644,16 @@ fn execute<
 
             if result.is_ok() {
                 debug!("commit {}", cluster);
-                cluster.distribute_commit(&txn).await?;
+                cluster
+                    .distribute_commit(&txn)
+                    .instrument(tracing::debug_span!("commit", %cluster))
+                    .await?;
             } else {
                 debug!("rollback {}", cluster);
-                cluster.distribute_rollback(&txn).await;
+                cluster
+                    .distribute_rollback(&txn)
+                    .instrument(tracing::debug_span!("rollback", %cluster))
+                    .await;
             }
 
             result
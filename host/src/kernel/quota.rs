@@ -0,0 +1,126 @@
+//! Resource quotas of a hosted cluster, served by the [`Kernel`] at [`QUOTA`].
+//!
+//! `GET /host/quota?key=<cluster link>` describes the quota of a cluster and its current usage, and
+//! `PUT /host/quota?key=<cluster link>` with a map like `{"disk": 1073741824, "cache": null}` sets
+//! its quota, where a `null` (or missing) limit means no limit. A quota limits the bytes which the
+//! data directory of a cluster may occupy on disk and in the block cache, and is enforced each
+//! time a block is created. Quotas are not persisted, so they must be set again after a restart.
+//! Only a host administrator (see [`super::SCOPE_ADMIN`]) can set a quota.
+
+use safecast::TryCastFrom;
+
+use tc_error::*;
+use tc_transact::fs::Dir;
+use tc_transact::Transaction;
+use tc_value::Value;
+use tcgeneric::{label, path_label, Label, Map, PathLabel, TCPath};
+
+use crate::fs;
+use crate::scalar::OpRefType;
+use crate::state::State;
+use crate::txn::Txn;
+
+use super::version::expect_link;
+use super::Kernel;
+
+/// The path at which to describe and configure the resource quota of a hosted cluster.
+pub const QUOTA: PathLabel = path_label(&["host", "quota"]);
+
+const CACHE: Label = label("cache");
+const DISK: Label = label("disk");
+
+impl Kernel {
+    /// Describe the quota and resource usage of the cluster at the path of the given [`Link`].
+    pub(super) async fn quota(&self, txn: &Txn, key: Value) -> TCResult<State> {
+        let dir = self.cluster_dir(txn, key, OpRefType::Get).await?;
+        let quota = dir.quota().await.unwrap_or_default();
+        let usage = dir.usage().await?;
+
+        let limits: Map<State> = vec![
+            (DISK.into(), Value::from(quota.disk).into()),
+            (CACHE.into(), Value::from(quota.cache).into()),
+        ]
+        .into_iter()
+        .collect();
+
+        let usage: Map<State> = vec![
+            (DISK.into(), Value::from(usage.disk).into()),
+            (CACHE.into(), Value::from(usage.cache).into()),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut description = Map::new();
+        description.insert(label("quota").into(), State::Map(limits));
+        description.insert(label("usage").into(), State::Map(usage));
+        Ok(State::Map(description))
+    }
+
+    /// Set the quota of the cluster at the path of the given [`Link`].
+    ///
+    /// Only a host administrator can set a quota, since a cluster could otherwise lift its own.
+    pub(super) async fn set_quota(&self, txn: &Txn, key: Value, value: State) -> TCResult<()> {
+        self.authorize_admin(txn, "setting the quota of a cluster")?;
+
+        let dir = self.cluster_dir(txn, key, OpRefType::Put).await?;
+
+        let limits = value.try_into_map(|v| TCError::bad_request("invalid quota", v))?;
+
+        let mut quota = fs::Quota::default();
+        for (name, limit) in limits {
+            let limit = Value::try_cast_from(limit, |v| {
+                TCError::bad_request(format!("invalid {} quota", name), v)
+            })?;
+
+            let limit = if limit.is_none() {
+                None
+            } else {
+                u64::try_cast_from(limit, |v| {
+                    TCError::bad_request(format!("invalid {} quota", name), v)
+                })
+                .map(Some)?
+            };
+
+            if name == DISK {
+                quota.disk = limit;
+            } else if name == CACHE {
+                quota.cache = limit;
+            } else {
+                return Err(TCError::bad_request("unrecognized quota", name));
+            }
+        }
+
+        dir.set_quota(quota).await;
+        Ok(())
+    }
+
+    async fn cluster_dir(&self, txn: &Txn, key: Value, method: OpRefType) -> TCResult<fs::Dir> {
+        let link = expect_link(key)?;
+        let (suffix, cluster) = self
+            .hosted
+            .peek(link.path())
+            .ok_or_else(|| TCError::not_found(&link))?;
+
+        if !suffix.is_empty() || cluster.path() != &link.path()[..] {
+            return Err(TCError::method_not_allowed(
+                method,
+                self,
+                TCPath::from(&link.path()[..]),
+            ));
+        }
+
+        let mut dir = self
+            .data_dir
+            .clone()
+            .ok_or_else(|| TCError::unsupported("this host has no data directory"))?;
+
+        for name in cluster.path() {
+            dir = dir
+                .get_dir(*txn.id(), name)
+                .await?
+                .ok_or_else(|| TCError::not_found(format!("data directory of {}", link)))?;
+        }
+
+        Ok(dir)
+    }
+}
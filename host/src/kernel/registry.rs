@@ -0,0 +1,379 @@
+//! A registry of named, versioned schemas, served by the [`Kernel`] at [`REGISTRY`].
+//!
+//! A schema (e.g. a `Table` or `Tensor` schema, or a class prototype) is published with
+//! `PUT /schema/<name>`, whose key is the [`Compatibility`] required of the new version with
+//! respect to the latest one (backward, by default). `GET /schema/<name>/<version>` returns a given
+//! version, and `GET /schema/<name>` returns the latest version, so that a cluster can reference
+//! a shared schema by link instead of duplicating its definition. Published versions are immutable,
+//! and only a host administrator can publish a new version.
+//!
+//! A version `B` of a schema can read data written with version `A` if every column (or member)
+//! of `B` is also present in `A`, with the same type and a maximum length no shorter than in `A`.
+//! The key of a `Table` can never change.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use futures::{future, stream, TryStreamExt};
+use log::debug;
+use safecast::*;
+use tokio::sync::RwLock;
+
+use tc_btree::Column;
+use tc_error::*;
+use tc_table::TableSchema;
+use tc_value::{Value, ValueType};
+use tcgeneric::{path_label, Id, PathLabel, PathSegment, TCPath, Tuple};
+
+use crate::scalar::{OpRefType, Scalar};
+use crate::state::State;
+
+/// The path of the schema registry.
+pub const REGISTRY: PathLabel = path_label(&["schema"]);
+
+const EXT: &str = "json";
+
+/// The compatibility required of a new version of a schema with its previous version.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Compatibility {
+    /// No compatibility is required.
+    None,
+
+    /// The new version can read data written with the previous version.
+    Backward,
+
+    /// The previous version can read data written with the new version.
+    Forward,
+
+    /// Both backward and forward compatibility.
+    Full,
+}
+
+impl Compatibility {
+    fn check(&self, previous: &Scalar, schema: &Scalar) -> Result<(), String> {
+        match self {
+            Self::None => Ok(()),
+            Self::Backward => reads(schema, previous),
+            Self::Forward => reads(previous, schema),
+            Self::Full => reads(schema, previous).and_then(|()| reads(previous, schema)),
+        }
+    }
+}
+
+impl Default for Compatibility {
+    fn default() -> Self {
+        Self::Backward
+    }
+}
+
+impl TryCastFrom<Value> for Compatibility {
+    fn can_cast_from(value: &Value) -> bool {
+        Self::opt_cast_from(value.clone()).is_some()
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        if value.is_none() {
+            return Some(Self::default());
+        }
+
+        let name = Id::opt_cast_from(value)?;
+        match name.as_str() {
+            "none" => Some(Self::None),
+            "backward" => Some(Self::Backward),
+            "forward" => Some(Self::Forward),
+            "full" => Some(Self::Full),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Backward => "backward",
+            Self::Forward => "forward",
+            Self::Full => "full",
+        })
+    }
+}
+
+type Versions = Vec<(Compatibility, Scalar)>;
+
+/// A registry of named, versioned schemas.
+///
+/// If the registry has a directory, each schema is persisted there as a JSON list of its versions.
+#[derive(Default)]
+pub struct Registry {
+    dir: Option<PathBuf>,
+    schemas: RwLock<BTreeMap<Id, Versions>>,
+}
+
+impl Registry {
+    /// Load a persistent `Registry` from the given directory.
+    pub async fn load(dir: PathBuf) -> TCResult<Self> {
+        let mut schemas = BTreeMap::new();
+
+        let mut entries = tokio::fs::read_dir(&dir).await.map_err(io_err)?;
+        while let Some(entry) = entries.next_entry().await.map_err(io_err)? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(EXT) {
+                continue;
+            }
+
+            let name: Id = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| TCError::internal(format!("invalid schema file {:?}", path)))?
+                .parse()?;
+
+            let json = tokio::fs::read(&path).await.map_err(io_err)?;
+            let versions: Scalar =
+                destream_json::decode((), stream::once(future::ready(Bytes::from(json))))
+                    .await
+                    .map_err(|e| {
+                        TCError::internal(format!("invalid schema file {:?}: {}", path, e))
+                    })?;
+
+            let versions = Vec::<(Value, Scalar)>::opt_cast_from(versions)
+                .and_then(|versions| {
+                    versions
+                        .into_iter()
+                        .map(|(compatibility, schema)| {
+                            Compatibility::opt_cast_from(compatibility)
+                                .map(|c| (c, normalize(schema)))
+                        })
+                        .collect::<Option<Versions>>()
+                })
+                .ok_or_else(|| TCError::internal(format!("invalid schema file {:?}", path)))?;
+
+            debug!("loaded schema {} from {:?}", name, path);
+            schemas.insert(name, versions);
+        }
+
+        Ok(Self {
+            dir: Some(dir),
+            schemas: RwLock::new(schemas),
+        })
+    }
+
+    /// Read a version of a schema, or list the registered schemas if the `path` is empty.
+    pub async fn get(&self, path: &[PathSegment], key: Value) -> TCResult<State> {
+        let schemas = self.schemas.read().await;
+
+        let (name, version) = match path {
+            [] => {
+                key.expect_none()?;
+                let names = schemas.keys().cloned().map(Value::from);
+                return Ok(State::from(Value::Tuple(names.collect())));
+            }
+            [name] if key.is_none() => (name, None),
+            [name] => {
+                let version = u64::opt_cast_from(key.clone())
+                    .ok_or_else(|| TCError::bad_request("invalid schema version", key))?;
+
+                (name, Some(version))
+            }
+            [name, version] => {
+                key.expect_none()?;
+                let version = version
+                    .as_str()
+                    .parse()
+                    .map_err(|_| TCError::bad_request("invalid schema version", version))?;
+
+                (name, Some(version))
+            }
+            _ => return Err(TCError::not_found(TCPath::from(path))),
+        };
+
+        let versions = schemas
+            .get(name)
+            .ok_or_else(|| TCError::not_found(format!("schema {}", name)))?;
+
+        let (_, schema) = match version {
+            Some(0) => return Err(TCError::bad_request("invalid schema version", 0)),
+            Some(version) => versions.get(version as usize - 1).ok_or_else(|| {
+                TCError::not_found(format!("version {} of schema {}", version, name))
+            })?,
+            None => versions.last().expect("schema version"),
+        };
+
+        Ok(State::Scalar(schema.clone()))
+    }
+
+    /// Publish a new version of the schema with the given name, if it's compatible with the
+    /// latest version.
+    ///
+    /// Publishing a schema identical to its latest version has no effect.
+    pub async fn put(&self, path: &[PathSegment], key: Value, value: State) -> TCResult<()> {
+        let name = match path {
+            [name] => name,
+            _ => {
+                return Err(TCError::method_not_allowed(
+                    OpRefType::Put,
+                    self,
+                    TCPath::from(path),
+                ))
+            }
+        };
+
+        let compatibility = Compatibility::try_cast_from(key, |v| {
+            TCError::bad_request("invalid schema compatibility", v)
+        })?;
+
+        let schema = Scalar::try_cast_from(value, |s| {
+            TCError::bad_request("a schema must be a Scalar, not", s)
+        })
+        .map(normalize)?;
+
+        let mut schemas = self.schemas.write().await;
+        let versions = schemas.entry(name.clone()).or_insert_with(Vec::new);
+
+        if let Some((_, previous)) = versions.last() {
+            if previous == &schema {
+                return Ok(());
+            }
+
+            compatibility.check(previous, &schema).map_err(|cause| {
+                TCError::bad_request(
+                    format!(
+                        "version {} of schema {} is not {} compatible with version {}",
+                        versions.len() + 1,
+                        name,
+                        compatibility,
+                        versions.len(),
+                    ),
+                    cause,
+                )
+            })?;
+        }
+
+        versions.push((compatibility, schema));
+        debug!("published version {} of schema {}", versions.len(), name);
+
+        if let Some(dir) = &self.dir {
+            let path = dir.join(format!("{}.{}", name, EXT));
+            if let Err(cause) = persist(path, versions.clone()).await {
+                versions.pop();
+                if versions.is_empty() {
+                    schemas.remove(name);
+                }
+
+                return Err(cause);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("schema registry")
+    }
+}
+
+async fn persist(path: PathBuf, versions: Versions) -> TCResult<()> {
+    let versions = versions
+        .into_iter()
+        .map(|(compatibility, schema)| {
+            let compatibility = Value::String(compatibility.to_string().into());
+            Scalar::Tuple(vec![Scalar::Value(compatibility), schema].into())
+        })
+        .collect::<Tuple<Scalar>>();
+
+    let json = destream_json::encode(Scalar::Tuple(versions)).map_err(TCError::internal)?;
+    let json = json
+        .map_err(TCError::internal)
+        .try_fold(Vec::new(), |mut buffer, chunk| {
+            buffer.extend_from_slice(&chunk);
+            future::ready(Ok(buffer))
+        })
+        .await?;
+
+    // write to a temporary file first so that a crash can't leave a partially-written schema
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, json).await.map_err(io_err)?;
+    tokio::fs::rename(&tmp, &path).await.map_err(io_err)
+}
+
+/// Represent the given `schema` as a [`Value`], if possible, so that equal schemas compare equal.
+fn normalize(schema: Scalar) -> Scalar {
+    match Value::opt_cast_from(schema.clone()) {
+        Some(value) => Scalar::Value(value),
+        None => schema,
+    }
+}
+
+/// Check that a reader of the `reader` schema can read data written with the `writer` schema.
+fn reads(reader: &Scalar, writer: &Scalar) -> Result<(), String> {
+    match (reader, writer) {
+        (Scalar::Map(reader), Scalar::Map(writer)) => {
+            for name in reader.keys() {
+                if !writer.contains_key(name) {
+                    return Err(format!("missing member {}", name));
+                }
+            }
+
+            Ok(())
+        }
+        (Scalar::Value(reader), Scalar::Value(writer)) => {
+            if TableSchema::can_cast_from(reader) && TableSchema::can_cast_from(writer) {
+                let reader = TableSchema::opt_cast_from(reader.clone()).expect("table schema");
+                let writer = TableSchema::opt_cast_from(writer.clone()).expect("table schema");
+                table_reads(&reader, &writer)
+            } else if reader == writer {
+                Ok(())
+            } else {
+                Err(format!("expected {} but found {}", reader, writer))
+            }
+        }
+        (reader, writer) if reader == writer => Ok(()),
+        (reader, writer) => Err(format!("expected {} but found {}", reader, writer)),
+    }
+}
+
+fn table_reads(reader: &TableSchema, writer: &TableSchema) -> Result<(), String> {
+    let (reader, writer) = (reader.primary(), writer.primary());
+
+    if reader.key() != writer.key() {
+        return Err(format!(
+            "the key of a table cannot change, but found {} instead of {}",
+            Tuple::<Column>::from(reader.key().to_vec()),
+            Tuple::<Column>::from(writer.key().to_vec()),
+        ));
+    }
+
+    for column in reader.values() {
+        let source = writer
+            .values()
+            .iter()
+            .find(|source| source.name == column.name)
+            .ok_or_else(|| format!("missing column {}", column.name))?;
+
+        if source.dtype != column.dtype && column.dtype != ValueType::Value {
+            return Err(format!(
+                "column {} has type {} instead of {}",
+                column.name, source.dtype, column.dtype
+            ));
+        }
+
+        let truncated = match (column.max_len, source.max_len) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(max_len), Some(source_len)) => max_len < source_len,
+        };
+
+        if truncated {
+            return Err(format!("column {} would be truncated", column.name));
+        }
+    }
+
+    Ok(())
+}
+
+fn io_err(cause: std::io::Error) -> TCError {
+    TCError::internal(format!("schema registry I/O error: {}", cause))
+}
@@ -0,0 +1,51 @@
+//! Transactional savepoints, served by the [`Kernel`] at [`SAVEPOINT`] and [`ROLLBACK`].
+//!
+//! Within a transaction, `PUT /transact/savepoint?key=<name>` saves the state of the data which
+//! the transaction has mutated on this host, and `POST /transact/rollback` with a map like
+//! `{"savepoint": <name>}` undoes every mutation made since then without aborting the transaction,
+//! so that a long, multi-step op can recover from a late error. A savepoint only covers the data
+//! on this host, and is discarded when the transaction ends.
+
+use safecast::TryCastFrom;
+
+use tc_error::*;
+use tc_value::Value;
+use tcgeneric::{label, path_label, Id, Label, Map, PathLabel};
+
+use crate::state::State;
+use crate::txn::Txn;
+
+use super::Kernel;
+
+/// The path at which to save the state of a transaction.
+pub const SAVEPOINT: PathLabel = path_label(&["transact", "savepoint"]);
+
+/// The path at which to roll back a transaction to a savepoint.
+pub const ROLLBACK: PathLabel = path_label(&["transact", "rollback"]);
+
+const NAME: Label = label("savepoint");
+
+impl Kernel {
+    /// Save the current state of the given [`Txn`] as the savepoint named by the given `key`.
+    pub(super) async fn save(&self, txn: &Txn, key: Value, value: State) -> TCResult<()> {
+        if !value.is_none() {
+            return Err(TCError::bad_request("a savepoint has no value, not", value));
+        }
+
+        let name = savepoint_name(key)?;
+        txn.save(name).await
+    }
+
+    /// Roll back the given [`Txn`] to the savepoint named in the given `params`.
+    pub(super) async fn rollback_to(&self, txn: &Txn, mut params: Map<State>) -> TCResult<()> {
+        let name: Value = params.require(&NAME.into())?;
+        params.expect_empty()?;
+
+        let name = savepoint_name(name)?;
+        txn.rollback_to_saved(&name).await
+    }
+}
+
+fn savepoint_name(name: Value) -> TCResult<Id> {
+    Id::try_cast_from(name, |v| TCError::bad_request("invalid savepoint name", v))
+}
@@ -0,0 +1,646 @@
+//! Recurring jobs, scheduled with a cron-style schedule and served by the [`Kernel`] at
+//! [`SCHEDULE`].
+//!
+//! `PUT /schedule/<name>` registers a job, whose key is its schedule and whose value is the
+//! reference (e.g. an `OpRef`) to resolve on that schedule. `GET /schedule` lists the registered
+//! jobs, `GET /schedule/<name>` describes a job and the outcome of its last run, and
+//! `DELETE /schedule/<name>` cancels a job. Only a host administrator (see
+//! [`super::SCOPE_ADMIN`]) can register or cancel a job.
+//!
+//! A schedule has five whitespace-separated fields: minute (0-59), hour (0-23), day of the month
+//! (1-31), month (1-12), and day of the week (0-7, where both 0 and 7 mean Sunday). Each field is
+//! either `*` or a comma-separated list of values and ranges like `1-5`, optionally followed by a
+//! step like `*/15`. As in cron, if both the day of the month and the day of the week are
+//! restricted then a job runs on a day which matches either one. The aliases `@hourly`, `@daily`,
+//! `@weekly`, `@monthly`, and `@yearly` are also supported. Schedules are evaluated in UTC.
+//!
+//! Each run of a job is executed in its own transaction by the [`Gateway`], authorized by the
+//! token of the request which registered the job, so a job can do no more than the administrator
+//! who registered it, and stops running successfully once that token expires.
+//!
+//! On a host with a data directory, the jobs are stored in a system [`Cluster`] at `/schedule`,
+//! so that registering or cancelling a job commits or rolls back with the transaction which
+//! requested it. The outcome of each run is kept in memory.
+//!
+//! [`Gateway`]: crate::gateway::Gateway
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use bytes::Bytes;
+use futures::{future, stream, TryStreamExt};
+use log::debug;
+use safecast::*;
+use tokio::sync::RwLock;
+
+use tc_btree::Column;
+use tc_error::*;
+use tc_table::{IndexSchema, TableSchema, TableStream};
+use tc_transact::Transaction;
+use tc_value::{DateTime, Link, LinkHost, TCString, Value, ValueType};
+use tcgeneric::*;
+
+use crate::chain::{ChainInstance, ChainType, Subject};
+use crate::cluster::{self, Cluster, PREPARE};
+use crate::collection::TableType;
+use crate::fs;
+use crate::object::{InstanceClass, InstanceExt};
+use crate::route::Public;
+use crate::scalar::{OpRef, OpRefType, Scalar, TCRef};
+use crate::state::State;
+use crate::txn::Txn;
+
+use super::{execute, maybe_claim_leadership};
+
+/// The path of the job scheduler.
+pub const SCHEDULE: PathLabel = path_label(&["schedule"]);
+
+const NANOS_PER_MINUTE: i64 = 60_000_000_000;
+
+const JOBS: Label = label("jobs");
+const NAME: Label = label("name");
+const SCHEDULE_KEY: Label = label("schedule");
+const OP: Label = label("op");
+const TOKEN: Label = label("token");
+const LAST_RUN: Label = label("last_run");
+const RESULT: Label = label("result");
+const ERROR: Label = label("error");
+
+/// A cron-style schedule.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Cron {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// Return `true` if a job with this schedule should run during the minute of the given time.
+    pub fn matches(&self, time: &DateTime) -> bool {
+        let (_, month, day) = time.date();
+        let (hour, minute, _) = time.time();
+        let weekday = time.weekday();
+
+        let day_matches = is_set(self.days, day);
+        let weekday_matches = is_set(self.weekdays, weekday);
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday_matches,
+            (false, true) => day_matches,
+            (false, false) => day_matches || weekday_matches,
+        };
+
+        is_set(self.minutes, minute)
+            && is_set(self.hours, hour)
+            && is_set(self.months, month)
+            && day_matches
+    }
+}
+
+impl FromStr for Cron {
+    type Err = TCError;
+
+    fn from_str(s: &str) -> TCResult<Self> {
+        let expanded = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(TCError::bad_request(
+                "a schedule requires five fields (minute, hour, day, month, weekday), not",
+                s,
+            ));
+        }
+
+        let invalid =
+            |cause: String| TCError::bad_request(format!("invalid schedule {}", s), cause);
+
+        let (minutes, _) = parse_field(fields[0], 0, 59).map_err(invalid)?;
+        let (hours, _) = parse_field(fields[1], 0, 23).map_err(invalid)?;
+        let (days, any_day) = parse_field(fields[2], 1, 31).map_err(invalid)?;
+        let (months, _) = parse_field(fields[3], 1, 12).map_err(invalid)?;
+        let (weekdays, any_weekday) = parse_field(fields[4], 0, 7).map_err(invalid)?;
+
+        // both 0 and 7 mean Sunday
+        let weekdays = if is_set(weekdays, 7) {
+            (weekdays | 1) & !(1 << 7)
+        } else {
+            weekdays
+        };
+
+        Ok(Self {
+            source: s.trim().to_string(),
+            minutes,
+            hours,
+            days,
+            months,
+            weekdays,
+            any_day,
+            any_weekday,
+        })
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Clone)]
+struct Job {
+    cron: Cron,
+    op: Scalar,
+    token: String,
+}
+
+impl Job {
+    // decode a job from a row of the table of jobs, i.e. `[name, schedule, op, token]`
+    async fn from_row(row: Vec<Value>) -> TCResult<(Id, Self)> {
+        let invalid = |v: &Value| TCError::internal(format!("invalid scheduled job: {}", v));
+
+        let mut row = row.into_iter();
+        let mut next = || row.next().ok_or_else(|| invalid(&Value::None));
+
+        let name = Id::try_cast_from(next()?, invalid)?;
+        let cron = TCString::try_cast_from(next()?, invalid)?;
+        let op = TCString::try_cast_from(next()?, invalid)?;
+        let token = TCString::try_cast_from(next()?, invalid)?;
+
+        let job = Self {
+            cron: cron.as_str().parse()?,
+            op: decode_op(op.as_str()).await?,
+            token: token.to_string(),
+        };
+
+        Ok((name, job))
+    }
+
+    async fn into_row(self) -> TCResult<Value> {
+        let op = encode_op(self.op).await?;
+
+        Ok(Value::Tuple(
+            vec![
+                Value::String(self.cron.to_string().into()),
+                Value::String(op.into()),
+                Value::String(self.token.into()),
+            ]
+            .into(),
+        ))
+    }
+
+    // the token which authorizes this job is deliberately left out of its description
+    fn describe(&self, run: Option<&Run>) -> Scalar {
+        let mut job = Map::<Scalar>::new();
+        job.insert(
+            SCHEDULE_KEY.into(),
+            Value::String(self.cron.to_string().into()).into(),
+        );
+        job.insert(OP.into(), self.op.clone());
+
+        let run = if let Some(run) = run {
+            run
+        } else {
+            return Scalar::Map(job);
+        };
+
+        if let Some(last_run) = run.last_run {
+            job.insert(LAST_RUN.into(), Value::DateTime(last_run).into());
+        }
+
+        match &run.outcome {
+            Some(Ok(result)) => {
+                job.insert(RESULT.into(), result.clone());
+            }
+            Some(Err(cause)) => {
+                job.insert(ERROR.into(), Value::String(cause.to_string().into()).into());
+            }
+            None => {}
+        }
+
+        Scalar::Map(job)
+    }
+}
+
+#[derive(Default)]
+struct Run {
+    last_run: Option<DateTime>,
+    outcome: Option<Result<Scalar, String>>,
+}
+
+/// A scheduler of recurring jobs.
+///
+/// A scheduler loaded from a data directory stores its jobs in a system [`Cluster`];
+/// otherwise its jobs are kept in memory.
+#[derive(Default)]
+pub struct Scheduler {
+    cluster: Option<InstanceExt<Cluster>>,
+    jobs: RwLock<BTreeMap<Id, Job>>,
+    runs: RwLock<BTreeMap<Id, Run>>,
+}
+
+impl Scheduler {
+    /// Load a persistent `Scheduler` from the given data directory.
+    pub async fn load(txn: &Txn, host: LinkHost, data_dir: fs::Dir) -> TCResult<Self> {
+        let cluster = cluster::instantiate(txn, host, system_cluster(), data_dir).await?;
+
+        Ok(Self {
+            cluster: Some(cluster),
+            jobs: RwLock::default(),
+            runs: RwLock::default(),
+        })
+    }
+
+    /// Return the system [`Cluster`] which stores the jobs of this `Scheduler`, if any.
+    pub fn cluster(&self) -> Option<&InstanceExt<Cluster>> {
+        self.cluster.as_ref()
+    }
+
+    /// Describe a job, or list the scheduled jobs if the `path` is empty.
+    pub async fn get(&self, txn: &Txn, path: &[PathSegment], key: Value) -> TCResult<State> {
+        key.expect_none()?;
+
+        match path {
+            [] => {
+                let jobs = self.jobs(txn).await?;
+                let names = jobs.into_iter().map(|(name, _)| Value::from(name));
+                Ok(State::from(Value::Tuple(names.collect())))
+            }
+            [name] => {
+                let jobs = self.jobs(txn).await?;
+                let job = jobs
+                    .get(name)
+                    .ok_or_else(|| TCError::not_found(format!("scheduled job {}", name)))?;
+
+                let runs = self.runs.read().await;
+                Ok(State::Scalar(job.describe(runs.get(name))))
+            }
+            _ => Err(TCError::not_found(TCPath::from(path))),
+        }
+    }
+
+    /// Schedule the given job, replacing any existing job with the same name.
+    pub async fn put(
+        &self,
+        txn: &Txn,
+        path: &[PathSegment],
+        key: Value,
+        value: State,
+    ) -> TCResult<()> {
+        let name = match path {
+            [name] => name,
+            _ => {
+                return Err(TCError::method_not_allowed(
+                    OpRefType::Put,
+                    self,
+                    TCPath::from(path),
+                ))
+            }
+        };
+
+        let cron: TCString = key.try_cast_into(|v| TCError::bad_request("invalid schedule", v))?;
+
+        let cron = cron.as_str().parse()?;
+
+        let op = Scalar::try_cast_from(value, |s| {
+            TCError::bad_request("a scheduled job must be a Scalar, not", s)
+        })?;
+
+        if !op.is_ref() {
+            return Err(TCError::bad_request(
+                "a scheduled job must be a reference to resolve, not",
+                op,
+            ));
+        }
+
+        let job = Job {
+            cron,
+            op,
+            token: txn.request().token().to_string(),
+        };
+
+        debug!("schedule job {} to run at {}", name, job.cron);
+
+        if let Some(cluster) = &self.cluster {
+            let key = Value::Tuple(vec![Value::Id(name.clone())].into());
+            let row = job.into_row().await?;
+
+            let txn = maybe_claim_leadership(cluster, txn).await?;
+            execute(txn, cluster, |txn, cluster| async move {
+                let path = [PathSegment::from(JOBS)];
+                cluster.put(&txn, &path, key, row.into()).await
+            })
+            .await?;
+        } else {
+            self.jobs.write().await.insert(name.clone(), job);
+        }
+
+        self.runs.write().await.remove(name);
+
+        Ok(())
+    }
+
+    /// Handle a "prepare" or "commit" instruction addressed to the system [`Cluster`] of this
+    /// `Scheduler` by the owner of a transaction which scheduled or cancelled a job.
+    pub async fn post(
+        &self,
+        txn: &Txn,
+        path: &[PathSegment],
+        params: Map<State>,
+    ) -> TCResult<State> {
+        match (path, &self.cluster) {
+            ([], Some(cluster)) if params.is_empty() || params.contains_key(&PREPARE.into()) => {
+                let txn = maybe_claim_leadership(cluster, txn).await?;
+                cluster.post(&txn, path, params).await
+            }
+            _ => Err(TCError::method_not_allowed(
+                OpRefType::Post,
+                self,
+                TCPath::from(path),
+            )),
+        }
+    }
+
+    /// Cancel the job with the given name.
+    ///
+    /// A `DELETE` request with an empty `path` is a rollback instruction for the system
+    /// [`Cluster`] of this `Scheduler`.
+    pub async fn delete(&self, txn: &Txn, path: &[PathSegment], key: Value) -> TCResult<()> {
+        key.expect_none()?;
+
+        let name = match (path, &self.cluster) {
+            ([], Some(cluster)) => return cluster.delete(txn, path, key).await,
+            ([name], _) => name,
+            _ => {
+                return Err(TCError::method_not_allowed(
+                    OpRefType::Delete,
+                    self,
+                    TCPath::from(path),
+                ))
+            }
+        };
+
+        if !self.jobs(txn).await?.contains_key(name) {
+            return Err(TCError::not_found(format!("scheduled job {}", name)));
+        }
+
+        if let Some(cluster) = &self.cluster {
+            let key = Value::Tuple(vec![Value::Id(name.clone())].into());
+
+            let txn = maybe_claim_leadership(cluster, txn).await?;
+            execute(txn, cluster, |txn, cluster| async move {
+                let path = [PathSegment::from(JOBS)];
+                cluster.delete(&txn, &path, key).await
+            })
+            .await?;
+        } else {
+            self.jobs.write().await.remove(name);
+        }
+
+        self.runs.write().await.remove(name);
+
+        Ok(())
+    }
+
+    /// Return the name, op, and auth token of each job due to run during the minute of the given
+    /// time, as of the given [`Txn`], marking each one as having run at that time.
+    ///
+    /// A job is never returned twice for the same minute.
+    pub async fn due(&self, txn: &Txn, now: DateTime) -> TCResult<Vec<(Id, Scalar, String)>> {
+        let minute = start_of_minute(&now);
+        let jobs = self.jobs(txn).await?;
+        let mut runs = self.runs.write().await;
+
+        let due = jobs
+            .into_iter()
+            .filter(|(_, job)| job.cron.matches(&now))
+            .filter(|(name, _)| {
+                runs.get(name)
+                    .and_then(|run| run.last_run)
+                    .map(|last_run| start_of_minute(&last_run) < minute)
+                    .unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(due
+            .into_iter()
+            .map(|(name, job)| {
+                runs.entry(name.clone()).or_default().last_run = Some(now);
+                (name, job.op, job.token)
+            })
+            .collect())
+    }
+
+    /// Record the outcome of the run of the given job which started at `started`.
+    ///
+    /// The outcome is discarded if the job has since been cancelled or replaced.
+    pub async fn record(&self, name: &Id, started: DateTime, result: TCResult<State>) {
+        let mut runs = self.runs.write().await;
+        let run = match runs.get_mut(name) {
+            Some(run) if run.last_run == Some(started) => run,
+            _ => return,
+        };
+
+        run.outcome = Some(match result {
+            Ok(state) => Ok(Scalar::opt_cast_from(state.clone())
+                .unwrap_or_else(|| Value::String(state.to_string().into()).into())),
+            Err(cause) => Err(cause.to_string()),
+        });
+    }
+
+    async fn jobs(&self, txn: &Txn) -> TCResult<BTreeMap<Id, Job>> {
+        let cluster = if let Some(cluster) = &self.cluster {
+            cluster
+        } else {
+            return Ok(self.jobs.read().await.clone());
+        };
+
+        let table = match cluster.chain(&JOBS.into()).map(|chain| chain.subject()) {
+            Some(Subject::Table(table)) => table.clone(),
+            _ => return Err(TCError::internal("the job scheduler has no table of jobs")),
+        };
+
+        let rows = table.rows(*txn.id()).await?;
+        rows.and_then(Job::from_row).try_collect().await
+    }
+}
+
+impl fmt::Display for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("job scheduler")
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<(u64, bool), String> {
+    let mut bits = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step {}", step))?;
+
+                if step == 0 {
+                    return Err("step cannot be zero".to_string());
+                }
+
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let parse = |n: &str| -> Result<u32, String> {
+            match n.parse() {
+                Ok(n) if n >= min && n <= max => Ok(n),
+                _ => Err(format!("{} is not in the range {}-{}", n, min, max)),
+            }
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start > end {
+                return Err(format!("invalid range {}", range));
+            }
+
+            (start, end)
+        } else {
+            let start = parse(range)?;
+            (start, if step.is_some() { max } else { start })
+        };
+
+        let step = step.unwrap_or(1) as usize;
+        for i in (start..=end).step_by(step) {
+            bits |= 1 << i;
+        }
+    }
+
+    Ok((bits, field == "*"))
+}
+
+#[inline]
+fn is_set(bits: u64, i: u32) -> bool {
+    bits & (1 << i) != 0
+}
+
+fn start_of_minute(time: &DateTime) -> DateTime {
+    let nanos = time.as_nanos();
+    DateTime::from_nanos(nanos - nanos.rem_euclid(NANOS_PER_MINUTE))
+}
+
+// the definition of the system cluster which stores the table of scheduled jobs
+fn system_cluster() -> InstanceClass {
+    let key = vec![Column::from((NAME.into(), ValueType::Id))];
+    let values = vec![
+        Column::from((SCHEDULE_KEY.into(), ValueType::String)),
+        Column::from((OP.into(), ValueType::String)),
+        Column::from((TOKEN.into(), ValueType::String)),
+    ];
+
+    let schema = TableSchema::from(IndexSchema::from((key, values)));
+    let table = OpRef::Get((
+        TableType::default().path().into(),
+        Value::cast_from(schema).into(),
+    ));
+
+    let chain = OpRef::Get((
+        ChainType::Sync.path().into(),
+        Scalar::Ref(Box::new(TCRef::Op(table))),
+    ));
+
+    let mut proto = Map::new();
+    proto.insert(JOBS.into(), Scalar::Ref(Box::new(TCRef::Op(chain))));
+
+    let link = Link::from(TCPathBuf::from(SCHEDULE));
+    InstanceClass::new(Some(link), proto)
+}
+
+async fn encode_op(op: Scalar) -> TCResult<String> {
+    let json = destream_json::encode(op).map_err(TCError::internal)?;
+    let json = json
+        .map_err(TCError::internal)
+        .try_fold(Vec::new(), |mut buffer, chunk| {
+            buffer.extend_from_slice(&chunk);
+            future::ready(Ok(buffer))
+        })
+        .await?;
+
+    String::from_utf8(json).map_err(TCError::internal)
+}
+
+async fn decode_op(json: &str) -> TCResult<Scalar> {
+    let json = Bytes::from(json.to_string());
+    destream_json::decode((), stream::once(future::ready(json)))
+        .await
+        .map_err(|e| TCError::internal(format!("invalid scheduled job: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cron() {
+        let cron: Cron = "*/15 9-17 * * 1-5".parse().unwrap();
+        assert!(cron.matches(&at("2021-06-30T09:45:00Z")));
+        assert!(!cron.matches(&at("2021-06-30T09:50:00Z")));
+        assert!(!cron.matches(&at("2021-06-30T18:00:00Z")));
+        assert!(!cron.matches(&at("2021-07-03T09:45:00Z")));
+
+        let cron: Cron = "0 0 1 * 7".parse().unwrap();
+        assert!(cron.matches(&at("2021-06-01T00:00:00Z")));
+        assert!(cron.matches(&at("2021-07-04T00:00:00Z")));
+        assert!(!cron.matches(&at("2021-07-05T00:00:00Z")));
+
+        let cron: Cron = "@daily".parse().unwrap();
+        assert!(cron.matches(&at("2021-06-30T00:00:59Z")));
+        assert!(!cron.matches(&at("2021-06-30T00:01:00Z")));
+
+        for invalid in &["", "* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *"] {
+            assert!(Cron::from_str(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_row() {
+        let link = Link::from(TCPathBuf::from(SCHEDULE));
+        let op = OpRef::Get((link.into(), Value::None.into()));
+
+        let job = Job {
+            cron: "@hourly".parse().unwrap(),
+            op: Scalar::Ref(Box::new(TCRef::Op(op))),
+            token: "token".to_string(),
+        };
+
+        let description = job.describe(None).to_string();
+        assert!(!description.contains("token"), "{}", description);
+
+        let mut row = vec![Value::Id(label("nightly").into())];
+        match job.clone().into_row().await.unwrap() {
+            Value::Tuple(values) => row.extend(values.into_inner()),
+            other => panic!("invalid row values: {}", other),
+        }
+
+        let (name, loaded) = Job::from_row(row).await.unwrap();
+        assert_eq!(name.as_str(), "nightly");
+        assert_eq!(loaded.cron.to_string(), job.cron.to_string());
+        assert_eq!(loaded.op.to_string(), job.op.to_string());
+        assert_eq!(loaded.token, job.token);
+    }
+}
@@ -0,0 +1,183 @@
+//! Tenant namespaces, which isolate the clusters hosted for different tenants of a shared host.
+//!
+//! A [`Namespace`] reserves a path prefix, like `/team_a`, for the tenant whose auth tokens are
+//! issued by a given host, like `http://auth.example.com/team_a`. Only a request whose token
+//! includes a claim by that issuer can see a cluster hosted under the prefix; to any other request,
+//! the cluster does not exist. The claims of a cluster in the same
+//! namespace, whether on this host or on one of its replicas, are accepted as the tenant's own, so
+//! that a cluster can call the other clusters of its tenant.
+//!
+//! A path outside every namespace is shared by every tenant, as on a host with no namespaces, but
+//! only a host administrator can install a cluster there (cf. [`SCOPE_ADMIN`]). A tenant can only
+//! install a cluster in its own namespace. A scheduled job runs with the credentials of the
+//! request which scheduled it, so it can only call the clusters which that request could see.
+//!
+//! [`SCOPE_ADMIN`]: super::SCOPE_ADMIN
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use log::debug;
+
+use tc_error::*;
+use tc_transact::Transaction;
+use tc_value::Link;
+use tcgeneric::{PathSegment, TCPath, TCPathBuf};
+
+use crate::cluster::Cluster;
+use crate::object::InstanceExt;
+use crate::txn::Txn;
+
+use super::Kernel;
+
+/// A path prefix reserved for the tenant whose auth tokens are issued by a given host.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Namespace {
+    prefix: TCPathBuf,
+    issuer: Link,
+}
+
+impl Namespace {
+    /// Reserve the given path `prefix` for the tenant whose tokens are issued by `issuer`.
+    pub fn new(prefix: TCPathBuf, issuer: Link) -> TCResult<Self> {
+        if prefix.is_empty() {
+            Err(TCError::bad_request(
+                "a tenant namespace cannot contain every path, but found prefix",
+                prefix,
+            ))
+        } else {
+            Ok(Self { prefix, issuer })
+        }
+    }
+
+    /// Return the path prefix reserved by this namespace.
+    pub fn prefix(&self) -> &TCPathBuf {
+        &self.prefix
+    }
+
+    /// Return `true` if the given `path` is in this namespace.
+    pub fn contains(&self, path: &[PathSegment]) -> bool {
+        path.starts_with(&self.prefix[..])
+    }
+}
+
+impl FromStr for Namespace {
+    type Err = TCError;
+
+    /// Parse a `Namespace` of the form `<prefix>=<issuer>`, e.g. `/team_a=http://auth.example.com`.
+    fn from_str(namespace: &str) -> TCResult<Self> {
+        let (prefix, issuer) = namespace.split_once('=').ok_or_else(|| {
+            TCError::bad_request(
+                "expected a tenant namespace like <prefix>=<issuer>, not",
+                namespace,
+            )
+        })?;
+
+        Self::new(prefix.parse()?, issuer.parse()?)
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "namespace {} of {}", self.prefix, self.issuer)
+    }
+}
+
+impl Kernel {
+    /// Return the most specific tenant [`Namespace`] which contains the given `path`, if any.
+    pub(super) fn namespace(&self, path: &[PathSegment]) -> Option<&Namespace> {
+        self.namespaces
+            .iter()
+            .filter(|namespace| namespace.contains(path))
+            .max_by_key(|namespace| namespace.prefix.len())
+    }
+
+    /// Return `true` if the given [`Txn`] may see the cluster hosted at the given `path`.
+    pub(super) async fn is_visible(&self, txn: &Txn, path: &[PathSegment]) -> bool {
+        if let Some(namespace) = self.namespace(path) {
+            let visible = self.is_tenant(txn, namespace).await;
+            if !visible {
+                debug!(
+                    "{} is not visible outside its {}",
+                    TCPath::from(path),
+                    namespace
+                );
+            }
+
+            visible
+        } else {
+            true
+        }
+    }
+
+    /// Return the hosted clusters which the given [`Txn`] may see.
+    pub async fn visible(&self, txn: &Txn) -> Vec<Arc<InstanceExt<Cluster>>> {
+        let mut visible = Vec::new();
+        for cluster in self.hosted() {
+            if self.is_visible(txn, cluster.path()).await {
+                visible.push(cluster);
+            }
+        }
+
+        visible
+    }
+
+    /// Return an error if the given [`Txn`] may not install a cluster at the given `path`.
+    ///
+    /// A tenant may install a cluster in its own namespace, and a host administrator may install
+    /// a cluster at any path; any other request is denied.
+    pub(super) async fn authorize_install(&self, txn: &Txn, path: &[PathSegment]) -> TCResult<()> {
+        if let Some(namespace) = self.namespace(path) {
+            if self.is_tenant(txn, namespace).await {
+                return Ok(());
+            }
+        }
+
+        if self.is_admin(txn) {
+            Ok(())
+        } else {
+            Err(TCError::unauthorized(format!(
+                "this token cannot install a cluster at {}",
+                TCPath::from(path)
+            )))
+        }
+    }
+
+    async fn is_tenant(&self, txn: &Txn, namespace: &Namespace) -> bool {
+        for (host, _actor_id, _scopes) in txn.request().scopes().iter() {
+            if host == &namespace.issuer {
+                return true;
+            } else if namespace.contains(host.path()) && self.is_cluster(txn, host).await {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // return `true` if the given `link` is a cluster hosted here, or a replica of one
+    async fn is_cluster(&self, txn: &Txn, link: &Link) -> bool {
+        let root = txn.gateway().root();
+        let remote = match link.host() {
+            None => return true,
+            Some(host) if host == root => return true,
+            Some(host) => host,
+        };
+
+        let cluster = match self.hosted.peek(link.path()) {
+            Some((suffix, cluster)) if suffix.is_empty() => cluster,
+            _ => return false,
+        };
+
+        match cluster.replicas(*txn.id()).await {
+            Ok(replicas) => replicas
+                .iter()
+                .any(|replica| replica.host().as_ref() == Some(remote)),
+            Err(cause) => {
+                debug!("unable to list the replicas of {}: {}", cluster, cause);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Side-by-side versions of a hosted cluster, served by the [`Kernel`] at [`VERSION`].
+//!
+//! A cluster hosted at a path ending in a version number, like `/app/service/v2`, is a version of
+//! the cluster at `/app/service`. A request to `/app/service` which doesn't specify a version is
+//! routed to the default version: the one explicitly chosen, if any, otherwise the latest version
+//! which is not deprecated. A deprecated version continues to serve requests, so that its clients
+//! can migrate to a newer version gradually.
+
+use safecast::TryCastFrom;
+
+use tc_error::*;
+use tc_value::{Link, Value};
+use tcgeneric::{label, path_label, Label, Map, PathLabel, TCPath};
+
+use crate::scalar::OpRefType;
+use crate::state::State;
+use crate::txn::Txn;
+
+use super::Kernel;
+
+/// The path at which to describe and configure the versions of a hosted cluster.
+pub const VERSION: PathLabel = path_label(&["host", "version"]);
+
+const DEFAULT: Label = label("default");
+const DEPRECATED: Label = label("deprecated");
+
+impl Kernel {
+    /// Describe the versions of the cluster at the path of the given [`Link`].
+    pub(super) fn versions(&self, key: Value) -> TCResult<State> {
+        let link = expect_link(key)?;
+        let (default, versions) = self
+            .hosted
+            .versions(link.path())
+            .ok_or_else(|| TCError::not_found(format!("versions of {}", link)))?;
+
+        let versions = versions
+            .into_iter()
+            .map(|cluster| {
+                let mut version = Map::new();
+                version.insert(
+                    label("link").into(),
+                    Value::from(cluster.link().clone()).into(),
+                );
+                version.insert(
+                    DEPRECATED.into(),
+                    Value::from(cluster.is_deprecated()).into(),
+                );
+                State::Map(version)
+            })
+            .collect();
+
+        let mut description = Map::new();
+        description.insert(DEFAULT.into(), Value::from(Link::from(default)).into());
+        description.insert(label("versions").into(), State::Tuple(versions));
+        Ok(State::Map(description))
+    }
+
+    /// Configure the version of a cluster at the path of the given [`Link`],
+    /// by setting its `default` and/or `deprecated` flags.
+    ///
+    /// Only a host administrator can configure the versions of a cluster.
+    pub(super) fn set_version(&self, txn: &Txn, key: Value, value: State) -> TCResult<()> {
+        self.authorize_admin(txn, "configuring the versions of a cluster")?;
+
+        let link = expect_link(key)?;
+        let (suffix, cluster) = self
+            .hosted
+            .peek(link.path())
+            .ok_or_else(|| TCError::not_found(&link))?;
+
+        if !suffix.is_empty() || cluster.path() != &link.path()[..] {
+            return Err(TCError::method_not_allowed(
+                OpRefType::Put,
+                self,
+                TCPath::from(&link.path()[..]),
+            ));
+        }
+
+        let flags =
+            value.try_into_map(|v| TCError::bad_request("invalid version configuration", v))?;
+
+        for (name, flag) in flags {
+            let flag = bool::try_cast_from(flag, |v| {
+                TCError::bad_request(format!("invalid value for {}", name), v)
+            })?;
+
+            if name == DEFAULT {
+                if flag {
+                    self.hosted.set_default(cluster.path())?;
+                } else {
+                    return Err(TCError::unsupported(
+                        "to change the default version, make another version the default",
+                    ));
+                }
+            } else if name == DEPRECATED {
+                cluster.set_deprecated(flag);
+            } else {
+                return Err(TCError::bad_request("unrecognized version setting", name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) fn expect_link(key: Value) -> TCResult<Link> {
+    Link::try_cast_from(key, |v| {
+        TCError::bad_request("expected a Link to a hosted cluster, not", v)
+    })
+}
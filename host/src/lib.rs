@@ -13,24 +13,34 @@ pub use kernel::*;
 pub use tc_btree as btree;
 pub use tc_error as error;
 pub use tc_table as table;
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 pub use tc_tensor as tensor;
 pub use tc_transact as transact;
 pub use tc_value as value;
 pub use tcgeneric as generic;
 
+mod admin;
+mod graphql;
 mod http;
+mod pg;
 
+pub mod admission;
+pub mod balance;
+pub mod bench;
 pub mod chain;
 pub mod closure;
 pub mod cluster;
 pub mod collection;
 pub mod fs;
 pub mod gateway;
+pub mod import;
 pub mod kernel;
+pub mod metrics;
 pub mod object;
 pub mod route;
 pub mod scalar;
+pub mod shard;
 pub mod state;
 pub mod stream;
+pub mod trace;
 pub mod txn;
@@ -1,6 +1,7 @@
 use std::net::IpAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use bytes::Bytes;
 use destream::de::FromStream;
@@ -11,9 +12,13 @@ use tokio::time::Duration;
 use tc_error::*;
 use tc_transact::{Transact, TxnId};
 
-use tc_value::{LinkHost, LinkProtocol};
-use tinychain::gateway::Gateway;
+use tc_value::{Link, LinkHost, LinkProtocol};
+use tinychain::admission::AdmissionPolicy;
+use tinychain::balance::BalancePolicy;
+use tinychain::chain::CompactionPolicy;
+use tinychain::gateway::{Gateway, RequestLimits};
 use tinychain::object::InstanceClass;
+use tinychain::txn::FinalizePolicy;
 use tinychain::*;
 
 type TokioError = Box<dyn std::error::Error + Send + Sync + 'static>;
@@ -47,8 +52,20 @@ fn duration(flag: &str) -> TCResult<Duration> {
         .map_err(|_| TCError::bad_request("invalid duration", flag))
 }
 
+fn duration_ms(flag: &str) -> TCResult<Duration> {
+    u64::from_str(flag)
+        .map(Duration::from_millis)
+        .map_err(|_| TCError::bad_request("invalid duration in milliseconds", flag))
+}
+
 #[derive(Clone, StructOpt)]
 struct Config {
+    #[structopt(
+        long = "config",
+        about = "path to a TOML file of options, which is reloaded on SIGHUP or PUT /config"
+    )]
+    pub config: Option<PathBuf>,
+
     #[structopt(
         long = "address",
         default_value = "0.0.0.0",
@@ -69,15 +86,96 @@ struct Config {
     #[structopt(long = "cache_size", default_value = "1G", parse(try_from_str = data_size))]
     pub cache_size: u64,
 
+    #[structopt(
+        long = "cache_limit",
+        default_value = "0",
+        parse(try_from_str = data_size),
+        about = "hard limit on the size of the block cache, at least --cache_size (disabled by default)"
+    )]
+    pub cache_limit: u64,
+
+    #[structopt(
+        long = "cache_cleanup_interval",
+        default_value = "1000",
+        parse(try_from_str = duration_ms),
+        about = "interval in milliseconds at which to evict blocks from the cache when it's full"
+    )]
+    pub cache_cleanup_interval: Duration,
+
+    #[structopt(
+        long = "flush_interval",
+        default_value = "0",
+        parse(try_from_str = duration_ms),
+        about = "interval in milliseconds at which to write back dirty blocks in the background (disabled by default)"
+    )]
+    pub flush_interval: Duration,
+
     #[structopt(
         long = "data_dir",
         about = "data directory (required to host a Cluster)"
     )]
     pub data_dir: Option<PathBuf>,
 
+    #[structopt(
+        long = "backup_dir",
+        about = "directory in which to store online backups of the data directory (disabled by default)"
+    )]
+    pub backup_dir: Option<PathBuf>,
+
+    #[structopt(
+        long = "restore",
+        about = "path to a backup to restore to the data directory, which must be empty, at startup"
+    )]
+    pub restore: Option<PathBuf>,
+
+    #[structopt(
+        long = "wal",
+        about = "log committed blocks to a write-ahead log in the data directory, and write them back at a checkpoint"
+    )]
+    pub wal: bool,
+
+    #[structopt(
+        long = "wal_sync",
+        default_value = "10ms",
+        about = "when to sync the write-ahead log to disk: \"always\", \"os\", or an interval like \"10ms\""
+    )]
+    pub wal_sync: tinychain::fs::SyncPolicy,
+
+    #[structopt(
+        long = "dedup",
+        about = "store identical blocks in the data directory only once (requires hard links)"
+    )]
+    pub dedup: bool,
+
     #[structopt(long = "cluster", about = "path(s) to Cluster config files")]
     pub clusters: Vec<PathBuf>,
 
+    #[structopt(
+        long = "tenant",
+        about = "a tenant namespace like /team_a=http://auth.example.com, whose clusters only the tenant's tokens can see or install"
+    )]
+    pub tenants: Vec<tinychain::Namespace>,
+
+    #[structopt(
+        long = "host_admin",
+        about = "an issuer trusted to grant the scope /host/admin, required to install a cluster or change the host configuration"
+    )]
+    pub host_admins: Vec<Link>,
+
+    #[structopt(
+        long = "drain_timeout",
+        default_value = "10",
+        parse(try_from_str = duration),
+        about = "maximum time in seconds to wait for the transactions in progress at a cluster to finish before reloading it"
+    )]
+    pub drain_timeout: Duration,
+
+    #[structopt(
+        long = "schema_registry",
+        about = "directory in which to persist the schema registry (in-memory by default)"
+    )]
+    pub schema_registry: Option<PathBuf>,
+
     #[structopt(
         long = "request_ttl",
         default_value = "30",
@@ -86,8 +184,212 @@ struct Config {
     )]
     pub request_ttl: Duration,
 
+    #[structopt(
+        long = "max_request_size",
+        about = "maximum allowed size in bytes of a request body (unlimited by default)"
+    )]
+    pub max_request_size: Option<u64>,
+
+    #[structopt(
+        long = "collection_request_ttl",
+        default_value = "600",
+        parse(try_from_str = duration),
+        about = "maximum allowed duration of a request which writes to a collection (e.g. an upload)"
+    )]
+    pub collection_request_ttl: Duration,
+
+    #[structopt(
+        long = "collection_max_request_size",
+        about = "maximum allowed size in bytes of a request which writes to a collection (unlimited by default)"
+    )]
+    pub collection_max_request_size: Option<u64>,
+
+    #[structopt(
+        long = "txn_horizon",
+        default_value = "3",
+        parse(try_from_str = duration),
+        about = "time to wait after a transaction expires before reclaiming its block versions"
+    )]
+    pub txn_horizon: Duration,
+
+    #[structopt(
+        long = "txn_gc_interval",
+        default_value = "100",
+        parse(try_from_str = duration_ms),
+        about = "interval in milliseconds at which to finalize expired transactions"
+    )]
+    pub txn_gc_interval: Duration,
+
+    #[structopt(
+        long = "txn_lock_timeout",
+        default_value = "5000",
+        parse(try_from_str = duration_ms),
+        about = "maximum time in milliseconds to wait for a transactional lock before failing with a conflict error"
+    )]
+    pub txn_lock_timeout: Duration,
+
+    #[structopt(
+        long = "op_parallelism",
+        default_value = "0",
+        about = "maximum number of providers in an Op to resolve concurrently (0 means the number of CPUs)"
+    )]
+    pub op_parallelism: usize,
+
+    #[structopt(
+        long = "chain_retain_blocks",
+        about = "compact each BlockChain down to its most recent N blocks (disabled by default)"
+    )]
+    pub chain_retain_blocks: Option<u64>,
+
+    #[structopt(
+        long = "chain_compaction_interval",
+        default_value = "3600",
+        parse(try_from_str = duration),
+        about = "interval in seconds at which to compact BlockChains"
+    )]
+    pub chain_compaction_interval: Duration,
+
+    #[structopt(
+        long = "max_concurrent_requests",
+        about = "the maximum number of inbound requests to execute concurrently (unlimited by default)"
+    )]
+    pub max_concurrent_requests: Option<usize>,
+
+    #[structopt(
+        long = "max_queued_requests",
+        default_value = "1024",
+        about = "the maximum number of inbound requests to queue before responding with 429 Too Many Requests"
+    )]
+    pub max_queued_requests: usize,
+
+    #[structopt(
+        long = "rate_limit",
+        about = "the maximum sustained number of requests per second from a single client (unlimited by default)"
+    )]
+    pub rate_limit: Option<f64>,
+
+    #[structopt(
+        long = "rate_limit_burst",
+        default_value = "100",
+        about = "the maximum number of requests a single client can make at once without being rate-limited"
+    )]
+    pub rate_limit_burst: u32,
+
+    #[structopt(
+        long = "balance_reads",
+        about = "balance reads of a remote cluster across its healthy replicas"
+    )]
+    pub balance_reads: bool,
+
+    #[structopt(
+        long = "tensor_switch_representation",
+        about = "materialize each Tensor as dense or sparse according to its density"
+    )]
+    pub tensor_switch_representation: bool,
+
+    #[structopt(
+        long = "tensor_dense_above",
+        default_value = "0.5",
+        about = "materialize a sparse Tensor as dense if more than this fraction of its elements are nonzero"
+    )]
+    pub tensor_dense_above: f64,
+
+    #[structopt(
+        long = "tensor_sparse_below",
+        default_value = "0.1",
+        about = "materialize a dense Tensor as sparse if no more than this fraction of its elements are nonzero"
+    )]
+    pub tensor_sparse_below: f64,
+
+    #[structopt(
+        long = "tensor_backend",
+        default_value = "default",
+        about = "the ArrayFire backend with which to compute Tensor math: default, cpu, cuda, or opencl"
+    )]
+    pub tensor_backend: String,
+
+    #[structopt(
+        long = "tensor_device",
+        default_value = "0",
+        about = "the index of the device to use with the ArrayFire backend"
+    )]
+    pub tensor_device: i32,
+
+    #[structopt(
+        long = "health_check_interval",
+        default_value = "10",
+        parse(try_from_str = duration),
+        about = "interval in seconds at which to check the health of each known replica"
+    )]
+    pub health_check_interval: Duration,
+
     #[structopt(long = "http_port", default_value = "8702")]
     pub http_port: u16,
+
+    #[structopt(
+        long = "pg_port",
+        about = "port for the read-only Postgres wire protocol listener (disabled by default)"
+    )]
+    pub pg_port: Option<u16>,
+
+    #[structopt(
+        long = "graphql",
+        about = "enable the read-only GraphQL endpoint at /graphql"
+    )]
+    pub graphql: bool,
+
+    #[structopt(
+        long = "admin",
+        about = "enable the read-only admin dashboard at /admin"
+    )]
+    pub admin: bool,
+
+    #[structopt(
+        long = "record",
+        about = "append the sanitized envelope of each inbound request to this trace log"
+    )]
+    pub record: Option<PathBuf>,
+
+    #[structopt(
+        long = "replay",
+        about = "replay the given trace log against --replay_target instead of starting a host"
+    )]
+    pub replay: Option<PathBuf>,
+
+    #[structopt(
+        long = "replay_target",
+        default_value = "http://127.0.0.1:8702",
+        about = "the host to replay a trace log against"
+    )]
+    pub replay_target: url::Url,
+
+    #[structopt(
+        long = "replay_speed",
+        default_value = "1.0",
+        about = "the speed at which to replay a trace log, relative to the recording"
+    )]
+    pub replay_speed: f64,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Clone, StructOpt)]
+enum Command {
+    #[structopt(
+        about = "run the built-in benchmark workloads and print the results as JSON lines"
+    )]
+    Bench {
+        #[structopt(long = "workload", about = "the workload(s) to run (default: all)")]
+        workloads: Vec<bench::Workload>,
+
+        #[structopt(
+            long = "size",
+            default_value = "10000",
+            about = "the number of elements in each workload"
+        )]
+        size: u64,
+    },
 }
 
 impl Config {
@@ -95,18 +397,157 @@ impl Config {
         gateway::Config {
             addr: self.address,
             http_port: self.http_port,
+            pg_port: self.pg_port,
+            graphql: self.graphql,
+            admin: self.admin,
+            admission: self.admission_policy(),
+            balance: self.balance_policy(),
+            compaction: self.compaction_policy(),
+            #[cfg(feature = "tc-tensor")]
+            representation: self.representation_policy(),
+            record: self.record.clone(),
             request_ttl: self.request_ttl,
+            max_request_size: self.max_request_size,
+            collection_limits: RequestLimits {
+                max_size: self.collection_max_request_size,
+                ttl: self.collection_request_ttl,
+            },
+            cache_size: self.cache_size as usize,
         }
     }
+
+    fn admission_policy(&self) -> Option<AdmissionPolicy> {
+        if self.max_concurrent_requests.is_some() || self.rate_limit.is_some() {
+            Some(AdmissionPolicy {
+                max_concurrent: self.max_concurrent_requests,
+                max_queued: self.max_queued_requests,
+                rate_limit: self.rate_limit,
+                burst: self.rate_limit_burst,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn balance_policy(&self) -> Option<BalancePolicy> {
+        if self.balance_reads {
+            Some(BalancePolicy {
+                interval: self.health_check_interval,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn compaction_policy(&self) -> Option<CompactionPolicy> {
+        self.chain_retain_blocks.map(|retain| CompactionPolicy {
+            retain,
+            interval: self.chain_compaction_interval,
+        })
+    }
+
+    #[cfg(feature = "tc-tensor")]
+    fn representation_policy(&self) -> Option<tc_tensor::RepresentationPolicy> {
+        if self.tensor_switch_representation {
+            Some(tc_tensor::RepresentationPolicy {
+                dense_above: self.tensor_dense_above,
+                sparse_below: self.tensor_sparse_below,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn finalize_policy(&self) -> FinalizePolicy {
+        FinalizePolicy {
+            horizon: self.txn_horizon,
+            interval: self.txn_gc_interval,
+        }
+    }
+
+    fn log_level(&self) -> TCResult<log::LevelFilter> {
+        self.log_level
+            .parse()
+            .map_err(|_| TCError::bad_request("invalid log level", &self.log_level))
+    }
+
+    fn cache_limit(&self) -> TCResult<usize> {
+        let cache_limit = self.cache_limit as usize;
+        if cache_limit > 0 && cache_limit < self.cache_size as usize {
+            Err(TCError::bad_request(
+                "the cache limit must be at least",
+                self.cache_size,
+            ))
+        } else {
+            Ok(cache_limit)
+        }
+    }
+
+    /// Apply the options which can be adjusted while the host is running.
+    fn reload(&self) -> TCResult<()> {
+        let log_level = self.log_level()?;
+        let cache_limit = self.cache_limit()?;
+
+        // the RUST_LOG environment variable, if set, takes precedence over the log level
+        if std::env::var_os(env_logger::DEFAULT_FILTER_ENV).is_none() {
+            log::set_max_level(log_level);
+        }
+
+        tinychain::fs::set_cache_budget(cache_limit, self.request_ttl);
+        tc_transact::lock::set_max_wait(self.txn_lock_timeout);
+        tinychain::scalar::set_op_parallelism(self.op_parallelism);
+        Ok(())
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), TokioError> {
+fn load_config() -> Result<Config, TokioError> {
     let config = Config::from_args();
+
+    if let Some(path) = &config.config {
+        let cli = std::env::args().collect::<Vec<String>>();
+        let args = ConfigFile::args(path, &cli)?;
+        Ok(Config::from_iter_safe(args)?)
+    } else {
+        Ok(config)
+    }
+}
+
+fn main() -> Result<(), TokioError> {
+    let config = load_config()?;
+
+    if std::env::var_os(env_logger::DEFAULT_FILTER_ENV).is_some() {
+        env_logger::Builder::from_env(env_logger::Env::default()).init();
+    } else {
+        // filter only by the maximum log level, so that it can be reloaded
+        env_logger::Builder::new()
+            .filter_level(log::LevelFilter::Trace)
+            .init();
+
+        log::set_max_level(config.log_level()?);
+    }
+
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    runtime.enable_all();
+
+    #[cfg(feature = "tc-tensor")]
+    {
+        let backend = config.tensor_backend.parse()?;
+        tc_tensor::init_backend(backend, config.tensor_device)?;
+        runtime.on_thread_start(tc_tensor::activate_backend);
+    }
+
+    runtime.build()?.block_on(run(config))
+}
+
+async fn run(config: Config) -> Result<(), TokioError> {
     let gateway_config = config.gateway();
+    let finalize_policy = config.finalize_policy();
 
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(config.log_level))
-        .init();
+    if let Some(trace) = &config.replay {
+        let report = trace::replay(trace, &config.replay_target, config.replay_speed).await?;
+        print!("{}", report);
+        return Ok(());
+    }
 
     if !config.workspace.exists() {
         log::info!(
@@ -122,42 +563,114 @@ async fn main() -> Result<(), TokioError> {
         return Err(TCError::bad_request("the minimum cache size is", MIN_CACHE_SIZE).into());
     }
 
-    let cache = freqfs::Cache::new(config.cache_size as usize, Duration::from_secs(1));
+    let cache_limit = config.cache_limit()?;
+
+    tinychain::fs::set_cache_budget(cache_limit, config.request_ttl);
+    tc_transact::lock::set_max_wait(config.txn_lock_timeout);
+    tinychain::scalar::set_op_parallelism(config.op_parallelism);
+    tinychain::fs::start_flusher(config.flush_interval);
+
+    let cache = freqfs::Cache::new(cache_size, config.cache_cleanup_interval);
     let workspace = cache.clone().load(config.workspace).await?;
     let txn_id = TxnId::new(Gateway::time());
 
+    let backups = match (&config.data_dir, config.backup_dir) {
+        (Some(data_dir), Some(backup_dir)) => {
+            std::fs::create_dir_all(&backup_dir)?;
+            Some(tinychain::Backups::new(data_dir.clone(), backup_dir))
+        }
+        (None, Some(_)) => {
+            return Err(TCError::internal("the --backup_dir option requires a --data_dir").into())
+        }
+        (_, None) => None,
+    };
+
     let data_dir = if let Some(data_dir) = config.data_dir {
         if !data_dir.exists() {
             panic!("{:?} does not exist--create it or provide a different path for the --data_dir flag", data_dir);
         }
 
+        if let Some(backup) = config.restore {
+            let manifest = tinychain::fs::restore(&backup, &data_dir).await?;
+            log::info!(
+                "restored {} files from {:?} as of {}",
+                manifest.files.len(),
+                backup,
+                manifest.txn_id
+            );
+        }
+
+        tinychain::fs::recover(&data_dir).await?;
+
+        if config.wal {
+            tinychain::fs::open_wal(&data_dir, config.wal_sync).await?;
+        }
+
+        if config.dedup {
+            tinychain::fs::enable_dedup(&data_dir).await?;
+        }
+
         let data_dir = cache.load(data_dir).await?;
         tinychain::fs::Dir::load(data_dir, txn_id)
             .map_ok(Some)
             .await?
+    } else if config.restore.is_some() {
+        return Err(TCError::internal("the --restore option requires a --data_dir").into());
+    } else if config.wal {
+        return Err(TCError::internal("the --wal option requires a --data_dir").into());
+    } else if config.dedup {
+        return Err(TCError::internal("the --dedup option requires a --data_dir").into());
     } else {
         None
     };
 
-    #[cfg(feature = "tensor")]
+    #[cfg(feature = "tc-tensor")]
     {
         tc_tensor::print_af_info();
         println!();
     }
 
-    let txn_server = tinychain::txn::TxnServer::new(workspace).await;
+    let txn_server =
+        tinychain::txn::TxnServer::new(workspace, data_dir.clone(), finalize_policy).await;
+
+    if let Some(Command::Bench { workloads, size }) = config.command {
+        let workloads = if workloads.is_empty() {
+            bench::Workload::all()
+        } else {
+            workloads
+        };
+
+        let kernel = tinychain::Kernel::new(std::iter::empty());
+        let gateway = Gateway::new(gateway_config, kernel, txn_server.clone());
+
+        for workload in workloads {
+            let txn_id = TxnId::new(Gateway::time());
+            let token = gateway.new_token(&txn_id)?;
+            let txn = txn_server.new_txn(gateway.clone(), txn_id, token).await?;
+
+            log::info!("running benchmark workload {} with size {}", workload, size);
+            let measurement = bench::run(&txn, workload, size).await?;
+            println!("{}", measurement.to_json());
+        }
+
+        return Ok(());
+    }
+    let registry = if let Some(dir) = config.schema_registry {
+        tinychain::Registry::load(dir).await?
+    } else {
+        tinychain::Registry::default()
+    };
+
+    let registry = Arc::new(registry);
+
     let mut clusters = Vec::with_capacity(config.clusters.len());
-    if !config.clusters.is_empty() {
+    let scheduler = if let Some(data_dir) = data_dir.clone() {
         let txn_server = txn_server.clone();
-        let kernel = tinychain::Kernel::new(std::iter::empty());
+        let kernel = tinychain::Kernel::new(std::iter::empty()).with_registry(registry.clone());
         let gateway = Gateway::new(gateway_config.clone(), kernel, txn_server.clone());
         let token = gateway.new_token(&txn_id)?;
         let txn = txn_server.new_txn(gateway, txn_id, token).await?;
 
-        let data_dir = data_dir.ok_or_else(|| {
-            TCError::internal("the --data_dir option is required to host a Cluster")
-        })?;
-
         let host = LinkHost::from((
             LinkProtocol::HTTP,
             config.address.clone(),
@@ -183,10 +696,48 @@ async fn main() -> Result<(), TokioError> {
             clusters.push(cluster);
         }
 
+        let scheduler = tinychain::Scheduler::load(&txn, host, data_dir.clone()).await?;
+
         data_dir.commit(&txn_id).await;
-    }
+        scheduler
+    } else if !config.clusters.is_empty() {
+        return Err(
+            TCError::internal("the --data_dir option is required to host a Cluster").into(),
+        );
+    } else {
+        tinychain::Scheduler::default()
+    };
 
-    let kernel = tinychain::Kernel::new(clusters);
+    let kernel = tinychain::Kernel::new(clusters)
+        .with_registry(registry)
+        .with_scheduler(Arc::new(scheduler))
+        .with_namespaces(config.tenants)
+        .with_admins(config.host_admins)
+        .with_drain_timeout(config.drain_timeout);
+    let kernel = if let Some(backups) = backups {
+        kernel.with_backups(backups)
+    } else {
+        kernel
+    };
+    let kernel = if let Some(data_dir) = data_dir {
+        kernel.with_data_dir(data_dir)
+    } else {
+        kernel
+    };
+    let kernel = if let Some(path) = config.config {
+        let cli = std::env::args().collect::<Vec<String>>();
+        let config = ConfigFile::new(path, cli, |args| {
+            Config::from_iter_safe(args)
+                .map_err(|e| TCError::bad_request("invalid host configuration", e))
+                .and_then(|config| config.reload())
+        });
+
+        let config = Arc::new(config);
+        config.clone().reload_on_hangup()?;
+        kernel.with_config(config)
+    } else {
+        kernel
+    };
     let gateway = tinychain::gateway::Gateway::new(gateway_config, kernel, txn_server);
 
     log::info!("starting server, cache size is {}", config.cache_size);
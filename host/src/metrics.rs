@@ -0,0 +1,287 @@
+//! Operational metrics of this host, exported at [`PATH`] in the Prometheus text format.
+//!
+//! The metrics are process-wide, so that they can be recorded wherever the measured event happens
+//! (e.g. a filesystem block read) without threading a registry through every call.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tcgeneric::PathSegment;
+
+/// The path at which a host exports its metrics.
+pub const PATH: &str = "/metrics";
+
+/// The content type of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// The route label of a request which no hosted cluster or built-in route serves.
+pub const OTHER: &str = "other";
+
+/// The built-in routes of a host which label the requests they serve, other than hosted clusters.
+const ROUTES: [&str; 12] = [
+    "/admin",
+    "/backup",
+    "/config",
+    "/graphql",
+    "/health",
+    "/host",
+    "/metrics",
+    "/ready",
+    "/schedule",
+    "/schema",
+    "/state",
+    "/transact",
+];
+
+/// The HTTP methods which label the requests they serve.
+const METHODS: [&str; 6] = ["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"];
+
+/// The upper bounds, in seconds, of the buckets of the transaction commit latency histogram.
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.];
+
+static METRICS: Metrics = Metrics::new();
+
+/// Record a response to a request with the given `method` to the given `route`.
+///
+/// The `route` is the path of a hosted cluster (cf. [`builtin_route`]), not the path of the
+/// request, so that the number of distinct labels is bounded by the number of hosted clusters.
+pub fn record_request(method: &str, route: &str, status: u16) {
+    let method = METHODS
+        .iter()
+        .find(|known| **known == method)
+        .copied()
+        .unwrap_or(OTHER);
+
+    let key = (method.to_string(), route.to_string(), status);
+    let mut requests = METRICS.requests.lock().expect("request metrics");
+    *requests.entry(key).or_insert(0) += 1;
+}
+
+/// Return the route label of a request to the given `path` if it's served by a built-in route,
+/// or [`OTHER`] if not.
+pub fn builtin_route(path: &[PathSegment]) -> &'static str {
+    let name = match path.first() {
+        Some(name) => name.as_str(),
+        None => return "/",
+    };
+
+    ROUTES
+        .iter()
+        .find(|route| &route[1..] == name)
+        .copied()
+        .unwrap_or(OTHER)
+}
+
+/// Record the time taken to commit a transaction.
+pub fn record_commit(latency: Duration) {
+    METRICS.commit_latency.observe(latency);
+}
+
+/// Record a read of a filesystem block, and whether it was already in the cache.
+pub fn record_cache_access(hit: bool) {
+    if hit {
+        METRICS.cache_hits.fetch_add(1, Ordering::Relaxed);
+    } else {
+        METRICS.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Record a read or write of a filesystem block of the given type (e.g. "array" for a tensor).
+pub fn record_block_io(block_type: &'static str, write: bool) {
+    let mut blocks = METRICS.block_io.lock().expect("block IO metrics");
+    *blocks.entry((block_type, write)).or_insert(0) += 1;
+}
+
+/// Render the current value of each metric in the Prometheus text format.
+pub fn render() -> String {
+    METRICS.to_string()
+}
+
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+
+        Self {
+            buckets: [ZERO; LATENCY_BUCKETS.len()],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.buckets) {
+            if seconds <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+struct Metrics {
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    commit_latency: Histogram,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    block_io: Mutex<BTreeMap<(&'static str, bool), u64>>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            requests: Mutex::new(BTreeMap::new()),
+            commit_latency: Histogram::new(),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            block_io: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let requests = self.requests.lock().expect("request metrics");
+        describe(
+            f,
+            "tc_requests_total",
+            "counter",
+            "Requests, by method, route, and status.",
+        )?;
+        for ((method, route, status), count) in requests.iter() {
+            writeln!(
+                f,
+                "tc_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                escape(method),
+                escape(route),
+                status,
+                count
+            )?;
+        }
+
+        let latency = &self.commit_latency;
+        describe(
+            f,
+            "tc_txn_commit_seconds",
+            "histogram",
+            "Transaction commit latency.",
+        )?;
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&latency.buckets) {
+            let bucket = bucket.load(Ordering::Relaxed);
+            writeln!(
+                f,
+                "tc_txn_commit_seconds_bucket{{le=\"{}\"}} {}",
+                bound, bucket
+            )?;
+        }
+
+        let count = latency.count.load(Ordering::Relaxed);
+        let sum = latency.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.;
+        writeln!(f, "tc_txn_commit_seconds_bucket{{le=\"+Inf\"}} {}", count)?;
+        writeln!(f, "tc_txn_commit_seconds_sum {}", sum)?;
+        writeln!(f, "tc_txn_commit_seconds_count {}", count)?;
+
+        let hits = self.cache_hits.load(Ordering::Relaxed);
+        describe(
+            f,
+            "tc_block_cache_hits_total",
+            "counter",
+            "Cached block reads.",
+        )?;
+        writeln!(f, "tc_block_cache_hits_total {}", hits)?;
+
+        let misses = self.cache_misses.load(Ordering::Relaxed);
+        describe(
+            f,
+            "tc_block_cache_misses_total",
+            "counter",
+            "Uncached block reads.",
+        )?;
+        writeln!(f, "tc_block_cache_misses_total {}", misses)?;
+
+        let block_io = self.block_io.lock().expect("block IO metrics");
+        describe(
+            f,
+            "tc_block_io_total",
+            "counter",
+            "Block reads and writes, by type.",
+        )?;
+        for ((block_type, write), count) in block_io.iter() {
+            let op = if *write { "write" } else { "read" };
+            let labels = format!("type=\"{}\",op=\"{}\"", block_type, op);
+            writeln!(f, "tc_block_io_total{{{}}} {}", labels, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn describe(f: &mut fmt::Formatter, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(f, "# HELP {} {}", name, help)?;
+    writeln!(f, "# TYPE {} {}", name, kind)
+}
+
+fn escape(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let histogram = Histogram::new();
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_millis(300));
+
+        let buckets: Vec<u64> = histogram
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+
+        assert_eq!(buckets, vec![0, 0, 1, 1, 1, 1, 1, 1, 2, 2]);
+        assert_eq!(histogram.count.load(Ordering::Relaxed), 2);
+        assert_eq!(histogram.sum_micros.load(Ordering::Relaxed), 303_000);
+    }
+
+    #[test]
+    fn test_builtin_route() {
+        let path: tcgeneric::TCPathBuf = "/state/scalar/value/string".parse().expect("path");
+        assert_eq!(builtin_route(&path), "/state");
+
+        let path: tcgeneric::TCPathBuf = "/no/such/route".parse().expect("path");
+        assert_eq!(builtin_route(&path), OTHER);
+
+        assert_eq!(builtin_route(&[]), "/");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("/app/\"table\""), "/app/\\\"table\\\"");
+    }
+}
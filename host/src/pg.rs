@@ -0,0 +1,599 @@
+//! A read-only Postgres wire protocol interface for `Gateway`.
+//!
+//! Supports the simple query protocol with a subset of SQL:
+//! `SELECT <columns | * | COUNT(*)> FROM <table> [WHERE ...] [GROUP BY ...] [ORDER BY ...] [LIMIT n]`
+//! where `<table>` is the path of a hosted `Table`, either quoted (`"/app/db/users"`)
+//! or with segments separated by dots (`app.db.users`).
+//!
+//! A client authenticates with an auth token, given either as its password or as the `token`
+//! startup parameter, and each of its queries is authorized by that token. Since SSL is not
+//! supported, the token is sent in cleartext, so this interface should only be exposed to a
+//! trusted network.
+//!
+//! Result rows are written to the client as they're read, so a query's result is not buffered in
+//! memory, but the whole query (including writing its result) must still complete within the
+//! request TTL of the host.
+
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use log::{debug, info};
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Instant;
+
+use tc_error::*;
+use tc_table::{Query, TableInstance, TableStream};
+use tc_transact::{Isolation, Transaction, TxnId};
+use tc_value::{Number, NumberType, Value, ValueType};
+use tcgeneric::{TCBoxTryStream, TCPathBuf};
+
+use crate::admission::Permit;
+use crate::collection::{Collection, Table};
+use crate::gateway::Gateway;
+use crate::state::State;
+
+const PROTOCOL_VERSION: i32 = 196608; // 3.0
+const SSL_REQUEST: i32 = 80877103;
+const CANCEL_REQUEST: i32 = 80877102;
+
+// the maximum length of a startup message, as in Postgres
+const MAX_STARTUP_LEN: i32 = 10_000;
+
+// the maximum length of any other message, which is plenty for a read-only query
+const MAX_MESSAGE_LEN: i32 = 1 << 20;
+
+const AUTH_OK: i32 = 0;
+const AUTH_CLEARTEXT_PASSWORD: i32 = 3;
+const TOKEN: &str = "token";
+
+const OID_BOOL: i32 = 16;
+const OID_INT8: i32 = 20;
+const OID_TEXT: i32 = 25;
+const OID_FLOAT8: i32 = 701;
+
+/// TinyChain's read-only Postgres wire protocol server. Should only be used through a [`Gateway`].
+pub struct PGServer {
+    gateway: Arc<Gateway>,
+}
+
+impl PGServer {
+    pub fn new(gateway: Arc<Gateway>) -> Self {
+        Self { gateway }
+    }
+
+    async fn handle(self: Arc<Self>, stream: TcpStream) -> io::Result<()> {
+        let remote = stream.peer_addr()?.ip();
+        let (reader, writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut writer = BufWriter::new(writer);
+
+        let params = loop {
+            let len = reader.read_i32().await?;
+            if len < 8 || len > MAX_STARTUP_LEN {
+                let message = format!("invalid startup message length {}", len);
+                write_error(&mut writer, "08P01", &message).await?;
+                return writer.flush().await;
+            }
+
+            let code = reader.read_i32().await?;
+
+            match code {
+                SSL_REQUEST => {
+                    writer.write_u8(b'N').await?;
+                    writer.flush().await?;
+                }
+                CANCEL_REQUEST => return Ok(()),
+                PROTOCOL_VERSION => {
+                    let mut params = vec![0; (len - 8) as usize];
+                    reader.read_exact(&mut params).await?;
+                    break parse_params(&params);
+                }
+                other => {
+                    let message = format!("unsupported protocol version {}", other);
+                    write_error(&mut writer, "08P01", &message).await?;
+                    return writer.flush().await;
+                }
+            }
+        };
+
+        let token = match params.into_iter().find(|(name, _)| name == TOKEN) {
+            Some((_, token)) => token,
+            None => {
+                write_message(&mut writer, b'R', &AUTH_CLEARTEXT_PASSWORD.to_be_bytes()).await?;
+                writer.flush().await?;
+
+                match read_message(&mut reader).await? {
+                    Ok((b'p', password)) => String::from_utf8_lossy(&password)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                    Ok(_) => {
+                        write_error(&mut writer, "08P01", "expected a password message").await?;
+                        return writer.flush().await;
+                    }
+                    Err(message) => {
+                        write_error(&mut writer, "08P01", &message).await?;
+                        return writer.flush().await;
+                    }
+                }
+            }
+        };
+
+        if let Err(cause) = self.authenticate(&token).await {
+            info!("Postgres wire protocol authentication failed: {}", cause);
+            write_error(&mut writer, "28P01", cause.message()).await?;
+            return writer.flush().await;
+        }
+
+        write_message(&mut writer, b'R', &AUTH_OK.to_be_bytes()).await?;
+        write_parameter(&mut writer, "server_version", "9.6.0").await?;
+        write_parameter(&mut writer, "client_encoding", "UTF8").await?;
+        write_parameter(&mut writer, "DateStyle", "ISO").await?;
+        write_message(&mut writer, b'Z', b"I").await?;
+        writer.flush().await?;
+
+        loop {
+            let (tag, body) = match read_message(&mut reader).await {
+                Ok(Ok(message)) => message,
+                Ok(Err(message)) => {
+                    write_error(&mut writer, "08P01", &message).await?;
+                    return writer.flush().await;
+                }
+                Err(cause) if cause.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(cause) => return Err(cause),
+            };
+
+            match tag {
+                b'Q' => {
+                    let sql = String::from_utf8_lossy(&body);
+                    let sql = sql.trim_end_matches('\0');
+                    self.query(&mut writer, sql, &token, remote).await?;
+                }
+                b'X' => return Ok(()),
+                other => {
+                    let message = format!(
+                        "unsupported message type {} (only simple queries are supported)",
+                        other as char
+                    );
+
+                    write_error(&mut writer, "0A000", &message).await?;
+                }
+            }
+
+            write_message(&mut writer, b'Z', b"I").await?;
+            writer.flush().await?;
+        }
+    }
+
+    // check that the given auth token is valid, before accepting any query
+    async fn authenticate(&self, token: &str) -> TCResult<()> {
+        let txn_id = TxnId::new(Gateway::time());
+        self.gateway
+            .new_txn(txn_id, Some(token.to_string()))
+            .await
+            .map(|_| ())
+    }
+
+    async fn query<W: AsyncWriteExt + Unpin>(
+        &self,
+        writer: &mut W,
+        sql: &str,
+        token: &str,
+        remote: IpAddr,
+    ) -> io::Result<()> {
+        debug!("SQL query: {}", sql);
+
+        let sql = sql.trim().trim_end_matches(';').trim();
+        if sql.is_empty() {
+            return write_message(writer, b'I', &[]).await;
+        }
+
+        let command = sql.split_whitespace().next().unwrap_or_default();
+        if command.eq_ignore_ascii_case("set") {
+            return write_message(writer, b'C', b"SET\0").await;
+        } else if !command.eq_ignore_ascii_case("select") {
+            let message = format!("{} is not supported by this read-only interface", command);
+            return write_error(writer, "25006", &message).await;
+        }
+
+        let deadline = Instant::now() + self.gateway.request_ttl();
+        let request = self.execute(sql, token, remote);
+        let result = match tokio::time::timeout_at(deadline, request).await {
+            Ok(result) => result,
+            Err(cause) => Err(TCError::timeout(cause)),
+        };
+
+        match result {
+            Ok(ResultSet::Count(count)) => {
+                write_row_description(writer, &[("count".to_string(), OID_INT8)]).await?;
+                write_data_row(writer, &[Some(count.to_string())]).await?;
+                write_message(writer, b'C', b"SELECT 1\0").await
+            }
+            Ok(ResultSet::Rows(rows)) => {
+                write_row_description(writer, &rows.columns).await?;
+                write_rows(writer, rows, deadline).await
+            }
+            Err(cause) => {
+                info!("SQL query failed: {}", cause);
+                write_error(writer, sql_state(&cause), cause.message()).await
+            }
+        }
+    }
+
+    async fn execute(&self, sql: &str, token: &str, remote: IpAddr) -> TCResult<ResultSet> {
+        let (path, query, count) = parse_sql(sql)?;
+
+        let txn = self
+            .gateway
+            .new_txn(TxnId::new(Gateway::time()), Some(token.to_string()))
+            .await?;
+
+        let _permit = self.gateway.admit(&txn, remote).await?;
+
+        let txn = txn.with_isolation(Isolation::ReadCommitted);
+        let txn_id = *txn.id();
+
+        let table = match txn.get(txn.link(path.clone()), Value::None).await? {
+            State::Collection(Collection::Table(table)) => table,
+            other => {
+                return Err(TCError::bad_request(
+                    format!("{} is not a Table", path),
+                    other,
+                ))
+            }
+        };
+
+        let grouped = query.is_grouped();
+        let table: Table = query.apply(table)?;
+
+        if count {
+            return table.count(txn_id).await.map(ResultSet::Count);
+        }
+
+        let columns = table
+            .key()
+            .iter()
+            .chain(table.values())
+            .map(|col| (col.name.to_string(), type_oid(col.dtype)))
+            .collect();
+
+        let rows = table.rows(txn_id).await?;
+
+        Ok(ResultSet::Rows(Rows {
+            columns,
+            rows,
+            grouped,
+            _permit,
+        }))
+    }
+}
+
+#[async_trait]
+impl crate::gateway::Server for PGServer {
+    type Error = io::Error;
+
+    async fn listen(self, addr: SocketAddr) -> Result<(), Self::Error> {
+        println!("Postgres wire protocol server listening on {}", &addr);
+
+        let listener = TcpListener::bind(addr).await?;
+        let server = Arc::new(self);
+
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            debug!("new Postgres wire protocol connection from {}", peer);
+
+            let server = server.clone();
+            tokio::spawn(async move {
+                if let Err(cause) = server.handle(stream).await {
+                    debug!("Postgres wire protocol connection closed: {}", cause);
+                }
+            });
+        }
+    }
+}
+
+impl fmt::Display for PGServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Postgres wire protocol server")
+    }
+}
+
+enum ResultSet {
+    Count(u64),
+    Rows(Rows),
+}
+
+// the rows of a query result, which hold its admission permit until they're all written
+struct Rows {
+    columns: Vec<(String, i32)>,
+    rows: TCBoxTryStream<'static, Vec<Value>>,
+    grouped: bool,
+    _permit: Option<Permit>,
+}
+
+fn parse_sql(sql: &str) -> TCResult<(TCPathBuf, Query, bool)> {
+    let (select, rest) = split_keyword(sql, "from")
+        .ok_or_else(|| TCError::bad_request("SQL query is missing a FROM clause", sql))?;
+
+    let rest = rest.trim_start();
+    let (table, rest) = if let Some(quoted) = rest.strip_prefix('"') {
+        let end = quoted
+            .find('"')
+            .ok_or_else(|| TCError::bad_request("unterminated table name", rest))?;
+
+        (&quoted[..end], &quoted[end + 1..])
+    } else {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        (&rest[..end], &rest[end..])
+    };
+
+    let path = if table.starts_with('/') {
+        table.parse()?
+    } else {
+        format!("/{}", table.replace('.', "/")).parse()?
+    };
+
+    let columns = select
+        .trim()
+        .get("select".len()..)
+        .unwrap_or_default()
+        .trim();
+    let count = columns
+        .replace(char::is_whitespace, "")
+        .eq_ignore_ascii_case("count(*)");
+
+    let query = if count {
+        format!("SELECT * {}", rest)
+    } else {
+        format!("SELECT {} {}", columns, rest)
+    };
+
+    Ok((path, query.parse()?, count))
+}
+
+fn split_keyword<'a>(sql: &'a str, keyword: &str) -> Option<(&'a str, &'a str)> {
+    let lower = sql.to_ascii_lowercase();
+    let mut quoted = None;
+
+    for (i, c) in lower.char_indices() {
+        match (quoted, c) {
+            (Some(q), c) if c == q => quoted = None,
+            (Some(_), _) => {}
+            (None, '\'') | (None, '"') => quoted = Some(c),
+            (None, c) if c.is_whitespace() => {
+                let start = i + c.len_utf8();
+                let end = start + keyword.len();
+
+                // `get` returns `None` if the keyword would end out of bounds or mid-character
+                if lower.get(start..end) != Some(keyword) {
+                    continue;
+                }
+
+                let follows = lower.get(end..).and_then(|rest| rest.chars().next());
+                if follows.map(char::is_whitespace).unwrap_or(true) {
+                    return Some((&sql[..i], &sql[end..]));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+// parse the `name\0value\0` pairs of a startup message, which end with an extra `\0`
+fn parse_params(params: &[u8]) -> Vec<(String, String)> {
+    let mut fields = params
+        .split(|b| *b == 0)
+        .map(|field| String::from_utf8_lossy(field).to_string());
+
+    let mut params = Vec::new();
+    while let (Some(name), Some(value)) = (fields.next(), fields.next()) {
+        if name.is_empty() {
+            break;
+        }
+
+        params.push((name, value));
+    }
+
+    params
+}
+
+// read a tagged message, or return an error message if its length is invalid
+async fn read_message<R: AsyncReadExt + Unpin>(
+    reader: &mut R,
+) -> io::Result<Result<(u8, Vec<u8>), String>> {
+    let tag = reader.read_u8().await?;
+    let len = reader.read_i32().await?;
+
+    if len < 4 || len > MAX_MESSAGE_LEN {
+        return Ok(Err(format!("invalid message length {}", len)));
+    }
+
+    let mut body = vec![0; (len - 4) as usize];
+    reader.read_exact(&mut body).await?;
+    Ok(Ok((tag, body)))
+}
+
+fn type_oid(dtype: ValueType) -> i32 {
+    match dtype {
+        ValueType::Number(NumberType::Bool) => OID_BOOL,
+        ValueType::Number(NumberType::Int(_)) | ValueType::Number(NumberType::UInt(_)) => OID_INT8,
+        ValueType::Number(NumberType::Float(_)) => OID_FLOAT8,
+        _ => OID_TEXT,
+    }
+}
+
+fn encode_value(value: Value) -> Option<String> {
+    match value {
+        Value::None => None,
+        Value::Number(Number::Bool(b)) => Some(if b.into() { "t" } else { "f" }.to_string()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn sql_state(cause: &TCError) -> &'static str {
+    match cause.code() {
+        ErrorType::BadRequest => "42601",
+        ErrorType::Conflict => "40001",
+        ErrorType::Forbidden | ErrorType::Unauthorized => "42501",
+        ErrorType::NotFound => "42P01",
+        ErrorType::NotImplemented => "0A000",
+        ErrorType::Timeout => "57014",
+        ErrorType::TooManyRequests => "53300",
+        _ => "XX000",
+    }
+}
+
+async fn write_message<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    tag: u8,
+    body: &[u8],
+) -> io::Result<()> {
+    writer.write_u8(tag).await?;
+    writer.write_i32(body.len() as i32 + 4).await?;
+    writer.write_all(body).await
+}
+
+async fn write_parameter<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    name: &str,
+    value: &str,
+) -> io::Result<()> {
+    let body = format!("{}\0{}\0", name, value);
+    write_message(writer, b'S', body.as_bytes()).await
+}
+
+async fn write_error<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    code: &str,
+    message: &str,
+) -> io::Result<()> {
+    let body = format!("SERROR\0C{}\0M{}\0\0", code, message);
+    write_message(writer, b'E', body.as_bytes()).await
+}
+
+async fn write_row_description<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    columns: &[(String, i32)],
+) -> io::Result<()> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&(columns.len() as i16).to_be_bytes());
+
+    for (name, oid) in columns {
+        body.extend_from_slice(name.as_bytes());
+        body.push(0);
+        body.extend_from_slice(&0i32.to_be_bytes()); // table OID
+        body.extend_from_slice(&0i16.to_be_bytes()); // column attribute number
+        body.extend_from_slice(&oid.to_be_bytes());
+        body.extend_from_slice(&(-1i16).to_be_bytes()); // type size
+        body.extend_from_slice(&(-1i32).to_be_bytes()); // type modifier
+        body.extend_from_slice(&0i16.to_be_bytes()); // text format
+    }
+
+    write_message(writer, b'T', &body).await
+}
+
+// write each row as it's read, so that a large result is never buffered in memory
+async fn write_rows<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    rows: Rows,
+    deadline: Instant,
+) -> io::Result<()> {
+    let Rows {
+        mut rows,
+        grouped,
+        _permit,
+        ..
+    } = rows;
+
+    let mut count = 0u64;
+    let mut last = None;
+
+    loop {
+        let row = match tokio::time::timeout_at(deadline, rows.try_next()).await {
+            Ok(Ok(Some(row))) => row,
+            Ok(Ok(None)) => break,
+            Ok(Err(cause)) => {
+                info!("SQL query failed after {} rows: {}", count, cause);
+                return write_error(writer, sql_state(&cause), cause.message()).await;
+            }
+            Err(cause) => {
+                let cause = TCError::timeout(cause);
+                info!("SQL query timed out after {} rows", count);
+                return write_error(writer, sql_state(&cause), cause.message()).await;
+            }
+        };
+
+        let row: Vec<Option<String>> = row.into_iter().map(encode_value).collect();
+
+        if grouped && last.as_ref() == Some(&row) {
+            continue;
+        }
+
+        write_data_row(writer, &row).await?;
+        count += 1;
+
+        if grouped {
+            last = Some(row);
+        }
+    }
+
+    let tag = format!("SELECT {}\0", count);
+    write_message(writer, b'C', tag.as_bytes()).await
+}
+
+async fn write_data_row<W: AsyncWriteExt + Unpin>(
+    writer: &mut W,
+    row: &[Option<String>],
+) -> io::Result<()> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&(row.len() as i16).to_be_bytes());
+
+    for cell in row {
+        if let Some(cell) = cell {
+            body.extend_from_slice(&(cell.len() as i32).to_be_bytes());
+            body.extend_from_slice(cell.as_bytes());
+        } else {
+            body.extend_from_slice(&(-1i32).to_be_bytes());
+        }
+    }
+
+    write_message(writer, b'D', &body).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_keyword() {
+        let sql = "SELECT name FROM users";
+        assert_eq!(split_keyword(sql, "from"), Some(("SELECT name", " users")));
+
+        assert_eq!(
+            split_keyword("SELECT \"x from\" FROM t", "from"),
+            Some(("SELECT \"x from\"", " t"))
+        );
+        assert_eq!(split_keyword("SELECT fro", "from"), None);
+        assert_eq!(split_keyword("SELECT é", "from"), None);
+        assert_eq!(split_keyword("SELECT * FROMé", "from"), None);
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let params = b"user\0alice\0token\0abc\0\0";
+        assert_eq!(
+            parse_params(params),
+            vec![
+                ("user".to_string(), "alice".to_string()),
+                ("token".to_string(), "abc".to_string()),
+            ]
+        );
+
+        assert!(parse_params(b"user").is_empty());
+    }
+}
@@ -1,12 +1,19 @@
+use std::convert::TryFrom;
+
 use log::debug;
 
 use tc_error::*;
 use tc_transact::Transaction;
-use tcgeneric::{PathSegment, TCPath};
+use tc_value::Value;
+use tcgeneric::{Instance, PathSegment, TCPath, Tuple};
 
 use crate::chain::{Chain, ChainInstance, ChainType, Subject};
+use crate::state::State;
+use crate::stream::TCStream;
 
-use super::{DeleteHandler, GetHandler, Handler, PostHandler, PutHandler, Route};
+use super::cluster::cast_txn_id;
+use super::reflect::{self, CLASS, SCHEMA};
+use super::{AttributeHandler, DeleteHandler, GetHandler, Handler, PostHandler, PutHandler, Route};
 
 impl Route for ChainType {
     fn route<'a>(&'a self, _path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
@@ -18,16 +25,28 @@ impl Route for Subject {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
         debug!("Subject::route {}", TCPath::from(path));
 
-        match self {
+        let handler = match self {
             Self::BTree(btree) => btree.route(path),
             Self::Map(map) => map.route(path),
             Self::Table(table) => table.route(path),
             Self::Tuple(tuple) => tuple.route(path),
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Dense(dense) => dense.route(path),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Sparse(sparse) => sparse.route(path),
+        };
+
+        if handler.is_some() {
+            handler
+        } else if path.len() == 1 && path[0] == CLASS {
+            let class = reflect::class_link(self.class());
+            Some(Box::new(AttributeHandler::from(class)))
+        } else if path.len() == 1 && path[0] == SCHEMA {
+            let schema = reflect::describe_subject(self);
+            Some(Box::new(AttributeHandler::from(schema)))
+        } else {
+            None
         }
     }
 }
@@ -139,12 +158,97 @@ impl<'a> Handler<'a> for ChainHandler<'a> {
     }
 }
 
+struct HistoryHandler<'a> {
+    chain: &'a Chain,
+}
+
+impl<'a> From<&'a Chain> for HistoryHandler<'a> {
+    fn from(chain: &'a Chain) -> Self {
+        Self { chain }
+    }
+}
+
+impl<'a> Handler<'a> for HistoryHandler<'a> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                if key.is_none() {
+                    let txn_ids = self.chain.history(*txn.id()).await?;
+                    let txn_ids = txn_ids
+                        .into_iter()
+                        .map(|txn_id| Value::from(txn_id.to_string()))
+                        .collect::<Tuple<Value>>();
+
+                    Ok(Value::from(txn_ids).into())
+                } else {
+                    let past_txn_id = cast_txn_id(key)?;
+                    let past = self.chain.as_of(txn, past_txn_id).await?;
+                    Ok(State::from(past))
+                }
+            })
+        }))
+    }
+
+    fn put<'b>(self: Box<Self>) -> Option<PutHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key, value| {
+            Box::pin(async move {
+                key.expect_none()?;
+
+                let past_txn_id = Value::try_from(value)?;
+                let past_txn_id = cast_txn_id(past_txn_id)?;
+
+                debug!("revert {} to {}", self.chain, past_txn_id);
+                self.chain.revert(txn, past_txn_id).await
+            })
+        }))
+    }
+}
+
+struct ChangesHandler<'a> {
+    chain: &'a Chain,
+}
+
+impl<'a> From<&'a Chain> for ChangesHandler<'a> {
+    fn from(chain: &'a Chain) -> Self {
+        Self { chain }
+    }
+}
+
+impl<'a> Handler<'a> for ChangesHandler<'a> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let since = if key.is_none() {
+                    None
+                } else {
+                    cast_txn_id(key).map(Some)?
+                };
+
+                Ok(TCStream::changes(self.chain.clone(), since).into())
+            })
+        }))
+    }
+}
+
 impl Route for Chain {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
         debug!("Chain::route {}", TCPath::from(path));
 
         if path.len() == 1 && path[0].as_str() == "chain" {
             Some(Box::new(ChainHandler::from(self)))
+        } else if path.len() == 2 && path[0].as_str() == "chain" && path[1].as_str() == "history" {
+            Some(Box::new(HistoryHandler::from(self)))
+        } else if path.len() == 2 && path[0].as_str() == "chain" && path[1].as_str() == "changes" {
+            Some(Box::new(ChangesHandler::from(self)))
         } else {
             Some(Box::new(AppendHandler::new(self, path)))
         }
@@ -1,18 +1,27 @@
 use std::iter::FromIterator;
 
 use bytes::Bytes;
-use futures::future::{self, TryFutureExt};
+use futures::future::TryFutureExt;
+use futures::stream::TryStreamExt;
 use log::debug;
 use safecast::{TryCastFrom, TryCastInto};
 
 use tc_error::*;
+use tc_table::{PrivacyPolicy, TableInstance, TableSlice, TableStream};
 use tc_transact::{Transact, Transaction};
-use tc_value::{Link, Value};
-use tcgeneric::{label, Id, Tuple};
+use tc_value::{Link, TCString, Value};
+use tcgeneric::{label, Id, Map, PathSegment, Tuple};
 
-use crate::cluster::Cluster;
+use crate::chain::{Chain, ChainInstance, Subject};
+use crate::cluster::{Cluster, Library, View, BOUNDS, CLASSES, PREPARE};
+use crate::collection::Table;
+use crate::route::reflect::{self, CLASS, SCHEMA};
 use crate::route::*;
+use crate::scalar::Scalar;
 use crate::state::State;
+use crate::txn::{Txn, TxnId};
+
+use super::collection::{aggregate_key, cast_into_bounds, column_values};
 
 struct AuthorizeHandler<'a> {
     cluster: &'a Cluster,
@@ -43,21 +52,288 @@ impl<'a> From<&'a Cluster> for AuthorizeHandler<'a> {
     }
 }
 
+/// Routes requests to a [`Chain`] with restricted table columns, so that a request which lacks
+/// the scope required to read a column of a table sees a masked view of that table.
+struct ColumnAccessHandler<'a> {
+    cluster: &'a Cluster,
+    chain: &'a Chain,
+    path: &'a [PathSegment],
+}
+
+impl<'a> ColumnAccessHandler<'a> {
+    fn new(cluster: &'a Cluster, chain: &'a Chain, path: &'a [PathSegment]) -> Self {
+        Self {
+            cluster,
+            chain,
+            path,
+        }
+    }
+
+    /// Return a masked view of the [`Table`] addressed by this request, and the rest of the path,
+    /// if the request may not read all of its columns.
+    async fn masked(&self, txn: &Txn) -> TCResult<Option<(Table, &'a [PathSegment])>> {
+        let subject = self.chain.subject();
+        if !self.path.is_empty() && self.path[0].as_str() == "chain" {
+            let access = subject.column_access();
+            self.cluster.authorize_columns(txn, &access).await?;
+            return Ok(None);
+        }
+
+        match subject.member(self.path) {
+            (Subject::Table(table), suffix) => {
+                let schema = table.schema();
+                let masks = self.cluster.column_masks(txn, schema.access()).await?;
+                if masks.is_empty() {
+                    Ok(None)
+                } else {
+                    let table = Table::from(table.clone()).mask(masks)?;
+                    Ok(Some((table, suffix)))
+                }
+            }
+            (member, _) => {
+                let access = member.column_access();
+                self.cluster.authorize_columns(txn, &access).await?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<'a> Handler<'a> for ColumnAccessHandler<'a> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        let handler = self.chain.route(self.path)?.get()?;
+
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                if let Some((table, path)) = self.masked(txn).await? {
+                    table.get(txn, path, key).await
+                } else {
+                    handler(txn, key).await
+                }
+            })
+        }))
+    }
+
+    fn put<'b>(self: Box<Self>) -> Option<PutHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        self.chain.route(self.path)?.put()
+    }
+
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        let handler = self.chain.route(self.path)?.post()?;
+
+        Some(Box::new(|txn, params| {
+            Box::pin(async move {
+                if let Some((table, path)) = self.masked(txn).await? {
+                    table.post(txn, path, params).await
+                } else {
+                    handler(txn, params).await
+                }
+            })
+        }))
+    }
+
+    fn delete<'b>(self: Box<Self>) -> Option<DeleteHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        self.chain.route(self.path)?.delete()
+    }
+}
+
+/// Routes requests to a [`Chain`] with a private table, so that a request which lacks the scope of
+/// the table's [`PrivacyPolicy`] can only read noisy aggregates of it.
+struct PrivacyHandler<'a> {
+    cluster: &'a Cluster,
+    chain: &'a Chain,
+    path: &'a [PathSegment],
+}
+
+impl<'a> PrivacyHandler<'a> {
+    fn new(cluster: &'a Cluster, chain: &'a Chain, path: &'a [PathSegment]) -> Self {
+        Self {
+            cluster,
+            chain,
+            path,
+        }
+    }
+
+    /// Return the private [`Table`] addressed by this request, its [`PrivacyPolicy`], and the rest
+    /// of the path, if the request lacks the scope of the policy.
+    async fn private(
+        &self,
+        txn: &Txn,
+    ) -> TCResult<Option<(Table, PrivacyPolicy, &'a [PathSegment])>> {
+        let subject = self.chain.subject();
+        if !self.path.is_empty() && self.path[0].as_str() == "chain" {
+            let policies = subject.privacy();
+            self.cluster.authorize_privacy(txn, &policies).await?;
+            return Ok(None);
+        }
+
+        match subject.member(self.path) {
+            (Subject::Table(table), suffix) => {
+                let schema = table.schema();
+                let policy = match schema.privacy() {
+                    Some(policy) => policy.clone(),
+                    None => return Ok(None),
+                };
+
+                if self.cluster.is_authorized(txn, &policy.scope).await? {
+                    return Ok(None);
+                }
+
+                let masks = self.cluster.column_masks(txn, schema.access()).await?;
+                let table = Table::from(table.clone());
+                let table = if masks.is_empty() {
+                    table
+                } else {
+                    table.mask(masks)?
+                };
+
+                Ok(Some((table, policy, suffix)))
+            }
+            (member, _) => {
+                let policies = member.privacy();
+                self.cluster.authorize_privacy(txn, &policies).await?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<'a> Handler<'a> for PrivacyHandler<'a> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        let handler = route_chain(self.cluster, self.chain, self.path)?.get()?;
+
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                if let Some((table, policy, path)) = self.private(txn).await? {
+                    private_aggregate(txn, table, &policy, path, key).await
+                } else {
+                    handler(txn, key).await
+                }
+            })
+        }))
+    }
+
+    fn put<'b>(self: Box<Self>) -> Option<PutHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        route_chain(self.cluster, self.chain, self.path)?.put()
+    }
+
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        let handler = route_chain(self.cluster, self.chain, self.path)?.post()?;
+
+        Some(Box::new(|txn, params| {
+            Box::pin(async move {
+                if let Some((_, policy, _)) = self.private(txn).await? {
+                    Err(TCError::unauthorized(format!(
+                        "querying this table requires the scope {}",
+                        policy.scope
+                    )))
+                } else {
+                    handler(txn, params).await
+                }
+            })
+        }))
+    }
+
+    fn delete<'b>(self: Box<Self>) -> Option<DeleteHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        route_chain(self.cluster, self.chain, self.path)?.delete()
+    }
+}
+
+/// Compute the aggregate of a private `table` addressed by the given `path`,
+/// with noise added according to its `policy`.
+async fn private_aggregate(
+    txn: &Txn,
+    table: Table,
+    policy: &PrivacyPolicy,
+    path: &[PathSegment],
+    key: Value,
+) -> TCResult<State> {
+    let aggregate = match path {
+        [name] => name.as_str(),
+        _ => "",
+    };
+
+    let table = table.with_isolation(txn.isolation());
+    let result = match aggregate {
+        "count" => {
+            let bounds = cast_into_bounds(Scalar::Value(key))?;
+            let table = if bounds.is_empty() {
+                table
+            } else {
+                table.slice(bounds)?
+            };
+
+            let count = table.count(*txn.id()).await?;
+            policy.count(count)?
+        }
+        "sum" | "mean" => {
+            let (column, bounds) = aggregate_key(key)?;
+            let mut sum = policy.sum(&column)?;
+
+            let mut values = column_values(table, *txn.id(), column, bounds).await?;
+            while let Some(value) = values.try_next().await? {
+                sum.push(value)?;
+            }
+
+            if aggregate == "sum" {
+                sum.sum()?
+            } else {
+                sum.mean()?
+            }
+        }
+        _ => {
+            return Err(TCError::unauthorized(format!(
+                "reading the rows of this table requires the scope {}",
+                policy.scope
+            )))
+        }
+    };
+
+    Ok(Value::Number(result).into())
+}
+
 pub struct ClusterHandler<'a> {
     cluster: &'a Cluster,
 }
 
 impl<'a> ClusterHandler<'a> {
-    fn handle_get(self, key: Value) -> TCResult<State> {
+    async fn handle_get(self, txn: &Txn, key: Value) -> TCResult<State> {
         debug!("Cluster::get {}", key);
 
         if key.is_some() {
             let key: Id = key.try_cast_into(|v| TCError::bad_request("invalid ID", v))?;
-            self.cluster
+            let chain = self
+                .cluster
                 .chain(&key)
-                .cloned()
-                .map(State::from)
-                .ok_or_else(|| TCError::not_found(format!("{} member {}", self.cluster, key)))
+                .ok_or_else(|| TCError::not_found(format!("{} member {}", self.cluster, key)))?;
+
+            self.cluster.authorize_chain(txn, chain).await?;
+
+            Ok(State::from(chain.clone()))
         } else {
             let public_key = Bytes::from(self.cluster.public_key().to_vec());
             Ok(Value::from(public_key).into())
@@ -70,9 +346,7 @@ impl<'a> Handler<'a> for ClusterHandler<'a> {
     where
         'b: 'a,
     {
-        Some(Box::new(|_txn, key| {
-            Box::pin(future::ready(self.handle_get(key)))
-        }))
+        Some(Box::new(|txn, key| Box::pin(self.handle_get(txn, key))))
     }
 
     fn put<'b>(self: Box<Self>) -> Option<PutHandler<'a, 'b>>
@@ -97,10 +371,18 @@ impl<'a> Handler<'a> for ClusterHandler<'a> {
     where
         'b: 'a,
     {
-        Some(Box::new(|txn, params| {
+        Some(Box::new(|txn, mut params| {
             Box::pin(async move {
                 // TODO: authorize request using a scope
 
+                if params.contains_key(&PREPARE.into()) {
+                    let coordinator = params.require(&PREPARE.into())?;
+                    params.expect_empty()?;
+
+                    self.cluster.prepare(txn, coordinator).await?;
+                    return Ok(State::default());
+                }
+
                 if !params.is_empty() {
                     return Err(TCError::bad_request(
                         "unrecognized commit parameters",
@@ -108,9 +390,12 @@ impl<'a> Handler<'a> for ClusterHandler<'a> {
                     ));
                 }
 
-                if txn.is_leader(self.cluster.path()) {
+                if self.cluster.is_prepared(txn.id()).await {
+                    self.cluster.commit_prepared(txn).await?;
+                } else if txn.is_leader(self.cluster.path()) {
                     self.cluster.distribute_commit(txn).await?;
                 } else {
+                    self.cluster.refresh_views(txn).await?;
                     self.cluster.write_ahead(txn.id()).await;
                     self.cluster.commit(txn.id()).await;
                 }
@@ -131,7 +416,7 @@ impl<'a> Handler<'a> for ClusterHandler<'a> {
                 if txn.is_leader(self.cluster.path()) {
                     self.cluster.distribute_rollback(txn).await;
                 } else {
-                    self.cluster.finalize(txn.id()).await;
+                    self.cluster.rollback(txn.id()).await;
                 }
 
                 Ok(())
@@ -207,6 +492,146 @@ impl<'a> From<&'a Cluster> for InstallHandler<'a> {
     }
 }
 
+struct JournalHandler<'a> {
+    cluster: &'a Cluster,
+}
+
+impl<'a> Handler<'a> for JournalHandler<'a> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                let txn_id = cast_txn_id(key)?;
+                self.cluster.decision(&txn_id).map_ok(State::from).await
+            })
+        }))
+    }
+
+    fn delete<'b>(self: Box<Self>) -> Option<DeleteHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                let (txn_id, participant): (Value, Link) = key.try_cast_into(|v| {
+                    TCError::bad_request("expected a transaction ID and a participant, not", v)
+                })?;
+
+                let txn_id = cast_txn_id(txn_id)?;
+                self.cluster.acknowledge(txn_id, &participant).await
+            })
+        }))
+    }
+}
+
+impl<'a> From<&'a Cluster> for JournalHandler<'a> {
+    fn from(cluster: &'a Cluster) -> Self {
+        Self { cluster }
+    }
+}
+
+pub(super) fn cast_txn_id(key: Value) -> TCResult<TxnId> {
+    let txn_id: TCString =
+        key.try_cast_into(|v| TCError::bad_request("invalid transaction ID", v))?;
+
+    txn_id.as_str().parse()
+}
+
+struct ReplicateHandler<'a> {
+    cluster: &'a Cluster,
+}
+
+impl<'a> Handler<'a> for ReplicateHandler<'a> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                key.expect_none()?;
+                self.cluster.state(txn).map_ok(State::Map).await
+            })
+        }))
+    }
+}
+
+impl<'a> From<&'a Cluster> for ReplicateHandler<'a> {
+    fn from(cluster: &'a Cluster) -> Self {
+        Self { cluster }
+    }
+}
+
+struct ClassesHandler<'a> {
+    cluster: &'a Cluster,
+}
+
+impl<'a> Handler<'a> for ClassesHandler<'a> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                key.expect_none()?;
+
+                let classes = self
+                    .cluster
+                    .classes()
+                    .map(|(name, class)| (name.clone(), State::Object(class.clone().into())))
+                    .collect::<Map<State>>();
+
+                Ok(State::Map(classes))
+            })
+        }))
+    }
+}
+
+impl<'a> From<&'a Cluster> for ClassesHandler<'a> {
+    fn from(cluster: &'a Cluster) -> Self {
+        Self { cluster }
+    }
+}
+
+struct LibraryHandler {
+    link: Link,
+}
+
+impl<'a> Handler<'a> for LibraryHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| Box::pin(txn.get(self.link, key))))
+    }
+
+    fn put<'b>(self: Box<Self>) -> Option<PutHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key, value| {
+            Box::pin(txn.put(self.link, key, value))
+        }))
+    }
+
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, params| {
+            Box::pin(txn.post(self.link, State::Map(params)))
+        }))
+    }
+
+    fn delete<'b>(self: Box<Self>) -> Option<DeleteHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| Box::pin(txn.delete(self.link, key))))
+    }
+}
+
 struct ReplicaHandler<'a> {
     cluster: &'a Cluster,
 }
@@ -266,22 +691,97 @@ impl<'a> From<&'a Cluster> for ReplicaHandler<'a> {
     }
 }
 
+/// Routes reads of a materialized [`View`], which can only be written by refreshing it.
+struct ViewHandler<'a> {
+    handler: Box<dyn Handler<'a> + 'a>,
+}
+
+impl<'a> Handler<'a> for ViewHandler<'a> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        self.handler.get()
+    }
+
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        self.handler.post()
+    }
+}
+
+struct VerifyHandler<'a> {
+    cluster: &'a Cluster,
+}
+
+impl<'a> Handler<'a> for VerifyHandler<'a> {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                if params.contains_key(&BOUNDS.into()) {
+                    // another replica is verifying this one
+                    let bounds: Value = params.require(&BOUNDS.into())?;
+                    params.expect_empty()?;
+
+                    self.cluster.digest(&txn, bounds).map_ok(State::from).await
+                } else {
+                    params.expect_empty()?;
+                    self.cluster.verify(&txn).await
+                }
+            })
+        }))
+    }
+}
+
+impl<'a> From<&'a Cluster> for VerifyHandler<'a> {
+    fn from(cluster: &'a Cluster) -> Self {
+        Self { cluster }
+    }
+}
+
 impl Route for Cluster {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
         if path.is_empty() {
             Some(Box::new(ClusterHandler::from(self)))
         } else if let Some(chain) = self.chain(&path[0]) {
             debug!("Cluster has a Chain at {}", &path[0]);
-            chain.route(&path[1..])
+
+            if chain.subject().privacy().is_empty() {
+                route_chain(self, chain, &path[1..])
+            } else {
+                Some(Box::new(PrivacyHandler::new(self, chain, &path[1..])))
+            }
+        } else if let Some(view) = self.view(&path[0]) {
+            debug!("Cluster has a materialized View at {}", &path[0]);
+            route_view(view, &path[1..])
         } else if let Some(class) = self.class(&path[0]) {
             debug!("Cluster has a Class at {}", &path[0]);
             class.route(&path[1..])
+        } else if let Some(library) = self.library(&path[0]) {
+            debug!("Cluster depends on a Library at {}", &path[0]);
+            route_library(library, &path[1..])
+        } else if path.len() == 1 && path[0] == CLASSES {
+            Some(Box::new(ClassesHandler::from(self)))
+        } else if path.len() == 1 && path[0] == CLASS {
+            let link = Value::from(self.link().clone());
+            Some(Box::new(AttributeHandler::from(link)))
+        } else if path.len() == 1 && path[0] == SCHEMA {
+            let schema = reflect::describe_cluster(self);
+            Some(Box::new(AttributeHandler::from(schema)))
         } else if path.len() == 1 {
             match path[0].as_str() {
                 "authorize" => Some(Box::new(AuthorizeHandler::from(self))),
                 "grant" => Some(Box::new(GrantHandler::from(self))),
                 "install" => Some(Box::new(InstallHandler::from(self))),
+                "journal" => Some(Box::new(JournalHandler::from(self))),
                 "replicas" => Some(Box::new(ReplicaHandler::from(self))),
+                "replicate" => Some(Box::new(ReplicateHandler::from(self))),
+                "verify" => Some(Box::new(VerifyHandler::from(self))),
                 _ => None,
             }
         } else {
@@ -289,3 +789,42 @@ impl Route for Cluster {
         }
     }
 }
+
+fn route_library<'a>(
+    library: &'a Library,
+    path: &'a [PathSegment],
+) -> Option<Box<dyn Handler<'a> + 'a>> {
+    if path.is_empty() {
+        return None;
+    }
+
+    if let Some(class) = library.class(&path[0]) {
+        // serve the cached class definition without a network request
+        class.route(&path[1..])
+    } else {
+        let mut link = library.link().clone();
+        link.extend(path.iter().cloned());
+        Some(Box::new(LibraryHandler { link }))
+    }
+}
+
+fn route_view<'a>(view: &'a View, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+    if path == &["alter"] {
+        return None;
+    }
+
+    let handler = view.table().route(path)?;
+    Some(Box::new(ViewHandler { handler }))
+}
+
+fn route_chain<'a>(
+    cluster: &'a Cluster,
+    chain: &'a Chain,
+    path: &'a [PathSegment],
+) -> Option<Box<dyn Handler<'a> + 'a>> {
+    if chain.subject().column_access().is_empty() {
+        chain.route(path)
+    } else {
+        Some(Box::new(ColumnAccessHandler::new(cluster, chain, path)))
+    }
+}
@@ -205,7 +205,12 @@ impl<'a, T: BTreeInstance> Handler<'a> for CountHandler<'a, T> {
                     ));
                 }
 
-                self.btree.count(*txn.id()).map_ok(State::from).await
+                self.btree
+                    .clone()
+                    .with_isolation(txn.isolation())
+                    .count(*txn.id())
+                    .map_ok(State::from)
+                    .await
             })
         }))
     }
@@ -235,7 +240,8 @@ impl<'a, T: BTreeInstance> Handler<'a> for FirstHandler<'a, T> {
                     ));
                 }
 
-                let mut keys = self.btree.clone().keys(*txn.id()).await?;
+                let btree = self.btree.clone().with_isolation(txn.isolation());
+                let mut keys = btree.keys(*txn.id()).await?;
                 if let Some(values) = keys.try_next().await? {
                     let names = self.btree.schema().iter().map(|col| col.name()).cloned();
                     Ok(Map::from_iter(names.zip(values.into_iter().map(State::from))).into())
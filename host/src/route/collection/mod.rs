@@ -1,19 +1,27 @@
-use safecast::CastInto;
+use futures::TryFutureExt;
+use safecast::{CastInto, TryCastFrom};
 
-use tc_btree::BTreeInstance;
+use tc_btree::{BTreeInstance, BTreeType};
+use tc_error::*;
 use tc_table::TableInstance;
-use tc_value::Value;
-use tcgeneric::{PathSegment, Tuple};
+use tc_transact::fs::{CopyFrom, Dir};
+use tc_transact::Transaction;
+use tc_value::{Link, Value};
+use tcgeneric::{label, PathSegment, Tuple};
 
-use crate::collection::{Collection, CollectionType};
-use crate::route::GetHandler;
+use crate::collection::{BTreeFile, Collection, CollectionType, TableIndex};
+use crate::route::{GetHandler, PostHandler};
+use crate::state::State;
+use crate::txn::Txn;
 
 use super::{Handler, Route};
 
+pub(super) use table::{aggregate_key, cast_into_bounds, column_values};
+
 mod btree;
 mod table;
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 mod tensor;
 
 impl Route for CollectionType {
@@ -21,7 +29,7 @@ impl Route for CollectionType {
         match self {
             Self::BTree(btt) => btt.route(path),
             Self::Table(tt) => tt.route(path),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tt) => tt.route(path),
         }
     }
@@ -50,7 +58,7 @@ impl<'a> Handler<'a> for SchemaHandler<'a> {
 
                     Collection::Table(table) => table.schema().clone().cast_into(),
 
-                    #[cfg(feature = "tensor")]
+                    #[cfg(feature = "tc-tensor")]
                     Collection::Tensor(tensor) => tensor.schema().clone().cast_into(),
                 };
 
@@ -71,7 +79,7 @@ impl Route for Collection {
         let child_handler = match self {
             Self::BTree(btree) => btree.route(path),
             Self::Table(table) => table.route(path),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Self::Tensor(tensor) => tensor.route(path),
         };
 
@@ -90,6 +98,78 @@ impl Route for Collection {
     }
 }
 
+/// Copy the collection at a `source` [`Link`], which may be on another host, either into a new
+/// collection or, if a `target` [`Link`] is given, into an existing (e.g. hosted) collection.
+struct CopyFromHandler;
+
+impl<'a> Handler<'a> for CopyFromHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let source: Value = params.require(&label("source").into())?;
+                let source = Link::try_cast_from(source, |v| {
+                    TCError::bad_request("invalid link to a source collection", v)
+                })?;
+
+                let target: Value = params.or_default(&label("target").into())?;
+                params.expect_empty()?;
+
+                let collection = match txn.get(source.clone(), Value::None).await? {
+                    State::Collection(collection) => collection,
+                    other => {
+                        return Err(TCError::bad_request(
+                            format!("expected a collection at {} but found", source),
+                            other,
+                        ))
+                    }
+                };
+
+                if target.is_some() {
+                    let target = Link::try_cast_from(target, |v| {
+                        TCError::bad_request("invalid link to a target collection", v)
+                    })?;
+
+                    txn.put(target, Value::None, collection.into()).await?;
+                    Ok(State::default())
+                } else if source.host().is_some() {
+                    // a collection from another host is already decoded into a new collection
+                    Ok(State::Collection(collection))
+                } else {
+                    copy(txn, collection).map_ok(State::Collection).await
+                }
+            })
+        }))
+    }
+}
+
+async fn copy(txn: &Txn, collection: Collection) -> TCResult<Collection> {
+    let txn_id = *txn.id();
+
+    match collection {
+        Collection::BTree(btree) => {
+            let file = txn
+                .context()
+                .create_file_unique(txn_id, BTreeType::default())
+                .await?;
+
+            BTreeFile::copy_from(btree, file, txn)
+                .map_ok(Collection::from)
+                .await
+        }
+        Collection::Table(table) => {
+            let dir = txn.context().create_dir_unique(txn_id).await?;
+            TableIndex::copy_from(table, dir, txn)
+                .map_ok(Collection::from)
+                .await
+        }
+        #[cfg(feature = "tc-tensor")]
+        Collection::Tensor(tensor) => tensor.materialize(txn).map_ok(Collection::from).await,
+    }
+}
+
 pub(super) struct Static;
 
 impl Route for Static {
@@ -99,9 +179,10 @@ impl Route for Static {
         }
 
         match path[0].as_str() {
+            "copy_from" if path.len() == 1 => Some(Box::new(CopyFromHandler)),
             "btree" => btree::Static.route(&path[1..]),
             "table" => table::Static.route(&path[1..]),
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             "tensor" => tensor::Static.route(&path[1..]),
             _ => None,
         }
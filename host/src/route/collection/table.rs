@@ -1,22 +1,27 @@
-use futures::{future, StreamExt, TryFutureExt, TryStreamExt};
+use std::fmt;
+use std::iter::FromIterator;
+
+use bytes::Bytes;
+use futures::{future, stream, FutureExt, Stream, StreamExt, TryFutureExt, TryStreamExt};
 use log::debug;
 use safecast::*;
 
 use tc_error::*;
 use tc_table::{
-    Bounds, ColumnBound, Key, TableInstance, TableOrder, TableRead, TableSlice, TableStream,
-    TableType, TableWrite,
+    Alter, Bounds, Column, ColumnBound, Key, Limited, Row, TableInstance, TableOrder, TableRead,
+    TableSlice, TableStream, TableType, TableWrite, Values,
 };
 use tc_transact::fs::Dir;
-use tc_transact::Transaction;
-use tc_value::{Bound, Value};
-use tcgeneric::{label, Id, Map, PathSegment};
+use tc_transact::{Transaction, TxnId};
+use tc_value::{Bound, Number, NumberType, TCString, UIntType, Value, ValueType};
+use tcgeneric::{label, Id, Map, PathSegment, TCBoxTryStream, Tuple};
 
 use crate::collection::{Collection, Table, TableIndex};
 use crate::route::{DeleteHandler, GetHandler, Handler, PostHandler, PutHandler, Route};
 use crate::scalar::Scalar;
 use crate::state::State;
 use crate::stream::TCStream;
+use crate::txn::Txn;
 
 impl Route for TableType {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
@@ -28,6 +33,58 @@ impl Route for TableType {
     }
 }
 
+struct AlterHandler<'a> {
+    table: &'a TableIndex,
+}
+
+impl<'a> Handler<'a> for AlterHandler<'a> {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let add = label("add").into();
+                let drop = label("drop").into();
+                let rename = label("rename").into();
+
+                let alter = if params.contains_key(&add) {
+                    let column: Value = params.require(&add)?;
+                    let column = column
+                        .try_cast_into(|v| TCError::bad_request("invalid column to add", v))?;
+
+                    let default = params.or_default(&label("default").into())?;
+                    Alter::Add(column, default)
+                } else if params.contains_key(&drop) {
+                    let name: Value = params.require(&drop)?;
+                    let name =
+                        name.try_cast_into(|v| TCError::bad_request("invalid column to drop", v))?;
+
+                    Alter::Drop(name)
+                } else if params.contains_key(&rename) {
+                    let names: Value = params.require(&rename)?;
+                    let (from, to) = names.try_cast_into(|v| {
+                        TCError::bad_request("expected a (from, to) tuple of column names", v)
+                    })?;
+
+                    Alter::Rename(from, to)
+                } else {
+                    return Err(TCError::bad_request(
+                        "expected a column to add, drop, or rename, not",
+                        params,
+                    ));
+                };
+
+                params.expect_empty()?;
+
+                let dir = txn.context().create_dir_unique(*txn.id()).await?;
+                let table = self.table.alter(&dir, alter, *txn.id()).await?;
+                Ok(Collection::Table(table.into()).into())
+            })
+        }))
+    }
+}
+
 struct CopyHandler;
 
 impl<'a> Handler<'a> for CopyHandler {
@@ -43,33 +100,28 @@ impl<'a> Handler<'a> for CopyHandler {
                 })?;
 
                 let source: TCStream = params.require(&label("source").into())?;
+                let rules = params
+                    .or_default(&label("rules").into())
+                    .and_then(cast_rules)?;
                 params.expect_empty()?;
 
-                let txn_id = *txn.id();
+                let schema = schema.with_validation(rules)?;
 
                 let dir = txn.context().create_dir_unique(*txn.id()).await?;
                 let table = TableIndex::create(&dir, schema, *txn.id()).await?;
+                let primary = table.schema().primary().clone();
 
                 let rows = source.into_stream(txn.clone()).await?;
-                rows.map(|r| {
-                    r.and_then(|state| {
-                        Value::try_cast_from(state, |s| {
-                            TCError::bad_request("invalid Table row", s)
-                        })
-                    })
+                ingest(txn, table, rows, |state| {
+                    let value = Value::try_cast_from(state, |s| {
+                        TCError::bad_request("invalid Table row", s)
+                    })?;
+
+                    let row =
+                        value.try_cast_into(|v| TCError::bad_request("invalid Table row", v))?;
+                    primary.key_values_from_tuple(row)
                 })
-                .map(|r| {
-                    r.and_then(|value| {
-                        value.try_cast_into(|v| TCError::bad_request("invalid Table row", v))
-                    })
-                })
-                .map(|r| r.and_then(|row| table.schema().primary().key_values_from_tuple(row)))
-                .map_ok(|(key, values)| table.upsert(txn_id, key, values))
-                .try_buffer_unordered(num_cpus::get())
-                .try_fold((), |(), ()| future::ready(Ok(())))
-                .await?;
-
-                Ok(State::Collection(table.into()))
+                .await
             })
         }))
     }
@@ -98,11 +150,74 @@ impl<'a> Handler<'a> for CreateHandler {
     }
 }
 
+/// An aggregate of the values of a `Table` column.
+#[derive(Clone, Copy)]
+enum Aggregate {
+    Sum,
+    Mean,
+}
+
+struct AggregateHandler<T> {
+    table: T,
+    aggregate: Aggregate,
+}
+
+impl<T> AggregateHandler<T> {
+    fn new(table: T, aggregate: Aggregate) -> Self {
+        Self { table, aggregate }
+    }
+}
+
+impl<'a, T: TableInstance + 'a> Handler<'a> for AggregateHandler<T>
+where
+    Table: From<T>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let (column, bounds) = aggregate_key(key)?;
+                let table = Table::from(self.table).with_isolation(txn.isolation());
+                let values = column_values(table, *txn.id(), column, bounds).await?;
+
+                let (count, sum) = values
+                    .try_fold((0u64, None), |(count, sum), value| {
+                        if value.is_none() {
+                            return future::ready(Ok((count, sum)));
+                        }
+
+                        let value = Number::try_cast_from(value, |v| {
+                            TCError::bad_request("cannot aggregate a non-numeric value", v)
+                        });
+
+                        future::ready(value.map(|value| match sum {
+                            Some(sum) => (count + 1, Some(sum + value)),
+                            None => (count + 1, Some(value)),
+                        }))
+                    })
+                    .await?;
+
+                let aggregate = match (self.aggregate, sum) {
+                    (_, None) => Value::None,
+                    (Aggregate::Sum, Some(sum)) => Value::Number(sum),
+                    (Aggregate::Mean, Some(sum)) => {
+                        Value::Number((f64::cast_from(sum) / count as f64).into())
+                    }
+                };
+
+                Ok(State::from(aggregate))
+            })
+        }))
+    }
+}
+
 struct ContainsHandler<'a, T> {
     table: &'a T,
 }
 
-impl<'a, T: TableRead + 'a> Handler<'a> for ContainsHandler<'a, T> {
+impl<'a, T: TableRead + Clone + 'a> Handler<'a> for ContainsHandler<'a, T> {
     fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
     where
         'b: 'a,
@@ -110,7 +225,8 @@ impl<'a, T: TableRead + 'a> Handler<'a> for ContainsHandler<'a, T> {
         Some(Box::new(|txn, key| {
             Box::pin(async move {
                 let key = primary_key(key, self.table)?;
-                let row = self.table.read(txn.id(), &key).await?;
+                let table = self.table.clone().with_isolation(txn.isolation());
+                let row = table.read(txn.id(), &key).await?;
                 Ok(Value::from(row.is_some()).into())
             })
         }))
@@ -137,11 +253,13 @@ where
     {
         Some(Box::new(|txn, key| {
             Box::pin(async move {
+                let table = self.table.with_isolation(txn.isolation());
+
                 if key.is_none() {
-                    self.table.count(*txn.id()).map_ok(State::from).await
+                    table.count(*txn.id()).map_ok(State::from).await
                 } else {
                     let bounds = cast_into_bounds(Scalar::Value(key))?;
-                    let slice = self.table.slice(bounds)?;
+                    let slice = table.slice(bounds)?;
                     slice.count(*txn.id()).map_ok(State::from).await
                 }
             })
@@ -155,12 +273,50 @@ impl<T> From<T> for CountHandler<T> {
     }
 }
 
+struct CountDistinctHandler<T> {
+    table: T,
+}
+
+impl<'a, T: TableInstance + 'a> Handler<'a> for CountDistinctHandler<T>
+where
+    Table: From<T>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let (column, bounds) = aggregate_key(key)?;
+                let table = Table::from(self.table).with_isolation(txn.isolation());
+                let table = if bounds.is_empty() {
+                    table
+                } else {
+                    table.slice(bounds)?
+                };
+
+                table
+                    .count_distinct(*txn.id(), column)
+                    .map_ok(State::from)
+                    .await
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for CountDistinctHandler<T> {
+    fn from(table: T) -> Self {
+        Self { table }
+    }
+}
+
 struct LimitHandler<T> {
     table: T,
 }
 
 impl<'a, T: TableStream + 'a> Handler<'a> for LimitHandler<T>
 where
+    Table: From<T>,
     Table: From<T::Limit>,
 {
     fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
@@ -169,11 +325,26 @@ where
     {
         Some(Box::new(|_txn, key| {
             Box::pin(async move {
-                let limit = key.try_cast_into(|v| {
-                    TCError::bad_request("limit must be a positive integer, not", v)
-                })?;
+                let (limit, offset) = if key.matches::<(u64, u64)>() {
+                    key.opt_cast_into().unwrap()
+                } else {
+                    let limit = key.try_cast_into(|v| {
+                        TCError::bad_request(
+                            "limit must be a positive integer or a (limit, offset) tuple, not",
+                            v,
+                        )
+                    })?;
+
+                    (limit, 0)
+                };
 
-                Ok(Collection::Table(self.table.limit(limit).into()).into())
+                let table: Table = if offset == 0 {
+                    self.table.limit(limit).into()
+                } else {
+                    Limited::page(self.table, offset, limit).into()
+                };
+
+                Ok(Collection::Table(table).into())
             })
         }))
     }
@@ -188,7 +359,269 @@ impl<T> From<T> for LimitHandler<T> {
 struct LoadHandler;
 
 impl<'a> Handler<'a> for LoadHandler {
-    // TODO
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let source: State = params.require(&label("source").into())?;
+                let key: Value = params.or_default(&label("key").into())?;
+                let dtypes: Map<Value> = params.or_default(&label("dtypes").into())?;
+                let sample_size: Value = params.or_default(&label("sample").into())?;
+                let rules = params
+                    .or_default(&label("rules").into())
+                    .and_then(cast_rules)?;
+                params.expect_empty()?;
+
+                let key = if key.is_none() {
+                    vec![]
+                } else if key.matches::<Id>() {
+                    vec![key.opt_cast_into().unwrap()]
+                } else {
+                    key.try_cast_into(|v| TCError::bad_request("invalid primary key", v))?
+                };
+
+                let dtypes = dtypes
+                    .into_iter()
+                    .map(|(name, dtype)| {
+                        ValueType::try_cast_from(dtype, |v| {
+                            TCError::bad_request("invalid column type", v)
+                        })
+                        .map(|dtype| (name, dtype))
+                    })
+                    .collect::<TCResult<Map<ValueType>>>()?;
+
+                let sample_size = if sample_size.is_none() {
+                    tc_table::DEFAULT_SAMPLE_SIZE
+                } else {
+                    usize::try_cast_from(sample_size, |v| {
+                        TCError::bad_request("invalid sample size", v)
+                    })?
+                };
+
+                let mut inference = tc_table::SchemaInference::new(key, dtypes);
+
+                let mut rows: TCBoxTryStream<'static, Row> = if source.matches::<TCString>() {
+                    let csv = TCString::opt_cast_from(source).unwrap();
+                    let rows = tc_table::parse_csv(csv.as_str())?;
+
+                    // the whole document is already in memory, so every row can widen a column
+                    for row in &rows {
+                        inference.measure(row);
+                    }
+
+                    Box::pin(stream::iter(rows.into_iter().map(TCResult::Ok)))
+                } else {
+                    let source = TCStream::try_cast_from(source, |s| {
+                        TCError::bad_request("invalid source of Table rows", s)
+                    })?;
+
+                    let rows = source.into_stream(txn.clone()).await?;
+                    Box::pin(rows.map(|r| {
+                        r.and_then(|state| {
+                            Row::try_cast_from(state, |s| {
+                                TCError::bad_request("invalid Table row", s)
+                            })
+                        })
+                    }))
+                };
+
+                let mut sample = Vec::with_capacity(sample_size);
+                while sample.len() < sample_size {
+                    if let Some(row) = rows.try_next().await? {
+                        inference.sample(&row);
+                        sample.push(row);
+                    } else {
+                        break;
+                    }
+                }
+
+                let schema = inference.schema()?.with_validation(rules)?;
+                debug!("inferred Table schema {}", schema.primary());
+
+                let txn_id = *txn.id();
+                let dir = txn.context().create_dir_unique(txn_id).await?;
+                let table = TableIndex::create(&dir, schema, txn_id).await?;
+                let primary = table.schema().primary().clone();
+
+                let rows = stream::iter(sample.into_iter().map(TCResult::Ok)).chain(rows);
+                ingest(txn, table, rows, |row| {
+                    primary.key_values_from_row(row, true)
+                })
+                .await
+            })
+        }))
+    }
+}
+
+/// The default number of NDJSON rows to insert concurrently.
+const NDJSON_BATCH_SIZE: usize = 1_000;
+
+struct NdjsonHandler<'a> {
+    table: &'a TableIndex,
+}
+
+impl<'a> Handler<'a> for NdjsonHandler<'a> {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let source: TCString = params.require(&label("source").into())?;
+                let batch_size: Value = params.or_default(&label("batch_size").into())?;
+                params.expect_empty()?;
+
+                let batch_size = if batch_size.is_none() {
+                    NDJSON_BATCH_SIZE
+                } else {
+                    usize::try_cast_from(batch_size, |v| {
+                        TCError::bad_request("invalid batch size", v)
+                    })?
+                };
+
+                if batch_size == 0 {
+                    return Err(TCError::bad_request("invalid batch size", batch_size));
+                }
+
+                ingest_ndjson(txn, self.table, source.as_str(), batch_size).await
+            })
+        }))
+    }
+}
+
+/// Insert each line of the given newline-delimited JSON into the given `table`.
+///
+/// Each line must be a JSON object which maps column names to values, or a JSON list of the
+/// values of every column in order. Lines are parsed as they're inserted, in batches of up to
+/// `batch_size` rows. A line which can't be parsed or which can't be inserted doesn't abort the
+/// load; the result is a report of the number of rows loaded and a list of `(line, reason)`
+/// tuples, one for each rejected line.
+async fn ingest_ndjson(
+    txn: &Txn,
+    table: &TableIndex,
+    ndjson: &str,
+    batch_size: usize,
+) -> TCResult<State> {
+    let schema = table.schema();
+
+    // rows must be inserted one at a time in order to check them against each other
+    let concurrency = if schema.unique().is_empty() && schema.references().is_empty() {
+        num_cpus::get()
+    } else {
+        1
+    };
+
+    let mut lines = ndjson
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i as u64 + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .peekable();
+
+    let mut loaded = 0u64;
+    let mut errors = Vec::new();
+    while lines.peek().is_some() {
+        let mut batch = Vec::with_capacity(batch_size);
+        for (number, line) in lines.by_ref().take(batch_size) {
+            match parse_ndjson_row(schema.primary(), line).await {
+                Ok(row) => batch.push((number, row)),
+                Err(cause) => errors.push((number, cause)),
+            }
+        }
+
+        let inserted = stream::iter(batch)
+            .map(|(number, (key, values))| {
+                insert_ndjson_row(txn, table, key, values).map(move |r| (number, r))
+            })
+            .buffer_unordered(concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        for (number, result) in inserted {
+            match result {
+                Ok(()) => loaded += 1,
+                Err(cause) => errors.push((number, cause)),
+            }
+        }
+
+        debug!("loaded {} NDJSON rows into {}", loaded, schema.primary());
+    }
+
+    if let Some(i) = errors.iter().position(|(_, cause)| !is_row_error(cause)) {
+        return Err(errors.swap_remove(i).1);
+    }
+
+    errors.sort_by_key(|(number, _)| *number);
+
+    let errors = errors
+        .into_iter()
+        .map(|(number, cause)| {
+            let reason = Value::String(cause.message().to_string().into());
+            Value::Tuple(vec![Value::from(number), reason].into())
+        })
+        .collect();
+
+    let report = vec![
+        (label("loaded"), Value::from(loaded)),
+        (label("errors"), Value::Tuple(errors)),
+    ];
+
+    Ok(State::Map(
+        report
+            .into_iter()
+            .map(|(name, value)| (name.into(), State::from(value)))
+            .collect(),
+    ))
+}
+
+async fn parse_ndjson_row(primary: &tc_table::IndexSchema, line: &str) -> TCResult<(Key, Values)> {
+    let json = Bytes::copy_from_slice(line.as_bytes());
+    let row: Scalar = destream_json::decode((), stream::once(future::ready(json)))
+        .await
+        .map_err(|e| TCError::bad_request("invalid JSON", e))?;
+
+    if row.matches::<Row>() {
+        let row = row.opt_cast_into().unwrap();
+        primary.key_values_from_row(row, true)
+    } else if row.matches::<Tuple<Value>>() {
+        let row = row.opt_cast_into().unwrap();
+        primary.key_values_from_tuple(row)
+    } else {
+        Err(TCError::bad_request("invalid Table row", row))
+    }
+}
+
+async fn insert_ndjson_row(
+    txn: &Txn,
+    table: &TableIndex,
+    key: Key,
+    values: Values,
+) -> TCResult<()> {
+    let schema = table.schema();
+
+    if !schema.validation().is_empty() || !schema.references().is_empty() {
+        let row = schema
+            .primary()
+            .row_from_key_values(key.clone(), values.clone())?;
+
+        if let Some(reason) = tc_table::violations(schema.validation(), &row) {
+            return Err(TCError::bad_request("row failed validation", reason));
+        }
+
+        check_references(txn, table, &row).await?;
+    }
+
+    table.upsert(*txn.id(), key, values).await
+}
+
+/// Return `true` if the given error means that a single row was rejected, rather than the load.
+fn is_row_error(cause: &TCError) -> bool {
+    match cause.code() {
+        ErrorType::BadRequest | ErrorType::Conflict | ErrorType::NotFound => true,
+        _ => false,
+    }
 }
 
 struct OrderHandler<T> {
@@ -227,6 +660,42 @@ impl<T> From<T> for OrderHandler<T> {
     }
 }
 
+#[cfg(feature = "tc-tensor")]
+struct TensorHandler<T> {
+    table: T,
+}
+
+#[cfg(feature = "tc-tensor")]
+impl<'a, T: TableInstance + 'a> Handler<'a> for TensorHandler<T>
+where
+    Table: From<T>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let columns = key.try_cast_into(|v| {
+                    TCError::bad_request("invalid column list for tensor view", v)
+                })?;
+
+                let table = Table::from(self.table);
+                let view = tc_tensor::BlockListTable::new(table, columns, *txn.id()).await?;
+                let tensor = crate::collection::DenseTensor::from(view);
+                Ok(Collection::from(tensor).into())
+            })
+        }))
+    }
+}
+
+#[cfg(feature = "tc-tensor")]
+impl<T> From<T> for TensorHandler<T> {
+    fn from(table: T) -> Self {
+        Self { table }
+    }
+}
+
 struct TableHandler<'a, T> {
     table: &'a T,
 }
@@ -246,6 +715,8 @@ where
                 if key.is_some() {
                     let key = primary_key(key.clone(), self.table)?;
                     self.table
+                        .clone()
+                        .with_isolation(txn.isolation())
                         .read(txn.id(), &key)
                         .map_ok(Value::from)
                         .map_ok(State::from)
@@ -265,6 +736,12 @@ where
             Box::pin(async move {
                 debug!("Table PUT {:?} <- {:?}", key, values);
 
+                if key.is_none() {
+                    if let State::Collection(Collection::Table(source)) = values {
+                        return upsert_from(txn, self.table, source).await;
+                    }
+                }
+
                 let key = primary_key(key, self.table)?;
 
                 if values.is_map() {
@@ -280,6 +757,15 @@ where
                         })
                         .collect::<TCResult<Map<Value>>>()?;
 
+                    let schema = self.table.schema();
+                    if !schema.references().is_empty() {
+                        if let Some(row) = self.table.read(txn.id(), &key).await? {
+                            let mut row = schema.primary().row_from_values(row)?;
+                            row.extend(values.clone());
+                            check_references(txn, self.table, &row).await?;
+                        }
+                    }
+
                     self.table.update(*txn.id(), key, values).await
                 } else if values.is_tuple() {
                     let values =
@@ -292,6 +778,16 @@ where
                         })
                         .collect::<TCResult<Vec<Value>>>()?;
 
+                    let schema = self.table.schema();
+                    if !schema.references().is_empty() {
+                        let primary = schema.primary();
+                        let row = schema.values_with_defaults(values.to_vec())?;
+                        let row = primary.validate_values(row)?;
+                        let row = primary.row_from_key_values(key.to_vec(), row)?;
+
+                        check_references(txn, self.table, &row).await?;
+                    }
+
                     self.table.upsert(*txn.id(), key, values).await
                 } else {
                     Err(TCError::bad_request("invalid row values", values))
@@ -331,6 +827,95 @@ where
     }
 }
 
+struct QueryHandler<T> {
+    table: T,
+}
+
+impl<'a, T: TableInstance + 'a> Handler<'a> for QueryHandler<T>
+where
+    Table: From<T>,
+{
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, mut params| {
+            Box::pin(async move {
+                let query: TCString = params.require(&label("query").into())?;
+                params.expect_empty()?;
+
+                let query: tc_table::Query = query.as_str().parse()?;
+                debug!("Table query with bounds {}", query.bounds);
+
+                let grouped = query.is_grouped();
+                let table = query.apply(Table::from(self.table))?;
+
+                if grouped {
+                    Ok(TCStream::from(table).aggregate().into())
+                } else {
+                    Ok(Collection::Table(table).into())
+                }
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for QueryHandler<T> {
+    fn from(table: T) -> Self {
+        Self { table }
+    }
+}
+
+struct SampleHandler<T> {
+    table: T,
+}
+
+impl<'a, T: TableInstance + 'a> Handler<'a> for SampleHandler<T>
+where
+    Table: From<T>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let (size, seed) = if key.matches::<(u64, u64)>() {
+                    let (size, seed) = key.opt_cast_into().unwrap();
+                    (size, Some(seed))
+                } else {
+                    let size = key.try_cast_into(|v| {
+                        TCError::bad_request(
+                            "sample size must be a positive integer or a (size, seed) tuple, not",
+                            v,
+                        )
+                    })?;
+
+                    (size, None)
+                };
+
+                if size > MAX_PAGE_SIZE {
+                    return Err(TCError::bad_request(
+                        format!("sample size must be at most {}, not", MAX_PAGE_SIZE),
+                        size,
+                    ));
+                }
+
+                let table = Table::from(self.table).with_isolation(txn.isolation());
+                let rows = table.sample(*txn.id(), size as usize, seed).await?;
+                let rows = rows.into_iter().map(Value::from).collect();
+                Ok(Value::Tuple(rows).into())
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for SampleHandler<T> {
+    fn from(table: T) -> Self {
+        Self { table }
+    }
+}
+
 struct SchemaHandler<'a, T> {
     table: &'a T,
     schema: fn(&'a T) -> Value,
@@ -385,6 +970,140 @@ impl<T> From<T> for SelectHandler<T> {
     }
 }
 
+/// The maximum number of rows in a single page of a table.
+const MAX_PAGE_SIZE: u64 = 10_000;
+
+/// An opaque continuation token which resumes a paginated scan of a table after the last key seen.
+///
+/// The token also records the ID of the transaction which issued it. Resuming a scan in the same
+/// transaction reads the same snapshot; resuming it in a later transaction continues after the
+/// last key seen, so that no row which is present in both snapshots is repeated or skipped.
+struct Cursor {
+    txn_id: TxnId,
+    key: Key,
+}
+
+impl Cursor {
+    async fn decode(encoded: &str) -> TCResult<Self> {
+        let encoded = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .map_err(|e| TCError::bad_request("invalid table cursor", e))?;
+
+        let source = stream::once(future::ready(Ok::<Bytes, TCError>(Bytes::from(encoded))));
+        let (txn_id, key) = tbon::de::try_decode((), source)
+            .map_err(|e| TCError::bad_request("invalid table cursor", e))
+            .await?;
+
+        Ok(Self { txn_id, key })
+    }
+
+    async fn encode(self) -> TCResult<String> {
+        let encoded = tbon::en::encode((self.txn_id, self.key)).map_err(TCError::internal)?;
+        let encoded = encoded
+            .map_err(TCError::internal)
+            .try_fold(Vec::new(), |mut buffer, chunk| {
+                buffer.extend_from_slice(&chunk);
+                future::ready(Ok(buffer))
+            })
+            .await?;
+
+        Ok(base64::encode_config(encoded, base64::URL_SAFE_NO_PAD))
+    }
+}
+
+struct PageHandler<T> {
+    table: T,
+}
+
+impl<'a, T: TableInstance + 'a> Handler<'a> for PageHandler<T>
+where
+    Table: From<T>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let (limit, cursor) = if key.matches::<(u64, TCString)>() {
+                    let (limit, cursor): (u64, TCString) = key.opt_cast_into().unwrap();
+                    (limit, Some(Cursor::decode(cursor.as_str()).await?))
+                } else {
+                    let limit = key.try_cast_into(|v| {
+                        TCError::bad_request(
+                            "table page size must be a positive integer, optionally with a cursor, not",
+                            v,
+                        )
+                    })?;
+
+                    (limit, None)
+                };
+
+                if limit == 0 || limit > MAX_PAGE_SIZE {
+                    return Err(TCError::bad_request(
+                        format!(
+                            "table page size must be between 1 and {}, not",
+                            MAX_PAGE_SIZE
+                        ),
+                        limit,
+                    ));
+                }
+
+                let txn_id = *txn.id();
+                let table = Table::from(self.table).with_isolation(txn.isolation());
+                let key_columns = table.key().to_vec();
+
+                let rows: TCBoxTryStream<Vec<Value>> = if let Some(cursor) = cursor {
+                    if cursor.txn_id > txn_id {
+                        return Err(TCError::bad_request(
+                            "table cursor was issued by a later transaction",
+                            cursor.txn_id,
+                        ));
+                    }
+
+                    let mut pages = Vec::new();
+                    for bounds in Bounds::after_key(cursor.key, &key_columns)? {
+                        let slice = table.clone().slice(bounds)?;
+                        pages.push(slice.rows(txn_id).await?);
+                    }
+
+                    Box::pin(stream::iter(pages).flatten())
+                } else {
+                    let key_names = key_columns.iter().map(|col| &col.name).cloned().collect();
+                    table.order_by(key_names, false)?.rows(txn_id).await?
+                };
+
+                let mut rows: Vec<Vec<Value>> = rows.take(limit as usize + 1).try_collect().await?;
+
+                let cursor = if rows.len() as u64 > limit {
+                    rows.pop();
+                    let last = rows.last().expect("last row");
+                    let cursor = Cursor {
+                        txn_id,
+                        key: last[..key_columns.len()].to_vec(),
+                    };
+
+                    Value::String(cursor.encode().await?.into())
+                } else {
+                    Value::None
+                };
+
+                let rows = rows.into_iter().map(Value::from_iter).map(State::from);
+
+                let mut page = Map::new();
+                page.insert(label("rows").into(), State::Tuple(rows.collect()));
+                page.insert(label("cursor").into(), cursor.into());
+                Ok(State::Map(page))
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for PageHandler<T> {
+    fn from(table: T) -> Self {
+        Self { table }
+    }
+}
+
 struct StreamHandler<T> {
     table: T,
 }
@@ -444,13 +1163,22 @@ impl<'a, T> From<&'a T> for TableHandler<'a, T> {
 
 impl Route for Table {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
-        route(self, path)
+        match self {
+            Self::Table(table) if path == &["alter"] => Some(Box::new(AlterHandler { table })),
+            other => route(other, path),
+        }
     }
 }
 
 impl Route for TableIndex {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
-        route(self, path)
+        if path == &["alter"] {
+            Some(Box::new(AlterHandler { table: self }))
+        } else if path == &["load_ndjson"] {
+            Some(Box::new(NdjsonHandler { table: self }))
+        } else {
+            route(self, path)
+        }
     }
 }
 
@@ -469,15 +1197,29 @@ where
         Some(Box::new(TableHandler::from(table)))
     } else if path.len() == 1 {
         match path[0].as_str() {
+            #[cfg(feature = "tc-tensor")]
+            "as_tensor" => Some(Box::new(TensorHandler::from(table.clone()))),
             "columns" => Some(Box::new(SchemaHandler::new(table, column_schema))),
             "contains" => Some(Box::new(ContainsHandler::from(table))),
             "count" => Some(Box::new(CountHandler::from(table.clone()))),
+            "count_distinct" => Some(Box::new(CountDistinctHandler::from(table.clone()))),
             "key_columns" => Some(Box::new(SchemaHandler::new(table, key_columns))),
             "key_names" => Some(Box::new(SchemaHandler::new(table, key_names))),
             "limit" => Some(Box::new(LimitHandler::from(table.clone()))),
+            "mean" => Some(Box::new(AggregateHandler::new(
+                table.clone(),
+                Aggregate::Mean,
+            ))),
             "order" => Some(Box::new(OrderHandler::from(table.clone()))),
+            "page" => Some(Box::new(PageHandler::from(table.clone()))),
+            "query" => Some(Box::new(QueryHandler::from(table.clone()))),
+            "sample" => Some(Box::new(SampleHandler::from(table.clone()))),
             "select" => Some(Box::new(SelectHandler::from(table.clone()))),
             "rows" => Some(Box::new(StreamHandler::from(table.clone()))),
+            "sum" => Some(Box::new(AggregateHandler::new(
+                table.clone(),
+                Aggregate::Sum,
+            ))),
             _ => None,
         }
     } else {
@@ -493,14 +1235,174 @@ impl Route for Static {
             Some(Box::new(CreateHandler))
         } else if path == &["copy_from"] {
             Some(Box::new(CopyHandler))
+        } else if path == &["load"] {
+            Some(Box::new(LoadHandler))
         } else {
             None
         }
     }
 }
 
+/// Insert each of the given `rows` into the given `table`.
+///
+/// If the `table` has validation rules, each row which cannot be parsed or which violates a rule is
+/// written to a new quarantine `Table` together with the reason it was rejected, instead of
+/// aborting the load, and the result is a summary report which includes both tables.
+async fn ingest<T, S, P>(txn: &Txn, table: TableIndex, mut rows: S, parse: P) -> TCResult<State>
+where
+    T: fmt::Display + Send,
+    S: Stream<Item = TCResult<T>> + Send + Unpin,
+    P: Fn(T) -> TCResult<(Key, Values)> + Send + Sync,
+{
+    let txn_id = *txn.id();
+    let schema = table.schema();
+
+    if schema.validation().is_empty() {
+        // rows must be inserted one at a time in order to check them against each other
+        let concurrency = if schema.unique().is_empty() && schema.references().is_empty() {
+            num_cpus::get()
+        } else {
+            1
+        };
+
+        let target = &table;
+        rows.map(|r| r.and_then(|row| parse(row)))
+            .map_ok(|(key, values)| async move {
+                if !target.schema().references().is_empty() {
+                    let row = target
+                        .schema()
+                        .primary()
+                        .row_from_key_values(key.clone(), values.clone())?;
+
+                    check_references(txn, target, &row).await?;
+                }
+
+                target.upsert(txn_id, key, values).await
+            })
+            .try_buffer_unordered(concurrency)
+            .try_fold((), |(), ()| future::ready(Ok(())))
+            .await?;
+
+        return Ok(State::Collection(table.into()));
+    }
+
+    let quarantine = {
+        let row = ValueType::Number(NumberType::UInt(UIntType::U64));
+        let schema = tc_table::IndexSchema::from((
+            vec![Column::from((Id::from(label("row")), row))],
+            vec![
+                Column::from((Id::from(label("source")), ValueType::String)),
+                Column::from((Id::from(label("reason")), ValueType::String)),
+            ],
+        ));
+
+        let dir = txn.context().create_dir_unique(txn_id).await?;
+        TableIndex::create(&dir, schema.into(), txn_id).await?
+    };
+
+    let (mut loaded, mut quarantined) = (0u64, 0u64);
+    for position in 0u64.. {
+        let row = match rows.try_next().await? {
+            Some(row) => row,
+            None => break,
+        };
+
+        let source = row.to_string();
+        let row = parse(row).and_then(|(key, values)| {
+            let row = schema
+                .primary()
+                .row_from_key_values(key.clone(), values.clone())?;
+
+            match tc_table::violations(schema.validation(), &row) {
+                None => Ok((key, values)),
+                Some(reason) => Err(TCError::bad_request("row failed validation", reason)),
+            }
+        });
+
+        match row {
+            Ok((key, values)) => {
+                if !schema.references().is_empty() {
+                    let row = schema
+                        .primary()
+                        .row_from_key_values(key.clone(), values.clone())?;
+
+                    check_references(txn, &table, &row).await?;
+                }
+
+                table.upsert(txn_id, key, values).await?;
+                loaded += 1;
+            }
+            Err(cause) if cause.code() == ErrorType::BadRequest => {
+                debug!("quarantine row {}: {}", position, cause);
+
+                let key = vec![Value::from(position)];
+                let reason = cause.message().to_string();
+                let values = vec![Value::String(source.into()), Value::String(reason.into())];
+                quarantine.upsert(txn_id, key, values).await?;
+                quarantined += 1;
+            }
+            Err(cause) => return Err(cause),
+        }
+    }
+
+    let report = vec![
+        (label("table"), State::Collection(table.into())),
+        (label("quarantine"), State::Collection(quarantine.into())),
+        (label("loaded"), Value::from(loaded).into()),
+        (label("quarantined"), Value::from(quarantined).into()),
+    ];
+
+    Ok(State::Map(
+        report
+            .into_iter()
+            .map(|(name, state)| (name.into(), state))
+            .collect(),
+    ))
+}
+
+fn cast_rules(rules: Value) -> TCResult<Vec<tc_table::ValidationRule>> {
+    if rules.is_none() {
+        Ok(vec![])
+    } else {
+        rules.try_cast_into(|v| TCError::bad_request("invalid Table validation rules", v))
+    }
+}
+
+/// Parse the key of an aggregate request: a column name, optionally with the [`Bounds`] of the
+/// rows to aggregate.
+pub(crate) fn aggregate_key(key: Value) -> TCResult<(Id, Bounds)> {
+    if key.matches::<(Id, Value)>() {
+        let (column, bounds): (Id, Value) = key.opt_cast_into().unwrap();
+        cast_into_bounds(Scalar::Value(bounds)).map(|bounds| (column, bounds))
+    } else {
+        let column = key.try_cast_into(|v| {
+            TCError::bad_request("expected a column name to aggregate, not", v)
+        })?;
+
+        Ok((column, Bounds::default()))
+    }
+}
+
+/// Stream the values of the given `column` of the rows of `table` within the given `bounds`.
+pub(crate) async fn column_values(
+    table: Table,
+    txn_id: TxnId,
+    column: Id,
+    bounds: Bounds,
+) -> TCResult<TCBoxTryStream<'static, Value>> {
+    let table = if bounds.is_empty() {
+        table
+    } else {
+        table.slice(bounds)?
+    };
+
+    let rows = table.select(vec![column])?.rows(txn_id).await?;
+    let values = rows.map_ok(|mut row| row.pop().unwrap_or_default());
+    Ok(Box::pin(values))
+}
+
 #[inline]
-fn cast_into_bounds(scalar: Scalar) -> TCResult<Bounds> {
+pub(crate) fn cast_into_bounds(scalar: Scalar) -> TCResult<Bounds> {
     if scalar.is_none() {
         return Ok(Bounds::default());
     }
@@ -533,6 +1435,77 @@ fn primary_key<T: TableInstance>(key: Value, table: &T) -> TCResult<Key> {
     table.schema().primary().validate_key(key)
 }
 
+/// Insert (or update) each row of the `source` table into the given `table`, matching the
+/// columns of the `source` to the columns of the `table` by name.
+async fn upsert_from<T: TableWrite>(txn: &Txn, table: &T, source: Table) -> TCResult<()> {
+    let txn_id = *txn.id();
+    let schema = table.schema();
+    let primary = schema.primary();
+
+    // rows must be inserted one at a time in order to check them against each other
+    let concurrency = if schema.unique().is_empty() && schema.references().is_empty() {
+        num_cpus::get()
+    } else {
+        1
+    };
+
+    let columns = primary.column_names().cloned().collect();
+    let rows = source.select(columns)?.rows(txn_id).await?;
+    let schema = &schema;
+
+    rows.map(|r| r.and_then(|row| primary.key_values_from_tuple(row.into())))
+        .map_ok(|(key, values)| async move {
+            if !schema.references().is_empty() {
+                let row = primary.row_from_key_values(key.clone(), values.clone())?;
+                check_references(txn, table, &row).await?;
+            }
+
+            table.upsert(txn_id, key, values).await
+        })
+        .try_buffer_unordered(concurrency)
+        .try_fold((), |(), ()| future::ready(Ok(())))
+        .await
+}
+
+/// Return a `Conflict` error if the given `row` references a row which does not exist in
+/// another table, according to the foreign keys of the given table's schema.
+async fn check_references<T: TableInstance>(txn: &Txn, table: &T, row: &Row) -> TCResult<()> {
+    let schema = table.schema();
+
+    for (name, link) in schema.references() {
+        let key = schema
+            .index_columns(name)?
+            .iter()
+            .map(|col| row.get(col).cloned().unwrap_or_default())
+            .collect::<Vec<Value>>();
+
+        if key.iter().any(Value::is_none) {
+            continue;
+        }
+
+        let key = Value::Tuple(key.into());
+        let contains = txn
+            .get(link.clone().append(label("contains").into()), key.clone())
+            .await?;
+
+        let contains = bool::try_cast_from(contains, |s| {
+            TCError::bad_request("expected a boolean but found", s)
+        })?;
+
+        if !contains {
+            return Err(TCError::new(
+                ErrorType::Conflict,
+                format!(
+                    "{} references {} but there is no row with key {}",
+                    name, link, key
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 fn column_schema<T: TableInstance>(table: &T) -> Value {
     let columns = table
         .schema()
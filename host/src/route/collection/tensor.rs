@@ -8,12 +8,12 @@ use safecast::*;
 use tc_btree::Node;
 use tc_error::*;
 use tc_tensor::*;
-use tc_transact::fs::{CopyFrom, Dir};
+use tc_transact::fs::Dir;
 use tc_transact::Transaction;
 use tc_value::{
     Bound, Number, NumberClass, NumberInstance, NumberType, Range, TCString, Value, ValueType,
 };
-use tcgeneric::{label, Label, PathSegment, TCBoxTryFuture, Tuple};
+use tcgeneric::{label, Id, Label, PathSegment, TCBoxTryFuture, Tuple};
 
 use crate::collection::{Collection, DenseTensor, DenseTensorFile, SparseTensor, Tensor};
 use crate::fs;
@@ -215,28 +215,13 @@ impl<'a> Handler<'a> for CopyFromHandler {
     {
         Some(Box::new(|txn, mut params| {
             Box::pin(async move {
-                let source = params.require(&label("tensor").into())?;
+                let source: Tensor = params.require(&label("tensor").into())?;
                 params.expect_empty()?;
 
-                let copy = match source {
-                    Tensor::Dense(source) => {
-                        let file = txn
-                            .context()
-                            .create_file_unique(*txn.id(), TensorType::Dense)
-                            .await?;
-
-                        let blocks =
-                            BlockListFile::copy_from(source.into_inner(), file, txn).await?;
-
-                        DenseTensor::from(blocks.accessor()).into()
-                    }
-                    Tensor::Sparse(source) => {
-                        let dir = txn.context().create_dir_unique(*txn.id()).await?;
-                        let table = SparseTable::copy_from(source, dir, txn).await?;
-                        SparseTensor::from(table.accessor()).into()
-                    }
+                let copy = match txn.gateway().representation_policy() {
+                    Some(policy) => source.materialize_with(txn, policy).await?,
+                    None => source.materialize(txn).await?,
                 };
-
                 Ok(State::Collection(Collection::Tensor(copy)))
             })
         }))
@@ -334,6 +319,41 @@ impl<'a> Handler<'a> for CopySparseHandler {
     }
 }
 
+#[cfg(feature = "hdf5-import")]
+struct LoadHdf5Handler;
+
+#[cfg(feature = "hdf5-import")]
+impl<'a> Handler<'a> for LoadHdf5Handler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let data: Value = params.require(&label("data").into())?;
+                let data = bytes::Bytes::try_cast_from(data, |v| {
+                    TCError::bad_request("expected the contents of an HDF5 file, not", v)
+                })?;
+
+                let dest: Value = params.or_default(&label("dest").into())?;
+                let dest = if dest.is_some() {
+                    let dest = dest.try_cast_into(|v| {
+                        TCError::bad_request("expected a Link to import into, not", v)
+                    })?;
+
+                    Some(dest)
+                } else {
+                    None
+                };
+
+                params.expect_empty()?;
+
+                crate::import::hdf5::import(&txn, data, dest).await
+            })
+        }))
+    }
+}
+
 struct CreateHandler {
     class: TensorType,
 }
@@ -427,6 +447,217 @@ where
     }
 }
 
+struct FilledCountHandler<A> {
+    accessor: A,
+}
+
+impl<'a, A> Handler<'a> for FilledCountHandler<A>
+where
+    A: SparseAccess<fs::File<Array>, fs::File<Node>, fs::Dir, Txn>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let count = if key.is_none() {
+                    self.accessor.filled_count(txn.clone()).await?
+                } else {
+                    let bounds = cast_bounds(self.accessor.shape(), key)?;
+                    let slice = self.accessor.slice(bounds)?;
+                    slice.filled_count(txn.clone()).await?
+                };
+
+                Ok(Value::from(count).into())
+            })
+        }))
+    }
+}
+
+struct DensityHandler<A> {
+    accessor: A,
+}
+
+impl<'a, A> Handler<'a> for DensityHandler<A>
+where
+    A: SparseAccess<fs::File<Array>, fs::File<Node>, fs::Dir, Txn>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let density = if key.is_none() {
+                    self.accessor.density(txn.clone()).await?
+                } else {
+                    let bounds = cast_bounds(self.accessor.shape(), key)?;
+                    let slice = self.accessor.slice(bounds)?;
+                    slice.density(txn.clone()).await?
+                };
+
+                Ok(Value::from(Number::from(density)).into())
+            })
+        }))
+    }
+}
+
+struct AssignHandler {
+    tensor: Tensor,
+    op: AssignOp,
+}
+
+impl AssignHandler {
+    fn new<T>(tensor: T, op: AssignOp) -> Self
+    where
+        Tensor: From<T>,
+    {
+        Self {
+            tensor: tensor.into(),
+            op,
+        }
+    }
+}
+
+impl<'a> Handler<'a> for AssignHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let r = params.remove(&label("r").into()).ok_or_else(|| {
+                    TCError::bad_request("missing right-hand-side parameter r", &params)
+                })?;
+
+                params.expect_empty()?;
+
+                match r {
+                    State::Collection(Collection::Tensor(r)) => {
+                        self.tensor.assign(txn.clone(), self.op, r).await?
+                    }
+                    State::Scalar(Scalar::Value(r)) if r.matches::<Number>() => {
+                        let r = r.opt_cast_into().expect("numeric constant");
+                        self.tensor.assign_const(*txn.id(), self.op, r).await?
+                    }
+                    other => {
+                        return Err(TCError::bad_request(
+                            "expected a Tensor or Number, found",
+                            other,
+                        ))
+                    }
+                }
+
+                Ok(State::default())
+            })
+        }))
+    }
+}
+
+struct MaskAssignHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for MaskAssignHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let mask: Tensor = params.require(&label("mask").into())?;
+                let value: Value = params.require(&label("value").into())?;
+                params.expect_empty()?;
+
+                let value = Number::try_cast_from(value, |v| {
+                    TCError::bad_request("expected a Number, not", v)
+                })?;
+
+                self.tensor.mask_assign(txn, mask, value).await?;
+                Ok(State::default())
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for MaskAssignHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct MaskSelectHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for MaskSelectHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let mask: Tensor = params.require(&label("mask").into())?;
+                params.expect_empty()?;
+
+                let selected = self.tensor.mask_select(txn, mask).await?;
+                Ok(State::Collection(Collection::Tensor(selected)))
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for MaskSelectHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct MaterializeHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for MaterializeHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                key.expect_none()?;
+
+                let copy = match txn.gateway().representation_policy() {
+                    Some(policy) => self.tensor.materialize_with(txn, policy).await?,
+                    None => self.tensor.materialize(txn).await?,
+                };
+                Ok(State::Collection(Collection::Tensor(copy)))
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for MaterializeHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
 struct DiagonalHandler<T> {
     tensor: T,
 }
@@ -640,6 +871,8 @@ impl Route for TensorType {
                 "copy_from" => Some(Box::new(CopyDenseHandler)),
                 "concatenate" => Some(Box::new(ConcatenateHandler)),
                 "constant" => Some(Box::new(ConstantHandler)),
+                #[cfg(feature = "hdf5-import")]
+                "load_hdf5" => Some(Box::new(LoadHdf5Handler)),
                 "range" => Some(Box::new(RangeHandler)),
                 _ => None,
             },
@@ -777,21 +1010,20 @@ where
     }
 }
 
-struct TensorHandler<T> {
-    tensor: T,
+struct ScanHandler<F: Send> {
+    tensor: Tensor,
+    op: fn(Tensor, Txn, usize) -> F,
 }
 
-impl<'a, T: 'a> Handler<'a> for TensorHandler<T>
+impl<F: Send> ScanHandler<F> {
+    fn new(tensor: Tensor, op: fn(Tensor, Txn, usize) -> F) -> Self {
+        Self { tensor, op }
+    }
+}
+
+impl<'a, F> Handler<'a> for ScanHandler<F>
 where
-    T: TensorAccess
-        + TensorIO<fs::Dir, Txn = Txn>
-        + TensorDualIO<fs::Dir, Tensor, Txn = Txn>
-        + TensorTransform
-        + Clone
-        + Send
-        + Sync,
-    <T as TensorTransform>::Slice: TensorAccess + Send,
-    Tensor: From<<T as TensorTransform>::Slice>,
+    F: Future<Output = TCResult<Tensor>> + Send + 'a,
 {
     fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
     where
@@ -799,51 +1031,468 @@ where
     {
         Some(Box::new(|txn, key| {
             Box::pin(async move {
-                debug!("GET Tensor: {}", key);
-                let bounds = cast_bounds(self.tensor.shape(), key)?;
-
-                if bounds.size() == 0 {
-                    return Err(TCError::unsupported(format!(
-                        "invalid bounds for tensor with shape {}: {}",
-                        self.tensor.shape(),
-                        bounds
-                    )));
-                }
-
-                let shape = self.tensor.shape();
-                if bounds.is_coord(shape) {
-                    let coord = bounds.as_coord(shape).expect("tensor coordinate");
+                let axis = cast_axis(key, self.tensor.ndim())?;
 
-                    self.tensor
-                        .read_value(txn.clone(), coord)
-                        .map_ok(Value::from)
-                        .map_ok(State::from)
-                        .await
-                } else {
-                    self.tensor
-                        .slice(bounds)
-                        .map(Tensor::from)
-                        .map(Collection::from)
-                        .map(State::from)
-                }
+                (self.op)(self.tensor, txn.clone(), axis)
+                    .map_ok(Collection::from)
+                    .map_ok(State::from)
+                    .await
             })
         }))
     }
+}
 
-    fn put<'b>(self: Box<Self>) -> Option<PutHandler<'a, 'b>>
+struct SortHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for SortHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
     where
         'b: 'a,
     {
-        Some(Box::new(move |txn, key, value| {
-            debug!("PUT Tensor: {} <- {}", key, value);
-            Box::pin(write(self.tensor, txn, key, value))
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let axis: Value = params.or_default(&AXIS.into())?;
+                let ascending: Value = params.or_default(&label("ascending").into())?;
+                params.expect_empty()?;
+
+                let axis = cast_last_axis(axis, self.tensor.ndim())?;
+                let ascending = if ascending.is_none() {
+                    true
+                } else {
+                    ascending.try_cast_into(|v| TCError::bad_request("invalid sort order", v))?
+                };
+
+                let (values, indices) = self.tensor.sort(txn.clone(), axis, ascending).await?;
+                Ok(State::Tuple(
+                    vec![Collection::from(values).into(), Collection::from(indices).into()].into(),
+                ))
+            })
         }))
     }
 }
 
-impl<T> From<T> for TensorHandler<T> {
+impl<T> From<T> for SortHandler
+where
+    Tensor: From<T>,
+{
     fn from(tensor: T) -> Self {
-        Self { tensor }
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct TopKHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for TopKHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let k: Value = params.require(&label("k").into())?;
+                let axis: Value = params.or_default(&AXIS.into())?;
+                params.expect_empty()?;
+
+                let k = k.try_cast_into(|v| TCError::bad_request("invalid value for k", v))?;
+                let axis = cast_last_axis(axis, self.tensor.ndim())?;
+
+                let (values, indices) = self.tensor.topk(txn.clone(), k, axis).await?;
+                Ok(State::Tuple(
+                    vec![Collection::from(values).into(), Collection::from(indices).into()].into(),
+                ))
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for TopKHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct Im2ColHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for Im2ColHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let kernel: Value = params.require(&label("kernel").into())?;
+                let stride: Value = params.or_default(&label("stride").into())?;
+                params.expect_empty()?;
+
+                let kernel = kernel
+                    .try_cast_into(|v| TCError::bad_request("invalid kernel shape", v))?;
+
+                let stride = if stride.is_none() {
+                    1
+                } else {
+                    stride.try_cast_into(|v| TCError::bad_request("invalid window stride", v))?
+                };
+
+                self.tensor
+                    .im2col(txn.clone(), kernel, stride)
+                    .map_ok(Collection::from)
+                    .map_ok(State::from)
+                    .await
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for Im2ColHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct SoftmaxHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for SoftmaxHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let axis = cast_last_axis(key, self.tensor.ndim())?;
+
+                self.tensor
+                    .softmax(txn.clone(), axis)
+                    .map_ok(Collection::from)
+                    .map_ok(State::from)
+                    .await
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for SoftmaxHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct BatchNormHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for BatchNormHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let mean: Tensor = params.require(&label("mean").into())?;
+                let var: Tensor = params.require(&label("var").into())?;
+                let gamma: Tensor = params.require(&label("gamma").into())?;
+                let beta: Tensor = params.require(&label("beta").into())?;
+                let epsilon: Value = params.or_default(&label("epsilon").into())?;
+                params.expect_empty()?;
+
+                let epsilon = if epsilon.is_none() {
+                    Number::from(1e-5f64)
+                } else {
+                    Number::try_cast_from(epsilon, |v| {
+                        TCError::bad_request("expected a Number, not", v)
+                    })?
+                };
+
+                self.tensor
+                    .batch_norm(txn.clone(), mean, var, gamma, beta, epsilon)
+                    .map_ok(Collection::from)
+                    .map_ok(State::from)
+                    .await
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for BatchNormHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct NormHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for NormHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let ord: Value = params.or_default(&label("ord").into())?;
+                let axis: Value = params.or_default(&AXIS.into())?;
+                params.expect_empty()?;
+
+                let ord = cast_norm_ord(ord)?;
+
+                if axis.is_none() {
+                    self.tensor
+                        .norm_all(txn.clone(), ord)
+                        .map_ok(State::from)
+                        .await
+                } else {
+                    let axis = cast_axis(axis, self.tensor.ndim())?;
+                    self.tensor
+                        .norm(txn.clone(), ord, axis)
+                        .map_ok(Collection::from)
+                        .map_ok(State::from)
+                        .await
+                }
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for NormHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct HistogramHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for HistogramHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let bins = if key.is_none() {
+                    DEFAULT_BINS
+                } else {
+                    key.try_cast_into(|v| TCError::bad_request("invalid number of bins", v))?
+                };
+
+                let histogram = self.tensor.histogram(txn.clone(), bins).await?;
+
+                let counts = histogram.counts.into_iter().map(Value::from).collect();
+                let edges = histogram
+                    .edges
+                    .into_iter()
+                    .map(Number::from)
+                    .map(Value::from)
+                    .collect();
+
+                Ok(State::Tuple(
+                    vec![
+                        Value::Tuple(counts).into(),
+                        Value::Tuple(edges).into(),
+                    ]
+                    .into(),
+                ))
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for HistogramHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct QuantileHandler {
+    tensor: Tensor,
+}
+
+impl<'a> Handler<'a> for QuantileHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                let quantile = |q: Option<f64>| q.map(Number::from).map(Value::from);
+
+                match key {
+                    Value::Tuple(qs) => {
+                        let qs = qs
+                            .into_iter()
+                            .map(cast_quantile)
+                            .collect::<TCResult<Vec<f64>>>()?;
+
+                        let quantiles = self.tensor.quantiles(txn.clone(), &qs).await?;
+                        let quantiles = quantiles
+                            .into_iter()
+                            .map(|q| quantile(q).unwrap_or_default())
+                            .collect();
+
+                        Ok(State::from(Value::Tuple(quantiles)))
+                    }
+                    q => {
+                        let q = cast_quantile(q)?;
+                        let mut quantiles = self.tensor.quantiles(txn.clone(), &[q]).await?;
+                        let q = quantiles.pop().expect("quantile");
+                        Ok(State::from(quantile(q).unwrap_or_default()))
+                    }
+                }
+            })
+        }))
+    }
+}
+
+impl<T> From<T> for QuantileHandler
+where
+    Tensor: From<T>,
+{
+    fn from(tensor: T) -> Self {
+        Self {
+            tensor: tensor.into(),
+        }
+    }
+}
+
+struct MatrixHandler<F: Send> {
+    tensor: Tensor,
+    op: fn(Tensor, Txn) -> F,
+}
+
+impl<F: Send> MatrixHandler<F> {
+    fn new(tensor: Tensor, op: fn(Tensor, Txn) -> F) -> Self {
+        Self { tensor, op }
+    }
+}
+
+impl<'a, F, S> Handler<'a> for MatrixHandler<F>
+where
+    F: Future<Output = TCResult<S>> + Send + 'a,
+    State: From<S>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                key.expect_none()?;
+                (self.op)(self.tensor, txn.clone())
+                    .map_ok(State::from)
+                    .await
+            })
+        }))
+    }
+}
+
+struct TensorHandler<T> {
+    tensor: T,
+}
+
+impl<'a, T: 'a> Handler<'a> for TensorHandler<T>
+where
+    T: TensorAccess
+        + TensorIO<fs::Dir, Txn = Txn>
+        + TensorDualIO<fs::Dir, Tensor, Txn = Txn>
+        + TensorTransform
+        + Clone
+        + Send
+        + Sync,
+    <T as TensorTransform>::Slice: TensorAccess + Send,
+    Tensor: From<<T as TensorTransform>::Slice>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, key| {
+            Box::pin(async move {
+                debug!("GET Tensor: {}", key);
+                let bounds = cast_bounds(self.tensor.shape(), key)?;
+
+                if bounds.size() == 0 {
+                    return Err(TCError::unsupported(format!(
+                        "invalid bounds for tensor with shape {}: {}",
+                        self.tensor.shape(),
+                        bounds
+                    )));
+                }
+
+                let shape = self.tensor.shape();
+                if bounds.is_coord(shape) {
+                    let coord = bounds.as_coord(shape).expect("tensor coordinate");
+
+                    self.tensor
+                        .read_value(txn.clone(), coord)
+                        .map_ok(Value::from)
+                        .map_ok(State::from)
+                        .await
+                } else {
+                    self.tensor
+                        .slice(bounds)
+                        .map(Tensor::from)
+                        .map(Collection::from)
+                        .map(State::from)
+                }
+            })
+        }))
+    }
+
+    fn put<'b>(self: Box<Self>) -> Option<PutHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(move |txn, key, value| {
+            debug!("PUT Tensor: {} <- {}", key, value);
+            Box::pin(write(self.tensor, txn, key, value))
+        }))
+    }
+}
+
+impl<T> From<T> for TensorHandler<T> {
+    fn from(tensor: T) -> Self {
+        Self { tensor }
     }
 }
 
@@ -921,16 +1570,42 @@ impl<B: DenseWrite<fs::File<Array>, fs::File<Node>, fs::Dir, Txn>> Route for Den
 
 impl<A: SparseWrite<fs::File<Array>, fs::File<Node>, fs::Dir, Txn>> Route for SparseTensor<A> {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
-        route(self, path)
+        sparse_route(self, path).or_else(|| route(self, path))
     }
 }
 
 impl Route for Tensor {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+        if let Self::Sparse(sparse) = self {
+            if let Some(handler) = sparse_route(sparse, path) {
+                return Some(handler);
+            }
+        }
+
         route(self, path)
     }
 }
 
+/// Route the statistics which only a [`SparseTensor`] can report without reading every element.
+fn sparse_route<'a, A>(
+    tensor: &'a SparseTensor<A>,
+    path: &'a [PathSegment],
+) -> Option<Box<dyn Handler<'a> + 'a>>
+where
+    A: SparseAccess<fs::File<Array>, fs::File<Node>, fs::Dir, Txn>,
+{
+    if path.len() != 1 {
+        return None;
+    }
+
+    let accessor = tensor.clone().into_inner();
+    match path[0].as_str() {
+        "density" => Some(Box::new(DensityHandler { accessor })),
+        "filled_count" => Some(Box::new(FilledCountHandler { accessor })),
+        _ => None,
+    }
+}
+
 fn route<'a, T>(tensor: &'a T, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>>
 where
     T: TensorAccess
@@ -1005,6 +1680,17 @@ where
         match path[0].as_str() {
             // to stream
             "elements" => Some(Box::new(ElementsHandler::new(tensor))),
+            "materialize" => Some(Box::new(MaterializeHandler::from(tensor))),
+
+            // in-place arithmetic
+            "add_assign" => Some(Box::new(AssignHandler::new(tensor, AssignOp::Add))),
+            "div_assign" => Some(Box::new(AssignHandler::new(tensor, AssignOp::Div))),
+            "mul_assign" => Some(Box::new(AssignHandler::new(tensor, AssignOp::Mul))),
+            "sub_assign" => Some(Box::new(AssignHandler::new(tensor, AssignOp::Sub))),
+
+            // masks
+            "mask_assign" => Some(Box::new(MaskAssignHandler::from(tensor))),
+            "mask_select" => Some(Box::new(MaskSelectHandler::from(tensor))),
 
             // views
             "dense" => {
@@ -1084,6 +1770,12 @@ where
             "atanh" => Some(Box::new(UnaryHandler::new(tensor.into(), TensorTrig::atanh))),
             "tanh" => Some(Box::new(UnaryHandler::new(tensor.into(), TensorTrig::tanh))),
 
+            // complex numbers
+            "re" => Some(Box::new(UnaryHandler::new(tensor.into(), TensorComplex::re))),
+            "im" => Some(Box::new(UnaryHandler::new(tensor.into(), TensorComplex::im))),
+            "conj" => Some(Box::new(UnaryHandler::new(tensor.into(), TensorComplex::conj))),
+            "arg" => Some(Box::new(UnaryHandler::new(tensor.into(), TensorComplex::arg))),
+
             // unary ops
             "abs" => Some(Box::new(UnaryHandler::new(tensor.into(), TensorUnary::abs))),
             "all" => Some(Box::new(UnaryHandlerAsync::new(
@@ -1131,6 +1823,33 @@ where
             "reshape" => Some(Box::new(ReshapeHandler::from(tensor))),
             "transpose" => Some(Box::new(TransposeHandler::from(tensor))),
 
+            // cumulative ops
+            "cumprod" => Some(Box::new(ScanHandler::new(tensor.into(), TensorScan::cumprod))),
+            "cumsum" => Some(Box::new(ScanHandler::new(tensor.into(), TensorScan::cumsum))),
+
+            // sorting
+            "sort" => Some(Box::new(SortHandler::from(tensor))),
+            "topk" => Some(Box::new(TopKHandler::from(tensor))),
+
+            // windows
+            "im2col" => Some(Box::new(Im2ColHandler::from(tensor))),
+
+            // normalization
+            "batch_norm" => Some(Box::new(BatchNormHandler::from(tensor))),
+            "softmax" => Some(Box::new(SoftmaxHandler::from(tensor))),
+
+            // linear algebra
+            "norm" => Some(Box::new(NormHandler::from(tensor))),
+            "cholesky" => Some(Box::new(MatrixHandler::new(tensor.into(), Tensor::cholesky))),
+            "det" => Some(Box::new(MatrixHandler::new(tensor.into(), Tensor::det))),
+            "inv" => Some(Box::new(MatrixHandler::new(tensor.into(), Tensor::inv))),
+            "qr" => Some(Box::new(MatrixHandler::new(tensor.into(), qr))),
+            "svd" => Some(Box::new(MatrixHandler::new(tensor.into(), svd))),
+
+            // distribution
+            "histogram" => Some(Box::new(HistogramHandler::from(tensor))),
+            "quantile" => Some(Box::new(QuantileHandler::from(tensor))),
+
             // other
             "diagonal" => Some(Box::new(DiagonalHandler::from(tensor))),
 
@@ -1141,6 +1860,148 @@ where
     }
 }
 
+async fn qr(tensor: Tensor, txn: Txn) -> TCResult<State> {
+    let (q, r) = tensor.qr(txn).await?;
+    Ok(State::Tuple(
+        vec![Collection::from(q).into(), Collection::from(r).into()].into(),
+    ))
+}
+
+async fn svd(tensor: Tensor, txn: Txn) -> TCResult<State> {
+    let (u, s, vt) = tensor.svd(txn).await?;
+    Ok(State::Tuple(
+        vec![
+            Collection::from(u).into(),
+            Collection::from(s).into(),
+            Collection::from(vt).into(),
+        ]
+        .into(),
+    ))
+}
+
+struct CDistHandler;
+
+impl<'a> Handler<'a> for CDistHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let a: Tensor = params.require(&label("a").into())?;
+                let b: Tensor = params.require(&label("b").into())?;
+                let metric: Value = params.or_default(&label("metric").into())?;
+                params.expect_empty()?;
+
+                let metric = cast_metric(metric)?;
+
+                cdist(a, b, txn.clone(), metric)
+                    .map_ok(Collection::from)
+                    .map_ok(State::from)
+                    .await
+            })
+        }))
+    }
+}
+
+struct SolveHandler;
+
+impl<'a> Handler<'a> for SolveHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let a: Tensor = params.require(&label("a").into())?;
+                let b: Tensor = params.require(&label("b").into())?;
+                params.expect_empty()?;
+
+                a.solve(txn.clone(), b)
+                    .map_ok(Collection::from)
+                    .map_ok(State::from)
+                    .await
+            })
+        }))
+    }
+}
+
+struct WhereHandler;
+
+impl<'a> Handler<'a> for WhereHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, mut params| {
+            Box::pin(async move {
+                let cond: Tensor = params.require(&label("cond").into())?;
+                let then: Tensor = params.require(&label("then").into())?;
+                let or_else: Tensor = params.require(&label("or_else").into())?;
+                params.expect_empty()?;
+
+                select(cond, then, or_else)
+                    .map(Collection::from)
+                    .map(State::from)
+            })
+        }))
+    }
+}
+
+struct WatchHandler;
+
+impl<'a> Handler<'a> for WatchHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let tensor: Tensor = params.require(&label("tensor").into())?;
+                params.expect_empty()?;
+
+                txn.start_tape();
+                Ok(State::from(Collection::from(tensor)))
+            })
+        }))
+    }
+}
+
+struct BackwardHandler;
+
+impl<'a> Handler<'a> for BackwardHandler {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let loss: Value = params.require(&label("loss").into())?;
+                params.expect_empty()?;
+
+                let loss = Id::try_cast_from(loss, |v| {
+                    TCError::bad_request("invalid name for a loss to differentiate", v)
+                })?;
+
+                let tape = txn.tape().ok_or_else(|| {
+                    TCError::bad_request(
+                        "there is no gradient tape recording in transaction",
+                        txn.id(),
+                    )
+                })?;
+
+                let gradients = tape.backward(txn.clone(), &loss).await?;
+                let gradients = gradients
+                    .into_iter()
+                    .map(|(id, grad)| (id, State::from(Collection::from(grad))))
+                    .collect();
+
+                Ok(State::Map(gradients))
+            })
+        }))
+    }
+}
+
 pub struct Static;
 
 impl Route for Static {
@@ -1154,6 +2015,11 @@ impl Route for Static {
             "sparse" => TensorType::Sparse.route(&path[1..]),
             "copy_from" if path.len() == 1 => Some(Box::new(CopyFromHandler)),
             "einsum" if path.len() == 1 => Some(Box::new(EinsumHandler)),
+            "backward" if path.len() == 1 => Some(Box::new(BackwardHandler)),
+            "cdist" if path.len() == 1 => Some(Box::new(CDistHandler)),
+            "solve" if path.len() == 1 => Some(Box::new(SolveHandler)),
+            "watch" if path.len() == 1 => Some(Box::new(WatchHandler)),
+            "where" if path.len() == 1 => Some(Box::new(WhereHandler)),
             _ => None,
         }
     }
@@ -1219,6 +2085,44 @@ fn cast_bound(dim: u64, bound: Value) -> TCResult<u64> {
     }
 }
 
+fn cast_quantile(q: Value) -> TCResult<f64> {
+    let q: Number = q.try_cast_into(|v| TCError::bad_request("invalid quantile", v))?;
+    Ok(f64::cast_from(q))
+}
+
+fn cast_norm_ord(ord: Value) -> TCResult<NormOrd> {
+    match &ord {
+        Value::None => Ok(NormOrd::L2),
+        Value::Number(n) if f64::cast_from(*n) == 1. => Ok(NormOrd::L1),
+        Value::Number(n) if f64::cast_from(*n) == 2. => Ok(NormOrd::L2),
+        Value::Id(id) if id.as_str() == "inf" => Ok(NormOrd::Inf),
+        Value::String(s) if s.as_str() == "inf" => Ok(NormOrd::Inf),
+        _ => Err(TCError::bad_request(
+            "expected a norm order of 1, 2, or \"inf\" but found",
+            ord,
+        )),
+    }
+}
+
+fn cast_metric(metric: Value) -> TCResult<NormOrd> {
+    let name = match &metric {
+        Value::None => return Ok(NormOrd::L2),
+        Value::Id(id) => id.as_str(),
+        Value::String(s) => s.as_str(),
+        _ => "",
+    };
+
+    match name {
+        "cityblock" => Ok(NormOrd::L1),
+        "euclidean" => Ok(NormOrd::L2),
+        "chebyshev" => Ok(NormOrd::Inf),
+        _ => Err(TCError::bad_request(
+            "expected a distance metric (\"cityblock\", \"euclidean\", or \"chebyshev\") but found",
+            metric,
+        )),
+    }
+}
+
 fn cast_axis(axis: Value, ndim: usize) -> TCResult<usize> {
     debug!("cast axis {} with ndim {}", axis, ndim);
 
@@ -1241,6 +2145,19 @@ fn cast_axis(axis: Value, ndim: usize) -> TCResult<usize> {
     }
 }
 
+// like `cast_axis`, but default to the last axis
+fn cast_last_axis(axis: Value, ndim: usize) -> TCResult<usize> {
+    if axis.is_none() {
+        if ndim == 0 {
+            Err(TCError::unsupported("a zero-dimensional Tensor has no axis"))
+        } else {
+            Ok(ndim - 1)
+        }
+    } else {
+        cast_axis(axis, ndim)
+    }
+}
+
 fn cast_range(dim: u64, range: Range) -> TCResult<AxisBounds> {
     debug!("cast range from {} with dimension {}", range, dim);
 
@@ -18,6 +18,8 @@ use crate::state::State;
 
 use super::{AttributeHandler, GetHandler, Handler, PostHandler, Route};
 
+const FILTER_ERR: &str = "filter condition must return a Bool, not";
+
 struct AppendHandler<'a, T: Clone> {
     tuple: &'a Tuple<T>,
 }
@@ -172,6 +174,8 @@ where
             Box::pin(async move {
                 if key.is_none() {
                     Ok(State::from(self.map.clone()))
+                } else if let Value::Tuple(path) = key {
+                    get_path(State::from(self.map.clone()), path)
                 } else {
                     let key = Id::try_cast_from(key, |v| TCError::bad_request("invalid Id", v))?;
                     self.map.get(&key).cloned().map(State::from).ok_or_else(|| {
@@ -189,6 +193,184 @@ where
     }
 }
 
+// look up the element of a nested `Map` or `Tuple` at the given key `path`, one key per level
+fn get_path(state: State, path: Tuple<Value>) -> TCResult<State> {
+    let mut state = state;
+
+    for key in path {
+        state = match state {
+            State::Map(map) => member(map, key).map(State::from),
+            State::Scalar(Scalar::Map(map)) => member(map, key).map(State::from),
+            State::Tuple(tuple) => element(tuple, key).map(State::from),
+            State::Scalar(Scalar::Tuple(tuple)) => element(tuple, key).map(State::from),
+            State::Scalar(Scalar::Value(Value::Tuple(tuple))) => {
+                element(tuple, key).map(State::from)
+            }
+            other => Err(TCError::bad_request(
+                format!("cannot look up {} in", key),
+                other,
+            )),
+        }?;
+    }
+
+    Ok(state)
+}
+
+fn member<T>(map: Map<T>, key: Value) -> TCResult<T> {
+    let key = Id::try_cast_from(key, |v| TCError::bad_request("invalid Id", v))?;
+    let mut map = map.into_inner();
+    map.remove(&key).ok_or_else(|| {
+        let msg = format!(
+            "{} in Map with keys {}",
+            key,
+            map.keys().collect::<Tuple<&Id>>()
+        );
+
+        TCError::not_found(msg)
+    })
+}
+
+fn element<T>(tuple: Tuple<T>, key: Value) -> TCResult<T> {
+    let i = tuple_index(key)?;
+
+    let mut tuple = tuple.into_inner();
+    if i < tuple.len() {
+        Ok(tuple.swap_remove(i))
+    } else {
+        Err(TCError::not_found(format!("no such index: {}", i)))
+    }
+}
+
+// cast the given `key` to an index into a `Tuple`, which must be a non-negative integer
+fn tuple_index(key: Value) -> TCResult<usize> {
+    let i = Number::try_cast_from(key, |v| TCError::bad_request("invalid tuple index", v))?;
+
+    match i {
+        Number::UInt(i) => Ok(u64::from(i) as usize),
+        Number::Int(i) if i64::from(i) >= 0 => Ok(i64::from(i) as usize),
+        other => Err(TCError::bad_request(
+            "a tuple index must be a non-negative integer, not",
+            other,
+        )),
+    }
+}
+
+struct ContainsKeyHandler<'a, T> {
+    map: &'a Map<T>,
+}
+
+impl<'a, T: Send + Sync> Handler<'a> for ContainsKeyHandler<'a, T> {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                let key = Id::try_cast_from(key, |v| TCError::bad_request("invalid Id", v))?;
+                Ok(Value::from(self.map.contains_key(&key)).into())
+            })
+        }))
+    }
+}
+
+impl<'a, T> From<&'a Map<T>> for ContainsKeyHandler<'a, T> {
+    fn from(map: &'a Map<T>) -> Self {
+        Self { map }
+    }
+}
+
+struct MapFilterHandler<'a, T> {
+    map: &'a Map<T>,
+}
+
+impl<'a, T> Handler<'a> for MapFilterHandler<'a, T>
+where
+    T: Clone + Send + Sync + 'a,
+    State: From<T>,
+{
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let op: Closure = params.require(&label("op").into())?;
+                params.expect_empty()?;
+
+                let items = self
+                    .map
+                    .iter()
+                    .map(|(id, item)| (id.clone(), State::from(item.clone())))
+                    .collect::<Vec<(Id, State)>>();
+
+                let values = items.iter().map(|(_, item)| item.clone());
+                let values = values.collect::<Vec<State>>();
+
+                let keep: Vec<State> = stream::iter(values)
+                    .map(|item| op.clone().call(txn, item))
+                    .buffered(num_cpus::get())
+                    .try_collect()
+                    .await?;
+
+                let mut map = Map::new();
+                for ((id, item), keep) in items.into_iter().zip(keep) {
+                    if bool::try_cast_from(keep, |s| TCError::bad_request(FILTER_ERR, s))? {
+                        map.insert(id, item);
+                    }
+                }
+
+                Ok(State::Map(map))
+            })
+        }))
+    }
+}
+
+impl<'a, T> From<&'a Map<T>> for MapFilterHandler<'a, T> {
+    fn from(map: &'a Map<T>) -> Self {
+        Self { map }
+    }
+}
+
+struct MapMapHandler<'a, T> {
+    map: &'a Map<T>,
+}
+
+impl<'a, T> Handler<'a> for MapMapHandler<'a, T>
+where
+    T: Clone + Send + Sync + 'a,
+    State: From<T>,
+{
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let op: Closure = params.require(&label("op").into())?;
+                params.expect_empty()?;
+
+                let ids = self.map.keys().cloned().collect::<Vec<Id>>();
+                let items = self.map.values().cloned().map(State::from);
+                let items = items.collect::<Vec<State>>();
+
+                let mapped: Vec<State> = stream::iter(items)
+                    .map(|item| op.clone().call(txn, item))
+                    .buffered(num_cpus::get())
+                    .try_collect()
+                    .await?;
+
+                Ok(State::Map(ids.into_iter().zip(mapped).collect()))
+            })
+        }))
+    }
+}
+
+impl<'a, T> From<&'a Map<T>> for MapMapHandler<'a, T> {
+    fn from(map: &'a Map<T>) -> Self {
+        Self { map }
+    }
+}
+
 impl<T: Instance + Route + Clone + fmt::Display> Route for Map<T>
 where
     State: From<Map<T>>,
@@ -213,10 +395,13 @@ where
             state.route(&path[1..])
         } else if path.len() == 1 {
             match path[0].as_str() {
+                "contains" => Some(Box::new(ContainsKeyHandler::from(self))),
                 "eq" => Some(Box::new(EqMapHandler::from(self.clone()))),
+                "filter" => Some(Box::new(MapFilterHandler::from(self))),
                 "len" => Some(Box::new(AttributeHandler::from(Number::from(
                     self.len() as u64
                 )))),
+                "map" => Some(Box::new(MapMapHandler::from(self))),
                 _ => None,
             }
         } else {
@@ -225,6 +410,87 @@ where
     }
 }
 
+struct ContainsHandler<'a, T> {
+    tuple: &'a Tuple<T>,
+}
+
+impl<'a, T> Handler<'a> for ContainsHandler<'a, T>
+where
+    T: Clone + Send + Sync,
+    State: From<T>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                let contains = self
+                    .tuple
+                    .iter()
+                    .cloned()
+                    .map(State::from)
+                    .filter_map(Value::opt_cast_from)
+                    .any(|item| item == key);
+
+                Ok(Value::from(contains).into())
+            })
+        }))
+    }
+}
+
+impl<'a, T> From<&'a Tuple<T>> for ContainsHandler<'a, T> {
+    fn from(tuple: &'a Tuple<T>) -> Self {
+        Self { tuple }
+    }
+}
+
+struct TupleFilterHandler<'a, T> {
+    tuple: &'a Tuple<T>,
+}
+
+impl<'a, T> Handler<'a> for TupleFilterHandler<'a, T>
+where
+    T: Clone + Send + Sync + 'a,
+    State: From<T>,
+{
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let op: Closure = params.require(&label("op").into())?;
+                params.expect_empty()?;
+
+                let items = self.tuple.iter().cloned().map(State::from);
+                let items = items.collect::<Vec<State>>();
+
+                let keep: Vec<State> = stream::iter(items.clone())
+                    .map(|item| op.clone().call(txn, item))
+                    .buffered(num_cpus::get())
+                    .try_collect()
+                    .await?;
+
+                let mut tuple = Vec::with_capacity(items.len());
+                for (item, keep) in items.into_iter().zip(keep) {
+                    if bool::try_cast_from(keep, |s| TCError::bad_request(FILTER_ERR, s))? {
+                        tuple.push(item);
+                    }
+                }
+
+                Ok(State::Tuple(tuple.into()))
+            })
+        }))
+    }
+}
+
+impl<'a, T> From<&'a Tuple<T>> for TupleFilterHandler<'a, T> {
+    fn from(tuple: &'a Tuple<T>) -> Self {
+        Self { tuple }
+    }
+}
+
 struct TupleFoldHandler<'a, T> {
     tuple: &'a Tuple<T>,
 }
@@ -262,6 +528,113 @@ impl<'a, T: Clone + 'a> From<&'a Tuple<T>> for TupleFoldHandler<'a, T> {
     }
 }
 
+struct TupleReduceHandler<'a, T> {
+    tuple: &'a Tuple<T>,
+}
+
+impl<'a, T> Handler<'a> for TupleReduceHandler<'a, T>
+where
+    T: Clone + Send + Sync + 'a,
+    State: From<T>,
+{
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|txn, mut params| {
+            Box::pin(async move {
+                let op: Closure = params.require(&label("op").into())?;
+                params.expect_empty()?;
+
+                let mut items = self.tuple.iter().cloned().map(State::from);
+                let mut state = items
+                    .next()
+                    .ok_or_else(|| TCError::unsupported("cannot reduce an empty Tuple"))?;
+
+                const ITEM: Label = label("item");
+                const STATE: Label = label("state");
+                for item in items {
+                    let args = IntoIter::new([(ITEM.into(), item), (STATE.into(), state)]);
+                    state = op.clone().call(txn, State::Map(args.collect())).await?;
+                }
+
+                Ok(state)
+            })
+        }))
+    }
+}
+
+impl<'a, T> From<&'a Tuple<T>> for TupleReduceHandler<'a, T> {
+    fn from(tuple: &'a Tuple<T>) -> Self {
+        Self { tuple }
+    }
+}
+
+struct SliceHandler<'a, T> {
+    tuple: &'a Tuple<T>,
+}
+
+impl<'a, T> SliceHandler<'a, T> {
+    // resolve a (possibly negative) index relative to the length of the tuple, like Python
+    fn index(&self, i: Value) -> TCResult<usize> {
+        let len = self.tuple.len() as i64;
+        let i = Number::try_cast_from(i, |v| TCError::bad_request("invalid tuple index", v))?;
+        let i = i64::cast_from(i);
+        let i = if i < 0 { len + i } else { i };
+        Ok(i.max(0).min(len) as usize)
+    }
+}
+
+impl<'a, T> Handler<'a> for SliceHandler<'a, T>
+where
+    T: Clone + Send + Sync,
+    State: From<T>,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                let (start, stop) = match key {
+                    Value::Tuple(mut bounds) if bounds.len() == 2 => {
+                        let stop = bounds.pop().expect("stop");
+                        let start = bounds.pop().expect("start");
+                        (start, stop)
+                    }
+                    start => (start, Value::None),
+                };
+
+                let start = if start.is_none() {
+                    0
+                } else {
+                    self.index(start)?
+                };
+
+                let stop = if stop.is_none() {
+                    self.tuple.len()
+                } else {
+                    self.index(stop)?
+                };
+
+                let slice = self.tuple[start..stop.max(start)]
+                    .iter()
+                    .cloned()
+                    .map(State::from)
+                    .collect();
+
+                Ok(State::Tuple(slice))
+            })
+        }))
+    }
+}
+
+impl<'a, T> From<&'a Tuple<T>> for SliceHandler<'a, T> {
+    fn from(tuple: &'a Tuple<T>) -> Self {
+        Self { tuple }
+    }
+}
+
 struct MapOpHandler<I> {
     len: usize,
     items: I,
@@ -322,12 +695,10 @@ where
             Box::pin(async move {
                 if key.is_none() {
                     Ok(State::from(self.tuple.clone()))
+                } else if let Value::Tuple(path) = key {
+                    get_path(State::from(self.tuple.clone()), path)
                 } else {
-                    let i = Number::try_cast_from(key, |v| {
-                        TCError::bad_request("invalid tuple index", v)
-                    })?;
-
-                    let i = usize::cast_from(i);
+                    let i = tuple_index(key)?;
 
                     self.tuple
                         .get(i)
@@ -407,12 +778,16 @@ where
         } else if path.len() == 1 {
             match path[0].as_str() {
                 "append" => Some(Box::new(AppendHandler::from(self))),
+                "contains" => Some(Box::new(ContainsHandler::from(self))),
+                "filter" => Some(Box::new(TupleFilterHandler::from(self))),
                 "fold" => Some(Box::new(TupleFoldHandler::from(self))),
                 "len" => Some(Box::new(AttributeHandler::from(Number::from(
                     self.len() as u64
                 )))),
                 "eq" => Some(Box::new(EqTupleHandler::from(self.clone()))),
                 "map" => Some(Box::new(MapOpHandler::from(self))),
+                "reduce" => Some(Box::new(TupleReduceHandler::from(self))),
+                "slice" => Some(Box::new(SliceHandler::from(self))),
                 "zip" => Some(Box::new(ZipHandler::from(self))),
                 _ => None,
             }
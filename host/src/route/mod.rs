@@ -18,6 +18,7 @@ mod cluster;
 mod collection;
 mod generic;
 mod object;
+mod reflect;
 mod scalar;
 mod state;
 mod stream;
@@ -321,6 +322,7 @@ fn error_type(err_type: &Id) -> Option<ErrorType> {
         "not_found" => Some(ErrorType::NotFound),
         "not_implemented" => Some(ErrorType::NotImplemented),
         "timeout" => Some(ErrorType::Timeout),
+        "too_many_requests" => Some(ErrorType::TooManyRequests),
         "unauthorized" => Some(ErrorType::Unauthorized),
         _ => None,
     }
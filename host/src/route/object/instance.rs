@@ -7,7 +7,10 @@ use tc_value::Value;
 use tcgeneric::{Id, Instance, Map, PathSegment, TCPath};
 
 use crate::object::InstanceExt;
-use crate::route::{DeleteHandler, GetHandler, Handler, PostHandler, PutHandler, Route};
+use crate::route::reflect::{self, PROTO};
+use crate::route::{
+    AttributeHandler, DeleteHandler, GetHandler, Handler, PostHandler, PutHandler, Route,
+};
 use crate::scalar::*;
 use crate::state::{State, ToState};
 use crate::txn::Txn;
@@ -91,7 +94,8 @@ where
     InstanceExt<T>: ToState,
 {
     async fn call(self, txn: &Txn, params: Map<State>) -> TCResult<State> {
-        call_method(txn, self.subject, self.path, params, self.method).await
+        let (params, form) = destructure_params(self.method, params)?;
+        call_method(txn, self.subject, self.path, params, form).await
     }
 }
 
@@ -184,6 +188,9 @@ where
                 })),
                 other => other.route(&path[1..]),
             }
+        } else if path.len() == 1 && path[0] == PROTO {
+            let proto = reflect::describe_proto(self.proto());
+            Some(Box::new(AttributeHandler::from(proto)))
         } else if let Some(handler) = self.parent().route(path) {
             debug!("{} found in parent", TCPath::from(path));
             Some(handler)
@@ -3,7 +3,8 @@ use tcgeneric::PathSegment;
 use crate::object::{InstanceClass, InstanceExt, Object, ObjectType};
 use crate::state::State;
 
-use super::{GetHandler, Handler, Route};
+use super::reflect::{self, PROTO, SCHEMA};
+use super::{AttributeHandler, GetHandler, Handler, Route};
 
 mod instance;
 
@@ -39,6 +40,12 @@ impl Route for InstanceClass {
             Some(Box::new(ClassHandler { class: self }))
         } else if let Some(attribute) = self.proto().get(&path[0]) {
             attribute.route(&path[1..])
+        } else if path.len() == 1 && path[0] == PROTO {
+            let proto = reflect::describe_proto(self.proto());
+            Some(Box::new(AttributeHandler::from(proto)))
+        } else if path.len() == 1 && path[0] == SCHEMA {
+            let schema = reflect::describe_class(self);
+            Some(Box::new(AttributeHandler::from(schema)))
         } else {
             None
         }
@@ -0,0 +1,158 @@
+//! Reflection routes, which describe a subject in a machine-readable format.
+//!
+//! * `/class` is a [`Link`] to the class of the subject
+//! * `/proto` describes each attribute of the subject's class, including the signature of each op
+//! * `/schema` describes the data of the subject, like the columns of a table and their dtypes
+
+use safecast::CastInto;
+
+use tc_btree::BTreeInstance;
+use tc_table::TableInstance;
+#[cfg(feature = "tc-tensor")]
+use tc_transact::fs::Persist;
+use tc_value::{Link, Value};
+use tcgeneric::{label, Id, Instance, Label, Map, NativeClass, Tuple};
+
+use crate::chain::{ChainInstance, Subject};
+use crate::cluster::Cluster;
+use crate::object::InstanceClass;
+use crate::scalar::{OpDef, Scalar};
+
+/// The name of the route which serves a [`Link`] to the class of a subject.
+pub const CLASS: Label = label("class");
+
+/// The name of the route which describes the attributes of a subject's class.
+pub const PROTO: Label = label("proto");
+
+/// The name of the route which describes the data of a subject.
+pub const SCHEMA: Label = label("schema");
+
+/// Describe each attribute of the given class prototype.
+pub fn describe_proto(proto: &Map<Scalar>) -> Scalar {
+    proto
+        .iter()
+        .map(|(name, attr)| {
+            let description = match attr {
+                Scalar::Op(op_def) => describe_op(op_def),
+                other => describe(vec![(CLASS, class_link(other.class()))]),
+            };
+
+            (name.clone(), description)
+        })
+        .collect::<Map<Scalar>>()
+        .into()
+}
+
+/// Describe the signature of the given [`OpDef`].
+pub fn describe_op(op_def: &OpDef) -> Scalar {
+    let mut description = vec![(CLASS, class_link(op_def.class()))];
+
+    match op_def {
+        OpDef::Get((key_name, _)) | OpDef::Delete((key_name, _)) => {
+            description.push((label("key"), Value::from(key_name.clone()).into()));
+        }
+        OpDef::Put((key_name, value_name, _)) => {
+            description.push((label("key"), Value::from(key_name.clone()).into()));
+            description.push((label("value"), Value::from(value_name.clone()).into()));
+        }
+        OpDef::Post(_) => {}
+    }
+
+    let params = op_def
+        .params()
+        .into_iter()
+        .map(Value::from)
+        .collect::<Tuple<Value>>();
+
+    description.push((label("params"), Value::from(params).into()));
+
+    describe(description)
+}
+
+/// Describe the class and prototype of the given [`InstanceClass`].
+pub fn describe_class(class: &InstanceClass) -> Scalar {
+    describe(vec![
+        (label("extends"), Value::from(class.extends()).into()),
+        (PROTO, describe_proto(class.proto())),
+    ])
+}
+
+/// Describe the data of the given chain [`Subject`].
+pub fn describe_subject(subject: &Subject) -> Scalar {
+    let schema: Scalar = match subject {
+        Subject::BTree(btree) => Value::from(
+            BTreeInstance::schema(btree)
+                .to_vec()
+                .into_iter()
+                .collect::<Tuple<Value>>(),
+        )
+        .into(),
+        Subject::Map(map) => map
+            .iter()
+            .map(|(name, member)| (name.clone(), describe_subject(member)))
+            .collect::<Map<Scalar>>()
+            .into(),
+        Subject::Table(table) => {
+            let schema: Value = TableInstance::schema(table).clone().cast_into();
+            schema.into()
+        }
+        Subject::Tuple(tuple) => tuple
+            .iter()
+            .map(describe_subject)
+            .collect::<Tuple<Scalar>>()
+            .into(),
+        #[cfg(feature = "tc-tensor")]
+        Subject::Dense(dense) => {
+            let schema: Value = Persist::schema(dense).clone().cast_into();
+            schema.into()
+        }
+        #[cfg(feature = "tc-tensor")]
+        Subject::Sparse(sparse) => {
+            let schema: Value = Persist::schema(sparse).clone().cast_into();
+            schema.into()
+        }
+    };
+
+    describe(vec![(CLASS, class_link(subject.class())), (SCHEMA, schema)])
+}
+
+/// Describe the chains, classes, and dependencies of the given [`Cluster`].
+pub fn describe_cluster(cluster: &Cluster) -> Scalar {
+    let mut chains = Map::new();
+    let mut classes = Map::new();
+    let mut libraries = Map::new();
+
+    for name in cluster.ns() {
+        if let Some(chain) = cluster.chain(name) {
+            let description = describe(vec![
+                (CLASS, class_link(chain.class())),
+                (label("subject"), describe_subject(chain.subject())),
+            ]);
+
+            chains.insert(name.clone(), description);
+        } else if let Some(class) = cluster.class(name) {
+            classes.insert(name.clone(), describe_class(class));
+        } else if let Some(library) = cluster.library(name) {
+            libraries.insert(name.clone(), Value::from(library.link().clone()).into());
+        }
+    }
+
+    describe(vec![
+        (label("chains"), chains.into()),
+        (label("classes"), classes.into()),
+        (label("libraries"), libraries.into()),
+    ])
+}
+
+/// Return a [`Link`] to the given native class.
+pub fn class_link<C: NativeClass>(class: C) -> Scalar {
+    Value::from(Link::from(class.path())).into()
+}
+
+fn describe(entries: Vec<(Label, Scalar)>) -> Scalar {
+    entries
+        .into_iter()
+        .map(|(name, value)| (Id::from(name), value))
+        .collect::<Map<Scalar>>()
+        .into()
+}
@@ -0,0 +1,126 @@
+use bytes::Bytes;
+use safecast::TryCastInto;
+use sha2::{Digest, Sha256};
+
+use tc_error::*;
+use tc_value::{TCString, Value};
+use tcgeneric::PathSegment;
+
+use crate::route::{GetHandler, Handler, Route};
+use crate::state::State;
+
+struct Unary<F> {
+    name: &'static str,
+    op: F,
+}
+
+impl<F> Unary<F> {
+    fn new(name: &'static str, op: F) -> Self {
+        Self { name, op }
+    }
+}
+
+impl<'a, F> Handler<'a> for Unary<F>
+where
+    F: Fn() -> Value + Send + 'a,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, value| {
+            Box::pin(async move {
+                if value.is_some() {
+                    return Err(TCError::unsupported(format!(
+                        "{} does not have any parameters (found {})",
+                        self.name, value
+                    )));
+                }
+
+                Ok(State::from((self.op)()))
+            })
+        }))
+    }
+}
+
+impl Route for Bytes {
+    fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+        if path.len() != 1 {
+            return None;
+        }
+
+        let handler: Box<dyn Handler<'a> + 'a> = match path[0].as_str() {
+            "len" => Box::new(Unary::new("len", move || Value::from(self.len() as u64))),
+
+            // hashing
+            "blake3" => Box::new(Unary::new("blake3", move || {
+                let hash = blake3::hash(self);
+                Value::Bytes(Bytes::copy_from_slice(hash.as_bytes()))
+            })),
+            "sha256" => Box::new(Unary::new("sha256", move || {
+                let hash = Sha256::digest(self);
+                Value::Bytes(Bytes::copy_from_slice(&hash))
+            })),
+
+            // encoding
+            "to_base64" => Box::new(Unary::new("to_base64", move || {
+                Value::String(base64::encode(self).into())
+            })),
+            "to_hex" => Box::new(Unary::new("to_hex", move || {
+                Value::String(hex::encode(self).into())
+            })),
+
+            _ => return None,
+        };
+
+        Some(handler)
+    }
+}
+
+struct DecodeHandler {
+    encoding: &'static str,
+    decode: fn(&str) -> Option<Vec<u8>>,
+}
+
+impl<'a> Handler<'a> for DecodeHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                let encoded: TCString = key.try_cast_into(|v| {
+                    TCError::bad_request(format!("invalid {} string", self.encoding), v)
+                })?;
+
+                let decoded = (self.decode)(encoded.as_str()).ok_or_else(|| {
+                    TCError::bad_request(format!("invalid {} string", self.encoding), encoded)
+                })?;
+
+                Ok(State::from(Value::Bytes(decoded.into())))
+            })
+        }))
+    }
+}
+
+pub struct Static;
+
+impl Route for Static {
+    fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+        if path.len() != 1 {
+            return None;
+        }
+
+        match path[0].as_str() {
+            "from_base64" => Some(Box::new(DecodeHandler {
+                encoding: "base64",
+                decode: |encoded| base64::decode(encoded).ok(),
+            })),
+            "from_hex" => Some(Box::new(DecodeHandler {
+                encoding: "hex",
+                decode: |encoded| hex::decode(encoded).ok(),
+            })),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::str::FromStr;
+
+use safecast::{CastFrom, TryCastInto};
+
+use tc_error::*;
+use tc_value::{DateTime, Number, TCString, Value};
+use tcgeneric::PathSegment;
+
+use crate::route::{GetHandler, Handler, Route};
+use crate::state::State;
+
+use super::decimal::Dual;
+
+struct NowHandler;
+
+impl<'a> Handler<'a> for NowHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                if key.is_some() {
+                    return Err(TCError::unsupported(format!(
+                        "now does not have any parameters (found {})",
+                        key
+                    )));
+                }
+
+                Ok(State::from(Value::DateTime(DateTime::now())))
+            })
+        }))
+    }
+}
+
+struct ParseHandler;
+
+impl<'a> Handler<'a> for ParseHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                let datetime: TCString =
+                    key.try_cast_into(|v| TCError::bad_request("invalid RFC 3339 datetime", v))?;
+
+                let datetime = DateTime::from_str(&datetime.to_string())?;
+                Ok(State::from(Value::DateTime(datetime)))
+            })
+        }))
+    }
+}
+
+pub struct Static;
+
+impl Route for Static {
+    fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+        if path.len() != 1 {
+            return None;
+        }
+
+        match path[0].as_str() {
+            "now" => Some(Box::new(NowHandler)),
+            "parse" => Some(Box::new(ParseHandler)),
+            _ => None,
+        }
+    }
+}
+
+impl Route for DateTime {
+    fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+        if path.len() != 1 {
+            return None;
+        }
+
+        let handler: Box<dyn Handler<'a> + 'a> = match path[0].as_str() {
+            // arithmetic
+            "add_duration" => Box::new(Dual::new(move |seconds: Number| {
+                self.add_seconds(f64::cast_from(seconds)).map(Value::from)
+            })),
+            "sub" => Box::new(Dual::new(move |other: Value| match other {
+                Value::DateTime(other) => Ok(Value::Number(self.seconds_since(&other).into())),
+                other => {
+                    let seconds: Number =
+                        other.try_cast_into(|v| TCError::bad_request("invalid duration", v))?;
+
+                    self.add_seconds(-f64::cast_from(seconds)).map(Value::from)
+                }
+            })),
+
+            // comparison
+            "gt" => Box::new(Dual::new(move |other: DateTime| Ok((self > &other).into()))),
+            "gte" => Box::new(Dual::new(
+                move |other: DateTime| Ok((self >= &other).into()),
+            )),
+            "lt" => Box::new(Dual::new(move |other: DateTime| Ok((self < &other).into()))),
+            "lte" => Box::new(Dual::new(
+                move |other: DateTime| Ok((self <= &other).into()),
+            )),
+
+            _ => return None,
+        };
+
+        Some(handler)
+    }
+}
@@ -0,0 +1,178 @@
+use std::marker::PhantomData;
+
+use safecast::{TryCastFrom, TryCastInto};
+
+use tc_error::*;
+use tc_value::{BigInt, Decimal, Value};
+use tcgeneric::{label, PathSegment};
+
+use crate::route::{GetHandler, Handler, PostHandler, Route};
+use crate::state::State;
+
+pub(super) struct Dual<T, F> {
+    op: F,
+    operand: PhantomData<T>,
+}
+
+impl<T, F> Dual<T, F> {
+    pub fn new(op: F) -> Self {
+        Self {
+            op,
+            operand: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, F> Handler<'a> for Dual<T, F>
+where
+    T: TryCastFrom<Value> + Send + 'a,
+    F: Fn(T) -> TCResult<Value> + Send + 'a,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, value| {
+            Box::pin(async move {
+                let value = value.try_cast_into(|v| TCError::bad_request("invalid operand", v))?;
+                (self.op)(value).map(State::from)
+            })
+        }))
+    }
+
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, mut params| {
+            Box::pin(async move {
+                let value: Value = params.require(&label("r").into())?;
+                params.expect_empty()?;
+
+                let value = value.try_cast_into(|v| TCError::bad_request("invalid operand", v))?;
+                (self.op)(value).map(State::from)
+            })
+        }))
+    }
+}
+
+pub(super) struct Unary<F> {
+    name: &'static str,
+    op: F,
+}
+
+impl<F> Unary<F> {
+    pub fn new(name: &'static str, op: F) -> Self {
+        Self { name, op }
+    }
+}
+
+impl<'a, F> Handler<'a> for Unary<F>
+where
+    F: Fn() -> Value + Send + 'a,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, value| {
+            Box::pin(async move {
+                if value.is_some() {
+                    return Err(TCError::unsupported(format!(
+                        "{} does not have any parameters (found {})",
+                        self.name, value
+                    )));
+                }
+
+                Ok(State::from((self.op)()))
+            })
+        }))
+    }
+}
+
+impl Route for BigInt {
+    fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+        if path.len() != 1 {
+            return None;
+        }
+
+        let handler: Box<dyn Handler<'a> + 'a> = match path[0].as_str() {
+            // basic math
+            "abs" => Box::new(Unary::new("abs", move || {
+                Value::BigInt(if self < &BigInt::from(0) {
+                    -self
+                } else {
+                    self.clone()
+                })
+            })),
+            "add" => Box::new(Dual::new(move |other: BigInt| Ok((self + other).into()))),
+            "div" => Box::new(Dual::new(move |other: BigInt| {
+                if other == BigInt::from(0) {
+                    Err(TCError::bad_request("cannot divide by zero", self))
+                } else {
+                    Ok((self / other).into())
+                }
+            })),
+            "mul" => Box::new(Dual::new(move |other: BigInt| Ok((self * other).into()))),
+            "rem" => Box::new(Dual::new(move |other: BigInt| {
+                if other == BigInt::from(0) {
+                    Err(TCError::bad_request("cannot divide by zero", self))
+                } else {
+                    Ok((self % other).into())
+                }
+            })),
+            "sub" => Box::new(Dual::new(move |other: BigInt| Ok((self - other).into()))),
+
+            // comparison
+            "gt" => Box::new(Dual::new(move |other: BigInt| Ok((self > &other).into()))),
+            "gte" => Box::new(Dual::new(move |other: BigInt| Ok((self >= &other).into()))),
+            "lt" => Box::new(Dual::new(move |other: BigInt| Ok((self < &other).into()))),
+            "lte" => Box::new(Dual::new(move |other: BigInt| Ok((self <= &other).into()))),
+
+            _ => return None,
+        };
+
+        Some(handler)
+    }
+}
+
+impl Route for Decimal {
+    fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+        if path.len() != 1 {
+            return None;
+        }
+
+        let handler: Box<dyn Handler<'a> + 'a> = match path[0].as_str() {
+            // basic math
+            "abs" => Box::new(Unary::new("abs", move || self.abs().into())),
+            "add" => Box::new(Dual::new(move |other| Ok((self.clone() + other).into()))),
+            "div" => Box::new(Dual::new(move |other| {
+                self.checked_div(&other).map(Value::from)
+            })),
+            "mul" => Box::new(Dual::new(move |other| Ok((self.clone() * other).into()))),
+            "sub" => Box::new(Dual::new(move |other| Ok((self.clone() - other).into()))),
+
+            // rounding
+            "round" => Box::new(Dual::new(move |places: Value| {
+                let places = if places.is_none() {
+                    0
+                } else {
+                    places.try_cast_into(|v| TCError::bad_request("invalid decimal places", v))?
+                };
+
+                Ok(self.round(places).into())
+            })),
+            "trunc" => Box::new(Unary::new("trunc", move || self.trunc().into())),
+
+            // comparison
+            "gt" => Box::new(Dual::new(move |other: Decimal| Ok((self > &other).into()))),
+            "gte" => Box::new(Dual::new(move |other: Decimal| Ok((self >= &other).into()))),
+            "lt" => Box::new(Dual::new(move |other: Decimal| Ok((self < &other).into()))),
+            "lte" => Box::new(Dual::new(move |other: Decimal| Ok((self <= &other).into()))),
+
+            _ => return None,
+        };
+
+        Some(handler)
+    }
+}
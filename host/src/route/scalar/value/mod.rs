@@ -3,17 +3,22 @@ use safecast::TryCastFrom;
 use uuid::Uuid;
 
 use tc_error::TCError;
-use tc_value::Value;
-use tcgeneric::{label, Label, PathSegment};
+use tc_value::{Value, ValueType};
+use tcgeneric::{label, Label, NativeClass, PathSegment};
 
 use crate::route::{GetHandler, Handler, Route, SelfHandler};
 use crate::state::State;
 
+mod binary;
+mod datetime;
+mod decimal;
 mod number;
 mod string;
 
 pub const PREFIX: Label = label("value");
 
+const CAST: Label = label("cast");
+
 struct EqHandler<F> {
     call: F,
 }
@@ -38,6 +43,10 @@ impl<F> From<F> for EqHandler<F> {
 impl Route for Value {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
         let child_handler = match self {
+            Self::BigInt(i) => i.route(path),
+            Self::Bytes(bytes) => bytes.route(path),
+            Self::DateTime(datetime) => datetime.route(path),
+            Self::Decimal(decimal) => decimal.route(path),
             Self::Number(number) => number.route(path),
             Self::String(s) => s.route(path),
             Self::Tuple(tuple) => tuple.route(path),
@@ -95,6 +104,54 @@ impl<'a> Handler<'a> for UuidHandler<'a> {
     }
 }
 
+/// Whether an explicit cast may lose information.
+#[derive(Clone, Copy)]
+enum CastMode {
+    Strict,
+    Lossy,
+}
+
+struct CastHandler {
+    dtype: ValueType,
+    mode: CastMode,
+}
+
+impl<'a> Handler<'a> for CastHandler {
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                let value = match self.mode {
+                    CastMode::Strict => self.dtype.try_cast_strict(key),
+                    CastMode::Lossy => self.dtype.try_cast(key),
+                }?;
+
+                Ok(State::from(value))
+            })
+        }))
+    }
+}
+
+// route `<dtype>/cast/strict` or `<dtype>/cast/lossy`--a bare `<dtype>/cast` has no route,
+// so that a caller always has to choose whether the cast may lose information
+fn route_cast<'a>(path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
+    let i = path.iter().position(|segment| segment == &CAST)?;
+
+    let mode = match &path[i + 1..] {
+        [mode] if mode.as_str() == "strict" => CastMode::Strict,
+        [mode] if mode.as_str() == "lossy" => CastMode::Lossy,
+        _ => return None,
+    };
+
+    let mut class_path = ValueType::Value.path();
+    class_path.extend(path[..i].iter().cloned());
+    let dtype = ValueType::from_path(&class_path)?;
+
+    Some(Box::new(CastHandler { dtype, mode }))
+}
+
 pub struct Static;
 
 impl Route for Static {
@@ -103,13 +160,19 @@ impl Route for Static {
             return None;
         }
 
+        if let Some(handler) = route_cast(path) {
+            return Some(handler);
+        }
+
         match path[0].as_str() {
             "bytes" | "id" | "string" if path.len() == 2 => match path[1].as_str() {
                 "uuid" => Some(Box::new(UuidHandler {
                     dtype: path[0].as_str(),
                 })),
+                _ if path[0] == "bytes" => binary::Static.route(&path[1..]),
                 _ => None,
             },
+            "datetime" if path.len() == 2 => datetime::Static.route(&path[1..]),
             _ => None,
         }
     }
@@ -1,7 +1,7 @@
 use safecast::TryCastInto;
 
 use tc_error::*;
-use tc_value::{Number, NumberClass, NumberInstance, Trigonometry, Value};
+use tc_value::{CheckedArithmetic, ComplexInstance, Number, NumberInstance, Trigonometry, Value};
 use tcgeneric::{label, PathSegment};
 
 use crate::route::{GetHandler, Handler, PostHandler, Route};
@@ -91,18 +91,20 @@ impl Route for Number {
         let handler: Box<dyn Handler<'a> + 'a> = match path[0].as_str() {
             // basic math
             "abs" => Box::new(Unary::new("abs", move || self.abs())),
-            "add" => Box::new(Dual::new(move |other| Ok(*self + other))),
+            "add" => Box::new(Dual::new(move |other| self.checked_add(other))),
             "and" => Box::new(Dual::new(move |other| Ok(self.and(other)))),
-            "div" => Box::new(Dual::new(move |other: Number| {
-                if other == other.class().zero() {
-                    Err(TCError::unsupported("cannot divide by zero"))
-                } else {
-                    Ok(*self / other)
-                }
-            })),
-            "mul" => Box::new(Dual::new(move |other| Ok(*self * other))),
-            "sub" => Box::new(Dual::new(move |other| Ok(*self - other))),
-            "pow" => Box::new(Dual::new(move |other| Ok(self.pow(other)))),
+            "div" => Box::new(Dual::new(move |other| self.checked_div(other))),
+            "mul" => Box::new(Dual::new(move |other| self.checked_mul(other))),
+            "rem" => Box::new(Dual::new(move |other| self.checked_rem(other))),
+            "sub" => Box::new(Dual::new(move |other| self.checked_sub(other))),
+            "pow" => Box::new(Dual::new(move |other| self.checked_pow(other))),
+            "exp" => Box::new(Unary::new("exp", move || self.exp())),
+
+            // complex numbers
+            "re" => Box::new(Unary::new("re", move || self.re())),
+            "im" => Box::new(Unary::new("im", move || self.im())),
+            "conj" => Box::new(Unary::new("conj", move || self.conj())),
+            "arg" => Box::new(Unary::new("arg", move || self.arg())),
 
             // comparison
             "gt" => Box::new(Dual::new(move |other| Ok((*self > other).into()))),
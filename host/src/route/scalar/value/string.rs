@@ -1,12 +1,219 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
 use safecast::{Match, TryCastFrom, TryCastInto};
 
 use tc_error::*;
-use tc_value::{TCString, Value};
-use tcgeneric::{Map, PathSegment};
+use tc_value::{Number, TCString, Value};
+use tcgeneric::{label, Id, Label, Map, PathSegment, Tuple};
 
 use crate::route::{GetHandler, Handler, PostHandler, Route};
 use crate::state::State;
 
+const PATTERN: Label = label("pattern");
+const REGEX_CACHE_SIZE: usize = 256;
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+lazy_static! {
+    static ref REGEX_CACHE: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+}
+
+// compiling a regular expression is much more expensive than matching it,
+// so cache compiled patterns since the same one is typically applied to many rows of a table
+fn compile(pattern: &str) -> TCResult<Regex> {
+    let mut cache = REGEX_CACHE
+        .lock()
+        .map_err(|_| TCError::internal("regular expression cache is poisoned"))?;
+
+    if let Some(regex) = cache.get(pattern) {
+        return Ok(regex.clone());
+    }
+
+    let regex = RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|cause| {
+            TCError::bad_request(format!("invalid regular expression {}", pattern), cause)
+        })?;
+
+    if cache.len() >= REGEX_CACHE_SIZE {
+        cache.clear();
+    }
+
+    cache.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+struct RegexHandler<'a, F> {
+    string: &'a TCString,
+    arg: Option<Id>,
+    op: F,
+}
+
+impl<'a, F> RegexHandler<'a, F> {
+    fn new(string: &'a TCString, arg: Option<Id>, op: F) -> Self {
+        Self { string, arg, op }
+    }
+}
+
+impl<'a, F> RegexHandler<'a, F>
+where
+    F: Fn(&str, Regex, Value) -> TCResult<Value> + Send + 'a,
+{
+    fn call(self, pattern: Value, arg: Value) -> TCResult<State> {
+        let pattern: TCString =
+            pattern.try_cast_into(|v| TCError::bad_request("invalid regular expression", v))?;
+
+        let regex = compile(&pattern)?;
+        (self.op)(self.string, regex, arg).map(State::from)
+    }
+}
+
+impl<'a, F> Handler<'a> for RegexHandler<'a, F>
+where
+    F: Fn(&str, Regex, Value) -> TCResult<Value> + Send + 'a,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, key| {
+            Box::pin(async move {
+                if self.arg.is_some() && key.matches::<(Value, Value)>() {
+                    let (pattern, arg): (Value, Value) = key.opt_cast_into().unwrap();
+                    self.call(pattern, arg)
+                } else {
+                    self.call(key, Value::None)
+                }
+            })
+        }))
+    }
+
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, mut params| {
+            Box::pin(async move {
+                let pattern: Value = params.require(&PATTERN.into())?;
+                let arg: Value = if let Some(arg) = &self.arg {
+                    params.or_default(arg)?
+                } else {
+                    Value::None
+                };
+
+                params.expect_empty()?;
+
+                self.call(pattern, arg)
+            })
+        }))
+    }
+}
+
+fn is_match(string: &str, regex: Regex, _: Value) -> TCResult<Value> {
+    Ok(regex.is_match(string).into())
+}
+
+fn extract(string: &str, regex: Regex, group: Value) -> TCResult<Value> {
+    let captures = if let Some(captures) = regex.captures(string) {
+        captures
+    } else {
+        return Ok(Value::None);
+    };
+
+    let group = match group {
+        Value::None => captures.get(0),
+        Value::Number(Number::UInt(i)) => captures.get(u64::from(i) as usize),
+        Value::Number(Number::Int(i)) if i64::from(i) >= 0 => captures.get(i64::from(i) as usize),
+        Value::Id(name) => captures.name(name.as_str()),
+        Value::String(name) => captures.name(&name),
+        other => return Err(TCError::bad_request("invalid capture group", other)),
+    };
+
+    Ok(group
+        .map(|group| Value::String(group.as_str().to_string().into()))
+        .unwrap_or_default())
+}
+
+fn replace(string: &str, regex: Regex, template: Value) -> TCResult<Value> {
+    let template: TCString =
+        template.try_cast_into(|v| TCError::bad_request("invalid replacement template", v))?;
+
+    let replaced = regex.replace_all(string, template.as_str());
+    Ok(Value::String(replaced.into_owned().into()))
+}
+
+struct Unary<F> {
+    name: &'static str,
+    op: F,
+}
+
+impl<F> Unary<F> {
+    fn new(name: &'static str, op: F) -> Self {
+        Self { name, op }
+    }
+}
+
+impl<'a, F> Handler<'a> for Unary<F>
+where
+    F: Fn() -> Value + Send + 'a,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, value| {
+            Box::pin(async move {
+                if value.is_some() {
+                    return Err(TCError::unsupported(format!(
+                        "{} does not have any parameters (found {})",
+                        self.name, value
+                    )));
+                }
+
+                Ok(State::from((self.op)()))
+            })
+        }))
+    }
+}
+
+struct Dual<F> {
+    op: F,
+}
+
+impl<F> Dual<F> {
+    fn new(op: F) -> Self {
+        Self { op }
+    }
+}
+
+impl<'a, F> Handler<'a> for Dual<F>
+where
+    F: Fn(Value) -> TCResult<Value> + Send + 'a,
+{
+    fn get<'b>(self: Box<Self>) -> Option<GetHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, value| {
+            Box::pin(async move { (self.op)(value).map(State::from) })
+        }))
+    }
+}
+
+fn cast_string(value: Value) -> TCResult<TCString> {
+    value.try_cast_into(|v| TCError::bad_request("expected a String but found", v))
+}
+
+fn cast_strings(value: Value) -> TCResult<Vec<TCString>> {
+    match value {
+        Value::Tuple(tuple) => tuple.into_iter().map(cast_string).collect(),
+        value => cast_string(value).map(|s| vec![s]),
+    }
+}
+
 struct RenderHandler<'a> {
     template: &'a TCString,
 }
@@ -63,6 +270,72 @@ impl Route for TCString {
 
         match path[0].as_str() {
             "render" => Some(Box::new(RenderHandler { template: self })),
+            "format" => Some(Box::new(Dual::new(move |args| {
+                self.format(&cast_strings(args)?).map(Value::String)
+            }))),
+
+            // case & whitespace
+            "lowercase" => Some(Box::new(Unary::new("lowercase", move || {
+                Value::String(self.to_lowercase().into())
+            }))),
+            "uppercase" => Some(Box::new(Unary::new("uppercase", move || {
+                Value::String(self.to_uppercase().into())
+            }))),
+            "trim" => Some(Box::new(Unary::new("trim", move || {
+                Value::String(self.trim().to_string().into())
+            }))),
+
+            // search
+            "contains" => Some(Box::new(Dual::new(move |pattern| {
+                cast_string(pattern).map(|pattern| self.contains(pattern.as_str()).into())
+            }))),
+            "ends_with" => Some(Box::new(Dual::new(move |suffix| {
+                cast_string(suffix).map(|suffix| self.ends_with(suffix.as_str()).into())
+            }))),
+            "starts_with" => Some(Box::new(Dual::new(move |prefix| {
+                cast_string(prefix).map(|prefix| self.starts_with(prefix.as_str()).into())
+            }))),
+
+            // split & join
+            "join" => Some(Box::new(Dual::new(move |values| {
+                let values = cast_strings(values)?
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<String>>();
+
+                Ok(Value::String(values.join(self.as_str()).into()))
+            }))),
+            "split" => Some(Box::new(Dual::new(move |separator: Value| {
+                let parts: Tuple<Value> = if separator.is_none() {
+                    self.split_whitespace()
+                        .map(|part| Value::String(part.to_string().into()))
+                        .collect()
+                } else {
+                    let separator = cast_string(separator)?;
+                    if separator.is_empty() {
+                        return Err(TCError::bad_request("invalid separator", "(empty)"));
+                    }
+
+                    self.split(separator.as_str())
+                        .map(|part| Value::String(part.to_string().into()))
+                        .collect()
+                };
+
+                Ok(Value::Tuple(parts))
+            }))),
+
+            // regular expressions
+            "matches" => Some(Box::new(RegexHandler::new(self, None, is_match))),
+            "extract" => Some(Box::new(RegexHandler::new(
+                self,
+                Some(label("group").into()),
+                extract,
+            ))),
+            "replace" => Some(Box::new(RegexHandler::new(
+                self,
+                Some(label("template").into()),
+                replace,
+            ))),
             _ => None,
         }
     }
@@ -6,6 +6,7 @@ use tc_error::*;
 use tc_value::{Link, Number};
 use tcgeneric::{label, Id, Instance, Label, NativeClass, PathSegment, Tuple};
 
+use crate::closure::Closure;
 use crate::object::{InstanceClass, Object};
 use crate::state::{State, StateType};
 
@@ -117,6 +118,21 @@ impl Route for StateType {
     }
 }
 
+struct BindHandler<'a> {
+    closure: &'a Closure,
+}
+
+impl<'a> Handler<'a> for BindHandler<'a> {
+    fn post<'b>(self: Box<Self>) -> Option<PostHandler<'a, 'b>>
+    where
+        'b: 'a,
+    {
+        Some(Box::new(|_txn, params| {
+            Box::pin(async move { self.closure.clone().bind(params).map(State::Closure) })
+        }))
+    }
+}
+
 impl Route for State {
     fn route<'a>(&'a self, path: &'a [PathSegment]) -> Option<Box<dyn Handler<'a> + 'a>> {
         debug!("{} route {}", self.class(), TCPath::from(path));
@@ -127,6 +143,10 @@ impl Route for State {
                 let handler: Box<dyn Handler<'a> + 'a> = Box::new(closure.clone());
                 Some(handler)
             }
+            Self::Closure(closure) if path.len() == 1 && path[0].as_str() == "bind" => {
+                let handler: Box<dyn Handler<'a> + 'a> = Box::new(BindHandler { closure });
+                Some(handler)
+            }
             Self::Collection(collection) => collection.route(path),
             Self::Map(map) => map.route(path),
             Self::Object(object) => object.route(path),
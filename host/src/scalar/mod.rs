@@ -1035,6 +1035,28 @@ impl<T1: TryCastFrom<Scalar>, T2: TryCastFrom<Scalar>, T3: TryCastFrom<Scalar>>
     }
 }
 
+impl<T1, T2, T3, T4> TryCastFrom<Scalar> for (T1, T2, T3, T4)
+where
+    T1: TryCastFrom<Scalar>,
+    T2: TryCastFrom<Scalar>,
+    T3: TryCastFrom<Scalar>,
+    T4: TryCastFrom<Scalar>,
+{
+    fn can_cast_from(scalar: &Scalar) -> bool {
+        match scalar {
+            Scalar::Tuple(tuple) => Self::can_cast_from(tuple),
+            _ => false,
+        }
+    }
+
+    fn opt_cast_from(scalar: Scalar) -> Option<Self> {
+        match scalar {
+            Scalar::Tuple(tuple) => Self::opt_cast_from(tuple),
+            _ => None,
+        }
+    }
+}
+
 /// A [`de::Visitor`] used to deserialize a [`Scalar`].
 #[derive(Default)]
 pub struct ScalarVisitor {
@@ -1271,6 +1293,15 @@ pub struct Scope<'a, T> {
     data: Map<State>,
 }
 
+impl<'a, T> Clone for Scope<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            subject: self.subject,
+            data: self.data.clone(),
+        }
+    }
+}
+
 impl<'a, T: ToState + Instance + Public> Scope<'a, T> {
     pub fn new<S: Into<State>, I: IntoIterator<Item = (Id, S)>>(
         subject: Option<&'a T>,
@@ -1,5 +1,6 @@
 //! User-defined [`OpDef`]s
 
+use std::collections::HashSet;
 use std::fmt;
 use std::iter;
 use std::str::FromStr;
@@ -15,6 +16,8 @@ use crate::route::{DeleteHandler, GetHandler, Handler, PostHandler, PutHandler};
 use crate::scalar::{Executor, Refer, Scalar};
 use crate::state::State;
 
+use super::{destructure_params, Signature, SIGNATURE};
+
 const PREFIX: PathLabel = path_label(&["state", "scalar", "op"]);
 
 /// The [`Class`] of a user-defined [`OpDef`].
@@ -121,17 +124,71 @@ impl OpDef {
         .iter()
     }
 
-    /// Return the last assignment in this `OpDef`.
+    /// Return the declared [`Signature`] of this `OpDef`, if any.
+    pub fn signature(&self) -> Option<Signature> {
+        match self {
+            Self::Post(form) => match Signature::of(form) {
+                Ok(signature) => signature,
+                Err(cause) => {
+                    debug!("invalid signature of {}: {}", self, cause);
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// Return the last assignment in this `OpDef` (not counting its [`Signature`], if any).
     pub fn last(&self) -> Option<&Id> {
         match self {
             Self::Get((_, form)) => form.last(),
             Self::Put((_, _, form)) => form.last(),
-            Self::Post(form) => form.last(),
+            Self::Post(form) => form.last().filter(|(id, _)| id != &SIGNATURE),
             Self::Delete((_, form)) => form.last(),
         }
         .map(|(id, _)| id)
     }
 
+    /// List the IDs referenced by this `OpDef` which it does not define itself (excluding its key
+    /// and value names), which are the parameters a caller is expected to provide,
+    /// or the declared parameters of its [`Signature`], if any.
+    pub fn params(&self) -> Vec<Id> {
+        if let Some(signature) = self.signature() {
+            return signature
+                .params()
+                .iter()
+                .map(|param| param.name().clone())
+                .collect();
+        }
+
+        let mut deps = HashSet::new();
+        for (_, provider) in self.form() {
+            provider.requires(&mut deps);
+        }
+
+        for (id, _) in self.form() {
+            deps.remove(id);
+        }
+
+        match self {
+            Self::Get((key_name, _)) | Self::Delete((key_name, _)) => {
+                deps.remove(key_name);
+            }
+            Self::Put((key_name, value_name, _)) => {
+                deps.remove(key_name);
+                deps.remove(value_name);
+            }
+            Self::Post(_) => {}
+        }
+
+        deps.remove(&Id::from(label("self")));
+        deps.remove(&Id::from(label("txn")));
+
+        let mut params: Vec<Id> = deps.into_iter().collect();
+        params.sort();
+        params
+    }
+
     /// Return `true` if this `OpDef` may execute a write operation to another service.
     pub fn is_inter_service_write(&self, cluster_path: &[PathSegment]) -> bool {
         self.form()
@@ -239,6 +296,8 @@ impl<'a> Handler<'a> for OpDef {
         if let OpDef::Post(op_def) = *self {
             Some(Box::new(|txn, params| {
                 Box::pin(async move {
+                    let (params, op_def) = destructure_params(op_def, params)?;
+
                     let capture = if let Some((capture, _)) = op_def.last() {
                         capture.clone()
                     } else {
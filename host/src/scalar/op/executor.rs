@@ -1,8 +1,9 @@
 //! An executor for an `OpDef`
 
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use futures::future::FutureExt;
 use futures::stream::{FuturesUnordered, StreamExt};
 use log::debug;
 
@@ -10,14 +11,35 @@ use tc_error::*;
 use tcgeneric::{Id, Instance, Map, Tuple};
 
 use crate::route::Public;
-use crate::scalar::{Refer, Scope};
+use crate::scalar::{Refer, Scalar, Scope, TCRef};
 use crate::state::{State, ToState};
 use crate::txn::Txn;
 
+#[cfg(feature = "tc-tensor")]
+use super::tape;
+
+// the maximum number of providers to resolve concurrently, or zero for the number of CPUs
+static PARALLELISM: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the maximum number of providers which an [`Executor`] resolves concurrently.
+///
+/// A `parallelism` of zero (the default) means the number of CPUs on this host.
+pub fn set_op_parallelism(parallelism: usize) {
+    PARALLELISM.store(parallelism, Ordering::Relaxed);
+}
+
+fn op_parallelism() -> usize {
+    match PARALLELISM.load(Ordering::Relaxed) {
+        0 => num_cpus::get(),
+        parallelism => parallelism,
+    }
+}
+
 /// An `OpDef` executor.
 pub struct Executor<'a, T> {
     txn: &'a Txn,
-    scope: Scope<'a, T>,
+    scope: Arc<Scope<'a, T>>,
+    parallelism: usize,
 }
 
 impl<'a, T: ToState + Instance + Public> Executor<'a, T> {
@@ -28,7 +50,7 @@ impl<'a, T: ToState + Instance + Public> Executor<'a, T> {
         data: I,
     ) -> Self {
         let scope = Scope::new(subject, data);
-        Self { txn, scope }
+        Self::from_scope(txn, scope)
     }
 
     pub fn with_context<S: Into<State>, I: IntoIterator<Item = (Id, S)>>(
@@ -38,11 +60,15 @@ impl<'a, T: ToState + Instance + Public> Executor<'a, T> {
         iter: I,
     ) -> Self {
         let scope = Scope::with_context(subject, context, iter);
-        Self { txn, scope }
+        Self::from_scope(txn, scope)
     }
 
     pub fn from_scope(txn: &'a Txn, scope: Scope<'a, T>) -> Self {
-        Self { txn, scope }
+        Self {
+            txn,
+            scope: Arc::new(scope),
+            parallelism: op_parallelism(),
+        }
     }
 
     /// Resolve the state of the variable `capture`, including any of its dependencies.
@@ -50,84 +76,326 @@ impl<'a, T: ToState + Instance + Public> Executor<'a, T> {
         debug!("execute op & capture {}", capture);
 
         while self.scope.resolve_id(&capture)?.is_ref() {
-            let mut pending = Vec::with_capacity(self.scope.len());
-            let mut unvisited = VecDeque::with_capacity(self.scope.len());
-            unvisited.push_back(capture.clone());
-
-            while let Some(id) = unvisited.pop_front() {
-                let state = self.scope.resolve_id(&id)?;
-                debug!("checking state {}: {}", id, state);
-
-                if state.is_ref() {
-                    let mut deps = HashSet::new();
-                    state.requires(&mut deps);
-
-                    if deps.contains(&id) {
-                        return Err(TCError::bad_request("circular dependency", id));
-                    }
-
-                    let mut ready = true;
-                    for dep_id in deps.into_iter() {
-                        if self.scope.resolve_id(&dep_id)?.is_ref() {
-                            ready = false;
-                            unvisited.push_back(dep_id);
-                        }
-                    }
+            let mut schedule = self.schedule(&capture)?;
 
-                    if ready {
-                        debug!("all deps resolved for {}", state);
-                        pending.push(id);
-                    } else {
-                        debug!("{} still has unresolved deps", id);
-                    }
-                } else {
-                    debug!("{} already resolved: {}", id, state);
-                }
-            }
-
-            if pending.is_empty() && self.scope.resolve_id(&capture)?.is_ref() {
+            if schedule.is_blocked() {
                 return Err(TCError::bad_request(
                     "cannot resolve all dependencies of",
                     capture,
                 ));
             }
 
-            let mut resolved = HashMap::with_capacity(pending.len());
-            {
-                let mut providers = FuturesUnordered::new();
-                for id in pending.into_iter() {
-                    debug!("enqueue {}", id);
+            // the states resolved since the scope was last shared with a provider
+            let mut resolved = Vec::new();
+
+            // resolve each provider as soon as all its dependencies are resolved,
+            // rather than waiting for every other provider at the same depth in the graph
+            let mut providers = FuturesUnordered::new();
+            loop {
+                while providers.len() < self.parallelism {
+                    let id = if let Some(id) = schedule.next() {
+                        id
+                    } else {
+                        break;
+                    };
+
+                    self.update_scope(&mut resolved);
+
                     let state = self.scope.resolve_id(&id)?;
                     debug!("provider for {} is {}", id, state);
-                    providers.push(
-                        state
-                            .clone()
-                            .resolve(&self.scope, &self.txn)
-                            .map(|r| (id, r)),
-                    );
+
+                    let provider = tape_provider(&state);
+                    let context = ExecutionContext::new(id, &state);
+
+                    let scope = self.scope.clone();
+                    let txn = self.txn;
+                    providers.push(async move {
+                        let result = state.resolve(&scope, txn).await;
+                        (context, provider, result)
+                    });
                 }
 
-                while let Some((id, r)) = providers.next().await {
-                    match r {
-                        Ok(state) => {
-                            debug!("{} resolved to {}", id, state);
-                            resolved.insert(id, state);
+                let (id, provider, state) =
+                    if let Some((context, provider, result)) = providers.next().await {
+                        match result {
+                            Ok(state) => (context.id, provider, state),
+                            Err(cause) => {
+                                self.update_scope(&mut resolved);
+                                return Err(context.annotate(&self.scope, cause));
+                            }
                         }
-                        Err(cause) => return Err(cause.consume(format!("while resolving {}", id))),
-                    }
+                    } else {
+                        break;
+                    };
+
+                debug!("{} resolved to {}", id, state);
+
+                self.record(&id, provider, &state)?;
+
+                // a provider which resolves to another reference is resolved again in the next pass
+                let is_ref = state.is_ref();
+                resolved.push((id.clone(), state));
+
+                if !is_ref {
+                    schedule.resolved(&id);
                 }
             }
 
-            self.scope.extend(resolved);
+            self.update_scope(&mut resolved);
         }
 
-        self.scope.remove(&capture).ok_or_else(|| {
-            let msg = format!(
-                "captured state {} in context {}",
-                capture,
-                self.scope.keys().collect::<Tuple<&Id>>()
-            );
-            TCError::not_found(msg)
-        })
+        Arc::make_mut(&mut self.scope)
+            .remove(&capture)
+            .ok_or_else(|| {
+                let msg = format!(
+                    "captured state {} in context {}",
+                    capture,
+                    self.scope.keys().collect::<Tuple<&Id>>()
+                );
+                TCError::not_found(msg)
+            })
+    }
+
+    // add the given `resolved` states to the scope
+    //
+    // the providers in flight share a snapshot of the scope, so this copies it only if one of them
+    // is still running, and otherwise updates it in place
+    fn update_scope(&mut self, resolved: &mut Vec<(Id, State)>) {
+        if !resolved.is_empty() {
+            Arc::make_mut(&mut self.scope).extend(resolved.drain(..));
+        }
+    }
+
+    // record the resolution of a differentiable `provider` on the gradient tape of the txn, if any
+    #[cfg(feature = "tc-tensor")]
+    fn record(&self, id: &Id, provider: Option<State>, state: &State) -> TCResult<()> {
+        if let Some(provider) = provider {
+            tape::record(self.txn, &self.scope, id, &provider, state)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(not(feature = "tc-tensor"))]
+    fn record(&self, _id: &Id, _provider: Option<State>, _state: &State) -> TCResult<()> {
+        Ok(())
+    }
+
+    // schedule the resolution of the unresolved states which `capture` depends on
+    fn schedule(&self, capture: &Id) -> TCResult<Schedule> {
+        let mut deps = HashMap::with_capacity(self.scope.len());
+
+        let mut unvisited = VecDeque::with_capacity(self.scope.len());
+        unvisited.push_back(capture.clone());
+
+        while let Some(id) = unvisited.pop_front() {
+            if deps.contains_key(&id) {
+                continue;
+            }
+
+            let state = self.scope.resolve_id(&id)?;
+            debug!("checking state {}: {}", id, state);
+
+            let mut requires = HashSet::new();
+            state.requires(&mut requires);
+
+            if requires.contains(&id) {
+                return Err(TCError::bad_request("circular dependency", id));
+            }
+
+            let mut unresolved = HashSet::with_capacity(requires.len());
+            for dep_id in requires.into_iter() {
+                if self.scope.resolve_id(&dep_id)?.is_ref() {
+                    unvisited.push_back(dep_id.clone());
+                    unresolved.insert(dep_id);
+                }
+            }
+
+            deps.insert(id, unresolved);
+        }
+
+        Ok(Schedule::new(deps))
+    }
+}
+
+/// The order in which to resolve the providers in an `OpDef`.
+///
+/// A provider is ready to resolve as soon as all its dependencies are resolved, so any providers
+/// which are ready at the same time are independent and can be resolved concurrently.
+struct Schedule {
+    deps: HashMap<Id, HashSet<Id>>,
+    dependents: HashMap<Id, Vec<Id>>,
+    ready: VecDeque<Id>,
+}
+
+impl Schedule {
+    // construct a new `Schedule` from a map of each provider to its unresolved dependencies
+    fn new(deps: HashMap<Id, HashSet<Id>>) -> Self {
+        let mut dependents: HashMap<Id, Vec<Id>> = HashMap::with_capacity(deps.len());
+        for (id, requires) in &deps {
+            for dep_id in requires {
+                dependents
+                    .entry(dep_id.clone())
+                    .or_default()
+                    .push(id.clone());
+            }
+        }
+
+        let ready = deps
+            .iter()
+            .filter(|(_, requires)| requires.is_empty())
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        Self {
+            deps,
+            dependents,
+            ready,
+        }
+    }
+
+    // return `true` if no provider is ready, i.e. every provider depends on another
+    fn is_blocked(&self) -> bool {
+        self.ready.is_empty()
+    }
+
+    // the next provider which is ready to resolve, if any
+    fn next(&mut self) -> Option<Id> {
+        self.ready.pop_front()
+    }
+
+    // mark the provider `id` as resolved, making ready any dependent with no other dependencies
+    fn resolved(&mut self, id: &Id) {
+        for dependent in self.dependents.remove(id).into_iter().flatten() {
+            let unresolved = self.deps.get_mut(&dependent).expect("dependencies");
+            unresolved.remove(id);
+
+            if unresolved.is_empty() {
+                self.ready.push_back(dependent);
+            }
+        }
+    }
+}
+
+/// The context in which a provider in an `OpDef` is resolved, used to identify it in case of error.
+struct ExecutionContext {
+    id: Id,
+    provider: String,
+}
+
+impl ExecutionContext {
+    fn new(id: Id, provider: &State) -> Self {
+        let provider = match provider {
+            State::Scalar(Scalar::Ref(tc_ref)) => match &**tc_ref {
+                TCRef::Op(op_ref) => format!("{} {}", op_ref.class(), op_ref.subject()),
+                other => other.class().to_string(),
+            },
+            other => other.class().to_string(),
+        };
+
+        Self { id, provider }
+    }
+
+    // annotate an error with the ID and provider which failed, and the IDs resolved so far
+    fn annotate<T>(self, scope: &Scope<T>, cause: TCError) -> TCError {
+        let resolved = scope
+            .iter()
+            .filter(|(_, state)| !state.is_ref())
+            .map(|(id, _)| id)
+            .collect::<Tuple<&Id>>();
+
+        cause.consume(format!(
+            "while resolving {} (provided by {}) with {} resolved",
+            self.id, self.provider, resolved
+        ))
+    }
+}
+
+// keep a copy of a provider which may need to be recorded on the gradient tape of the txn
+#[cfg(feature = "tc-tensor")]
+fn tape_provider(provider: &State) -> Option<State> {
+    if tape::is_tape_op(provider) {
+        Some(provider.clone())
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "tc-tensor"))]
+fn tape_provider(_provider: &State) -> Option<State> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> Id {
+        name.parse().expect("id")
+    }
+
+    fn schedule(deps: &[(&str, &[&str])]) -> Schedule {
+        let deps = deps
+            .iter()
+            .map(|(name, requires)| (id(name), ids(requires)))
+            .collect();
+
+        Schedule::new(deps)
+    }
+
+    fn ids(names: &[&str]) -> HashSet<Id> {
+        names.iter().map(|name| id(name)).collect()
+    }
+
+    fn drain(schedule: &mut Schedule) -> HashSet<Id> {
+        std::iter::from_fn(|| schedule.next()).collect()
+    }
+
+    #[test]
+    fn test_independent_providers() {
+        // "b" and "c" both depend on "a", and "d" depends on both "b" and "c"
+        let mut schedule =
+            schedule(&[("a", &[]), ("b", &["a"]), ("c", &["a"]), ("d", &["b", "c"])]);
+
+        assert_eq!(drain(&mut schedule), ids(&["a"]));
+
+        schedule.resolved(&id("a"));
+        assert_eq!(
+            drain(&mut schedule),
+            ids(&["b", "c"]),
+            "independent providers should be ready to resolve concurrently"
+        );
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let mut schedule = schedule(&[
+            ("a", &[]),
+            ("b", &["a"]),
+            ("c", &["a"]),
+            ("d", &["b", "c"]),
+            ("e", &[]),
+        ]);
+
+        assert_eq!(drain(&mut schedule).len(), 2);
+
+        schedule.resolved(&id("a"));
+        assert_eq!(drain(&mut schedule).len(), 2);
+
+        schedule.resolved(&id("c"));
+        assert!(schedule.next().is_none(), "d must wait for b");
+
+        schedule.resolved(&id("e"));
+        assert!(schedule.next().is_none(), "d does not depend on e");
+
+        schedule.resolved(&id("b"));
+        assert_eq!(schedule.next(), Some(id("d")));
+        assert!(schedule.next().is_none());
+    }
+
+    #[test]
+    fn test_blocked() {
+        let schedule = schedule(&[("a", &["b"]), ("b", &["a"])]);
+        assert!(schedule.is_blocked());
     }
 }
@@ -2,6 +2,10 @@
 
 pub use def::*;
 pub use executor::*;
+pub use signature::*;
 
 mod def;
 mod executor;
+mod signature;
+#[cfg(feature = "tc-tensor")]
+mod tape;
@@ -0,0 +1,261 @@
+//! The optional [`Signature`] of a POST [`OpDef`](super::OpDef).
+//!
+//! A POST op can declare its parameters as the first assignment in its form, with the reserved ID
+//! [`SIGNATURE`] and a map of each parameter's name to its type, optionally followed by a default:
+//!
+//! ```json
+//! {"/state/scalar/op/post": [
+//!     ["_params", {"x": "/state/scalar/value/number/int", "y": ["/state/scalar/value/number/float", 1.0]}],
+//!     ["z", {"$x/add": ["$y"]}]
+//! ]}
+//! ```
+//!
+//! The params of a call are then validated against the signature before the op is executed: each
+//! declared parameter is cast into its type (without any loss of precision) or set to its default,
+//! and a missing or undeclared parameter is an error. An op with no signature accepts any params.
+
+use std::fmt;
+
+use log::debug;
+
+use tc_error::*;
+use tc_value::Value;
+use tcgeneric::{label, Id, Instance, Label, Map, NativeClass};
+
+use crate::scalar::{Scalar, ScalarType};
+use crate::state::{State, StateType};
+
+use super::PostOp;
+
+/// The reserved ID of the [`Signature`] of a POST op.
+pub const SIGNATURE: Label = label("_params");
+
+/// A named, typed parameter of a POST op, with an optional default.
+#[derive(Clone)]
+pub struct Param {
+    name: Id,
+    dtype: StateType,
+    default: Option<Scalar>,
+}
+
+impl Param {
+    /// The name of this parameter.
+    pub fn name(&self) -> &Id {
+        &self.name
+    }
+
+    /// The type of this parameter.
+    pub fn dtype(&self) -> StateType {
+        self.dtype
+    }
+
+    /// The default value of this parameter, if it's optional.
+    pub fn default(&self) -> Option<&Scalar> {
+        self.default.as_ref()
+    }
+
+    fn try_from_scalar(name: Id, spec: Scalar) -> TCResult<Self> {
+        let (dtype, default) = match spec {
+            Scalar::Tuple(spec) if spec.len() == 2 => {
+                let mut spec = spec.into_inner().into_iter();
+                let dtype = spec.next().expect("parameter type");
+                (dtype, spec.next())
+            }
+            Scalar::Tuple(spec) if spec.len() == 1 => {
+                (spec.into_inner().pop().expect("parameter type"), None)
+            }
+            dtype => (dtype, None),
+        };
+
+        let dtype = match dtype {
+            Scalar::Value(Value::Link(link)) if link.host().is_none() => link.path().clone(),
+            Scalar::Value(Value::String(path)) => path.as_str().parse()?,
+            other => {
+                return Err(TCError::bad_request(
+                    format!("invalid type of parameter {}", name),
+                    other,
+                ))
+            }
+        };
+
+        let dtype = StateType::from_path(&dtype).ok_or_else(|| {
+            TCError::bad_request(format!("unknown type of parameter {}", name), &dtype)
+        })?;
+
+        Ok(Self {
+            name,
+            dtype,
+            default,
+        })
+    }
+
+    fn validate(&self, state: State) -> TCResult<State> {
+        if state.is_ref() {
+            // the state will be resolved, and therefore checked, by the op itself
+            return Ok(state);
+        }
+
+        let err = |state: &State| {
+            TCError::bad_request(
+                format!(
+                    "expected {} for parameter {} but found",
+                    self.dtype, self.name
+                ),
+                state,
+            )
+        };
+
+        match self.dtype {
+            StateType::Scalar(ScalarType::Value(dtype)) => match state {
+                State::Scalar(Scalar::Value(value)) => dtype
+                    .try_cast_strict(value)
+                    .map(State::from)
+                    .map_err(|cause| cause.consume(format!("invalid parameter {}", self.name))),
+                other => Err(err(&other)),
+            },
+            StateType::Scalar(dtype) => match state {
+                State::Scalar(scalar) => {
+                    let desc = scalar.to_string();
+                    scalar.into_type(dtype).map(State::Scalar).ok_or_else(|| {
+                        TCError::bad_request(
+                            format!("expected {} for parameter {} but found", dtype, self.name),
+                            desc,
+                        )
+                    })
+                }
+                other => Err(err(&other)),
+            },
+            StateType::Map => match state {
+                State::Map(_) | State::Scalar(Scalar::Map(_)) => Ok(state),
+                other => Err(err(&other)),
+            },
+            StateType::Tuple => match state {
+                State::Tuple(_) | State::Scalar(Scalar::Tuple(_)) => Ok(state),
+                other => Err(err(&other)),
+            },
+            dtype if state.class().path().starts_with(&dtype.path()) => Ok(state),
+            _ => Err(err(&state)),
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(default) = &self.default {
+            write!(f, "{}: {} = {}", self.name, self.dtype, default)
+        } else {
+            write!(f, "{}: {}", self.name, self.dtype)
+        }
+    }
+}
+
+/// The declared parameters of a POST op.
+#[derive(Clone)]
+pub struct Signature {
+    params: Vec<Param>,
+}
+
+impl Signature {
+    /// Return the [`Signature`] declared by the given POST op `form`, if any.
+    pub fn of(form: &[(Id, Scalar)]) -> TCResult<Option<Self>> {
+        match form.first() {
+            Some((id, spec)) if id == &SIGNATURE => Self::try_from_scalar(spec.clone()).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Split the [`Signature`], if any, from the given POST op `form`.
+    pub fn split(mut form: PostOp) -> TCResult<(Option<Self>, PostOp)> {
+        match form.first() {
+            Some((id, _)) if id == &SIGNATURE => {
+                let (_, spec) = form.remove(0);
+                Self::try_from_scalar(spec).map(|signature| (Some(signature), form))
+            }
+            _ => Ok((None, form)),
+        }
+    }
+
+    /// The declared parameters of this signature.
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    /// Validate the given `params` of a call, casting each into its declared type
+    /// and filling in the default of each optional parameter which is missing.
+    pub fn destructure(&self, mut params: Map<State>) -> TCResult<Map<State>> {
+        let mut destructured = Map::new();
+
+        for param in &self.params {
+            let state = if let Some(state) = params.remove(&param.name) {
+                state
+            } else if let Some(default) = &param.default {
+                State::Scalar(default.clone())
+            } else {
+                return Err(TCError::bad_request("missing required parameter", param));
+            };
+
+            let state = param.validate(state)?;
+            destructured.insert(param.name.clone(), state);
+        }
+
+        if let Some(name) = params.keys().next() {
+            return Err(TCError::bad_request(
+                format!("this op takes the parameters {}, not", self),
+                name,
+            ));
+        }
+
+        debug!("destructured params {} of {}", destructured, self);
+
+        Ok(destructured)
+    }
+
+    fn try_from_scalar(spec: Scalar) -> TCResult<Self> {
+        let spec = match spec {
+            Scalar::Map(spec) => spec,
+            other => {
+                return Err(TCError::bad_request(
+                    "expected a map of parameter names to types but found",
+                    other,
+                ))
+            }
+        };
+
+        let params = spec
+            .into_iter()
+            .map(|(name, spec)| Param::try_from_scalar(name, spec))
+            .collect::<TCResult<Vec<Param>>>()?;
+
+        Ok(Self { params })
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("(")?;
+
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+
+            fmt::Display::fmt(param, f)?;
+        }
+
+        f.write_str(")")
+    }
+}
+
+/// Validate the given `params` against the signature of the given POST op `form`, if any,
+/// and return the validated params and the remaining form.
+pub fn destructure_params(form: PostOp, params: Map<State>) -> TCResult<(Map<State>, PostOp)> {
+    let (signature, form) = Signature::split(form)?;
+
+    let params = if let Some(signature) = signature {
+        signature.destructure(params)?
+    } else {
+        params
+    };
+
+    Ok((params, form))
+}
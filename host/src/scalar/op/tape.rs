@@ -0,0 +1,193 @@
+//! Record the differentiable `Tensor` ops resolved by an [`Executor`](super::Executor) on the
+//! gradient tape of its [`Txn`].
+//!
+//! Only an op of the form `$x/add(r=$y)` or `$x/sum(axis)` is recognized, i.e. a method of a
+//! `Tensor` in the same context whose argument is another state in the context or a constant.
+//! Each is recorded under the name of the state which it resolves, so that a loss can later be
+//! differentiated by name.
+
+use log::debug;
+use safecast::CastFrom;
+
+use tc_error::*;
+use tc_tensor::{Operand, Shape, TapeOp, TensorAccess};
+use tc_value::{Number, NumberInstance, Value};
+use tcgeneric::{label, path_label, Id, Instance, PathLabel, TCPathBuf};
+
+use crate::collection::{Collection, Tensor};
+use crate::fs;
+use crate::route::Public;
+use crate::scalar::{IdRef, OpRef, Scalar, Scope, Subject, TCRef};
+use crate::state::{State, ToState};
+use crate::txn::Txn;
+
+/// The path of the op which watches a `Tensor` on the gradient tape of a transaction.
+pub const WATCH: PathLabel = path_label(&["state", "collection", "tensor", "watch"]);
+
+type TensorOperand =
+    Operand<fs::File<tc_tensor::Array>, fs::File<tc_btree::Node>, fs::Dir, Txn>;
+
+/// Return `true` if resolving the given `provider` might need to be recorded on a gradient tape.
+pub fn is_tape_op(provider: &State) -> bool {
+    match op_ref(provider) {
+        Some(OpRef::Post((Subject::Link(link), _))) => link.path() == &TCPathBuf::from(WATCH),
+        Some(OpRef::Get((Subject::Ref(_, path), _)))
+        | Some(OpRef::Post((Subject::Ref(_, path), _))) => {
+            path.len() == 1 && tape_op(path[0].as_str()).is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Record the resolution of `provider`, named `id`, to `state` on the gradient tape of `txn`.
+///
+/// This is a no-op if `txn` is not recording, or if `provider` is not a differentiable op of a
+/// tracked `Tensor`.
+pub fn record<'a, T: ToState + Instance + Public>(
+    txn: &Txn,
+    scope: &Scope<'a, T>,
+    id: &Id,
+    provider: &State,
+    state: &State,
+) -> TCResult<()> {
+    let tape = if let Some(tape) = txn.tape() {
+        tape
+    } else {
+        return Ok(());
+    };
+
+    let (shape, dtype) = match state {
+        State::Collection(Collection::Tensor(tensor)) => (tensor.shape().clone(), tensor.dtype()),
+        State::Scalar(Scalar::Value(Value::Number(n))) => (Shape::from(vec![1]), n.class()),
+        _ => return Ok(()),
+    };
+
+    let (subject, op, operands) = match op_ref(provider) {
+        Some(OpRef::Post((Subject::Link(link), _))) if link.path() == &TCPathBuf::from(WATCH) => {
+            if let State::Collection(Collection::Tensor(tensor)) = state {
+                tape.watch(id.clone(), tensor);
+            }
+
+            return Ok(());
+        }
+        Some(OpRef::Get((Subject::Ref(subject, path), key))) if path.len() == 1 => {
+            let subject = match tensor(scope, subject)? {
+                Some(subject) => subject,
+                None => return Ok(()),
+            };
+
+            let op = match (tape_op(path[0].as_str()), key) {
+                (Some(TapeOp::Sum(_)), Scalar::Value(Value::None)) => TapeOp::Sum(None),
+                (Some(TapeOp::Sum(_)), Scalar::Value(Value::Number(axis))) => {
+                    match cast_axis(*axis, subject.1.ndim()) {
+                        Some(axis) => TapeOp::Sum(Some(axis)),
+                        None => return Ok(()),
+                    }
+                }
+                (Some(TapeOp::Exp), Scalar::Value(Value::None)) => TapeOp::Exp,
+                _ => return Ok(()),
+            };
+
+            (subject, op, vec![])
+        }
+        Some(OpRef::Post((Subject::Ref(subject, path), params))) if path.len() == 1 => {
+            let subject = match tensor(scope, subject)? {
+                Some(subject) => subject,
+                None => return Ok(()),
+            };
+
+            let op = match tape_op(path[0].as_str()) {
+                Some(TapeOp::Exp) | Some(TapeOp::Sum(_)) | None => return Ok(()),
+                Some(op) => op,
+            };
+
+            let r: Id = label("r").into();
+            let r = match params.get(&r) {
+                Some(r) => r,
+                None => return Ok(()),
+            };
+
+            match operand(scope, r)? {
+                Some(r) => (subject, op, vec![r]),
+                None => return Ok(()),
+            }
+        }
+        _ => return Ok(()),
+    };
+
+    let (subject_id, subject) = subject;
+    let mut all_operands = Vec::with_capacity(operands.len() + 1);
+    all_operands.push(Operand::Tensor(subject_id, subject));
+    all_operands.extend(operands);
+
+    if tape.record(id.clone(), op, all_operands, shape, dtype)? {
+        debug!("recorded {} of {} on the gradient tape", op, id);
+    }
+
+    Ok(())
+}
+
+fn op_ref(provider: &State) -> Option<&OpRef> {
+    match provider {
+        State::Scalar(Scalar::Ref(tc_ref)) => match &**tc_ref {
+            TCRef::Op(op_ref) => Some(op_ref),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn tape_op(method: &str) -> Option<TapeOp> {
+    match method {
+        "add" => Some(TapeOp::Add),
+        "div" => Some(TapeOp::Div),
+        "exp" => Some(TapeOp::Exp),
+        "mul" => Some(TapeOp::Mul),
+        "sub" => Some(TapeOp::Sub),
+        "sum" => Some(TapeOp::Sum(None)),
+        _ => None,
+    }
+}
+
+fn cast_axis(axis: Number, ndim: usize) -> Option<usize> {
+    let axis = i64::cast_from(axis);
+    if axis >= ndim as i64 || axis < -(ndim as i64) {
+        None
+    } else if axis >= 0 {
+        Some(axis as usize)
+    } else {
+        Some(ndim - (-axis) as usize)
+    }
+}
+
+// the tensor named by the given `id_ref` in the given `scope`, if it is a tensor
+fn tensor<'a, T: ToState + Instance + Public>(
+    scope: &Scope<'a, T>,
+    id_ref: &IdRef,
+) -> TCResult<Option<(Id, Tensor)>> {
+    match scope.resolve_id(id_ref.id())? {
+        State::Collection(Collection::Tensor(tensor)) => Ok(Some((id_ref.id().clone(), tensor))),
+        _ => Ok(None),
+    }
+}
+
+// the operand given by the `scalar` argument of an op, if it is a tensor or a constant number
+fn operand<'a, T: ToState + Instance + Public>(
+    scope: &Scope<'a, T>,
+    scalar: &Scalar,
+) -> TCResult<Option<TensorOperand>> {
+    match scalar {
+        Scalar::Value(Value::Number(n)) => Ok(Some(Operand::Const(*n))),
+        Scalar::Ref(tc_ref) => match &**tc_ref {
+            TCRef::Id(id_ref) => match scope.resolve_id(id_ref.id())? {
+                State::Collection(Collection::Tensor(tensor)) => {
+                    Ok(Some(Operand::Tensor(id_ref.id().clone(), tensor)))
+                }
+                State::Scalar(Scalar::Value(Value::Number(n))) => Ok(Some(Operand::Const(n))),
+                _ => Ok(None),
+            },
+            _ => Ok(None),
+        },
+        _ => Ok(None),
+    }
+}
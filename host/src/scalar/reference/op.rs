@@ -305,6 +305,18 @@ pub enum OpRef {
     Delete(DeleteRef),
 }
 
+impl OpRef {
+    /// The [`Subject`] of this op, i.e. the link or reference to its provider.
+    pub fn subject(&self) -> &Subject {
+        match self {
+            Self::Get((subject, _)) => subject,
+            Self::Put((subject, _, _)) => subject,
+            Self::Post((subject, _)) => subject,
+            Self::Delete((subject, _)) => subject,
+        }
+    }
+}
+
 impl Instance for OpRef {
     type Class = OpRefType;
 
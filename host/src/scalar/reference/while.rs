@@ -7,7 +7,7 @@ use async_trait::async_trait;
 use destream::{de, en};
 use futures::try_join;
 use log::debug;
-use safecast::{Match, TryCastFrom, TryCastInto};
+use safecast::{CastFrom, Match, TryCastFrom, TryCastInto};
 
 use tc_error::*;
 use tcgeneric::{Id, Instance, PathSegment, TCPathBuf};
@@ -20,12 +20,16 @@ use crate::txn::Txn;
 
 use super::Refer;
 
+/// The maximum number of iterations of a [`While`] loop which does not specify its own limit.
+pub const DEFAULT_LIMIT: u64 = 100_000;
+
 /// A while loop.
 #[derive(Clone, Eq, PartialEq)]
 pub struct While {
     cond: Scalar,
     closure: Scalar,
     state: Scalar,
+    limit: Scalar,
 }
 
 #[async_trait]
@@ -35,6 +39,7 @@ impl Refer for While {
             cond: self.cond.dereference_self(path),
             closure: self.closure.dereference_self(path),
             state: self.state.dereference_self(path),
+            limit: self.limit.dereference_self(path),
         }
     }
 
@@ -46,6 +51,7 @@ impl Refer for While {
         self.cond.is_inter_service_write(cluster_path)
             || self.closure.is_inter_service_write(cluster_path)
             || self.state.is_inter_service_write(cluster_path)
+            || self.limit.is_inter_service_write(cluster_path)
     }
 
     fn reference_self(self, path: &TCPathBuf) -> Self {
@@ -53,6 +59,7 @@ impl Refer for While {
             cond: self.cond.reference_self(path),
             closure: self.closure.reference_self(path),
             state: self.state.reference_self(path),
+            limit: self.limit.reference_self(path),
         }
     }
 
@@ -60,6 +67,7 @@ impl Refer for While {
         self.cond.requires(deps);
         self.closure.requires(deps);
         self.state.requires(deps);
+        self.limit.requires(deps);
     }
 
     async fn resolve<'a, T: ToState + Instance + Public>(
@@ -79,14 +87,37 @@ impl Refer for While {
                 "While does not allow nested conditional",
                 self.state,
             ));
+        } else if self.limit.is_conditional() {
+            return Err(TCError::bad_request(
+                "While does not allow nested conditional",
+                self.limit,
+            ));
         }
 
-        let (cond, closure, mut state) = try_join!(
+        let (cond, closure, mut state, limit) = try_join!(
             self.cond.resolve(context, txn),
             self.closure.resolve(context, txn),
-            self.state.resolve(context, txn)
+            self.state.resolve(context, txn),
+            self.limit.resolve(context, txn)
         )?;
 
+        let limit = if limit.is_none() {
+            DEFAULT_LIMIT
+        } else {
+            let limit = Number::try_cast_from(limit, |s| {
+                TCError::bad_request("invalid iteration limit for While loop", s)
+            })?;
+
+            if limit < Number::from(1u64) {
+                return Err(TCError::bad_request(
+                    "While loop iteration limit must be positive, not",
+                    limit,
+                ));
+            }
+
+            u64::cast_from(limit)
+        };
+
         let cond = Closure::try_cast_from(cond, |s| {
             TCError::bad_request("while loop condition should be an Op or Closure, found", s)
         })?;
@@ -95,12 +126,21 @@ impl Refer for While {
             TCError::bad_request("while loop requires an Op or Closure, found", s)
         })?;
 
+        let mut iterations = 0u64;
         loop {
             let still_going = cond.clone().call(txn, state.clone()).await?;
 
             debug!("While condition is {}", cond);
             if let State::Scalar(Scalar::Value(Value::Number(Number::Bool(b)))) = still_going {
                 if b.into() {
+                    if iterations == limit {
+                        break Err(TCError::bad_request(
+                            "While loop exceeded its maximum number of iterations",
+                            limit,
+                        ));
+                    }
+
+                    iterations += 1;
                     state = closure.clone().call(txn, state).await?;
 
                     if state.is_conditional() {
@@ -127,6 +167,7 @@ impl Refer for While {
 impl TryCastFrom<Scalar> for While {
     fn can_cast_from(scalar: &Scalar) -> bool {
         scalar.matches::<(Scalar, Scalar, Scalar)>()
+            || scalar.matches::<(Scalar, Scalar, Scalar, Scalar)>()
     }
 
     fn opt_cast_from(scalar: Scalar) -> Option<Self> {
@@ -135,7 +176,17 @@ impl TryCastFrom<Scalar> for While {
                 cond,
                 closure,
                 state,
+                limit: Scalar::Value(Value::None),
             })
+        } else if scalar.matches::<(Scalar, Scalar, Scalar, Scalar)>() {
+            scalar
+                .opt_cast_into()
+                .map(|(cond, closure, state, limit)| Self {
+                    cond,
+                    closure,
+                    state,
+                    limit,
+                })
         } else {
             None
         }
@@ -154,13 +205,22 @@ impl de::FromStream for While {
 
 impl<'en> en::IntoStream<'en> for While {
     fn into_stream<E: en::Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
-        (self.cond, self.closure, self.state).into_stream(encoder)
+        if self.limit.is_none() {
+            (self.cond, self.closure, self.state).into_stream(encoder)
+        } else {
+            (self.cond, self.closure, self.state, self.limit).into_stream(encoder)
+        }
     }
 }
 
 impl<'en> en::ToStream<'en> for While {
     fn to_stream<E: en::Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
-        en::IntoStream::into_stream((&self.cond, &self.closure, &self.state), encoder)
+        if self.limit.is_none() {
+            en::IntoStream::into_stream((&self.cond, &self.closure, &self.state), encoder)
+        } else {
+            let limit = &self.limit;
+            en::IntoStream::into_stream((&self.cond, &self.closure, &self.state, limit), encoder)
+        }
     }
 }
 
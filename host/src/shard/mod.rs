@@ -0,0 +1,10 @@
+//! Partition a [`Collection`](crate::collection::Collection) across the replicas of a
+//! [`Cluster`](crate::cluster::Cluster), so that its size is not limited by a single host.
+
+mod table;
+#[cfg(feature = "tc-tensor")]
+mod tensor;
+
+pub use table::{Partition, ShardedTable};
+#[cfg(feature = "tc-tensor")]
+pub use tensor::{ShardMap, ShardedTensor};
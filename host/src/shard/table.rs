@@ -0,0 +1,325 @@
+//! Partition a `Table` by ranges of its leading primary key column across the replicas of a
+//! cluster.
+//!
+//! Each [`Partition`] of a [`ShardedTable`] is a `Table` with the same schema, hosted by a single
+//! replica, which holds every row whose leading key column falls within its range. Point reads and
+//! writes go only to the partition which owns the key. A scan is sent only to the partitions which
+//! overlap its bounds on the leading key column, in key order, and their rows are concatenated.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use collate::Collate;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use log::debug;
+use safecast::{TryCastFrom, TryCastInto};
+
+use tc_error::*;
+use tc_table::{Bounds, ColumnBound, IndexSchema, Key, Values};
+use tc_value::{Bound, Link, Range, Value, ValueCollator, EX, IN};
+use tcgeneric::{label, Id, Label, Map, TCBoxTryStream};
+
+use crate::state::State;
+use crate::txn::Txn;
+
+const ROWS: Label = label("rows");
+
+/// A range of the leading primary key column of a [`ShardedTable`], hosted by a single replica.
+#[derive(Clone)]
+pub struct Partition {
+    /// The inclusive lower bound of this partition, or `Value::None` for the first partition.
+    pub start: Value,
+
+    /// The link to the `Table` which holds the rows of this partition.
+    pub replica: Link,
+}
+
+impl fmt::Display for Partition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start.is_none() {
+            write!(f, "partition at {}", self.replica)
+        } else {
+            write!(f, "partition from {} at {}", self.start, self.replica)
+        }
+    }
+}
+
+/// A `Table` whose rows are partitioned by primary key range across the replicas of a cluster.
+pub struct ShardedTable {
+    schema: IndexSchema,
+    partitions: Vec<Partition>,
+    collator: ValueCollator,
+}
+
+impl ShardedTable {
+    /// Construct a new `ShardedTable` with the given `schema` and `partitions`, which must be
+    /// listed in order of their `start` key.
+    pub fn new(schema: IndexSchema, partitions: Vec<Partition>) -> TCResult<Self> {
+        if schema.key().is_empty() {
+            return Err(TCError::unsupported(
+                "cannot partition a Table with no primary key",
+            ));
+        }
+
+        match partitions.first() {
+            None => {
+                return Err(TCError::bad_request(
+                    "a ShardedTable requires at least one partition, not",
+                    0,
+                ))
+            }
+            Some(first) if first.start.is_some() => {
+                return Err(TCError::bad_request(
+                    "the first partition of a ShardedTable must be unbounded, not",
+                    first,
+                ))
+            }
+            Some(_) => {}
+        }
+
+        let collator = ValueCollator::default();
+        for pair in partitions[1..].windows(2) {
+            if collator.compare(&pair[0].start, &pair[1].start) != Ordering::Less {
+                return Err(TCError::bad_request(
+                    "ShardedTable partitions must be in ascending key order, but found",
+                    &pair[1],
+                ));
+            }
+        }
+
+        if partitions[1..]
+            .iter()
+            .any(|partition| partition.start.is_none())
+        {
+            return Err(TCError::bad_request(
+                "only the first partition of a ShardedTable may be unbounded",
+                partitions.len(),
+            ));
+        }
+
+        Ok(Self {
+            schema,
+            partitions,
+            collator,
+        })
+    }
+
+    /// The schema of each partition of this `ShardedTable`.
+    pub fn schema(&self) -> &IndexSchema {
+        &self.schema
+    }
+
+    /// The partitions of this `ShardedTable`, in key order.
+    pub fn partitions(&self) -> &[Partition] {
+        &self.partitions
+    }
+
+    /// Read the values of the row with the given `key`, if present.
+    pub async fn read(&self, txn: &Txn, key: Key) -> TCResult<Option<Values>> {
+        let key = self.schema.validate_key(key)?;
+        let owner = self.owner(&key[0]);
+
+        let row = txn
+            .get(owner.replica.clone(), key.into_iter().collect())
+            .await?;
+        let row = Value::try_cast_from(row, |s| TCError::bad_request("invalid row", s))?;
+
+        if row.is_none() {
+            Ok(None)
+        } else {
+            row.try_cast_into(|v| TCError::bad_request("invalid row", v))
+                .map(Some)
+        }
+    }
+
+    /// Insert or update the row with the given `key` in the partition which owns it.
+    pub async fn upsert(&self, txn: &Txn, key: Key, values: Values) -> TCResult<()> {
+        let key = self.schema.validate_key(key)?;
+        let owner = self.owner(&key[0]);
+
+        debug!("upsert {:?} into {}", key, owner);
+
+        let values = State::from(values.into_iter().collect::<Value>());
+        txn.put(owner.replica.clone(), key.into_iter().collect(), values)
+            .await
+    }
+
+    /// Delete the row with the given `key` from the partition which owns it.
+    pub async fn delete(&self, txn: &Txn, key: Key) -> TCResult<()> {
+        let key = self.schema.validate_key(key)?;
+        let owner = self.owner(&key[0]);
+
+        debug!("delete {:?} from {}", key, owner);
+
+        txn.delete(owner.replica.clone(), key.into_iter().collect())
+            .await
+    }
+
+    /// Stream the rows within the given `bounds` from each partition which overlaps them.
+    pub fn rows<'a>(
+        &'a self,
+        txn: &'a Txn,
+        bounds: Bounds,
+    ) -> TCResult<TCBoxTryStream<'a, Values>> {
+        let bounds = bounds.validate(&self.schema.columns())?;
+        let leading = self.schema.key()[0].name();
+
+        let partitions = match bounds.get(leading) {
+            None => self.partitions.iter().collect(),
+            Some(ColumnBound::Is(key)) => vec![self.owner(key)],
+            Some(ColumnBound::In(range)) => (0..self.partitions.len())
+                .filter(|i| self.overlaps(*i, range))
+                .map(|i| &self.partitions[i])
+                .collect::<Vec<&Partition>>(),
+        };
+
+        debug!(
+            "scan {} of {} partitions",
+            partitions.len(),
+            self.partitions.len()
+        );
+
+        let params = bounds
+            .iter()
+            .map(|(name, bound)| (name.clone(), State::from(bound_value(bound.clone()))))
+            .collect::<Map<State>>();
+
+        let rows = stream::iter(partitions)
+            .then(move |partition| {
+                let link = partition.replica.clone().append(ROWS.into());
+                txn.post(link, State::Map(params.clone()))
+            })
+            .map(|rows| {
+                let rows = Value::try_cast_from(rows?, |s| {
+                    TCError::bad_request("invalid rows from Table partition", s)
+                })?;
+
+                let rows: Vec<Values> =
+                    rows.try_cast_into(|v| TCError::bad_request("invalid Table rows", v))?;
+
+                TCResult::Ok(stream::iter(rows.into_iter().map(Ok)))
+            })
+            .try_flatten();
+
+        Ok(Box::pin(rows))
+    }
+
+    /// Return the [`Partition`] which owns the given value of the leading key column.
+    fn owner(&self, key: &Value) -> &Partition {
+        let owner = self.partitions[1..]
+            .iter()
+            .rposition(|partition| {
+                self.collator.compare(&partition.start, key) != Ordering::Greater
+            })
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        &self.partitions[owner]
+    }
+
+    /// Return `true` if the partition at index `i` may hold any key in the given `range`.
+    fn overlaps(&self, i: usize, range: &Range) -> bool {
+        use Ordering::*;
+
+        if i > 0 {
+            let start = &self.partitions[i].start;
+            let before = match &range.end {
+                Bound::Un => false,
+                Bound::In(end) => self.collator.compare(end, start) == Less,
+                Bound::Ex(end) => self.collator.compare(end, start) != Greater,
+            };
+
+            if before {
+                return false;
+            }
+        }
+
+        if let Some(next) = self.partitions.get(i + 1) {
+            let after = match &range.start {
+                Bound::Un => false,
+                Bound::In(start) | Bound::Ex(start) => {
+                    self.collator.compare(start, &next.start) != Less
+                }
+            };
+
+            if after {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Encode a [`ColumnBound`] as a [`Value`] which can be sent to another host.
+fn bound_value(bound: ColumnBound) -> Value {
+    match bound {
+        ColumnBound::Is(value) => value,
+        ColumnBound::In(Range { start, end }) => {
+            let start = match start {
+                Bound::In(value) => (Id::from(IN), value),
+                Bound::Ex(value) => (Id::from(EX), value),
+                Bound::Un => (Id::from(IN), Value::None),
+            };
+
+            let end = match end {
+                Bound::In(value) => (Id::from(IN), value),
+                Bound::Ex(value) => (Id::from(EX), value),
+                Bound::Un => (Id::from(EX), Value::None),
+            };
+
+            Value::Tuple(
+                vec![
+                    Value::Tuple(vec![start.0.into(), start.1].into()),
+                    Value::Tuple(vec![end.0.into(), end.1].into()),
+                ]
+                .into(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tc_btree::Column;
+    use tc_value::{NumberType, UIntType};
+
+    use super::*;
+
+    fn partition(start: Value, port: u16) -> Partition {
+        let replica = format!("http://127.0.0.1:{}/app/table", port);
+        let replica = replica.parse().expect("link");
+        Partition { start, replica }
+    }
+
+    #[test]
+    fn test_partition() {
+        let key = vec![Column::from((label("id"), NumberType::UInt(UIntType::U64)))];
+        let schema = IndexSchema::from((key, vec![]));
+
+        let table = ShardedTable::new(
+            schema,
+            vec![
+                partition(Value::None, 8702),
+                partition(Value::from(10u64), 8703),
+                partition(Value::from(20u64), 8704),
+            ],
+        )
+        .expect("sharded table");
+
+        let owner = |key: u64| table.owner(&Value::from(key)).replica.to_string();
+        assert_eq!(owner(0), "http://127.0.0.1:8702/app/table");
+        assert_eq!(owner(10), "http://127.0.0.1:8703/app/table");
+        assert_eq!(owner(25), "http://127.0.0.1:8704/app/table");
+
+        let range = Range {
+            start: Bound::In(Value::from(12u64)),
+            end: Bound::Ex(Value::from(20u64)),
+        };
+
+        let overlapping = (0..3)
+            .filter(|i| table.overlaps(*i, &range))
+            .collect::<Vec<_>>();
+        assert_eq!(overlapping, vec![1]);
+    }
+}
@@ -0,0 +1,369 @@
+//! Partition a dense [`Tensor`](crate::collection::Tensor) along its leading axis across the
+//! replicas of a [`Cluster`](crate::cluster::Cluster).
+//!
+//! A [`ShardMap`] divides the leading axis of a tensor into blocks of a fixed number of rows and
+//! assigns each block to a replica by consistent hashing, so that adding or removing a replica
+//! only moves the blocks which hash to it. Each replica stores the blocks assigned to it, in
+//! order, as a single dense tensor with the shape given by [`ShardMap::shard_shape`].
+//!
+//! A [`ShardedTensor`] routes each read and write of the logical tensor through the gateway to the
+//! replica which owns the affected rows.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Range;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use log::debug;
+use safecast::TryCastFrom;
+use sha2::{Digest, Sha256};
+
+use tc_error::*;
+use tc_tensor::{AxisBounds, Bounds, Coord, Shape, TensorAccess, TensorIO};
+use tc_transact::{Transaction, TxnId};
+use tc_value::{Link, Number, NumberType, Value};
+
+use crate::fs;
+use crate::state::State;
+use crate::txn::Txn;
+
+/// The number of points each replica occupies on the hash ring.
+const VNODES: u64 = 64;
+
+struct Shard {
+    replica: Link,
+    rows: Range<u64>,
+    offset: u64,
+}
+
+/// The assignment of each block of rows of a tensor to a replica.
+pub struct ShardMap {
+    shape: Shape,
+    block_rows: u64,
+    shards: Vec<Shard>,
+}
+
+impl ShardMap {
+    /// Assign each block of `block_rows` rows of a tensor with the given `shape` to one of the
+    /// given `replicas`.
+    pub fn new<I: IntoIterator<Item = Link>>(
+        replicas: I,
+        shape: Shape,
+        block_rows: u64,
+    ) -> TCResult<Self> {
+        if shape.is_empty() {
+            return Err(TCError::unsupported("cannot shard a Tensor with no axes"));
+        } else if block_rows == 0 {
+            return Err(TCError::bad_request(
+                "a shard must contain at least one row, not",
+                block_rows,
+            ));
+        }
+
+        let mut ring = Vec::new();
+        for replica in replicas {
+            for vnode in 0..VNODES {
+                ring.push((hash(&format!("{}#{}", replica, vnode)), replica.clone()));
+            }
+        }
+
+        if ring.is_empty() {
+            return Err(TCError::bad_request(
+                "cannot shard a Tensor across zero replicas",
+                shape,
+            ));
+        }
+
+        ring.sort_by(|(l, _), (r, _)| l.cmp(r));
+        ring.dedup_by(|(l, _), (r, _)| l == r);
+
+        let mut offsets = HashMap::<Link, u64>::new();
+        let mut shards = Vec::new();
+        let mut start = 0;
+        while start < shape[0] {
+            let end = Ord::min(start + block_rows, shape[0]);
+
+            let point = hash(&format!("block#{}", shards.len()));
+            let owner = ring
+                .iter()
+                .find(|(vnode, _)| *vnode >= point)
+                .unwrap_or(&ring[0]);
+
+            let replica = owner.1.clone();
+            let offset = offsets.entry(replica.clone()).or_insert(0);
+            shards.push(Shard {
+                replica,
+                rows: start..end,
+                offset: *offset,
+            });
+
+            *offset += end - start;
+            start = end;
+        }
+
+        debug!(
+            "split Tensor with shape {} into {} shards",
+            shape,
+            shards.len()
+        );
+
+        Ok(Self {
+            shape,
+            block_rows,
+            shards,
+        })
+    }
+
+    /// The shape of the logical tensor.
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    /// The shape of the dense tensor which the given `replica` must store.
+    pub fn shard_shape(&self, replica: &Link) -> Shape {
+        let rows = self
+            .shards
+            .iter()
+            .filter(|shard| &shard.replica == replica)
+            .map(|shard| shard.rows.end - shard.rows.start)
+            .sum();
+
+        let mut shape = self.shape.to_vec();
+        shape[0] = rows;
+        shape.into()
+    }
+
+    /// Return the replica which owns the given `row`, and the index of that row in its shard.
+    fn locate(&self, row: u64) -> (&Link, u64) {
+        let shard = &self.shards[(row / self.block_rows) as usize];
+        (&shard.replica, shard.offset + (row - shard.rows.start))
+    }
+
+    /// Split the given bounds of the leading axis into the bounds of each affected shard.
+    fn split(&self, bounds: &AxisBounds) -> Vec<(&Link, AxisBounds)> {
+        match bounds {
+            AxisBounds::At(row) => {
+                let (replica, row) = self.locate(*row);
+                vec![(replica, AxisBounds::At(row))]
+            }
+            AxisBounds::In(rows) => self
+                .shards
+                .iter()
+                .filter(|shard| shard.rows.start < rows.end && rows.start < shard.rows.end)
+                .map(|shard| {
+                    let start = Ord::max(rows.start, shard.rows.start) - shard.rows.start;
+                    let end = Ord::min(rows.end, shard.rows.end) - shard.rows.start;
+                    let local = (shard.offset + start)..(shard.offset + end);
+                    (&shard.replica, AxisBounds::In(local))
+                })
+                .collect(),
+            AxisBounds::Of(rows) => rows
+                .iter()
+                .map(|row| {
+                    let (replica, row) = self.locate(*row);
+                    (replica, AxisBounds::At(row))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A dense tensor whose rows are partitioned across the replicas of a cluster.
+///
+/// A `ShardedTensor` issues its writes within the [`Txn`] which constructed it.
+#[derive(Clone)]
+pub struct ShardedTensor {
+    txn: Txn,
+    dtype: NumberType,
+    map: Arc<ShardMap>,
+}
+
+impl ShardedTensor {
+    /// Construct a new `ShardedTensor` with the given `dtype` and [`ShardMap`].
+    pub fn new(txn: Txn, dtype: NumberType, map: ShardMap) -> Self {
+        Self {
+            txn,
+            dtype,
+            map: Arc::new(map),
+        }
+    }
+
+    /// The [`ShardMap`] of this `ShardedTensor`.
+    pub fn shards(&self) -> &ShardMap {
+        &self.map
+    }
+
+    fn txn(&self, txn_id: TxnId) -> TCResult<&Txn> {
+        if &txn_id == self.txn.id() {
+            Ok(&self.txn)
+        } else {
+            Err(TCError::bad_request(
+                "a ShardedTensor cannot be written outside the transaction which constructed it",
+                txn_id,
+            ))
+        }
+    }
+}
+
+impl TensorAccess for ShardedTensor {
+    fn dtype(&self) -> NumberType {
+        self.dtype
+    }
+
+    fn ndim(&self) -> usize {
+        self.map.shape.len()
+    }
+
+    fn shape(&'_ self) -> &'_ Shape {
+        &self.map.shape
+    }
+
+    fn size(&self) -> u64 {
+        self.map.shape.size()
+    }
+}
+
+#[async_trait]
+impl TensorIO<fs::Dir> for ShardedTensor {
+    type Txn = Txn;
+
+    async fn read_value(self, txn: Self::Txn, coord: Coord) -> TCResult<Number> {
+        self.map.shape.validate_coord(&coord)?;
+
+        let (replica, row) = self.map.locate(coord[0]);
+        let key = coord_value(row, &coord[1..]);
+
+        let value = txn.get(replica.clone(), key).await?;
+        Number::try_cast_from(value, |v| {
+            TCError::bad_request("expected a tensor element but found", v)
+        })
+    }
+
+    async fn write_value(&self, txn_id: TxnId, bounds: Bounds, value: Number) -> TCResult<()> {
+        let txn = self.txn(txn_id)?;
+        self.map.shape.validate_bounds(&bounds)?;
+
+        let mut bounds = bounds;
+        bounds.normalize(&self.map.shape);
+
+        let trailing = &bounds[1..];
+        if trailing
+            .iter()
+            .any(|axis| matches!(axis, AxisBounds::Of(_)))
+        {
+            // a list of indices is not distinguishable from a range when encoded as a key
+            let writes = bounds
+                .affected()
+                .map(|coord| self.write_value_at(txn_id, coord, value));
+
+            try_join_all(writes).await?;
+            return Ok(());
+        }
+
+        let writes = self
+            .map
+            .split(&bounds[0])
+            .into_iter()
+            .map(|(replica, rows)| {
+                let key = std::iter::once(rows)
+                    .chain(trailing.iter().cloned())
+                    .map(bound_value)
+                    .collect();
+
+                txn.put(replica.clone(), key, State::from(Value::from(value)))
+            });
+
+        try_join_all(writes).await?;
+        Ok(())
+    }
+
+    async fn write_value_at(&self, txn_id: TxnId, coord: Coord, value: Number) -> TCResult<()> {
+        let txn = self.txn(txn_id)?;
+        self.map.shape.validate_coord(&coord)?;
+
+        let (replica, row) = self.map.locate(coord[0]);
+        let key = coord_value(row, &coord[1..]);
+
+        txn.put(replica.clone(), key, State::from(Value::from(value)))
+            .await
+    }
+}
+
+impl fmt::Display for ShardedTensor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a Tensor with shape {} sharded into {} blocks",
+            self.map.shape,
+            self.map.shards.len()
+        )
+    }
+}
+
+fn bound_value(bound: AxisBounds) -> Value {
+    match bound {
+        AxisBounds::At(i) => Value::from(i),
+        AxisBounds::In(range) => Value::from_iter(vec![range.start, range.end]),
+        AxisBounds::Of(indices) => Value::from_iter(indices),
+    }
+}
+
+fn coord_value(row: u64, trailing: &[u64]) -> Value {
+    std::iter::once(row)
+        .chain(trailing.iter().copied())
+        .collect()
+}
+
+fn hash(key: &str) -> u64 {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(s: &str) -> Link {
+        s.parse().expect("link")
+    }
+
+    #[test]
+    fn test_shard_map() {
+        let replicas = vec![
+            link("http://127.0.0.1:8702/app/tensor"),
+            link("http://127.0.0.1:8703/app/tensor"),
+            link("http://127.0.0.1:8704/app/tensor"),
+        ];
+
+        let shape = Shape::from(vec![100, 3]);
+        let map = ShardMap::new(replicas.clone(), shape, 8).expect("shard map");
+        assert_eq!(map.shards.len(), 13);
+
+        let rows: u64 = replicas
+            .iter()
+            .map(|replica| map.shard_shape(replica)[0])
+            .sum();
+
+        assert_eq!(rows, 100);
+
+        let split = map.split(&AxisBounds::In(0..100));
+        assert_eq!(split.len(), 13);
+        assert_eq!(
+            split.iter().map(|(_, bounds)| bounds.dim()).sum::<u64>(),
+            100
+        );
+
+        // removing a replica only moves the blocks which it owned
+        let fewer = ShardMap::new(replicas[..2].to_vec(), map.shape.clone(), 8).expect("shard map");
+        for (before, after) in map.shards.iter().zip(&fewer.shards) {
+            if before.replica != replicas[2] {
+                assert_eq!(before.replica, after.replica);
+            }
+        }
+    }
+}
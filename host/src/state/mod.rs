@@ -126,7 +126,7 @@ impl From<TableType> for StateType {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl From<TensorType> for StateType {
     fn from(tt: TensorType) -> Self {
         Self::Collection(tt.into())
@@ -521,7 +521,7 @@ impl From<TCStream> for State {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl From<Tensor> for State {
     fn from(tensor: Tensor) -> Self {
         Self::Collection(tensor.into())
@@ -987,7 +987,7 @@ impl TryCastFrom<State> for Scalar {
     }
 }
 
-#[cfg(feature = "tensor")]
+#[cfg(feature = "tc-tensor")]
 impl TryCastFrom<State> for Tensor {
     fn can_cast_from(state: &State) -> bool {
         match state {
@@ -13,11 +13,12 @@ use safecast::{CastFrom, CastInto, TryCastFrom};
 
 use tc_btree::BTreeInstance;
 use tc_error::*;
-use tc_table::TableStream;
-use tc_transact::{IntoView, Transaction};
+use tc_table::{TableInstance, TableStream};
+use tc_transact::{IntoView, Transaction, TxnId};
 use tc_value::{Number, UInt};
 use tcgeneric::{Id, Map, TCBoxTryFuture, TCBoxTryStream};
 
+use crate::chain::{Chain, ChainInstance};
 use crate::closure::Closure;
 use crate::collection::Collection;
 use crate::fs;
@@ -32,6 +33,7 @@ mod group;
 #[derive(Clone)]
 pub enum TCStream {
     Aggregate(Box<TCStream>),
+    Changes(Box<Chain>, Option<TxnId>),
     Collection(Collection),
     Map(Box<TCStream>, Closure),
     Range(Number, Number, Number),
@@ -46,6 +48,12 @@ impl TCStream {
         Self::Aggregate(Box::new(self))
     }
 
+    /// Stream each change to the subject of the given [`Chain`] committed after `since`,
+    /// or every change in its history if `since` is `None`.
+    pub fn changes(chain: Chain, since: Option<TxnId>) -> Self {
+        Self::Changes(Box::new(chain), since)
+    }
+
     /// Fold this stream with the given initial `State` and `Closure`.
     ///
     /// For example, folding `[1, 2, 3]` with `0` and `Number::add` will produce `6`.
@@ -99,6 +107,13 @@ impl TCStream {
                         .map_ok(Self::execute_aggregate)
                         .await
                 }
+                Self::Changes(chain, since) => {
+                    let changes = chain.changes(&txn, since).await?;
+                    let changes: TCBoxTryStream<'static, State> =
+                        Box::pin(changes.map_ok(Value::from).map_ok(State::from));
+
+                    Ok(changes)
+                }
                 Self::Collection(collection) => Self::execute_stream(collection, txn).await,
                 Self::Map(source, op) => {
                     source
@@ -147,6 +162,7 @@ impl TCStream {
     ) -> TCResult<TCBoxTryStream<'static, State>> {
         match collection {
             Collection::BTree(btree) => {
+                let btree = btree.with_isolation(txn.isolation());
                 let keys = btree.keys(*txn.id()).await?;
                 let keys: TCBoxTryStream<'static, State> =
                     Box::pin(keys.map_ok(Value::from).map_ok(State::from));
@@ -154,6 +170,7 @@ impl TCStream {
                 Ok(keys)
             }
             Collection::Table(table) => {
+                let table = table.with_isolation(txn.isolation());
                 let rows = table.rows(*txn.id()).await?;
                 let rows: TCBoxTryStream<'static, State> =
                     Box::pin(rows.map_ok(Value::from).map_ok(State::from));
@@ -161,7 +178,7 @@ impl TCStream {
                 Ok(rows)
             }
 
-            #[cfg(feature = "tensor")]
+            #[cfg(feature = "tc-tensor")]
             Collection::Tensor(tensor) => match tensor {
                 tc_tensor::Tensor::Dense(dense) => {
                     use tc_tensor::DenseAccess;
@@ -0,0 +1,380 @@
+//! Record the envelopes of inbound requests to a local trace log, and replay a trace log against
+//! a test host, for realistic performance regression testing.
+//!
+//! A trace log is a newline-delimited JSON file with one [`Envelope`] per line. An envelope is
+//! sanitized: it records the method, path, timing, and the sizes of the request key and body,
+//! but never their contents, nor the request's auth token or transaction ID. A replayed request
+//! carries a placeholder key and body of the recorded size, so a replay exercises the same routes
+//! with the same payload sizes but not necessarily the same code paths within a route;
+//! [`Report`] counts the replayed requests whose status differs from the recorded status.
+
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use futures::future;
+use hyper::body::{Body, HttpBody};
+use hyper::client::HttpConnector;
+use log::{debug, warn};
+use serde_json::{json, Value as Json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::sync::mpsc;
+use url::Url;
+
+use tc_error::*;
+
+/// The sanitized envelope of a single inbound request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Envelope {
+    /// The time at which the request was received, relative to the start of the recording.
+    pub offset: Duration,
+    /// The HTTP method of the request.
+    pub method: String,
+    /// The path requested, without its query string.
+    pub path: String,
+    /// The length of the encoded `key` parameter, if any.
+    pub key_len: usize,
+    /// The length of the request body, as given by its `Content-Length` header.
+    pub body_len: u64,
+    /// The HTTP status code of the response.
+    pub status: u16,
+    /// The time taken to construct the response, not including streaming the response body.
+    pub duration: Duration,
+}
+
+impl Envelope {
+    fn to_json(&self) -> Json {
+        json!({
+            "offset_us": self.offset.as_micros() as u64,
+            "method": self.method,
+            "path": self.path,
+            "key_len": self.key_len,
+            "body_len": self.body_len,
+            "status": self.status,
+            "duration_us": self.duration.as_micros() as u64,
+        })
+    }
+
+    fn from_json(json: &Json) -> TCResult<Self> {
+        let err = || TCError::bad_request("invalid request envelope", json);
+
+        let int = |name: &str| json.get(name).and_then(Json::as_u64).ok_or_else(err);
+        let string = |name: &str| {
+            json.get(name)
+                .and_then(Json::as_str)
+                .map(String::from)
+                .ok_or_else(err)
+        };
+
+        Ok(Self {
+            offset: Duration::from_micros(int("offset_us")?),
+            method: string("method")?,
+            path: string("path")?,
+            key_len: int("key_len")? as usize,
+            body_len: int("body_len")?,
+            status: int("status")? as u16,
+            duration: Duration::from_micros(int("duration_us")?),
+        })
+    }
+}
+
+/// Appends the [`Envelope`] of each request handled to a trace log.
+#[derive(Clone)]
+pub struct Recorder {
+    start: Instant,
+    log: mpsc::UnboundedSender<Envelope>,
+}
+
+impl Recorder {
+    /// Start recording to the trace log at `path`, appending to it if it already exists.
+    pub async fn create(path: &Path) -> TCResult<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| {
+                TCError::internal(format!("unable to open trace log {:?}: {}", path, e))
+            })?;
+
+        let (log, envelopes) = mpsc::unbounded_channel();
+        tokio::spawn(write_log(BufWriter::new(file), envelopes));
+
+        Ok(Self {
+            start: Instant::now(),
+            log,
+        })
+    }
+
+    /// Return the time elapsed since this `Recorder` was created.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Append the given `envelope` to the trace log.
+    pub fn record(&self, envelope: Envelope) {
+        if self.log.send(envelope).is_err() {
+            warn!("the trace log is closed, dropping a request envelope");
+        }
+    }
+}
+
+async fn write_log<W: AsyncWriteExt + Unpin>(
+    mut file: W,
+    mut envelopes: mpsc::UnboundedReceiver<Envelope>,
+) {
+    while let Some(envelope) = envelopes.recv().await {
+        let mut lines = line(&envelope);
+        while let Ok(envelope) = envelopes.try_recv() {
+            lines.extend(line(&envelope));
+        }
+
+        let written = match file.write_all(&lines).await {
+            Ok(()) => file.flush().await,
+            Err(cause) => Err(cause),
+        };
+
+        if let Err(cause) = written {
+            warn!("error writing to trace log: {}", cause);
+        }
+    }
+}
+
+fn line(envelope: &Envelope) -> Vec<u8> {
+    let mut line = envelope.to_json().to_string().into_bytes();
+    line.push(b'\n');
+    line
+}
+
+/// The outcome of replaying a trace log.
+pub struct Report {
+    elapsed: Duration,
+    failed: usize,
+    mismatched: usize,
+    recorded: Vec<Duration>,
+    replayed: Vec<Duration>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "replayed {} requests in {:.3}s ({} failed, {} with a different status than recorded)",
+            self.replayed.len(),
+            self.elapsed.as_secs_f64(),
+            self.failed,
+            self.mismatched
+        )?;
+
+        writeln!(
+            f,
+            "{:<10}{:>12}{:>12}{:>12}{:>12}",
+            "latency", "p50", "p90", "p99", "max"
+        )?;
+
+        for (name, latencies) in &[("recorded", &self.recorded), ("replayed", &self.replayed)] {
+            writeln!(
+                f,
+                "{:<10}{:>12?}{:>12?}{:>12?}{:>12?}",
+                name,
+                percentile(latencies, 50),
+                percentile(latencies, 90),
+                percentile(latencies, 99),
+                percentile(latencies, 100),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replay the trace log at `path` against the host at `target`.
+///
+/// Each request is issued at its recorded offset divided by `speed`, so a `speed` of `2.0`
+/// replays the trace in half the time it took to record.
+pub async fn replay(path: &Path, target: &Url, speed: f64) -> TCResult<Report> {
+    if !speed.is_finite() || speed <= 0. {
+        return Err(TCError::bad_request(
+            "replay speed must be positive, not",
+            speed,
+        ));
+    }
+
+    let envelopes = read_log(path).await?;
+    debug!("replaying {} requests from {:?}", envelopes.len(), path);
+
+    let client = hyper::Client::builder().http2_only(true).build_http();
+
+    let start = tokio::time::Instant::now();
+    let replays = envelopes.into_iter().map(|envelope| {
+        let client = &client;
+        async move {
+            tokio::time::sleep_until(start + envelope.offset.div_f64(speed)).await;
+            let replayed = issue(client, target, &envelope).await;
+            (envelope, replayed)
+        }
+    });
+
+    let replays = future::join_all(replays).await;
+    let elapsed = start.elapsed();
+
+    let mut report = Report {
+        elapsed,
+        failed: 0,
+        mismatched: 0,
+        recorded: Vec::with_capacity(replays.len()),
+        replayed: Vec::with_capacity(replays.len()),
+    };
+
+    for (envelope, replayed) in replays {
+        report.recorded.push(envelope.duration);
+
+        match replayed {
+            Ok((status, duration)) => {
+                if status != envelope.status {
+                    report.mismatched += 1;
+                }
+
+                report.replayed.push(duration);
+            }
+            Err(cause) => {
+                debug!(
+                    "error replaying {} {}: {}",
+                    envelope.method, envelope.path, cause
+                );
+                report.failed += 1;
+            }
+        }
+    }
+
+    report.recorded.sort();
+    report.replayed.sort();
+
+    Ok(report)
+}
+
+async fn read_log(path: &Path) -> TCResult<Vec<Envelope>> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| TCError::bad_request(format!("unable to open trace log {:?}", path), e))?;
+
+    let mut lines = BufReader::new(file).lines();
+    let mut envelopes = Vec::new();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| TCError::bad_request("error reading trace log", e))?
+    {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let json = serde_json::from_str(&line)
+            .map_err(|e| TCError::bad_request("invalid trace log entry", e))?;
+
+        envelopes.push(Envelope::from_json(&json)?);
+    }
+
+    envelopes.sort_by_key(|envelope| envelope.offset);
+    Ok(envelopes)
+}
+
+async fn issue(
+    client: &hyper::Client<HttpConnector, Body>,
+    target: &Url,
+    envelope: &Envelope,
+) -> TCResult<(u16, Duration)> {
+    let mut url = target
+        .join(&envelope.path)
+        .map_err(|e| TCError::bad_request("invalid path in trace log", e))?;
+
+    if envelope.key_len > 0 {
+        let key = String::from_utf8(placeholder(envelope.key_len as u64)).expect("placeholder");
+        url.query_pairs_mut().append_pair("key", &key);
+    }
+
+    let body = if envelope.body_len > 0 {
+        Body::from(placeholder(envelope.body_len))
+    } else {
+        Body::empty()
+    };
+
+    let request = hyper::Request::builder()
+        .method(envelope.method.as_str())
+        .uri(url.as_str())
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .map_err(|e| TCError::bad_request("invalid request in trace log", e))?;
+
+    let start = Instant::now();
+    let response = client
+        .request(request)
+        .await
+        .map_err(|e| TCError::bad_gateway(e))?;
+
+    let duration = start.elapsed();
+    let status = response.status().as_u16();
+
+    // drain the response body so that its connection can be reused
+    let mut body = response.into_body();
+    while let Some(chunk) = body.data().await {
+        chunk.map_err(|e| TCError::bad_gateway(e))?;
+    }
+
+    Ok((status, duration))
+}
+
+/// Construct a valid JSON placeholder exactly `len` bytes long.
+fn placeholder(len: u64) -> Vec<u8> {
+    let len = len as usize;
+    if len < 2 {
+        return vec![b'0'; len];
+    }
+
+    let mut placeholder = vec![b'x'; len];
+    placeholder[0] = b'"';
+    placeholder[len - 1] = b'"';
+    placeholder
+}
+
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::default();
+    }
+
+    let i = ((sorted.len() * p) / 100).min(sorted.len() - 1);
+    sorted[i]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope() {
+        let envelope = Envelope {
+            offset: Duration::from_millis(1500),
+            method: "PUT".to_string(),
+            path: "/app/table".to_string(),
+            key_len: 8,
+            body_len: 1024,
+            status: 200,
+            duration: Duration::from_micros(350),
+        };
+
+        let json: Json = serde_json::from_slice(&line(&envelope)).unwrap();
+        assert_eq!(Envelope::from_json(&json).unwrap(), envelope);
+    }
+
+    #[test]
+    fn test_placeholder() {
+        for len in 0..4 {
+            let placeholder = placeholder(len);
+            assert_eq!(placeholder.len() as u64, len);
+
+            if len > 0 {
+                let _: Json = serde_json::from_slice(&placeholder).unwrap();
+            }
+        }
+    }
+}
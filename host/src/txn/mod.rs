@@ -1,8 +1,9 @@
 //! The transaction context [`Txn`].
 
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::iter::FromIterator;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
 use futures::future::TryFutureExt;
@@ -10,12 +11,16 @@ use log::debug;
 
 use tc_error::*;
 use tc_transact::fs::Dir;
-use tc_transact::Transaction;
+use tc_transact::{Isolation, Transaction};
 use tc_value::{Link, Value};
 use tcgeneric::{Id, NetworkTime, PathSegment, TCPathBuf, Tuple};
 
+use crate::cluster::{Cluster, ClusterSavepoint};
+#[cfg(feature = "tc-tensor")]
+use crate::collection::TensorTape;
 use crate::fs;
 use crate::gateway::Gateway;
+use crate::object::InstanceExt;
 use crate::state::State;
 
 pub use request::*;
@@ -27,18 +32,31 @@ mod server;
 
 struct Active {
     workspace: fs::Dir,
+    data_dir: Option<fs::Dir>,
     expires: NetworkTime,
     scope: Scope,
+    savepoints: Mutex<HashMap<Id, Savepoint>>,
+    #[cfg(feature = "tc-tensor")]
+    tape: Mutex<Option<Arc<TensorTape>>>,
 }
 
 impl Active {
-    fn new(txn_id: &TxnId, workspace: fs::Dir, expires: NetworkTime) -> Self {
+    fn new(
+        txn_id: &TxnId,
+        workspace: fs::Dir,
+        data_dir: Option<fs::Dir>,
+        expires: NetworkTime,
+    ) -> Self {
         let scope = TCPathBuf::from(txn_id.to_id());
 
         Self {
             workspace,
+            data_dir,
             expires,
             scope,
+            savepoints: Mutex::new(HashMap::new()),
+            #[cfg(feature = "tc-tensor")]
+            tape: Mutex::new(None),
         }
     }
 
@@ -51,6 +69,15 @@ impl Active {
     }
 }
 
+/// A handle to the state of a [`Txn`] at a point in time, to roll back to with [`Txn::rollback_to`].
+#[derive(Clone)]
+pub struct Savepoint {
+    txn_id: TxnId,
+    workspace: fs::DirSavepoint,
+    data_dir: Option<fs::DirSavepoint>,
+    clusters: Vec<(Arc<InstanceExt<Cluster>>, ClusterSavepoint)>,
+}
+
 /// A transaction context.
 #[derive(Clone)]
 pub struct Txn {
@@ -58,10 +85,13 @@ pub struct Txn {
     gateway: Arc<Gateway>,
     request: Arc<Request>,
     dir: fs::Dir,
+    isolation: Isolation,
+    trace_id: Arc<str>,
 }
 
 impl Txn {
     fn new(active: Arc<Active>, gateway: Arc<Gateway>, dir: fs::Dir, request: Request) -> Self {
+        let trace_id = request.txn_id().to_string().into();
         let request = Arc::new(request);
 
         Self {
@@ -69,9 +99,30 @@ impl Txn {
             gateway,
             request,
             dir,
+            isolation: Isolation::default(),
+            trace_id,
         }
     }
 
+    /// Return a copy of this `Txn` which reads with the given [`Isolation`] level.
+    pub fn with_isolation(self, isolation: Isolation) -> Self {
+        Self { isolation, ..self }
+    }
+
+    /// Return a copy of this `Txn` which belongs to the trace with the given ID.
+    ///
+    /// By default, the trace ID of a `Txn` is its [`TxnId`].
+    pub fn with_trace_id(self, trace_id: String) -> Self {
+        let trace_id = trace_id.into();
+        Self { trace_id, ..self }
+    }
+
+    /// Return the ID of the trace to which this `Txn` belongs, for correlating log records
+    /// across tasks and hosts.
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
     /// Return the current number of strong references to this `Txn`.
     pub fn ref_count(&self) -> usize {
         Arc::strong_count(&self.active)
@@ -133,6 +184,8 @@ impl Txn {
             gateway: self.gateway.clone(),
             dir: self.dir.clone(),
             request: Arc::new(Request::new(*txn_id, token, claims)),
+            isolation: self.isolation,
+            trace_id: self.trace_id.clone(),
         })
     }
 
@@ -230,6 +283,108 @@ impl Txn {
         self.gateway.link(path)
     }
 
+    /// Return the gradient tape of this transaction on this host, if one has been started.
+    #[cfg(feature = "tc-tensor")]
+    pub fn tape(&self) -> Option<Arc<TensorTape>> {
+        self.active.tape.lock().expect("gradient tape").clone()
+    }
+
+    /// Start recording differentiable `Tensor` operations in this transaction on this host,
+    /// or return the gradient tape which is already recording.
+    #[cfg(feature = "tc-tensor")]
+    pub fn start_tape(&self) -> Arc<TensorTape> {
+        let mut tape = self.active.tape.lock().expect("gradient tape");
+        tape.get_or_insert_with(|| Arc::new(TensorTape::new())).clone()
+    }
+
+    /// Borrow the [`Gateway`] of the host executing this transaction.
+    pub(crate) fn gateway(&self) -> &Gateway {
+        &self.gateway
+    }
+
+    /// Save the current state of the data mutated by this transaction on this host,
+    /// including the in-memory state of each hosted [`Cluster`].
+    pub async fn savepoint(&self) -> TCResult<Savepoint> {
+        let txn_id = *self.id();
+        let workspace = self.active.workspace.savepoint(txn_id).await?;
+
+        let data_dir = if let Some(data_dir) = &self.active.data_dir {
+            data_dir.savepoint(txn_id).map_ok(Some).await?
+        } else {
+            None
+        };
+
+        let clusters = self
+            .gateway
+            .hosted()
+            .into_iter()
+            .map(|cluster| {
+                let savepoint = cluster.savepoint(txn_id)?;
+                Ok((cluster, savepoint))
+            })
+            .collect::<TCResult<_>>()?;
+
+        Ok(Savepoint {
+            txn_id,
+            workspace,
+            data_dir,
+            clusters,
+        })
+    }
+
+    /// Undo the mutations made by this transaction on this host since the given [`Savepoint`].
+    ///
+    /// The transaction remains active and can still be committed.
+    pub async fn rollback_to(&self, savepoint: &Savepoint) -> TCResult<()> {
+        let txn_id = *self.id();
+        if savepoint.txn_id != txn_id {
+            return Err(TCError::bad_request(
+                "cannot roll back to a savepoint from transaction",
+                savepoint.txn_id,
+            ));
+        }
+
+        debug!("roll back transaction {} to a savepoint", txn_id);
+
+        self.active
+            .workspace
+            .rollback(txn_id, savepoint.workspace.clone())
+            .await?;
+
+        if let (Some(data_dir), Some(savepoint)) = (&self.active.data_dir, &savepoint.data_dir) {
+            data_dir.rollback(txn_id, savepoint.clone()).await?;
+        }
+
+        for (cluster, savepoint) in &savepoint.clusters {
+            cluster.rollback_to(txn_id, savepoint.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Save the current state of this transaction on this host as the given `name`,
+    /// replacing any savepoint previously saved with the same name.
+    pub async fn save(&self, name: Id) -> TCResult<()> {
+        let savepoint = self.savepoint().await?;
+        let mut savepoints = self.active.savepoints.lock().expect("savepoints");
+        savepoints.insert(name, savepoint);
+        Ok(())
+    }
+
+    /// Undo the mutations made by this transaction on this host since the savepoint
+    /// saved as the given `name`.
+    pub async fn rollback_to_saved(&self, name: &Id) -> TCResult<()> {
+        let savepoint = {
+            let savepoints = self.active.savepoints.lock().expect("savepoints");
+            savepoints
+                .get(name)
+                .cloned()
+                .ok_or_else(|| TCError::not_found(format!("savepoint {}", name)))?
+        };
+
+        self.rollback_to(&savepoint).await
+    }
+
     /// Return the [`Request`] which initiated this transaction on this host.
     pub fn request(&'_ self) -> &'_ Request {
         &self.request
@@ -263,6 +418,10 @@ impl Transaction<fs::Dir> for Txn {
         self.request.txn_id()
     }
 
+    fn isolation(&self) -> Isolation {
+        self.isolation
+    }
+
     fn context(&'_ self) -> &'_ fs::Dir {
         &self.dir
     }
@@ -279,6 +438,8 @@ impl Transaction<fs::Dir> for Txn {
             gateway: self.gateway.clone(),
             request: self.request.clone(),
             dir,
+            isolation: self.isolation,
+            trace_id: self.trace_id.clone(),
         })
     }
 
@@ -291,6 +452,8 @@ impl Transaction<fs::Dir> for Txn {
                 gateway: self.gateway.clone(),
                 request: self.request.clone(),
                 dir,
+                isolation: self.isolation,
+                trace_id: self.trace_id.clone(),
             })
             .await
     }
@@ -2,6 +2,7 @@
 
 use std::collections::hash_map::{Entry, HashMap};
 use std::convert::TryInto;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -12,29 +13,140 @@ use tokio::sync::RwLock;
 
 use tc_error::*;
 use tc_transact::fs::Dir;
+use tc_value::Value;
+use tcgeneric::{label, path_label, Id, Map, PathLabel};
 
 use crate::fs;
 use crate::gateway::Gateway;
+use crate::state::State;
 
 use super::request::*;
 use super::{Active, Txn, TxnId};
 
-const GRACE: Duration = Duration::from_secs(3);
-const INTERVAL: Duration = Duration::from_millis(100);
+/// The path at which to report [`TxnMetrics`].
+pub const METRICS: PathLabel = path_label(&["transact", "metrics"]);
+
+/// The default time to wait after a transaction expires before finalizing it.
+pub const DEFAULT_HORIZON: Duration = Duration::from_secs(3);
+
+/// The default interval at which to check for expired transactions.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The policy by which a [`TxnServer`] finalizes expired transactions.
+#[derive(Clone, Copy)]
+pub struct FinalizePolicy {
+    /// How long after a transaction expires to finalize it and reclaim its block versions
+    pub horizon: Duration,
+
+    /// How often to check for expired transactions
+    pub interval: Duration,
+}
+
+impl Default for FinalizePolicy {
+    fn default() -> Self {
+        Self {
+            horizon: DEFAULT_HORIZON,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+}
+
+/// A summary of the transaction lifecycle of a [`TxnServer`].
+pub struct TxnMetrics {
+    /// The number of transactions currently active
+    pub active: usize,
+
+    /// The number of transaction-specific block versions in the data directory not yet finalized
+    pub pending_versions: usize,
+
+    /// The number of expired transactions finalized since this host started
+    pub finalized: u64,
+}
+
+impl From<TxnMetrics> for State {
+    fn from(metrics: TxnMetrics) -> Self {
+        let metrics: Map<State> = vec![
+            (label("active"), Value::from(metrics.active as u64)),
+            (
+                label("pending_versions"),
+                Value::from(metrics.pending_versions as u64),
+            ),
+            (label("finalized"), Value::from(metrics.finalized)),
+        ]
+        .into_iter()
+        .map(|(name, value)| (Id::from(name), State::from(value)))
+        .collect();
+
+        State::Map(metrics)
+    }
+}
 
 /// Server to keep track of the transactions currently active for this host.
 #[derive(Clone)]
 pub struct TxnServer {
     active: Arc<RwLock<HashMap<TxnId, Arc<Active>>>>,
     workspace: DirLock<fs::CacheBlock>,
+    data_dir: Option<fs::Dir>,
+    pending_versions: Option<Arc<AtomicUsize>>,
+    finalized: Arc<AtomicU64>,
 }
 
 impl TxnServer {
     /// Construct a new `TxnServer`.
-    pub async fn new(workspace: DirLock<fs::CacheBlock>) -> Self {
+    pub async fn new(
+        workspace: DirLock<fs::CacheBlock>,
+        data_dir: Option<fs::Dir>,
+        policy: FinalizePolicy,
+    ) -> Self {
         let active = Arc::new(RwLock::new(HashMap::new()));
-        spawn_cleanup_thread(workspace.clone(), active.clone());
-        Self { active, workspace }
+        let finalized = Arc::new(AtomicU64::new(0));
+
+        let pending_versions = if let Some(data_dir) = &data_dir {
+            Some(data_dir.track_pending_versions().await)
+        } else {
+            None
+        };
+
+        let server = Self {
+            active,
+            workspace,
+            data_dir,
+            pending_versions,
+            finalized,
+        };
+
+        spawn_cleanup_thread(server.clone(), policy);
+        server
+    }
+
+    /// Report the current [`TxnMetrics`] of this `TxnServer`.
+    pub async fn metrics(&self) -> TxnMetrics {
+        let active = self.active.read().await.len();
+
+        let pending_versions = if let Some(pending_versions) = &self.pending_versions {
+            pending_versions.load(Ordering::Relaxed)
+        } else {
+            0
+        };
+
+        TxnMetrics {
+            active,
+            pending_versions,
+            finalized: self.finalized.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Return the total size of the workspace and data directory files loaded in the cache.
+    pub async fn cache_occupancy(&self) -> usize {
+        let workspace = fs::cache_occupancy(&self.workspace).await;
+
+        let data_dir = if let Some(data_dir) = &self.data_dir {
+            data_dir.cache_occupancy().await
+        } else {
+            0
+        };
+
+        workspace + data_dir
     }
 
     /// Return the active `Txn` with the given [`TxnId`], or initiate a new [`Txn`].
@@ -44,7 +156,7 @@ impl TxnServer {
         txn_id: TxnId,
         token: (String, Claims),
     ) -> TCResult<Txn> {
-        debug!("TxnServer::new_txn");
+        tracing::debug!(%txn_id, "TxnServer::new_txn");
 
         let expires = token.1.expires().try_into()?;
         let request = Request::new(txn_id, token.0, token.1);
@@ -59,7 +171,8 @@ impl TxnServer {
             Entry::Vacant(entry) => {
                 let workspace = self.txn_dir(txn_id).await?;
                 let dir = workspace.create_dir_unique(txn_id).await?;
-                let active = Arc::new(Active::new(&txn_id, workspace, expires));
+                let data_dir = self.data_dir.clone();
+                let active = Arc::new(Active::new(&txn_id, workspace, data_dir, expires));
                 let txn = Txn::new(active.clone(), gateway, dir, request);
                 entry.insert(active);
                 Ok(txn)
@@ -97,32 +210,24 @@ impl TxnServer {
     }
 }
 
-fn spawn_cleanup_thread(
-    workspace: DirLock<fs::CacheBlock>,
-    active: Arc<RwLock<HashMap<TxnId, Arc<Active>>>>,
-) {
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(
-        INTERVAL.as_millis() as u64,
-    ));
+fn spawn_cleanup_thread(server: TxnServer, policy: FinalizePolicy) {
+    let mut interval = tokio::time::interval(policy.interval);
 
     tokio::spawn(async move {
         loop {
             interval.tick().await;
-            cleanup(&workspace, &active).await;
+            cleanup(&server, policy.horizon).await;
         }
     });
 }
 
-async fn cleanup(
-    workspace: &DirLock<fs::CacheBlock>,
-    txn_pool: &RwLock<HashMap<TxnId, Arc<Active>>>,
-) {
+async fn cleanup(server: &TxnServer, horizon: Duration) {
     let now = Gateway::time();
-    let mut txn_pool = txn_pool.write().await;
+    let mut txn_pool = server.active.write().await;
     let expired: Vec<TxnId> = txn_pool
         .iter()
         .filter_map(|(txn_id, active)| {
-            if active.expires() + GRACE < now {
+            if active.expires() + horizon < now {
                 Some(txn_id)
             } else {
                 None
@@ -131,11 +236,29 @@ async fn cleanup(
         .cloned()
         .collect();
 
-    let mut workspace = workspace.write().await;
-    for txn_id in expired.into_iter() {
-        if let Some(_active) = txn_pool.remove(&txn_id) {
-            debug!("clean up txn {}", txn_id);
-            workspace.delete(txn_id.to_string());
+    let mut finalized = Vec::with_capacity(expired.len());
+
+    {
+        let mut workspace = server.workspace.write().await;
+        for txn_id in expired.into_iter() {
+            if let Some(_active) = txn_pool.remove(&txn_id) {
+                tracing::debug!(%txn_id, "finalize transaction");
+                workspace.delete(txn_id.to_string());
+                finalized.push(txn_id);
+            }
         }
     }
+
+    // release the transaction pool so that new transactions don't wait on disk I/O
+    std::mem::drop(txn_pool);
+
+    if let Some(data_dir) = &server.data_dir {
+        if !finalized.is_empty() {
+            data_dir.reclaim(&finalized).await;
+        }
+    }
+
+    server
+        .finalized
+        .fetch_add(finalized.len() as u64, Ordering::Relaxed);
 }
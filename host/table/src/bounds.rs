@@ -117,6 +117,35 @@ impl Bounds {
         Self { inner }
     }
 
+    /// Construct a list of `Bounds` which together select every key which sorts after the given
+    /// `key`, in order, according to the given schema.
+    ///
+    /// For a key `(a, b)` this is `a = a0 AND b > b0`, followed by `a > a0`.
+    pub fn after_key(key: Vec<Value>, key_columns: &[Column]) -> TCResult<Vec<Self>> {
+        if key.len() != key_columns.len() {
+            return Err(TCError::bad_request("invalid key length", Tuple::from(key)));
+        }
+
+        let names: Vec<Id> = key_columns.iter().map(|c| c.name()).cloned().collect();
+
+        let bounds = (0..key.len())
+            .rev()
+            .map(|i| {
+                let mut inner: HashMap<Id, ColumnBound> = names[..i]
+                    .iter()
+                    .cloned()
+                    .zip(key[..i].iter().cloned().map(ColumnBound::Is))
+                    .collect();
+
+                let bound = (Bound::Ex(key[i].clone()), Bound::Un).into();
+                inner.insert(names[i].clone(), bound);
+                Self { inner }
+            })
+            .collect();
+
+        Ok(bounds)
+    }
+
     /// Convert these `Bounds` into an equivalent [`tc_btree::Range`] according to the given schema.
     pub fn into_btree_range(mut self, columns: &[Column]) -> TCResult<tc_btree::Range> {
         let on_err = |bounds: &HashMap<Id, ColumnBound>| {
@@ -1,24 +1,25 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
-use std::iter::FromIterator;
+use std::iter::{self, FromIterator};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use futures::future::{self, join_all, try_join_all, TryFutureExt};
-use futures::stream::TryStreamExt;
+use futures::stream::{StreamExt, TryStreamExt};
 use log::debug;
 use safecast::AsType;
 
 use tc_btree::{BTreeFile, BTreeInstance, BTreeType, BTreeWrite, Node};
 use tc_error::*;
 use tc_transact::fs::{CopyFrom, Dir, File, Persist, Restore};
-use tc_transact::{Transact, Transaction, TxnId};
+use tc_transact::{Isolation, Transact, Transaction, TxnId};
 use tc_value::Value;
 use tcgeneric::{label, Id, Instance, Label, TCBoxTryStream, Tuple};
 
+use super::stats::{self, IndexStats, Statistics};
 use super::view::{Limited, MergeSource, Merged, Selection, TableSlice as Slice};
 use super::{
-    Bounds, Column, ColumnBound, IndexSchema, IndexSlice, Key, Row, Table, TableInstance,
+    Alter, Bounds, Column, ColumnBound, IndexSchema, IndexSlice, Key, Row, Table, TableInstance,
     TableOrder, TableRead, TableSchema, TableSlice, TableStream, TableType, TableWrite, Values,
 };
 
@@ -28,21 +29,39 @@ const PRIMARY_INDEX: Label = label("primary");
 pub struct Index<F, D, Txn> {
     btree: BTreeFile<F, D, Txn>,
     schema: IndexSchema,
+    stats: Arc<Statistics>,
 }
 
 impl<F: File<Node>, D: Dir, Txn: Transaction<D>> Index<F, D, Txn> {
     pub async fn create(file: F, schema: IndexSchema, txn_id: TxnId) -> TCResult<Self> {
         BTreeFile::create(file, schema.clone().into(), txn_id)
-            .map_ok(|btree| Index { btree, schema })
+            .map_ok(|btree| Self::new(btree, schema, Statistics::new))
             .await
     }
 
+    fn new<S>(btree: BTreeFile<F, D, Txn>, schema: IndexSchema, stats: S) -> Self
+    where
+        S: FnOnce(usize) -> Statistics,
+    {
+        let stats = stats(schema.columns().len());
+
+        Self {
+            btree,
+            schema,
+            stats: Arc::new(stats),
+        }
+    }
+
     pub fn btree(&'_ self) -> &'_ BTreeFile<F, D, Txn> {
         &self.btree
     }
 
     pub async fn is_empty(&self, txn: &Txn) -> TCResult<bool> {
-        self.btree.is_empty(*txn.id()).await
+        self.btree
+            .clone()
+            .with_isolation(txn.isolation())
+            .is_empty(*txn.id())
+            .await
     }
 
     pub fn index_slice(self, bounds: Bounds) -> TCResult<IndexSlice<F, D, Txn>> {
@@ -55,6 +74,11 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> Index<F, D, Txn> {
         &self.schema
     }
 
+    /// Return the committed [`IndexStats`] of this `Index`.
+    pub fn stats(&self) -> IndexStats {
+        self.stats.committed()
+    }
+
     pub fn validate_slice_bounds(&self, outer: Bounds, inner: Bounds) -> TCResult<()> {
         let columns = &self.schema.columns();
         let outer = outer.validate(columns)?.into_btree_range(columns)?;
@@ -97,10 +121,18 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> Index<F, D, Txn> {
             })
             .collect::<TCResult<Key>>()?;
 
-        self.delete_inner(txn_id, key).await
+        self.delete_inner(txn_id, key).await?;
+        self.stats.delete(txn_id);
+        Ok(())
     }
 
-    async fn replace(&self, txn_id: TxnId, mut row: Row, mut update: Row) -> TCResult<()> {
+    async fn replace(
+        &self,
+        txn_id: TxnId,
+        mut row: Row,
+        mut update: Row,
+        exists: bool,
+    ) -> TCResult<()> {
         debug!("Index::replace {} with updated values {}", row, update);
 
         let old_key = self
@@ -128,6 +160,7 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> Index<F, D, Txn> {
             .collect::<TCResult<Key>>()?;
 
         self.delete_inner(txn_id, old_key).await?;
+        self.stats.insert(txn_id, &new_key, exists);
         self.btree.insert(txn_id, new_key).await
     }
 }
@@ -155,6 +188,13 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableInstance for Index<F, D, T
     fn schema(&self) -> TableSchema {
         self.schema.clone().into()
     }
+
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        Self {
+            btree: self.btree.with_isolation(isolation),
+            ..self
+        }
+    }
 }
 
 impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableOrder for Index<F, D, Txn> {
@@ -264,11 +304,13 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableSlice for Index<F, D, Txn>
 #[async_trait]
 impl<F: File<Node> + Transact, D: Dir, Txn: Transaction<D>> Transact for Index<F, D, Txn> {
     async fn commit(&self, txn_id: &TxnId) {
-        self.btree.commit(txn_id).await
+        self.btree.commit(txn_id).await;
+        self.stats.commit(txn_id);
     }
 
     async fn finalize(&self, txn_id: &TxnId) {
-        self.btree.finalize(txn_id).await
+        self.btree.finalize(txn_id).await;
+        self.stats.finalize(txn_id);
     }
 }
 
@@ -287,7 +329,7 @@ where
 
     async fn load(txn: &Txn, schema: IndexSchema, file: F) -> TCResult<Self> {
         BTreeFile::load(txn, schema.clone().into(), file)
-            .map_ok(|btree| Self { schema, btree })
+            .map_ok(|btree| Self::new(btree, schema, Statistics::load))
             .await
     }
 }
@@ -298,7 +340,9 @@ where
     D::File: AsType<F>,
 {
     async fn restore(&self, backup: &Self, txn_id: TxnId) -> TCResult<()> {
-        self.btree.restore(&backup.btree, txn_id).await
+        self.btree.restore(&backup.btree, txn_id).await?;
+        self.stats.restore(&backup.stats, txn_id);
+        Ok(())
     }
 }
 
@@ -373,6 +417,100 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableIndex<F, D, Txn> {
         })
     }
 
+    /// Construct a new `TableIndex` by bulk-loading each of its indices from the given `source`,
+    /// which must support ordering by the columns of every index in the given `schema`.
+    async fn bulk_load<T>(
+        source: T,
+        context: &D,
+        schema: TableSchema,
+        txn_id: TxnId,
+    ) -> TCResult<Self>
+    where
+        T: TableOrder + TableStream + Clone + 'static,
+        <T as TableOrder>::OrderBy: TableStream,
+        D::File: AsType<F>,
+        D::FileClass: From<BTreeType>,
+    {
+        let primary_schema = schema.primary();
+        let primary_file = context
+            .create_file(txn_id, PRIMARY_INDEX.into(), BTreeType::default())
+            .await?;
+
+        let order = index_order(primary_schema);
+        let primary = Self::backfill(source.clone(), primary_file, primary_schema, order, txn_id);
+
+        let auxiliary = try_join_all(schema.indices().iter().map(|(name, columns)| {
+            let source = source.clone();
+            async move {
+                if *name == PRIMARY_INDEX {
+                    return Err(TCError::bad_request(
+                        "cannot create an auxiliary index with reserved name",
+                        PRIMARY_INDEX,
+                    ));
+                }
+
+                let file = context
+                    .create_file(txn_id, name.clone(), BTreeType::default())
+                    .await?;
+
+                let index_schema = primary_schema.auxiliary(columns)?;
+                Self::backfill(source, file, &index_schema, columns.to_vec(), txn_id)
+                    .map_ok(|index| (name.clone(), index))
+                    .await
+            }
+        }));
+
+        let (primary, auxiliary) = future::try_join(primary, auxiliary).await?;
+
+        Ok(TableIndex {
+            inner: Arc::new(Inner {
+                schema: schema.clone(),
+                primary,
+                auxiliary: auxiliary.into_iter().collect(),
+            }),
+        })
+    }
+
+    /// Fill a new [`Index`] with the rows of the given `source` in the given `order`,
+    /// which must match the order of the index `schema`.
+    async fn backfill<T>(
+        source: T,
+        file: F,
+        schema: &IndexSchema,
+        order: Vec<Id>,
+        txn_id: TxnId,
+    ) -> TCResult<Index<F, D, Txn>>
+    where
+        T: TableOrder + TableStream + 'static,
+        <T as TableOrder>::OrderBy: TableStream,
+    {
+        let source_columns: Vec<Id> = source.schema().primary().column_names().cloned().collect();
+
+        let stats = Arc::new(Statistics::new(schema.columns().len()));
+        let index_stats = stats.clone();
+
+        let index_schema = schema.clone();
+        let keys = source
+            .order_by(order, false)?
+            .rows(txn_id)
+            .await?
+            .map(move |row| {
+                let row = row?;
+                let row: Row = source_columns.iter().cloned().zip(row).collect();
+                let key = index_schema.values_from_row(row, false)?;
+                index_stats.insert(txn_id, &key, false);
+                Ok(key)
+            });
+
+        let btree = BTreeFile::bulk_load(file, schema.clone().into(), txn_id, keys).await?;
+
+        Ok(Index {
+            btree,
+            schema: schema.clone(),
+            stats,
+        })
+    }
+
     async fn create_index(
         file: F,
         primary: &IndexSchema,
@@ -381,7 +519,46 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableIndex<F, D, Txn> {
     ) -> TCResult<Index<F, D, Txn>> {
         let schema = primary.auxiliary(&key)?;
         let btree = BTreeFile::create(file, schema.clone().into(), txn_id).await?;
-        Ok(Index { btree, schema })
+        Ok(Index::new(btree, schema, Statistics::new))
+    }
+
+    /// Construct a copy of this table in the given `context` with the given [`Alter`] change
+    /// applied to its schema and to each of its rows.
+    ///
+    /// Every index of the new table is rewritten eagerly, within the given transaction.
+    pub async fn alter(
+        &self,
+        context: &D,
+        alter: Alter,
+        txn_id: TxnId,
+    ) -> TCResult<TableIndex<F, D, Txn>>
+    where
+        D::File: AsType<F>,
+        D::FileClass: From<BTreeType>,
+    {
+        debug!("{}: {}", self, alter);
+
+        let schema = self.inner.schema.alter(&alter)?;
+        let table = Self::create(context, schema, txn_id).await?;
+
+        let source = self.inner.schema.primary();
+        let columns: Vec<Id> = source.column_names().cloned().collect();
+        let primary = table.inner.primary.schema().clone();
+
+        self.clone()
+            .rows(txn_id)
+            .await?
+            .map(|row| {
+                let mut row: Row = columns.iter().cloned().zip(row?).collect();
+                alter.apply(&mut row);
+                primary.key_values_from_row(row, true)
+            })
+            .map_ok(|(key, values)| table.upsert(txn_id, key, values))
+            .try_buffer_unordered(num_cpus::get())
+            .try_fold((), |(), ()| future::ready(Ok(())))
+            .await?;
+
+        Ok(table)
     }
 
     /// Return `true` if this table has zero rows.
@@ -408,22 +585,98 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableIndex<F, D, Txn> {
         &self.inner.primary
     }
 
-    /// Return an index which supports the given [`Bounds`], or an error if there is none.
+    /// Return the index which supports the given [`Bounds`] with the fewest estimated rows to
+    /// read, or an error if there is none.
+    ///
+    /// The estimate is based on the committed [`IndexStats`] of each index, so if the statistics
+    /// of any supporting index are incomplete (e.g. because this table was loaded from disk)
+    /// this is the primary index, if it supports the given `bounds`, or else the first auxiliary
+    /// index which does.
     pub fn supporting_index(&self, bounds: &Bounds) -> TCResult<Index<F, D, Txn>> {
-        if self.inner.primary.validate_bounds(bounds).is_ok() {
-            return Ok(self.inner.primary.clone());
+        let primary = PRIMARY_INDEX.into();
+        let indices = iter::once((&primary, &self.inner.primary)).chain(
+            self.inner
+                .auxiliary
+                .iter()
+                .map(|(name, index)| (name, index)),
+        );
+
+        let mut supporting = Vec::new();
+        for (name, index) in indices {
+            if index.validate_bounds(bounds).is_err() {
+                continue;
+            }
+
+            let stats = index.stats();
+            if stats.is_complete() {
+                let cost = stats.estimate(index.schema().columns(), bounds);
+                debug!(
+                    "index {} can slice {} with an estimated {} rows",
+                    name, bounds, cost
+                );
+
+                supporting.push((index, Some(cost)));
+            } else {
+                debug!("index {} can slice {} but has no statistics", name, bounds);
+                supporting.push((index, None));
+            }
         }
 
-        for (_, index) in &self.inner.auxiliary {
-            if index.validate_bounds(bounds).is_ok() {
-                return Ok(index.clone());
+        stats::cheapest(supporting).cloned().ok_or_else(|| {
+            TCError::bad_request("this table has no index which supports bounds", bounds)
+        })
+    }
+
+    /// Return a `Conflict` error if the given `row` would violate a unique constraint of this
+    /// table, i.e. if a row with a different key has the same values in a unique index.
+    async fn check_unique(&self, txn_id: TxnId, row: &Row) -> TCResult<()> {
+        let primary = &self.inner.primary.schema;
+        let key = primary.key_from_row(row)?;
+
+        for name in self.inner.schema.unique() {
+            let columns = self.inner.schema.index_columns(name)?;
+            let values = columns
+                .iter()
+                .map(|col| row.get(col).cloned().unwrap_or_default())
+                .collect::<Vec<Value>>();
+
+            if values.iter().any(Value::is_none) {
+                continue;
+            }
+
+            let index = self
+                .inner
+                .auxiliary
+                .iter()
+                .find(|(index, _)| index == name)
+                .map(|(_, index)| index.clone())
+                .ok_or_else(|| TCError::not_found(format!("index {}", name)))?;
+
+            let bounds = Bounds::from_key(values.to_vec(), &index.schema.key()[..values.len()]);
+            let index_schema = index.schema.clone();
+            let mut existing = index.slice_rows(txn_id, bounds, false).await?;
+            while let Some(other) = existing.try_next().await? {
+                let other = index_schema
+                    .column_names()
+                    .cloned()
+                    .zip(other)
+                    .collect::<Row>();
+
+                if primary.key_from_row(&other)? != key {
+                    return Err(TCError::new(
+                        ErrorType::Conflict,
+                        format!(
+                            "{} violates the unique constraint {} on {}",
+                            Tuple::from(key),
+                            name,
+                            Tuple::from(values)
+                        ),
+                    ));
+                }
             }
         }
 
-        Err(TCError::bad_request(
-            "this table has no index which supports bounds",
-            bounds,
-        ))
+        Ok(())
     }
 
     /// Stream the rows within the given [`Bounds`] from the primary index of this `TableIndex`.
@@ -462,6 +715,23 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableInstance for TableIndex<F,
     fn schema(&self) -> TableSchema {
         self.inner.schema.clone()
     }
+
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        let auxiliary = self
+            .inner
+            .auxiliary
+            .iter()
+            .map(|(name, index)| (name.clone(), index.clone().with_isolation(isolation)))
+            .collect();
+
+        Self {
+            inner: Arc::new(Inner {
+                schema: self.inner.schema.clone(),
+                primary: self.inner.primary.clone().with_isolation(isolation),
+                auxiliary,
+            }),
+        }
+    }
 }
 
 impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableOrder for TableIndex<F, D, Txn> {
@@ -598,10 +868,9 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableSlice for TableIndex<F, D,
     fn slice(self, bounds: Bounds) -> TCResult<Merged<F, D, Txn>> {
         debug!("TableIndex::slice {}", bounds);
 
-        let primary = &self.inner.primary;
-        let auxiliary = &self.inner.auxiliary;
-
-        let columns: Vec<Id> = primary
+        let columns: Vec<Id> = self
+            .inner
+            .primary
             .schema()
             .columns()
             .iter()
@@ -625,50 +894,21 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableSlice for TableIndex<F, D,
                 let subset: HashMap<Id, ColumnBound> = bounds[..i].to_vec().into_iter().collect();
                 let subset = Bounds::from(subset);
 
-                if primary.validate_bounds(&subset).is_ok() {
-                    debug!("primary key can slice {}", subset);
+                match self.supporting_index(&subset) {
+                    Ok(index) => {
+                        let index_slice = index.index_slice(subset)?;
+                        let merged = Merged::new(merge_source, index_slice)?;
 
-                    let index_slice = primary.clone().index_slice(subset)?;
-                    let merged = Merged::new(merge_source, index_slice)?;
-
-                    bounds = &bounds[i..];
-                    if bounds.is_empty() {
-                        return Ok(merged);
-                    }
-
-                    merge_source = MergeSource::Merge(Box::new(merged));
-                    break;
-                } else {
-                    let mut supported = false;
-                    for (name, index) in auxiliary {
-                        debug!("checking index {} with schema {}", name, index.schema());
-
-                        match index.validate_bounds(&subset) {
-                            Ok(()) => {
-                                debug!("index {} can slice {}", name, subset);
-                                supported = true;
-
-                                let index_slice = index.clone().index_slice(subset)?;
-                                let merged = Merged::new(merge_source, index_slice)?;
-
-                                bounds = &bounds[i..];
-                                if bounds.is_empty() {
-                                    return Ok(merged);
-                                }
-
-                                merge_source = MergeSource::Merge(Box::new(merged));
-                                break;
-                            }
-                            Err(cause) => {
-                                debug!("index {} cannot slice {}: {}", name, subset, cause);
-                            }
+                        bounds = &bounds[i..];
+                        if bounds.is_empty() {
+                            return Ok(merged);
                         }
-                    }
 
-                    if supported {
+                        merge_source = MergeSource::Merge(Box::new(merged));
                         break;
                     }
-                };
+                    Err(cause) => debug!("no index can slice {}: {}", subset, cause),
+                }
 
                 i = i - 1;
             }
@@ -769,6 +1009,8 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableWrite for TableIndex<F, D,
     }
 
     async fn update(&self, txn_id: TxnId, key: Key, values: Row) -> TCResult<()> {
+        self.inner.schema.validate_not_null(&values)?;
+
         let columns_updated: HashSet<Id> = values.keys().cloned().collect();
 
         let primary = &self.inner.primary;
@@ -780,6 +1022,12 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableWrite for TableIndex<F, D,
             None => return Ok(()),
         };
 
+        if !self.inner.schema.unique().is_empty() {
+            let mut updated = row.clone();
+            updated.extend(primary.schema.validate_row_partial(values.clone())?);
+            self.check_unique(txn_id, &updated).await?;
+        }
+
         let mut updates = Vec::with_capacity(aux.len() + 1);
         for (_, index) in aux {
             if !index
@@ -790,10 +1038,10 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableWrite for TableIndex<F, D,
                 continue;
             }
 
-            updates.push(index.replace(txn_id, row.clone(), values.clone()));
+            updates.push(index.replace(txn_id, row.clone(), values.clone(), true));
         }
 
-        updates.push(primary.replace(txn_id, row, values));
+        updates.push(primary.replace(txn_id, row, values, true));
         try_join_all(updates).await?;
 
         Ok(())
@@ -804,6 +1052,8 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableWrite for TableIndex<F, D,
         let aux = &self.inner.auxiliary;
 
         let key = primary.schema.validate_key(key)?;
+        let exists = self.read(&txn_id, &key).await?.is_some();
+        let values = self.inner.schema.values_with_defaults(values)?;
         let values = primary.schema.validate_values(values)?;
 
         let columns: HashSet<Id> = primary
@@ -815,6 +1065,9 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableWrite for TableIndex<F, D,
             .collect();
 
         let row = primary.schema.row_from_key_values(key, values)?;
+        self.inner.schema.validate_not_null(&row)?;
+        self.check_unique(txn_id, &row).await?;
+
         let update: Row = row
             .clone()
             .into_iter()
@@ -823,10 +1076,10 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableWrite for TableIndex<F, D,
 
         let mut upserts = Vec::with_capacity(aux.len() + 1);
         for (_name, index) in aux {
-            upserts.push(index.replace(txn_id, row.clone(), update.clone()));
+            upserts.push(index.replace(txn_id, row.clone(), update.clone(), exists));
         }
 
-        upserts.push(primary.replace(txn_id, row, update));
+        upserts.push(primary.replace(txn_id, row, update, exists));
         try_join_all(upserts).await?;
 
         Ok(())
@@ -935,15 +1188,31 @@ where
 }
 
 #[async_trait]
-impl<F: File<Node>, D: Dir, Txn: Transaction<D>, I: TableStream + 'static> CopyFrom<D, I>
-    for TableIndex<F, D, Txn>
+impl<F, D, Txn, I> CopyFrom<D, I> for TableIndex<F, D, Txn>
 where
+    F: File<Node>,
+    D: Dir,
+    Txn: Transaction<D>,
+    I: TableOrder + TableStream + Clone + 'static,
+    <I as TableOrder>::OrderBy: TableStream,
     D::File: AsType<F>,
     <D as Dir>::FileClass: From<BTreeType> + Send,
 {
     async fn copy_from(source: I, dir: D, txn: &Txn) -> TCResult<Self> {
         let txn_id = *txn.id();
         let schema = source.schema();
+
+        let ordered = schema
+            .indices()
+            .iter()
+            .map(|(_, columns)| columns.as_slice())
+            .chain(iter::once(index_order(schema.primary()).as_slice()))
+            .all(|order| source.validate_order(order).is_ok());
+
+        if ordered {
+            return Self::bulk_load(source, &dir, schema, txn_id).await;
+        }
+
         let key_len = schema.primary().key().len();
         let table = Self::create(&dir, schema, txn_id).await?;
 
@@ -965,6 +1234,11 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> From<TableIndex<F, D, Txn>> for
     }
 }
 
+/// The columns by which to order the rows of a table to match the order of the given index.
+fn index_order(schema: &IndexSchema) -> Vec<Id> {
+    schema.key().iter().map(|col| col.name.clone()).collect()
+}
+
 impl<F: File<Node>, D: Dir, Txn: Transaction<D>> fmt::Display for TableIndex<F, D, Txn> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str("a Table")
@@ -0,0 +1,313 @@
+//! Infer a [`TableSchema`] from a sample of uploaded rows
+
+use std::collections::HashMap;
+
+use tc_error::*;
+use tc_value::{Number, Value, ValueType};
+use tcgeneric::{Id, Instance, Map};
+
+use super::{Column, IndexSchema, Row, TableSchema};
+
+/// The default number of rows to sample before inferring a [`TableSchema`].
+pub const DEFAULT_SAMPLE_SIZE: usize = 100;
+
+/// The minimum maximum length of an inferred column whose type has no fixed size (e.g. `String`).
+pub const DEFAULT_MAX_LEN: usize = 256;
+
+/// Infers the [`TableSchema`] of a sequence of [`Row`]s.
+///
+/// Column names are listed in the order in which they're first encountered.
+/// The inferred type of a column is the narrowest [`ValueType`] compatible with
+/// every sampled value; columns with no non-null values default to `String`.
+///
+/// A column whose type has no fixed size has the maximum length of every value measured, but at
+/// least [`DEFAULT_MAX_LEN`], so that a longer value after the sample can still be inserted.
+pub struct SchemaInference {
+    key: Vec<Id>,
+    overrides: Map<ValueType>,
+    columns: Vec<Id>,
+    dtypes: HashMap<Id, ValueType>,
+    max_len: HashMap<Id, usize>,
+}
+
+impl SchemaInference {
+    /// Construct a new `SchemaInference` with the given primary key and type overrides.
+    ///
+    /// If `key` is empty, the first column encountered will be used as the primary key.
+    pub fn new(key: Vec<Id>, overrides: Map<ValueType>) -> Self {
+        Self {
+            key,
+            overrides,
+            columns: vec![],
+            dtypes: HashMap::new(),
+            max_len: HashMap::new(),
+        }
+    }
+
+    /// Update the maximum length of each column with the values of the given `row`,
+    /// without sampling their types (e.g. for a row after the sample which is already loaded).
+    pub fn measure(&mut self, row: &Row) {
+        for (name, value) in row.iter() {
+            if let Value::String(s) = value {
+                let max_len = self.max_len.entry(name.clone()).or_insert(0);
+                *max_len = Ord::max(*max_len, s.len());
+            }
+        }
+    }
+
+    /// Update the inferred column types with the values of the given `row`.
+    pub fn sample(&mut self, row: &Row) {
+        self.measure(row);
+
+        for (name, value) in row.iter() {
+            if !self.dtypes.contains_key(name) && !self.columns.contains(name) {
+                self.columns.push(name.clone());
+            }
+
+            if value.is_none() {
+                continue;
+            }
+
+            let dtype = self
+                .dtypes
+                .get(name)
+                .map(|dtype| widen(*dtype, value.class()))
+                .unwrap_or_else(|| value.class());
+
+            self.dtypes.insert(name.clone(), dtype);
+        }
+    }
+
+    /// Return the inferred [`TableSchema`].
+    pub fn schema(self) -> TCResult<TableSchema> {
+        if self.columns.is_empty() {
+            return Err(TCError::unsupported(
+                "cannot infer a Table schema without any sample data",
+            ));
+        }
+
+        for name in self.overrides.keys() {
+            if !self.columns.contains(name) {
+                return Err(TCError::not_found(format!(
+                    "column {} to override in inferred schema",
+                    name
+                )));
+            }
+        }
+
+        let key = if self.key.is_empty() {
+            vec![self.columns[0].clone()]
+        } else {
+            for name in &self.key {
+                if !self.columns.contains(name) {
+                    return Err(TCError::not_found(format!(
+                        "key column {} in sampled data",
+                        name
+                    )));
+                }
+            }
+
+            self.key.clone()
+        };
+
+        let mut key_columns = Vec::with_capacity(key.len());
+        let mut value_columns = Vec::with_capacity(self.columns.len() - key.len());
+
+        for name in &key {
+            key_columns.push(self.column(name));
+        }
+
+        for name in &self.columns {
+            if !key.contains(name) {
+                value_columns.push(self.column(name));
+            }
+        }
+
+        Ok(IndexSchema::from((key_columns, value_columns)).into())
+    }
+
+    fn column(&self, name: &Id) -> Column {
+        let dtype = if let Some(dtype) = self.overrides.get(name) {
+            *dtype
+        } else {
+            self.dtypes.get(name).cloned().unwrap_or(ValueType::String)
+        };
+
+        if dtype.size().is_some() {
+            (name.clone(), dtype).into()
+        } else {
+            let max_len = self.max_len.get(name).copied().unwrap_or_default();
+            (name.clone(), dtype, Ord::max(max_len, DEFAULT_MAX_LEN)).into()
+        }
+    }
+}
+
+/// Parse a single CSV cell into the narrowest compatible [`Value`].
+pub fn parse_cell(cell: &str) -> Value {
+    let cell = cell.trim();
+
+    if cell.is_empty() {
+        Value::None
+    } else if has_leading_zero(cell) {
+        // e.g. a zip code or an ID like "007", which would lose its leading zeros as a number
+        Value::String(cell.to_string().into())
+    } else if cell.eq_ignore_ascii_case("true") {
+        Value::from(true)
+    } else if cell.eq_ignore_ascii_case("false") {
+        Value::from(false)
+    } else if let Ok(i) = cell.parse::<i64>() {
+        Value::Number(Number::from(i))
+    } else if let Ok(f) = cell.parse::<f64>() {
+        Value::Number(Number::from(f))
+    } else {
+        Value::String(cell.to_string().into())
+    }
+}
+
+fn has_leading_zero(cell: &str) -> bool {
+    let digits = cell.strip_prefix(&['-', '+'][..]).unwrap_or(cell);
+    let mut chars = digits.chars();
+    chars.next() == Some('0') && chars.next().map_or(false, |c| c.is_ascii_digit())
+}
+
+/// Parse a CSV document with a header line into a list of [`Row`]s.
+pub fn parse_csv(csv: &str) -> TCResult<Vec<Row>> {
+    let mut lines = csv.lines().filter(|line| !line.trim().is_empty());
+
+    let header = if let Some(header) = lines.next() {
+        split_csv_line(header)?
+            .into_iter()
+            .map(|name| name.trim().parse())
+            .collect::<TCResult<Vec<Id>>>()?
+    } else {
+        return Ok(vec![]);
+    };
+
+    let mut rows = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let cells = split_csv_line(line)?;
+        if cells.len() != header.len() {
+            return Err(TCError::bad_request(
+                format!(
+                    "CSV row {} has {} cells but the header has {} columns",
+                    i + 1,
+                    cells.len(),
+                    header.len()
+                ),
+                line,
+            ));
+        }
+
+        let row = header
+            .iter()
+            .cloned()
+            .zip(cells.iter().map(|cell| parse_cell(cell)))
+            .collect();
+
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+fn split_csv_line(line: &str) -> TCResult<Vec<String>> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(TCError::bad_request("unterminated quote in CSV line", line));
+    }
+
+    cells.push(cell);
+    Ok(cells)
+}
+
+fn widen(current: ValueType, sampled: ValueType) -> ValueType {
+    match (current, sampled) {
+        (l, r) if l == r => l,
+        (ValueType::Number(l), ValueType::Number(r)) => ValueType::Number(Ord::max(l, r)),
+        (ValueType::Number(_), ValueType::String) | (ValueType::String, ValueType::Number(_)) => {
+            ValueType::String
+        }
+        _ => ValueType::Value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let rows = parse_csv("id,name,score\n1,\"Smith, J.\",2.5\n2,Jones,3\n").unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let mut inference = SchemaInference::new(vec![], Map::new());
+        for row in &rows {
+            inference.sample(row);
+        }
+
+        let schema = inference.schema().unwrap();
+        let key = schema.primary().key();
+        assert_eq!(key.len(), 1);
+        assert_eq!(key[0].name.as_str(), "id");
+
+        let values = schema.primary().values();
+        assert_eq!(values[0].dtype, ValueType::String);
+        assert_eq!(values[0].max_len, Some(DEFAULT_MAX_LEN));
+    }
+
+    #[test]
+    fn test_parse_cell() {
+        assert_eq!(parse_cell("0"), Value::Number(Number::from(0i64)));
+        assert_eq!(parse_cell("-0.5"), Value::Number(Number::from(-0.5f64)));
+        assert_eq!(parse_cell("007"), Value::String("007".to_string().into()));
+        assert_eq!(parse_cell("-01"), Value::String("-01".to_string().into()));
+    }
+
+    #[test]
+    fn test_max_len() {
+        let long = "x".repeat(DEFAULT_MAX_LEN + 1);
+        let rows = parse_csv(&format!("id,name,note,misc\n1,a,,1\n2,{},,b\n", long)).unwrap();
+
+        let mut inference = SchemaInference::new(vec![], Map::new());
+        inference.sample(&rows[0]);
+        inference.measure(&rows[1]);
+
+        let schema = inference.schema().unwrap();
+        let values = schema.primary().values();
+
+        // the length of a row after the sample widens the column
+        assert_eq!(values[0].dtype, ValueType::String);
+        assert_eq!(values[0].max_len, Some(long.len()));
+
+        // an all-null column has a default length
+        assert_eq!(values[1].dtype, ValueType::String);
+        assert_eq!(values[1].max_len, Some(DEFAULT_MAX_LEN));
+
+        // so does a column widened from a number to a string
+        let mut inference = SchemaInference::new(vec![], Map::new());
+        for row in &rows {
+            inference.sample(row);
+        }
+
+        let schema = inference.schema().unwrap();
+        let misc = &schema.primary().values()[2];
+        assert_eq!(misc.dtype, ValueType::String);
+        assert_eq!(misc.max_len, Some(DEFAULT_MAX_LEN));
+    }
+}
@@ -1,5 +1,6 @@
 //! A [`Table`], an ordered collection of [`Row`]s which supports `BTree`-based indexing
 
+use std::collections::HashMap;
 use std::fmt;
 use std::marker::PhantomData;
 
@@ -12,7 +13,7 @@ use safecast::AsType;
 use tc_btree::{BTreeType, Node};
 use tc_error::*;
 use tc_transact::fs::{Dir, File, Hash};
-use tc_transact::{IntoView, Transaction, TxnId};
+use tc_transact::{IntoView, Isolation, Transaction, TxnId};
 use tc_value::Value;
 use tcgeneric::{
     path_label, Class, Id, Instance, NativeClass, PathLabel, PathSegment, TCBoxTryStream, TCPathBuf,
@@ -23,12 +24,23 @@ use view::*;
 
 pub use bounds::*;
 pub use index::TableIndex;
+pub use infer::*;
+pub use privacy::*;
+pub use query::Query;
 pub use schema::*;
-pub use view::Merged;
+pub use stats::IndexStats;
+pub use validate::*;
+pub use view::{Limited, Merged};
 
 mod bounds;
 mod index;
+mod infer;
+mod privacy;
+mod query;
 mod schema;
+mod sketch;
+mod stats;
+mod validate;
 mod view;
 
 /// The key of a [`Table`] row.
@@ -49,6 +61,11 @@ pub trait TableInstance: Instance<Class = TableType> {
 
     /// Return the schema of this `Table`.
     fn schema(&self) -> TableSchema;
+
+    /// Return a handle to this `Table` which reads with the given [`Isolation`] level.
+    ///
+    /// Writes are not affected by the isolation level.
+    fn with_isolation(self, isolation: Isolation) -> Self;
 }
 
 /// [`Table`] sort methods
@@ -185,6 +202,44 @@ pub enum Table<F, D, Txn> {
     TableSlice(view::TableSlice<F, D, Txn>),
 }
 
+impl<F: File<Node>, D: Dir, Txn: Transaction<D>> Table<F, D, Txn>
+where
+    Self: Send + Sync,
+{
+    /// Return a view of this `Table` which hides the given columns, according to their
+    /// [`ColumnMask`].
+    ///
+    /// The masked columns cannot be used to order or slice the returned view or any view of it.
+    pub fn mask(self, masks: HashMap<Id, ColumnMask>) -> TCResult<Self> {
+        if masks.is_empty() {
+            Ok(self)
+        } else {
+            Selection::masked(self, masks).map(Self::from)
+        }
+    }
+
+    /// Estimate the number of distinct (non-null) values of the given `column` of this `Table`,
+    /// using a HyperLogLog sketch.
+    pub async fn count_distinct(self, txn_id: TxnId, column: Id) -> TCResult<u64> {
+        let rows = self.select(vec![column])?.rows(txn_id).await?;
+        let values = rows.map_ok(|mut row| row.pop().unwrap_or_default());
+        sketch::count_distinct(Box::pin(values)).await
+    }
+
+    /// Return a uniform random sample of up to `n` rows of this `Table`, in order.
+    ///
+    /// The same `seed` always selects the same rows from the same `Table`.
+    pub async fn sample(
+        self,
+        txn_id: TxnId,
+        n: usize,
+        seed: Option<u64>,
+    ) -> TCResult<Vec<Vec<Value>>> {
+        let rows = self.rows(txn_id).await?;
+        sketch::sample(rows, n, seed).await
+    }
+}
+
 impl<F, D, Txn> Instance for Table<F, D, Txn>
 where
     Self: Send + Sync,
@@ -243,6 +298,20 @@ where
             Self::TableSlice(slice) => slice.schema(),
         }
     }
+
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        match self {
+            Self::Table(table) => Self::Table(table.with_isolation(isolation)),
+            Self::Index(index) => Self::Index(index.with_isolation(isolation)),
+            Self::IndexSlice(slice) => Self::IndexSlice(slice.with_isolation(isolation)),
+            Self::Limit(limited) => Self::Limit(Box::new(limited.with_isolation(isolation))),
+            Self::Merge(merged) => Self::Merge(merged.with_isolation(isolation)),
+            Self::Selection(selection) => {
+                Self::Selection(Box::new(selection.with_isolation(isolation)))
+            }
+            Self::TableSlice(slice) => Self::TableSlice(slice.with_isolation(isolation)),
+        }
+    }
 }
 
 #[async_trait]
@@ -306,6 +375,7 @@ where
     async fn read(&self, txn_id: &TxnId, key: &Key) -> TCResult<Option<Vec<Value>>> {
         match self {
             Self::Table(table) => table.read(txn_id, key).await,
+            Self::Selection(selection) => selection.read(txn_id, key).await,
             other => Err(TCError::unsupported(format!(
                 "{} does not support GET by key",
                 other
@@ -382,6 +452,7 @@ where
         match self {
             Self::Table(table) => table.slice(bounds).map(Self::from),
             Self::Merge(merge) => merge.slice(bounds).map(Self::from),
+            Self::Selection(selection) => selection.slice(bounds).map(Self::from),
             Self::TableSlice(slice) => slice.slice(bounds).map(Self::from),
             other => Err(TCError::unsupported(format!(
                 "instance of {} does not support slicing",
@@ -394,6 +465,7 @@ where
         match self {
             Self::Table(table) => table.validate_bounds(bounds),
             Self::Merge(merge) => merge.validate_bounds(bounds),
+            Self::Selection(selection) => selection.validate_bounds(bounds),
             Self::TableSlice(slice) => slice.validate_bounds(bounds),
             other => Err(TCError::unsupported(format!(
                 "instance of {} does not support slicing",
@@ -478,7 +550,7 @@ impl<'en, F: File<Node>, D: Dir, Txn: Transaction<D>> IntoView<'en, D> for Table
 
     async fn into_view(self, txn: Txn) -> TCResult<TableView<'en>> {
         let schema = self.schema().clone();
-        let rows = self.rows(*txn.id()).await?;
+        let rows = self.with_isolation(txn.isolation()).rows(*txn.id()).await?;
         Ok(TableView { schema, rows })
     }
 }
@@ -0,0 +1,225 @@
+//! Differentially private aggregates of a `Table`.
+
+use std::fmt;
+
+use rand::Rng;
+use safecast::*;
+
+use tc_error::*;
+use tc_value::{Number, Value};
+use tcgeneric::{Id, TCPathBuf, Tuple};
+
+/// A policy which exposes only noisy aggregates of a `Table` to a reader who lacks its `scope`.
+///
+/// The result of each `count`, `sum`, or `mean` is perturbed with noise drawn from a Laplace
+/// distribution with scale `sensitivity / epsilon`, so that the result reveals (almost) nothing
+/// about any single row. A smaller `epsilon` gives a stronger privacy guarantee and a noisier
+/// result. An aggregate of fewer than `threshold` rows is refused outright.
+#[derive(Clone, Eq, PartialEq)]
+pub struct PrivacyPolicy {
+    /// The scope required to read exact aggregates and individual rows.
+    pub scope: TCPathBuf,
+
+    /// The privacy budget of a single aggregate query.
+    pub epsilon: Number,
+
+    /// The minimum number of rows in an aggregate.
+    pub threshold: u64,
+
+    /// The range to which the values of each column are clamped when summed.
+    ///
+    /// Only these columns can be summed or averaged, since the sensitivity of a sum is unbounded
+    /// unless the range of its values is known in advance.
+    pub bounds: Vec<(Id, Number, Number)>,
+}
+
+impl PrivacyPolicy {
+    /// Return an error if this policy is not valid for a `Table` with the given columns.
+    pub fn validate<'a, I: IntoIterator<Item = &'a Id>>(&self, columns: I) -> TCResult<()> {
+        if self.epsilon() <= 0. || !self.epsilon().is_finite() {
+            return Err(TCError::bad_request(
+                "privacy budget must be a positive number, not",
+                self.epsilon,
+            ));
+        }
+
+        let columns: Vec<&Id> = columns.into_iter().collect();
+        for (column, min, max) in &self.bounds {
+            if !columns.contains(&column) {
+                return Err(TCError::bad_request(
+                    "cannot bound the values of nonexistent column",
+                    column,
+                ));
+            }
+
+            if min > max {
+                return Err(TCError::bad_request(
+                    format!("invalid range for column {}", column),
+                    Tuple::<Number>::from(vec![*min, *max]),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add noise to the given row `count`.
+    pub fn count(&self, count: u64) -> TCResult<Number> {
+        self.check_threshold(count)?;
+
+        let count = count as f64 + laplace(1. / self.epsilon());
+        Ok(Number::from(count.max(0.).round()))
+    }
+
+    /// Construct a new [`PrivateSum`] of the values of the given `column`.
+    pub fn sum(&self, column: &Id) -> TCResult<PrivateSum> {
+        let (_, min, max) = self
+            .bounds
+            .iter()
+            .find(|(name, _, _)| name == column)
+            .ok_or_else(|| {
+                TCError::unauthorized(format!(
+                    "column {} cannot be aggregated without a range for its values",
+                    column
+                ))
+            })?;
+
+        Ok(PrivateSum {
+            policy: self,
+            min: f64::cast_from(*min),
+            max: f64::cast_from(*max),
+            count: 0,
+            sum: 0.,
+        })
+    }
+
+    fn check_threshold(&self, count: u64) -> TCResult<()> {
+        if count < self.threshold {
+            Err(TCError::unauthorized(format!(
+                "cannot aggregate fewer than {} rows",
+                self.threshold
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn epsilon(&self) -> f64 {
+        f64::cast_from(self.epsilon)
+    }
+}
+
+impl TryCastFrom<Value> for PrivacyPolicy {
+    fn can_cast_from(value: &Value) -> bool {
+        value.matches::<(TCPathBuf, Number, u64, Vec<(Id, Number, Number)>)>()
+            || value.matches::<(TCPathBuf, Number, u64)>()
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        if value.matches::<(TCPathBuf, Number, u64, Vec<(Id, Number, Number)>)>() {
+            let (scope, epsilon, threshold, bounds) = value.opt_cast_into()?;
+            Some(Self {
+                scope,
+                epsilon,
+                threshold,
+                bounds,
+            })
+        } else {
+            let (scope, epsilon, threshold) = value.opt_cast_into()?;
+            Some(Self {
+                scope,
+                epsilon,
+                threshold,
+                bounds: vec![],
+            })
+        }
+    }
+}
+
+impl CastFrom<PrivacyPolicy> for Value {
+    fn cast_from(policy: PrivacyPolicy) -> Self {
+        let bounds = policy.bounds.into_iter().map(|(column, min, max)| {
+            Value::Tuple(vec![column.into(), min.into(), max.into()].into())
+        });
+
+        Value::Tuple(
+            vec![
+                policy.scope.into(),
+                policy.epsilon.into(),
+                Number::from(policy.threshold).into(),
+                bounds.collect(),
+            ]
+            .into(),
+        )
+    }
+}
+
+impl fmt::Display for PrivacyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "aggregates of at least {} rows with epsilon {} unless authorized by {}",
+            self.threshold, self.epsilon, self.scope
+        )
+    }
+}
+
+/// A sum of the values of a `Table` column, subject to a [`PrivacyPolicy`].
+pub struct PrivateSum<'a> {
+    policy: &'a PrivacyPolicy,
+    min: f64,
+    max: f64,
+    count: u64,
+    sum: f64,
+}
+
+impl<'a> PrivateSum<'a> {
+    /// Add the given `value` to this sum, clamped to the range of its column.
+    ///
+    /// A `None` value is ignored.
+    pub fn push(&mut self, value: Value) -> TCResult<()> {
+        if value.is_none() {
+            return Ok(());
+        }
+
+        let value = Number::try_cast_from(value, |v| {
+            TCError::bad_request("cannot aggregate a non-numeric value", v)
+        })?;
+
+        self.count += 1;
+        self.sum += f64::cast_from(value).max(self.min).min(self.max);
+        Ok(())
+    }
+
+    /// Add noise to this sum.
+    pub fn sum(self) -> TCResult<Number> {
+        let epsilon = self.policy.epsilon();
+        self.noisy_sum(epsilon).map(Number::from)
+    }
+
+    /// Add noise to the mean of the values in this sum.
+    ///
+    /// The privacy budget is divided equally between the sum and the count of the values.
+    pub fn mean(self) -> TCResult<Number> {
+        let epsilon = self.policy.epsilon() / 2.;
+        let count = self.count as f64 + laplace(1. / epsilon);
+        let (min, max) = (self.min, self.max);
+        let sum = self.noisy_sum(epsilon)?;
+
+        let mean = sum / count.max(1.);
+        Ok(Number::from(mean.max(min).min(max)))
+    }
+
+    fn noisy_sum(&self, epsilon: f64) -> TCResult<f64> {
+        self.policy.check_threshold(self.count)?;
+
+        let sensitivity = self.min.abs().max(self.max.abs());
+        Ok(self.sum + laplace(sensitivity / epsilon))
+    }
+}
+
+/// Draw a sample from the Laplace distribution centered at zero with the given `scale`.
+fn laplace(scale: f64) -> f64 {
+    let u: f64 = rand::thread_rng().gen_range(-0.5..0.5);
+    -scale * u.signum() * (1. - 2. * u.abs()).ln()
+}
@@ -0,0 +1,549 @@
+//! A compact textual query syntax for a `Table`
+
+use std::fmt;
+use std::str::FromStr;
+
+use safecast::CastInto;
+
+use tc_btree::Node;
+use tc_error::*;
+use tc_transact::fs::{Dir, File};
+use tc_transact::Transaction;
+use tc_value::{Bound, Number, Range, Value};
+use tcgeneric::{Id, Tuple};
+
+use super::view::Limited;
+use super::{Bounds, ColumnBound, Table, TableOrder, TableSlice, TableStream};
+
+/// A query over a `Table`, parsed from a string of the form:
+///
+/// `[SELECT <columns>] [WHERE <column> <op> <value> [AND ...]] [GROUP BY <columns>]
+/// [ORDER BY <columns> [ASC | DESC]] [LIMIT <n>] [OFFSET <n>]`
+///
+/// where `<op>` is one of `=`, `<`, `<=`, `>`, or `>=`. String values must be quoted
+/// with single quotes. Keywords are case-insensitive.
+#[derive(Clone, Default)]
+pub struct Query {
+    pub select: Option<Vec<Id>>,
+    pub bounds: Bounds,
+    pub group_by: Option<Vec<Id>>,
+    pub order_by: Option<(Vec<Id>, bool)>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+impl Query {
+    /// Return `true` if this query has a GROUP BY clause.
+    pub fn is_grouped(&self) -> bool {
+        self.group_by.is_some()
+    }
+
+    /// Construct a view of the given `table` which selects the rows matched by this `Query`.
+    ///
+    /// If this query is grouped, the view contains the group columns of each matching row,
+    /// in order, so that equal sequential rows can be aggregated by the caller.
+    pub fn apply<F, D, Txn>(self, table: Table<F, D, Txn>) -> TCResult<Table<F, D, Txn>>
+    where
+        F: File<Node>,
+        D: Dir,
+        Txn: Transaction<D>,
+        Table<F, D, Txn>: Send + Sync,
+    {
+        let mut table = table;
+
+        if !self.bounds.is_empty() {
+            table = table.slice(self.bounds)?;
+        }
+
+        if let Some(group_by) = self.group_by {
+            if self.limit.is_some() || self.offset.is_some() {
+                return Err(TCError::unsupported(
+                    "a Table query cannot combine GROUP BY with LIMIT or OFFSET",
+                ));
+            }
+
+            let reverse = match self.order_by {
+                Some((order, reverse)) if order == group_by => reverse,
+                Some((order, _)) => {
+                    return Err(TCError::bad_request(
+                        "grouped Table query must be ordered by its group columns, not",
+                        Tuple::from(order),
+                    ))
+                }
+                None => false,
+            };
+
+            if let Some(select) = self.select {
+                if select != group_by {
+                    return Err(TCError::bad_request(
+                        "grouped Table query can only select",
+                        Tuple::from(group_by),
+                    ));
+                }
+            }
+
+            let table = table.order_by(group_by.clone(), reverse)?;
+            return table.select(group_by);
+        }
+
+        if let Some((order, reverse)) = self.order_by {
+            table = table.order_by(order, reverse)?;
+        }
+
+        if let Some(select) = self.select {
+            table = table.select(select)?;
+        }
+
+        match (self.limit, self.offset) {
+            (Some(limit), None) => table = table.limit(limit),
+            (limit, Some(offset)) => {
+                table = Limited::page(table, offset, limit.unwrap_or(u64::MAX)).into()
+            }
+            (None, None) => {}
+        }
+
+        Ok(table)
+    }
+}
+
+impl FromStr for Query {
+    type Err = TCError;
+
+    fn from_str(query: &str) -> TCResult<Self> {
+        let tokens = tokenize(query)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+
+        let mut parsed = Query::default();
+
+        if parser.keyword("select") {
+            if parser.symbol("*") {
+                parsed.select = None;
+            } else {
+                parsed.select = Some(parser.columns()?);
+            }
+        }
+
+        if parser.keyword("where") {
+            loop {
+                let (name, bound) = parser.condition()?;
+                let bound = if let Some(existing) = parsed.bounds.remove(&name) {
+                    intersect(&name, existing, bound)?
+                } else {
+                    bound
+                };
+
+                parsed.bounds.insert(name, bound);
+
+                if !parser.keyword("and") {
+                    break;
+                }
+            }
+        }
+
+        if parser.keyword("group") {
+            parser.expect_keyword("by")?;
+            parsed.group_by = Some(parser.columns()?);
+        }
+
+        if parser.keyword("order") {
+            parser.expect_keyword("by")?;
+            let columns = parser.columns()?;
+            let reverse = if parser.keyword("desc") {
+                true
+            } else {
+                parser.keyword("asc");
+                false
+            };
+
+            parsed.order_by = Some((columns, reverse));
+        }
+
+        if parser.keyword("limit") {
+            parsed.limit = Some(parser.count("LIMIT")?);
+        }
+
+        if parser.keyword("offset") {
+            parsed.offset = Some(parser.count("OFFSET")?);
+        }
+
+        if let Some(token) = parser.next() {
+            return Err(TCError::bad_request("unexpected token in query", token));
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[derive(Clone)]
+enum Token {
+    Word(String),
+    Symbol(&'static str),
+    Literal(Value),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Word(word) => f.write_str(word),
+            Self::Symbol(symbol) => f.write_str(symbol),
+            Self::Literal(value) => fmt::Display::fmt(value, f),
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        if token.is_some() {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> TCResult<()> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(TCError::bad_request("query is missing keyword", keyword))
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(s)) if *s == symbol => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn count(&mut self, keyword: &str) -> TCResult<u64> {
+        match self.next() {
+            Some(Token::Literal(Value::Number(n))) => {
+                let count: i64 = (*n).cast_into();
+                if count < 0 || Number::from(count) != *n {
+                    return Err(TCError::bad_request(
+                        format!("invalid query {}", keyword.to_lowercase()),
+                        n,
+                    ));
+                }
+
+                Ok(count as u64)
+            }
+            Some(other) => Err(TCError::bad_request(
+                format!("invalid query {}", keyword.to_lowercase()),
+                other,
+            )),
+            None => Err(TCError::bad_request("query ends with", keyword)),
+        }
+    }
+
+    fn column(&mut self) -> TCResult<Id> {
+        match self.next() {
+            Some(Token::Word(name)) => name.parse(),
+            Some(other) => Err(TCError::bad_request(
+                "expected a column name but found",
+                other,
+            )),
+            None => Err(TCError::bad_request(
+                "expected a column name but found",
+                "end of query",
+            )),
+        }
+    }
+
+    fn columns(&mut self) -> TCResult<Vec<Id>> {
+        let mut columns = vec![self.column()?];
+        while self.symbol(",") {
+            columns.push(self.column()?);
+        }
+
+        Ok(columns)
+    }
+
+    fn condition(&mut self) -> TCResult<(Id, ColumnBound)> {
+        let name = self.column()?;
+
+        let op = match self.next() {
+            Some(Token::Symbol(op)) if *op != "," && *op != "*" => *op,
+            Some(other) => return Err(TCError::bad_request("invalid comparison operator", other)),
+            None => return Err(TCError::bad_request("query ends with column", name)),
+        };
+
+        let value = match self.next() {
+            Some(Token::Literal(value)) => value.clone(),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("null") => Value::None,
+            Some(other) => return Err(TCError::bad_request("invalid value in query", other)),
+            None => return Err(TCError::bad_request("query ends with operator", op)),
+        };
+
+        let bound = match op {
+            "=" => ColumnBound::Is(value),
+            "<" => (Bound::Un, Bound::Ex(value)).into(),
+            "<=" => (Bound::Un, Bound::In(value)).into(),
+            ">" => (Bound::Ex(value), Bound::Un).into(),
+            ">=" => (Bound::In(value), Bound::Un).into(),
+            other => return Err(TCError::bad_request("invalid comparison operator", other)),
+        };
+
+        Ok((name, bound))
+    }
+}
+
+fn intersect(name: &Id, left: ColumnBound, right: ColumnBound) -> TCResult<ColumnBound> {
+    match (left, right) {
+        (ColumnBound::In(left), ColumnBound::In(right)) => {
+            let start = match (left.start, right.start) {
+                (Bound::Un, start) | (start, Bound::Un) => start,
+                _ => return Err(TCError::bad_request("duplicate lower bound for", name)),
+            };
+
+            let end = match (left.end, right.end) {
+                (Bound::Un, end) | (end, Bound::Un) => end,
+                _ => return Err(TCError::bad_request("duplicate upper bound for", name)),
+            };
+
+            Ok(ColumnBound::In(Range { start, end }))
+        }
+        _ => Err(TCError::bad_request(
+            "an equality condition cannot be combined with another condition on",
+            name,
+        )),
+    }
+}
+
+fn tokenize(query: &str) -> TCResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            ',' => tokens.push(Token::Symbol(",")),
+            '*' => tokens.push(Token::Symbol("*")),
+            '=' => tokens.push(Token::Symbol("=")),
+            '<' | '>' => {
+                let symbol = match (c, chars.peek()) {
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    _ => ">",
+                };
+
+                if symbol.len() == 2 {
+                    chars.next();
+                }
+
+                tokens.push(Token::Symbol(symbol));
+            }
+            '\'' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            string.push('\'');
+                        }
+                        Some('\'') => break,
+                        Some(c) => string.push(c),
+                        None => {
+                            return Err(TCError::bad_request(
+                                "unterminated string in query",
+                                string,
+                            ))
+                        }
+                    }
+                }
+
+                tokens.push(Token::Literal(Value::String(string.into())));
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || *c == '.' || *c == '-' || *c == '+' {
+                        number.push(*c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                let number = if let Ok(i) = number.parse::<i64>() {
+                    Number::from(i)
+                } else {
+                    number
+                        .parse::<f64>()
+                        .map(Number::from)
+                        .map_err(|e| TCError::bad_request("invalid number in query", e))?
+                };
+
+                tokens.push(Token::Literal(Value::Number(number)));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.peek() {
+                    if c.is_alphanumeric() || *c == '_' || *c == '-' {
+                        word.push(*c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                if word.eq_ignore_ascii_case("true") {
+                    tokens.push(Token::Literal(true.into()));
+                } else if word.eq_ignore_ascii_case("false") {
+                    tokens.push(Token::Literal(false.into()));
+                } else {
+                    tokens.push(Token::Word(word));
+                }
+            }
+            other => return Err(TCError::bad_request("invalid character in query", other)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> Id {
+        name.parse().unwrap()
+    }
+
+    fn number(n: i64) -> Value {
+        Value::Number(Number::from(n))
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query: Query =
+            "SELECT name, views WHERE views >= 10 AND views < 20 AND name = 'it''s' \
+            ORDER BY views DESC LIMIT 5 OFFSET 10"
+                .parse()
+                .unwrap();
+
+        assert_eq!(query.select, Some(vec![id("name"), id("views")]));
+        assert_eq!(query.group_by, None);
+        assert_eq!(query.order_by, Some((vec![id("views")], true)));
+        assert_eq!(query.limit, Some(5));
+        assert_eq!(query.offset, Some(10));
+        assert!(!query.is_grouped());
+
+        match query.bounds.get(&id("views")) {
+            Some(ColumnBound::In(Range {
+                start: Bound::In(start),
+                end: Bound::Ex(end),
+            })) => {
+                assert_eq!(start, &number(10));
+                assert_eq!(end, &number(20));
+            }
+            _ => panic!("expected the range [10, 20) on views"),
+        }
+
+        match query.bounds.get(&id("name")) {
+            Some(ColumnBound::Is(value)) => {
+                assert_eq!(value, &Value::String("it's".to_string().into()))
+            }
+            _ => panic!("expected an exact bound on name"),
+        }
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let query: Query =
+            "select * where a = null and b < 1 and c <= 2 and d > true and e >= -1.5"
+                .parse()
+                .unwrap();
+
+        assert_eq!(query.select, None);
+        assert_eq!(query.bounds.len(), 5);
+
+        assert!(matches!(
+            query.bounds.get(&id("a")),
+            Some(ColumnBound::Is(Value::None))
+        ));
+
+        assert!(matches!(
+            query.bounds.get(&id("b")),
+            Some(ColumnBound::In(Range { start: Bound::Un, end: Bound::Ex(end) })) if end == &number(1)
+        ));
+
+        assert!(matches!(
+            query.bounds.get(&id("c")),
+            Some(ColumnBound::In(Range { start: Bound::Un, end: Bound::In(end) })) if end == &number(2)
+        ));
+
+        assert!(matches!(
+            query.bounds.get(&id("d")),
+            Some(ColumnBound::In(Range { start: Bound::Ex(start), end: Bound::Un })) if start == &Value::from(true)
+        ));
+
+        assert!(matches!(
+            query.bounds.get(&id("e")),
+            Some(ColumnBound::In(Range { start: Bound::In(Value::Number(start)), end: Bound::Un })) if start == &Number::from(-1.5f64)
+        ));
+    }
+
+    #[test]
+    fn test_parse_group_by() {
+        let query: Query = "SELECT country GROUP BY country ORDER BY country"
+            .parse()
+            .unwrap();
+
+        assert!(query.is_grouped());
+        assert_eq!(query.select, Some(vec![id("country")]));
+        assert_eq!(query.group_by, Some(vec![id("country")]));
+        assert_eq!(query.order_by, Some((vec![id("country")], false)));
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        let malformed = [
+            "SELECT",
+            "SELECT name,",
+            "SELECT name extra",
+            "SELECT name;",
+            "WHERE views",
+            "WHERE views >=",
+            "WHERE views != 1",
+            "WHERE views = views",
+            "WHERE name = 'unterminated",
+            "WHERE views = 1 AND views > 2",
+            "WHERE views > 1 AND views > 2",
+            "WHERE views < 1 AND views <= 2",
+            "GROUP name",
+            "ORDER name",
+            "LIMIT",
+            "LIMIT -1",
+            "LIMIT 1.5",
+            "OFFSET 'one'",
+        ];
+
+        for query in malformed.iter() {
+            assert!(query.parse::<Query>().is_err(), "parsed {}", query);
+        }
+    }
+}
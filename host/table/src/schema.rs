@@ -8,10 +8,10 @@ use futures::TryFutureExt;
 use safecast::*;
 
 use tc_error::*;
-use tc_value::{Value, ValueType};
-use tcgeneric::{Id, Map, Tuple};
+use tc_value::{Link, Value, ValueType};
+use tcgeneric::{label, Id, Label, Map, TCPathBuf, Tuple};
 
-use super::{Key, Values};
+use super::{Key, PrivacyPolicy, ValidationRule, Values};
 
 pub use tc_btree::Column;
 
@@ -414,11 +414,152 @@ impl fmt::Display for IndexSchema {
     }
 }
 
+/// How a column is hidden from a reader who lacks the scope required to read it.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum ColumnMask {
+    /// The column is present, but every value in it is `None`.
+    Mask,
+
+    /// The column is left out of the row entirely.
+    Omit,
+}
+
+impl TryCastFrom<Value> for ColumnMask {
+    fn can_cast_from(value: &Value) -> bool {
+        Self::opt_cast_from(value.clone()).is_some()
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        let mask = Id::opt_cast_from(value)?;
+        match mask.as_str() {
+            "mask" => Some(Self::Mask),
+            "omit" => Some(Self::Omit),
+            _ => None,
+        }
+    }
+}
+
+impl CastFrom<ColumnMask> for Value {
+    fn cast_from(mask: ColumnMask) -> Self {
+        match mask {
+            ColumnMask::Mask => Value::Id(label("mask").into()),
+            ColumnMask::Omit => Value::Id(label("omit").into()),
+        }
+    }
+}
+
+impl fmt::Display for ColumnMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Mask => f.write_str("mask"),
+            Self::Omit => f.write_str("omit"),
+        }
+    }
+}
+
+/// A rule which restricts reads of a `Table` column to the holders of an auth `scope`.
+#[derive(Clone, Eq, PartialEq)]
+pub struct ColumnAccess {
+    /// The name of the restricted column.
+    pub column: Id,
+
+    /// The scope required to read the column.
+    pub scope: TCPathBuf,
+
+    /// How the column appears to a reader who lacks the required scope.
+    pub mask: ColumnMask,
+}
+
+impl TryCastFrom<Value> for ColumnAccess {
+    fn can_cast_from(value: &Value) -> bool {
+        value.matches::<(Id, TCPathBuf, ColumnMask)>()
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        let (column, scope, mask) = value.opt_cast_into()?;
+        Some(Self {
+            column,
+            scope,
+            mask,
+        })
+    }
+}
+
+impl CastFrom<ColumnAccess> for Value {
+    fn cast_from(access: ColumnAccess) -> Self {
+        Value::Tuple(
+            vec![
+                access.column.into(),
+                access.scope.into(),
+                access.mask.cast_into(),
+            ]
+            .into(),
+        )
+    }
+}
+
+impl fmt::Display for ColumnAccess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} requires {} ({})", self.column, self.scope, self.mask)
+    }
+}
+
+/// A change to the columns of a `Table`.
+#[derive(Clone)]
+pub enum Alter {
+    /// Add a value column, filling in the given default value for each existing row.
+    Add(Column, Value),
+
+    /// Drop a value column.
+    Drop(Id),
+
+    /// Rename a column.
+    Rename(Id, Id),
+}
+
+impl Alter {
+    /// Apply this change to a single `row` of a `Table`.
+    pub fn apply(&self, row: &mut Row) {
+        match self {
+            Self::Add(column, default) => {
+                row.insert(column.name.clone(), default.clone());
+            }
+            Self::Drop(name) => {
+                row.remove(name);
+            }
+            Self::Rename(from, to) => {
+                if let Some(value) = row.remove(from) {
+                    row.insert(to.clone(), value);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Alter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Add(column, default) => {
+                write!(f, "add column {} with default {}", column.name, default)
+            }
+            Self::Drop(name) => write!(f, "drop column {}", name),
+            Self::Rename(from, to) => write!(f, "rename column {} to {}", from, to),
+        }
+    }
+}
+
 /// The schema of a `Table`.
 #[derive(Clone, Eq, PartialEq)]
 pub struct TableSchema {
     primary: IndexSchema,
     indices: Vec<(Id, Vec<Id>)>,
+    access: Vec<ColumnAccess>,
+    privacy: Option<PrivacyPolicy>,
+    validation: Vec<ValidationRule>,
+    unique: Vec<Id>,
+    references: Vec<(Id, Link)>,
+    defaults: Vec<(Id, Value)>,
+    not_null: Vec<Id>,
 }
 
 impl TableSchema {
@@ -427,7 +568,286 @@ impl TableSchema {
         Self {
             primary,
             indices: indices.into_iter().collect(),
+            access: vec![],
+            privacy: None,
+            validation: vec![],
+            unique: vec![],
+            references: vec![],
+            defaults: vec![],
+            not_null: vec![],
+        }
+    }
+
+    /// Restrict reads of the columns of this schema according to the given [`ColumnAccess`] rules.
+    pub fn with_access<I: IntoIterator<Item = ColumnAccess>>(mut self, access: I) -> Self {
+        self.access.extend(access);
+        self
+    }
+
+    /// Return the [`ColumnAccess`] rules of this schema.
+    pub fn access(&self) -> &[ColumnAccess] {
+        &self.access
+    }
+
+    /// Expose only noisy aggregates of this table to readers who lack the scope of the given
+    /// [`PrivacyPolicy`].
+    pub fn with_privacy(mut self, policy: PrivacyPolicy) -> Self {
+        self.privacy = Some(policy);
+        self
+    }
+
+    /// Return the [`PrivacyPolicy`] of this schema, if any.
+    pub fn privacy(&self) -> Option<&PrivacyPolicy> {
+        self.privacy.as_ref()
+    }
+
+    /// Require each row ingested in bulk to satisfy the given [`ValidationRule`]s.
+    pub fn with_validation<I: IntoIterator<Item = ValidationRule>>(
+        mut self,
+        rules: I,
+    ) -> TCResult<Self> {
+        for rule in rules {
+            for column in rule.columns() {
+                if !self.primary.column_names().any(|name| name == column) {
+                    return Err(TCError::not_found(format!("column {} to validate", column)));
+                }
+            }
+
+            self.validation.push(rule);
+        }
+
+        Ok(self)
+    }
+
+    /// Return the [`ValidationRule`]s of this schema.
+    pub fn validation(&self) -> &[ValidationRule] {
+        &self.validation
+    }
+
+    /// Require the (non-`None`) values of the columns of the given index to be unique.
+    pub fn with_unique(mut self, index: Id) -> TCResult<Self> {
+        self.index_columns(&index)?;
+
+        if !self.unique.contains(&index) {
+            self.unique.push(index);
+        }
+
+        Ok(self)
+    }
+
+    /// Return the names of the indices whose (non-`None`) values must be unique.
+    pub fn unique(&self) -> &[Id] {
+        &self.unique
+    }
+
+    /// Require the (non-`None`) values of the columns of the given index to be the key of a row
+    /// in the `Table` at the given [`Link`].
+    pub fn with_reference(mut self, index: Id, table: Link) -> TCResult<Self> {
+        self.index_columns(&index)?;
+        self.references.retain(|(name, _)| name != &index);
+        self.references.push((index, table));
+        Ok(self)
+    }
+
+    /// Return the names of the indices which reference another `Table`, and a [`Link`] to each.
+    pub fn references(&self) -> &[(Id, Link)] {
+        &self.references
+    }
+
+    /// Fill in the given `default` value for the given value `column` when a row is inserted
+    /// without it.
+    pub fn with_default(mut self, column: Id, default: Value) -> TCResult<Self> {
+        let dtype = self
+            .primary
+            .values
+            .iter()
+            .find(|col| col.name == column)
+            .map(|col| col.dtype)
+            .ok_or_else(|| TCError::not_found(format!("value column {}", column)))?;
+
+        let default = dtype.try_cast(default)?;
+        self.defaults.retain(|(name, _)| name != &column);
+        if !default.is_none() {
+            self.defaults.push((column, default));
+        }
+
+        Ok(self)
+    }
+
+    /// Return the default value of each column which has one.
+    pub fn defaults(&self) -> &[(Id, Value)] {
+        &self.defaults
+    }
+
+    /// Reject any write of `Value::None` to the given `column`.
+    pub fn with_not_null(mut self, column: Id) -> TCResult<Self> {
+        if !self.primary.column_names().any(|name| name == &column) {
+            return Err(TCError::not_found(format!("column {}", column)));
+        }
+
+        if !self.not_null.contains(&column) {
+            self.not_null.push(column);
+        }
+
+        Ok(self)
+    }
+
+    /// Return the names of the columns which do not allow `Value::None`.
+    pub fn not_null(&self) -> &[Id] {
+        &self.not_null
+    }
+
+    /// Given the values of a row to insert, fill in the default value of any missing columns.
+    pub fn values_with_defaults(&self, mut values: Values) -> TCResult<Values> {
+        for col in self.primary.values.iter().skip(values.len()) {
+            let default = self
+                .defaults
+                .iter()
+                .find(|(name, _)| name == &col.name)
+                .map(|(_, default)| default.clone())
+                .ok_or_else(|| {
+                    TCError::bad_request("missing value for column with no default", &col.name)
+                })?;
+
+            values.push(default);
+        }
+
+        Ok(values)
+    }
+
+    /// Return an error if the given (possibly partial) `row` has a `Value::None` in a column
+    /// which does not allow it.
+    pub fn validate_not_null(&self, row: &Row) -> TCResult<()> {
+        for column in &self.not_null {
+            if let Some(Value::None) = row.get(column) {
+                return Err(TCError::bad_request(
+                    "cannot write None to NOT NULL column",
+                    column,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the names of the columns of the index with the given `name`.
+    pub fn index_columns(&self, name: &Id) -> TCResult<&[Id]> {
+        self.indices
+            .iter()
+            .find(|(index, _)| index == name)
+            .map(|(_, columns)| &columns[..])
+            .ok_or_else(|| TCError::not_found(format!("index {}", name)))
+    }
+
+    /// Return a copy of this schema with the given [`Alter`] change applied.
+    ///
+    /// A key column cannot be added or dropped, and a column cannot be dropped while an index
+    /// or a validation rule depends on it. Renaming a column also renames it in each index and rule.
+    pub fn alter(&self, alter: &Alter) -> TCResult<Self> {
+        let exists = |name: &Id| self.primary.column_names().any(|col| col == name);
+
+        let mut altered = self.clone();
+
+        match alter {
+            Alter::Add(column, default) => {
+                if exists(&column.name) {
+                    return Err(TCError::bad_request(
+                        "table already has a column named",
+                        &column.name,
+                    ));
+                }
+
+                if !default.is_none() {
+                    column.dtype.try_cast(default.clone())?;
+                }
+
+                altered.primary.values.push(column.clone());
+            }
+            Alter::Drop(name) => {
+                if self.primary.key.iter().any(|col| &col.name == name) {
+                    return Err(TCError::bad_request("cannot drop key column", name));
+                }
+
+                if !exists(name) {
+                    return Err(TCError::not_found(format!("column {}", name)));
+                }
+
+                if let Some((index, _)) = self.indices.iter().find(|(_, cols)| cols.contains(name))
+                {
+                    return Err(TCError::bad_request(
+                        format!("cannot drop column {} which is indexed by", name),
+                        index,
+                    ));
+                }
+
+                if self
+                    .validation
+                    .iter()
+                    .any(|rule| rule.columns().contains(&name))
+                {
+                    return Err(TCError::bad_request(
+                        "cannot drop a column with a validation rule",
+                        name,
+                    ));
+                }
+
+                altered.primary.values.retain(|col| &col.name != name);
+                altered.access.retain(|rule| &rule.column != name);
+                altered.defaults.retain(|(col, _)| col != name);
+                altered.not_null.retain(|col| col != name);
+                if let Some(privacy) = &mut altered.privacy {
+                    privacy.bounds.retain(|(col, _, _)| col != name);
+                }
+            }
+            Alter::Rename(from, to) => {
+                if !exists(from) {
+                    return Err(TCError::not_found(format!("column {}", from)));
+                } else if exists(to) {
+                    return Err(TCError::bad_request("table already has a column named", to));
+                }
+
+                let rename = |name: &mut Id| {
+                    if name == from {
+                        *name = to.clone();
+                    }
+                };
+
+                for col in altered
+                    .primary
+                    .key
+                    .iter_mut()
+                    .chain(altered.primary.values.iter_mut())
+                {
+                    rename(&mut col.name);
+                }
+
+                for (_, cols) in &mut altered.indices {
+                    cols.iter_mut().for_each(rename);
+                }
+
+                for rule in &mut altered.access {
+                    rename(&mut rule.column);
+                }
+
+                for (col, _) in &mut altered.defaults {
+                    rename(col);
+                }
+
+                altered.not_null.iter_mut().for_each(rename);
+
+                for rule in &mut altered.validation {
+                    rule.rename(from, to);
+                }
+
+                if let Some(privacy) = &mut altered.privacy {
+                    for (col, _, _) in &mut privacy.bounds {
+                        rename(col);
+                    }
+                }
+            }
         }
+
+        Ok(altered)
     }
 
     /// Return a list of index names and the names of the columns they index.
@@ -446,58 +866,219 @@ impl de::FromStream for TableSchema {
     type Context = ();
 
     async fn from_stream<D: de::Decoder>(cxt: (), decoder: &mut D) -> Result<Self, D::Error> {
-        de::FromStream::from_stream(cxt, decoder)
-            .map_ok(|(primary, indices)| Self { primary, indices })
-            .await
+        let value: Value = de::FromStream::from_stream(cxt, decoder).await?;
+        Self::try_cast_from(value, |v| de::Error::invalid_value(v, "a Table schema"))
     }
 }
 
 impl<'en> en::IntoStream<'en> for TableSchema {
     fn into_stream<E: en::Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
-        (self.primary, self.indices).into_stream(encoder)
+        Value::cast_from(self).into_stream(encoder)
     }
 }
 
 impl From<IndexSchema> for TableSchema {
     fn from(schema: IndexSchema) -> TableSchema {
-        TableSchema {
-            primary: schema,
-            indices: vec![],
-        }
+        TableSchema::new(schema, vec![])
     }
 }
 
+const PRIMARY: Label = label("primary");
+const INDICES: Label = label("indices");
+const ACCESS: Label = label("access");
+const PRIVACY: Label = label("privacy");
+const VALIDATION: Label = label("validation");
+const UNIQUE: Label = label("unique");
+const REFERENCES: Label = label("references");
+const DEFAULTS: Label = label("defaults");
+const NOT_NULL: Label = label("not_null");
+
+/// A `TableSchema` can be cast from a [`Value`] in any of these forms:
+///  - an [`IndexSchema`], for a `Table` with no other indices
+///  - a tuple `(primary, indices)` of an [`IndexSchema`] and a list of `(name, columns)` indices
+///  - a keyed map, i.e. a tuple of `(name, value)` pairs, with a required `primary` entry and
+///    optional `indices`, `access`, `privacy`, `validation`, `unique`, `references`, `defaults`,
+///    and `not_null` entries
 impl TryCastFrom<Value> for TableSchema {
     fn can_cast_from(value: &Value) -> bool {
-        value.matches::<(IndexSchema, Vec<(Id, Vec<Id>)>)>() || value.matches::<IndexSchema>()
+        if value.matches::<IndexSchema>() || value.matches::<(IndexSchema, Vec<(Id, Vec<Id>)>)>() {
+            true
+        } else {
+            Self::opt_cast_from(value.clone()).is_some()
+        }
     }
 
     fn opt_cast_from(value: Value) -> Option<TableSchema> {
-        if value.matches::<(IndexSchema, Vec<(Id, Vec<Id>)>)>() {
+        if value.matches::<IndexSchema>() {
+            let primary: IndexSchema = value.opt_cast_into().unwrap();
+            Some(TableSchema::from(primary))
+        } else if value.matches::<(IndexSchema, Vec<(Id, Vec<Id>)>)>() {
             let (primary, indices): (IndexSchema, Vec<(Id, Vec<Id>)>) =
                 value.opt_cast_into().unwrap();
 
-            let indices = indices.into_iter().collect();
-            Some(TableSchema { primary, indices })
-        } else if value.matches::<IndexSchema>() {
-            let primary = value.opt_cast_into().unwrap();
-            let indices = vec![];
-            Some(TableSchema { primary, indices })
+            Some(TableSchema::new(primary, indices))
         } else {
-            None
+            let entries: Vec<(Id, Value)> = value.opt_cast_into()?;
+            Self::from_entries(entries).ok()
+        }
+    }
+}
+
+impl TableSchema {
+    fn from_entries(entries: Vec<(Id, Value)>) -> TCResult<TableSchema> {
+        fn cast<T: TryCastFrom<Value>>(name: &Id, value: Value) -> TCResult<T> {
+            T::try_cast_from(value, |v| {
+                TCError::bad_request(format!("invalid {} of Table schema", name), v)
+            })
+        }
+
+        let mut entries: HashMap<Id, Value> = entries.into_iter().collect();
+        let mut take = |name: Label| entries.remove(&Id::from(name));
+
+        let primary = take(PRIMARY)
+            .ok_or_else(|| TCError::bad_request("Table schema is missing", Id::from(PRIMARY)))?;
+
+        let primary: IndexSchema = cast(&PRIMARY.into(), primary)?;
+
+        let indices: Vec<(Id, Vec<Id>)> = match take(INDICES) {
+            Some(indices) => cast(&INDICES.into(), indices)?,
+            None => vec![],
+        };
+
+        let mut schema = TableSchema::new(primary, indices);
+
+        if let Some(access) = take(ACCESS) {
+            let access: Vec<ColumnAccess> = cast(&ACCESS.into(), access)?;
+            schema = schema.with_access(access);
+        }
+
+        if let Some(privacy) = take(PRIVACY) {
+            schema = schema.with_privacy(cast(&PRIVACY.into(), privacy)?);
+        }
+
+        if let Some(validation) = take(VALIDATION) {
+            let validation: Vec<ValidationRule> = cast(&VALIDATION.into(), validation)?;
+            schema = schema.with_validation(validation)?;
+        }
+
+        if let Some(unique) = take(UNIQUE) {
+            let unique: Vec<Id> = cast(&UNIQUE.into(), unique)?;
+            for index in unique {
+                schema = schema.with_unique(index)?;
+            }
+        }
+
+        if let Some(references) = take(REFERENCES) {
+            let references: Vec<(Id, Link)> = cast(&REFERENCES.into(), references)?;
+            for (index, table) in references {
+                schema = schema.with_reference(index, table)?;
+            }
+        }
+
+        if let Some(defaults) = take(DEFAULTS) {
+            let defaults: Vec<(Id, Value)> = cast(&DEFAULTS.into(), defaults)?;
+            for (column, default) in defaults {
+                schema = schema.with_default(column, default)?;
+            }
+        }
+
+        if let Some(not_null) = take(NOT_NULL) {
+            let not_null: Vec<Id> = cast(&NOT_NULL.into(), not_null)?;
+            for column in not_null {
+                schema = schema.with_not_null(column)?;
+            }
         }
+
+        if let Some(name) = entries.keys().next() {
+            return Err(TCError::bad_request(
+                "unrecognized Table schema option",
+                name,
+            ));
+        }
+
+        Ok(schema)
+    }
+
+    fn is_basic(&self) -> bool {
+        self.access.is_empty()
+            && self.privacy.is_none()
+            && self.validation.is_empty()
+            && self.unique.is_empty()
+            && self.references.is_empty()
+            && self.defaults.is_empty()
+            && self.not_null.is_empty()
     }
 }
 
+/// A `TableSchema` with only a primary index and other indices is cast into the tuple
+/// `(primary, indices)`; any other `TableSchema` is cast into a keyed map of its options.
 impl CastFrom<TableSchema> for Value {
     fn cast_from(schema: TableSchema) -> Self {
+        fn entry<V: Into<Value>>(name: Label, value: V) -> Value {
+            Value::Tuple(vec![Value::from(Id::from(name)), value.into()].into())
+        }
+
+        fn pair<V: Into<Value>>(name: Id, value: V) -> Value {
+            Value::Tuple(vec![Value::from(name), value.into()].into())
+        }
+
+        let is_basic = schema.is_basic();
+
         let indices = schema
             .indices
             .into_iter()
-            .map(|(id, col_names)| (Value::from(id), Tuple::<Value>::from_iter(col_names)))
-            .map(|(id, col_names)| Value::Tuple(vec![id, col_names.into()].into()));
+            .map(|(name, columns)| pair(name, Value::from_iter(columns)));
+
+        let primary = Value::cast_from(schema.primary);
+        let indices = Value::from_iter(indices);
+
+        if is_basic {
+            return Value::Tuple(vec![primary, indices].into());
+        }
+
+        let mut entries = vec![entry(PRIMARY, primary), entry(INDICES, indices)];
+
+        if !schema.access.is_empty() {
+            let access = schema.access.into_iter().map(Value::cast_from);
+            entries.push(entry(ACCESS, Value::from_iter(access)));
+        }
+
+        if let Some(privacy) = schema.privacy {
+            entries.push(entry(PRIVACY, Value::cast_from(privacy)));
+        }
+
+        if !schema.validation.is_empty() {
+            let validation = schema.validation.into_iter().map(Value::cast_from);
+            entries.push(entry(VALIDATION, Value::from_iter(validation)));
+        }
 
-        Self::Tuple(vec![schema.primary.cast_into(), Value::from_iter(indices)].into())
+        if !schema.unique.is_empty() {
+            entries.push(entry(UNIQUE, Value::from_iter(schema.unique)));
+        }
+
+        if !schema.references.is_empty() {
+            let references = schema
+                .references
+                .into_iter()
+                .map(|(index, table)| pair(index, table));
+
+            entries.push(entry(REFERENCES, Value::from_iter(references)));
+        }
+
+        if !schema.defaults.is_empty() {
+            let defaults = schema
+                .defaults
+                .into_iter()
+                .map(|(column, default)| pair(column, default));
+
+            entries.push(entry(DEFAULTS, Value::from_iter(defaults)));
+        }
+
+        if !schema.not_null.is_empty() {
+            entries.push(entry(NOT_NULL, Value::from_iter(schema.not_null)));
+        }
+
+        Value::Tuple(entries.into())
     }
 }
 
@@ -507,7 +1088,43 @@ impl fmt::Display for TableSchema {
         if !self.indices.is_empty() {
             writeln!(f, "indices:")?;
             for (name, columns) in &self.indices {
-                writeln!(f, "{}: {}", name, Tuple::<&Id>::from_iter(columns))?;
+                write!(f, "{}: {}", name, Tuple::<&Id>::from_iter(columns))?;
+
+                if self.unique.contains(name) {
+                    write!(f, " (unique)")?;
+                }
+
+                if let Some((_, table)) = self.references.iter().find(|(index, _)| index == name) {
+                    write!(f, " references {}", table)?;
+                }
+
+                writeln!(f)?;
+            }
+        }
+
+        if !self.access.is_empty() {
+            writeln!(f, "access:")?;
+            for access in &self.access {
+                writeln!(f, "{}", access)?;
+            }
+        }
+
+        if let Some(privacy) = &self.privacy {
+            writeln!(f, "privacy: {}", privacy)?;
+        }
+
+        for (column, default) in &self.defaults {
+            writeln!(f, "{} defaults to {}", column, default)?;
+        }
+
+        for column in &self.not_null {
+            writeln!(f, "{} is NOT NULL", column)?;
+        }
+
+        if !self.validation.is_empty() {
+            writeln!(f, "validation:")?;
+            for rule in &self.validation {
+                writeln!(f, "{}", rule)?;
             }
         }
 
@@ -0,0 +1,134 @@
+//! Approximate summaries of the rows of a `Table`, computed in a single pass over a stream
+//! in bounded memory.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+use futures::stream::TryStreamExt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use tc_error::*;
+use tc_value::Value;
+use tcgeneric::TCBoxTryStream;
+
+// the precision of the HyperLogLog sketch used to count distinct values
+const PRECISION: u32 = 14;
+
+/// A HyperLogLog sketch, which estimates the number of distinct values inserted into it with a
+/// relative standard error of about `1.04 / sqrt(2^precision)`.
+#[derive(Clone)]
+pub(crate) struct HyperLogLog {
+    precision: u32,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Construct a new, empty sketch with `2^precision` registers.
+    pub fn new(precision: u32) -> Self {
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Insert the given `hash` of a value into this sketch.
+    pub fn insert(&mut self, hash: u64) {
+        let register = (hash >> (64 - self.precision)) as usize;
+        let sentinel = 1 << (self.precision - 1);
+        let rank = ((hash << self.precision) | sentinel).leading_zeros() + 1;
+        self.registers[register] = Ord::max(self.registers[register], rank as u8);
+    }
+
+    /// Merge the given sketch, with the same precision, into this one.
+    pub fn merge(&mut self, other: &Self) {
+        debug_assert_eq!(self.precision, other.precision);
+
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = Ord::max(*register, *other);
+        }
+    }
+
+    /// Estimate the number of distinct values inserted into this sketch.
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1. + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        let estimate = if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for a small number of distinct values
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        };
+
+        estimate.round() as u64
+    }
+}
+
+/// Estimate the number of distinct values in the given stream using a HyperLogLog sketch.
+///
+/// `None` values are not counted. The estimate has a relative standard error of about 0.8%, and
+/// is exact (with high probability) for small numbers of distinct values.
+pub async fn count_distinct<'a>(values: TCBoxTryStream<'a, Value>) -> TCResult<u64> {
+    let sketch = values
+        .try_fold(HyperLogLog::new(PRECISION), |mut sketch, value| {
+            if !value.is_none() {
+                let mut hasher = DefaultHasher::new();
+                hash_value(&mut hasher, &value);
+                sketch.insert(hasher.finish());
+            }
+
+            futures::future::ready(Ok(sketch))
+        })
+        .await?;
+
+    Ok(sketch.estimate())
+}
+
+/// Select a uniform random sample of up to `n` of the given `rows` using reservoir sampling,
+/// preserving their order.
+///
+/// The same `seed` always selects the same sample from the same sequence of rows.
+pub async fn sample<'a>(
+    rows: TCBoxTryStream<'a, Vec<Value>>,
+    n: usize,
+    seed: Option<u64>,
+) -> TCResult<Vec<Vec<Value>>> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let (mut reservoir, _) = rows
+        .try_fold(
+            (Vec::<(u64, Vec<Value>)>::new(), 0u64),
+            |(mut reservoir, seen), row| {
+                if reservoir.len() < n {
+                    reservoir.push((seen, row));
+                } else {
+                    let i = rng.gen_range(0..=seen);
+                    if i < n as u64 {
+                        reservoir[i as usize] = (seen, row);
+                    }
+                }
+
+                futures::future::ready(Ok((reservoir, seen + 1)))
+            },
+        )
+        .await?;
+
+    reservoir.sort_by_key(|(i, _)| *i);
+    Ok(reservoir.into_iter().map(|(_, row)| row).collect())
+}
+
+/// Write the given `value` to the given `hasher`, such that a sequence of values written in
+/// order hashes differently than their concatenation.
+///
+/// The resulting hash is only stable for the lifetime of this process.
+pub(crate) fn hash_value<H: Hasher>(hasher: &mut H, value: &Value) {
+    hasher.write(value.to_string().as_bytes());
+    hasher.write_u8(0xff);
+}
@@ -0,0 +1,279 @@
+//! Statistics about the rows of each `Index` of a `Table`, used to choose the cheapest index to
+//! slice when more than one supports the requested bounds.
+//!
+//! Statistics are accumulated as rows are written, separately for each transaction, and only
+//! merged into the statistics of an index when the transaction commits. They are not persisted,
+//! so the statistics of an index loaded from disk are incomplete, and are not used to choose
+//! between indices.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Mutex;
+
+use tc_transact::TxnId;
+use tc_value::Value;
+
+use super::sketch::{hash_value, HyperLogLog};
+use super::{Bounds, Column, ColumnBound};
+
+// the precision of the sketch of each key prefix, for a relative error of about 3%
+const PRECISION: u32 = 10;
+
+// the assumed fraction of the rows of an index which are within a range bound
+const RANGE_SELECTIVITY: f64 = 1. / 3.;
+
+/// Statistics about the rows of an `Index`: its row count, and a sketch of the number of distinct
+/// values of each prefix of its columns.
+#[derive(Clone)]
+pub struct IndexStats {
+    complete: bool,
+    rows: i64,
+    prefixes: Vec<HyperLogLog>,
+}
+
+impl IndexStats {
+    fn new(columns: usize) -> Self {
+        Self {
+            complete: true,
+            rows: 0,
+            prefixes: vec![HyperLogLog::new(PRECISION); columns],
+        }
+    }
+
+    /// Return `false` if these statistics may not account for every row of the index,
+    /// e.g. because it was loaded from disk.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The number of rows in the index.
+    pub fn rows(&self) -> u64 {
+        Ord::max(self.rows, 0) as u64
+    }
+
+    /// The estimated number of distinct values of the first `len` columns of the index.
+    pub fn distinct(&self, len: usize) -> u64 {
+        if len == 0 {
+            Ord::min(self.rows(), 1)
+        } else {
+            self.prefixes[len - 1].estimate()
+        }
+    }
+
+    /// Estimate the number of rows of an index with the given `columns` within the given
+    /// `bounds`, which must be supported by the index.
+    pub fn estimate(&self, columns: &[Column], bounds: &Bounds) -> f64 {
+        let mut prefix = 0;
+        let mut range = false;
+        for column in columns {
+            match bounds.get(column.name()) {
+                Some(ColumnBound::Is(_)) => prefix += 1,
+                Some(ColumnBound::In(_)) => {
+                    range = true;
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        let mut rows = self.rows() as f64;
+        if prefix > 0 {
+            rows /= Ord::max(self.distinct(prefix), 1) as f64;
+        }
+
+        if range {
+            rows *= RANGE_SELECTIVITY;
+        }
+
+        rows
+    }
+
+    fn insert(&mut self, key: &[Value]) {
+        let mut hasher = DefaultHasher::new();
+        for (value, sketch) in key.iter().zip(&mut self.prefixes) {
+            hash_value(&mut hasher, value);
+            sketch.insert(hasher.finish());
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.rows += other.rows;
+
+        for (sketch, other) in self.prefixes.iter_mut().zip(&other.prefixes) {
+            sketch.merge(other);
+        }
+    }
+}
+
+struct Pending {
+    replace: bool,
+    delta: IndexStats,
+}
+
+/// The committed [`IndexStats`] of an `Index`, and the changes pending in each transaction.
+pub(crate) struct Statistics {
+    columns: usize,
+    committed: Mutex<IndexStats>,
+    pending: Mutex<HashMap<TxnId, Pending>>,
+}
+
+impl Statistics {
+    pub fn new(columns: usize) -> Self {
+        Self {
+            columns,
+            committed: Mutex::new(IndexStats::new(columns)),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Construct the incomplete statistics of an existing index, whose rows are not counted.
+    pub fn load(columns: usize) -> Self {
+        let mut committed = IndexStats::new(columns);
+        committed.complete = false;
+
+        Self {
+            columns,
+            committed: Mutex::new(committed),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return a copy of the committed statistics.
+    pub fn committed(&self) -> IndexStats {
+        self.committed.lock().expect("index stats").clone()
+    }
+
+    /// Record the insertion of the given `key`, as a new row unless the row `exists` already.
+    pub fn insert(&self, txn_id: TxnId, key: &[Value], exists: bool) {
+        self.update(txn_id, |delta| {
+            if !exists {
+                delta.rows += 1;
+            }
+
+            delta.insert(key);
+        })
+    }
+
+    /// Record the deletion of an existing row.
+    pub fn delete(&self, txn_id: TxnId) {
+        self.update(txn_id, |delta| delta.rows -= 1)
+    }
+
+    /// Replace these statistics with the committed statistics of the given `backup` when the
+    /// given transaction commits.
+    pub fn restore(&self, backup: &Self, txn_id: TxnId) {
+        let delta = backup.committed();
+        let mut pending = self.pending.lock().expect("pending index stats");
+        pending.insert(
+            txn_id,
+            Pending {
+                replace: true,
+                delta,
+            },
+        );
+    }
+
+    pub fn commit(&self, txn_id: &TxnId) {
+        let pending = self
+            .pending
+            .lock()
+            .expect("pending index stats")
+            .remove(txn_id);
+
+        if let Some(Pending { replace, delta }) = pending {
+            let mut committed = self.committed.lock().expect("index stats");
+            if replace {
+                *committed = delta;
+            } else {
+                committed.merge(&delta);
+            }
+        }
+    }
+
+    pub fn finalize(&self, txn_id: &TxnId) {
+        self.pending
+            .lock()
+            .expect("pending index stats")
+            .remove(txn_id);
+    }
+
+    fn update<U: FnOnce(&mut IndexStats)>(&self, txn_id: TxnId, update: U) {
+        let mut pending = self.pending.lock().expect("pending index stats");
+        let pending = pending.entry(txn_id).or_insert_with(|| Pending {
+            replace: false,
+            delta: IndexStats::new(self.columns),
+        });
+
+        update(&mut pending.delta);
+    }
+}
+
+/// Return the candidate index with the fewest estimated rows to read, or the first candidate if
+/// there is no estimate for any of them (because its statistics are incomplete).
+pub(crate) fn cheapest<T>(candidates: Vec<(T, Option<f64>)>) -> Option<T> {
+    if candidates.iter().all(|(_, cost)| cost.is_some()) {
+        candidates
+            .into_iter()
+            .fold(
+                None,
+                |cheapest: Option<(T, Option<f64>)>, (candidate, cost)| match cheapest {
+                    Some((_, min)) if min <= cost => cheapest,
+                    _ => Some((candidate, cost)),
+                },
+            )
+            .map(|(candidate, _)| candidate)
+    } else {
+        candidates
+            .into_iter()
+            .next()
+            .map(|(candidate, _)| candidate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tcgeneric::NetworkTime;
+
+    use super::*;
+
+    #[test]
+    fn test_load_incomplete() {
+        let txn_id = TxnId::new(NetworkTime::from_nanos(1));
+        let key = [Value::from(1u64), Value::from(2u64)];
+
+        let created = Statistics::new(2);
+        created.insert(txn_id, &key, false);
+        created.commit(&txn_id);
+        assert!(created.committed().is_complete());
+        assert_eq!(created.committed().rows(), 1);
+
+        // rows written after an index is loaded don't make its statistics complete
+        let loaded = Statistics::load(2);
+        loaded.insert(txn_id, &key, false);
+        loaded.commit(&txn_id);
+        assert!(!loaded.committed().is_complete());
+        assert_eq!(loaded.committed().rows(), 1);
+
+        let txn_id = TxnId::new(NetworkTime::from_nanos(2));
+        loaded.restore(&created, txn_id);
+        assert!(!loaded.committed().is_complete());
+        loaded.commit(&txn_id);
+        assert!(loaded.committed().is_complete());
+    }
+
+    #[test]
+    fn test_cheapest() {
+        assert_eq!(cheapest::<&str>(vec![]), None);
+
+        let candidates = vec![("primary", Some(10.)), ("a", Some(1.)), ("b", Some(1.))];
+        assert_eq!(cheapest(candidates), Some("a"));
+
+        // an index without statistics may be much larger than it looks
+        let candidates = vec![("primary", Some(10.)), ("a", None), ("b", Some(0.))];
+        assert_eq!(cheapest(candidates), Some("primary"));
+
+        let candidates = vec![("a", None), ("b", Some(0.))];
+        assert_eq!(cheapest(candidates), Some("a"));
+    }
+}
@@ -0,0 +1,232 @@
+//! Declarative validation rules for the rows of a `Table`
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use collate::Collate;
+use regex::Regex;
+use safecast::*;
+
+use tc_value::{Number, TCString, Value, ValueCollator};
+use tcgeneric::{label, Id, Label};
+
+use super::Row;
+
+/// A comparison between two columns of the same row.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn name(&self) -> Label {
+        match self {
+            Self::Eq => label("eq"),
+            Self::Ne => label("ne"),
+            Self::Lt => label("lt"),
+            Self::Le => label("le"),
+            Self::Gt => label("gt"),
+            Self::Ge => label("ge"),
+        }
+    }
+
+    fn matches(&self, order: Ordering) -> bool {
+        match self {
+            Self::Eq => order == Ordering::Equal,
+            Self::Ne => order != Ordering::Equal,
+            Self::Lt => order == Ordering::Less,
+            Self::Le => order != Ordering::Greater,
+            Self::Gt => order == Ordering::Greater,
+            Self::Ge => order != Ordering::Less,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.name(), f)
+    }
+}
+
+/// A rule which the values of each row of a `Table` must satisfy.
+///
+/// A rule is not applied to a `None` value.
+#[derive(Clone)]
+pub enum ValidationRule {
+    /// The (string) value of the column must match a regular expression.
+    Matches(Id, Regex),
+
+    /// The (numeric) value of the column must fall within an inclusive range.
+    Range(Id, Number, Number),
+
+    /// The value of the first column must compare to the value of the second as given.
+    Compare(Id, Comparison, Id),
+}
+
+impl ValidationRule {
+    /// Return the names of the columns which this rule reads.
+    pub fn columns(&self) -> Vec<&Id> {
+        match self {
+            Self::Matches(column, _) => vec![column],
+            Self::Range(column, _, _) => vec![column],
+            Self::Compare(left, _, right) => vec![left, right],
+        }
+    }
+
+    /// Rename the column `from` to `to` wherever this rule reads it.
+    pub fn rename(&mut self, from: &Id, to: &Id) {
+        let columns = match self {
+            Self::Matches(column, _) => vec![column],
+            Self::Range(column, _, _) => vec![column],
+            Self::Compare(left, _, right) => vec![left, right],
+        };
+
+        for column in columns {
+            if column == from {
+                *column = to.clone();
+            }
+        }
+    }
+
+    /// Check the given `row`, returning a description of the violation if it fails this rule.
+    pub fn check(&self, row: &Row) -> Option<String> {
+        match self {
+            Self::Matches(column, regex) => match row.get(column) {
+                Some(Value::String(value)) if regex.is_match(value.as_str()) => None,
+                Some(Value::String(value)) => {
+                    Some(format!("{} {} does not match {}", column, value, regex))
+                }
+                Some(Value::None) | None => None,
+                Some(other) => Some(format!("{} {} is not a string", column, other)),
+            },
+            Self::Range(column, min, max) => match row.get(column) {
+                Some(Value::Number(n)) if n >= min && n <= max => None,
+                Some(Value::Number(n)) => Some(format!(
+                    "{} {} is outside the range [{}, {}]",
+                    column, n, min, max
+                )),
+                Some(Value::None) | None => None,
+                Some(other) => Some(format!("{} {} is not a number", column, other)),
+            },
+            Self::Compare(left, op, right) => match (row.get(left), row.get(right)) {
+                (Some(l), Some(r)) if l.is_some() && r.is_some() => {
+                    let order = ValueCollator::default().compare(l, r);
+                    if op.matches(order) {
+                        None
+                    } else {
+                        Some(format!("expected {} {} {}", left, op, right))
+                    }
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+impl PartialEq for ValidationRule {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Matches(l, lr), Self::Matches(r, rr)) => l == r && lr.as_str() == rr.as_str(),
+            (Self::Range(l, lmin, lmax), Self::Range(r, rmin, rmax)) => {
+                l == r && lmin == rmin && lmax == rmax
+            }
+            (Self::Compare(ll, lop, lr), Self::Compare(rl, rop, rr)) => {
+                ll == rl && lop == rop && lr == rr
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ValidationRule {}
+
+impl TryCastFrom<Value> for ValidationRule {
+    fn can_cast_from(value: &Value) -> bool {
+        Self::opt_cast_from(value.clone()).is_some()
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        let mut rule: Vec<Value> = value.opt_cast_into()?;
+        if rule.is_empty() {
+            return None;
+        }
+
+        let name = Id::opt_cast_from(rule.remove(0))?;
+        match name.as_str() {
+            "regex" => {
+                let (column, pattern): (Id, TCString) =
+                    Value::Tuple(rule.into()).opt_cast_into()?;
+                let regex = Regex::new(pattern.as_str()).ok()?;
+                Some(Self::Matches(column, regex))
+            }
+            "range" => {
+                let (column, min, max) = Value::Tuple(rule.into()).opt_cast_into()?;
+                Some(Self::Range(column, min, max))
+            }
+            op => {
+                let op = match op {
+                    "eq" => Comparison::Eq,
+                    "ne" => Comparison::Ne,
+                    "lt" => Comparison::Lt,
+                    "le" => Comparison::Le,
+                    "gt" => Comparison::Gt,
+                    "ge" => Comparison::Ge,
+                    _ => return None,
+                };
+
+                let (left, right) = Value::Tuple(rule.into()).opt_cast_into()?;
+                Some(Self::Compare(left, op, right))
+            }
+        }
+    }
+}
+
+impl CastFrom<ValidationRule> for Value {
+    fn cast_from(rule: ValidationRule) -> Self {
+        let rule: Vec<Value> = match rule {
+            ValidationRule::Matches(column, regex) => vec![
+                Id::from(label("regex")).into(),
+                column.into(),
+                Value::String(regex.as_str().to_string().into()),
+            ],
+            ValidationRule::Range(column, min, max) => vec![
+                Id::from(label("range")).into(),
+                column.into(),
+                min.into(),
+                max.into(),
+            ],
+            ValidationRule::Compare(left, op, right) => {
+                vec![Id::from(op.name()).into(), left.into(), right.into()]
+            }
+        };
+
+        Value::Tuple(rule.into())
+    }
+}
+
+impl fmt::Display for ValidationRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Matches(column, regex) => write!(f, "{} matches {}", column, regex),
+            Self::Range(column, min, max) => write!(f, "{} in [{}, {}]", column, min, max),
+            Self::Compare(left, op, right) => write!(f, "{} {} {}", left, op, right),
+        }
+    }
+}
+
+/// Check the given `row` against each of the given `rules`, and return a description of its
+/// violations, if there are any.
+pub fn violations(rules: &[ValidationRule], row: &Row) -> Option<String> {
+    let violations: Vec<String> = rules.iter().filter_map(|rule| rule.check(row)).collect();
+
+    if violations.is_empty() {
+        None
+    } else {
+        Some(violations.join("; "))
+    }
+}
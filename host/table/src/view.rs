@@ -10,14 +10,14 @@ use log::debug;
 use tc_btree::{BTreeFile, BTreeInstance, Node};
 use tc_error::*;
 use tc_transact::fs::{Dir, File};
-use tc_transact::{Transaction, TxnId};
+use tc_transact::{Isolation, Transaction, TxnId};
 use tc_value::Value;
 use tcgeneric::{Id, Instance, TCBoxTryStream};
 
 use super::index::TableIndex;
 use super::{
-    Bounds, Column, IndexSchema, Table, TableInstance, TableOrder, TableSchema, TableStream,
-    TableType,
+    Bounds, Column, ColumnMask, IndexSchema, Key, Table, TableInstance, TableOrder, TableRead,
+    TableSchema, TableStream, TableType,
 };
 
 #[derive(Clone)]
@@ -81,6 +81,7 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> IndexSlice<F, D, Txn> {
     pub async fn is_empty(&self, txn: &Txn) -> TCResult<bool> {
         self.source
             .clone()
+            .with_isolation(txn.isolation())
             .slice(self.range.clone(), self.reverse)?
             .is_empty(*txn.id())
             .await
@@ -143,6 +144,13 @@ where
     fn schema(&self) -> TableSchema {
         self.schema.clone().into()
     }
+
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        Self {
+            source: self.source.with_isolation(isolation),
+            ..self
+        }
+    }
 }
 
 impl<F, D, Txn> TableOrder for IndexSlice<F, D, Txn>
@@ -222,13 +230,20 @@ impl<F, D, Txn> From<IndexSlice<F, D, Txn>> for Table<F, D, Txn> {
 #[derive(Clone)]
 pub struct Limited<F, D, Txn> {
     source: Table<F, D, Txn>,
+    offset: u64,
     limit: u64,
 }
 
 impl<F, D, Txn> Limited<F, D, Txn> {
     pub fn new<T: Into<Table<F, D, Txn>>>(source: T, limit: u64) -> Self {
+        Self::page(source, 0, limit)
+    }
+
+    /// Skip the first `offset` rows of the given `source` and return at most `limit` rows.
+    pub fn page<T: Into<Table<F, D, Txn>>>(source: T, offset: u64, limit: u64) -> Self {
         Limited {
             source: source.into(),
+            offset,
             limit,
         }
     }
@@ -257,6 +272,13 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableInstance for Limited<F, D,
     fn schema(&self) -> TableSchema {
         self.source.schema()
     }
+
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        Self {
+            source: self.source.with_isolation(isolation),
+            ..self
+        }
+    }
 }
 
 #[async_trait]
@@ -266,6 +288,7 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableStream for Limited<F, D, T
 
     async fn count(self, txn_id: TxnId) -> TCResult<u64> {
         let source_count = self.source.count(txn_id).await?;
+        let source_count = source_count.saturating_sub(self.offset);
         Ok(u64::min(source_count, self.limit as u64))
     }
 
@@ -278,7 +301,16 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableStream for Limited<F, D, T
     }
 
     async fn rows<'a>(self, txn_id: TxnId) -> TCResult<TCBoxTryStream<'a, Vec<Value>>> {
-        let rows = self.source.rows(txn_id).await?;
+        let rows = match self.source {
+            Table::Merge(merged) if self.offset > 0 => {
+                merged.rows_from(txn_id, self.offset).await?
+            }
+            source => {
+                let rows = source.rows(txn_id).await?;
+                Box::pin(rows.skip(self.offset as usize))
+            }
+        };
+
         let rows: TCBoxTryStream<Vec<Value>> = Box::pin(rows.take(self.limit as usize));
         Ok(rows)
     }
@@ -297,6 +329,13 @@ pub enum MergeSource<F, D, Txn> {
 }
 
 impl<F: File<Node>, D: Dir, Txn: Transaction<D>> MergeSource<F, D, Txn> {
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        match self {
+            Self::Table(table) => Self::Table(table.with_isolation(isolation)),
+            Self::Merge(merged) => Self::Merge(Box::new(merged.with_isolation(isolation))),
+        }
+    }
+
     fn bounds(&'_ self) -> &'_ Bounds {
         match self {
             Self::Table(table) => table.bounds(),
@@ -382,6 +421,31 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> Merged<F, D, Txn> {
         self.left.into_source()
     }
 
+    /// Stream the rows of this merge, skipping the first `offset` rows.
+    ///
+    /// The skipped rows are never read from the source table, only their keys.
+    pub async fn rows_from<'a>(
+        self,
+        txn_id: TxnId,
+        offset: u64,
+    ) -> TCResult<TCBoxTryStream<'a, Vec<Value>>> {
+        let key_columns = self.key().to_vec();
+        let key_names = key_columns.iter().map(|col| &col.name).cloned().collect();
+        let keys = self.right.select(key_names)?.rows(txn_id).await?;
+
+        let left = self.left;
+        let left_clone = left.clone();
+        let merge = keys
+            .map_ok(move |key| Bounds::from_key(key, &key_columns))
+            .try_filter(move |bounds| future::ready(left.validate_bounds(bounds).is_ok()))
+            .skip(offset as usize)
+            .map_ok(move |bounds| Box::pin(left_clone.clone().slice_rows(txn_id, bounds, false)))
+            .try_buffered(num_cpus::get())
+            .try_flatten();
+
+        Ok(Box::pin(merge))
+    }
+
     /// Stream the rows within the given [`Bounds`] of this merge
     pub async fn slice_rows<'a>(
         self,
@@ -425,6 +489,14 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableInstance for Merged<F, D,
             MergeSource::Merge(merged) => merged.schema(),
         }
     }
+
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        Self {
+            left: self.left.with_isolation(isolation),
+            right: self.right.with_isolation(isolation),
+            bounds: self.bounds,
+        }
+    }
 }
 
 #[async_trait]
@@ -441,20 +513,7 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>> TableStream for Merged<F, D, Tx
     }
 
     async fn rows<'a>(self, txn_id: TxnId) -> TCResult<TCBoxTryStream<'a, Vec<Value>>> {
-        let key_columns = self.key().to_vec();
-        let key_names = key_columns.iter().map(|col| &col.name).cloned().collect();
-        let keys = self.right.select(key_names)?.rows(txn_id).await?;
-
-        let left = self.left;
-        let left_clone = left.clone();
-        let merge = keys
-            .map_ok(move |key| Bounds::from_key(key, &key_columns))
-            .try_filter(move |bounds| future::ready(left.validate_bounds(bounds).is_ok()))
-            .map_ok(move |bounds| Box::pin(left_clone.clone().slice_rows(txn_id, bounds, false)))
-            .try_buffered(num_cpus::get())
-            .try_flatten();
-
-        Ok(Box::pin(merge))
+        self.rows_from(txn_id, 0).await
     }
 }
 
@@ -509,12 +568,20 @@ impl<F, D, Txn> From<Merged<F, D, Txn>> for Table<F, D, Txn> {
     }
 }
 
+/// A view of a subset of the columns of a `Table`.
+///
+/// A `Selection` may also mask columns which the reader is not permitted to see: a masked column
+/// is present in each row but its values are all `None`, and a `Selection` will not order or
+/// slice its source by a masked column. Since a `Selection` never reveals the columns it hides,
+/// any view of a `Selection` (including another `Selection`) hides them as well.
 #[derive(Clone)]
 pub struct Selection<F, D, Txn, T> {
     source: T,
     schema: IndexSchema,
     columns: Vec<Id>,
     indices: Vec<usize>,
+    masked: HashSet<Id>,
+    restricted: HashSet<Id>,
     phantom: Phantom<F, D, Txn>,
 }
 
@@ -559,9 +626,60 @@ impl<F: File<Node>, D: Dir, Txn: Transaction<D>, T: TableInstance> Selection<F,
             schema,
             columns,
             indices,
+            masked: HashSet::new(),
+            restricted: HashSet::new(),
             phantom: Phantom::default(),
         })
     }
+
+    /// Construct a view of every column of the given `source` which hides the given columns.
+    pub fn masked(source: T, masks: HashMap<Id, ColumnMask>) -> TCResult<Self> {
+        let columns = source
+            .schema()
+            .primary()
+            .column_names()
+            .filter(|name| masks.get(name) != Some(&ColumnMask::Omit))
+            .cloned()
+            .collect();
+
+        let mut selection = Self::new(source, columns)?;
+
+        for (name, mask) in masks {
+            if mask == ColumnMask::Mask {
+                selection.masked.insert(name.clone());
+            }
+
+            selection.restricted.insert(name);
+        }
+
+        Ok(selection)
+    }
+
+    fn validate_columns<'a, I: IntoIterator<Item = &'a Id>>(&self, columns: I) -> TCResult<()> {
+        let mut restricted = Vec::new();
+        let mut unknown = Vec::new();
+        for name in columns {
+            if self.restricted.contains(name) {
+                restricted.push(name.to_string());
+            } else if !self.columns.contains(name) {
+                unknown.push(name.to_string());
+            }
+        }
+
+        if !restricted.is_empty() {
+            Err(TCError::unauthorized(format!(
+                "not authorized to filter or order by column(s) {}",
+                restricted.join(", ")
+            )))
+        } else if !unknown.is_empty() {
+            Err(TCError::bad_request(
+                "Tried to filter or order by unselected columns",
+                unknown.join(", "),
+            ))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl<F, D, Txn, T> Instance for Selection<F, D, Txn, T>
@@ -606,6 +724,13 @@ where
         let values = select(source.values());
         IndexSchema::from((key, values)).into()
     }
+
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        Self {
+            source: self.source.with_isolation(isolation),
+            ..self
+        }
+    }
 }
 
 impl<F, D, Txn, T> TableOrder for Selection<F, D, Txn, T>
@@ -614,10 +739,9 @@ where
     D: Dir,
     Txn: Transaction<D>,
     T: TableOrder,
-    <T as TableOrder>::Reverse: TableStream,
 {
     type OrderBy = Selection<F, D, Txn, <T as TableOrder>::OrderBy>;
-    type Reverse = <<T as TableOrder>::Reverse as TableStream>::Selection;
+    type Reverse = Selection<F, D, Txn, <T as TableOrder>::Reverse>;
 
     fn order_by(self, order: Vec<Id>, reverse: bool) -> TCResult<Self::OrderBy> {
         self.validate_order(&order)?;
@@ -629,34 +753,75 @@ where
             schema: self.schema,
             columns: self.columns,
             indices: self.indices,
+            masked: self.masked,
+            restricted: self.restricted,
             phantom: Phantom::default(),
         })
     }
 
     fn reverse(self) -> TCResult<Self::Reverse> {
-        self.source.reverse()?.select(self.columns)
+        let source = self.source.reverse()?;
+
+        Ok(Selection {
+            source,
+            schema: self.schema,
+            columns: self.columns,
+            indices: self.indices,
+            masked: self.masked,
+            restricted: self.restricted,
+            phantom: Phantom::default(),
+        })
     }
 
     fn validate_order(&self, order: &[Id]) -> TCResult<()> {
-        let order_columns: HashSet<Id> = order.iter().cloned().collect();
-        let selected: HashSet<Id> = self
-            .schema
-            .columns()
-            .iter()
-            .map(|c| c.name())
-            .cloned()
-            .collect();
+        self.validate_columns(order)?;
+        self.source.validate_order(order)
+    }
+}
 
-        let mut unknown: HashSet<&Id> = selected.difference(&order_columns).collect();
-        if !unknown.is_empty() {
-            let unknown: Vec<String> = unknown.drain().map(|c| c.to_string()).collect();
-            return Err(TCError::bad_request(
-                "Tried to order by unselected columns",
-                unknown.join(", "),
-            ));
-        }
+#[async_trait]
+impl<F, D, Txn, T> TableRead for Selection<F, D, Txn, T>
+where
+    F: File<Node>,
+    D: Dir,
+    Txn: Transaction<D>,
+    T: TableRead,
+{
+    async fn read(&self, txn_id: &TxnId, key: &Key) -> TCResult<Option<Vec<Value>>> {
+        let row = self.source.read(txn_id, key).await?;
+        Ok(row.map(|row| self.select_row(row)))
+    }
+}
 
-        self.source.validate_order(order)
+impl<F, D, Txn, T> super::TableSlice for Selection<F, D, Txn, T>
+where
+    F: File<Node>,
+    D: Dir,
+    Txn: Transaction<D>,
+    T: super::TableSlice,
+    Table<F, D, Txn>: From<Self>,
+{
+    type Slice = Selection<F, D, Txn, <T as super::TableSlice>::Slice>;
+
+    fn slice(self, bounds: Bounds) -> TCResult<Self::Slice> {
+        self.validate_bounds(&bounds)?;
+
+        let source = self.source.slice(bounds)?;
+
+        Ok(Selection {
+            source,
+            schema: self.schema,
+            columns: self.columns,
+            indices: self.indices,
+            masked: self.masked,
+            restricted: self.restricted,
+            phantom: Phantom::default(),
+        })
+    }
+
+    fn validate_bounds(&self, bounds: &Bounds) -> TCResult<()> {
+        self.validate_columns(bounds.keys())?;
+        self.source.validate_bounds(bounds)
     }
 }
 
@@ -686,8 +851,20 @@ where
 
     async fn rows<'a>(self, txn_id: TxnId) -> TCResult<TCBoxTryStream<'a, Vec<Value>>> {
         let indices = self.indices.to_vec();
+        let masked = self.masked_positions();
         let selected = self.source.rows(txn_id).await?.map_ok(move |row| {
-            let selection: Vec<Value> = indices.iter().map(|i| row[*i].clone()).collect();
+            let selection: Vec<Value> = indices
+                .iter()
+                .enumerate()
+                .map(|(i, source_i)| {
+                    if masked.contains(&i) {
+                        Value::None
+                    } else {
+                        row[*source_i].clone()
+                    }
+                })
+                .collect();
+
             selection
         });
 
@@ -696,6 +873,32 @@ where
     }
 }
 
+impl<F, D, Txn, T> Selection<F, D, Txn, T> {
+    fn masked_positions(&self) -> HashSet<usize> {
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| self.masked.contains(name))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn select_row(&self, row: Vec<Value>) -> Vec<Value> {
+        let masked = self.masked_positions();
+        self.indices
+            .iter()
+            .enumerate()
+            .map(|(i, source_i)| {
+                if masked.contains(&i) {
+                    Value::None
+                } else {
+                    row[*source_i].clone()
+                }
+            })
+            .collect()
+    }
+}
+
 impl<F, D, Txn, T> From<Selection<F, D, Txn, T>> for Table<F, D, Txn>
 where
     F: File<Node>,
@@ -710,6 +913,8 @@ where
             schema: selection.schema,
             columns: selection.columns,
             indices: selection.indices,
+            masked: selection.masked,
+            restricted: selection.restricted,
             phantom: Phantom::default(),
         }))
     }
@@ -786,6 +991,13 @@ where
     fn schema(&self) -> TableSchema {
         self.source().schema()
     }
+
+    fn with_isolation(self, isolation: Isolation) -> Self {
+        Self {
+            table: self.table.with_isolation(isolation),
+            slice: self.slice.with_isolation(isolation),
+        }
+    }
 }
 
 impl<F, D, Txn> TableOrder for TableSlice<F, D, Txn>
@@ -0,0 +1,222 @@
+//! Selection of the ArrayFire backend which computes [`Tensor`](super::Tensor) math.
+//!
+//! ArrayFire selects a backend and device per thread, so the host selects one once at startup
+//! with [`init_backend`] and then calls [`activate_backend`] on each thread of its runtime.
+//!
+//! The pure-Rust `ndarray` block backend always computes on the CPU, so without the `arrayfire`
+//! feature [`Backend::Cpu`] (on device 0) is the only backend available.
+
+#[cfg(feature = "arrayfire")]
+use std::any::Any;
+use std::fmt;
+#[cfg(feature = "arrayfire")]
+use std::panic;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI32, AtomicU8, Ordering};
+
+use log::{info, warn};
+
+use tc_error::*;
+
+static BACKEND: AtomicU8 = AtomicU8::new(0);
+static DEVICE: AtomicI32 = AtomicI32::new(0);
+
+/// An ArrayFire backend
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Backend {
+    /// The default backend chosen by ArrayFire, which prefers CUDA, then OpenCL, then CPU
+    Default,
+    Cpu,
+    Cuda,
+    OpenCL,
+}
+
+impl Backend {
+    /// Return the backends whose runtime libraries are installed on this host.
+    #[cfg(feature = "arrayfire")]
+    pub fn available() -> Vec<Self> {
+        af::get_available_backends()
+            .into_iter()
+            .map(Self::from)
+            .collect()
+    }
+
+    /// Return the backends whose runtime libraries are installed on this host.
+    #[cfg(not(feature = "arrayfire"))]
+    pub fn available() -> Vec<Self> {
+        vec![Self::Cpu]
+    }
+
+    fn code(&self) -> u8 {
+        match self {
+            Self::Default => 0,
+            Self::Cpu => 1,
+            Self::Cuda => 2,
+            Self::OpenCL => 3,
+        }
+    }
+
+    fn from_code(code: u8) -> Self {
+        match code {
+            1 => Self::Cpu,
+            2 => Self::Cuda,
+            3 => Self::OpenCL,
+            _ => Self::Default,
+        }
+    }
+}
+
+#[cfg(feature = "arrayfire")]
+impl From<af::Backend> for Backend {
+    fn from(backend: af::Backend) -> Self {
+        match backend {
+            af::Backend::CPU => Self::Cpu,
+            af::Backend::CUDA => Self::Cuda,
+            af::Backend::OPENCL => Self::OpenCL,
+            af::Backend::DEFAULT => Self::Default,
+        }
+    }
+}
+
+#[cfg(feature = "arrayfire")]
+impl From<Backend> for af::Backend {
+    fn from(backend: Backend) -> Self {
+        match backend {
+            Backend::Default => Self::DEFAULT,
+            Backend::Cpu => Self::CPU,
+            Backend::Cuda => Self::CUDA,
+            Backend::OpenCL => Self::OPENCL,
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = TCError;
+
+    fn from_str(name: &str) -> TCResult<Self> {
+        match name {
+            "default" => Ok(Self::Default),
+            "cpu" => Ok(Self::Cpu),
+            "cuda" => Ok(Self::Cuda),
+            "opencl" => Ok(Self::OpenCL),
+            other => Err(TCError::bad_request(
+                "expected an ArrayFire backend (default, cpu, cuda, or opencl) but found",
+                other,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Default => "default",
+            Self::Cpu => "cpu",
+            Self::Cuda => "cuda",
+            Self::OpenCL => "opencl",
+        })
+    }
+}
+
+/// Initialize the given ArrayFire `backend` on the given `device` on the current thread, and use
+/// it for every thread which later calls [`activate_backend`].
+///
+/// If the backend can't be initialized (e.g. because there is no GPU on this host), this logs a
+/// warning and falls back to the CPU backend instead. Returns the backend actually in use, or an
+/// error if even the CPU backend can't be initialized.
+pub fn init_backend(backend: Backend, device: i32) -> TCResult<Backend> {
+    let (backend, device) = match activate(backend, device) {
+        Ok(active) => (active, device),
+        Err(cause) => {
+            warn!(
+                "unable to initialize the ArrayFire {} backend on device {}, falling back to cpu: {}",
+                backend, device, cause
+            );
+
+            match activate(Backend::Cpu, 0) {
+                Ok(active) => (active, 0),
+                Err(cause) => {
+                    return Err(TCError::new(
+                        ErrorType::Internal,
+                        format!("unable to initialize the ArrayFire cpu backend: {}", cause),
+                    ))
+                }
+            }
+        }
+    };
+
+    info!("using the ArrayFire {} backend on device {}", backend, device);
+
+    BACKEND.store(backend.code(), Ordering::SeqCst);
+    DEVICE.store(device, Ordering::SeqCst);
+
+    Ok(backend)
+}
+
+/// Activate the backend and device chosen by [`init_backend`] on the current thread.
+pub fn activate_backend() {
+    let (backend, device) = active_backend();
+    if let Err(cause) = activate(backend, device) {
+        warn!(
+            "unable to activate the ArrayFire {} backend on device {}: {}",
+            backend, device, cause
+        );
+    }
+}
+
+/// Return the backend and device chosen by [`init_backend`].
+pub fn active_backend() -> (Backend, i32) {
+    let backend = Backend::from_code(BACKEND.load(Ordering::SeqCst));
+    let device = DEVICE.load(Ordering::SeqCst);
+    (backend, device)
+}
+
+// ArrayFire panics on an initialization error, so catch the panic and return its message instead
+#[cfg(feature = "arrayfire")]
+fn activate(backend: Backend, device: i32) -> Result<Backend, String> {
+    if backend != Backend::Default && !Backend::available().contains(&backend) {
+        return Err(format!("the {} backend is not installed", backend));
+    }
+
+    panic::catch_unwind(move || {
+        if backend != Backend::Default {
+            af::set_backend(backend.into());
+        }
+
+        let count = af::device_count();
+        if device < 0 || device >= count {
+            return Err(format!(
+                "there is no device {} (this backend has {} devices)",
+                device, count
+            ));
+        }
+
+        af::set_device(device);
+        af::sync(device);
+
+        Ok(Backend::from(af::get_active_backend()))
+    })
+    .map_err(panic_message)?
+}
+
+#[cfg(not(feature = "arrayfire"))]
+fn activate(backend: Backend, device: i32) -> Result<Backend, String> {
+    match backend {
+        Backend::Default | Backend::Cpu if device == 0 => Ok(Backend::Cpu),
+        Backend::Default | Backend::Cpu => {
+            Err(format!("there is no device {} (the cpu has 1)", device))
+        }
+        other => Err(format!("the {} backend requires ArrayFire", other)),
+    }
+}
+
+#[cfg(feature = "arrayfire")]
+fn panic_message(cause: Box<dyn Any + Send>) -> String {
+    if let Some(message) = cause.downcast_ref::<String>() {
+        message.clone()
+    } else if let Some(message) = cause.downcast_ref::<&str>() {
+        message.to_string()
+    } else {
+        "unknown error".to_string()
+    }
+}
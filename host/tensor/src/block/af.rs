@@ -0,0 +1,110 @@
+//! The ArrayFire block array backend, provided by `afarray`.
+
+use std::ops::Deref;
+
+pub use af::HasAfEnum as Element;
+pub use afarray::{
+    print_af_info, product_dtype, reduce_product, reduce_sum, sum_dtype, Array, ArrayExt,
+    ArrayInstance, Complex, CoordBlocks, CoordMerge, CoordUnique, Coords, Offsets,
+};
+
+/// Sort each lane of `lane_len` consecutive elements of the given `block`, returning the sorted
+/// values and the index of each within its lane, or `None` if its data type can't be sorted.
+pub fn sort_lanes(block: &Array, lane_len: u64, ascending: bool) -> Option<(Array, ArrayExt<u32>)> {
+    let len = block.len() as u64;
+    let lanes = af::Dim4::new(&[lane_len, len / lane_len, 1, 1]);
+    let flat = af::Dim4::new(&[len, 1, 1, 1]);
+
+    macro_rules! sort {
+        ($block:expr, $variant:ident) => {{
+            let block = af::moddims($block.deref(), lanes);
+            let (values, indices) = af::sort_index(&block, 0, ascending);
+            let values = Array::$variant(af::moddims(&values, flat).into());
+            Some((values, ArrayExt::from(af::moddims(&indices, flat))))
+        }};
+    }
+
+    match block {
+        Array::F32(block) => sort!(block, F32),
+        Array::F64(block) => sort!(block, F64),
+        Array::I16(block) => sort!(block, I16),
+        Array::I32(block) => sort!(block, I32),
+        Array::I64(block) => sort!(block, I64),
+        Array::U8(block) => sort!(block, U8),
+        Array::U16(block) => sort!(block, U16),
+        Array::U32(block) => sort!(block, U32),
+        Array::U64(block) => sort!(block, U64),
+        Array::Bool(_) | Array::C32(_) | Array::C64(_) => None,
+    }
+}
+
+/// The real component of a complex array, or `None` if the array is not complex.
+pub fn re(array: &Array) -> Option<Array> {
+    match array {
+        Array::C32(c) => Some(Array::F32(af::real(c.deref()).into())),
+        Array::C64(c) => Some(Array::F64(af::real(c.deref()).into())),
+        _ => None,
+    }
+}
+
+/// The imaginary component of a complex array, or `None` if the array is not complex.
+pub fn im(array: &Array) -> Option<Array> {
+    match array {
+        Array::C32(c) => Some(Array::F32(af::imag(c.deref()).into())),
+        Array::C64(c) => Some(Array::F64(af::imag(c.deref()).into())),
+        _ => None,
+    }
+}
+
+/// The complex conjugate of a complex array, or `None` if the array is not complex.
+pub fn conj(array: &Array) -> Option<Array> {
+    match array {
+        Array::C32(c) => Some(Array::C32(af::conjg(c.deref()).into())),
+        Array::C64(c) => Some(Array::C64(af::conjg(c.deref()).into())),
+        _ => None,
+    }
+}
+
+/// The argument (phase angle) of a complex array, or `None` if the array is not complex.
+pub fn arg(array: &Array) -> Option<Array> {
+    match array {
+        Array::C32(c) => Some(Array::F32(af::arg(c.deref()).into())),
+        Array::C64(c) => Some(Array::F64(af::arg(c.deref()).into())),
+        _ => None,
+    }
+}
+
+/// Divide each of the given `offsets` by `divisor`.
+pub fn offsets_div(offsets: &Offsets, divisor: u64) -> Offsets {
+    af::div(offsets.deref(), &scalar(divisor), true).into()
+}
+
+/// The remainder of each of the given `offsets` divided by `divisor`.
+pub fn offsets_rem(offsets: &Offsets, divisor: u64) -> Offsets {
+    af::modulo(offsets.deref(), &scalar(divisor), true).into()
+}
+
+/// A mask of the given `offsets` which are equal to `value`.
+pub fn offsets_eq(offsets: &Offsets, value: u64) -> ArrayExt<bool> {
+    af::eq(offsets.deref(), &scalar(value), true).into()
+}
+
+/// Count the given `offsets` which are equal to `value`.
+pub fn offsets_count(offsets: &Offsets, value: u64) -> usize {
+    let (count, _) = af::sum_all(&af::eq(offsets.deref(), &scalar(value), true));
+    count as usize
+}
+
+/// Zero each of the given `offsets` where the `mask` is false.
+pub fn offsets_mask(offsets: &Offsets, mask: &ArrayExt<bool>) -> Offsets {
+    (offsets.deref() * mask.deref()).into()
+}
+
+/// An empty list of offsets.
+pub fn offsets_empty() -> Offsets {
+    af::Array::new_empty(af::Dim4::default()).into()
+}
+
+fn scalar(value: u64) -> af::Array<u64> {
+    af::constant(value, af::Dim4::new(&[1, 1, 1, 1]))
+}
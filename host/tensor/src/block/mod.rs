@@ -0,0 +1,23 @@
+//! The block array type in which a [`DenseTensor`](super::DenseTensor) stores its elements, and
+//! the coordinate types which index it.
+//!
+//! The array backend is chosen at compile time. The `arrayfire` feature (the default) uses
+//! `afarray`, which computes on a CPU or GPU using the ArrayFire runtime, and the `ndarray` feature
+//! uses a pure-Rust implementation which runs on any host. Each backend provides the same items,
+//! so the other modules of this crate import them from here and don't depend on which is enabled.
+//! If both features are enabled, ArrayFire is used.
+
+#[cfg(not(any(feature = "arrayfire", feature = "ndarray")))]
+compile_error!(
+    "tc-tensor requires an array backend: enable the \"arrayfire\" or \"ndarray\" feature"
+);
+
+#[cfg(feature = "arrayfire")]
+pub use self::af::*;
+#[cfg(all(feature = "ndarray", not(feature = "arrayfire")))]
+pub use self::nd::*;
+
+#[cfg(feature = "arrayfire")]
+mod af;
+#[cfg(all(feature = "ndarray", not(feature = "arrayfire")))]
+mod nd;
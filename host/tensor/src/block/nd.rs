@@ -0,0 +1,2021 @@
+//! A pure-Rust block array backend built on `ndarray`, for hosts without ArrayFire.
+//!
+//! This provides the same API as `afarray`, computed element-wise on the CPU. Integer arithmetic
+//! wraps on overflow, and integer division by zero is zero, like ArrayFire. A boolean array
+//! follows the arithmetic of [`Number::Bool`]: `+` is `or`, `-` is `xor`, and `*` is `and`.
+
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::{Add, Deref, DerefMut, Div, Mul, Sub};
+use std::pin::Pin;
+use std::task::{self, Poll};
+
+use async_trait::async_trait;
+use destream::{de, en};
+use futures::future;
+use futures::ready;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use ndarray::{s, Array1, Array2, Axis};
+use pin_project::pin_project;
+use safecast::CastFrom;
+
+use tc_error::TCError;
+use tc_transact::fs::BlockData;
+use tc_value::{
+    ComplexType, DType, FloatType, IntType, Number, NumberInstance, NumberType, Promote, UIntType,
+};
+
+use self::sealed::Numeric;
+
+pub use num_complex::Complex;
+
+/// A list of offsets into a block, or into the flattened elements of a tensor.
+pub type Offsets = ArrayExt<u64>;
+
+/// An error encountered while manipulating an [`Array`].
+#[derive(Debug)]
+pub struct ArrayError {
+    message: String,
+}
+
+impl ArrayError {
+    fn new<M: fmt::Display>(message: M) -> Self {
+        Self {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::error::Error for ArrayError {}
+
+impl fmt::Display for ArrayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<ArrayError> for TCError {
+    fn from(cause: ArrayError) -> Self {
+        TCError::internal(format!("tensor error: {}", cause))
+    }
+}
+
+mod sealed {
+    use std::cmp::Ordering;
+
+    use num_complex::Complex;
+
+    /// The element-wise arithmetic of an [`Array`](super::Array).
+    pub trait Numeric: Copy + PartialEq {
+        fn one() -> Self;
+
+        fn add(self, other: Self) -> Self;
+
+        fn sub(self, other: Self) -> Self;
+
+        fn mul(self, other: Self) -> Self;
+
+        fn div(self, other: Self) -> Self;
+
+        fn pow(self, exp: Self) -> Self;
+
+        fn compare(&self, other: &Self) -> Option<Ordering>;
+
+        fn is_zero(&self) -> bool;
+
+        fn is_nan(&self) -> bool;
+
+        fn is_infinite(&self) -> bool;
+    }
+
+    impl Numeric for bool {
+        fn one() -> Self {
+            true
+        }
+
+        fn add(self, other: Self) -> Self {
+            self || other
+        }
+
+        fn sub(self, other: Self) -> Self {
+            self ^ other
+        }
+
+        fn mul(self, other: Self) -> Self {
+            self && other
+        }
+
+        fn div(self, _other: Self) -> Self {
+            self
+        }
+
+        fn pow(self, exp: Self) -> Self {
+            self || !exp
+        }
+
+        fn compare(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+
+        fn is_zero(&self) -> bool {
+            !self
+        }
+
+        fn is_nan(&self) -> bool {
+            false
+        }
+
+        fn is_infinite(&self) -> bool {
+            false
+        }
+    }
+
+    macro_rules! numeric_int {
+        ($($t:ty),*) => {
+            $(
+                impl Numeric for $t {
+                    fn one() -> Self {
+                        1
+                    }
+
+                    fn add(self, other: Self) -> Self {
+                        self.wrapping_add(other)
+                    }
+
+                    fn sub(self, other: Self) -> Self {
+                        self.wrapping_sub(other)
+                    }
+
+                    fn mul(self, other: Self) -> Self {
+                        self.wrapping_mul(other)
+                    }
+
+                    fn div(self, other: Self) -> Self {
+                        self.checked_div(other).unwrap_or(0)
+                    }
+
+                    fn pow(self, exp: Self) -> Self {
+                        // like a Number, an integer raised to a negative power is zero
+                        if (exp as i128) < 0 {
+                            0
+                        } else if (exp as i128) > u32::MAX as i128 {
+                            self.wrapping_pow(u32::MAX)
+                        } else {
+                            self.wrapping_pow(exp as u32)
+                        }
+                    }
+
+                    fn compare(&self, other: &Self) -> Option<Ordering> {
+                        Some(self.cmp(other))
+                    }
+
+                    fn is_zero(&self) -> bool {
+                        *self == 0
+                    }
+
+                    fn is_nan(&self) -> bool {
+                        false
+                    }
+
+                    fn is_infinite(&self) -> bool {
+                        false
+                    }
+                }
+            )*
+        };
+    }
+
+    numeric_int!(i16, i32, i64, u8, u16, u32, u64);
+
+    macro_rules! numeric_float {
+        ($($t:ty),*) => {
+            $(
+                impl Numeric for $t {
+                    fn one() -> Self {
+                        1.
+                    }
+
+                    fn add(self, other: Self) -> Self {
+                        self + other
+                    }
+
+                    fn sub(self, other: Self) -> Self {
+                        self - other
+                    }
+
+                    fn mul(self, other: Self) -> Self {
+                        self * other
+                    }
+
+                    fn div(self, other: Self) -> Self {
+                        self / other
+                    }
+
+                    fn pow(self, exp: Self) -> Self {
+                        self.powf(exp)
+                    }
+
+                    fn compare(&self, other: &Self) -> Option<Ordering> {
+                        self.partial_cmp(other)
+                    }
+
+                    fn is_zero(&self) -> bool {
+                        *self == 0.
+                    }
+
+                    fn is_nan(&self) -> bool {
+                        <$t>::is_nan(*self)
+                    }
+
+                    fn is_infinite(&self) -> bool {
+                        <$t>::is_infinite(*self)
+                    }
+                }
+
+                impl Numeric for Complex<$t> {
+                    fn one() -> Self {
+                        Complex::new(1., 0.)
+                    }
+
+                    fn add(self, other: Self) -> Self {
+                        self + other
+                    }
+
+                    fn sub(self, other: Self) -> Self {
+                        self - other
+                    }
+
+                    fn mul(self, other: Self) -> Self {
+                        self * other
+                    }
+
+                    fn div(self, other: Self) -> Self {
+                        self / other
+                    }
+
+                    fn pow(self, exp: Self) -> Self {
+                        self.powc(exp)
+                    }
+
+                    // complex numbers are ordered by their magnitude
+                    fn compare(&self, other: &Self) -> Option<Ordering> {
+                        self.norm().partial_cmp(&other.norm())
+                    }
+
+                    fn is_zero(&self) -> bool {
+                        self.re == 0. && self.im == 0.
+                    }
+
+                    fn is_nan(&self) -> bool {
+                        Complex::is_nan(*self)
+                    }
+
+                    fn is_infinite(&self) -> bool {
+                        Complex::is_infinite(*self)
+                    }
+                }
+            )*
+        };
+    }
+
+    numeric_float!(f32, f64);
+}
+
+/// The type of an element of an [`ArrayExt`].
+pub trait Element:
+    Numeric
+    + DType
+    + CastFrom<Number>
+    + Into<Number>
+    + Default
+    + Send
+    + Sync
+    + fmt::Debug
+    + fmt::Display
+    + 'static
+{
+}
+
+impl Element for bool {}
+impl Element for i16 {}
+impl Element for i32 {}
+impl Element for i64 {}
+impl Element for u8 {}
+impl Element for u16 {}
+impl Element for u32 {}
+impl Element for u64 {}
+impl Element for f32 {}
+impl Element for f64 {}
+impl Element for Complex<f32> {}
+impl Element for Complex<f64> {}
+
+/// Common methods of a one-dimensional array.
+pub trait ArrayInstance {
+    type DType;
+
+    /// The number of elements in this array.
+    fn len(&self) -> usize;
+
+    /// Return `true` if this array has no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy the elements of this array into a `Vec`.
+    fn to_vec(&self) -> Vec<Self::DType>;
+}
+
+/// A one-dimensional array of elements of type `T`.
+#[derive(Clone, Debug)]
+pub struct ArrayExt<T>(Array1<T>);
+
+impl<T: Element> ArrayExt<T> {
+    /// Construct an array of `len` copies of the given `value`.
+    pub fn constant(value: T, len: usize) -> Self {
+        Self(Array1::from_elem(len, value))
+    }
+
+    /// Construct a new array with the elements of `left` followed by those of `right`.
+    pub fn concatenate(left: &Self, right: &Self) -> Self {
+        left.iter().chain(right.iter()).copied().collect()
+    }
+
+    /// The [`NumberType`] of the elements of this array.
+    pub fn dtype(&self) -> NumberType {
+        T::dtype()
+    }
+
+    /// Copy the elements of this array in the range `start..end`.
+    pub fn slice(&self, start: usize, end: usize) -> Self {
+        Self(self.0.slice(s![start..end]).to_owned())
+    }
+
+    /// Gather the elements of this array at the given `indices`.
+    pub fn get(&self, indices: &Offsets) -> Self {
+        indices.iter().map(|i| self.0[*i as usize]).collect()
+    }
+
+    /// Scatter the given `values` to the given `indices` of this array, broadcasting a single value.
+    pub fn set(&mut self, indices: &Offsets, values: &Self) -> Result<(), ArrayError> {
+        if values.len() != 1 && values.len() != indices.len() {
+            return Err(ArrayError::new(format!(
+                "cannot set {} elements from an array of length {}",
+                indices.len(),
+                values.len()
+            )));
+        }
+
+        for (n, i) in indices.iter().enumerate() {
+            let value = if values.len() == 1 {
+                values[0]
+            } else {
+                values[n]
+            };
+
+            self.set_value(*i as usize, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the element of this array at the given `index`.
+    pub fn set_value(&mut self, index: usize, value: T) -> Result<(), ArrayError> {
+        let len = self.len();
+        match self.0.get_mut(index) {
+            Some(element) => {
+                *element = value;
+                Ok(())
+            }
+            None => Err(ArrayError::new(format!(
+                "index {} is out of bounds for an array of length {}",
+                index, len
+            ))),
+        }
+    }
+
+    /// Cast the elements of this array into type `O`.
+    pub fn type_cast<O: Element>(&self) -> ArrayExt<O> {
+        if let Some(this) = (self as &dyn Any).downcast_ref::<ArrayExt<O>>() {
+            return this.clone();
+        }
+
+        self.iter()
+            .map(|element| {
+                let n: Number = (*element).into();
+                O::cast_from(n)
+            })
+            .collect()
+    }
+
+    /// The sorted, unique elements of this array.
+    ///
+    /// If `sorted` is `true`, this array must already be sorted in ascending order.
+    pub fn unique(&self, sorted: bool) -> Self {
+        let mut elements = self.0.to_vec();
+        if !sorted {
+            elements.sort_by(compare);
+        }
+
+        elements.dedup();
+        elements.into()
+    }
+
+    /// Sort this array in place.
+    pub fn sort(&mut self, ascending: bool) {
+        let mut elements = self.0.to_vec();
+        if ascending {
+            elements.sort_by(compare);
+        } else {
+            elements.sort_by(|l, r| compare(r, l));
+        }
+
+        *self = elements.into();
+    }
+
+    /// Return `true` if every element of this array is nonzero.
+    pub fn all(&self) -> bool {
+        self.iter().all(|element| !element.is_zero())
+    }
+
+    /// Return `true` if any element of this array is nonzero.
+    pub fn any(&self) -> bool {
+        self.iter().any(|element| !element.is_zero())
+    }
+
+    /// The sum of the elements of this array.
+    pub fn sum(&self) -> T {
+        self.iter()
+            .fold(T::default(), |sum, element| sum.add(*element))
+    }
+
+    /// The product of the elements of this array.
+    pub fn product(&self) -> T {
+        self.iter()
+            .fold(T::one(), |product, element| product.mul(*element))
+    }
+
+    fn map_elements<O, F: Fn(T) -> O>(&self, f: F) -> ArrayExt<O> {
+        ArrayExt(self.0.iter().map(|element| f(*element)).collect())
+    }
+
+    // combine this array element-wise with `other`, broadcasting an array of length 1
+    fn zip_elements<O, F: Fn(T, T) -> O>(&self, other: &Self, f: F) -> ArrayExt<O> {
+        let elements = match (self.len(), other.len()) {
+            (l, r) if l == r => self
+                .iter()
+                .zip(other.iter())
+                .map(|(l, r)| f(*l, *r))
+                .collect(),
+            (_, 1) => self.iter().map(|l| f(*l, other[0])).collect(),
+            (1, _) => other.iter().map(|r| f(self[0], *r)).collect(),
+            (l, r) => panic!("cannot combine arrays of length {} and {}", l, r),
+        };
+
+        ArrayExt(elements)
+    }
+}
+
+impl ArrayExt<u64> {
+    /// Construct an array of the integers in the range `start..end`.
+    pub fn range(start: u64, end: u64) -> Self {
+        (start..end).collect()
+    }
+}
+
+macro_rules! complex_parts {
+    ($t:ty) => {
+        impl ArrayExt<Complex<$t>> {
+            /// The real component of each element of this array.
+            pub fn re(&self) -> ArrayExt<$t> {
+                self.map_elements(|c| c.re)
+            }
+
+            /// The imaginary component of each element of this array.
+            pub fn im(&self) -> ArrayExt<$t> {
+                self.map_elements(|c| c.im)
+            }
+        }
+
+        impl From<(ArrayExt<$t>, ArrayExt<$t>)> for ArrayExt<Complex<$t>> {
+            fn from(parts: (ArrayExt<$t>, ArrayExt<$t>)) -> Self {
+                let (re, im) = parts;
+                re.zip_elements(&im, Complex::new)
+            }
+        }
+    };
+}
+
+complex_parts!(f32);
+complex_parts!(f64);
+
+impl<T: Clone> ArrayInstance for ArrayExt<T> {
+    type DType = T;
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn to_vec(&self) -> Vec<T> {
+        self.0.to_vec()
+    }
+}
+
+impl<T> Deref for ArrayExt<T> {
+    type Target = Array1<T>;
+
+    fn deref(&self) -> &Array1<T> {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for ArrayExt<T> {
+    fn deref_mut(&mut self) -> &mut Array1<T> {
+        &mut self.0
+    }
+}
+
+impl<T: Clone> From<&[T]> for ArrayExt<T> {
+    fn from(elements: &[T]) -> Self {
+        Self(Array1::from(elements.to_vec()))
+    }
+}
+
+impl<T> From<Vec<T>> for ArrayExt<T> {
+    fn from(elements: Vec<T>) -> Self {
+        Self(Array1::from(elements))
+    }
+}
+
+impl<T> FromIterator<T> for ArrayExt<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(Array1::from_iter(iter))
+    }
+}
+
+fn compare<T: Element>(l: &T, r: &T) -> Ordering {
+    l.compare(r).unwrap_or(Ordering::Equal)
+}
+
+// call the given function with the type alias `$t` set to the element type of the given dtype
+macro_rules! with_dtype {
+    ($dtype:expr, $t:ident => $e:expr) => {
+        match $dtype {
+            NumberType::Bool => {
+                type $t = bool;
+                $e
+            }
+            NumberType::Complex(ComplexType::C32) => {
+                type $t = Complex<f32>;
+                $e
+            }
+            NumberType::Complex(_) => {
+                type $t = Complex<f64>;
+                $e
+            }
+            NumberType::Float(FloatType::F32) => {
+                type $t = f32;
+                $e
+            }
+            NumberType::Float(_) | NumberType::Number => {
+                type $t = f64;
+                $e
+            }
+            NumberType::Int(IntType::I8) | NumberType::Int(IntType::I16) => {
+                type $t = i16;
+                $e
+            }
+            NumberType::Int(IntType::I32) => {
+                type $t = i32;
+                $e
+            }
+            NumberType::Int(_) => {
+                type $t = i64;
+                $e
+            }
+            NumberType::UInt(UIntType::U8) => {
+                type $t = u8;
+                $e
+            }
+            NumberType::UInt(UIntType::U16) => {
+                type $t = u16;
+                $e
+            }
+            NumberType::UInt(UIntType::U32) => {
+                type $t = u32;
+                $e
+            }
+            NumberType::UInt(_) => {
+                type $t = u64;
+                $e
+            }
+        }
+    };
+}
+
+// evaluate the given expression for the `ArrayExt` in any variant of an `Array`
+macro_rules! dispatch {
+    ($this:expr, $a:ident => $e:expr) => {
+        match $this {
+            Array::Bool($a) => $e,
+            Array::C32($a) => $e,
+            Array::C64($a) => $e,
+            Array::F32($a) => $e,
+            Array::F64($a) => $e,
+            Array::I16($a) => $e,
+            Array::I32($a) => $e,
+            Array::I64($a) => $e,
+            Array::U8($a) => $e,
+            Array::U16($a) => $e,
+            Array::U32($a) => $e,
+            Array::U64($a) => $e,
+        }
+    };
+}
+
+// evaluate the given expression for the `ArrayExt` in any variant of an `Array`,
+// returning an `Array` of the same variant
+macro_rules! map_variant {
+    ($this:expr, $a:ident => $e:expr) => {
+        match $this {
+            Array::Bool($a) => Array::Bool($e),
+            Array::C32($a) => Array::C32($e),
+            Array::C64($a) => Array::C64($e),
+            Array::F32($a) => Array::F32($e),
+            Array::F64($a) => Array::F64($e),
+            Array::I16($a) => Array::I16($e),
+            Array::I32($a) => Array::I32($e),
+            Array::I64($a) => Array::I64($e),
+            Array::U8($a) => Array::U8($e),
+            Array::U16($a) => Array::U16($e),
+            Array::U32($a) => Array::U32($e),
+            Array::U64($a) => Array::U64($e),
+        }
+    };
+}
+
+// evaluate the given expression for two `Array`s of the same variant
+macro_rules! dispatch_pair {
+    ($left:expr, $right:expr, $l:ident, $r:ident => $e:expr) => {
+        match ($left, $right) {
+            (Array::Bool($l), Array::Bool($r)) => $e,
+            (Array::C32($l), Array::C32($r)) => $e,
+            (Array::C64($l), Array::C64($r)) => $e,
+            (Array::F32($l), Array::F32($r)) => $e,
+            (Array::F64($l), Array::F64($r)) => $e,
+            (Array::I16($l), Array::I16($r)) => $e,
+            (Array::I32($l), Array::I32($r)) => $e,
+            (Array::I64($l), Array::I64($r)) => $e,
+            (Array::U8($l), Array::U8($r)) => $e,
+            (Array::U16($l), Array::U16($r)) => $e,
+            (Array::U32($l), Array::U32($r)) => $e,
+            (Array::U64($l), Array::U64($r)) => $e,
+            (l, r) => unreachable!("arrays of type {} and {}", l.dtype(), r.dtype()),
+        }
+    };
+}
+
+// evaluate the given expression for two `Array`s of the same variant,
+// returning an `Array` of that variant
+macro_rules! map_pair {
+    ($left:expr, $right:expr, $l:ident, $r:ident => $e:expr) => {
+        match ($left, $right) {
+            (Array::Bool($l), Array::Bool($r)) => Array::Bool($e),
+            (Array::C32($l), Array::C32($r)) => Array::C32($e),
+            (Array::C64($l), Array::C64($r)) => Array::C64($e),
+            (Array::F32($l), Array::F32($r)) => Array::F32($e),
+            (Array::F64($l), Array::F64($r)) => Array::F64($e),
+            (Array::I16($l), Array::I16($r)) => Array::I16($e),
+            (Array::I32($l), Array::I32($r)) => Array::I32($e),
+            (Array::I64($l), Array::I64($r)) => Array::I64($e),
+            (Array::U8($l), Array::U8($r)) => Array::U8($e),
+            (Array::U16($l), Array::U16($r)) => Array::U16($e),
+            (Array::U32($l), Array::U32($r)) => Array::U32($e),
+            (Array::U64($l), Array::U64($r)) => Array::U64($e),
+            (l, r) => unreachable!("arrays of type {} and {}", l.dtype(), r.dtype()),
+        }
+    };
+}
+
+// combine two arrays element-wise after promoting them to a common type
+macro_rules! arithmetic {
+    ($left:expr, $right:expr, $op:path) => {{
+        let dtype = $left.dtype().promote($right.dtype());
+        let (left, right) = ($left.cast_ref(dtype), $right.cast_ref(dtype));
+        map_pair!(&*left, &*right, l, r => l.zip_elements(r, $op))
+    }};
+}
+
+// define a trigonometric function of an array, which computes with `Array::to_float`
+macro_rules! float_fn {
+    ($($name:ident),*) => {
+        $(
+            pub fn $name(&self) -> Array {
+                match self.to_float() {
+                    Array::C32(a) => Array::C32(a.map_elements(|x| x.$name())),
+                    Array::C64(a) => Array::C64(a.map_elements(|x| x.$name())),
+                    Array::F32(a) => Array::F32(a.map_elements(|x| x.$name())),
+                    Array::F64(a) => Array::F64(a.map_elements(|x| x.$name())),
+                    other => unreachable!("{} is not a floating-point type", other.dtype()),
+                }
+            }
+        )*
+    };
+}
+
+/// A one-dimensional array of elements of any [`NumberType`].
+#[derive(Clone, Debug)]
+pub enum Array {
+    Bool(ArrayExt<bool>),
+    C32(ArrayExt<Complex<f32>>),
+    C64(ArrayExt<Complex<f64>>),
+    F32(ArrayExt<f32>),
+    F64(ArrayExt<f64>),
+    I16(ArrayExt<i16>),
+    I32(ArrayExt<i32>),
+    I64(ArrayExt<i64>),
+    U8(ArrayExt<u8>),
+    U16(ArrayExt<u16>),
+    U32(ArrayExt<u32>),
+    U64(ArrayExt<u64>),
+}
+
+impl Array {
+    /// Construct an array of `len` copies of the given `value`.
+    pub fn constant(value: Number, len: usize) -> Self {
+        with_dtype!(value.class(), T => ArrayExt::<T>::constant(T::cast_from(value), len).into())
+    }
+
+    /// Construct a new array with the elements of `left` followed by those of `right`.
+    pub fn concatenate(left: &Self, right: &Self) -> Self {
+        let dtype = left.dtype().promote(right.dtype());
+        let (left, right) = (left.cast_ref(dtype), right.cast_ref(dtype));
+        map_pair!(&*left, &*right, l, r => ArrayExt::concatenate(l, r))
+    }
+
+    /// The [`NumberType`] of the elements of this array.
+    pub fn dtype(&self) -> NumberType {
+        dispatch!(self, a => a.dtype())
+    }
+
+    /// The number of elements in this array.
+    pub fn len(&self) -> usize {
+        dispatch!(self, a => a.len())
+    }
+
+    /// Return `true` if this array has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cast this array into the given [`NumberType`].
+    pub fn cast_into(&self, dtype: NumberType) -> Self {
+        with_dtype!(dtype, T => self.type_cast::<T>().into())
+    }
+
+    /// Cast the elements of this array into type `T`.
+    pub fn type_cast<T: Element>(&self) -> ArrayExt<T> {
+        dispatch!(self, a => a.type_cast())
+    }
+
+    /// Copy the elements of this array into a `Vec` of [`Number`]s.
+    pub fn to_vec(&self) -> Vec<Number> {
+        dispatch!(self, a => a.iter().map(|element| (*element).into()).collect())
+    }
+
+    /// Gather the elements of this array at the given `indices`.
+    pub fn get(&self, indices: &Offsets) -> Self {
+        map_variant!(self, a => a.get(indices))
+    }
+
+    /// Scatter the given `values` to the given `indices` of this array, broadcasting a single value.
+    pub fn set(&mut self, indices: &Offsets, values: &Self) -> Result<(), ArrayError> {
+        dispatch!(self, a => a.set(indices, &values.type_cast()))
+    }
+
+    /// Return the element of this array at the given `index`.
+    pub fn get_value(&self, index: usize) -> Number {
+        dispatch!(self, a => a[index].into())
+    }
+
+    /// Set the element of this array at the given `index`.
+    pub fn set_value(&mut self, index: usize, value: Number) -> Result<(), ArrayError> {
+        dispatch!(self, a => a.set_value(index, CastFrom::cast_from(value)))
+    }
+
+    /// Copy the elements of this array in the range `start..end`.
+    pub fn slice(&self, start: usize, end: usize) -> Result<Self, ArrayError> {
+        if start > end || end > self.len() {
+            return Err(ArrayError::new(format!(
+                "invalid range {}..{} for an array of length {}",
+                start,
+                end,
+                self.len()
+            )));
+        }
+
+        Ok(map_variant!(self, a => a.slice(start, end)))
+    }
+
+    /// Split this array into two at the given index.
+    pub fn split(&self, at: usize) -> Result<(Self, Self), ArrayError> {
+        let left = self.slice(0, at)?;
+        let right = self.slice(at, self.len())?;
+        Ok((left, right))
+    }
+
+    /// Sort this array in place.
+    pub fn sort(&mut self, ascending: bool) -> Result<(), ArrayError> {
+        match self {
+            Self::C32(_) | Self::C64(_) => Err(ArrayError::new("cannot sort a complex array")),
+            this => {
+                dispatch!(this, a => a.sort(ascending));
+                Ok(())
+            }
+        }
+    }
+
+    /// Return `true` if every element of this array is nonzero.
+    pub fn all(&self) -> bool {
+        dispatch!(self, a => a.all())
+    }
+
+    /// Return `true` if any element of this array is nonzero.
+    pub fn any(&self) -> bool {
+        dispatch!(self, a => a.any())
+    }
+
+    /// The sum of the elements of this array, as a [`sum_dtype`].
+    pub fn sum(&self) -> Number {
+        let this = self.cast_ref(sum_dtype(self.dtype()));
+        dispatch!(&*this, a => a.sum().into())
+    }
+
+    /// The product of the elements of this array, as a [`product_dtype`].
+    pub fn product(&self) -> Number {
+        let this = self.cast_ref(product_dtype(self.dtype()));
+        dispatch!(&*this, a => a.product().into())
+    }
+
+    /// Return a boolean array which is `true` where this array is not a number.
+    pub fn is_nan(&self) -> Self {
+        Self::Bool(dispatch!(self, a => a.map_elements(|x| x.is_nan())))
+    }
+
+    /// Return a boolean array which is `true` where this array is infinite.
+    pub fn is_infinite(&self) -> Self {
+        Self::Bool(dispatch!(self, a => a.map_elements(|x| x.is_infinite())))
+    }
+
+    /// Element-wise logical and.
+    pub fn and(&self, other: &Self) -> Self {
+        self.logic(other, |l, r| l && r)
+    }
+
+    /// Element-wise logical and with a constant.
+    pub fn and_const(&self, other: Number) -> Self {
+        self.and(&Self::constant(other, 1))
+    }
+
+    /// Element-wise logical or.
+    pub fn or(&self, other: &Self) -> Self {
+        self.logic(other, |l, r| l || r)
+    }
+
+    /// Element-wise logical or with a constant.
+    pub fn or_const(&self, other: Number) -> Self {
+        self.or(&Self::constant(other, 1))
+    }
+
+    /// Element-wise logical exclusive or.
+    pub fn xor(&self, other: &Self) -> Self {
+        self.logic(other, |l, r| l ^ r)
+    }
+
+    /// Element-wise logical exclusive or with a constant.
+    pub fn xor_const(&self, other: Number) -> Self {
+        self.xor(&Self::constant(other, 1))
+    }
+
+    /// Element-wise logical not.
+    pub fn not(&self) -> Self {
+        Self::Bool(self.type_cast::<bool>().map_elements(|x| !x))
+    }
+
+    /// Element-wise equality.
+    pub fn eq(&self, other: &Self) -> Self {
+        let dtype = self.dtype().promote(other.dtype());
+        let (left, right) = (self.cast_ref(dtype), other.cast_ref(dtype));
+        Self::Bool(dispatch_pair!(&*left, &*right, l, r => l.zip_elements(r, |l, r| l == r)))
+    }
+
+    /// Element-wise equality with a constant.
+    pub fn eq_const(&self, other: Number) -> Self {
+        self.eq(&Self::constant(other, 1))
+    }
+
+    /// Element-wise inequality.
+    pub fn ne(&self, other: &Self) -> Self {
+        self.eq(other).not()
+    }
+
+    /// Element-wise inequality with a constant.
+    pub fn ne_const(&self, other: Number) -> Self {
+        self.ne(&Self::constant(other, 1))
+    }
+
+    /// Element-wise greater than.
+    pub fn gt(&self, other: &Self) -> Self {
+        self.compare(other, |order| order == Ordering::Greater)
+    }
+
+    /// Element-wise greater than a constant.
+    pub fn gt_const(&self, other: Number) -> Self {
+        self.gt(&Self::constant(other, 1))
+    }
+
+    /// Element-wise greater than or equal to.
+    pub fn gte(&self, other: &Self) -> Self {
+        self.compare(other, |order| order != Ordering::Less)
+    }
+
+    /// Element-wise greater than or equal to a constant.
+    pub fn gte_const(&self, other: Number) -> Self {
+        self.gte(&Self::constant(other, 1))
+    }
+
+    /// Element-wise less than.
+    pub fn lt(&self, other: &Self) -> Self {
+        self.compare(other, |order| order == Ordering::Less)
+    }
+
+    /// Element-wise less than a constant.
+    pub fn lt_const(&self, other: Number) -> Self {
+        self.lt(&Self::constant(other, 1))
+    }
+
+    /// Element-wise less than or equal to.
+    pub fn lte(&self, other: &Self) -> Self {
+        self.compare(other, |order| order != Ordering::Greater)
+    }
+
+    /// Element-wise less than or equal to a constant.
+    pub fn lte_const(&self, other: Number) -> Self {
+        self.lte(&Self::constant(other, 1))
+    }
+
+    /// Raise each element of this array to the power of the corresponding element of `other`.
+    pub fn pow(&self, other: &Self) -> Self {
+        arithmetic!(self, other, Numeric::pow)
+    }
+
+    /// Raise each element of this array to the given power.
+    pub fn pow_const(&self, other: Number) -> Self {
+        self.pow(&Self::constant(other, 1))
+    }
+
+    /// The absolute value of each element of this array.
+    ///
+    /// The absolute value of a complex number is its magnitude, a real number.
+    pub fn abs(&self) -> Self {
+        match self {
+            Self::C32(a) => Self::F32(a.map_elements(|x| x.norm())),
+            Self::C64(a) => Self::F64(a.map_elements(|x| x.norm())),
+            Self::F32(a) => Self::F32(a.map_elements(f32::abs)),
+            Self::F64(a) => Self::F64(a.map_elements(f64::abs)),
+            Self::I16(a) => Self::I16(a.map_elements(i16::wrapping_abs)),
+            Self::I32(a) => Self::I32(a.map_elements(i32::wrapping_abs)),
+            Self::I64(a) => Self::I64(a.map_elements(i64::wrapping_abs)),
+            unsigned => unsigned.clone(),
+        }
+    }
+
+    /// Raise `e` to the power of each element of this array.
+    ///
+    /// The result is a 64-bit float, unless this array is complex.
+    pub fn exp(&self) -> Self {
+        match self {
+            Self::C32(a) => Self::C32(a.map_elements(|x| x.exp())),
+            Self::C64(a) => Self::C64(a.map_elements(|x| x.exp())),
+            real => match real.cast_into(FloatType::F64.into()) {
+                Self::F64(a) => Self::F64(a.map_elements(f64::exp)),
+                other => unreachable!("exp of {}", other.dtype()),
+            },
+        }
+    }
+
+    float_fn!(sin, cos, tan, asin, acos, atan, sinh, cosh, tanh, asinh, acosh, atanh);
+
+    fn cast_ref(&self, dtype: NumberType) -> Cow<'_, Self> {
+        if self.dtype() == dtype {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.cast_into(dtype))
+        }
+    }
+
+    fn logic<F: Fn(bool, bool) -> bool>(&self, other: &Self, f: F) -> Self {
+        let left = self.type_cast::<bool>();
+        let right = other.type_cast::<bool>();
+        Self::Bool(left.zip_elements(&right, f))
+    }
+
+    fn compare<F: Fn(Ordering) -> bool>(&self, other: &Self, f: F) -> Self {
+        let dtype = self.dtype().promote(other.dtype());
+        let (left, right) = (self.cast_ref(dtype), other.cast_ref(dtype));
+
+        let result = dispatch_pair!(&*left, &*right, l, r => {
+            l.zip_elements(r, |l, r| l.compare(&r).map(&f).unwrap_or(false))
+        });
+
+        Self::Bool(result)
+    }
+
+    // the trigonometric type of this array: 64-bit integers become 64-bit floats,
+    // and other integers 32-bit floats
+    fn to_float(&self) -> Self {
+        match self {
+            Self::C32(_) | Self::C64(_) | Self::F32(_) | Self::F64(_) => self.clone(),
+            Self::I64(_) | Self::U64(_) => self.cast_into(FloatType::F64.into()),
+            _ => self.cast_into(FloatType::F32.into()),
+        }
+    }
+}
+
+macro_rules! array_from {
+    ($t:ty, $variant:ident) => {
+        impl From<ArrayExt<$t>> for Array {
+            fn from(array: ArrayExt<$t>) -> Self {
+                Self::$variant(array)
+            }
+        }
+    };
+}
+
+array_from!(bool, Bool);
+array_from!(Complex<f32>, C32);
+array_from!(Complex<f64>, C64);
+array_from!(f32, F32);
+array_from!(f64, F64);
+array_from!(i16, I16);
+array_from!(i32, I32);
+array_from!(i64, I64);
+array_from!(u8, U8);
+array_from!(u16, U16);
+array_from!(u32, U32);
+array_from!(u64, U64);
+
+impl From<Vec<Number>> for Array {
+    fn from(elements: Vec<Number>) -> Self {
+        let dtype = elements
+            .iter()
+            .map(|n| n.class())
+            .fold(NumberType::Bool, Promote::promote);
+
+        with_dtype!(dtype, T => elements
+            .into_iter()
+            .map(T::cast_from)
+            .collect::<ArrayExt<T>>()
+            .into())
+    }
+}
+
+macro_rules! array_op {
+    ($op:ident, $fun:ident) => {
+        impl $op for &Array {
+            type Output = Array;
+
+            fn $fun(self, other: &Array) -> Array {
+                arithmetic!(self, other, Numeric::$fun)
+            }
+        }
+
+        impl $op<Number> for &Array {
+            type Output = Array;
+
+            fn $fun(self, other: Number) -> Array {
+                $op::$fun(self, &Array::constant(other, 1))
+            }
+        }
+    };
+}
+
+array_op!(Add, add);
+array_op!(Div, div);
+array_op!(Mul, mul);
+array_op!(Sub, sub);
+
+impl fmt::Display for Array {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[")?;
+
+        for (i, n) in self.to_vec().into_iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+
+            write!(f, "{}", n)?;
+        }
+
+        write!(f, "] ({})", self.dtype())
+    }
+}
+
+impl BlockData for Array {
+    fn ext() -> &'static str {
+        "array"
+    }
+}
+
+// the name by which an array's data type is encoded
+fn dtype_name(array: &Array) -> &'static str {
+    match array {
+        Array::Bool(_) => "bool",
+        Array::C32(_) => "c32",
+        Array::C64(_) => "c64",
+        Array::F32(_) => "f32",
+        Array::F64(_) => "f64",
+        Array::I16(_) => "i16",
+        Array::I32(_) => "i32",
+        Array::I64(_) => "i64",
+        Array::U8(_) => "u8",
+        Array::U16(_) => "u16",
+        Array::U32(_) => "u32",
+        Array::U64(_) => "u64",
+    }
+}
+
+impl<'en> en::IntoStream<'en> for Array {
+    fn into_stream<E: en::Encoder<'en>>(self, encoder: E) -> Result<E::Ok, E::Error> {
+        let dtype = dtype_name(&self);
+
+        match self {
+            Self::C32(a) => {
+                (dtype, a.iter().map(|c| (c.re, c.im)).collect::<Vec<_>>()).into_stream(encoder)
+            }
+            Self::C64(a) => {
+                (dtype, a.iter().map(|c| (c.re, c.im)).collect::<Vec<_>>()).into_stream(encoder)
+            }
+            Self::Bool(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::F32(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::F64(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::I16(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::I32(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::I64(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::U8(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::U16(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::U32(a) => (dtype, a.to_vec()).into_stream(encoder),
+            Self::U64(a) => (dtype, a.to_vec()).into_stream(encoder),
+        }
+    }
+}
+
+impl<'en> en::ToStream<'en> for Array {
+    fn to_stream<E: en::Encoder<'en>>(&'en self, encoder: E) -> Result<E::Ok, E::Error> {
+        en::IntoStream::into_stream(self.clone(), encoder)
+    }
+}
+
+#[async_trait]
+impl de::FromStream for Array {
+    type Context = ();
+
+    async fn from_stream<D: de::Decoder>(_context: (), decoder: &mut D) -> Result<Self, D::Error> {
+        decoder.decode_seq(ArrayVisitor).await
+    }
+}
+
+struct ArrayVisitor;
+
+#[async_trait]
+impl de::Visitor for ArrayVisitor {
+    type Value = Array;
+
+    fn expecting() -> &'static str {
+        "a tensor block"
+    }
+
+    async fn visit_seq<A: de::SeqAccess>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let dtype: String = seq
+            .next_element(())
+            .await?
+            .ok_or_else(|| de::Error::invalid_length(0, Self::expecting()))?;
+
+        macro_rules! decode {
+            ($t:ty) => {{
+                let elements: Vec<$t> = seq
+                    .next_element(())
+                    .await?
+                    .ok_or_else(|| de::Error::invalid_length(1, Self::expecting()))?;
+
+                elements
+            }};
+        }
+
+        let array = match dtype.as_str() {
+            "bool" => Array::Bool(decode!(bool).into()),
+            "c32" => Array::C32(
+                decode!((f32, f32))
+                    .into_iter()
+                    .map(|(re, im)| Complex::new(re, im))
+                    .collect(),
+            ),
+            "c64" => Array::C64(
+                decode!((f64, f64))
+                    .into_iter()
+                    .map(|(re, im)| Complex::new(re, im))
+                    .collect(),
+            ),
+            "f32" => Array::F32(decode!(f32).into()),
+            "f64" => Array::F64(decode!(f64).into()),
+            "i16" => Array::I16(decode!(i16).into()),
+            "i32" => Array::I32(decode!(i32).into()),
+            "i64" => Array::I64(decode!(i64).into()),
+            "u8" => Array::U8(decode!(u8).into()),
+            "u16" => Array::U16(decode!(u16).into()),
+            "u32" => Array::U32(decode!(u32).into()),
+            "u64" => Array::U64(decode!(u64).into()),
+            other => return Err(de::Error::invalid_value(other, "a tensor data type")),
+        };
+
+        Ok(array)
+    }
+}
+
+/// The data type of the sum of an array of the given type.
+pub fn sum_dtype(dtype: NumberType) -> NumberType {
+    match dtype {
+        NumberType::Bool | NumberType::UInt(_) => UIntType::U64.into(),
+        NumberType::Int(_) => IntType::I64.into(),
+        other => other,
+    }
+}
+
+/// The data type of the product of an array of the given type.
+pub fn product_dtype(dtype: NumberType) -> NumberType {
+    sum_dtype(dtype)
+}
+
+/// Print a description of the array backend in use.
+pub fn print_af_info() {
+    println!("ArrayFire is not enabled: Tensor math is computed on the CPU in pure Rust");
+}
+
+/// Sort each lane of `lane_len` consecutive elements of the given `block`, returning the sorted
+/// values and the index of each within its lane, or `None` if its data type can't be sorted.
+pub fn sort_lanes(block: &Array, lane_len: u64, ascending: bool) -> Option<(Array, ArrayExt<u32>)> {
+    fn sort<T: Element>(
+        block: &ArrayExt<T>,
+        lane_len: usize,
+        ascending: bool,
+    ) -> (ArrayExt<T>, ArrayExt<u32>) {
+        let elements = block.to_vec();
+        let mut values = Vec::with_capacity(elements.len());
+        let mut indices = Vec::with_capacity(elements.len());
+
+        for lane in elements.chunks(lane_len) {
+            let mut order: Vec<u32> = (0..lane.len() as u32).collect();
+            order.sort_by(|l, r| {
+                let order = compare(&lane[*l as usize], &lane[*r as usize]);
+                if ascending {
+                    order
+                } else {
+                    order.reverse()
+                }
+            });
+
+            values.extend(order.iter().map(|i| lane[*i as usize]));
+            indices.extend(order);
+        }
+
+        (values.into(), indices.into())
+    }
+
+    let lane_len = lane_len as usize;
+
+    macro_rules! sort {
+        ($block:expr, $variant:ident) => {{
+            let (values, indices) = sort($block, lane_len, ascending);
+            Some((Array::$variant(values), indices))
+        }};
+    }
+
+    match block {
+        Array::F32(block) => sort!(block, F32),
+        Array::F64(block) => sort!(block, F64),
+        Array::I16(block) => sort!(block, I16),
+        Array::I32(block) => sort!(block, I32),
+        Array::I64(block) => sort!(block, I64),
+        Array::U8(block) => sort!(block, U8),
+        Array::U16(block) => sort!(block, U16),
+        Array::U32(block) => sort!(block, U32),
+        Array::U64(block) => sort!(block, U64),
+        Array::Bool(_) | Array::C32(_) | Array::C64(_) => None,
+    }
+}
+
+/// The real component of a complex array, or `None` if the array is not complex.
+pub fn re(array: &Array) -> Option<Array> {
+    match array {
+        Array::C32(c) => Some(Array::F32(c.re())),
+        Array::C64(c) => Some(Array::F64(c.re())),
+        _ => None,
+    }
+}
+
+/// The imaginary component of a complex array, or `None` if the array is not complex.
+pub fn im(array: &Array) -> Option<Array> {
+    match array {
+        Array::C32(c) => Some(Array::F32(c.im())),
+        Array::C64(c) => Some(Array::F64(c.im())),
+        _ => None,
+    }
+}
+
+/// The complex conjugate of a complex array, or `None` if the array is not complex.
+pub fn conj(array: &Array) -> Option<Array> {
+    match array {
+        Array::C32(c) => Some(Array::C32(c.map_elements(|c| c.conj()))),
+        Array::C64(c) => Some(Array::C64(c.map_elements(|c| c.conj()))),
+        _ => None,
+    }
+}
+
+/// The argument (phase angle) of a complex array, or `None` if the array is not complex.
+pub fn arg(array: &Array) -> Option<Array> {
+    match array {
+        Array::C32(c) => Some(Array::F32(c.map_elements(|c| c.arg()))),
+        Array::C64(c) => Some(Array::F64(c.map_elements(|c| c.arg()))),
+        _ => None,
+    }
+}
+
+/// Divide each of the given `offsets` by `divisor`.
+pub fn offsets_div(offsets: &Offsets, divisor: u64) -> Offsets {
+    offsets.map_elements(|offset| offset / divisor)
+}
+
+/// The remainder of each of the given `offsets` divided by `divisor`.
+pub fn offsets_rem(offsets: &Offsets, divisor: u64) -> Offsets {
+    offsets.map_elements(|offset| offset % divisor)
+}
+
+/// A mask of the given `offsets` which are equal to `value`.
+pub fn offsets_eq(offsets: &Offsets, value: u64) -> ArrayExt<bool> {
+    offsets.map_elements(|offset| offset == value)
+}
+
+/// Count the given `offsets` which are equal to `value`.
+pub fn offsets_count(offsets: &Offsets, value: u64) -> usize {
+    offsets.iter().filter(|offset| **offset == value).count()
+}
+
+/// Zero each of the given `offsets` where the `mask` is false.
+pub fn offsets_mask(offsets: &Offsets, mask: &ArrayExt<bool>) -> Offsets {
+    offsets
+        .iter()
+        .zip(mask.iter())
+        .map(|(offset, keep)| if *keep { *offset } else { 0 })
+        .collect()
+}
+
+/// An empty list of offsets.
+pub fn offsets_empty() -> Offsets {
+    Vec::new().into()
+}
+
+/// A list of n-dimensional coordinates, with shape `[len, ndim]`.
+#[derive(Clone, Debug)]
+pub struct Coords(Array2<u64>);
+
+impl Coords {
+    /// Construct a list of the coordinates in a tensor of the given `shape` at the given `offsets`.
+    pub fn from_offsets(offsets: Offsets, shape: &[u64]) -> Self {
+        let ndim = shape.len();
+        let mut coords = Array2::zeros((offsets.len(), ndim));
+
+        for (i, offset) in offsets.iter().enumerate() {
+            let mut offset = *offset;
+            for x in (0..ndim).rev() {
+                coords[[i, x]] = offset % shape[x];
+                offset /= shape[x];
+            }
+        }
+
+        Self(coords)
+    }
+
+    fn from_vec(coords: Vec<Vec<u64>>, ndim: usize) -> Self {
+        let len = coords.len();
+        let elements = coords.into_iter().flatten().collect();
+        let coords = Array2::from_shape_vec((len, ndim), elements).expect("coordinates");
+        Self(coords)
+    }
+
+    /// The number of coordinates in this list.
+    pub fn len(&self) -> usize {
+        self.0.nrows()
+    }
+
+    /// Return `true` if this list has no coordinates.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of dimensions of each coordinate in this list.
+    pub fn ndim(&self) -> usize {
+        self.0.ncols()
+    }
+
+    /// The offset of each coordinate in this list in a tensor of the given `shape`.
+    pub fn to_offsets(&self, shape: &[u64]) -> Offsets {
+        assert_eq!(shape.len(), self.ndim());
+
+        let mut strides = vec![1; shape.len()];
+        for x in (0..shape.len().saturating_sub(1)).rev() {
+            strides[x] = strides[x + 1] * shape[x + 1];
+        }
+
+        self.0
+            .rows()
+            .into_iter()
+            .map(|coord| {
+                coord
+                    .iter()
+                    .zip(&strides)
+                    .map(|(i, stride)| i * stride)
+                    .sum::<u64>()
+            })
+            .collect()
+    }
+
+    /// Copy the coordinates in this list into a `Vec`.
+    pub fn to_vec(&self) -> Vec<Vec<u64>> {
+        self.0
+            .rows()
+            .into_iter()
+            .map(|coord| coord.to_vec())
+            .collect()
+    }
+
+    /// Convert this list into a `Vec` of coordinates.
+    pub fn into_vec(self) -> Vec<Vec<u64>> {
+        self.to_vec()
+    }
+
+    /// Select the given `axes` of each coordinate in this list.
+    pub fn get(&self, axes: &[usize]) -> Self {
+        Self(self.0.select(Axis(1), axes))
+    }
+
+    /// Insert a new axis with index 0 into each coordinate in this list.
+    pub fn expand_dim(&self, axis: usize) -> Self {
+        let mut expanded = Array2::zeros((self.len(), self.ndim() + 1));
+        for x in 0..self.ndim() {
+            let dest = if x < axis { x } else { x + 1 };
+            expanded.column_mut(dest).assign(&self.0.column(x));
+        }
+
+        Self(expanded)
+    }
+
+    /// Remove the given `axis` from each coordinate in this list.
+    pub fn contract_dim(&self, axis: usize) -> Self {
+        let axes: Vec<usize> = (0..self.ndim()).filter(|x| *x != axis).collect();
+        self.get(&axes)
+    }
+
+    /// Reverse the given `axis` of each coordinate in this list, in a tensor of the given `shape`.
+    pub fn flip(mut self, shape: &[u64], axis: usize) -> Self {
+        let dim = shape[axis];
+        self.0.column_mut(axis).mapv_inplace(|i| dim - 1 - i);
+
+        self
+    }
+
+    /// Map each coordinate in this list, of a broadcast tensor, to a coordinate in the source
+    /// tensor of shape `source_shape`, given which of the broadcast axes are `broadcast`.
+    pub fn unbroadcast(&self, source_shape: &[u64], broadcast: &[bool]) -> Self {
+        let source_ndim = source_shape.len();
+        let offset = self.ndim() - source_ndim;
+
+        let mut source = Array2::zeros((self.len(), source_ndim));
+        for axis in 0..source_ndim {
+            if !broadcast[axis + offset] {
+                source
+                    .column_mut(axis)
+                    .assign(&self.0.column(axis + offset));
+            }
+        }
+
+        Self(source)
+    }
+
+    /// Map each coordinate in this list, of a slice, to a coordinate in the source tensor of shape
+    /// `source_shape`, given the `elided` axes and `offset` of the slice.
+    pub fn unslice(
+        &self,
+        source_shape: &[u64],
+        elided: &HashMap<usize, u64>,
+        offset: &HashMap<usize, u64>,
+    ) -> Self {
+        let mut source = Array2::zeros((self.len(), source_shape.len()));
+
+        let mut axis = 0;
+        for source_axis in 0..source_shape.len() {
+            let mut column = source.column_mut(source_axis);
+
+            if let Some(i) = elided.get(&source_axis) {
+                column.fill(*i);
+            } else {
+                let offset = offset.get(&source_axis).copied().unwrap_or(0);
+                column.assign(&self.0.column(axis).mapv(|i| i + offset));
+                axis += 1;
+            }
+        }
+
+        Self(source)
+    }
+
+    /// Map each coordinate in this list, of a source tensor, to a coordinate in a slice of shape
+    /// `shape`, given the `elided` axes and `offset` of the slice.
+    pub fn slice(
+        &self,
+        shape: &[u64],
+        elided: &HashMap<usize, u64>,
+        offset: &HashMap<usize, u64>,
+    ) -> Self {
+        let axes: Vec<usize> = (0..self.ndim())
+            .filter(|axis| !elided.contains_key(axis))
+            .collect();
+
+        debug_assert_eq!(axes.len(), shape.len());
+
+        let mut coords = self.get(&axes);
+        for (axis, source_axis) in axes.into_iter().enumerate() {
+            let offset = offset.get(&source_axis).copied().unwrap_or(0);
+            coords.0.column_mut(axis).mapv_inplace(|i| i - offset);
+        }
+
+        coords
+    }
+
+    /// Permute the axes of each coordinate in this list, so that axis `i` of the result is axis
+    /// `permutation[i]` of this list, or reverse them if no `permutation` is given.
+    pub fn transpose(&self, permutation: Option<&Vec<usize>>) -> Self {
+        match permutation {
+            Some(permutation) => self.get(permutation),
+            None => {
+                let axes: Vec<usize> = (0..self.ndim()).rev().collect();
+                self.get(&axes)
+            }
+        }
+    }
+}
+
+type OffsetStream<'a, E> = Pin<Box<dyn Stream<Item = Result<u64, E>> + Send + 'a>>;
+type CoordStream<'a, E> = Pin<Box<dyn Stream<Item = Result<Coords, E>> + Send + 'a>>;
+
+/// A stream of [`Coords`] in blocks of (at most) `block_size`, from a stream of coordinates.
+#[pin_project]
+pub struct CoordBlocks<S: Stream> {
+    #[pin]
+    source: stream::Chunks<S>,
+    ndim: usize,
+}
+
+impl<E, S: Stream<Item = Result<Vec<u64>, E>>> CoordBlocks<S> {
+    /// Construct a new stream of [`Coords`] from the given `source` of coordinates.
+    pub fn new(source: S, ndim: usize, block_size: usize) -> Self {
+        Self {
+            source: source.chunks(block_size),
+            ndim,
+        }
+    }
+}
+
+impl<E, S: Stream<Item = Result<Vec<u64>, E>>> Stream for CoordBlocks<S> {
+    type Item = Result<Coords, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cxt: &mut task::Context) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let ndim = *this.ndim;
+
+        let block = ready!(this.source.poll_next(cxt)).map(|coords| {
+            coords
+                .into_iter()
+                .collect::<Result<Vec<Vec<u64>>, E>>()
+                .map(|coords| Coords::from_vec(coords, ndim))
+        });
+
+        Poll::Ready(block)
+    }
+}
+
+/// A stream of the unique [`Coords`] in a sorted stream of `Coords`.
+pub struct CoordUnique<'a, E> {
+    coords: CoordStream<'a, E>,
+}
+
+impl<'a, E: Send + 'a> CoordUnique<'a, E> {
+    /// Construct a new stream of the unique coordinates in the given sorted `source`.
+    pub fn new<S>(source: S, shape: Vec<u64>, block_size: usize) -> Self
+    where
+        S: Stream<Item = Result<Coords, E>> + Send + 'a,
+    {
+        let mut last = None;
+        let offsets = coords_to_offsets(source, shape.clone()).try_filter(move |offset| {
+            let unique = last != Some(*offset);
+            last = Some(*offset);
+            future::ready(unique)
+        });
+
+        Self {
+            coords: offsets_to_coords(offsets, shape, block_size),
+        }
+    }
+}
+
+impl<'a, E> Stream for CoordUnique<'a, E> {
+    type Item = Result<Coords, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cxt: &mut task::Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().coords.as_mut().poll_next(cxt)
+    }
+}
+
+/// A stream of the sorted union of two sorted streams of [`Coords`].
+pub struct CoordMerge<'a, E> {
+    coords: CoordStream<'a, E>,
+}
+
+impl<'a, E: Send + 'a> CoordMerge<'a, E> {
+    /// Construct a new stream of the sorted union of the given sorted `left` and `right` streams.
+    pub fn new<L, R>(left: L, right: R, shape: Vec<u64>, block_size: usize) -> Self
+    where
+        L: Stream<Item = Result<Coords, E>> + Send + 'a,
+        R: Stream<Item = Result<Coords, E>> + Send + 'a,
+    {
+        enum Peek {
+            Offset(u64),
+            Error,
+            Done,
+        }
+
+        fn peek<E>(item: Option<&Result<u64, E>>) -> Peek {
+            match item {
+                Some(Ok(offset)) => Peek::Offset(*offset),
+                Some(Err(_)) => Peek::Error,
+                None => Peek::Done,
+            }
+        }
+
+        let left = Box::pin(coords_to_offsets(left, shape.clone()).peekable());
+        let right = Box::pin(coords_to_offsets(right, shape.clone()).peekable());
+
+        let offsets = stream::unfold((left, right), |(mut left, mut right)| async move {
+            let l = peek(left.as_mut().peek().await);
+            let r = peek(right.as_mut().peek().await);
+
+            let next = match (l, r) {
+                (Peek::Done, Peek::Done) => None,
+                (Peek::Error, _) | (Peek::Offset(_), Peek::Done) => left.next().await,
+                (_, Peek::Error) | (Peek::Done, Peek::Offset(_)) => right.next().await,
+                (Peek::Offset(l), Peek::Offset(r)) => match l.cmp(&r) {
+                    Ordering::Less => left.next().await,
+                    Ordering::Greater => right.next().await,
+                    Ordering::Equal => {
+                        right.next().await;
+                        left.next().await
+                    }
+                },
+            };
+
+            next.map(|offset| (offset, (left, right)))
+        });
+
+        Self {
+            coords: offsets_to_coords(offsets, shape, block_size),
+        }
+    }
+}
+
+impl<'a, E> Stream for CoordMerge<'a, E> {
+    type Item = Result<Coords, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cxt: &mut task::Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().coords.as_mut().poll_next(cxt)
+    }
+}
+
+fn coords_to_offsets<'a, E, S>(coords: S, shape: Vec<u64>) -> OffsetStream<'a, E>
+where
+    E: Send + 'a,
+    S: Stream<Item = Result<Coords, E>> + Send + 'a,
+{
+    let offsets = coords
+        .map_ok(move |coords| stream::iter(coords.to_offsets(&shape).to_vec()).map(Ok::<u64, E>))
+        .try_flatten();
+
+    Box::pin(offsets)
+}
+
+fn offsets_to_coords<'a, E, S>(offsets: S, shape: Vec<u64>, block_size: usize) -> CoordStream<'a, E>
+where
+    E: Send + 'a,
+    S: Stream<Item = Result<u64, E>> + Send + 'a,
+{
+    let coords = offsets.chunks(block_size).map(move |offsets| {
+        offsets
+            .into_iter()
+            .collect::<Result<Offsets, E>>()
+            .map(|offsets| Coords::from_offsets(offsets, &shape))
+    });
+
+    Box::pin(coords)
+}
+
+/// Sum each `stride` consecutive elements of the given stream of `blocks`, returning a stream of
+/// blocks of (at most) `block_size` sums with the given `dtype`.
+pub fn reduce_sum<'a, E, S>(
+    blocks: S,
+    dtype: NumberType,
+    block_size: usize,
+    stride: u64,
+) -> Box<dyn Stream<Item = Result<Array, E>> + Send + Unpin + 'a>
+where
+    E: Send + 'a,
+    S: Stream<Item = Result<Array, E>> + Send + Unpin + 'a,
+{
+    reduce(blocks, dtype, block_size, stride, Array::sum)
+}
+
+/// Multiply each `stride` consecutive elements of the given stream of `blocks`, returning a stream
+/// of blocks of (at most) `block_size` products with the given `dtype`.
+pub fn reduce_product<'a, E, S>(
+    blocks: S,
+    dtype: NumberType,
+    block_size: usize,
+    stride: u64,
+) -> Box<dyn Stream<Item = Result<Array, E>> + Send + Unpin + 'a>
+where
+    E: Send + 'a,
+    S: Stream<Item = Result<Array, E>> + Send + Unpin + 'a,
+{
+    reduce(blocks, dtype, block_size, stride, Array::product)
+}
+
+struct Reduce<S> {
+    blocks: S,
+    pending: Option<Array>,
+    reduced: Vec<Number>,
+    done: bool,
+}
+
+fn reduce<'a, E, S>(
+    blocks: S,
+    dtype: NumberType,
+    block_size: usize,
+    stride: u64,
+    reductor: fn(&Array) -> Number,
+) -> Box<dyn Stream<Item = Result<Array, E>> + Send + Unpin + 'a>
+where
+    E: Send + 'a,
+    S: Stream<Item = Result<Array, E>> + Send + Unpin + 'a,
+{
+    let stride = Ord::max(stride, 1) as usize;
+
+    let state = Reduce {
+        blocks,
+        pending: None,
+        reduced: Vec::with_capacity(block_size),
+        done: false,
+    };
+
+    let reduced = stream::unfold(state, move |mut state| async move {
+        loop {
+            if state.reduced.len() >= block_size {
+                let rest = state.reduced.split_off(block_size);
+                let block = mem::replace(&mut state.reduced, rest);
+                return Some((Ok(Array::from(block).cast_into(dtype)), state));
+            } else if state.done {
+                if state.reduced.is_empty() {
+                    return None;
+                }
+
+                let block = mem::take(&mut state.reduced);
+                return Some((Ok(Array::from(block).cast_into(dtype)), state));
+            }
+
+            match state.blocks.next().await {
+                Some(Ok(block)) => {
+                    let block = match state.pending.take() {
+                        Some(pending) => Array::concatenate(&pending, &block),
+                        None => block,
+                    };
+
+                    let whole = (block.len() / stride) * stride;
+                    for start in (0..whole).step_by(stride) {
+                        let values = block.slice(start, start + stride).expect("stride");
+                        state.reduced.push(reductor(&values));
+                    }
+
+                    if whole < block.len() {
+                        let rest = block.slice(whole, block.len()).expect("partial stride");
+                        state.pending = Some(rest);
+                    }
+                }
+                Some(Err(cause)) => {
+                    state.reduced.clear();
+                    state.pending = None;
+                    state.done = true;
+                    return Some((Err(cause), state));
+                }
+                None => {
+                    if let Some(pending) = state.pending.take() {
+                        state.reduced.push(reductor(&pending));
+                    }
+
+                    state.done = true;
+                }
+            }
+        }
+    });
+
+    Box::new(Box::pin(reduced))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coords(coords: Vec<Vec<u64>>) -> Coords {
+        let ndim = coords[0].len();
+        Coords::from_vec(coords, ndim)
+    }
+
+    #[test]
+    fn test_offsets() {
+        let shape = [2, 3, 4];
+        let offsets = Offsets::range(0, 24);
+        let coords = Coords::from_offsets(offsets.clone(), &shape);
+
+        assert_eq!(coords.len(), 24);
+        assert_eq!(coords.ndim(), 3);
+        assert_eq!(coords.to_vec()[17], vec![1, 1, 1]);
+        assert_eq!(coords.to_offsets(&shape).to_vec(), offsets.to_vec());
+    }
+
+    #[test]
+    fn test_coord_transforms() {
+        let source = coords(vec![vec![0, 1, 2], vec![1, 2, 3]]);
+
+        let transposed = source.transpose(Some(&vec![2, 0, 1]));
+        assert_eq!(transposed.to_vec(), vec![vec![2, 0, 1], vec![3, 1, 2]]);
+        assert_eq!(source.transpose(None).to_vec()[0], vec![2, 1, 0]);
+
+        let expanded = source.expand_dim(1);
+        assert_eq!(expanded.to_vec()[1], vec![1, 0, 2, 3]);
+        assert_eq!(expanded.contract_dim(1).to_vec(), source.to_vec());
+
+        let flipped = source.clone().flip(&[2, 3, 4], 2);
+        assert_eq!(flipped.to_vec(), vec![vec![0, 1, 1], vec![1, 2, 0]]);
+
+        let unbroadcast = source.unbroadcast(&[1, 4], &[false, true, false]);
+        assert_eq!(unbroadcast.to_vec(), vec![vec![0, 2], vec![0, 3]]);
+
+        let elided: HashMap<usize, u64> = [(0, 5)].iter().cloned().collect();
+        let offset: HashMap<usize, u64> = [(2, 10)].iter().cloned().collect();
+        let unsliced = source.get(&[1, 2]).unslice(&[8, 4, 20], &elided, &offset);
+        assert_eq!(unsliced.to_vec(), vec![vec![5, 1, 12], vec![5, 2, 13]]);
+        assert_eq!(
+            unsliced.slice(&[4, 10], &elided, &offset).to_vec(),
+            source.get(&[1, 2]).to_vec()
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let left = Array::from(vec![
+            Number::from(1u8),
+            Number::from(2u8),
+            Number::from(250u8),
+        ]);
+        let right = Array::from(vec![
+            Number::from(10u8),
+            Number::from(0u8),
+            Number::from(10u8),
+        ]);
+
+        assert_eq!(left.dtype(), NumberType::UInt(UIntType::U8));
+
+        let sum = &left + &right;
+        assert_eq!(
+            sum.to_vec(),
+            vec![Number::from(11u8), Number::from(2u8), Number::from(4u8)]
+        );
+
+        let quotient = &left / &right;
+        assert_eq!(quotient.get_value(1), Number::from(0u8));
+
+        let promoted = &left * Number::from(0.5f32);
+        assert_eq!(promoted.dtype(), NumberType::Float(FloatType::F32));
+        assert_eq!(promoted.get_value(0), Number::from(0.5f32));
+
+        let flags = left.gt_const(Number::from(1u8));
+        let expected = vec![Number::from(false), Number::from(true), Number::from(true)];
+        assert_eq!(flags.to_vec(), expected);
+        assert!(flags.any() && !flags.all());
+
+        assert_eq!(left.sum(), Number::from(253u64));
+        assert_eq!(
+            Array::constant(Number::from(true), 3).sum(),
+            Number::from(3u64)
+        );
+    }
+
+    #[test]
+    fn test_get_set() {
+        let mut array = Array::constant(Number::from(0i32), 5);
+        let indices = Offsets::from(vec![1, 3]);
+
+        array
+            .set(&indices, &Array::constant(Number::from(7i32), 1))
+            .unwrap();
+        assert_eq!(array.get(&indices).to_vec(), vec![Number::from(7i32); 2]);
+        let value = Array::constant(Number::from(1i32), 1);
+        assert!(array.set(&Offsets::from(vec![5]), &value).is_err());
+
+        let (left, right) = array.split(2).unwrap();
+        assert_eq!(left.len(), 2);
+        assert_eq!(right.to_vec(), array.slice(2, 5).unwrap().to_vec());
+    }
+
+    #[test]
+    fn test_sort_lanes() {
+        let block = Array::from(
+            vec![3i64, 1, 2, 6, 5, 4]
+                .into_iter()
+                .map(Number::from)
+                .collect::<Vec<_>>(),
+        );
+        let (values, indices) = sort_lanes(&block, 3, false).unwrap();
+
+        let expected: Vec<Number> = vec![3i64, 2, 1, 6, 5, 4]
+            .into_iter()
+            .map(Number::from)
+            .collect();
+        assert_eq!(values.to_vec(), expected);
+        assert_eq!(indices.to_vec(), vec![0, 2, 1, 0, 1, 2]);
+
+        assert!(sort_lanes(&Array::constant(true.into(), 2), 2, true).is_none());
+    }
+
+    #[test]
+    fn test_reduce() {
+        let blocks = vec![
+            Ok::<Array, ()>(Array::from(Offsets::range(0, 5))),
+            Ok(Array::from(Offsets::range(5, 9))),
+        ];
+
+        let reduced = reduce_sum(stream::iter(blocks), UIntType::U64.into(), 2, 3);
+        let reduced: Vec<Array> = futures::executor::block_on(reduced.try_collect()).unwrap();
+
+        assert_eq!(reduced.len(), 2);
+        assert_eq!(
+            reduced
+                .into_iter()
+                .flat_map(|block| block.to_vec())
+                .collect::<Vec<_>>(),
+            vec![Number::from(3u64), Number::from(12u64), Number::from(21u64)]
+        );
+    }
+
+    #[test]
+    fn test_coord_merge() {
+        let shape = vec![4, 4];
+        let left = stream::iter(vec![Ok::<Coords, ()>(coords(vec![vec![0, 1], vec![2, 2]]))]);
+        let right = stream::iter(vec![Ok(coords(vec![vec![0, 1], vec![1, 0], vec![3, 3]]))]);
+
+        let merged = CoordMerge::new(left, right, shape.clone(), 2);
+        let merged: Vec<Coords> = futures::executor::block_on(merged.try_collect()).unwrap();
+        let merged: Vec<Vec<u64>> = merged
+            .into_iter()
+            .flat_map(|coords| coords.into_vec())
+            .collect();
+        assert_eq!(merged, vec![vec![0, 1], vec![1, 0], vec![2, 2], vec![3, 3]]);
+
+        let repeated = stream::iter(vec![Ok::<Coords, ()>(coords(vec![
+            vec![0, 1],
+            vec![0, 1],
+            vec![3, 0],
+        ]))]);
+        let unique = CoordUnique::new(repeated, shape, 8);
+        let unique: Vec<Coords> = futures::executor::block_on(unique.try_collect()).unwrap();
+        assert_eq!(unique[0].to_vec(), vec![vec![0, 1], vec![3, 0]]);
+    }
+}
@@ -30,7 +30,8 @@ impl Iterator for AxisIter {
                 if at == &v.len() {
                     None
                 } else {
-                    Some(v[*at])
+                    *at += 1;
+                    Some(v[*at - 1])
                 }
             }
             Step(iter) => iter.next(),
@@ -145,6 +146,22 @@ impl fmt::Display for AxisBounds {
     }
 }
 
+/// Return the range of indices selected by the given [`AxisBounds`], if they are contiguous.
+fn axis_range(bound: &AxisBounds) -> Option<ops::Range<u64>> {
+    match bound {
+        AxisBounds::At(i) => Some(*i..(*i + 1)),
+        AxisBounds::In(range) => Some(range.clone()),
+        AxisBounds::Of(indices) if indices.is_empty() => None,
+        AxisBounds::Of(indices) => {
+            if indices.windows(2).all(|pair| pair[1] == pair[0] + 1) {
+                Some(indices[0]..(indices[indices.len() - 1] + 1))
+            } else {
+                None
+            }
+        }
+    }
+}
+
 /// `Tensor` bounds
 #[derive(Clone)]
 pub struct Bounds {
@@ -276,6 +293,75 @@ impl Bounds {
     pub fn size(&self) -> u64 {
         self.axes.iter().map(|bound| bound.dim()).product()
     }
+
+    /// Return the range of row-major offsets selected by these `Bounds` in a `Tensor` with the
+    /// given [`Shape`], or `None` if the selected elements are not contiguous.
+    pub fn to_offset_range(&self, shape: &Shape) -> Option<ops::Range<u64>> {
+        let mut ranges = self.to_offset_ranges(shape);
+        let mut contiguous = ranges.next().unwrap_or(0..0);
+        for range in ranges {
+            if range.start == contiguous.end {
+                contiguous.end = range.end;
+            } else {
+                return None;
+            }
+        }
+
+        Some(contiguous)
+    }
+
+    /// Split the elements selected by these `Bounds` in a `Tensor` with the given [`Shape`] into
+    /// contiguous ranges of row-major offsets, in order.
+    pub fn to_offset_ranges(
+        &self,
+        shape: &Shape,
+    ) -> Box<dyn Iterator<Item = ops::Range<u64>> + Send> {
+        let mut bounds = self.clone();
+        bounds.normalize(shape);
+
+        let strides = super::coord_bounds(shape);
+
+        let partial = bounds
+            .axes
+            .iter()
+            .zip(shape.iter())
+            .rposition(|(bound, dim)| bound != &AxisBounds::all(*dim));
+
+        let partial = match partial {
+            Some(axis) => axis,
+            None => return Box::new(iter::once(0..shape.size())),
+        };
+
+        // each run covers every axis after the innermost partial axis,
+        // as well as the partial axis itself if its bounds are contiguous
+        let (outer, start, len) = match axis_range(&bounds[partial]) {
+            Some(range) => (
+                partial,
+                range.start * strides[partial],
+                (range.end - range.start) * strides[partial],
+            ),
+            None => (partial + 1, 0, strides[partial]),
+        };
+
+        if len == 0 {
+            return Box::new(iter::empty());
+        } else if outer == 0 {
+            return Box::new(iter::once(start..(start + len)));
+        }
+
+        let outer = Bounds::from(bounds.axes[..outer].to_vec());
+        let ranges = outer.affected().map(move |coord| {
+            let offset = coord
+                .iter()
+                .zip(&strides)
+                .map(|(x, stride)| x * stride)
+                .sum::<u64>();
+
+            (offset + start)..(offset + start + len)
+        });
+
+        Box::new(ranges)
+    }
 }
 
 impl IntoIterator for Bounds {
@@ -567,3 +653,48 @@ impl fmt::Debug for Shape {
         fmt::Display::fmt(self, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_offset_range() {
+        let shape = Shape::from(vec![4, 3, 2]);
+
+        let bounds = Bounds::from(vec![AxisBounds::In(1..3)]);
+        assert_eq!(bounds.to_offset_range(&shape), Some(6..18));
+
+        let bounds = Bounds::from(vec![AxisBounds::At(2), AxisBounds::In(1..3)]);
+        assert_eq!(bounds.to_offset_range(&shape), Some(14..18));
+
+        let bounds = Bounds::from(vec![AxisBounds::Of(vec![1, 2]), AxisBounds::At(0)]);
+        assert_eq!(bounds.to_offset_range(&shape), None);
+
+        let bounds = Bounds::from(vec![AxisBounds::all(4), AxisBounds::At(1)]);
+        assert_eq!(bounds.to_offset_range(&shape), None);
+
+        let bounds = Bounds::all(&shape);
+        assert_eq!(bounds.to_offset_range(&shape), Some(0..24));
+    }
+
+    #[test]
+    fn test_to_offset_ranges() {
+        let shape = Shape::from(vec![4, 3, 2]);
+
+        let bounds = Bounds::from(vec![AxisBounds::In(1..3), AxisBounds::In(1..3)]);
+        let ranges: Vec<_> = bounds.to_offset_ranges(&shape).collect();
+        assert_eq!(ranges, vec![8..12, 14..18]);
+
+        let bounds = Bounds::from(vec![AxisBounds::At(3), AxisBounds::Of(vec![0, 2])]);
+        let ranges: Vec<_> = bounds.to_offset_ranges(&shape).collect();
+        assert_eq!(ranges, vec![18..20, 22..24]);
+
+        let bounds = Bounds::from(vec![
+            AxisBounds::all(4),
+            AxisBounds::all(3),
+            AxisBounds::At(1),
+        ]);
+        assert_eq!(bounds.to_offset_ranges(&shape).count(), 12);
+    }
+}
@@ -1,6 +1,5 @@
 use std::fmt;
 
-use afarray::{Array, ArrayExt, Coords, Offsets};
 use async_trait::async_trait;
 use futures::future::{self, TryFutureExt};
 use futures::stream::{self, StreamExt, TryStreamExt};
@@ -12,18 +11,22 @@ use tc_btree::*;
 use tc_error::*;
 use tc_transact::fs::{Dir, File};
 use tc_transact::{Transaction, TxnId};
-use tc_value::{FloatInstance, Number, NumberClass, NumberInstance, NumberType};
+use tc_value::{FloatInstance, Number, NumberClass, NumberInstance, NumberType, Promote};
 use tcgeneric::{TCBoxStream, TCBoxTryFuture, TCBoxTryStream, Tuple};
 
+use crate::block::{
+    product_dtype, reduce_product, reduce_sum, sum_dtype, Array, ArrayExt, Coords, Offsets,
+};
 use crate::sparse::{SparseAccess, SparseAccessor};
 use crate::stream::{Read, ReadValueAt};
 use crate::{
     transform, Bounds, Coord, Phantom, Shape, TensorAccess, TensorReduce, TensorType, ERR_INF,
-    ERR_NAN,
+    ERR_NAN, ERR_VIEW_WRITE,
 };
 
 use super::file::{BlockListFile, BlockListFileSlice};
 use super::stream::SparseValueStream;
+use super::table::BlockListTable;
 use super::{DenseTensor, PER_BLOCK};
 
 /// Common [`DenseTensor`] access methods
@@ -113,8 +116,10 @@ pub enum DenseAccessor<FD, FS, D, T> {
     File(BlockListFile<FD, FS, D, T>),
     Reduce(Box<BlockListReduce<FD, FS, D, T, Self>>),
     Reshape(Box<BlockListReshape<FD, FS, D, T, Self>>),
+    Select(Box<BlockListSelect<FD, FS, D, T, Self, Self, Self>>),
     Slice(BlockListFileSlice<FD, FS, D, T>),
     Sparse(BlockListSparse<FD, FS, D, T, SparseAccessor<FD, FS, D, T>>),
+    Table(BlockListTable<FD, FS, D, T>),
     Transpose(Box<BlockListTranspose<FD, FS, D, T, Self>>),
     Unary(Box<BlockListUnary<FD, FS, D, T, Self>>),
 }
@@ -132,7 +137,9 @@ macro_rules! dispatch {
             Self::Flip($var) => $call,
             Self::Reduce($var) => $call,
             Self::Reshape($var) => $call,
+            Self::Select($var) => $call,
             Self::Sparse($var) => $call,
+            Self::Table($var) => $call,
             Self::Transpose($var) => $call,
             Self::Unary($var) => $call,
         }
@@ -226,14 +233,14 @@ where
     ) -> TCResult<()> {
         match self {
             Self::File(file) => file.write(txn, bounds, value).await,
-            _ => Err(TCError::unsupported("cannot write to a Tensor view")),
+            _ => Err(TCError::unsupported(ERR_VIEW_WRITE)),
         }
     }
 
     async fn write_value(&self, txn_id: TxnId, bounds: Bounds, number: Number) -> TCResult<()> {
         match self {
             Self::File(file) => file.write_value(txn_id, bounds, number).await,
-            _ => Err(TCError::unsupported("cannot write to a Tensor view")),
+            _ => Err(TCError::unsupported(ERR_VIEW_WRITE)),
         }
     }
 }
@@ -377,19 +384,23 @@ where
         debug!("BlockListCombine::block_stream");
 
         Box::pin(async move {
+            let operand_dtype = self.left.dtype().promote(self.right.dtype());
             let left = self.left.block_stream(txn.clone());
             let right = self.right.block_stream(txn);
             let (left, right) = try_join!(left, right)?;
 
             let combinator = self.combinator;
+            let dtype = self.dtype;
             let blocks = left
                 .zip(right)
                 .map(|(l, r)| Ok((l?, r?)))
                 .map_ok(move |(l, r)| {
+                    let l = cast_block(l, operand_dtype);
+                    let r = cast_block(r, operand_dtype);
                     let combined = combinator(&l, &r);
                     debug_assert_eq!(combined.len(), l.len());
                     debug_assert_eq!(combined.len(), r.len());
-                    combined
+                    cast_block(combined, dtype)
                 })
                 .map(|result| {
                     result.and_then(|array| {
@@ -449,12 +460,15 @@ where
     }
 
     async fn read_values(self, txn: Self::Txn, coords: Coords) -> TCResult<Array> {
+        let operand_dtype = self.left.dtype().promote(self.right.dtype());
         let (left, right) = try_join!(
             self.left.read_values(txn.clone(), coords.clone()),
             self.right.read_values(txn, coords)
         )?;
 
-        let values = (self.combinator)(&left, &right);
+        let left = cast_block(left, operand_dtype);
+        let right = cast_block(right, operand_dtype);
+        let values = cast_block((self.combinator)(&left, &right), self.dtype);
         if values.is_infinite().any() {
             Err(TCError::unsupported(ERR_INF))
         } else if values.is_nan().any() {
@@ -480,11 +494,14 @@ where
 
     fn read_value_at<'a>(self, txn: Self::Txn, coord: Coord) -> Read<'a> {
         Box::pin(async move {
+            let operand_dtype = self.left.dtype().promote(self.right.dtype());
             let left = self.left.read_value_at(txn.clone(), coord.to_vec());
             let right = self.right.read_value_at(txn, coord);
             let ((coord, left), (_, right)) = try_join!(left, right)?;
 
-            let value = (self.value_combinator)(left, right);
+            let left = left.into_type(operand_dtype);
+            let right = right.into_type(operand_dtype);
+            let value = (self.value_combinator)(left, right).into_type(self.dtype);
             if value.is_infinite() {
                 Err(TCError::unsupported(ERR_INF))
             } else if value.is_nan() {
@@ -496,12 +513,240 @@ where
     }
 }
 
+// cast the given block to `dtype`, if it does not already have that type
+#[inline]
+fn cast_block(block: Array, dtype: NumberType) -> Array {
+    if block.dtype() == dtype {
+        block
+    } else {
+        block.cast_into(dtype)
+    }
+}
+
 impl<FD, FS, D, T, L, R> fmt::Display for BlockListCombine<FD, FS, D, T, L, R> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("dense Tensor-Tensor op")
     }
 }
 
+#[derive(Clone)]
+pub struct BlockListSelect<FD, FS, D, T, C, L, R> {
+    cond: C,
+    then: L,
+    or_else: R,
+    dtype: NumberType,
+    phantom: Phantom<FD, FS, D, T>,
+}
+
+impl<FD, FS, D, T, C, L, R> BlockListSelect<FD, FS, D, T, C, L, R>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    C: DenseAccess<FD, FS, D, T>,
+    L: DenseAccess<FD, FS, D, T>,
+    R: DenseAccess<FD, FS, D, T>,
+{
+    pub fn new(cond: C, then: L, or_else: R) -> TCResult<Self> {
+        if cond.dtype() != NumberType::Bool {
+            return Err(TCError::bad_request(
+                "the condition of a Tensor selection must be boolean, not",
+                cond.dtype(),
+            ));
+        }
+
+        if then.shape() != cond.shape() || or_else.shape() != cond.shape() {
+            return Err(TCError::bad_request(
+                format!(
+                    "cannot select from shapes {} and {} with a condition of shape",
+                    then.shape(),
+                    or_else.shape()
+                ),
+                cond.shape(),
+            ));
+        }
+
+        let dtype = then.dtype().promote(or_else.dtype());
+
+        Ok(Self {
+            cond,
+            then,
+            or_else,
+            dtype,
+            phantom: Phantom::default(),
+        })
+    }
+}
+
+impl<FD, FS, D, T, C, L, R> TensorAccess for BlockListSelect<FD, FS, D, T, C, L, R>
+where
+    FD: File<Array>,
+    FS: File<Node>,
+    D: Dir,
+    T: Transaction<D>,
+    C: DenseAccess<FD, FS, D, T>,
+    L: DenseAccess<FD, FS, D, T>,
+    R: DenseAccess<FD, FS, D, T>,
+{
+    fn dtype(&self) -> NumberType {
+        self.dtype
+    }
+
+    fn ndim(&self) -> usize {
+        self.cond.ndim()
+    }
+
+    fn shape(&'_ self) -> &'_ Shape {
+        self.cond.shape()
+    }
+
+    fn size(&self) -> u64 {
+        self.cond.size()
+    }
+}
+
+#[async_trait]
+impl<FD, FS, D, T, C, L, R> DenseAccess<FD, FS, D, T> for BlockListSelect<FD, FS, D, T, C, L, R>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+    C: DenseAccess<FD, FS, D, T>,
+    L: DenseAccess<FD, FS, D, T>,
+    R: DenseAccess<FD, FS, D, T>,
+{
+    type Slice = BlockListSelect<FD, FS, D, T, C::Slice, L::Slice, R::Slice>;
+    type Transpose = BlockListSelect<FD, FS, D, T, C::Transpose, L::Transpose, R::Transpose>;
+
+    fn accessor(self) -> DenseAccessor<FD, FS, D, T> {
+        let select = BlockListSelect {
+            cond: self.cond.accessor(),
+            then: self.then.accessor(),
+            or_else: self.or_else.accessor(),
+            dtype: self.dtype,
+            phantom: self.phantom,
+        };
+
+        DenseAccessor::Select(Box::new(select))
+    }
+
+    fn block_stream<'a>(self, txn: T) -> TCBoxTryFuture<'a, TCBoxTryStream<'a, Array>> {
+        debug!("BlockListSelect::block_stream");
+
+        Box::pin(async move {
+            let (cond, then, or_else) = try_join!(
+                self.cond.block_stream(txn.clone()),
+                self.then.block_stream(txn.clone()),
+                self.or_else.block_stream(txn)
+            )?;
+
+            let dtype = self.dtype;
+            let blocks = cond
+                .zip(then)
+                .zip(or_else)
+                .map(|((cond, then), or_else)| Ok((cond?, then?, or_else?)))
+                .map_ok(move |(cond, then, or_else)| {
+                    let then = cast_block(then, dtype);
+                    let or_else = cast_block(or_else, dtype);
+                    let selected = select_block(&cond, &then, &or_else);
+                    debug_assert_eq!(selected.len(), cond.len());
+                    selected
+                });
+
+            let blocks: TCBoxTryStream<'a, Array> = Box::pin(blocks);
+            Ok(blocks)
+        })
+    }
+
+    fn slice(self, bounds: Bounds) -> TCResult<Self::Slice> {
+        debug!("slice {} from BlockListSelect", bounds);
+
+        let cond = self.cond.slice(bounds.clone())?;
+        let then = self.then.slice(bounds.clone())?;
+        let or_else = self.or_else.slice(bounds)?;
+
+        BlockListSelect::new(cond, then, or_else)
+    }
+
+    fn transpose(self, permutation: Option<Vec<usize>>) -> TCResult<Self::Transpose> {
+        debug!("BlockListSelect::transpose {:?}", permutation);
+
+        let cond = self.cond.transpose(permutation.clone())?;
+        let then = self.then.transpose(permutation.clone())?;
+        let or_else = self.or_else.transpose(permutation)?;
+
+        BlockListSelect::new(cond, then, or_else)
+    }
+
+    async fn read_values(self, txn: Self::Txn, coords: Coords) -> TCResult<Array> {
+        let (cond, then, or_else) = try_join!(
+            self.cond.read_values(txn.clone(), coords.clone()),
+            self.then.read_values(txn.clone(), coords.clone()),
+            self.or_else.read_values(txn, coords)
+        )?;
+
+        let then = cast_block(then, self.dtype);
+        let or_else = cast_block(or_else, self.dtype);
+        Ok(select_block(&cond, &then, &or_else))
+    }
+}
+
+impl<FD, FS, D, T, C, L, R> ReadValueAt<D> for BlockListSelect<FD, FS, D, T, C, L, R>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+    C: DenseAccess<FD, FS, D, T>,
+    L: DenseAccess<FD, FS, D, T>,
+    R: DenseAccess<FD, FS, D, T>,
+{
+    type Txn = T;
+
+    fn read_value_at<'a>(self, txn: Self::Txn, coord: Coord) -> Read<'a> {
+        Box::pin(async move {
+            let dtype = self.dtype;
+            let (coord, cond) = self.cond.read_value_at(txn.clone(), coord).await?;
+
+            let (coord, value) = if cond == cond.class().zero() {
+                self.or_else.read_value_at(txn, coord).await?
+            } else {
+                self.then.read_value_at(txn, coord).await?
+            };
+
+            Ok((coord, value.into_type(dtype)))
+        })
+    }
+}
+
+impl<FD, FS, D, T, C, L, R> fmt::Display for BlockListSelect<FD, FS, D, T, C, L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("dense Tensor selection")
+    }
+}
+
+// select each element of `then` where `cond` is true, and of `or_else` where it's false
+fn select_block(cond: &Array, then: &Array, or_else: &Array) -> Array {
+    debug_assert_eq!(then.dtype(), or_else.dtype());
+
+    if then.dtype() == NumberType::Bool {
+        let then = then.and(cond);
+        let or_else = or_else.and(&cond.not());
+        then.or(&or_else)
+    } else {
+        let keep = cast_block(cond.clone(), then.dtype());
+        let skip = cast_block(cond.not(), then.dtype());
+        &(then * &keep) + &(or_else * &skip)
+    }
+}
+
 #[derive(Clone)]
 pub struct BlockListConst<FD, FS, D, T, B> {
     source: B,
@@ -540,7 +785,8 @@ where
 {
     fn dtype(&self) -> NumberType {
         let combinator = self.value_combinator;
-        combinator(self.source.dtype().zero(), self.other.class().zero()).class()
+        let dtype = self.source.dtype().promote(self.other.class());
+        combinator(dtype.zero(), dtype.zero()).class()
     }
 
     fn ndim(&self) -> usize {
@@ -583,10 +829,12 @@ where
     fn block_stream<'a>(self, txn: T) -> TCBoxTryFuture<'a, TCBoxTryStream<'a, Array>> {
         Box::pin(async move {
             let combinator = self.combinator;
-            let right = self.other;
+            let operand_dtype = self.source.dtype().promote(self.other.class());
+            let right = self.other.into_type(operand_dtype);
 
             let left = self.source.block_stream(txn).await?;
-            let blocks = left.map_ok(move |block| combinator(block, right));
+            let blocks =
+                left.map_ok(move |block| combinator(cast_block(block, operand_dtype), right));
             let blocks: TCBoxTryStream<'a, Array> = Box::pin(blocks);
             Ok(blocks)
         })
@@ -614,10 +862,11 @@ where
 
     async fn read_values(self, txn: Self::Txn, coords: Coords) -> TCResult<Array> {
         let combinator = self.combinator;
-        let other = self.other;
+        let operand_dtype = self.source.dtype().promote(self.other.class());
+        let other = self.other.into_type(operand_dtype);
         self.source
             .read_values(txn, coords)
-            .map_ok(|values| combinator(values, other))
+            .map_ok(|values| combinator(cast_block(values, operand_dtype), other))
             .await
     }
 }
@@ -634,11 +883,12 @@ where
 
     fn read_value_at<'a>(self, txn: Self::Txn, coord: Coord) -> Read<'a> {
         let combinator = self.value_combinator;
-        let other = self.other;
+        let operand_dtype = self.source.dtype().promote(self.other.class());
+        let other = self.other.into_type(operand_dtype);
         let read = self
             .source
             .read_value_at(txn, coord)
-            .map_ok(move |(coord, val)| (coord, combinator(val, other)));
+            .map_ok(move |(coord, val)| (coord, combinator(val.into_type(operand_dtype), other)));
 
         Box::pin(read)
     }
@@ -1261,9 +1511,9 @@ impl Reductor {
     fn call(self, blocks: TCBoxTryStream<Array>) -> TCBoxTryStream<Array> {
         let reduced = match self {
             Self::Product(dtype, stride) => {
-                afarray::reduce_product(blocks, dtype, PER_BLOCK, stride)
+                reduce_product(blocks, dtype, PER_BLOCK, stride)
             }
-            Self::Sum(dtype, stride) => afarray::reduce_sum(blocks, dtype, PER_BLOCK, stride),
+            Self::Sum(dtype, stride) => reduce_sum(blocks, dtype, PER_BLOCK, stride),
         };
 
         std::pin::Pin::new(reduced)
@@ -1293,7 +1543,7 @@ where
 {
     pub fn product(source: B, axis: usize) -> TCResult<Self> {
         let rebase = transform::Reduce::new(source.shape().clone(), axis)?;
-        let dtype = afarray::product_dtype(source.dtype());
+        let dtype = product_dtype(source.dtype());
         let stride = source.size() / (source.size() / source.shape()[axis]);
 
         Ok(BlockListReduce {
@@ -1306,7 +1556,7 @@ where
 
     pub fn sum(source: B, axis: usize) -> TCResult<Self> {
         let rebase = transform::Reduce::new(source.shape().clone(), axis)?;
-        let dtype = afarray::sum_dtype(source.dtype());
+        let dtype = sum_dtype(source.dtype());
         let stride = source.size() / (source.size() / source.shape()[axis]);
 
         Ok(BlockListReduce {
@@ -1,10 +1,8 @@
 use std::fmt;
 use std::iter::{self, FromIterator};
 use std::marker::PhantomData;
-use std::ops::Deref;
+use std::ops;
 
-use afarray::{Array, ArrayExt, ArrayInstance, CoordBlocks, Coords, Offsets};
-use arrayfire as af;
 use async_trait::async_trait;
 use destream::de;
 use futures::stream::{self, Stream, StreamExt, TryStreamExt};
@@ -16,10 +14,11 @@ use strided::Stride;
 use tc_btree::Node;
 use tc_error::*;
 use tc_transact::fs::{BlockId, CopyFrom, Dir, File, Persist, Restore};
-use tc_transact::{Transact, Transaction, TxnId};
-use tc_value::{Number, NumberClass, NumberInstance, NumberType};
+use tc_transact::{Isolation, Transact, Transaction, TxnId};
+use tc_value::{Number, NumberClass, NumberInstance, NumberType, Promote};
 use tcgeneric::{TCBoxTryFuture, TCBoxTryStream};
 
+use crate::block::{self, Array, ArrayExt, ArrayInstance, CoordBlocks, Coords, Element, Offsets};
 use crate::stream::{Read, ReadValueAt};
 use crate::transform;
 use crate::{coord_bounds, Bounds, Coord, Schema, Shape, TensorAccess, TensorType};
@@ -187,7 +186,7 @@ where
         start: Number,
         stop: Number,
     ) -> TCResult<Self> {
-        let dtype = Ord::max(start.class(), stop.class());
+        let dtype = start.class().promote(stop.class());
         let step = (stop - start) / Number::from(shape.size() as f32);
 
         debug!(
@@ -270,6 +269,44 @@ where
             .map_err(TCError::from)
     }
 
+    /// Write the given `value` to each of the given contiguous ranges of offsets, with a single
+    /// assignment per range within each block.
+    async fn write_value_ranges<I>(&self, txn_id: TxnId, ranges: I, value: Number) -> TCResult<()>
+    where
+        I: Iterator<Item = ops::Range<u64>> + Send,
+    {
+        let value = value.into_type(self.dtype());
+        let per_block = PER_BLOCK as u64;
+
+        let mut block: Option<(u64, FD::Write)> = None;
+        for range in ranges {
+            let mut start = range.start;
+            while start < range.end {
+                let block_id = start / per_block;
+                let end = Ord::min((block_id + 1) * per_block, range.end);
+
+                if block.as_ref().map(|(id, _)| *id) != Some(block_id) {
+                    // release the lock on the last block before acquiring the next
+                    drop(block.take());
+
+                    let guard = self.file.write_block(txn_id, block_id.into()).await?;
+                    block = Some((block_id, guard));
+                }
+
+                let (_, guard) = block.as_mut().expect("dense tensor block");
+
+                let offset = start % per_block;
+                let indices = ArrayExt::range(offset, offset + (end - start));
+                let values = Array::constant(value.clone(), (end - start) as usize);
+                guard.set(&indices, &values)?;
+
+                start = end;
+            }
+        }
+
+        Ok(())
+    }
+
     async fn overwrite<B: DenseAccess<FD, FS, D, T>>(&self, txn: T, value: B) -> TCResult<()> {
         if value.shape() != self.shape() {
             return Err(TCError::unsupported(format!(
@@ -294,6 +331,88 @@ where
             .try_fold((), |_, _| future::ready(Ok(())))
             .await
     }
+
+    /// Update each block of this `BlockListFile` in place by combining it with the corresponding
+    /// block of `other`, without allocating a new file.
+    ///
+    /// Returns an error if the blocks of `other` are not aligned with the blocks of this file.
+    pub async fn combine_assign<B: DenseAccess<FD, FS, D, T>>(
+        &self,
+        txn: T,
+        other: B,
+        combinator: fn(&Array, &Array) -> TCResult<Array>,
+    ) -> TCResult<()> {
+        if other.shape() != self.shape() {
+            return Err(TCError::unsupported(format!(
+                "cannot update a Tensor of shape {} in place with one of shape {}",
+                self.shape(),
+                other.shape()
+            )));
+        }
+
+        let txn_id = *txn.id();
+        let dtype = self.dtype();
+        let num_blocks = div_ceil(self.size(), PER_BLOCK as u64);
+        let contents = other.block_stream(txn).await?;
+        let updated = stream::iter((0..num_blocks).map(BlockId::from))
+            .zip(contents)
+            .map(|(block_id, r)| r.map(|array| (block_id, array)))
+            .map_ok(|(block_id, array)| async move {
+                let mut block = self.file.write_block(txn_id, block_id).await?;
+                if block.len() != array.len() {
+                    return Err(TCError::new(
+                        ErrorType::Internal,
+                        format!(
+                            "block {} of a Tensor has {} elements but the block to combine with it has {}",
+                            block_id,
+                            block.len(),
+                            array.len()
+                        ),
+                    ));
+                }
+
+                let combined = combinator(&block, &array)?;
+                *block = combined.cast_into(dtype);
+                Ok(())
+            })
+            .try_buffer_unordered(num_cpus::get())
+            .try_fold(0u64, |updated, ()| future::ready(Ok(updated + 1)))
+            .await?;
+
+        if updated == num_blocks {
+            Ok(())
+        } else {
+            Err(TCError::new(
+                ErrorType::Internal,
+                format!(
+                    "updated {} blocks of a Tensor in place but expected to update {}",
+                    updated, num_blocks
+                ),
+            ))
+        }
+    }
+
+    /// Update each block of this `BlockListFile` in place by combining it with the given scalar,
+    /// without allocating a new file.
+    pub async fn combine_assign_const(
+        &self,
+        txn_id: TxnId,
+        other: Number,
+        combinator: fn(&Array, Number) -> Array,
+    ) -> TCResult<()> {
+        let dtype = self.dtype();
+        let num_blocks = div_ceil(self.size(), PER_BLOCK as u64);
+        stream::iter((0..num_blocks).map(BlockId::from))
+            .map(|block_id| async move {
+                let mut block = self.file.write_block(txn_id, block_id).await?;
+                let combined = combinator(&block, other);
+                *block = combined.cast_into(dtype);
+                TCResult::Ok(())
+            })
+            .buffer_unordered(num_cpus::get())
+            .try_fold((), |_, _| future::ready(Ok(())))
+            .await
+    }
 }
 
 impl<FD: Send, FS: Send, D: Send, T: Send> TensorAccess for BlockListFile<FD, FS, D, T> {
@@ -339,12 +458,20 @@ where
                 return Ok(blocks);
             }
 
+            let txn_id = *txn.id();
+            let isolation = txn.isolation();
             let file = self.file;
             let block_stream = Box::pin(
                 stream::iter(0..(div_ceil(size, PER_BLOCK as u64)))
                     .map(BlockId::from)
-                    .then(move |block_id| file.clone().read_block_owned(*txn.id(), block_id))
-                    .map_ok(|block| (*block).clone()),
+                    .then(move |block_id| {
+                        let file = file.clone();
+                        async move {
+                            file.read_block_isolated(txn_id, block_id, isolation)
+                                .map_ok(|block| (*block).clone())
+                                .await
+                        }
+                    }),
             );
 
             let block_stream: TCBoxTryStream<Array> = Box::pin(block_stream);
@@ -362,23 +489,22 @@ where
 
     async fn read_values(self, txn: Self::Txn, coords: Coords) -> TCResult<Array> {
         let txn_id = *txn.id();
-        let per_block = ArrayExt::from(&[PER_BLOCK as u64][..]);
-
+        let isolation = txn.isolation();
         let offsets = coords.to_offsets(self.shape());
-        let block_offsets = &offsets / &per_block;
+        let block_offsets = block::offsets_div(&offsets, PER_BLOCK as u64);
         let block_ids = block_offsets.unique(false).to_vec();
 
         let values = Array::constant(self.dtype().zero(), coords.len());
         let file = self.file;
         stream::iter(block_ids)
             .map(|block_id| {
-                let af_block_id = af::Array::new(&[block_id], af::Dim4::new(&[1, 1, 1, 1]));
-                let mask = ArrayExt::from(af::eq(block_offsets.deref(), &af_block_id, true));
-                let indices = (&offsets % &per_block).deref() * mask.deref();
-                (block_id, mask.into(), indices.into())
+                let mask = block::offsets_eq(&block_offsets, block_id);
+                let indices = block::offsets_rem(&offsets, PER_BLOCK as u64);
+                let indices = block::offsets_mask(&indices, &mask);
+                (block_id, mask.into(), indices)
             })
             .map(|(block_id, mask, indices)| {
-                file.read_block(txn_id, block_id.into())
+                file.read_block_isolated(txn_id, block_id.into(), isolation)
                     .map_ok(move |block| block.get(&indices))
                     .map_ok(move |block_values| &block_values * &mask)
             })
@@ -439,38 +565,30 @@ where
 
         let blocks = value.block_stream(txn).await?;
 
-        let af_per_block = af::constant(PER_BLOCK as u64, af::Dim4::new(&[1, 1, 1, 1]));
         stream::iter(offsets)
             .zip(blocks)
             .map(|(offsets, r)| r.map(|array| (offsets, array)))
-            .map_ok(|(offsets, array)| {
-                let af_per_block = af_per_block.clone();
-
-                async move {
-                    let indices: ArrayExt<u64> =
-                        af::modulo(offsets.deref(), &af_per_block, true).into();
+            .map_ok(|(offsets, array)| async move {
+                let indices = block::offsets_rem(&offsets, PER_BLOCK as u64);
 
-                    let block_offsets = af::div(offsets.deref(), &af_per_block, true);
-                    let block_ids = ArrayExt::from(af::set_unique(&block_offsets, true)).to_vec();
+                let block_offsets = block::offsets_div(&offsets, PER_BLOCK as u64);
+                let block_ids = block_offsets.unique(true).to_vec();
 
-                    let mut start = 0;
-                    for block_id in block_ids.into_iter() {
-                        let af_block_id = ArrayExt::from(&[block_id][..]);
-                        let (len, _) =
-                            af::sum_all(&af::eq(&block_offsets, af_block_id.deref(), true));
-                        let end = start + len as usize;
-                        let indices = indices.slice(start, end);
-                        let array = array.slice(start, end).map_err(TCError::from)?;
-
-                        let mut block = self.file.write_block(txn_id, block_id.into()).await?;
+                let mut start = 0;
+                for block_id in block_ids.into_iter() {
+                    let len = block::offsets_count(&block_offsets, block_id);
+                    let end = start + len;
+                    let indices = indices.slice(start, end);
+                    let array = array.slice(start, end).map_err(TCError::from)?;
 
-                        block.set(&indices, &array)?;
+                    let mut block = self.file.write_block(txn_id, block_id.into()).await?;
 
-                        start = end;
-                    }
+                    block.set(&indices, &array)?;
 
-                    Ok(())
+                    start = end;
                 }
+
+                Ok(())
             })
             .try_buffer_unordered(num_cpus::get())
             .try_fold((), |(), ()| future::ready(Ok(())))
@@ -487,6 +605,15 @@ where
 
         bounds.normalize(self.shape());
 
+        let mut ranges = bounds.to_offset_ranges(self.shape()).peekable();
+        if let Some(range) = ranges.peek() {
+            if range.end - range.start > 1 {
+                return self.write_value_ranges(txn_id, ranges, value).await;
+            }
+        } else {
+            return Ok(());
+        }
+
         let coords = stream::iter(bounds.affected().map(TCResult::Ok));
         CoordBlocks::new(coords, bounds.len(), PER_BLOCK)
             .map_ok(|coords| {
@@ -542,7 +669,10 @@ where
                 .sum();
 
             let block_id = BlockId::from(offset / PER_BLOCK as u64);
-            let block = self.file.read_block(*txn.id(), block_id).await?;
+            let block = self
+                .file
+                .read_block_isolated(*txn.id(), block_id, txn.isolation())
+                .await?;
 
             let value = block.get_value((offset % PER_BLOCK as u64) as usize);
 
@@ -602,7 +732,50 @@ where
         &self.schema
     }
 
-    async fn load(_txn: &T, schema: Self::Schema, file: Self::Store) -> TCResult<Self> {
+    async fn load(txn: &T, schema: Self::Schema, file: Self::Store) -> TCResult<Self> {
+        let txn_id = *txn.id();
+        let size = schema.shape.size();
+        let num_blocks = div_ceil(size, PER_BLOCK as u64);
+
+        let block_ids = file.block_ids(txn_id).await?;
+        if block_ids.len() as u64 != num_blocks {
+            return Err(TCError::new(
+                ErrorType::Internal,
+                format!(
+                    "dense tensor of shape {} requires {} blocks but found {}",
+                    schema.shape,
+                    num_blocks,
+                    block_ids.len()
+                ),
+            ));
+        }
+
+        if let Some(block_id) = (0..num_blocks)
+            .map(BlockId::from)
+            .find(|block_id| !block_ids.contains(block_id))
+        {
+            return Err(TCError::new(
+                ErrorType::Internal,
+                format!(
+                    "dense tensor of shape {} is missing block {}",
+                    schema.shape, block_id
+                ),
+            ));
+        }
+
+        stream::iter(0..num_blocks)
+            .map(|block_id| {
+                let file = &file;
+                let schema = &schema;
+                async move {
+                    let block = file.read_block(txn_id, BlockId::from(block_id)).await?;
+                    validate_block(schema, block_id, &block)
+                }
+            })
+            .buffer_unordered(num_cpus::get())
+            .try_fold((), |_, _| future::ready(Ok(())))
+            .await?;
+
         Ok(Self::new(file, schema))
     }
 }
@@ -727,7 +900,7 @@ impl<'a, F: File<Array>> BlockListVisitor<'a, F> {
         Self { txn_id, file }
     }
 
-    async fn create_block<T: af::HasAfEnum, E: de::Error>(
+    async fn create_block<T: Element, E: de::Error>(
         &self,
         block_id: u64,
         block: ArrayExt<T>,
@@ -746,7 +919,7 @@ impl<'a, F: File<Array>> BlockListVisitor<'a, F> {
 
 impl<'a, F: File<Array>> BlockListVisitor<'a, F> {
     async fn visit_array<
-        T: af::HasAfEnum + Clone + Copy + Default,
+        T: Element + Clone + Copy + Default,
         A: de::ArrayAccess<T>,
         const BUF_SIZE: usize,
     >(
@@ -875,8 +1048,8 @@ struct ComplexBlockListVisitor<'a, F> {
 
 impl<'a, F: File<Array>> ComplexBlockListVisitor<'a, F> {
     async fn visit_array<
-        C: af::HasAfEnum,
-        T: af::HasAfEnum + Clone + Copy + Default,
+        C: Element,
+        T: Element + Clone + Copy + Default,
         A: de::ArrayAccess<T>,
         const BUF_SIZE: usize,
     >(
@@ -1005,11 +1178,27 @@ where
         }
 
         let txn_id = *txn.id();
+        let isolation = txn.isolation();
         let file = self.source.file;
         let shape = self.source.schema.shape;
         let mut bounds = self.rebase.bounds().clone();
         bounds.normalize(&shape);
 
+        if let Some(offsets) = bounds.to_offset_range(&shape) {
+            debug!("read contiguous slice {:?} of dense tensor file", offsets);
+
+            // read whole blocks (or contiguous sub-arrays) directly, without gathering
+            let blocks = stream::iter(offsets.clone().step_by(PER_BLOCK))
+                .map(move |start| {
+                    let end = Ord::min(start + PER_BLOCK as u64, offsets.end);
+                    read_range(file.clone(), txn_id, isolation, start..end)
+                })
+                .buffered(num_cpus::get());
+
+            let blocks: TCBoxTryStream<Array> = Box::pin(blocks);
+            return Box::pin(future::ready(Ok(blocks)));
+        }
+
         let ndim = bounds.len();
         let coords = stream::iter(bounds.affected().map(TCResult::Ok));
         let values = CoordBlocks::new(coords, ndim, PER_BLOCK).and_then(move |coords| {
@@ -1023,7 +1212,9 @@ where
                     let (block_offsets, new_start) =
                         block_offsets(&af_indices, &af_offsets, start, block_id);
 
-                    let block = file_clone.read_block(txn_id, block_id.into()).await?;
+                    let block = file_clone
+                        .read_block_isolated(txn_id, block_id.into(), isolation)
+                        .await?;
 
                     values.extend(block.get(&block_offsets.into()).to_vec());
                     start = new_start;
@@ -1089,6 +1280,38 @@ fn div_ceil(l: u64, r: u64) -> u64 {
     }
 }
 
+async fn read_range<FD: File<Array>>(
+    file: FD,
+    txn_id: TxnId,
+    isolation: Isolation,
+    offsets: ops::Range<u64>,
+) -> TCResult<Array> {
+    let block_id = offsets.start / PER_BLOCK as u64;
+    let start = (offsets.start % PER_BLOCK as u64) as usize;
+    let len = (offsets.end - offsets.start) as usize;
+
+    let block = file
+        .read_block_isolated(txn_id, block_id.into(), isolation)
+        .await?;
+
+    if start == 0 && len == block.len() {
+        return Ok((*block).clone());
+    } else if start + len <= block.len() {
+        return block.slice(start, start + len).map_err(TCError::from);
+    }
+
+    // the range spans the boundary between two blocks
+    let left = block.slice(start, block.len()).map_err(TCError::from)?;
+    let next = file
+        .read_block_isolated(txn_id, (block_id + 1).into(), isolation)
+        .await?;
+    let right = next
+        .slice(0, start + len - block.len())
+        .map_err(TCError::from)?;
+
+    Ok(Array::concatenate(&left, &right))
+}
+
 fn block_offsets(
     indices: &ArrayExt<u64>,
     offsets: &ArrayExt<u64>,
@@ -1097,28 +1320,100 @@ fn block_offsets(
 ) -> (ArrayExt<u64>, usize) {
     assert_eq!(indices.len(), offsets.len());
 
-    let num_to_update = af::sum_all(&af::eq(
-        indices.deref(),
-        &af::constant(block_id, af::Dim4::new(&[1, 1, 1, 1])),
-        true,
-    ))
-    .0;
+    let num_to_update = block::offsets_count(indices, block_id);
 
     if num_to_update == 0 {
-        return (af::Array::new_empty(af::Dim4::default()).into(), start);
+        return (block::offsets_empty(), start);
     }
 
-    let end = start + num_to_update as usize;
+    let end = start + num_to_update;
     let block_offsets = offsets.slice(start, end);
 
     (block_offsets, end)
 }
 
 fn coord_block(coords: Coords, shape: &[u64]) -> (Vec<u64>, ArrayExt<u64>, Offsets) {
-    let af_per_block = af::constant(PER_BLOCK as u64, af::Dim4::new(&[1, 1, 1, 1]));
-
     let offsets = coords.to_offsets(shape);
-    let block_offsets = ArrayExt::from(af::div(offsets.deref(), &af_per_block, true));
+    let block_offsets = block::offsets_div(&offsets, PER_BLOCK as u64);
     let block_ids = block_offsets.unique(true);
     (block_ids.to_vec(), block_offsets, offsets)
 }
+
+// check that the block at the given ordinal `block_id` is valid for a tensor with this `schema`
+fn validate_block(schema: &Schema, block_id: u64, block: &Array) -> TCResult<()> {
+    if block.dtype() != schema.dtype {
+        return Err(TCError::new(
+            ErrorType::Internal,
+            format!(
+                "dense tensor with data type {} has block {} of type {}",
+                schema.dtype,
+                block_id,
+                block.dtype()
+            ),
+        ));
+    }
+
+    let offset = block_id * PER_BLOCK as u64;
+    let expected_len = Ord::min(schema.shape.size().saturating_sub(offset), PER_BLOCK as u64);
+    if block.len() as u64 != expected_len {
+        return Err(TCError::new(
+            ErrorType::Internal,
+            format!(
+                "dense tensor of shape {} requires {} elements in block {} but found {}",
+                schema.shape,
+                expected_len,
+                block_id,
+                block.len()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use tc_value::{FloatType, UIntType};
+
+    use super::*;
+
+    // encode and decode the given `block` the way the host's block cache persists it
+    fn round_trip(block: &Array) -> Array {
+        block_on(async {
+            let encoded: Vec<_> = tbon::en::encode(block)
+                .expect("encoder")
+                .try_collect()
+                .await
+                .expect("encoded block");
+
+            tbon::de::decode((), stream::iter(encoded))
+                .await
+                .expect("decoded block")
+        })
+    }
+
+    #[test]
+    fn test_validate_block() {
+        let schema = Schema {
+            shape: vec![PER_BLOCK as u64 + 3].into(),
+            dtype: NumberType::Float(FloatType::F32),
+        };
+
+        let one = Number::from(1f32);
+        let full = round_trip(&Array::constant(one, PER_BLOCK));
+        let trailing = round_trip(&Array::constant(one, 3));
+        assert!(validate_block(&schema, 0, &full).is_ok());
+        assert!(validate_block(&schema, 1, &trailing).is_ok());
+
+        // a block which is the wrong length for its position
+        let truncated = round_trip(&Array::constant(one, PER_BLOCK - 1));
+        assert!(validate_block(&schema, 0, &truncated).is_err());
+        assert!(validate_block(&schema, 1, &full).is_err());
+
+        // a block which is the right length but the wrong type
+        let corrupt = round_trip(&Array::constant(Number::from(1u64), 3));
+        assert_eq!(corrupt.dtype(), NumberType::UInt(UIntType::U64));
+        assert!(validate_block(&schema, 1, &corrupt).is_err());
+    }
+}
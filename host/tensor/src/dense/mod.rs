@@ -2,12 +2,11 @@ use std::fmt;
 use std::marker::PhantomData;
 use std::ops::{Add, Div, Mul, Sub};
 
-use afarray::{Array, ArrayInstance, CoordBlocks};
-use arrayfire as af;
 use async_trait::async_trait;
 use destream::{de, en};
 use futures::future::{self, TryFutureExt};
 use futures::stream::{Stream, StreamExt, TryStreamExt};
+use futures::try_join;
 use log::{debug, warn};
 use safecast::{AsType, CastFrom};
 
@@ -15,25 +14,32 @@ use tc_btree::Node;
 use tc_error::*;
 use tc_transact::fs::{CopyFrom, Dir, File, Hash, Persist, Restore};
 use tc_transact::{IntoView, Transact, Transaction, TxnId};
-use tc_value::{FloatType, Number, NumberClass, NumberInstance, NumberType, Trigonometry};
+use tc_value::{
+    ComplexInstance, ComplexType, FloatType, Number, NumberClass, NumberInstance, NumberType,
+    Promote, Trigonometry, UIntType,
+};
 use tcgeneric::{Instance, TCBoxTryFuture, TCBoxTryStream};
 
+use super::block::{self, Array, ArrayExt, ArrayInstance, Complex, CoordBlocks, Element};
 use super::sparse::{DenseToSparse, SparseTensor};
 use super::stream::{Read, ReadValueAt};
 use super::{
-    trig_dtype, Bounds, Coord, Phantom, Schema, Shape, Tensor, TensorAccess, TensorBoolean,
-    TensorBooleanConst, TensorCompare, TensorCompareConst, TensorDiagonal, TensorDualIO, TensorIO,
-    TensorInstance, TensorMath, TensorMathConst, TensorPersist, TensorReduce, TensorTransform,
-    TensorTrig, TensorType, TensorUnary, ERR_COMPLEX_EXPONENT,
+    arg_dtype, real_dtype, trig_dtype, AssignOp, Bounds, Coord, Phantom, Schema, Shape, Tensor,
+    TensorAccess, TensorBoolean, TensorBooleanConst, TensorCompare, TensorCompareConst,
+    TensorComplex, TensorDiagonal, TensorDualIO, TensorIO, TensorInstance, TensorMath,
+    TensorMathConst, TensorPersist, TensorReduce, TensorScan, TensorTransform, TensorTrig,
+    TensorType, TensorUnary, ERR_COMPLEX_EXPONENT,
 };
 
 use access::*;
-pub use access::{BlockListSparse, DenseAccess, DenseAccessor, DenseWrite};
+pub use access::{BlockListSelect, BlockListSparse, DenseAccess, DenseAccessor, DenseWrite};
 pub use file::BlockListFile;
+pub use table::BlockListTable;
 
 mod access;
 mod file;
 mod stream;
+mod table;
 
 /// The number of bytes in one mebibyte.
 const MEBIBYTE: usize = 1_048_576;
@@ -149,7 +155,7 @@ where
     {
         let schema = Schema {
             shape: shape.into(),
-            dtype: Ord::max(start.class(), stop.class()),
+            dtype: start.class().promote(stop.class()),
         };
         schema.validate()?;
 
@@ -157,6 +163,146 @@ where
             .map_ok(Self::from)
             .await
     }
+
+    /// Add `other` to this `DenseTensor` in place.
+    pub async fn add_assign<O>(&self, txn: T, other: DenseTensor<FD, FS, D, T, O>) -> TCResult<()>
+    where
+        O: DenseAccess<FD, FS, D, T>,
+    {
+        fn add_array(l: &Array, r: &Array) -> TCResult<Array> {
+            Ok(l + r)
+        }
+
+        self.blocks
+            .combine_assign(txn, other.blocks, add_array)
+            .await
+    }
+
+    /// Divide this `DenseTensor` by `other` in place.
+    ///
+    /// Returns an error if any element of `other` is zero.
+    pub async fn div_assign<O>(&self, txn: T, other: DenseTensor<FD, FS, D, T, O>) -> TCResult<()>
+    where
+        O: DenseAccess<FD, FS, D, T>,
+    {
+        fn div_array(l: &Array, r: &Array) -> TCResult<Array> {
+            if r.all() {
+                Ok(l / r)
+            } else {
+                Err(TCError::unsupported("cannot divide a Tensor by zero"))
+            }
+        }
+
+        self.blocks
+            .combine_assign(txn, other.blocks, div_array)
+            .await
+    }
+
+    /// Multiply this `DenseTensor` by `other` in place.
+    pub async fn mul_assign<O>(&self, txn: T, other: DenseTensor<FD, FS, D, T, O>) -> TCResult<()>
+    where
+        O: DenseAccess<FD, FS, D, T>,
+    {
+        fn mul_array(l: &Array, r: &Array) -> TCResult<Array> {
+            Ok(l * r)
+        }
+
+        self.blocks
+            .combine_assign(txn, other.blocks, mul_array)
+            .await
+    }
+
+    /// Subtract `other` from this `DenseTensor` in place.
+    pub async fn sub_assign<O>(&self, txn: T, other: DenseTensor<FD, FS, D, T, O>) -> TCResult<()>
+    where
+        O: DenseAccess<FD, FS, D, T>,
+    {
+        fn sub_array(l: &Array, r: &Array) -> TCResult<Array> {
+            Ok(l - r)
+        }
+
+        self.blocks
+            .combine_assign(txn, other.blocks, sub_array)
+            .await
+    }
+
+    /// Add the scalar `other` to each element of this `DenseTensor` in place.
+    pub async fn add_assign_const(&self, txn_id: TxnId, other: Number) -> TCResult<()> {
+        fn add_array(l: &Array, r: Number) -> Array {
+            l + r
+        }
+
+        self.blocks
+            .combine_assign_const(txn_id, other, add_array)
+            .await
+    }
+
+    /// Divide each element of this `DenseTensor` by the scalar `other` in place.
+    ///
+    /// Returns an error if `other` is zero.
+    pub async fn div_assign_const(&self, txn_id: TxnId, other: Number) -> TCResult<()> {
+        if other == other.class().zero() {
+            return Err(TCError::unsupported("cannot divide a Tensor by zero"));
+        }
+
+        fn div_array(l: &Array, r: Number) -> Array {
+            l / r
+        }
+
+        self.blocks
+            .combine_assign_const(txn_id, other, div_array)
+            .await
+    }
+
+    /// Multiply each element of this `DenseTensor` by the scalar `other` in place.
+    pub async fn mul_assign_const(&self, txn_id: TxnId, other: Number) -> TCResult<()> {
+        fn mul_array(l: &Array, r: Number) -> Array {
+            l * r
+        }
+
+        self.blocks
+            .combine_assign_const(txn_id, other, mul_array)
+            .await
+    }
+
+    /// Subtract the scalar `other` from each element of this `DenseTensor` in place.
+    pub async fn sub_assign_const(&self, txn_id: TxnId, other: Number) -> TCResult<()> {
+        fn sub_array(l: &Array, r: Number) -> Array {
+            l - r
+        }
+
+        self.blocks
+            .combine_assign_const(txn_id, other, sub_array)
+            .await
+    }
+
+    /// Update this `DenseTensor` in place by combining it with `other` using the given `op`.
+    pub async fn assign<O>(
+        &self,
+        txn: T,
+        op: AssignOp,
+        other: DenseTensor<FD, FS, D, T, O>,
+    ) -> TCResult<()>
+    where
+        O: DenseAccess<FD, FS, D, T>,
+    {
+        match op {
+            AssignOp::Add => self.add_assign(txn, other).await,
+            AssignOp::Div => self.div_assign(txn, other).await,
+            AssignOp::Mul => self.mul_assign(txn, other).await,
+            AssignOp::Sub => self.sub_assign(txn, other).await,
+        }
+    }
+
+    /// Update this `DenseTensor` in place by combining it with the scalar `other` using `op`.
+    pub async fn assign_const(&self, txn_id: TxnId, op: AssignOp, other: Number) -> TCResult<()> {
+        match op {
+            AssignOp::Add => self.add_assign_const(txn_id, other).await,
+            AssignOp::Div => self.div_assign_const(txn_id, other).await,
+            AssignOp::Mul => self.mul_assign_const(txn_id, other).await,
+            AssignOp::Sub => self.sub_assign_const(txn_id, other).await,
+        }
+    }
 }
 
 impl<FD, FS, D, T> TensorPersist for DenseTensor<FD, FS, D, T, DenseAccessor<FD, FS, D, T>> {
@@ -669,12 +815,11 @@ where
     type LeftCombine = DenseTensor<FD, FS, D, T, BlockListCombine<FD, FS, D, T, B, O>>;
 
     fn add(self, other: DenseTensor<FD, FS, D, T, O>) -> TCResult<Self::Combine> {
-        fn add_array(l: &Array, r: &Array) -> Array {
-            debug_assert_eq!(l.len(), r.len());
-            l + r
+        fn add_array(l: &Array, r: &Array) -> TCResult<Array> {
+            Ok(l + r)
         }
 
-        let dtype = Ord::max(self.dtype(), other.dtype());
+        let dtype = self.dtype().promote(other.dtype());
         self.combine(other, add_array, Add::add, dtype)
     }
 
@@ -688,17 +833,16 @@ where
             l / r
         }
 
-        let dtype = Ord::max(self.dtype(), other.dtype());
+        let dtype = self.dtype().promote(other.dtype());
         self.combine(other, div_array, Div::div, dtype)
     }
 
     fn mul(self, other: DenseTensor<FD, FS, D, T, O>) -> TCResult<Self::Combine> {
-        fn mul_array(l: &Array, r: &Array) -> Array {
-            debug_assert_eq!(l.len(), r.len());
-            l * r
+        fn mul_array(l: &Array, r: &Array) -> TCResult<Array> {
+            Ok(l * r)
         }
 
-        let dtype = Ord::max(self.dtype(), other.dtype());
+        let dtype = self.dtype().promote(other.dtype());
         self.combine(other, mul_array, Mul::mul, dtype)
     }
 
@@ -712,17 +856,16 @@ where
             l.pow(r)
         }
 
-        let dtype = Ord::max(self.dtype(), other.dtype());
+        let dtype = self.dtype().promote(other.dtype());
         self.combine(other, pow_array, Number::pow, dtype)
     }
 
     fn sub(self, other: DenseTensor<FD, FS, D, T, O>) -> TCResult<Self::Combine> {
-        fn sub_array(l: &Array, r: &Array) -> Array {
-            debug_assert_eq!(l.len(), r.len());
-            l - r
+        fn sub_array(l: &Array, r: &Array) -> TCResult<Array> {
+            Ok(l - r)
         }
 
-        let dtype = Ord::max(self.dtype(), other.dtype());
+        let dtype = self.dtype().promote(other.dtype());
         self.combine(other, sub_array, Sub::sub, dtype)
     }
 }
@@ -848,6 +991,879 @@ where
     }
 }
 
+impl<FD, FS, D, T, B> DenseTensor<FD, FS, D, T, B>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    /// Compute a running total along the given `axis` using the given `op`.
+    ///
+    /// The scanned axis is first transposed to the end, so that each run of `shape[axis]`
+    /// consecutive elements is one lane of the scan, and the running total is carried across
+    /// block boundaries. The result is written to a new file and transposed back.
+    async fn scan(
+        self,
+        txn: T,
+        axis: usize,
+        op: fn(Number, Number) -> Number,
+    ) -> TCResult<DenseTensor<FD, FS, D, T, DenseAccessor<FD, FS, D, T>>> {
+        let ndim = self.ndim();
+        if axis >= ndim {
+            return Err(TCError::bad_request(
+                format!("Tensor with shape {} has no axis", self.shape()),
+                axis,
+            ));
+        }
+
+        let permutation = if axis == ndim - 1 {
+            None
+        } else {
+            let mut permutation: Vec<usize> = (0..ndim).filter(|x| *x != axis).collect();
+            permutation.push(axis);
+            Some(permutation)
+        };
+
+        let source = if let Some(permutation) = &permutation {
+            self.blocks.transpose(Some(permutation.to_vec()))?.accessor()
+        } else {
+            self.blocks.accessor()
+        };
+
+        let txn_id = *txn.id();
+        let file = txn
+            .context()
+            .create_file_unique(txn_id, TensorType::Dense)
+            .await?;
+
+        let dtype = source.dtype();
+        let shape = source.shape().clone();
+        let lane_len = shape[ndim - 1];
+
+        let mut lane_offset = 0u64;
+        let mut carry = dtype.zero();
+        let blocks = source.block_stream(txn).await?.map_ok(move |block| {
+            let mut values = block.to_vec();
+            for value in values.iter_mut() {
+                carry = if lane_offset == 0 {
+                    *value
+                } else {
+                    op(carry, *value)
+                };
+
+                *value = carry;
+                lane_offset = (lane_offset + 1) % lane_len;
+            }
+
+            Array::from(values).cast_into(dtype)
+        });
+
+        let scan = BlockListFile::from_blocks(file, txn_id, Some(shape), dtype, blocks).await?;
+
+        if let Some(permutation) = permutation {
+            let mut inverse = vec![0; ndim];
+            for (i, x) in permutation.into_iter().enumerate() {
+                inverse[x] = i;
+            }
+
+            scan.transpose(Some(inverse))
+                .map(|transpose| transpose.accessor())
+                .map(DenseTensor::from)
+        } else {
+            Ok(DenseTensor::from(scan.accessor()))
+        }
+    }
+}
+
+impl<FD, FS, D, T, B> DenseTensor<FD, FS, D, T, B>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    /// Sort this tensor along the given `axis`.
+    ///
+    /// Returns the sorted values and, for each, its index along `axis` in this tensor.
+    pub async fn sort(
+        self,
+        txn: T,
+        axis: usize,
+        ascending: bool,
+    ) -> TCResult<(DenseSorted<FD, FS, D, T>, DenseSorted<FD, FS, D, T>)> {
+        self.sort_lanes(txn, axis, ascending, None).await
+    }
+
+    /// Return the `k` greatest elements of this tensor along the given `axis`, in descending order.
+    ///
+    /// Returns the values and, for each, its index along `axis` in this tensor.
+    pub async fn topk(
+        self,
+        txn: T,
+        k: u64,
+        axis: usize,
+    ) -> TCResult<(DenseSorted<FD, FS, D, T>, DenseSorted<FD, FS, D, T>)> {
+        if axis < self.ndim() && k > self.shape()[axis] {
+            return Err(TCError::bad_request(
+                format!("cannot select the top {} elements of an axis of length", k),
+                self.shape()[axis],
+            ));
+        }
+
+        self.sort_lanes(txn, axis, false, Some(k)).await
+    }
+
+    /// Sort each lane of this tensor along `axis`, keeping only the first `keep` of each.
+    ///
+    /// The sorted axis is first transposed to the end, so that each run of `shape[axis]`
+    /// consecutive elements is one lane. Lanes which fit within a block are sorted together by
+    /// ArrayFire; a longer lane is sorted one block at a time and its sorted blocks are merged.
+    /// The source is read twice, once to write the sorted values and once to write their indices.
+    async fn sort_lanes(
+        self,
+        txn: T,
+        axis: usize,
+        ascending: bool,
+        keep: Option<u64>,
+    ) -> TCResult<(DenseSorted<FD, FS, D, T>, DenseSorted<FD, FS, D, T>)> {
+        let ndim = self.ndim();
+        if axis >= ndim {
+            return Err(TCError::bad_request(
+                format!("Tensor with shape {} has no axis", self.shape()),
+                axis,
+            ));
+        }
+
+        let dtype = self.dtype();
+        if let NumberType::Complex(_) = dtype {
+            return Err(TCError::unsupported("cannot sort a Tensor of complex numbers"));
+        }
+
+        let permutation = if axis == ndim - 1 {
+            None
+        } else {
+            let mut permutation: Vec<usize> = (0..ndim).filter(|x| *x != axis).collect();
+            permutation.push(axis);
+            Some(permutation)
+        };
+
+        let source = if let Some(permutation) = &permutation {
+            self.blocks.transpose(Some(permutation.to_vec()))?.accessor()
+        } else {
+            self.blocks.accessor()
+        };
+
+        let lane_len = source.shape()[ndim - 1];
+        let keep = keep.unwrap_or(lane_len);
+
+        let mut shape = source.shape().to_vec();
+        shape[ndim - 1] = keep;
+        let shape = Shape::from(shape);
+
+        let txn_id = *txn.id();
+        let (values_file, indices_file) = try_join!(
+            txn.context().create_file_unique(txn_id, TensorType::Dense),
+            txn.context().create_file_unique(txn_id, TensorType::Dense)
+        )?;
+
+        let values = sorted_lanes(source.clone(), txn.clone(), ascending, keep)
+            .await?
+            .map_ok(|(values, _)| futures::stream::iter(values.into_iter().map(Ok)))
+            .try_flatten();
+
+        let values =
+            BlockListFile::from_values(values_file, txn_id, shape.clone(), dtype, values).await?;
+
+        let indices = sorted_lanes(source, txn, ascending, keep)
+            .await?
+            .map_ok(|(_, indices)| {
+                futures::stream::iter(indices.into_iter().map(Number::from).map(Ok))
+            })
+            .try_flatten();
+
+        let index_type = NumberType::UInt(UIntType::U64);
+        let indices =
+            BlockListFile::from_values(indices_file, txn_id, shape, index_type, indices).await?;
+
+        if let Some(permutation) = permutation {
+            let mut inverse = vec![0; ndim];
+            for (i, x) in permutation.into_iter().enumerate() {
+                inverse[x] = i;
+            }
+
+            let values = values.transpose(Some(inverse.to_vec()))?.accessor();
+            let indices = indices.transpose(Some(inverse))?.accessor();
+            Ok((values.into(), indices.into()))
+        } else {
+            Ok((values.accessor().into(), indices.accessor().into()))
+        }
+    }
+}
+
+/// The result of sorting a [`DenseTensor`]
+type DenseSorted<FD, FS, D, T> = DenseTensor<FD, FS, D, T, DenseAccessor<FD, FS, D, T>>;
+
+// sort each lane along the last axis of `source` and keep the first `keep` elements of each,
+// as a stream of (values, indices) batches of whole lanes in row-major order
+async fn sorted_lanes<'a, FD, FS, D, T, B>(
+    source: B,
+    txn: T,
+    ascending: bool,
+    keep: u64,
+) -> TCResult<TCBoxTryStream<'a, (Vec<Number>, Vec<u64>)>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+{
+    let lane_len = source.shape()[source.ndim() - 1];
+    if lane_len == 0 {
+        return Ok(Box::pin(futures::stream::empty()));
+    }
+
+    let values = source.value_stream(txn).await?;
+
+    if lane_len <= PER_BLOCK as u64 {
+        let batch_len = (PER_BLOCK / lane_len as usize) * lane_len as usize;
+        let batches = values
+            .chunks(batch_len)
+            .map(|batch| batch.into_iter().collect::<TCResult<Vec<Number>>>())
+            .map(move |batch| {
+                let (values, indices) = sort_block(Array::from(batch?), lane_len, ascending)?;
+                let indices = indices.to_vec().into_iter().map(u64::from).collect();
+                Ok(truncate_lanes(values.to_vec(), indices, lane_len, keep))
+            });
+
+        Ok(Box::pin(batches))
+    } else {
+        let lanes = values
+            .chunks(lane_len as usize)
+            .map(|lane| lane.into_iter().collect::<TCResult<Vec<Number>>>())
+            .map(move |lane| {
+                let lane = lane?;
+
+                let mut runs = Vec::with_capacity(lane.len() / PER_BLOCK + 1);
+                for (i, block) in lane.chunks(PER_BLOCK).enumerate() {
+                    let offset = (i * PER_BLOCK) as u64;
+                    let len = block.len() as u64;
+                    let (values, indices) =
+                        sort_block(Array::from(block.to_vec()), len, ascending)?;
+
+                    let indices = indices
+                        .to_vec()
+                        .into_iter()
+                        .map(|i| u64::from(i) + offset)
+                        .collect();
+
+                    runs.push((values.to_vec(), indices));
+                }
+
+                let (values, indices) = merge_runs(runs, ascending);
+                Ok(truncate_lanes(values, indices, lane_len, keep))
+            });
+
+        Ok(Box::pin(lanes))
+    }
+}
+
+// sort each lane of `lane_len` consecutive elements of `block`, returning the sorted values and
+// the index of each within its lane
+fn sort_block(block: Array, lane_len: u64, ascending: bool) -> TCResult<(Array, ArrayExt<u32>)> {
+    let dtype = block.dtype();
+
+    // ArrayFire does not sort boolean arrays, so sort them as integers
+    let block = if dtype == NumberType::Bool {
+        block.cast_into(NumberType::UInt(UIntType::U8))
+    } else {
+        block
+    };
+
+    let (values, indices) = block::sort_lanes(&block, lane_len, ascending).ok_or_else(|| {
+        TCError::unsupported(format!("cannot sort an array of type {}", block.dtype()))
+    })?;
+
+    if dtype == NumberType::Bool {
+        Ok((values.cast_into(dtype), indices))
+    } else {
+        Ok((values, indices))
+    }
+}
+
+// merge sorted runs of (value, index) pairs pairwise until only one run remains
+fn merge_runs(mut runs: Vec<(Vec<Number>, Vec<u64>)>, ascending: bool) -> (Vec<Number>, Vec<u64>) {
+    while runs.len() > 1 {
+        let mut merged = Vec::with_capacity((runs.len() + 1) / 2);
+        let mut pairs = runs.into_iter();
+        while let Some(left) = pairs.next() {
+            if let Some(right) = pairs.next() {
+                merged.push(merge_pair(left, right, ascending));
+            } else {
+                merged.push(left);
+            }
+        }
+
+        runs = merged;
+    }
+
+    runs.pop().unwrap_or_default()
+}
+
+fn merge_pair(
+    left: (Vec<Number>, Vec<u64>),
+    right: (Vec<Number>, Vec<u64>),
+    ascending: bool,
+) -> (Vec<Number>, Vec<u64>) {
+    let len = left.0.len() + right.0.len();
+    let mut values = Vec::with_capacity(len);
+    let mut indices = Vec::with_capacity(len);
+
+    let mut left = left.0.into_iter().zip(left.1).peekable();
+    let mut right = right.0.into_iter().zip(right.1).peekable();
+
+    loop {
+        let take_left = match (left.peek(), right.peek()) {
+            (Some((l, _)), Some((r, _))) => {
+                if ascending {
+                    l <= r
+                } else {
+                    l >= r
+                }
+            }
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+
+        let (value, index) = if take_left {
+            left.next()
+        } else {
+            right.next()
+        }
+        .expect("sorted run");
+
+        values.push(value);
+        indices.push(index);
+    }
+
+    (values, indices)
+}
+
+// keep only the first `keep` elements of each lane of `lane_len` elements
+fn truncate_lanes(
+    values: Vec<Number>,
+    indices: Vec<u64>,
+    lane_len: u64,
+    keep: u64,
+) -> (Vec<Number>, Vec<u64>) {
+    if keep >= lane_len {
+        return (values, indices);
+    }
+
+    let (lane_len, keep) = (lane_len as usize, keep as usize);
+    let values = values
+        .chunks(lane_len)
+        .flat_map(|lane| lane[..keep].iter().cloned())
+        .collect();
+
+    let indices = indices
+        .chunks(lane_len)
+        .flat_map(|lane| lane[..keep].iter().cloned())
+        .collect();
+
+    (values, indices)
+}
+
+impl<FD, FS, D, T, B> DenseTensor<FD, FS, D, T, B>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    /// Gather every window of the given `kernel` shape, moved `stride` elements at a time, into a
+    /// new tensor (an "im2col" transform), so that a convolution is a single `einsum`.
+    ///
+    /// This tensor must have the shape `[batch, spatial..., channels]`, with one spatial axis for
+    /// each axis of `kernel`. The result has the shape `[batch, windows..., kernel..., channels]`.
+    ///
+    /// The source is read once, in order. Each block is appended to a buffer which keeps only the
+    /// trailing "halo" of elements still needed by a window which overlaps the next block.
+    pub async fn im2col(
+        self,
+        txn: T,
+        kernel: Vec<u64>,
+        stride: u64,
+    ) -> TCResult<DenseTensor<FD, FS, D, T, BlockListFile<FD, FS, D, T>>> {
+        let plan = WindowPlan::new(self.shape(), kernel, stride)?;
+        let shape = plan.shape();
+        let dtype = self.dtype();
+
+        let txn_id = *txn.id();
+        let file = txn
+            .context()
+            .create_file_unique(txn_id, TensorType::Dense)
+            .await?;
+
+        let count = plan.count();
+        let mut next = 0u64;
+        let mut buffer: Vec<Number> = Vec::with_capacity(PER_BLOCK);
+        let mut buffer_start = 0u64;
+
+        let values = self
+            .blocks
+            .block_stream(txn)
+            .await?
+            .map_ok(move |block| {
+                buffer.extend(block.to_vec());
+                let buffer_end = buffer_start + buffer.len() as u64;
+
+                let mut windows = Vec::new();
+                while next < count && plan.end(next) <= buffer_end {
+                    for (start, len) in plan.segments(next) {
+                        let start = (start - buffer_start) as usize;
+                        windows.extend_from_slice(&buffer[start..start + len as usize]);
+                    }
+
+                    next += 1;
+                }
+
+                let halo_start = if next < count {
+                    Ord::min(plan.start(next), buffer_end)
+                } else {
+                    buffer_end
+                };
+
+                buffer.drain(..(halo_start - buffer_start) as usize);
+                buffer_start = halo_start;
+
+                futures::stream::iter(windows.into_iter().map(Ok))
+            })
+            .try_flatten();
+
+        let windows = BlockListFile::from_values(file, txn_id, shape, dtype, values).await?;
+        Ok(windows.into())
+    }
+}
+
+// the windows of an im2col transform, as contiguous segments of the row-major source offsets
+struct WindowPlan {
+    batch: u64,
+    windows: Vec<u64>,
+    kernel: Vec<u64>,
+    stride: u64,
+    strides: Vec<u64>,
+    channels: u64,
+}
+
+impl WindowPlan {
+    fn new(shape: &Shape, kernel: Vec<u64>, stride: u64) -> TCResult<Self> {
+        if kernel.is_empty() || shape.len() != kernel.len() + 2 {
+            return Err(TCError::bad_request(
+                format!(
+                    "a kernel of shape {:?} requires a Tensor with shape [batch, spatial..., channels], not",
+                    kernel
+                ),
+                shape,
+            ));
+        } else if stride == 0 {
+            return Err(TCError::bad_request("invalid window stride", stride));
+        }
+
+        let spatial = &shape[1..shape.len() - 1];
+        let mut windows = Vec::with_capacity(kernel.len());
+        for (dim, size) in spatial.iter().zip(&kernel) {
+            if *size == 0 || size > dim {
+                return Err(TCError::bad_request(
+                    format!("invalid kernel for spatial dimensions {:?}", spatial),
+                    format!("{:?}", kernel),
+                ));
+            }
+
+            windows.push(((dim - size) / stride) + 1);
+        }
+
+        Ok(Self {
+            batch: shape[0],
+            windows,
+            kernel,
+            stride,
+            strides: super::coord_bounds(shape),
+            channels: shape[shape.len() - 1],
+        })
+    }
+
+    fn shape(&self) -> Shape {
+        let mut shape = Vec::with_capacity((self.kernel.len() * 2) + 2);
+        shape.push(self.batch);
+        shape.extend(&self.windows);
+        shape.extend(&self.kernel);
+        shape.push(self.channels);
+        shape.into()
+    }
+
+    fn count(&self) -> u64 {
+        self.batch * self.windows.iter().product::<u64>()
+    }
+
+    // the source offset of the first element of the window at `index`
+    fn start(&self, index: u64) -> u64 {
+        let mut offset = 0;
+        let mut index = index;
+        for (axis, size) in self.windows.iter().enumerate().rev() {
+            offset += (index % size) * self.stride * self.strides[axis + 1];
+            index /= size;
+        }
+
+        offset + (index * self.strides[0])
+    }
+
+    // the source offset just past the last element of the window at `index`
+    fn end(&self, index: u64) -> u64 {
+        let last = self.kernel[..self.kernel.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(axis, size)| (size - 1) * self.strides[axis + 1])
+            .sum::<u64>();
+
+        self.start(index) + last + self.segment_len()
+    }
+
+    // the (offset, length) of each contiguous run of source elements in the window at `index`
+    fn segments(&self, index: u64) -> Vec<(u64, u64)> {
+        let start = self.start(index);
+        let outer = &self.kernel[..self.kernel.len() - 1];
+        let count = outer.iter().product::<u64>();
+
+        (0..count)
+            .map(|i| {
+                let mut offset = start;
+                let mut i = i;
+                for (axis, size) in outer.iter().enumerate().rev() {
+                    offset += (i % size) * self.strides[axis + 1];
+                    i /= size;
+                }
+
+                (offset, self.segment_len())
+            })
+            .collect()
+    }
+
+    fn segment_len(&self) -> u64 {
+        self.kernel[self.kernel.len() - 1] * self.channels
+    }
+}
+
+impl<FD, FS, D, T, B> DenseTensor<FD, FS, D, T, B>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    /// Compute the softmax of this tensor along the given `axis`.
+    ///
+    /// The axis is first transposed to the end, so that each run of `shape[axis]` consecutive
+    /// elements is one lane. Lanes which fit within a block are normalized in a single pass, a
+    /// batch of whole lanes at a time. A longer lane takes two passes: the first finds its maximum
+    /// and the sum of its exponents, and the second writes the normalized exponents.
+    pub async fn softmax(
+        self,
+        txn: T,
+        axis: usize,
+    ) -> TCResult<DenseTensor<FD, FS, D, T, DenseAccessor<FD, FS, D, T>>> {
+        let ndim = self.ndim();
+        if axis >= ndim {
+            return Err(TCError::bad_request(
+                format!("Tensor with shape {} has no axis", self.shape()),
+                axis,
+            ));
+        }
+
+        let dtype = float_dtype(self.dtype())?;
+
+        let permutation = if axis == ndim - 1 {
+            None
+        } else {
+            let mut permutation: Vec<usize> = (0..ndim).filter(|x| *x != axis).collect();
+            permutation.push(axis);
+            Some(permutation)
+        };
+
+        let source = if let Some(permutation) = &permutation {
+            self.blocks.transpose(Some(permutation.to_vec()))?.accessor()
+        } else {
+            self.blocks.accessor()
+        };
+
+        let txn_id = *txn.id();
+        let file = txn
+            .context()
+            .create_file_unique(txn_id, TensorType::Dense)
+            .await?;
+
+        let shape = source.shape().clone();
+
+        // an empty lane has no elements to normalize, but a chunk size must be nonzero
+        let lane_len = Ord::max(shape[ndim - 1], 1);
+
+        let softmax = if lane_len <= PER_BLOCK as u64 {
+            let batch_len = (PER_BLOCK / lane_len as usize) * lane_len as usize;
+            let values = source
+                .value_stream(txn)
+                .await?
+                .chunks(batch_len)
+                .map(|batch| batch.into_iter().collect::<TCResult<Vec<Number>>>())
+                .map_ok(move |batch| {
+                    let values = batch
+                        .chunks(lane_len as usize)
+                        .flat_map(softmax_lane)
+                        .collect::<Vec<Number>>();
+
+                    futures::stream::iter(values.into_iter().map(Ok))
+                })
+                .try_flatten();
+
+            BlockListFile::from_values(file, txn_id, shape, dtype, values).await?
+        } else {
+            let (stats, _) = source
+                .clone()
+                .value_stream(txn.clone())
+                .await?
+                .try_fold((Vec::new(), 0u64), move |(mut stats, offset), value| {
+                    let value = f64::cast_from(value);
+                    if offset == 0 {
+                        stats.push((value, 1.));
+                    } else if let Some((max, sum)) = stats.last_mut() {
+                        if value > *max {
+                            *sum = (*sum * (*max - value).exp()) + 1.;
+                            *max = value;
+                        } else {
+                            *sum += (value - *max).exp();
+                        }
+                    }
+
+                    future::ready(Ok((stats, (offset + 1) % lane_len)))
+                })
+                .await?;
+
+            let mut offset = 0u64;
+            let values = source.value_stream(txn).await?.map_ok(move |value| {
+                let (max, sum): (f64, f64) = stats[(offset / lane_len) as usize];
+                offset += 1;
+                Number::from((f64::cast_from(value) - max).exp() / sum)
+            });
+
+            BlockListFile::from_values(file, txn_id, shape, dtype, values).await?
+        };
+
+        if let Some(permutation) = permutation {
+            let mut inverse = vec![0; ndim];
+            for (i, x) in permutation.into_iter().enumerate() {
+                inverse[x] = i;
+            }
+
+            softmax
+                .transpose(Some(inverse))
+                .map(|transpose| transpose.accessor())
+                .map(DenseTensor::from)
+        } else {
+            Ok(DenseTensor::from(softmax.accessor()))
+        }
+    }
+
+    /// Normalize this tensor as `gamma * (x - mean) / sqrt(var + epsilon) + beta`.
+    ///
+    /// Each parameter must have the same shape as this tensor (i.e. it must already be broadcast).
+    /// The result is computed one block at a time in a single pass over all five block streams.
+    pub async fn batch_norm(
+        self,
+        txn: T,
+        mean: DenseAccessor<FD, FS, D, T>,
+        var: DenseAccessor<FD, FS, D, T>,
+        gamma: DenseAccessor<FD, FS, D, T>,
+        beta: DenseAccessor<FD, FS, D, T>,
+        epsilon: Number,
+    ) -> TCResult<DenseTensor<FD, FS, D, T, BlockListFile<FD, FS, D, T>>> {
+        let shape = self.shape().clone();
+        for param in &[&mean, &var, &gamma, &beta] {
+            if param.shape() != &shape {
+                return Err(TCError::unsupported(format!(
+                    "cannot normalize a Tensor with shape {} using a parameter with shape {}",
+                    shape,
+                    param.shape()
+                )));
+            }
+        }
+
+        let dtype = [mean.dtype(), var.dtype(), gamma.dtype(), beta.dtype()]
+            .iter()
+            .fold(self.dtype(), |dtype, param| dtype.promote(*param));
+
+        let dtype = float_dtype(dtype)?;
+
+        let txn_id = *txn.id();
+        let file = txn
+            .context()
+            .create_file_unique(txn_id, TensorType::Dense)
+            .await?;
+
+        let (x, mean, var, gamma, beta) = try_join!(
+            self.blocks.block_stream(txn.clone()),
+            mean.block_stream(txn.clone()),
+            var.block_stream(txn.clone()),
+            gamma.block_stream(txn.clone()),
+            beta.block_stream(txn)
+        )?;
+
+        let blocks = x.zip(mean).zip(var).zip(gamma).zip(beta).map(
+            move |((((x, mean), var), gamma), beta)| {
+                let x = x?.cast_into(dtype);
+                let mean = mean?.cast_into(dtype);
+                let var = var?.cast_into(dtype);
+                let gamma = gamma?.cast_into(dtype);
+                let beta = beta?.cast_into(dtype);
+
+                let std = (&var + epsilon).pow_const(Number::from(0.5f64));
+                let norm = &(&x - &mean) / &std;
+                TCResult::Ok(&(&norm * &gamma) + &beta)
+            },
+        );
+
+        let norm = BlockListFile::from_blocks(file, txn_id, Some(shape), dtype, blocks).await?;
+        Ok(norm.into())
+    }
+
+    /// Compute the infinity norm (the maximum absolute value) of this tensor along the given
+    /// `axis`.
+    ///
+    /// Like [`DenseTensor::softmax`], the axis is first transposed to the end so that each lane is
+    /// a run of consecutive elements, which are reduced in a single streaming pass.
+    pub async fn norm_inf(
+        self,
+        txn: T,
+        axis: usize,
+    ) -> TCResult<DenseTensor<FD, FS, D, T, BlockListFile<FD, FS, D, T>>> {
+        let ndim = self.ndim();
+        if axis >= ndim {
+            return Err(TCError::bad_request(
+                format!("Tensor with shape {} has no axis", self.shape()),
+                axis,
+            ));
+        }
+
+        let dtype = real_dtype(self.dtype());
+
+        let source = if axis == ndim - 1 {
+            self.blocks.accessor()
+        } else {
+            let mut permutation: Vec<usize> = (0..ndim).filter(|x| *x != axis).collect();
+            permutation.push(axis);
+            self.blocks.transpose(Some(permutation))?.accessor()
+        };
+
+        let mut shape = source.shape().to_vec();
+        let lane_len = shape.pop().expect("lane length");
+        let shape = Shape::from(shape);
+
+        let txn_id = *txn.id();
+        let file = txn
+            .context()
+            .create_file_unique(txn_id, TensorType::Dense)
+            .await?;
+
+        if lane_len == 0 {
+            // the norm of an empty lane is zero
+            let zero = Number::from(0f64);
+            let values = futures::stream::repeat(zero).take(shape.size() as usize).map(Ok);
+            let norm = BlockListFile::from_values(file, txn_id, shape, dtype, values).await?;
+            return Ok(norm.into());
+        }
+
+        let mut max = 0f64;
+        let mut offset = 0u64;
+        let values = source
+            .value_stream(txn)
+            .await?
+            .try_filter_map(move |value| {
+                max = max.max(f64::cast_from(value.abs()));
+                offset += 1;
+
+                let norm = if offset == lane_len {
+                    let norm = Number::from(max);
+                    max = 0.;
+                    offset = 0;
+                    Some(norm)
+                } else {
+                    None
+                };
+
+                future::ready(Ok(norm))
+            });
+
+        let norm = BlockListFile::from_values(file, txn_id, shape, dtype, values).await?;
+        Ok(norm.into())
+    }
+}
+
+// the floating-point type of the result of normalizing a tensor of the given `dtype`
+fn float_dtype(dtype: NumberType) -> TCResult<NumberType> {
+    match trig_dtype(dtype) {
+        NumberType::Complex(_) => Err(TCError::unsupported(
+            "cannot normalize a Tensor of complex numbers",
+        )),
+        NumberType::Float(ft) => Ok(ft.into()),
+        _ => Ok(FloatType::F32.into()),
+    }
+}
+
+// the softmax of a single lane of values
+fn softmax_lane(lane: &[Number]) -> Vec<Number> {
+    let lane: Vec<f64> = lane.iter().cloned().map(f64::cast_from).collect();
+    let max = lane.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = lane.into_iter().map(|x| (x - max).exp()).collect();
+    let sum: f64 = exps.iter().sum();
+    exps.into_iter().map(|x| Number::from(x / sum)).collect()
+}
+
+#[async_trait]
+impl<FD, FS, D, T, B> TensorScan<D> for DenseTensor<FD, FS, D, T, B>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    type Txn = T;
+    type Scan = DenseTensor<FD, FS, D, T, DenseAccessor<FD, FS, D, T>>;
+
+    async fn cumprod(self, txn: Self::Txn, axis: usize) -> TCResult<Self::Scan> {
+        self.scan(txn, axis, Mul::mul).await
+    }
+
+    async fn cumsum(self, txn: Self::Txn, axis: usize) -> TCResult<Self::Scan> {
+        self.scan(txn, axis, Add::add).await
+    }
+}
+
 impl<FD, FS, D, T, B> TensorReduce<D> for DenseTensor<FD, FS, D, T, B>
 where
     D: Dir,
@@ -956,6 +1972,31 @@ where
     }
 }
 
+fn array_re(array: &Array) -> Array {
+    block::re(array).unwrap_or_else(|| array.clone())
+}
+
+fn array_im(array: &Array) -> Array {
+    block::im(array).unwrap_or_else(|| Array::constant(array.dtype().zero(), array.len()))
+}
+
+fn array_conj(array: &Array) -> Array {
+    block::conj(array).unwrap_or_else(|| array.clone())
+}
+
+fn array_arg(array: &Array) -> Array {
+    if let Some(arg) = block::arg(array) {
+        return arg;
+    }
+
+    match array {
+        Array::F64(_) | Array::I32(_) | Array::I64(_) | Array::U32(_) | Array::U64(_) => {
+            array_arg(&array.cast_into(ComplexType::C64.into()))
+        }
+        real => array_arg(&real.cast_into(ComplexType::C32.into())),
+    }
+}
+
 macro_rules! trig {
     ($fun:ident) => {
         fn $fun(&self) -> TCResult<Self::Unary> {
@@ -993,6 +2034,41 @@ where
     trig! {atanh}
 }
 
+impl<FD, FS, D, T, B> TensorComplex for DenseTensor<FD, FS, D, T, B>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    B: DenseAccess<FD, FS, D, T>,
+{
+    type Unary = DenseTensor<FD, FS, D, T, BlockListUnary<FD, FS, D, T, B>>;
+
+    fn re(&self) -> TCResult<Self::Unary> {
+        let dtype = real_dtype(self.dtype());
+        let blocks = BlockListUnary::new(self.blocks.clone(), array_re, Number::re, dtype);
+        Ok(DenseTensor::from(blocks))
+    }
+
+    fn im(&self) -> TCResult<Self::Unary> {
+        let dtype = real_dtype(self.dtype());
+        let blocks = BlockListUnary::new(self.blocks.clone(), array_im, Number::im, dtype);
+        Ok(DenseTensor::from(blocks))
+    }
+
+    fn conj(&self) -> TCResult<Self::Unary> {
+        let dtype = self.dtype();
+        let blocks = BlockListUnary::new(self.blocks.clone(), array_conj, Number::conj, dtype);
+        Ok(DenseTensor::from(blocks))
+    }
+
+    fn arg(&self) -> TCResult<Self::Unary> {
+        let dtype = arg_dtype(self.dtype());
+        let blocks = BlockListUnary::new(self.blocks.clone(), array_arg, Number::arg, dtype);
+        Ok(DenseTensor::from(blocks))
+    }
+}
+
 #[async_trait]
 impl<FD, FS, D, T, B> TensorUnary<D> for DenseTensor<FD, FS, D, T, B>
 where
@@ -1020,16 +2096,20 @@ where
 
     fn exp(&self) -> TCResult<Self::Unary> {
         fn exp(n: Number) -> Number {
-            let n = f64::cast_from(n);
-            n.exp().into()
+            if let Number::Complex(_) = n {
+                n.exp()
+            } else {
+                let n = f64::cast_from(n);
+                n.exp().into()
+            }
         }
 
-        let blocks = BlockListUnary::new(
-            self.blocks.clone(),
-            Array::exp,
-            exp,
-            NumberType::Float(FloatType::F64),
-        );
+        let dtype = match self.dtype() {
+            NumberType::Complex(ct) => NumberType::Complex(ct),
+            _ => NumberType::Float(FloatType::F64),
+        };
+
+        let blocks = BlockListUnary::new(self.blocks.clone(), Array::exp, exp, dtype);
 
         Ok(DenseTensor::from(blocks))
     }
@@ -1330,7 +2410,7 @@ impl<'en> en::IntoStream<'en> for BlockStreamView<'en> {
             ComplexType as CT, FloatType as FT, IntType as IT, NumberType as NT, UIntType as UT,
         };
 
-        fn encodable<'en, T: af::HasAfEnum + Clone + Default + 'en>(
+        fn encodable<'en, T: Element + Clone + Default + 'en>(
             blocks: TCBoxTryStream<'en, Array>,
         ) -> impl Stream<Item = Vec<T>> + 'en {
             // an error can't be encoded within an array
@@ -1382,7 +2462,7 @@ fn encodable_c32<'en>(blocks: TCBoxTryStream<'en, Array>) -> impl Stream<Item =
         .take_while(|r| future::ready(r.is_ok()))
         .map(|block| block.expect("tensor block"))
         .map(|arr| {
-            let source = arr.type_cast::<afarray::Complex<f32>>();
+            let source = arr.type_cast::<Complex<f32>>();
             let re = source.re();
             let im = source.im();
 
@@ -1403,7 +2483,7 @@ fn encodable_c64<'en>(blocks: TCBoxTryStream<'en, Array>) -> impl Stream<Item =
         .take_while(|r| future::ready(r.is_ok()))
         .map(|block| block.expect("tensor block"))
         .map(|arr| {
-            let source = arr.type_cast::<afarray::Complex<f64>>();
+            let source = arr.type_cast::<Complex<f64>>();
             let re = source.re();
             let im = source.im();
 
@@ -0,0 +1,313 @@
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops;
+
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use log::debug;
+use safecast::{AsType, TryCastFrom};
+
+use tc_btree::Node;
+use tc_error::*;
+use tc_table::{Limited, Table, TableInstance, TableStream};
+use tc_transact::fs::{Dir, File};
+use tc_transact::{Transaction, TxnId};
+use tc_value::{Number, NumberClass, NumberType, Promote, Value, ValueType};
+use tcgeneric::{Id, TCBoxTryFuture, TCBoxTryStream, Tuple};
+
+use crate::block::{Array, Coords};
+use crate::stream::{Read, ReadValueAt};
+use crate::{AxisBounds, Bounds, Coord, Phantom, Shape, TensorAccess, TensorType};
+
+use super::access::BlockListTranspose;
+use super::{DenseAccess, DenseAccessor};
+
+/// A read-only view of the numeric columns of a [`Table`] as a 2-dimensional dense tensor,
+/// with one row per row of the table (in its natural order) and one column per table column.
+///
+/// Values are read from the table on demand, so the table is never copied into a tensor file.
+#[derive(Clone)]
+pub struct BlockListTable<FD, FS, D, T> {
+    table: Table<FS, D, T>,
+    rows: ops::Range<u64>,
+    columns: Vec<Id>,
+    row_axis: bool,
+    column_axis: bool,
+    shape: Shape,
+    dtype: NumberType,
+    phantom: Phantom<FD, FS, D, T>,
+}
+
+impl<FD, FS, D, T> BlockListTable<FD, FS, D, T>
+where
+    FD: File<Array>,
+    FS: File<Node>,
+    D: Dir,
+    T: Transaction<D>,
+{
+    /// Construct a view of the given `columns` of the given `table`, as of the given transaction.
+    ///
+    /// Each column must have a numeric data type.
+    pub async fn new(table: Table<FS, D, T>, columns: Vec<Id>, txn_id: TxnId) -> TCResult<Self> {
+        debug!("BlockListTable::new {}", Tuple::<&Id>::from_iter(&columns));
+
+        if columns.is_empty() {
+            return Err(TCError::bad_request(
+                "a tensor view of a table requires at least one column",
+                Tuple::<Id>::from(columns),
+            ));
+        }
+
+        let schema = table.schema();
+        let mut dtype: Option<NumberType> = None;
+        for name in &columns {
+            let column = schema
+                .primary()
+                .columns()
+                .into_iter()
+                .find(|col| &col.name == name)
+                .ok_or_else(|| TCError::not_found(format!("column {}", name)))?;
+
+            match column.dtype {
+                ValueType::Number(nt) => {
+                    dtype = Some(dtype.map(|dtype| dtype.promote(nt)).unwrap_or(nt));
+                }
+                other => {
+                    return Err(TCError::bad_request(
+                        format!("column {} is not numeric", name),
+                        other,
+                    ))
+                }
+            }
+        }
+
+        let num_rows = table.clone().count(txn_id).await?;
+        let shape = vec![num_rows, columns.len() as u64].into();
+
+        Ok(Self {
+            table,
+            rows: 0..num_rows,
+            columns,
+            row_axis: true,
+            column_axis: true,
+            shape,
+            dtype: dtype.expect("dtype"),
+            phantom: Phantom::default(),
+        })
+    }
+
+    fn source(&self, rows: ops::Range<u64>, columns: Vec<Id>) -> TCResult<Limited<FS, D, T>> {
+        let selection = self.table.clone().select(columns)?;
+        Ok(Limited::page(selection, rows.start, rows.end - rows.start))
+    }
+}
+
+impl<FD, FS, D, T> TensorAccess for BlockListTable<FD, FS, D, T>
+where
+    FD: File<Array>,
+    FS: File<Node>,
+    D: Dir,
+    T: Transaction<D>,
+{
+    fn dtype(&self) -> NumberType {
+        self.dtype
+    }
+
+    fn ndim(&self) -> usize {
+        self.shape.len()
+    }
+
+    fn shape(&'_ self) -> &'_ Shape {
+        &self.shape
+    }
+
+    fn size(&self) -> u64 {
+        self.shape.size()
+    }
+}
+
+#[async_trait]
+impl<FD, FS, D, T> DenseAccess<FD, FS, D, T> for BlockListTable<FD, FS, D, T>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    type Slice = Self;
+    type Transpose = BlockListTranspose<FD, FS, D, T, Self>;
+
+    fn accessor(self) -> DenseAccessor<FD, FS, D, T> {
+        DenseAccessor::Table(self)
+    }
+
+    fn value_stream<'a>(self, txn: T) -> TCBoxTryFuture<'a, TCBoxTryStream<'a, Number>> {
+        debug!("BlockListTable::value_stream");
+
+        Box::pin(async move {
+            let dtype = self.dtype;
+            let source = self.source(self.rows.clone(), self.columns.clone())?;
+            let rows = source
+                .with_isolation(txn.isolation())
+                .rows(*txn.id())
+                .await?;
+
+            let values = rows
+                .map_ok(move |row| stream::iter(row.into_iter().map(move |v| cast(v, dtype))))
+                .try_flatten();
+
+            let values: TCBoxTryStream<'a, Number> = Box::pin(values);
+            Ok(values)
+        })
+    }
+
+    fn slice(self, bounds: Bounds) -> TCResult<Self> {
+        debug!("BlockListTable::slice {}", bounds);
+
+        self.shape.validate_bounds(&bounds)?;
+
+        let mut bounds = bounds;
+        bounds.normalize(&self.shape);
+        let mut bounds = bounds.into_iter();
+
+        let (rows, row_axis) = if self.row_axis {
+            match bounds.next().expect("row bounds") {
+                AxisBounds::At(i) => {
+                    let i = self.rows.start + i;
+                    (i..(i + 1), false)
+                }
+                AxisBounds::In(range) => {
+                    let start = self.rows.start + range.start;
+                    (start..(start + (range.end - range.start)), true)
+                }
+                AxisBounds::Of(_) => {
+                    return Err(TCError::unsupported(
+                        "a tensor view of a table only supports a contiguous range of rows",
+                    ))
+                }
+            }
+        } else {
+            (self.rows.clone(), false)
+        };
+
+        let (columns, column_axis) = if self.column_axis {
+            match bounds.next().expect("column bounds") {
+                AxisBounds::At(i) => (vec![self.columns[i as usize].clone()], false),
+                AxisBounds::In(range) => {
+                    let columns =
+                        self.columns[(range.start as usize)..(range.end as usize)].to_vec();
+                    (columns, true)
+                }
+                AxisBounds::Of(indices) => {
+                    let columns = indices
+                        .into_iter()
+                        .map(|i| self.columns[i as usize].clone())
+                        .collect();
+
+                    (columns, true)
+                }
+            }
+        } else {
+            (self.columns, false)
+        };
+
+        let mut shape = Vec::with_capacity(2);
+        if row_axis {
+            shape.push(rows.end - rows.start);
+        }
+
+        if column_axis {
+            shape.push(columns.len() as u64);
+        }
+
+        Ok(Self {
+            table: self.table,
+            rows,
+            columns,
+            row_axis,
+            column_axis,
+            shape: shape.into(),
+            dtype: self.dtype,
+            phantom: Phantom::default(),
+        })
+    }
+
+    fn transpose(self, permutation: Option<Vec<usize>>) -> TCResult<Self::Transpose> {
+        BlockListTranspose::new(self, permutation)
+    }
+
+    async fn read_values(self, txn: Self::Txn, coords: Coords) -> TCResult<Array> {
+        let coords = coords.into_vec();
+        let values: Vec<Number> = stream::iter(coords)
+            .map(|coord| self.clone().read_value_at(txn.clone(), coord))
+            .buffered(num_cpus::get())
+            .map_ok(|(_coord, value)| value)
+            .try_collect()
+            .await?;
+
+        Ok(Array::from(values))
+    }
+}
+
+impl<FD, FS, D, T> ReadValueAt<D> for BlockListTable<FD, FS, D, T>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    type Txn = T;
+
+    fn read_value_at<'a>(self, txn: T, coord: Coord) -> Read<'a> {
+        Box::pin(async move {
+            self.shape.validate_coord(&coord)?;
+
+            let mut axes = coord.iter().copied();
+            let row = if self.row_axis {
+                self.rows.start + axes.next().expect("row")
+            } else {
+                self.rows.start
+            };
+
+            let column = if self.column_axis {
+                self.columns[axes.next().expect("column") as usize].clone()
+            } else {
+                self.columns[0].clone()
+            };
+
+            let source = self.source(row..(row + 1), vec![column])?;
+            let mut rows = source
+                .with_isolation(txn.isolation())
+                .rows(*txn.id())
+                .await?;
+            let value = rows
+                .try_next()
+                .await?
+                .and_then(|mut row| row.pop())
+                .ok_or_else(|| TCError::not_found(format!("table row {}", row)))?;
+
+            cast(value, self.dtype).map(|value| (coord, value))
+        })
+    }
+}
+
+impl<FD, FS, D, T> fmt::Display for BlockListTable<FD, FS, D, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "dense Tensor view of table columns {}",
+            Tuple::<&Id>::from_iter(&self.columns)
+        )
+    }
+}
+
+fn cast(value: Value, dtype: NumberType) -> TCResult<Number> {
+    Number::try_cast_from(value, |v| {
+        TCError::bad_request("tensor view of a table column found a non-numeric value", v)
+    })
+    .map(|n| dtype.cast(n))
+}
@@ -4,38 +4,57 @@ use std::marker::PhantomData;
 
 use async_trait::async_trait;
 use destream::{de, en};
+use futures::future;
+use futures::stream::{StreamExt, TryStreamExt};
 use futures::TryFutureExt;
 use log::debug;
 use safecast::*;
 
 use tc_btree::{BTreeType, Node};
 use tc_error::*;
-use tc_transact::fs::{Dir, File};
+use tc_transact::fs::{CopyFrom, Dir, File};
 use tc_transact::{IntoView, Transaction, TxnId};
-use tc_value::{FloatType, IntType, Number, NumberType, UIntType, Value, ValueType};
+use tc_value::{
+    ComplexType, FloatType, IntType, Number, NumberType, Promote, UIntType, Value, ValueType,
+};
 use tcgeneric::{
     label, path_label, Class, Instance, NativeClass, PathLabel, PathSegment, TCBoxTryFuture,
     TCPathBuf, Tuple,
 };
 
+use dense::BlockListSelect;
+use sparse::SparseSelect;
 use stream::ReadValueAt;
 
-pub use afarray::{print_af_info, Array};
+pub use block::{print_af_info, Array};
+pub use backend::{activate_backend, active_backend, init_backend, Backend};
 pub use bounds::{AxisBounds, Bounds, Shape};
-pub use dense::{BlockListFile, DenseAccess, DenseAccessor, DenseTensor, DenseWrite};
+pub use dense::{
+    BlockListFile, BlockListTable, DenseAccess, DenseAccessor, DenseTensor, DenseWrite,
+};
 pub use einsum::einsum;
+pub use linalg::MAX_MATRIX_SIZE;
 pub use sparse::{SparseAccess, SparseAccessor, SparseTable, SparseTensor, SparseWrite};
+pub use summary::{Histogram, DEFAULT_BINS};
+pub use tape::{Operand, Tape, TapeOp};
 
+mod backend;
+mod block;
 mod bounds;
 mod dense;
 mod einsum;
+mod linalg;
 mod sparse;
 mod stream;
+mod summary;
+mod tape;
 mod transform;
 
 const ERR_COMPLEX_EXPONENT: &str = "raising to a complex power is not supported";
 const ERR_INF: &str = "Tensor combination resulted in an infinite value";
 const ERR_NAN: &str = "Tensor combination resulted in a non-numeric value";
+const ERR_VIEW_WRITE: &str =
+    "cannot write to a Tensor view (materialize it first to get a writable copy)";
 
 const PREFIX: PathLabel = path_label(&["state", "collection", "tensor"]);
 
@@ -360,6 +379,22 @@ pub trait TensorReduce<D: Dir> {
     fn sum_all(&self, txn: Self::Txn) -> TCBoxTryFuture<Number>;
 }
 
+/// [`Tensor`] cumulative operations, which compute a running total along one axis
+#[async_trait]
+pub trait TensorScan<D: Dir> {
+    /// The type of [`Transaction`] to expect
+    type Txn: Transaction<D>;
+
+    /// The result type of a cumulative operation
+    type Scan: TensorAccess;
+
+    /// Return the cumulative product of this [`Tensor`] along the given `axis`.
+    async fn cumprod(self, txn: Self::Txn, axis: usize) -> TCResult<Self::Scan>;
+
+    /// Return the cumulative sum of this [`Tensor`] along the given `axis`.
+    async fn cumsum(self, txn: Self::Txn, axis: usize) -> TCResult<Self::Scan>;
+}
+
 /// [`Tensor`] transforms
 pub trait TensorTransform {
     /// A broadcast [`Tensor`]
@@ -474,6 +509,41 @@ pub trait TensorTrig {
     fn atanh(&self) -> TCResult<Self::Unary>;
 }
 
+/// [`Tensor`] operations on complex numbers
+pub trait TensorComplex {
+    /// The return type of a unary operation
+    type Unary: TensorInstance;
+
+    /// Element-wise real component
+    fn re(&self) -> TCResult<Self::Unary>;
+
+    /// Element-wise imaginary component
+    fn im(&self) -> TCResult<Self::Unary>;
+
+    /// Element-wise complex conjugate
+    fn conj(&self) -> TCResult<Self::Unary>;
+
+    /// Element-wise phase angle, in radians
+    fn arg(&self) -> TCResult<Self::Unary>;
+}
+
+// the type of the real and imaginary components of a number of the given type
+fn real_dtype(dtype: NumberType) -> NumberType {
+    match dtype {
+        NumberType::Complex(ComplexType::C32) => FloatType::F32.into(),
+        NumberType::Complex(_) => FloatType::F64.into(),
+        other => other,
+    }
+}
+
+// the type of the phase angle of a number of the given type
+fn arg_dtype(dtype: NumberType) -> NumberType {
+    match dtype {
+        NumberType::Complex(_) => real_dtype(dtype),
+        real => NumberType::from(FloatType::F32).promote(real),
+    }
+}
+
 fn trig_dtype(dtype: NumberType) -> NumberType {
     match dtype {
         NumberType::Int(it) => match it {
@@ -524,6 +594,78 @@ impl fmt::Display for TensorType {
     }
 }
 
+/// The policy by which a [`Tensor`] switches between a dense and a sparse representation when
+/// it's materialized.
+#[derive(Clone, Copy)]
+pub struct RepresentationPolicy {
+    /// Store a sparse tensor as dense if more than this fraction of its elements are nonzero
+    pub dense_above: f64,
+
+    /// Store a dense tensor as sparse if no more than this fraction of its elements are nonzero
+    pub sparse_below: f64,
+}
+
+impl RepresentationPolicy {
+    /// Return the [`TensorType`] to use for a tensor of type `current` with the given `density`.
+    pub fn choose(&self, current: TensorType, density: f64) -> TensorType {
+        match current {
+            TensorType::Sparse if density > self.dense_above => TensorType::Dense,
+            TensorType::Dense if density <= self.sparse_below => TensorType::Sparse,
+            current => current,
+        }
+    }
+}
+
+impl Default for RepresentationPolicy {
+    fn default() -> Self {
+        Self {
+            dense_above: 0.5,
+            sparse_below: 0.1,
+        }
+    }
+}
+
+/// The order of a vector norm, or equivalently the metric of a distance
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum NormOrd {
+    /// The sum of absolute values, i.e. the Manhattan or "cityblock" distance
+    L1,
+    /// The square root of the sum of squares, i.e. the Euclidean distance
+    L2,
+    /// The maximum absolute value, i.e. the Chebyshev distance
+    Inf,
+}
+
+impl fmt::Display for NormOrd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::L1 => f.write_str("L1"),
+            Self::L2 => f.write_str("L2"),
+            Self::Inf => f.write_str("infinity"),
+        }
+    }
+}
+
+/// An arithmetic operation which updates a dense [`Tensor`] in place
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum AssignOp {
+    Add,
+    Div,
+    Mul,
+    Sub,
+}
+
+impl fmt::Display for AssignOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Add => f.write_str("add"),
+            Self::Div => f.write_str("divide"),
+            Self::Mul => f.write_str("multiply"),
+            Self::Sub => f.write_str("subtract"),
+        }
+    }
+}
+
 /// An n-dimensional array of numbers which supports basic math and logic operations
 #[derive(Clone)]
 pub enum Tensor<FD, FS, D, T> {
@@ -548,6 +690,354 @@ where
     }
 }
 
+impl<FD, FS, D, T> Tensor<FD, FS, D, T>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<BTreeType> + From<TensorType>,
+{
+    /// Copy this `Tensor`, which may be a virtual view such as a broadcast or a slice, into a new
+    /// writable `Tensor` of the same type, in a single streamed pass.
+    pub async fn materialize(self, txn: &T) -> TCResult<Self> {
+        let txn_id = *txn.id();
+
+        match self {
+            Self::Dense(dense) => {
+                let file = txn
+                    .context()
+                    .create_file_unique(txn_id, TensorType::Dense)
+                    .await?;
+
+                let blocks = BlockListFile::copy_from(dense.into_inner(), file, txn).await?;
+                Ok(Self::Dense(DenseTensor::from(blocks.accessor())))
+            }
+            Self::Sparse(sparse) => {
+                let dir = txn.context().create_dir_unique(txn_id).await?;
+                let table = SparseTable::copy_from(sparse, dir, txn).await?;
+                Ok(Self::Sparse(SparseTensor::from(table.accessor())))
+            }
+        }
+    }
+
+    /// Return a flat dense vector of the elements of this `Tensor` where the boolean `mask` is
+    /// true, in row-major order.
+    ///
+    /// The `mask` is broadcast to the shape of this `Tensor` if necessary.
+    pub async fn mask_select(self, txn: &T, mask: Self) -> TCResult<Self> {
+        let txn_id = *txn.id();
+        let mask = mask.into_mask(self.shape())?;
+        let size = mask.clone().filled_count(txn.clone()).await?;
+
+        debug!("select {} elements of a Tensor by mask", size);
+
+        let values = {
+            let txn = txn.clone();
+            let source = self.clone();
+            mask.filled(txn.clone())
+                .await?
+                .map_ok(move |(coord, _)| source.clone().read_value(txn.clone(), coord))
+                .try_buffered(num_cpus::get())
+        };
+
+        let file = txn
+            .context()
+            .create_file_unique(txn_id, TensorType::Dense)
+            .await?;
+
+        let blocks =
+            BlockListFile::from_values(file, txn_id, vec![size].into(), self.dtype(), values)
+                .await?;
+
+        Ok(Self::Dense(DenseTensor::from(blocks.accessor())))
+    }
+
+    /// Overwrite each element of this `Tensor` where the boolean `mask` is true with `value`.
+    ///
+    /// The `mask` is read in full before any element is written, so it may be a view of this
+    /// `Tensor` itself, like `x < 0`.
+    pub async fn mask_assign(&self, txn: &T, mask: Self, value: Number) -> TCResult<()> {
+        let txn_id = *txn.id();
+        let mask = mask.into_mask(self.shape())?;
+
+        let coords: Vec<Coord> = mask
+            .filled(txn.clone())
+            .await?
+            .map_ok(|(coord, _)| coord)
+            .try_collect()
+            .await?;
+
+        debug!("assign {} to {} elements of a Tensor by mask", value, coords.len());
+
+        futures::stream::iter(coords)
+            .map(|coord| self.write_value_at(txn_id, coord, value))
+            .buffer_unordered(num_cpus::get())
+            .try_fold((), |_, _| future::ready(Ok(())))
+            .await
+    }
+
+    /// Update this `Tensor` in place by combining each of its elements with the corresponding
+    /// element of `other` (broadcast to the shape of this `Tensor` if needed) using `op`.
+    ///
+    /// Only a dense `Tensor` backed by its own file can be updated in place, not a view or a
+    /// sparse `Tensor`.
+    pub async fn assign(&self, txn: T, op: AssignOp, other: Self) -> TCResult<()> {
+        let this = self.as_dense_file()?;
+
+        let other = if other.shape() == self.shape() {
+            other
+        } else {
+            other.broadcast(self.shape().clone())?
+        };
+
+        debug!("{} a Tensor in place", op);
+
+        match other {
+            Self::Dense(other) => this.assign(txn, op, other).await,
+            Self::Sparse(other) => this.assign(txn, op, other.into_dense()).await,
+        }
+    }
+
+    /// Update this `Tensor` in place by combining each of its elements with the scalar `other`
+    /// using `op`.
+    pub async fn assign_const(&self, txn_id: TxnId, op: AssignOp, other: Number) -> TCResult<()> {
+        let this = self.as_dense_file()?;
+
+        debug!("{} a Tensor in place with {}", op, other);
+
+        this.assign_const(txn_id, op, other).await
+    }
+
+    fn as_dense_file(&self) -> TCResult<DenseTensor<FD, FS, D, T, BlockListFile<FD, FS, D, T>>> {
+        match self {
+            Self::Dense(dense) => dense
+                .clone()
+                .as_persistent()
+                .ok_or_else(|| TCError::unsupported(ERR_VIEW_WRITE)),
+            Self::Sparse(_) => Err(TCError::unsupported(
+                "cannot update a sparse Tensor in place (convert it to a dense Tensor first)",
+            )),
+        }
+    }
+
+    fn into_mask(self, shape: &Shape) -> TCResult<SparseAccessor<FD, FS, D, T>> {
+        if self.dtype() != NumberType::Bool {
+            return Err(TCError::bad_request(
+                "a Tensor mask must be boolean, not",
+                self.dtype(),
+            ));
+        }
+
+        let mask = if self.shape() == shape {
+            self
+        } else {
+            self.broadcast(shape.clone())?
+        };
+
+        match mask {
+            Self::Dense(dense) => Ok(dense.into_sparse().into_inner().accessor()),
+            Self::Sparse(sparse) => Ok(sparse.into_inner()),
+        }
+    }
+
+    /// Sort this `Tensor` along the given `axis`, as a dense tensor.
+    ///
+    /// Returns the sorted values and, for each, its index along `axis` in this `Tensor`.
+    pub async fn sort(self, txn: T, axis: usize, ascending: bool) -> TCResult<(Self, Self)> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        let (values, indices) = dense.sort(txn, axis, ascending).await?;
+        Ok((values.into(), indices.into()))
+    }
+
+    /// Return the `k` greatest elements of this `Tensor` along the given `axis`, as a dense tensor
+    /// in descending order.
+    ///
+    /// Returns the values and, for each, its index along `axis` in this `Tensor`.
+    pub async fn topk(self, txn: T, k: u64, axis: usize) -> TCResult<(Self, Self)> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        let (values, indices) = dense.topk(txn, k, axis).await?;
+        Ok((values.into(), indices.into()))
+    }
+
+    /// Gather every window of the given `kernel` shape of this `Tensor`, moved `stride` elements
+    /// at a time, into a new dense tensor.
+    ///
+    /// See [`DenseTensor::im2col`].
+    pub async fn im2col(self, txn: T, kernel: Vec<u64>, stride: u64) -> TCResult<Self> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        dense.im2col(txn, kernel, stride).map_ok(Self::from).await
+    }
+
+    /// Compute the softmax of this `Tensor` along the given `axis`, as a dense tensor.
+    pub async fn softmax(self, txn: T, axis: usize) -> TCResult<Self> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        dense.softmax(txn, axis).map_ok(Self::from).await
+    }
+
+    /// Normalize this `Tensor` as `gamma * (x - mean) / sqrt(var + epsilon) + beta`, as a dense
+    /// tensor, broadcasting each parameter to the shape of this `Tensor`.
+    pub async fn batch_norm(
+        self,
+        txn: T,
+        mean: Self,
+        var: Self,
+        gamma: Self,
+        beta: Self,
+        epsilon: Number,
+    ) -> TCResult<Self> {
+        let shape = self.shape().clone();
+        let mean = dense_accessor(mean.broadcast(shape.clone())?);
+        let var = dense_accessor(var.broadcast(shape.clone())?);
+        let gamma = dense_accessor(gamma.broadcast(shape.clone())?);
+        let beta = dense_accessor(beta.broadcast(shape)?);
+
+        let dense = DenseTensor::from(dense_accessor(self));
+        dense
+            .batch_norm(txn, mean, var, gamma, beta, epsilon)
+            .map_ok(Self::from)
+            .await
+    }
+
+    /// Compute the inverse of this square matrix, as a dense tensor.
+    ///
+    /// Returns an unsupported error if this matrix is too large to load into memory.
+    pub async fn inv(self, txn: T) -> TCResult<Self> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        linalg::inv(dense, txn).map_ok(Self::from).await
+    }
+
+    /// Compute the determinant of this square matrix.
+    pub async fn det(self, txn: T) -> TCResult<Number> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        linalg::det(dense, txn).await
+    }
+
+    /// Solve the system of linear equations `self * x = b` for `x`, as a dense tensor.
+    pub async fn solve(self, txn: T, b: Self) -> TCResult<Self> {
+        let a = DenseTensor::from(dense_accessor(self));
+        let b = DenseTensor::from(dense_accessor(b));
+        linalg::solve(a, b, txn).map_ok(Self::from).await
+    }
+
+    /// Compute the lower-triangular Cholesky factor of this symmetric, positive-definite matrix,
+    /// as a dense tensor.
+    pub async fn cholesky(self, txn: T) -> TCResult<Self> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        linalg::cholesky(dense, txn).map_ok(Self::from).await
+    }
+
+    /// Compute the singular value decomposition `U * S * V^T` of this matrix, as dense tensors.
+    ///
+    /// Returns `U`, the singular values `S` in descending order, and `V^T`.
+    pub async fn svd(self, txn: T) -> TCResult<(Self, Self, Self)> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        let (u, s, vt) = linalg::svd(dense, txn).await?;
+        Ok((u.into(), s.into(), vt.into()))
+    }
+
+    /// Compute the QR decomposition of this matrix, as dense tensors.
+    ///
+    /// Returns the orthogonal matrix `Q` and the upper-triangular matrix `R`.
+    pub async fn qr(self, txn: T) -> TCResult<(Self, Self)> {
+        let dense = DenseTensor::from(dense_accessor(self));
+        let (q, r) = linalg::qr(dense, txn).await?;
+        Ok((q.into(), r.into()))
+    }
+
+    /// Compute the norm of the given order of this `Tensor` along the given `axis`.
+    ///
+    /// The L1 and L2 norms are lazy views; the infinity norm is computed as a dense tensor.
+    pub async fn norm(self, txn: T, ord: NormOrd, axis: usize) -> TCResult<Self> {
+        if axis >= self.ndim() {
+            return Err(TCError::bad_request(
+                format!("Tensor with shape {} has no axis", self.shape()),
+                axis,
+            ));
+        }
+
+        let abs = self.abs()?.cast_into(arg_dtype(self.dtype()))?;
+
+        match ord {
+            NormOrd::L1 => abs.sum(axis),
+            NormOrd::L2 => abs
+                .pow_const(Number::from(2f64))?
+                .sum(axis)?
+                .pow_const(Number::from(0.5f64)),
+            NormOrd::Inf => {
+                let dense = DenseTensor::from(dense_accessor(abs));
+                dense.norm_inf(txn, axis).map_ok(Self::from).await
+            }
+        }
+    }
+
+    /// Compute the norm of the given order of all the elements in this `Tensor`.
+    pub async fn norm_all(self, txn: T, ord: NormOrd) -> TCResult<Number> {
+        let abs = self.abs()?.cast_into(arg_dtype(self.dtype()))?;
+
+        match ord {
+            NormOrd::L1 => abs.sum_all(txn).await,
+            NormOrd::L2 => {
+                let sum = abs.pow_const(Number::from(2f64))?.sum_all(txn).await?;
+                Ok(Number::from(f64::cast_from(sum).sqrt()))
+            }
+            NormOrd::Inf => {
+                dense_accessor(abs)
+                    .value_stream(txn)
+                    .await?
+                    .map_ok(f64::cast_from)
+                    .try_fold(0f64, |max, value| future::ready(Ok(max.max(value))))
+                    .map_ok(Number::from)
+                    .await
+            }
+        }
+    }
+
+    /// Compute a histogram of the elements of this `Tensor` in `bins` equal-width bins which span
+    /// the range of its values, in two passes.
+    pub async fn histogram(self, txn: T, bins: usize) -> TCResult<Histogram> {
+        summary::histogram(self, txn, bins).await
+    }
+
+    /// Estimate the given quantiles `qs` of the elements of this `Tensor` in a single pass, using
+    /// a t-digest. Each estimate is `None` if this `Tensor` is empty.
+    pub async fn quantiles(self, txn: T, qs: &[f64]) -> TCResult<Vec<Option<f64>>> {
+        summary::quantiles(self, txn, qs).await
+    }
+
+    /// Return the fraction of the elements of this `Tensor` which are nonzero.
+    ///
+    /// For a dense tensor this requires reading every element.
+    pub async fn density(self, txn: T) -> TCResult<f64> {
+        match self {
+            Self::Dense(dense) => dense.into_sparse().into_inner().density(txn).await,
+            Self::Sparse(sparse) => sparse.into_inner().density(txn).await,
+        }
+    }
+
+    /// Materialize this `Tensor` like [`Tensor::materialize`], but as a dense or sparse tensor
+    /// according to its density and the given [`RepresentationPolicy`].
+    pub async fn materialize_with(self, txn: &T, policy: &RepresentationPolicy) -> TCResult<Self> {
+        let current = self.class();
+        let density = self.clone().density(txn.clone()).await?;
+
+        let tensor = match policy.choose(current, density) {
+            TensorType::Dense if current == TensorType::Sparse => {
+                debug!("materialize a sparse Tensor with density {} as dense", density);
+                self.into_dense()
+            }
+            TensorType::Sparse if current == TensorType::Dense => {
+                debug!("materialize a dense Tensor with density {} as sparse", density);
+                self.into_sparse()
+            }
+            _ => self,
+        };
+
+        tensor.materialize(txn).await
+    }
+}
+
 impl<FD: File<Array>, FS: File<Node>, D: Dir, T: Transaction<D>> Instance for Tensor<FD, FS, D, T> {
     type Class = TensorType;
 
@@ -822,6 +1312,34 @@ where
     }
 }
 
+#[async_trait]
+impl<FD, FS, D, T> TensorScan<D> for Tensor<FD, FS, D, T>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    type Txn = T;
+    type Scan = Self;
+
+    async fn cumprod(self, txn: Self::Txn, axis: usize) -> TCResult<Self::Scan> {
+        match self {
+            Self::Dense(dense) => dense.cumprod(txn, axis).map_ok(Self::from).await,
+            Self::Sparse(sparse) => sparse.cumprod(txn, axis).map_ok(Self::from).await,
+        }
+    }
+
+    async fn cumsum(self, txn: Self::Txn, axis: usize) -> TCResult<Self::Scan> {
+        match self {
+            Self::Dense(dense) => dense.cumsum(txn, axis).map_ok(Self::from).await,
+            Self::Sparse(sparse) => sparse.cumsum(txn, axis).map_ok(Self::from).await,
+        }
+    }
+}
+
 #[async_trait]
 impl<FD, FS, D, T> TensorIO<D> for Tensor<FD, FS, D, T>
 where
@@ -1139,6 +1657,23 @@ where
     trig! {tanh}
 }
 
+impl<FD, FS, D, T> TensorComplex for Tensor<FD, FS, D, T>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    type Unary = Self;
+
+    trig! {re}
+    trig! {im}
+    trig! {conj}
+    trig! {arg}
+}
+
 #[async_trait]
 impl<FD, FS, D, T> TensorUnary<D> for Tensor<FD, FS, D, T>
 where
@@ -1397,6 +1932,38 @@ where
     Ok((left.broadcast(shape.clone())?, right.broadcast(shape)?))
 }
 
+/// Compute the distance between each pair of rows of the matrices `a` and `b`, with shapes
+/// `[m, d]` and `[n, d]`, according to the metric given by `ord`, as a dense matrix with shape
+/// `[m, n]`.
+///
+/// The distances are computed block-wise by broadcasting `a` and `b` to the shape `[m, n, d]`.
+pub async fn cdist<FD, FS, D, T>(
+    a: Tensor<FD, FS, D, T>,
+    b: Tensor<FD, FS, D, T>,
+    txn: T,
+    ord: NormOrd,
+) -> TCResult<Tensor<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    if a.ndim() != 2 || b.ndim() != 2 || a.shape()[1] != b.shape()[1] {
+        return Err(TCError::unsupported(format!(
+            "cannot compute the pairwise distances between matrices with shapes {} and {}",
+            a.shape(),
+            b.shape()
+        )));
+    }
+
+    let (a, b) = broadcast(a.expand_dims(1)?, b.expand_dims(0)?)?;
+    let delta = a.sub(b)?.into_dense();
+    delta.norm(txn, ord, 2).await
+}
+
 #[derive(Clone)]
 struct Phantom<FD, FS, D, T> {
     dense: PhantomData<FD>,
@@ -1417,6 +1984,75 @@ impl<FD, FS, D, T> Default for Phantom<FD, FS, D, T> {
     }
 }
 
+/// Select each element of `then` where `cond` is true and of `or_else` where it's false, like
+/// NumPy's `where`, broadcasting all three to a common shape if necessary.
+///
+/// The result is sparse if both `then` and `or_else` are sparse, and dense otherwise.
+pub fn select<FD, FS, D, T>(
+    cond: Tensor<FD, FS, D, T>,
+    then: Tensor<FD, FS, D, T>,
+    or_else: Tensor<FD, FS, D, T>,
+) -> TCResult<Tensor<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    let (then, or_else) = if then.shape() == or_else.shape() {
+        (then, or_else)
+    } else {
+        broadcast(then, or_else)?
+    };
+
+    let (cond, then, or_else) = if cond.shape() == then.shape() {
+        (cond, then, or_else)
+    } else {
+        let (cond, then) = broadcast(cond, then)?;
+        let or_else = or_else.broadcast(then.shape().clone())?;
+        (cond, then, or_else)
+    };
+
+    match (then, or_else) {
+        (Tensor::Sparse(then), Tensor::Sparse(or_else)) => {
+            let cond = match cond {
+                Tensor::Dense(dense) => dense.into_sparse().into_inner().accessor(),
+                Tensor::Sparse(sparse) => sparse.into_inner(),
+            };
+
+            let select = SparseSelect::new(cond, then.into_inner(), or_else.into_inner())?;
+            Ok(Tensor::Sparse(select.accessor().into()))
+        }
+        (then, or_else) => {
+            let select = BlockListSelect::new(
+                dense_accessor(cond),
+                dense_accessor(then),
+                dense_accessor(or_else),
+            )?;
+
+            Ok(Tensor::Dense(select.accessor().into()))
+        }
+    }
+}
+
+#[inline]
+fn dense_accessor<FD, FS, D, T>(tensor: Tensor<FD, FS, D, T>) -> DenseAccessor<FD, FS, D, T>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    match tensor {
+        Tensor::Dense(dense) => dense.into_inner(),
+        Tensor::Sparse(sparse) => sparse.into_dense().into_inner().accessor(),
+    }
+}
+
 #[inline]
 fn coord_bounds(shape: &[u64]) -> Vec<u64> {
     (0..shape.len())
@@ -0,0 +1,49 @@
+//! Linear algebra kernels computed by ArrayFire.
+
+pub fn inverse(values: &[f64], n: u64) -> Vec<f64> {
+    host(&af::inverse(&device(values, n, n), af::MatProp::NONE))
+}
+
+pub fn det(values: &[f64], n: u64) -> f64 {
+    let (det, _) = af::det(&device(values, n, n));
+    det
+}
+
+pub fn solve(a: &[f64], b: &[f64], n: u64, cols: u64) -> Vec<f64> {
+    let x = af::solve(&device(a, n, n), &device(b, n, cols), af::MatProp::NONE);
+    host(&x)
+}
+
+pub fn cholesky(values: &[f64], n: u64) -> Option<Vec<f64>> {
+    let (factor, info) = af::cholesky(&device(values, n, n), false);
+    if info == 0 {
+        Some(host(&af::lower(&factor, false)))
+    } else {
+        None
+    }
+}
+
+pub fn svd(values: &[f64], m: u64, n: u64) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let (u, s, vt) = af::svd(&device(values, m, n));
+    (host(&u), host(&s), host(&vt))
+}
+
+pub fn qr(values: &[f64], m: u64, n: u64) -> (Vec<f64>, Vec<f64>) {
+    let (q, r, _tau) = af::qr(&device(values, m, n));
+    (host(&q), host(&r))
+}
+
+// copy the given row-major `values` to an ArrayFire array with shape `[rows, cols]`
+fn device(values: &[f64], rows: u64, cols: u64) -> af::Array<f64> {
+    // ArrayFire arrays are column-major, so the row-major values of a matrix are its transpose
+    let transpose = af::Array::new(values, af::Dim4::new(&[cols, rows, 1, 1]));
+    af::transpose(&transpose, false)
+}
+
+// copy the given ArrayFire `matrix` into host memory in row-major order
+fn host(matrix: &af::Array<f64>) -> Vec<f64> {
+    let transpose = af::transpose(matrix, false);
+    let mut values = vec![0.; transpose.elements()];
+    transpose.host(&mut values);
+    values
+}
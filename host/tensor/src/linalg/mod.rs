@@ -0,0 +1,331 @@
+//! Linear algebra on 2-dimensional dense tensors which fit in memory.
+//!
+//! Each matrix is loaded into memory as a row-major `Vec<f64>` and passed to the kernels of the
+//! array backend enabled at compile time: ArrayFire, or a pure-Rust implementation on `ndarray`.
+
+use futures::stream::{self, TryStreamExt};
+use safecast::{AsType, CastFrom};
+
+use tc_btree::Node;
+use tc_error::*;
+use tc_transact::fs::{Dir, File};
+use tc_transact::Transaction;
+use tc_value::{FloatType, Number, NumberType, Promote};
+
+use super::dense::{BlockListFile, DenseAccess, DenseTensor, PER_BLOCK};
+use super::{Array, Shape, TensorAccess, TensorType};
+
+#[cfg(feature = "arrayfire")]
+use self::af as kernel;
+#[cfg(all(feature = "ndarray", not(feature = "arrayfire")))]
+use self::nd as kernel;
+
+#[cfg(feature = "arrayfire")]
+mod af;
+#[cfg(all(feature = "ndarray", not(feature = "arrayfire")))]
+mod nd;
+
+/// The maximum number of elements of a matrix which can be loaded into memory for a linear
+/// algebra operation, equal to 64 blocks.
+pub const MAX_MATRIX_SIZE: u64 = 64 * PER_BLOCK as u64;
+
+type Matrix<FD, FS, D, T> = DenseTensor<FD, FS, D, T, BlockListFile<FD, FS, D, T>>;
+
+/// Compute the inverse of the given square `matrix`.
+pub async fn inv<FD, FS, D, T, B>(
+    matrix: DenseTensor<FD, FS, D, T, B>,
+    txn: T,
+) -> TCResult<Matrix<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    require_square("invert", &matrix)?;
+
+    let shape = matrix.shape().clone();
+    let dtype = matrix_dtype(matrix.dtype());
+    let (values, [n, _]) = load(matrix, txn.clone()).await?;
+
+    let values = kernel::inverse(&values, n);
+    if values.iter().any(|n| !n.is_finite()) {
+        return Err(TCError::bad_request(
+            "cannot invert a singular matrix with shape",
+            shape,
+        ));
+    }
+
+    store(txn, shape, dtype, values).await
+}
+
+/// Compute the determinant of the given square `matrix`.
+pub async fn det<FD, FS, D, T, B>(matrix: DenseTensor<FD, FS, D, T, B>, txn: T) -> TCResult<Number>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+{
+    require_square("compute the determinant of", &matrix)?;
+
+    let (values, [n, _]) = load(matrix, txn).await?;
+    Ok(Number::from(kernel::det(&values, n)))
+}
+
+/// Solve the system of linear equations `a * x = b` for `x`.
+///
+/// `a` must be a square matrix, and `b` either a vector or a matrix with the same number of rows.
+pub async fn solve<FD, FS, D, T, A, B>(
+    a: DenseTensor<FD, FS, D, T, A>,
+    b: DenseTensor<FD, FS, D, T, B>,
+    txn: T,
+) -> TCResult<Matrix<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    A: DenseAccess<FD, FS, D, T>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    require_square("solve a system of equations with", &a)?;
+
+    if b.ndim() == 0 || b.ndim() > 2 || b.shape()[0] != a.shape()[0] {
+        return Err(TCError::unsupported(format!(
+            "cannot solve a system of equations with shape {} for a right-hand side with shape {}",
+            a.shape(),
+            b.shape()
+        )));
+    }
+
+    let shape = b.shape().clone();
+    let dtype = matrix_dtype(a.dtype()).promote(matrix_dtype(b.dtype()));
+    let a_shape = a.shape().clone();
+
+    let (a, [n, _]) = load(a, txn.clone()).await?;
+    let (b, [_, cols]) = load(b, txn.clone()).await?;
+
+    let values = kernel::solve(&a, &b, n, cols);
+    if values.iter().any(|n| !n.is_finite()) {
+        return Err(TCError::bad_request(
+            "cannot solve a system of equations with a singular matrix of shape",
+            a_shape,
+        ));
+    }
+
+    store(txn, shape, dtype, values).await
+}
+
+/// Compute the lower-triangular Cholesky factor `L` of the given symmetric, positive-definite
+/// `matrix`, such that `matrix = L * L^T`.
+pub async fn cholesky<FD, FS, D, T, B>(
+    matrix: DenseTensor<FD, FS, D, T, B>,
+    txn: T,
+) -> TCResult<Matrix<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    require_square("compute the Cholesky decomposition of", &matrix)?;
+
+    let shape = matrix.shape().clone();
+    let dtype = matrix_dtype(matrix.dtype());
+
+    let (values, [n, _]) = load(matrix, txn.clone()).await?;
+    let factor = kernel::cholesky(&values, n).ok_or_else(|| {
+        TCError::bad_request(
+            "cannot compute the Cholesky decomposition of a matrix which is not positive-definite, with shape",
+            shape.clone(),
+        )
+    })?;
+
+    store(txn, shape, dtype, factor).await
+}
+
+/// Compute the singular value decomposition `U * S * V^T` of the given `matrix`, with shape
+/// `[m, n]`.
+///
+/// Returns `U` with shape `[m, m]`, the singular values `S` in descending order with shape
+/// `[min(m, n)]`, and `V^T` with shape `[n, n]`.
+pub async fn svd<FD, FS, D, T, B>(
+    matrix: DenseTensor<FD, FS, D, T, B>,
+    txn: T,
+) -> TCResult<(
+    Matrix<FD, FS, D, T>,
+    Matrix<FD, FS, D, T>,
+    Matrix<FD, FS, D, T>,
+)>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    require_matrix("compute the singular value decomposition of", &matrix)?;
+
+    let (m, n) = (matrix.shape()[0], matrix.shape()[1]);
+    let dtype = matrix_dtype(matrix.dtype());
+
+    let (values, _) = load(matrix, txn.clone()).await?;
+    let (u, s, vt) = kernel::svd(&values, m, n);
+
+    let u = store(txn.clone(), vec![m, m].into(), dtype, u).await?;
+    let s = store(txn.clone(), vec![m.min(n)].into(), dtype, s).await?;
+    let vt = store(txn, vec![n, n].into(), dtype, vt).await?;
+
+    Ok((u, s, vt))
+}
+
+/// Compute the QR decomposition `Q * R` of the given `matrix`, with shape `[m, n]`.
+///
+/// Returns the orthogonal matrix `Q` with shape `[m, m]` and the upper-triangular matrix `R`
+/// with shape `[m, n]`.
+pub async fn qr<FD, FS, D, T, B>(
+    matrix: DenseTensor<FD, FS, D, T, B>,
+    txn: T,
+) -> TCResult<(Matrix<FD, FS, D, T>, Matrix<FD, FS, D, T>)>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+    D::FileClass: From<TensorType>,
+{
+    require_matrix("compute the QR decomposition of", &matrix)?;
+
+    let (m, n) = (matrix.shape()[0], matrix.shape()[1]);
+    let dtype = matrix_dtype(matrix.dtype());
+
+    let (values, _) = load(matrix, txn.clone()).await?;
+    let (q, r) = kernel::qr(&values, m, n);
+
+    let q = store(txn.clone(), vec![m, m].into(), dtype, q).await?;
+    let r = store(txn, vec![m, n].into(), dtype, r).await?;
+
+    Ok((q, r))
+}
+
+fn require_matrix<T: TensorAccess>(action: &str, matrix: &T) -> TCResult<()> {
+    if matrix.ndim() == 2 {
+        Ok(())
+    } else {
+        Err(TCError::unsupported(format!(
+            "cannot {} a Tensor with shape {} (expected a matrix)",
+            action,
+            matrix.shape()
+        )))
+    }
+}
+
+fn require_square<T: TensorAccess>(action: &str, matrix: &T) -> TCResult<()> {
+    if matrix.ndim() == 2 && matrix.shape()[0] == matrix.shape()[1] {
+        Ok(())
+    } else {
+        Err(TCError::unsupported(format!(
+            "cannot {} a Tensor with shape {} (expected a square matrix)",
+            action,
+            matrix.shape()
+        )))
+    }
+}
+
+// the datatype of the result of a linear algebra op on a matrix of the given type
+fn matrix_dtype(dtype: NumberType) -> NumberType {
+    match dtype {
+        NumberType::Float(FloatType::F32) => FloatType::F32.into(),
+        _ => FloatType::F64.into(),
+    }
+}
+
+// read the given `matrix` into memory in row-major order, with its (row, column) shape,
+// treating a vector as a single column
+async fn load<FD, FS, D, T, B>(
+    matrix: DenseTensor<FD, FS, D, T, B>,
+    txn: T,
+) -> TCResult<(Vec<f64>, [u64; 2])>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    B: DenseAccess<FD, FS, D, T>,
+{
+    if matrix.ndim() == 0 || matrix.ndim() > 2 {
+        return Err(TCError::unsupported(format!(
+            "expected a matrix, not a Tensor with shape {}",
+            matrix.shape()
+        )));
+    } else if matrix.size() > MAX_MATRIX_SIZE {
+        return Err(TCError::unsupported(format!(
+            "a matrix with shape {} is too large to load into memory (the maximum size is {} elements)",
+            matrix.shape(),
+            MAX_MATRIX_SIZE
+        )));
+    } else if let NumberType::Complex(_) = matrix.dtype() {
+        return Err(TCError::unsupported(
+            "linear algebra on a complex matrix is not supported",
+        ));
+    }
+
+    let rows = matrix.shape()[0];
+    let cols = if matrix.ndim() == 2 {
+        matrix.shape()[1]
+    } else {
+        1
+    };
+
+    let values: Vec<f64> = matrix
+        .into_inner()
+        .value_stream(txn)
+        .await?
+        .map_ok(f64::cast_from)
+        .try_collect()
+        .await?;
+
+    Ok((values, [rows, cols]))
+}
+
+// write the given row-major `values` to a new dense tensor
+async fn store<FD, FS, D, T>(
+    txn: T,
+    shape: Shape,
+    dtype: NumberType,
+    values: Vec<f64>,
+) -> TCResult<Matrix<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    let txn_id = *txn.id();
+    let file = txn
+        .context()
+        .create_file_unique(txn_id, TensorType::Dense)
+        .await?;
+
+    let values = stream::iter(values.into_iter().map(Number::from).map(Ok));
+    let blocks = BlockListFile::from_values(file, txn_id, shape, dtype, values).await?;
+    Ok(blocks.into())
+}
@@ -0,0 +1,389 @@
+//! Linear algebra kernels computed in pure Rust on `ndarray`.
+
+use std::cmp::Ordering;
+
+use ndarray::{Array1, Array2};
+
+// the maximum number of Jacobi sweeps used to compute a singular value decomposition
+const MAX_SWEEPS: usize = 64;
+
+pub fn inverse(values: &[f64], n: u64) -> Vec<f64> {
+    let identity = Array2::eye(n as usize);
+    match gauss_jordan(matrix(values, n, n), identity) {
+        Some(inverse) => inverse.iter().cloned().collect(),
+        None => vec![f64::NAN; values.len()],
+    }
+}
+
+pub fn det(values: &[f64], n: u64) -> f64 {
+    let mut a = matrix(values, n, n);
+    let n = n as usize;
+
+    let mut det = 1.;
+    for k in 0..n {
+        let pivot = pivot(&a, k);
+        if a[[pivot, k]] == 0. {
+            return 0.;
+        } else if pivot != k {
+            swap_rows(&mut a, pivot, k);
+            det = -det;
+        }
+
+        det *= a[[k, k]];
+
+        for i in (k + 1)..n {
+            let factor = a[[i, k]] / a[[k, k]];
+            for j in k..n {
+                a[[i, j]] -= factor * a[[k, j]];
+            }
+        }
+    }
+
+    det
+}
+
+pub fn solve(a: &[f64], b: &[f64], n: u64, cols: u64) -> Vec<f64> {
+    match gauss_jordan(matrix(a, n, n), matrix(b, n, cols)) {
+        Some(x) => x.iter().cloned().collect(),
+        None => vec![f64::NAN; b.len()],
+    }
+}
+
+pub fn cholesky(values: &[f64], n: u64) -> Option<Vec<f64>> {
+    let a = matrix(values, n, n);
+    let n = n as usize;
+
+    let mut factor = Array2::<f64>::zeros((n, n));
+    for j in 0..n {
+        let sum: f64 = (0..j).map(|k| factor[[j, k]] * factor[[j, k]]).sum();
+        let diagonal = a[[j, j]] - sum;
+        if !(diagonal > 0.) {
+            return None;
+        }
+
+        factor[[j, j]] = diagonal.sqrt();
+
+        for i in (j + 1)..n {
+            let sum: f64 = (0..j).map(|k| factor[[i, k]] * factor[[j, k]]).sum();
+            factor[[i, j]] = (a[[i, j]] - sum) / factor[[j, j]];
+        }
+    }
+
+    Some(factor.iter().cloned().collect())
+}
+
+pub fn svd(values: &[f64], m: u64, n: u64) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    // one-sided (Hestenes) Jacobi: orthogonalize the columns of `a` by plane rotations, so that
+    // a * v = u * s where the norm of each column of `a` is its singular value
+    let mut a = matrix(values, m, n);
+    let (m, n) = (m as usize, n as usize);
+    let mut v = Array2::<f64>::eye(n);
+
+    for _ in 0..MAX_SWEEPS {
+        let mut rotated = false;
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let alpha = a.column(p).dot(&a.column(p));
+                let beta = a.column(q).dot(&a.column(q));
+                let gamma = a.column(p).dot(&a.column(q));
+
+                if gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() {
+                    continue;
+                }
+
+                rotated = true;
+
+                let zeta = (beta - alpha) / (2. * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1. + zeta * zeta).sqrt());
+                let c = 1. / (1. + t * t).sqrt();
+                let s = c * t;
+
+                rotate_columns(&mut a, p, q, c, s);
+                rotate_columns(&mut v, p, q, c, s);
+            }
+        }
+
+        if !rotated {
+            break;
+        }
+    }
+
+    let mut order: Vec<(usize, f64)> = (0..n)
+        .map(|j| (j, a.column(j).dot(&a.column(j)).sqrt()))
+        .collect();
+
+    order.sort_by(|(_, l), (_, r)| r.partial_cmp(l).unwrap_or(Ordering::Equal));
+
+    let k = m.min(n);
+    let tolerance = f64::EPSILON * order.first().map(|(_, s)| *s).unwrap_or(0.) * m.max(n) as f64;
+
+    let mut u = Vec::with_capacity(m);
+    let mut s = Vec::with_capacity(k);
+    for (j, sigma) in order.iter().take(k) {
+        s.push(*sigma);
+
+        if *sigma > tolerance {
+            u.push(Some(a.column(*j).mapv(|x| x / sigma)));
+        } else {
+            u.push(None);
+        }
+    }
+
+    u.resize(m, None);
+    let u = complete_basis(u, m);
+
+    let mut vt = Array2::<f64>::zeros((n, n));
+    for (i, (j, _)) in order.iter().enumerate() {
+        vt.row_mut(i).assign(&v.column(*j));
+    }
+
+    (u.iter().cloned().collect(), s, vt.iter().cloned().collect())
+}
+
+pub fn qr(values: &[f64], m: u64, n: u64) -> (Vec<f64>, Vec<f64>) {
+    // Householder reflections
+    let mut r = matrix(values, m, n);
+    let (m, n) = (m as usize, n as usize);
+    let mut q = Array2::<f64>::eye(m);
+
+    for k in 0..n.min(m.saturating_sub(1)) {
+        let x: Array1<f64> = (k..m).map(|i| r[[i, k]]).collect();
+        let norm = x.dot(&x).sqrt();
+        if norm == 0. {
+            continue;
+        }
+
+        let alpha = if x[0] > 0. { -norm } else { norm };
+        let mut v = x;
+        v[0] -= alpha;
+
+        let v_norm = v.dot(&v).sqrt();
+        if v_norm == 0. {
+            continue;
+        }
+
+        v.mapv_inplace(|x| x / v_norm);
+
+        // r = (I - 2vv^T) r, applied to rows k..m
+        for j in 0..n {
+            let dot: f64 = (k..m).map(|i| v[i - k] * r[[i, j]]).sum();
+            for i in k..m {
+                r[[i, j]] -= 2. * v[i - k] * dot;
+            }
+        }
+
+        // q = q (I - 2vv^T), applied to columns k..m
+        for i in 0..m {
+            let dot: f64 = (k..m).map(|j| q[[i, j]] * v[j - k]).sum();
+            for j in k..m {
+                q[[i, j]] -= 2. * dot * v[j - k];
+            }
+        }
+    }
+
+    for i in 0..m {
+        for j in 0..i.min(n) {
+            r[[i, j]] = 0.;
+        }
+    }
+
+    (q.iter().cloned().collect(), r.iter().cloned().collect())
+}
+
+fn matrix(values: &[f64], rows: u64, cols: u64) -> Array2<f64> {
+    Array2::from_shape_vec((rows as usize, cols as usize), values.to_vec()).expect("matrix values")
+}
+
+// solve `a * x = b` for `x` by Gauss-Jordan elimination with partial pivoting,
+// or return `None` if `a` is singular
+fn gauss_jordan(mut a: Array2<f64>, mut b: Array2<f64>) -> Option<Array2<f64>> {
+    let n = a.nrows();
+    let scale = a.iter().fold(0., |max: f64, x| max.max(x.abs()));
+    let tolerance = f64::EPSILON * scale * n as f64;
+
+    for k in 0..n {
+        let pivot = pivot(&a, k);
+        if a[[pivot, k]].abs() <= tolerance {
+            return None;
+        } else if pivot != k {
+            swap_rows(&mut a, pivot, k);
+            swap_rows(&mut b, pivot, k);
+        }
+
+        let divisor = a[[k, k]];
+        a.row_mut(k).mapv_inplace(|x| x / divisor);
+        b.row_mut(k).mapv_inplace(|x| x / divisor);
+
+        for i in 0..n {
+            if i == k {
+                continue;
+            }
+
+            let factor = a[[i, k]];
+            if factor == 0. {
+                continue;
+            }
+
+            for j in 0..a.ncols() {
+                a[[i, j]] -= factor * a[[k, j]];
+            }
+
+            for j in 0..b.ncols() {
+                b[[i, j]] -= factor * b[[k, j]];
+            }
+        }
+    }
+
+    Some(b)
+}
+
+// the row at or below `k` with the largest absolute value in column `k`
+fn pivot(a: &Array2<f64>, k: usize) -> usize {
+    (k..a.nrows())
+        .max_by(|l, r| {
+            a[[*l, k]]
+                .abs()
+                .partial_cmp(&a[[*r, k]].abs())
+                .unwrap_or(Ordering::Equal)
+        })
+        .expect("pivot")
+}
+
+fn swap_rows(a: &mut Array2<f64>, i: usize, k: usize) {
+    for j in 0..a.ncols() {
+        a.swap([i, j], [k, j]);
+    }
+}
+
+fn rotate_columns(a: &mut Array2<f64>, p: usize, q: usize, c: f64, s: f64) {
+    for i in 0..a.nrows() {
+        let (x, y) = (a[[i, p]], a[[i, q]]);
+        a[[i, p]] = c * x - s * y;
+        a[[i, q]] = s * x + c * y;
+    }
+}
+
+// fill in the missing columns of the given partial orthonormal basis of R^m by Gram-Schmidt
+// orthogonalization of the standard basis vectors
+fn complete_basis(columns: Vec<Option<Array1<f64>>>, m: usize) -> Array2<f64> {
+    let mut basis: Vec<Array1<f64>> = columns.iter().flatten().cloned().collect();
+    let mut candidates = 0..m;
+
+    let mut complete = Array2::<f64>::zeros((m, m));
+    for (j, column) in columns.into_iter().enumerate() {
+        let column = match column {
+            Some(column) => column,
+            None => loop {
+                let i = candidates.next().expect("basis vector");
+                let mut candidate = Array1::<f64>::zeros(m);
+                candidate[i] = 1.;
+
+                for vector in &basis {
+                    let dot = candidate.dot(vector);
+                    candidate.scaled_add(-dot, vector);
+                }
+
+                let norm = candidate.dot(&candidate).sqrt();
+                if norm > 1e-8 {
+                    candidate.mapv_inplace(|x| x / norm);
+                    basis.push(candidate.clone());
+                    break candidate;
+                }
+            },
+        };
+
+        complete.column_mut(j).assign(&column);
+    }
+
+    complete
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-9;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < EPSILON, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    fn matmul(l: &[f64], r: &[f64], m: u64, k: u64, n: u64) -> Vec<f64> {
+        let product = matrix(l, m, k).dot(&matrix(r, k, n));
+        product.iter().cloned().collect()
+    }
+
+    fn transpose(values: &[f64], m: u64, n: u64) -> Vec<f64> {
+        matrix(values, m, n).t().iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_inverse() {
+        let a = [4., 7., 2., 6.];
+        assert_close(&inverse(&a, 2), &[0.6, -0.7, -0.2, 0.4]);
+        assert!(inverse(&[1., 2., 2., 4.], 2).iter().all(|n| n.is_nan()));
+    }
+
+    #[test]
+    fn test_det() {
+        assert!((det(&[4., 7., 2., 6.], 2) - 10.).abs() < EPSILON);
+        assert!((det(&[0., 1., 1., 0.], 2) + 1.).abs() < EPSILON);
+        assert_eq!(det(&[1., 2., 2., 4.], 2), 0.);
+    }
+
+    #[test]
+    fn test_solve() {
+        let a = [3., 2., -1., 2., -2., 4., -1., 0.5, -1.];
+        let b = [1., -2., 0.];
+        assert_close(&solve(&a, &b, 3, 1), &[1., -2., -2.]);
+    }
+
+    #[test]
+    fn test_cholesky() {
+        let a = [4., 12., -16., 12., 37., -43., -16., -43., 98.];
+        let l = cholesky(&a, 3).expect("factor");
+        assert_close(&l, &[2., 0., 0., 6., 1., 0., -8., 5., 3.]);
+        assert!(cholesky(&[1., 2., 2., 1.], 2).is_none());
+    }
+
+    #[test]
+    fn test_qr() {
+        let a = [1., 2., 3., 4., 5., 6.];
+        let (q, r) = qr(&a, 3, 2);
+
+        assert_close(&matmul(&q, &r, 3, 3, 2), &a);
+        assert_close(
+            &matmul(&transpose(&q, 3, 3), &q, 3, 3, 3),
+            &[1., 0., 0., 0., 1., 0., 0., 0., 1.],
+        );
+        assert!(r[2].abs() < EPSILON && r[4].abs() < EPSILON && r[5].abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_svd() {
+        for (a, m, n) in vec![
+            (vec![3., 2., 2., 2., 3., -2.], 2, 3),
+            (vec![1., 2., 3., 4., 5., 6.], 3, 2),
+            (vec![1., 1., 1., 1.], 2, 2),
+        ] {
+            let (u, s, vt) = svd(&a, m, n);
+            assert!(s.windows(2).all(|w| w[0] >= w[1]));
+
+            let mut sigma = vec![0.; (m * n) as usize];
+            for (i, s) in s.iter().enumerate() {
+                sigma[i * n as usize + i] = *s;
+            }
+
+            let usv = matmul(&matmul(&u, &sigma, m, m, n), &vt, m, n, n);
+            assert_close(&usv, &a);
+
+            let identity = Array2::<f64>::eye(m as usize);
+            let identity: Vec<f64> = identity.iter().cloned().collect();
+            assert_close(&matmul(&transpose(&u, m, m), &u, m, m, m), &identity);
+        }
+    }
+}
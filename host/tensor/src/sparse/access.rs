@@ -1,6 +1,5 @@
 use std::fmt;
 
-use afarray::{Array, CoordBlocks, CoordMerge, Coords};
 use async_trait::async_trait;
 use futures::future::{self, TryFutureExt};
 use futures::stream::{self, StreamExt, TryStreamExt};
@@ -12,14 +11,15 @@ use tc_btree::Node;
 use tc_error::*;
 use tc_transact::fs::{Dir, File};
 use tc_transact::{Transaction, TxnId};
-use tc_value::{FloatInstance, Number, NumberClass, NumberInstance, NumberType};
+use tc_value::{FloatInstance, Number, NumberClass, NumberInstance, NumberType, Promote};
 use tcgeneric::{TCBoxTryFuture, TCBoxTryStream, Tuple};
 
+use crate::block::{Array, CoordBlocks, CoordMerge, Coords};
 use crate::dense::{DenseAccess, DenseAccessor, DenseTensor, PER_BLOCK};
 use crate::stream::{sorted_coords, sorted_values, Read, ReadValueAt};
 use crate::{
     coord_bounds, transform, AxisBounds, Bounds, Coord, Phantom, Shape, TensorAccess, TensorType,
-    TensorUnary, ERR_INF, ERR_NAN,
+    TensorUnary, ERR_INF, ERR_NAN, ERR_VIEW_WRITE,
 };
 
 use super::combine::{coord_to_offset, SparseCombine};
@@ -54,6 +54,17 @@ pub trait SparseAccess<FD: File<Array>, FS: File<Node>, D: Dir, T: Transaction<D
     /// Return the number of nonzero values in this [`SparseTensor`].
     async fn filled_count(self, txn: T) -> TCResult<u64>;
 
+    /// Return the fraction of the elements of this [`SparseTensor`] which are nonzero.
+    async fn density(self, txn: T) -> TCResult<f64> {
+        let size = self.size();
+        if size == 0 {
+            return Ok(0.);
+        }
+
+        let filled = self.filled_count(txn).await?;
+        Ok(filled as f64 / size as f64)
+    }
+
     /// Return a slice of this accessor with the given [`Bounds`].
     fn slice(self, bounds: Bounds) -> TCResult<Self::Slice>;
 
@@ -86,6 +97,7 @@ pub enum SparseAccessor<FD, FS, D, T> {
     Slice(SparseTableSlice<FD, FS, D, T>),
     Reduce(Box<SparseReduce<FD, FS, D, T>>),
     Reshape(Box<SparseReshape<FD, FS, D, T, Self>>),
+    Select(Box<SparseSelect<FD, FS, D, T, Self, Self, Self>>),
     Table(SparseTable<FD, FS, D, T>),
     Transpose(Box<SparseTranspose<FD, FS, D, T, Self>>),
     Unary(Box<SparseUnary<FD, FS, D, T>>),
@@ -105,6 +117,7 @@ macro_rules! dispatch {
             Self::Slice($var) => $call,
             Self::Reduce($var) => $call,
             Self::Reshape($var) => $call,
+            Self::Select($var) => $call,
             Self::Table($var) => $call,
             Self::Transpose($var) => $call,
             Self::Unary($var) => $call,
@@ -193,7 +206,7 @@ where
     async fn write_value(&self, txn_id: TxnId, coord: Coord, value: Number) -> TCResult<()> {
         match self {
             Self::Table(table) => table.write_value(txn_id, coord, value).await,
-            _ => Err(TCError::unsupported("cannot write to a Tensor view")),
+            _ => Err(TCError::unsupported(ERR_VIEW_WRITE)),
         }
     }
 }
@@ -746,18 +759,18 @@ where
 
         let coord_bounds = coord_bounds(self.shape());
         let combinator = self.combinator;
-        let left_zero = self.left.dtype().zero();
-        let right_zero = self.right.dtype().zero();
+        let dtype = self.left.dtype().promote(self.right.dtype());
+        let zero = dtype.zero();
 
         let offset = move |row: &SparseRow| coord_to_offset(&row.0, &coord_bounds);
         let combined = SparseCombine::new(left, right, offset)
             .map_ok(move |(l, r)| match (l, r) {
                 (Some((l_coord, l)), Some((r_coord, r))) => {
                     debug_assert_eq!(l_coord, r_coord);
-                    (l_coord, combinator(l, r))
+                    (l_coord, combinator(l.into_type(dtype), r.into_type(dtype)))
                 }
-                (Some((l_coord, l)), None) => (l_coord, combinator(l, right_zero)),
-                (None, Some((r_coord, r))) => (r_coord, combinator(left_zero, r)),
+                (Some((l_coord, l)), None) => (l_coord, combinator(l.into_type(dtype), zero)),
+                (None, Some((r_coord, r))) => (r_coord, combinator(zero, r.into_type(dtype))),
                 (None, None) => {
                     panic!("expected a coordinate and value from one sparse tensor stream")
                 }
@@ -788,7 +801,8 @@ where
     R: SparseAccess<FD, FS, D, T>,
 {
     fn dtype(&self) -> NumberType {
-        (self.combinator)(self.left.dtype().zero(), self.right.dtype().zero()).class()
+        let dtype = self.left.dtype().promote(self.right.dtype());
+        (self.combinator)(dtype.zero(), dtype.zero()).class()
     }
 
     fn ndim(&self) -> usize {
@@ -905,10 +919,11 @@ where
 
     fn read_value_at<'a>(self, txn: T, coord: Coord) -> Read<'a> {
         Box::pin(async move {
+            let dtype = self.left.dtype().promote(self.right.dtype());
             let left = self.left.read_value_at(txn.clone(), coord.to_vec());
             let right = self.right.read_value_at(txn, coord);
             let ((coord, left), (_, right)) = try_join!(left, right)?;
-            let value = (self.combinator)(left, right);
+            let value = (self.combinator)(left.into_type(dtype), right.into_type(dtype));
             Ok((coord, value))
         })
     }
@@ -920,6 +935,221 @@ impl<FD, FS, D, T, L, R> fmt::Display for SparseCombinator<FD, FS, D, T, L, R> {
     }
 }
 
+#[derive(Clone)]
+pub struct SparseSelect<FD, FS, D, T, C, L, R> {
+    cond: C,
+    then: L,
+    or_else: R,
+    phantom: Phantom<FD, FS, D, T>,
+}
+
+impl<FD, FS, D, T, C, L, R> SparseSelect<FD, FS, D, T, C, L, R>
+where
+    FD: File<Array>,
+    FS: File<Node>,
+    D: Dir,
+    T: Transaction<D>,
+    C: SparseAccess<FD, FS, D, T>,
+    L: SparseAccess<FD, FS, D, T>,
+    R: SparseAccess<FD, FS, D, T>,
+{
+    pub fn new(cond: C, then: L, or_else: R) -> TCResult<Self> {
+        if cond.dtype() != NumberType::Bool {
+            return Err(TCError::bad_request(
+                "the condition of a Tensor selection must be boolean, not",
+                cond.dtype(),
+            ));
+        }
+
+        if then.shape() != cond.shape() || or_else.shape() != cond.shape() {
+            return Err(TCError::unsupported(
+                "tried to select from SparseTensors with different shapes",
+            ));
+        }
+
+        Ok(SparseSelect {
+            cond,
+            then,
+            or_else,
+            phantom: Phantom::default(),
+        })
+    }
+}
+
+impl<FD, FS, D, T, C, L, R> TensorAccess for SparseSelect<FD, FS, D, T, C, L, R>
+where
+    FD: File<Array>,
+    FS: File<Node>,
+    D: Dir,
+    T: Transaction<D>,
+    C: SparseAccess<FD, FS, D, T>,
+    L: SparseAccess<FD, FS, D, T>,
+    R: SparseAccess<FD, FS, D, T>,
+{
+    fn dtype(&self) -> NumberType {
+        self.then.dtype().promote(self.or_else.dtype())
+    }
+
+    fn ndim(&self) -> usize {
+        self.cond.ndim()
+    }
+
+    fn shape(&'_ self) -> &'_ Shape {
+        self.cond.shape()
+    }
+
+    fn size(&self) -> u64 {
+        self.cond.size()
+    }
+}
+
+#[async_trait]
+impl<FD, FS, D, T, C, L, R> SparseAccess<FD, FS, D, T> for SparseSelect<FD, FS, D, T, C, L, R>
+where
+    FD: File<Array>,
+    FS: File<Node>,
+    D: Dir,
+    T: Transaction<D>,
+    C: SparseAccess<FD, FS, D, T>,
+    L: SparseAccess<FD, FS, D, T>,
+    R: SparseAccess<FD, FS, D, T>,
+{
+    type Slice = SparseSelect<FD, FS, D, T, C::Slice, L::Slice, R::Slice>;
+    type Transpose = SparseSelect<FD, FS, D, T, C::Transpose, L::Transpose, R::Transpose>;
+
+    fn accessor(self) -> SparseAccessor<FD, FS, D, T> {
+        SparseAccessor::Select(Box::new(SparseSelect {
+            cond: self.cond.accessor(),
+            then: self.then.accessor(),
+            or_else: self.or_else.accessor(),
+            phantom: self.phantom,
+        }))
+    }
+
+    async fn filled<'a>(self, txn: T) -> TCResult<SparseStream<'a>> {
+        let dtype = self.dtype();
+        let zero = dtype.zero();
+
+        let then = self.then.filled(txn.clone());
+        let or_else = self.or_else.filled(txn.clone());
+        let (then, or_else) = try_join!(then, or_else)?;
+
+        // only a coordinate filled in `then` or `or_else` can be filled in the selection
+        let coord_bounds = coord_bounds(self.cond.shape());
+        let offset = move |row: &SparseRow| coord_to_offset(&row.0, &coord_bounds);
+
+        let cond = self.cond;
+        let selected = SparseCombine::new(then, or_else, offset)
+            .map_ok(move |(then, or_else)| {
+                let (coord, then, or_else) = match (then, or_else) {
+                    (Some((coord, then)), Some((_, or_else))) => (coord, then, or_else),
+                    (Some((coord, then)), None) => (coord, then, zero),
+                    (None, Some((coord, or_else))) => (coord, zero, or_else),
+                    (None, None) => {
+                        panic!("expected a coordinate and value from one sparse tensor stream")
+                    }
+                };
+
+                cond.clone()
+                    .read_value_at(txn.clone(), coord)
+                    .map_ok(move |(coord, cond)| {
+                        let value = if cond == cond.class().zero() {
+                            or_else
+                        } else {
+                            then
+                        };
+
+                        (coord, value.into_type(dtype))
+                    })
+            })
+            .try_buffered(num_cpus::get())
+            .try_filter(move |(_, value)| future::ready(*value != zero));
+
+        Ok(Box::pin(selected))
+    }
+
+    async fn filled_at<'a>(self, txn: T, axes: Vec<usize>) -> TCResult<TCBoxTryStream<'a, Coords>> {
+        self.shape().validate_axes(&axes)?;
+
+        if axes.is_empty() {
+            return Ok(Box::pin(stream::empty()));
+        }
+
+        let shape = {
+            let shape = self.shape();
+            axes.iter().map(|x| shape[*x]).collect()
+        };
+
+        let (then, or_else) = try_join!(
+            self.then.filled_at(txn.clone(), axes.clone()),
+            self.or_else.filled_at(txn, axes)
+        )?;
+
+        let filled_at = CoordMerge::new(then, or_else, shape, PER_BLOCK);
+        Ok(Box::pin(filled_at))
+    }
+
+    async fn filled_count(self, txn: T) -> TCResult<u64> {
+        let filled = self.filled(txn).await?;
+
+        filled
+            .try_fold(0u64, |count, _| future::ready(Ok(count + 1)))
+            .await
+    }
+
+    fn slice(self, bounds: Bounds) -> TCResult<Self::Slice> {
+        debug!("SparseSelect::slice {}", bounds);
+
+        let cond = self.cond.slice(bounds.clone())?;
+        let then = self.then.slice(bounds.clone())?;
+        let or_else = self.or_else.slice(bounds)?;
+
+        SparseSelect::new(cond, then, or_else)
+    }
+
+    fn transpose(self, permutation: Option<Vec<usize>>) -> TCResult<Self::Transpose> {
+        let cond = self.cond.transpose(permutation.clone())?;
+        let then = self.then.transpose(permutation.clone())?;
+        let or_else = self.or_else.transpose(permutation)?;
+
+        SparseSelect::new(cond, then, or_else)
+    }
+}
+
+impl<FD, FS, D, T, C, L, R> ReadValueAt<D> for SparseSelect<FD, FS, D, T, C, L, R>
+where
+    FD: File<Array>,
+    FS: File<Node>,
+    D: Dir,
+    T: Transaction<D>,
+    C: SparseAccess<FD, FS, D, T>,
+    L: SparseAccess<FD, FS, D, T>,
+    R: SparseAccess<FD, FS, D, T>,
+{
+    type Txn = T;
+
+    fn read_value_at<'a>(self, txn: T, coord: Coord) -> Read<'a> {
+        Box::pin(async move {
+            let dtype = self.dtype();
+            let (coord, cond) = self.cond.read_value_at(txn.clone(), coord).await?;
+
+            let (coord, value) = if cond == cond.class().zero() {
+                self.or_else.read_value_at(txn, coord).await?
+            } else {
+                self.then.read_value_at(txn, coord).await?
+            };
+
+            Ok((coord, value.into_type(dtype)))
+        })
+    }
+}
+
+impl<FD, FS, D, T, C, L, R> fmt::Display for SparseSelect<FD, FS, D, T, C, L, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a selection from two sparse Tensors")
+    }
+}
+
 #[derive(Clone)]
 pub struct SparseConstCombinator<FD, FS, D, T, A> {
     source: A,
@@ -948,7 +1178,7 @@ where
     A: SparseAccess<FD, FS, D, T>,
 {
     fn dtype(&self) -> NumberType {
-        Ord::max(self.source.dtype(), self.other.class())
+        self.source.dtype().promote(self.other.class())
     }
 
     fn ndim(&self) -> usize {
@@ -989,10 +1219,11 @@ where
 
     async fn filled<'a>(self, txn: T) -> TCResult<SparseStream<'a>> {
         let combinator = self.combinator;
-        let other = self.other;
+        let dtype = self.source.dtype().promote(self.other.class());
+        let other = self.other.into_type(dtype);
         let filled = self.source.filled(txn).await?;
         Ok(Box::pin(filled.map_ok(move |(coord, value)| {
-            (coord, combinator(value, other))
+            (coord, combinator(value.into_type(dtype), other))
         })))
     }
 
@@ -1036,11 +1267,12 @@ where
     fn read_value_at<'a>(self, txn: T, coord: Coord) -> Read<'a> {
         Box::pin(async move {
             let combinator = self.combinator;
-            let other = self.other;
+            let dtype = self.source.dtype().promote(self.other.class());
+            let other = self.other.into_type(dtype);
 
             self.source
                 .read_value_at(txn, coord)
-                .map_ok(|(coord, val)| (coord, combinator(val, other)))
+                .map_ok(|(coord, val)| (coord, combinator(val.into_type(dtype), other)))
                 .await
         })
     }
@@ -1090,6 +1322,7 @@ where
             self.left, self.right
         );
 
+        let dtype = self.left.dtype().promote(self.right.dtype());
         let left = self.left.filled(txn.clone()).await?;
 
         let combinator = self.combinator;
@@ -1102,7 +1335,12 @@ where
                     .read_value_at(txn.clone(), coord)
                     .map_ok(move |(coord, right_value)| (coord, left_value, right_value))
             })
-            .map_ok(move |(coord, left, right)| (coord, combinator(left, right)));
+            .map_ok(move |(coord, left, right)| {
+                (
+                    coord,
+                    combinator(left.into_type(dtype), right.into_type(dtype)),
+                )
+            });
 
         Ok(Box::pin(filled))
     }
@@ -1118,7 +1356,8 @@ where
     R: SparseAccess<FD, FS, D, T>,
 {
     fn dtype(&self) -> NumberType {
-        (self.combinator)(self.left.dtype().zero(), self.right.dtype().zero()).class()
+        let dtype = self.left.dtype().promote(self.right.dtype());
+        (self.combinator)(dtype.zero(), dtype.zero()).class()
     }
 
     fn ndim(&self) -> usize {
@@ -1224,13 +1463,14 @@ where
         Box::pin(async move {
             let left_zero = self.left.dtype().zero();
             let zero = self.dtype().zero();
+            let dtype = self.left.dtype().promote(self.right.dtype());
 
             let (coord, left) = self.left.read_value_at(txn.clone(), coord).await?;
             let (coord, value) = if left == left_zero {
                 (coord, zero)
             } else {
                 let (coord, right) = self.right.read_value_at(txn, coord).await?;
-                let value = (self.combinator)(left, right);
+                let value = (self.combinator)(left.into_type(dtype), right.into_type(dtype));
                 (coord, value)
             };
 
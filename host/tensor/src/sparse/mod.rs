@@ -3,7 +3,6 @@ use std::marker::PhantomData;
 use std::ops::{Add, Div, Mul, Sub};
 use std::pin::Pin;
 
-use afarray::Array;
 use async_trait::async_trait;
 use destream::{de, en};
 use futures::future::{self, TryFutureExt};
@@ -15,21 +14,26 @@ use tc_btree::{BTreeType, Node};
 use tc_error::*;
 use tc_transact::fs::{CopyFrom, Dir, File, Hash, Persist, Restore};
 use tc_transact::{IntoView, Transact, Transaction, TxnId};
-use tc_value::{FloatType, Number, NumberClass, NumberInstance, NumberType, Trigonometry};
+use tc_value::{
+    ComplexInstance, FloatType, Number, NumberClass, NumberInstance, NumberType, Promote,
+    Trigonometry,
+};
 use tcgeneric::{Instance, TCBoxTryFuture, TCBoxTryStream};
 
-use super::dense::{BlockListSparse, DenseTensor, PER_BLOCK};
+use super::block::Array;
+use super::dense::{BlockListSparse, DenseAccessor, DenseTensor, PER_BLOCK};
 use super::stream::ReadValueAt;
 use super::transform;
 use super::{
-    trig_dtype, Bounds, Coord, Phantom, Schema, Shape, Tensor, TensorAccess, TensorBoolean,
-    TensorBooleanConst, TensorCompare, TensorCompareConst, TensorDiagonal, TensorDualIO, TensorIO,
-    TensorInstance, TensorMath, TensorMathConst, TensorPersist, TensorReduce, TensorTransform,
-    TensorTrig, TensorType, TensorUnary, ERR_COMPLEX_EXPONENT,
+    arg_dtype, real_dtype, trig_dtype, Bounds, Coord, Phantom, Schema, Shape, Tensor, TensorAccess,
+    TensorBoolean, TensorBooleanConst, TensorCompare, TensorCompareConst, TensorComplex,
+    TensorDiagonal, TensorDualIO, TensorIO, TensorInstance, TensorMath, TensorMathConst,
+    TensorPersist, TensorReduce, TensorScan, TensorTransform, TensorTrig, TensorType,
+    TensorUnary, ERR_COMPLEX_EXPONENT,
 };
 
 use access::*;
-pub use access::{DenseToSparse, SparseAccess, SparseAccessor, SparseWrite};
+pub use access::{DenseToSparse, SparseAccess, SparseAccessor, SparseSelect, SparseWrite};
 pub use table::SparseTable;
 
 mod access;
@@ -672,7 +676,7 @@ where
         fn div(l: Number, r: Number) -> Number {
             // to prevent a divide-by-zero error, treat the right-hand side as if it doesn't exist
             if r == r.class().zero() {
-                Ord::max(l.class(), r.class()).zero()
+                l.class().promote(r.class()).zero()
             } else {
                 l / r
             }
@@ -778,6 +782,29 @@ impl<FD, FS, D, T, A> TensorMathConst for SparseTensor<FD, FS, D, T, A> {
     }
 }
 
+#[async_trait]
+impl<FD, FS, D, T, A> TensorScan<D> for SparseTensor<FD, FS, D, T, A>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+    A: SparseAccess<FD, FS, D, T>,
+{
+    type Txn = T;
+    type Scan = DenseTensor<FD, FS, D, T, DenseAccessor<FD, FS, D, T>>;
+
+    async fn cumprod(self, txn: Self::Txn, axis: usize) -> TCResult<Self::Scan> {
+        self.into_dense().cumprod(txn, axis).await
+    }
+
+    async fn cumsum(self, txn: Self::Txn, axis: usize) -> TCResult<Self::Scan> {
+        self.into_dense().cumsum(txn, axis).await
+    }
+}
+
 impl<FD, FS, D, T, A> TensorReduce<D> for SparseTensor<FD, FS, D, T, A>
 where
     D: Dir,
@@ -932,6 +959,33 @@ where
     trig! {atanh}
 }
 
+macro_rules! complex {
+    ($fun:ident, $dtype:expr) => {
+        fn $fun(&self) -> TCResult<Self::Unary> {
+            let dtype = $dtype(self.dtype());
+            let source = self.accessor.clone().accessor();
+            let accessor = SparseUnary::new(source, Number::$fun, dtype);
+            Ok(SparseTensor::from(accessor))
+        }
+    };
+}
+
+impl<FD, FS, D, T, A> TensorComplex for SparseTensor<FD, FS, D, T, A>
+where
+    FD: File<Array>,
+    FS: File<Node>,
+    D: Dir,
+    T: Transaction<D>,
+    A: SparseAccess<FD, FS, D, T>,
+{
+    type Unary = SparseTensor<FD, FS, D, T, SparseUnary<FD, FS, D, T>>;
+
+    complex! {re, real_dtype}
+    complex! {im, real_dtype}
+    complex! {conj, std::convert::identity}
+    complex! {arg, arg_dtype}
+}
+
 #[async_trait]
 impl<FD, FS, D, T, A> TensorUnary<D> for SparseTensor<FD, FS, D, T, A>
 where
@@ -954,11 +1008,19 @@ where
 
     fn exp(&self) -> TCResult<Self::Unary> {
         fn exp(n: Number) -> Number {
-            let n = f64::cast_from(n);
-            n.exp().into()
+            if let Number::Complex(_) = n {
+                n.exp()
+            } else {
+                let n = f64::cast_from(n);
+                n.exp().into()
+            }
         }
 
-        let dtype = NumberType::Float(FloatType::F64);
+        let dtype = match self.dtype() {
+            NumberType::Complex(ct) => NumberType::Complex(ct),
+            _ => NumberType::Float(FloatType::F64),
+        };
+
         let source = self.accessor.clone().accessor();
         let accessor = SparseUnary::new(source, exp, dtype);
         Ok(SparseTensor::from(accessor))
@@ -3,7 +3,6 @@ use std::convert::TryInto;
 use std::fmt;
 use std::marker::PhantomData;
 
-use afarray::{Array, CoordBlocks, CoordUnique, Coords};
 use async_trait::async_trait;
 use destream::de;
 use futures::future::{self, TryFutureExt};
@@ -14,13 +13,15 @@ use safecast::AsType;
 use tc_btree::{BTreeType, Node};
 use tc_error::*;
 use tc_table::{
-    Column, ColumnBound, Merged, TableIndex, TableSchema, TableSlice, TableStream, TableWrite,
+    Column, ColumnBound, Merged, TableIndex, TableInstance, TableSchema, TableSlice, TableStream,
+    TableWrite,
 };
 use tc_transact::fs::{CopyFrom, Dir, File, Persist, Restore};
 use tc_transact::{Transact, Transaction, TxnId};
 use tc_value::{Bound, Number, NumberClass, NumberInstance, NumberType, UInt, Value, ValueType};
 use tcgeneric::{label, Id, Label, TCBoxTryStream, Tuple};
 
+use crate::block::{Array, CoordBlocks, CoordUnique, Coords};
 use crate::dense::PER_BLOCK;
 use crate::stream::{sorted_coords, Read, ReadValueAt};
 use crate::transform;
@@ -113,7 +114,8 @@ where
     }
 
     async fn filled<'a>(self, txn: T) -> TCResult<SparseStream<'a>> {
-        let rows = self.table.rows(*txn.id()).await?;
+        let table = self.table.with_isolation(txn.isolation());
+        let rows = table.rows(*txn.id()).await?;
         let filled = rows.and_then(|row| future::ready(expect_row(row)));
         let filled: SparseStream = Box::pin(filled);
         Ok(filled)
@@ -132,7 +134,8 @@ where
 
         let shape = self.shape();
         let shape = axes.iter().map(|x| shape[*x]).collect::<Vec<u64>>();
-        let coords = filled_at::<FD, FS, D, T, _>(&txn, axes, self.table).await?;
+        let table = self.table.with_isolation(txn.isolation());
+        let coords = filled_at::<FD, FS, D, T, _>(&txn, axes, table).await?;
 
         let coords = CoordBlocks::new(coords, shape.len(), PER_BLOCK);
 
@@ -145,7 +148,8 @@ where
     }
 
     async fn filled_count(self, txn: T) -> TCResult<u64> {
-        self.table.count(*txn.id()).await
+        let table = self.table.with_isolation(txn.isolation());
+        table.count(*txn.id()).await
     }
 
     fn slice(self, bounds: Bounds) -> TCResult<Self::Slice> {
@@ -203,7 +207,8 @@ where
         Box::pin(async move {
             self.shape().validate_coord(&coord)?;
             let dtype = self.dtype();
-            read_value_at(self.table, txn, coord, dtype).await
+            let table = self.table.with_isolation(txn.isolation());
+            read_value_at(table, txn, coord, dtype).await
         })
     }
 }
@@ -402,7 +407,8 @@ where
         debug!("SparseTableSlice::filled");
 
         let rebase = self.rebase;
-        let rows = self.table.rows(*txn.id()).await?;
+        let table = self.table.with_isolation(txn.isolation());
+        let rows = table.rows(*txn.id()).await?;
         let filled = rows
             .map(|r| r.and_then(|row| expect_row(row)))
             .map_ok(move |(coord, value)| (rebase.map_coord(coord), value));
@@ -422,7 +428,8 @@ where
         let shape = axes.iter().map(|x| shape[*x]).collect::<Vec<u64>>();
         let source_axes = (0..self.source.ndim()).collect();
         let rebase = self.rebase;
-        let source_coords = filled_at::<FD, FS, D, T, _>(&txn, source_axes, self.table).await?;
+        let table = self.table.with_isolation(txn.isolation());
+        let source_coords = filled_at::<FD, FS, D, T, _>(&txn, source_axes, table).await?;
         let coords = CoordBlocks::new(source_coords, self.source.ndim(), PER_BLOCK)
             .map_ok(move |coords| rebase.map_coords(coords))
             .map_ok(move |coords| coords.get(&axes));
@@ -432,7 +439,8 @@ where
     }
 
     async fn filled_count(self, txn: T) -> TCResult<u64> {
-        self.table.count(*txn.id()).await
+        let table = self.table.with_isolation(txn.isolation());
+        table.count(*txn.id()).await
     }
 
     fn slice(self, bounds: Bounds) -> TCResult<Self::Slice> {
@@ -470,7 +478,8 @@ where
             self.shape().validate_coord(&coord)?;
             let dtype = self.dtype();
             let source_coord = self.rebase.invert_coord(&coord);
-            let (_, value) = read_value_at(self.table, txn, source_coord, dtype).await?;
+            let table = self.table.with_isolation(txn.isolation());
+            let (_, value) = read_value_at(table, txn, source_coord, dtype).await?;
             Ok((coord, value))
         })
     }
@@ -1,4 +1,3 @@
-use afarray::{Array, ArrayExt, CoordUnique, Coords, Offsets};
 use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use log::debug;
 use safecast::AsType;
@@ -9,6 +8,7 @@ use tc_transact::fs::{Dir, File};
 use tc_transact::{Transaction, TxnId};
 use tc_value::{Number, UIntType};
 
+use crate::block::{Array, ArrayExt, CoordUnique, Coords, Offsets};
 use crate::dense::{BlockListFile, PER_BLOCK};
 use crate::{Coord, Shape, TensorAccess, TensorType};
 
@@ -0,0 +1,332 @@
+//! Summary statistics of the distribution of the elements of a [`Tensor`], computed by streaming
+//! its values so that only the (small) summary is held in memory.
+//!
+//! The implicit zeros of a sparse tensor are counted rather than streamed, so a summary of a
+//! sparse tensor only reads its filled values.
+
+use std::cmp::Ordering;
+
+use futures::stream::TryStreamExt;
+use safecast::{AsType, CastFrom};
+
+use tc_btree::Node;
+use tc_error::*;
+use tc_transact::fs::{Dir, File};
+use tc_transact::Transaction;
+use tc_value::{Number, NumberType};
+use tcgeneric::TCBoxTryStream;
+
+use super::{Array, DenseAccess, SparseAccess, Tensor, TensorAccess, TensorType};
+
+/// The default number of bins of a histogram
+pub const DEFAULT_BINS: usize = 10;
+
+// the compression parameter of a t-digest, which bounds the number of centroids it keeps
+const COMPRESSION: f64 = 100.;
+
+/// A histogram of the elements of a [`Tensor`], like the result of NumPy's `histogram`
+pub struct Histogram {
+    /// The number of elements in each bin
+    pub counts: Vec<u64>,
+    /// The `counts.len() + 1` edges of the bins, in ascending order
+    pub edges: Vec<f64>,
+}
+
+/// Compute a histogram of the elements of the given `tensor` in `bins` equal-width bins which
+/// span the range of its values.
+///
+/// Each bin includes its lower edge, and the last bin also includes its upper edge. `NaN` values
+/// are not counted.
+pub async fn histogram<FD, FS, D, T>(
+    tensor: Tensor<FD, FS, D, T>,
+    txn: T,
+    bins: usize,
+) -> TCResult<Histogram>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    if bins == 0 {
+        return Err(TCError::bad_request(
+            "a histogram requires at least one bin, not",
+            bins,
+        ));
+    }
+
+    let (values, zeros) = stream_values(tensor.clone(), txn.clone()).await?;
+    let init = if zeros > 0 { Some((0., 0.)) } else { None };
+
+    let range = values
+        .try_fold(init, |range, value| {
+            let range = match range {
+                _ if value.is_nan() => range,
+                Some((min, max)) => Some((f64::min(min, value), f64::max(max, value))),
+                None => Some((value, value)),
+            };
+
+            futures::future::ready(Ok(range))
+        })
+        .await?;
+
+    let (min, max) = range.unwrap_or((0., 0.));
+    let width = (max - min) / bins as f64;
+    let edges = (0..=bins)
+        .map(|i| {
+            if i == bins {
+                max
+            } else {
+                min + (i as f64 * width)
+            }
+        })
+        .collect();
+
+    let bin = move |value: f64| {
+        if width > 0. {
+            Ord::min(((value - min) / width) as usize, bins - 1)
+        } else {
+            0
+        }
+    };
+
+    let mut counts = vec![0u64; bins];
+    if zeros > 0 {
+        counts[bin(0.)] += zeros;
+    }
+
+    let (values, _) = stream_values(tensor, txn).await?;
+    let counts = values
+        .try_fold(counts, |mut counts, value| {
+            if !value.is_nan() {
+                counts[bin(value)] += 1;
+            }
+
+            futures::future::ready(Ok(counts))
+        })
+        .await?;
+
+    Ok(Histogram { counts, edges })
+}
+
+/// Estimate the given quantiles `qs` (each in the range `[0, 1]`) of the elements of the given
+/// `tensor`, using a t-digest.
+///
+/// Returns `None` for each quantile if the tensor has no (non-`NaN`) elements.
+pub async fn quantiles<FD, FS, D, T>(
+    tensor: Tensor<FD, FS, D, T>,
+    txn: T,
+    qs: &[f64],
+) -> TCResult<Vec<Option<f64>>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    for q in qs {
+        if !(0. ..=1.).contains(q) {
+            return Err(TCError::bad_request(
+                "a quantile must be between 0 and 1, not",
+                q,
+            ));
+        }
+    }
+
+    let (values, zeros) = stream_values(tensor, txn).await?;
+
+    let mut digest = values
+        .try_fold(TDigest::default(), |mut digest, value| {
+            if !value.is_nan() {
+                digest.insert(value);
+            }
+
+            futures::future::ready(Ok(digest))
+        })
+        .await?;
+
+    digest.zeros = zeros;
+    digest.compress();
+
+    Ok(qs.iter().map(|q| digest.quantile(*q)).collect())
+}
+
+// stream the values of the given `tensor`, or only the filled values of a sparse tensor along with
+// the number of its implicit zeros
+async fn stream_values<'a, FD, FS, D, T>(
+    tensor: Tensor<FD, FS, D, T>,
+    txn: T,
+) -> TCResult<(TCBoxTryStream<'a, f64>, u64)>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    if let NumberType::Complex(_) = tensor.dtype() {
+        return Err(TCError::unsupported(
+            "cannot summarize the distribution of a complex Tensor",
+        ));
+    }
+
+    match tensor {
+        Tensor::Dense(dense) => {
+            let values = dense.into_inner().value_stream(txn).await?;
+            let values: TCBoxTryStream<'a, f64> = Box::pin(values.map_ok(f64::cast_from));
+            Ok((values, 0))
+        }
+        Tensor::Sparse(sparse) => {
+            let size = sparse.size();
+            let sparse = sparse.into_inner();
+            let filled = sparse.clone().filled_count(txn.clone()).await?;
+
+            let values = sparse.filled(txn).await?;
+            let values: TCBoxTryStream<'a, f64> =
+                Box::pin(values.map_ok(|(_coord, value): (_, Number)| f64::cast_from(value)));
+
+            Ok((values, size - filled))
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A merging t-digest, which estimates the quantiles of a stream of values in bounded memory.
+///
+/// Zeros are counted exactly (in `zeros`) rather than inserted, so that the implicit zeros of a
+/// sparse tensor, which often dominate its distribution, don't blur the estimates near zero.
+#[derive(Default)]
+struct TDigest {
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+    zeros: u64,
+}
+
+impl TDigest {
+    fn insert(&mut self, value: f64) {
+        if self.centroids.is_empty() && self.buffer.is_empty() {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = f64::min(self.min, value);
+            self.max = f64::max(self.max, value);
+        }
+
+        self.buffer.push(value);
+
+        if self.buffer.len() >= (COMPRESSION * 10.) as usize {
+            self.compress();
+        }
+    }
+
+    // merge the buffered values into the centroids, keeping each centroid no larger than the
+    // bound `4 * n * q * (1 - q) / COMPRESSION` at its quantile `q`
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let mut all = std::mem::take(&mut self.centroids);
+        all.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1. }),
+        );
+        all.sort_by(|l, r| l.mean.partial_cmp(&r.mean).unwrap_or(Ordering::Equal));
+
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+
+        let mut merged = Vec::with_capacity(all.len());
+        let mut before = 0.;
+        let mut all = all.into_iter();
+        let mut current = all.next().expect("centroid");
+
+        for next in all {
+            let q = (before + current.weight + (next.weight / 2.)) / total;
+            let limit = 4. * total * q * (1. - q) / COMPRESSION;
+
+            if current.weight + next.weight <= limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                before += current.weight;
+                merged.push(current);
+                current = next;
+            }
+        }
+
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    // estimate the `q` quantile of all the inserted values, including zeros
+    fn quantile(&self, q: f64) -> Option<f64> {
+        let nonzero: f64 = self.centroids.iter().map(|c| c.weight).sum();
+        let zeros = self.zeros as f64;
+        let total = nonzero + zeros;
+
+        if total == 0. {
+            return None;
+        }
+
+        let rank = q * total;
+        let negative: f64 = self
+            .centroids
+            .iter()
+            .filter(|c| c.mean < 0.)
+            .map(|c| c.weight)
+            .sum();
+
+        if rank < negative {
+            Some(self.quantile_nonzero(rank))
+        } else if zeros > 0. && rank <= negative + zeros {
+            Some(0.)
+        } else {
+            Some(self.quantile_nonzero(rank - zeros))
+        }
+    }
+
+    // estimate the value at the given `rank` among the nonzero values, interpolating between the
+    // centers of adjacent centroids
+    fn quantile_nonzero(&self, rank: f64) -> f64 {
+        let centroids = &self.centroids;
+        if centroids.len() == 1 {
+            return centroids[0].mean;
+        }
+
+        let first = &centroids[0];
+        if rank < first.weight / 2. {
+            let fraction = rank / (first.weight / 2.);
+            return self.min + (first.mean - self.min) * fraction;
+        }
+
+        let mut center = first.weight / 2.;
+        for pair in centroids.windows(2) {
+            let next_center = center + (pair[0].weight + pair[1].weight) / 2.;
+            if rank < next_center {
+                let fraction = (rank - center) / (next_center - center);
+                return pair[0].mean + (pair[1].mean - pair[0].mean) * fraction;
+            }
+
+            center = next_center;
+        }
+
+        let last = &centroids[centroids.len() - 1];
+        let remaining = last.weight / 2.;
+        let fraction = f64::min((rank - center) / remaining, 1.);
+        last.mean + (self.max - last.mean) * fraction
+    }
+}
@@ -0,0 +1,410 @@
+//! Reverse-mode automatic differentiation of [`Tensor`] operations.
+//!
+//! A [`Tape`] records each differentiable operation applied to a tracked tensor under the name of
+//! its result, so that [`Tape::backward`] can compute the gradient of a loss with respect to each
+//! watched input by walking the recorded operations in reverse.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+
+use log::debug;
+use safecast::AsType;
+
+use tc_btree::Node;
+use tc_error::*;
+use tc_transact::fs::{Dir, File};
+use tc_transact::Transaction;
+use tc_value::{Number, NumberClass, NumberType};
+use tcgeneric::{Id, Map};
+
+use super::{
+    broadcast, Array, DenseTensor, Shape, Tensor, TensorAccess, TensorMath, TensorMathConst,
+    TensorReduce, TensorTransform, TensorType, TensorUnary,
+};
+
+/// A differentiable operation which can be recorded on a [`Tape`]
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum TapeOp {
+    Add,
+    Div,
+    Exp,
+    Mul,
+    Sub,
+    /// The sum along the given axis, or of all elements if `None`
+    Sum(Option<usize>),
+}
+
+impl TapeOp {
+    fn arity(&self) -> usize {
+        match self {
+            Self::Exp | Self::Sum(_) => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for TapeOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Add => f.write_str("add"),
+            Self::Div => f.write_str("div"),
+            Self::Exp => f.write_str("exp"),
+            Self::Mul => f.write_str("mul"),
+            Self::Sub => f.write_str("sub"),
+            Self::Sum(Some(axis)) => write!(f, "sum along axis {}", axis),
+            Self::Sum(None) => f.write_str("sum"),
+        }
+    }
+}
+
+/// An operand of an operation recorded on a [`Tape`]
+#[derive(Clone)]
+pub enum Operand<FD, FS, D, T> {
+    /// A constant
+    Const(Number),
+    /// A tensor, and the name of the state which it was resolved from
+    Tensor(Id, Tensor<FD, FS, D, T>),
+}
+
+#[derive(Clone)]
+struct Record<FD, FS, D, T> {
+    id: Id,
+    op: TapeOp,
+    operands: Vec<Operand<FD, FS, D, T>>,
+    shape: Shape,
+    dtype: NumberType,
+}
+
+struct Recording<FD, FS, D, T> {
+    watched: Vec<(Id, Shape, NumberType)>,
+    tracked: HashSet<Id>,
+    records: Vec<Record<FD, FS, D, T>>,
+}
+
+/// A gradient tape, which records the differentiable operations applied to its watched inputs
+/// (and to the results of those operations), in the order they are resolved.
+pub struct Tape<FD, FS, D, T> {
+    recording: Mutex<Recording<FD, FS, D, T>>,
+}
+
+impl<FD, FS, D, T> Tape<FD, FS, D, T> {
+    /// Construct a new, empty `Tape`.
+    pub fn new() -> Self {
+        let recording = Recording {
+            watched: Vec::new(),
+            tracked: HashSet::new(),
+            records: Vec::new(),
+        };
+
+        Self {
+            recording: Mutex::new(recording),
+        }
+    }
+}
+
+impl<FD, FS, D, T> Default for Tape<FD, FS, D, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<FD, FS, D, T> Tape<FD, FS, D, T>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    /// Watch the given `tensor`, named `id`, as an input to differentiate with respect to.
+    pub fn watch(&self, id: Id, tensor: &Tensor<FD, FS, D, T>) {
+        debug!("watch {} on the gradient tape", id);
+
+        let mut recording = self.recording.lock().expect("gradient tape");
+        recording
+            .watched
+            .push((id.clone(), tensor.shape().clone(), tensor.dtype()));
+
+        recording.tracked.insert(id);
+    }
+
+    /// Record the result of applying `op` to the given `operands`, under the name `id`.
+    ///
+    /// The operation is only recorded if one of its operands is a tracked tensor, i.e. a watched
+    /// input or the result of a recorded operation. Returns `true` if the operation was recorded.
+    pub fn record(
+        &self,
+        id: Id,
+        op: TapeOp,
+        operands: Vec<Operand<FD, FS, D, T>>,
+        shape: Shape,
+        dtype: NumberType,
+    ) -> TCResult<bool> {
+        if operands.len() != op.arity() {
+            return Err(TCError::bad_request(
+                format!("{} requires {} operands, found", op, op.arity()),
+                operands.len(),
+            ));
+        }
+
+        let mut recording = self.recording.lock().expect("gradient tape");
+
+        let tracked = operands.iter().any(|operand| match operand {
+            Operand::Tensor(id, _) => recording.tracked.contains(id),
+            Operand::Const(_) => false,
+        });
+
+        if tracked {
+            debug!("record {} of {} on the gradient tape", op, id);
+
+            recording.tracked.insert(id.clone());
+            recording.records.push(Record {
+                id,
+                op,
+                operands,
+                shape,
+                dtype,
+            });
+        }
+
+        Ok(tracked)
+    }
+
+    /// Compute the gradient of the state named `loss` with respect to each watched input.
+    ///
+    /// The gradient of an input which the loss does not depend on is zero.
+    pub async fn backward(&self, txn: T, loss: &Id) -> TCResult<Map<Tensor<FD, FS, D, T>>> {
+        let (watched, records) = {
+            let recording = self.recording.lock().expect("gradient tape");
+            (recording.watched.to_vec(), recording.records.to_vec())
+        };
+
+        let (shape, dtype, end) =
+            if let Some(i) = records.iter().rposition(|record| &record.id == loss) {
+                (records[i].shape.clone(), records[i].dtype, i + 1)
+            } else if let Some((_, shape, dtype)) = watched.iter().find(|(id, _, _)| id == loss) {
+                (shape.clone(), *dtype, 0)
+            } else {
+                return Err(TCError::not_found(format!(
+                    "{} on the gradient tape",
+                    loss
+                )));
+            };
+
+        let mut grads = HashMap::with_capacity(end + 1);
+        grads.insert(loss.clone(), constant(&txn, shape, dtype.one()).await?);
+
+        for record in records[..end].iter().rev() {
+            let grad = if let Some(grad) = grads.get(&record.id) {
+                grad.clone()
+            } else {
+                continue;
+            };
+
+            debug!("backpropagate through {} of {}", record.op, record.id);
+
+            for (id, grad) in record.gradients(grad)? {
+                let grad = match grads.remove(&id) {
+                    Some(sum) => add(sum, grad)?,
+                    None => grad,
+                };
+
+                grads.insert(id, grad);
+            }
+        }
+
+        let mut gradients = Map::new();
+        for (id, shape, dtype) in watched {
+            let grad = match grads.remove(&id) {
+                Some(grad) => grad,
+                None => constant(&txn, shape, dtype.zero()).await?,
+            };
+
+            gradients.insert(id, grad);
+        }
+
+        Ok(gradients)
+    }
+}
+
+impl<FD, FS, D, T> Record<FD, FS, D, T>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    // the gradient of each tensor operand of this record, given the gradient of its result
+    fn gradients(
+        &self,
+        grad: Tensor<FD, FS, D, T>,
+    ) -> TCResult<Vec<(Id, Tensor<FD, FS, D, T>)>> {
+        use Operand::{Const, Tensor as Var};
+
+        let mut gradients = Vec::with_capacity(self.operands.len());
+
+        match self.op {
+            TapeOp::Add => {
+                for operand in &self.operands {
+                    if let Var(id, x) = operand {
+                        gradients.push((id.clone(), unbroadcast(grad.clone(), x.shape())?));
+                    }
+                }
+            }
+            TapeOp::Sub => {
+                if let Var(id, l) = &self.operands[0] {
+                    gradients.push((id.clone(), unbroadcast(grad.clone(), l.shape())?));
+                }
+
+                if let Var(id, r) = &self.operands[1] {
+                    let grad = grad.mul_const(Number::from(-1))?;
+                    gradients.push((id.clone(), unbroadcast(grad, r.shape())?));
+                }
+            }
+            TapeOp::Mul => {
+                let (l, r) = (&self.operands[0], &self.operands[1]);
+
+                if let Var(id, x) = l {
+                    gradients.push((id.clone(), unbroadcast(mul(grad.clone(), r)?, x.shape())?));
+                }
+
+                if let Var(id, x) = r {
+                    gradients.push((id.clone(), unbroadcast(mul(grad, l)?, x.shape())?));
+                }
+            }
+            TapeOp::Div => {
+                let (l, r) = (&self.operands[0], &self.operands[1]);
+
+                if let Var(id, x) = l {
+                    let grad = match r {
+                        Const(n) => grad.clone().div_const(*n)?,
+                        Var(_, r) => {
+                            let (grad, r) = broadcast(grad.clone(), r.clone())?;
+                            grad.div(r)?
+                        }
+                    };
+
+                    gradients.push((id.clone(), unbroadcast(grad, x.shape())?));
+                }
+
+                if let Var(id, x) = r {
+                    // d(l / r) / dr = -l / r^2
+                    let square = x.clone().mul(x.clone())?;
+                    let grad = mul(grad, l)?.mul_const(Number::from(-1))?;
+                    let (grad, square) = broadcast(grad, square)?;
+                    gradients.push((id.clone(), unbroadcast(grad.div(square)?, x.shape())?));
+                }
+            }
+            TapeOp::Exp => {
+                if let Var(id, x) = &self.operands[0] {
+                    gradients.push((id.clone(), grad.mul(x.exp()?)?));
+                }
+            }
+            TapeOp::Sum(axis) => {
+                if let Var(id, x) = &self.operands[0] {
+                    let grad = match axis {
+                        Some(axis) => grad.expand_dims(axis)?,
+                        None => grad,
+                    };
+
+                    gradients.push((id.clone(), grad.broadcast(x.shape().clone())?));
+                }
+            }
+        }
+
+        Ok(gradients)
+    }
+}
+
+// a new dense tensor of the given `shape` filled with `value`
+async fn constant<FD, FS, D, T>(
+    txn: &T,
+    shape: Shape,
+    value: Number,
+) -> TCResult<Tensor<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    let txn_id = *txn.id();
+    let file = txn
+        .context()
+        .create_file_unique(txn_id, TensorType::Dense)
+        .await?;
+
+    DenseTensor::constant(file, txn_id, shape, value)
+        .await
+        .map(Tensor::from)
+}
+
+fn add<FD, FS, D, T>(
+    l: Tensor<FD, FS, D, T>,
+    r: Tensor<FD, FS, D, T>,
+) -> TCResult<Tensor<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    let (l, r) = broadcast(l, r)?;
+    l.add(r)
+}
+
+fn mul<FD, FS, D, T>(
+    grad: Tensor<FD, FS, D, T>,
+    operand: &Operand<FD, FS, D, T>,
+) -> TCResult<Tensor<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    match operand {
+        Operand::Const(n) => grad.mul_const(*n),
+        Operand::Tensor(_, x) => {
+            let (grad, x) = broadcast(grad, x.clone())?;
+            grad.mul(x)
+        }
+    }
+}
+
+// sum the given gradient over the axes along which an operand of the given `shape` was broadcast
+fn unbroadcast<FD, FS, D, T>(
+    grad: Tensor<FD, FS, D, T>,
+    shape: &Shape,
+) -> TCResult<Tensor<FD, FS, D, T>>
+where
+    D: Dir,
+    T: Transaction<D>,
+    FD: File<Array>,
+    FS: File<Node>,
+    D::File: AsType<FD> + AsType<FS>,
+    D::FileClass: From<TensorType>,
+{
+    let mut grad = grad;
+    while grad.ndim() > shape.len() {
+        grad = grad.sum(0)?;
+    }
+
+    for axis in 0..shape.len() {
+        if shape[axis] == 1 && grad.shape()[axis] != 1 {
+            grad = grad.sum(axis)?.expand_dims(axis)?;
+        }
+    }
+
+    Ok(grad)
+}
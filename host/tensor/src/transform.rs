@@ -2,12 +2,12 @@ use std::collections::{HashMap, HashSet};
 use std::iter;
 use std::ops;
 
-use afarray::Coords;
 use log::debug;
 
 use tc_error::*;
 use tcgeneric::Tuple;
 
+use crate::block::Coords;
 use crate::bounds::{AxisBounds, Bounds, Shape};
 
 use super::Coord;
@@ -14,7 +14,7 @@ use sha2::{Digest, Sha256};
 use tc_error::*;
 use tcgeneric::{Id, PathSegment, TCBoxTryStream};
 
-use super::{Transaction, TxnId};
+use super::{Isolation, Transaction, TxnId};
 
 /// An alias for [`Id`] used for code clarity.
 pub type BlockId = PathSegment;
@@ -88,6 +88,14 @@ pub trait File<B: Clone>: Store + Sized + 'static {
     /// Get a read lock on the block at `name`.
     async fn read_block(&self, txn_id: TxnId, name: BlockId) -> TCResult<Self::Read>;
 
+    /// Get a read lock on the block at `name` with the given [`Isolation`] level.
+    async fn read_block_isolated(
+        &self,
+        txn_id: TxnId,
+        name: BlockId,
+        isolation: Isolation,
+    ) -> TCResult<Self::Read>;
+
     /// Get a read lock on the block at `name`, without borrowing.
     async fn read_block_owned(self, txn_id: TxnId, name: BlockId) -> TCResult<Self::Read>;
 
@@ -2,6 +2,9 @@
 //!
 //! This library is part of TinyChain: [http://github.com/haydnv/tinychain](http://github.com/haydnv/tinychain)
 
+use std::fmt;
+use std::str::FromStr;
+
 use async_trait::async_trait;
 use destream::en;
 
@@ -25,6 +28,45 @@ pub trait IntoView<'en, D: fs::Dir> {
     async fn into_view(self, txn: Self::Txn) -> TCResult<Self::View>;
 }
 
+/// The isolation level of the reads made by a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Isolation {
+    /// Read the state of the data as of the start of the transaction,
+    /// waiting for any earlier pending writes to commit.
+    Snapshot,
+
+    /// Read the latest committed state of the data (or this transaction's own writes)
+    /// without waiting on writes pending in other transactions.
+    ReadCommitted,
+}
+
+impl Default for Isolation {
+    fn default() -> Self {
+        Self::Snapshot
+    }
+}
+
+impl FromStr for Isolation {
+    type Err = TCError;
+
+    fn from_str(s: &str) -> TCResult<Self> {
+        match s {
+            "snapshot" => Ok(Self::Snapshot),
+            "read_committed" => Ok(Self::ReadCommitted),
+            other => Err(TCError::bad_request("invalid isolation level", other)),
+        }
+    }
+}
+
+impl fmt::Display for Isolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Snapshot => "snapshot",
+            Self::ReadCommitted => "read_committed",
+        })
+    }
+}
+
 /// Transaction lifecycle callbacks
 #[async_trait]
 pub trait Transact {
@@ -41,6 +83,11 @@ pub trait Transaction<D: fs::Dir>: Clone + Sized + Send + Sync + 'static {
     /// The [`TxnId`] of this transaction context.
     fn id(&'_ self) -> &'_ TxnId;
 
+    /// The [`Isolation`] level of reads made in this transaction context.
+    fn isolation(&self) -> Isolation {
+        Isolation::default()
+    }
+
     /// Borrow the [`fs::Dir`] of this transaction context.
     fn context(&'_ self) -> &'_ D;
 
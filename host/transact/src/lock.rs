@@ -5,26 +5,55 @@ use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt;
 use std::ops::{Deref, DerefMut};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::task::{Context, Poll, Waker};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::Future;
 use log::{debug, info};
+use tokio::time::{sleep, Sleep};
 
 use tc_error::*;
 
-use super::{Transact, TxnId};
+use super::{Isolation, Transact, TxnId};
+
+/// The default maximum time to wait to acquire a [`TxnLock`] before aborting with a conflict error.
+///
+/// Since a lock only ever waits on a transaction which began earlier, a wait this long means
+/// that the earlier transaction is stuck, e.g. waiting on a resource held by the waiting one.
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(5);
+
+// the maximum wait in milliseconds
+static MAX_WAIT: AtomicU64 = AtomicU64::new(DEFAULT_MAX_WAIT.as_millis() as u64);
+
+/// Set the maximum time to wait to acquire a [`TxnLock`] before aborting with a conflict error.
+///
+/// This applies to every lock, starting with the next wait to acquire one.
+pub fn set_max_wait(max_wait: Duration) {
+    MAX_WAIT.store(max_wait.as_millis() as u64, Ordering::Relaxed);
+}
+
+/// Return the maximum time to wait to acquire a [`TxnLock`], as set by [`set_max_wait`].
+pub fn max_wait() -> Duration {
+    Duration::from_millis(MAX_WAIT.load(Ordering::Relaxed))
+}
 
 /// An immutable read guard for a transactional state.
 pub struct TxnLockReadGuard<T> {
     lock: TxnLock<T>,
     txn_id: TxnId,
+    isolation: Isolation,
 }
 
 impl<T> TxnLockReadGuard<T> {
-    fn new(lock: TxnLock<T>, txn_id: TxnId) -> Self {
-        Self { lock, txn_id }
+    fn new(lock: TxnLock<T>, txn_id: TxnId, isolation: Isolation) -> Self {
+        Self {
+            lock,
+            txn_id,
+            isolation,
+        }
     }
 }
 
@@ -49,10 +78,12 @@ impl<T> Drop for TxnLockReadGuard<T> {
 
         assert_ne!(state.writer, Some(self.txn_id));
 
-        let num_readers = state
-            .readers
-            .get_mut(&self.txn_id)
-            .expect("read lock count");
+        let readers = match self.isolation {
+            Isolation::Snapshot => &mut state.readers,
+            Isolation::ReadCommitted => &mut state.committed_readers,
+        };
+
+        let num_readers = readers.get_mut(&self.txn_id).expect("read lock count");
 
         *num_readers -= 1;
 
@@ -112,6 +143,10 @@ impl<T> Drop for TxnLockWriteGuard<T> {
             assert_eq!(readers, &0);
         }
 
+        if let Some(readers) = state.committed_readers.get(&self.txn_id) {
+            assert_eq!(readers, &0);
+        }
+
         state.writer = None;
         state.wake();
     }
@@ -122,12 +157,22 @@ struct LockState<T> {
     versions: BTreeMap<TxnId, UnsafeCell<T>>,
     last_commit: TxnId,
     readers: BTreeMap<TxnId, usize>,
+    // read-committed readers don't prevent a past transaction from acquiring a write lock
+    committed_readers: BTreeMap<TxnId, usize>,
+    // versions copied from the latest commit which may need to be refreshed before a write
+    unsynced: BTreeSet<TxnId>,
     writer: Option<TxnId>,
     pending_writes: BTreeSet<TxnId>,
     wakers: VecDeque<Waker>,
 }
 
 impl<T> LockState<T> {
+    fn num_readers(&self, txn_id: &TxnId) -> usize {
+        let snapshot = self.readers.get(txn_id).cloned().unwrap_or_default();
+        let committed = self.committed_readers.get(txn_id).cloned();
+        snapshot + committed.unwrap_or_default()
+    }
+
     fn wake(&mut self) {
         while let Some(waker) = self.wakers.pop_front() {
             waker.wake();
@@ -161,6 +206,8 @@ impl<T> TxnLock<T> {
             versions: BTreeMap::new(),
             last_commit: super::id::MIN_ID,
             readers: BTreeMap::new(),
+            committed_readers: BTreeMap::new(),
+            unsynced: BTreeSet::new(),
             writer: None,
             pending_writes: BTreeSet::new(),
             wakers: VecDeque::new(),
@@ -185,19 +232,33 @@ impl<T> TxnLock<T> {
 impl<T: Clone + Send> TxnLock<T> {
     /// Try to acquire a read lock.
     pub fn read(&self, txn_id: TxnId) -> TxnLockReadFuture<T> {
+        self.read_isolated(txn_id, Isolation::Snapshot)
+    }
+
+    /// Try to acquire a read lock with the given [`Isolation`] level.
+    pub fn read_isolated(&self, txn_id: TxnId, isolation: Isolation) -> TxnLockReadFuture<T> {
         TxnLockReadFuture {
             lock: self.clone(),
             txn_id,
+            isolation,
+            deadline: None,
         }
     }
 
-    fn try_read(&self, txn_id: TxnId) -> TCResult<Option<TxnLockReadGuard<T>>> {
+    fn try_read(
+        &self,
+        txn_id: TxnId,
+        isolation: Isolation,
+    ) -> TCResult<Option<TxnLockReadGuard<T>>> {
         let mut state = self.lock_inner("TxnLock::try_read");
-        for reserved in state.pending_writes.iter().rev() {
-            if reserved > &state.last_commit && reserved < &txn_id {
-                // if there's a pending write that can change the value at this txn_id, wait it out
-                debug!("TxnLock waiting on a pending write at {}", reserved);
-                return Ok(None);
+
+        if isolation == Isolation::Snapshot {
+            for reserved in state.pending_writes.iter().rev() {
+                if reserved > &state.last_commit && reserved < &txn_id {
+                    // if there's a pending write that can change the value at this txn_id, wait it out
+                    debug!("TxnLock waiting on a pending write at {}", reserved);
+                    return Ok(None);
+                }
             }
         }
 
@@ -207,6 +268,16 @@ impl<T: Clone + Send> TxnLock<T> {
             return Ok(None);
         }
 
+        if isolation == Isolation::Snapshot && state.unsynced.contains(&txn_id) {
+            let readers = state.num_readers(&txn_id);
+
+            if readers == 0 {
+                // a snapshot read must not observe a version copied before a past write committed
+                state.unsynced.remove(&txn_id);
+                state.versions.remove(&txn_id);
+            }
+        }
+
         if !state.versions.contains_key(&txn_id) {
             if txn_id <= state.last_commit {
                 // if the requested time is too old, just return an error
@@ -220,10 +291,19 @@ impl<T: Clone + Send> TxnLock<T> {
 
             let version = UnsafeCell::new(unsafe { (&*state.canon.get()).clone() });
             state.versions.insert(txn_id, version);
+
+            if isolation == Isolation::ReadCommitted {
+                state.unsynced.insert(txn_id);
+            }
         }
 
-        *state.readers.entry(txn_id).or_insert(0) += 1;
-        Ok(Some(TxnLockReadGuard::new(self.clone(), txn_id)))
+        let readers = match isolation {
+            Isolation::Snapshot => &mut state.readers,
+            Isolation::ReadCommitted => &mut state.committed_readers,
+        };
+
+        *readers.entry(txn_id).or_insert(0) += 1;
+        Ok(Some(TxnLockReadGuard::new(self.clone(), txn_id, isolation)))
     }
 
     /// Try to acquire a write lock.
@@ -231,6 +311,7 @@ impl<T: Clone + Send> TxnLock<T> {
         TxnLockWriteFuture {
             lock: self.clone(),
             txn_id,
+            deadline: None,
         }
     }
 
@@ -275,16 +356,16 @@ impl<T: Clone + Send> TxnLock<T> {
             }
         }
 
-        if let Some(readers) = state.readers.get(&txn_id) {
+        let readers = state.num_readers(&txn_id);
+
+        if readers > 0 {
             // if there's an active read lock for this txn_id, wait it out
-            if readers > &0 {
-                debug!(
-                    "TxnLock {} has {} active readers at {}",
-                    self.inner.name, readers, txn_id
-                );
+            debug!(
+                "TxnLock {} has {} active readers at {}",
+                self.inner.name, readers, txn_id
+            );
 
-                return Ok(None);
-            }
+            return Ok(None);
         }
 
         if let Some(writer) = &state.writer {
@@ -297,6 +378,11 @@ impl<T: Clone + Send> TxnLock<T> {
             return Ok(None);
         }
 
+        if state.unsynced.remove(&txn_id) && !state.pending_writes.contains(&txn_id) {
+            // this version was read without waiting on past writes, which have now committed
+            state.versions.remove(&txn_id);
+        }
+
         if !state.versions.contains_key(&txn_id) {
             let version = UnsafeCell::new(unsafe { (&*state.canon.get()).clone() });
             state.versions.insert(txn_id, version);
@@ -305,6 +391,72 @@ impl<T: Clone + Send> TxnLock<T> {
         state.writer = Some(txn_id);
         Ok(Some(TxnLockWriteGuard::new(self.clone(), txn_id)))
     }
+
+    /// Return a copy of the latest committed version of the locked state,
+    /// without acquiring a read lock.
+    pub fn canon(&self) -> T {
+        let state = self.lock_inner("TxnLock::canon");
+        unsafe { (&*state.canon.get()).clone() }
+    }
+
+    /// Return `true` if the locked state has been written at `txn_id` and not yet committed.
+    pub fn is_pending(&self, txn_id: &TxnId) -> bool {
+        let state = self.lock_inner("TxnLock::is_pending");
+        state.pending_writes.contains(txn_id)
+    }
+
+    /// Return a copy of the version of the locked state at `txn_id`, if there is one,
+    /// without acquiring a read lock.
+    pub fn savepoint(&self, txn_id: TxnId) -> TCResult<Option<T>> {
+        let state = self.lock_inner("TxnLock::savepoint");
+
+        if state.writer == Some(txn_id) {
+            debug!(
+                "can't save {} while it has an active write lock",
+                self.inner.name
+            );
+
+            return Err(TCError::conflict());
+        }
+
+        let version = state
+            .versions
+            .get(&txn_id)
+            .map(|version| unsafe { (&*version.get()).clone() });
+
+        Ok(version)
+    }
+
+    /// Restore the version of the locked state at `txn_id` to the given `savepoint`.
+    ///
+    /// If the `savepoint` is `None`, this discards any version of the locked state at `txn_id`.
+    pub fn rollback(&self, txn_id: TxnId, savepoint: Option<T>) -> TCResult<()> {
+        let mut state = self.lock_inner("TxnLock::rollback");
+
+        if state.writer == Some(txn_id) || state.num_readers(&txn_id) > 0 {
+            debug!(
+                "can't roll back {} while it has an active lock at {}",
+                self.inner.name, txn_id
+            );
+
+            return Err(TCError::conflict());
+        }
+
+        if txn_id <= state.last_commit {
+            return Err(TCError::conflict());
+        }
+
+        if let Some(version) = savepoint {
+            state.versions.insert(txn_id, UnsafeCell::new(version));
+        } else {
+            state.versions.remove(&txn_id);
+            state.pending_writes.remove(&txn_id);
+            state.unsynced.remove(&txn_id);
+        }
+
+        state.wake();
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -378,25 +530,36 @@ impl<T: PartialEq + Clone + Send> Transact for TxnLock<T> {
         let mut state = self.lock_inner("TxnLock::finalize");
         state.versions.remove(txn_id);
         state.pending_writes.remove(txn_id);
+        state.unsynced.remove(txn_id);
+        state.committed_readers.remove(txn_id);
     }
 }
 
 pub struct TxnLockReadFuture<T> {
     lock: TxnLock<T>,
     txn_id: TxnId,
+    isolation: Isolation,
+    deadline: Option<Pin<Box<Sleep>>>,
 }
 
 impl<T: Clone + Send> Future for TxnLockReadFuture<T> {
     type Output = TCResult<TxnLockReadGuard<T>>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        match self.lock.try_read(self.txn_id) {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.lock.try_read(self.txn_id, self.isolation) {
             Ok(Some(guard)) => Poll::Ready(Ok(guard)),
             Err(cause) => Poll::Ready(Err(cause)),
             Ok(None) => {
-                let mut state = self.lock.lock_inner("TxnLockWriteFuture::poll");
-                state.wakers.push_back(cx.waker().clone());
-                Poll::Pending
+                {
+                    let mut state = self.lock.lock_inner("TxnLockReadFuture::poll");
+                    state.wakers.push_back(cx.waker().clone());
+                }
+
+                let txn_id = self.txn_id;
+                let lock = self.lock.clone();
+                poll_deadline(&mut self.deadline, cx, |max_wait| {
+                    lock.timeout(&txn_id, max_wait)
+                })
             }
         }
     }
@@ -405,20 +568,86 @@ impl<T: Clone + Send> Future for TxnLockReadFuture<T> {
 pub struct TxnLockWriteFuture<T> {
     lock: TxnLock<T>,
     txn_id: TxnId,
+    deadline: Option<Pin<Box<Sleep>>>,
 }
 
 impl<T: Clone + Send> Future for TxnLockWriteFuture<T> {
     type Output = TCResult<TxnLockWriteGuard<T>>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.lock.try_write(self.txn_id) {
             Ok(Some(guard)) => Poll::Ready(Ok(guard)),
             Err(cause) => Poll::Ready(Err(cause)),
             Ok(None) => {
-                let mut state = self.lock.lock_inner("TxnLockWriteFuture::poll");
-                state.wakers.push_back(cx.waker().clone());
-                Poll::Pending
+                {
+                    let mut state = self.lock.lock_inner("TxnLockWriteFuture::poll");
+                    state.wakers.push_back(cx.waker().clone());
+                }
+
+                let txn_id = self.txn_id;
+                let lock = self.lock.clone();
+                poll_deadline(&mut self.deadline, cx, |max_wait| {
+                    lock.timeout(&txn_id, max_wait)
+                })
             }
         }
     }
 }
+
+impl<T> TxnLock<T> {
+    fn timeout(&self, txn_id: &TxnId, max_wait: Duration) -> TCError {
+        let message = format!(
+            "transaction {} timed out after {}ms waiting for a lock on {}",
+            txn_id,
+            max_wait.as_millis(),
+            self.inner.name
+        );
+
+        info!("{}", message);
+        TCError::new(ErrorType::Conflict, message)
+    }
+}
+
+fn poll_deadline<G, E: FnOnce(Duration) -> TCError>(
+    deadline: &mut Option<Pin<Box<Sleep>>>,
+    cx: &mut Context<'_>,
+    on_timeout: E,
+) -> Poll<TCResult<G>> {
+    let max_wait = max_wait();
+    let deadline = deadline.get_or_insert_with(|| Box::pin(sleep(max_wait)));
+
+    match deadline.as_mut().poll(cx) {
+        Poll::Ready(()) => Poll::Ready(Err(on_timeout(max_wait))),
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tcgeneric::NetworkTime;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timeout() {
+        set_max_wait(Duration::from_millis(50));
+
+        let lock = TxnLock::new("test lock", 0u64);
+        let first = TxnId::new(NetworkTime::from_nanos(1));
+        let second = TxnId::new(NetworkTime::from_nanos(2));
+
+        let mut guard = lock.write(first).await.expect("write lock");
+        *guard = 1;
+
+        let cause = lock.write(second).await.err().expect("timeout");
+        assert!(cause.code() == ErrorType::Conflict);
+        assert!(cause.message().contains("test lock"));
+        assert!(cause.message().contains(&second.to_string()));
+
+        std::mem::drop(guard);
+        lock.commit(&first).await;
+
+        let guard = lock.read(second).await.expect("read lock");
+        assert_eq!(*guard, 1);
+    }
+}
@@ -0,0 +1,304 @@
+//! A [`DateTime`] value, with [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) parsing and
+//! formatting.
+
+use std::fmt;
+use std::str::FromStr;
+
+use tc_error::*;
+use tcgeneric::NetworkTime;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// A UTC timestamp with nanosecond precision, stored as the number of nanoseconds since the
+/// Unix epoch so that it can be collated (and indexed) as a fixed-size integer.
+#[derive(Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DateTime {
+    nanos: i64,
+}
+
+impl DateTime {
+    /// The current time.
+    pub fn now() -> Self {
+        Self::from(NetworkTime::now())
+    }
+
+    /// Construct a new `DateTime` from a number of nanoseconds since the Unix epoch.
+    pub fn from_nanos(nanos: i64) -> Self {
+        Self { nanos }
+    }
+
+    /// The number of nanoseconds since the Unix epoch.
+    pub fn as_nanos(&self) -> i64 {
+        self.nanos
+    }
+
+    /// Add the given duration in `seconds`, which may be negative or fractional.
+    pub fn add_seconds(&self, seconds: f64) -> TCResult<Self> {
+        let nanos = (seconds * NANOS_PER_SECOND as f64).round();
+        if nanos.is_finite() && nanos.abs() < i64::MAX as f64 {
+            if let Some(nanos) = self.nanos.checked_add(nanos as i64) {
+                return Ok(Self { nanos });
+            }
+        }
+
+        Err(TCError::bad_request(
+            format!("cannot add {} seconds to", seconds),
+            self,
+        ))
+    }
+
+    /// The duration in seconds since `other`, which is negative if `other` is later than `self`.
+    pub fn seconds_since(&self, other: &Self) -> f64 {
+        (self.nanos as i128 - other.nanos as i128) as f64 / NANOS_PER_SECOND as f64
+    }
+
+    /// The calendar date of this `DateTime`, as a `(year, month, day)` tuple.
+    pub fn date(&self) -> (i64, u32, u32) {
+        let (year, month, day) = civil_from_days(self.days());
+        (year, month as u32, day as u32)
+    }
+
+    /// The time of day of this `DateTime`, as an `(hour, minute, second)` tuple.
+    pub fn time(&self) -> (u32, u32, u32) {
+        let seconds = self.seconds().rem_euclid(SECONDS_PER_DAY) as u32;
+        (seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+    }
+
+    /// The day of the week of this `DateTime`, where Sunday is `0`.
+    pub fn weekday(&self) -> u32 {
+        // 1970-01-01 was a Thursday
+        (self.days() + 4).rem_euclid(7) as u32
+    }
+
+    fn seconds(&self) -> i64 {
+        self.nanos.div_euclid(NANOS_PER_SECOND)
+    }
+
+    fn days(&self) -> i64 {
+        self.seconds().div_euclid(SECONDS_PER_DAY)
+    }
+}
+
+impl From<NetworkTime> for DateTime {
+    fn from(time: NetworkTime) -> Self {
+        Self {
+            nanos: time.as_nanos() as i64,
+        }
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = TCError;
+
+    fn from_str(s: &str) -> TCResult<Self> {
+        let invalid = || TCError::bad_request("invalid RFC 3339 datetime", s);
+
+        let bytes = s.as_bytes();
+        if bytes.len() < 20 || !s.is_ascii() {
+            return Err(invalid());
+        }
+
+        let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+        if separators.iter().any(|(i, sep)| bytes[*i] != *sep)
+            || !matches!(bytes[10], b'T' | b't' | b' ')
+        {
+            return Err(invalid());
+        }
+
+        let digits = |start: usize, end: usize| -> TCResult<i64> {
+            let digits = &s[start..end];
+            if digits.bytes().all(|b| b.is_ascii_digit()) {
+                digits.parse().map_err(|_| invalid())
+            } else {
+                Err(invalid())
+            }
+        };
+
+        let year = digits(0, 4)?;
+        let month = digits(5, 7)?;
+        let day = digits(8, 10)?;
+        let hour = digits(11, 13)?;
+        let minute = digits(14, 16)?;
+        let second = digits(17, 19)?;
+
+        if month < 1
+            || month > 12
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return Err(invalid());
+        }
+
+        let mut i = 19;
+        let mut nanos = 0;
+        if bytes[i] == b'.' {
+            let start = i + 1;
+            i = start;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+
+            if i == start {
+                return Err(invalid());
+            }
+
+            // any precision beyond nanoseconds is truncated
+            let frac = &s[start..i.min(start + 9)];
+            nanos = digits(start, start + frac.len())? * 10i64.pow(9 - frac.len() as u32);
+        }
+
+        let offset = match &s[i..] {
+            "Z" | "z" => 0,
+            offset if offset.len() == 6 && bytes[i + 3] == b':' => {
+                let offset_minutes = digits(i + 1, i + 3)? * 60 + digits(i + 4, i + 6)?;
+                match bytes[i] {
+                    b'+' => offset_minutes * 60,
+                    b'-' => -offset_minutes * 60,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(invalid()),
+        };
+
+        let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY
+            + hour * 3600
+            + minute * 60
+            + second
+            - offset;
+
+        seconds
+            .checked_mul(NANOS_PER_SECOND)
+            .and_then(|n| n.checked_add(nanos))
+            .map(Self::from_nanos)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Debug for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let nanos = self.nanos.rem_euclid(NANOS_PER_SECOND);
+        let (year, month, day) = self.date();
+        let (hour, minute, second) = self.time();
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year, month, day, hour, minute, second
+        )?;
+
+        if nanos > 0 {
+            let frac = format!("{:09}", nanos);
+            write!(f, ".{}", frac.trim_end_matches('0'))?;
+        }
+
+        f.write_str("Z")
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// the number of days since 1970-01-01 in the proleptic Gregorian calendar
+// see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// the inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for (s, expected) in &[
+            ("1970-01-01T00:00:00Z", "1970-01-01T00:00:00Z"),
+            ("2021-06-30T12:34:56.5Z", "2021-06-30T12:34:56.5Z"),
+            (
+                "2021-06-30 12:34:56.123456789123z",
+                "2021-06-30T12:34:56.123456789Z",
+            ),
+            ("2021-06-30T12:34:56+02:00", "2021-06-30T10:34:56Z"),
+            ("2020-02-29T23:00:00-01:30", "2020-03-01T00:30:00Z"),
+            ("1969-12-31T23:59:59.9Z", "1969-12-31T23:59:59.9Z"),
+        ] {
+            assert_eq!(&DateTime::from_str(s).unwrap().to_string(), expected);
+        }
+
+        for s in &[
+            "",
+            "2021-06-30",
+            "2021-06-30T12:34:56",
+            "2021-13-01T00:00:00Z",
+            "2021-02-29T00:00:00Z",
+            "2021-06-30T24:00:00Z",
+            "2021-06-30T12:34:56.Z",
+            "2021-06-30T12:34:56+0200",
+        ] {
+            assert!(DateTime::from_str(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let epoch = DateTime::from_nanos(0);
+        let later = epoch.add_seconds(86_400. * 365.).unwrap();
+
+        assert_eq!(later.to_string(), "1971-01-01T00:00:00Z");
+        assert_eq!(later.seconds_since(&epoch), 86_400. * 365.);
+        assert!(epoch < later);
+        assert!(epoch.add_seconds(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_fields() {
+        let datetime = DateTime::from_str("2021-06-30T12:34:56Z").unwrap();
+        assert_eq!(datetime.date(), (2021, 6, 30));
+        assert_eq!(datetime.time(), (12, 34, 56));
+        assert_eq!(datetime.weekday(), 3);
+
+        let datetime = DateTime::from_str("1969-12-31T23:59:59.9Z").unwrap();
+        assert_eq!(datetime.date(), (1969, 12, 31));
+        assert_eq!(datetime.time(), (23, 59, 59));
+        assert_eq!(datetime.weekday(), 3);
+    }
+}
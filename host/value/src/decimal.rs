@@ -0,0 +1,404 @@
+//! Arbitrary-precision [`BigInt`] and [`Decimal`] numbers, for values which cannot tolerate
+//! the rounding error of a floating-point [`Number`].
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Pow, Signed, ToPrimitive, Zero};
+use safecast::{CastFrom, TryCastFrom};
+
+use tc_error::*;
+
+use super::{Float, Number};
+
+/// The number of decimal places to which the quotient of a [`Decimal`] division is rounded.
+pub const DIVISION_SCALE: u32 = 28;
+
+/// The maximum scale (or negative scale) of a parsed [`Decimal`].
+const MAX_SCALE: i64 = u16::MAX as i64;
+
+/// An arbitrary-precision decimal number, stored as an integer number of units of `10^-scale`.
+///
+/// Unlike a [`Float`], a `Decimal` represents a decimal fraction like `0.1` exactly, so sums and
+/// products of `Decimal`s (e.g. currency amounts) are never rounded.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct Decimal {
+    digits: BigInt,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Construct a new `Decimal` equal to `digits * 10^-scale`.
+    pub fn new(digits: BigInt, scale: u32) -> Self {
+        let mut decimal = Self { digits, scale };
+
+        // strip trailing zeros so that equal `Decimal`s have the same representation
+        let ten = BigInt::from(10u8);
+        while decimal.scale > 0 {
+            let (quotient, remainder) = decimal.digits.div_rem(&ten);
+            if remainder.is_zero() {
+                decimal.digits = quotient;
+                decimal.scale -= 1;
+            } else {
+                break;
+            }
+        }
+
+        decimal
+    }
+
+    /// Return the number of digits after the decimal point.
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Return `true` if this `Decimal` has no fractional part.
+    pub fn is_integer(&self) -> bool {
+        self.scale == 0
+    }
+
+    /// Return `true` if this `Decimal` is zero.
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_zero()
+    }
+
+    /// Return the absolute value of this `Decimal`.
+    pub fn abs(&self) -> Self {
+        Self {
+            digits: self.digits.abs(),
+            scale: self.scale,
+        }
+    }
+
+    /// Divide this `Decimal` by `other`, rounding the quotient to [`DIVISION_SCALE`] places.
+    pub fn checked_div(&self, other: &Self) -> TCResult<Self> {
+        if other.is_zero() {
+            return Err(TCError::bad_request("cannot divide by zero", self));
+        }
+
+        let shift = (DIVISION_SCALE + other.scale) as i64 - self.scale as i64;
+        let quotient = if shift >= 0 {
+            div_round(&self.digits * pow10(shift as u32), &other.digits)
+        } else {
+            div_round(self.digits.clone(), &(&other.digits * pow10(-shift as u32)))
+        };
+
+        Ok(Self::new(quotient, DIVISION_SCALE))
+    }
+
+    /// Round this `Decimal` to the given number of decimal places, rounding half to even.
+    pub fn round(&self, scale: u32) -> Self {
+        if scale >= self.scale {
+            self.clone()
+        } else {
+            let digits = div_round(self.digits.clone(), &pow10(self.scale - scale));
+            Self::new(digits, scale)
+        }
+    }
+
+    /// Return the integer part of this `Decimal`, rounding toward zero.
+    pub fn trunc(&self) -> BigInt {
+        &self.digits / pow10(self.scale)
+    }
+
+    fn align(&self, other: &Self) -> (BigInt, BigInt, u32) {
+        match self.scale.cmp(&other.scale) {
+            Ordering::Less => (
+                &self.digits * pow10(other.scale - self.scale),
+                other.digits.clone(),
+                other.scale,
+            ),
+            Ordering::Equal => (self.digits.clone(), other.digits.clone(), self.scale),
+            Ordering::Greater => (
+                self.digits.clone(),
+                &other.digits * pow10(self.scale - other.scale),
+                self.scale,
+            ),
+        }
+    }
+}
+
+impl Default for Decimal {
+    fn default() -> Self {
+        Self {
+            digits: BigInt::zero(),
+            scale: 0,
+        }
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (l, r, _) = self.align(other);
+        l.cmp(&r)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for Decimal {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let (l, r, scale) = self.align(&other);
+        Self::new(l + r, scale)
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(self.digits * other.digits, self.scale + other.scale)
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            digits: -self.digits,
+            scale: self.scale,
+        }
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        let (l, r, scale) = self.align(&other);
+        Self::new(l - r, scale)
+    }
+}
+
+impl From<BigInt> for Decimal {
+    fn from(digits: BigInt) -> Self {
+        Self::new(digits, 0)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(i: i64) -> Self {
+        Self::from(BigInt::from(i))
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(u: u64) -> Self {
+        Self::from(BigInt::from(u))
+    }
+}
+
+impl TryCastFrom<Number> for Decimal {
+    fn can_cast_from(number: &Number) -> bool {
+        match number {
+            Number::Complex(_) => false,
+            Number::Float(f) => f64::cast_from(*f).is_finite(),
+            _ => true,
+        }
+    }
+
+    fn opt_cast_from(number: Number) -> Option<Self> {
+        match number {
+            Number::Bool(b) => Some(Self::from(u64::from(bool::from(b)))),
+            Number::Complex(_) => None,
+            // the `Display` implementation of a float never uses exponential notation
+            Number::Float(Float::F32(f)) if f.is_finite() => f.to_string().parse().ok(),
+            Number::Float(Float::F64(f)) if f.is_finite() => f.to_string().parse().ok(),
+            Number::Float(_) => None,
+            Number::Int(i) => Some(Self::from(i64::cast_from(i))),
+            Number::UInt(u) => Some(Self::from(u64::cast_from(u))),
+        }
+    }
+}
+
+impl CastFrom<Decimal> for Number {
+    fn cast_from(decimal: Decimal) -> Self {
+        let f = f64::from_str(&decimal.to_string()).expect("decimal");
+        Number::Float(Float::F64(f))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = TCError;
+
+    fn from_str(s: &str) -> TCResult<Self> {
+        let invalid = || TCError::bad_request("invalid decimal", s);
+
+        let (mantissa, exponent) = match s.find(|c| c == 'e' || c == 'E') {
+            Some(i) => (&s[..i], i64::from_str(&s[i + 1..]).map_err(|_| invalid())?),
+            None => (s, 0),
+        };
+
+        let (negative, mantissa) = if let Some(mantissa) = mantissa.strip_prefix('-') {
+            (true, mantissa)
+        } else {
+            (false, mantissa.strip_prefix('+').unwrap_or(mantissa))
+        };
+
+        let (int, frac) = match mantissa.find('.') {
+            Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+            None => (mantissa, ""),
+        };
+
+        let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if (int.is_empty() && frac.is_empty()) || !is_digits(int) || !is_digits(frac) {
+            return Err(invalid());
+        }
+
+        let scale = frac.len() as i64 - exponent;
+        if scale.abs() > MAX_SCALE {
+            return Err(invalid());
+        }
+
+        let digits = BigInt::from_str(&[int, frac].concat()).map_err(|_| invalid())?;
+        let digits = if negative { -digits } else { digits };
+
+        if scale >= 0 {
+            Ok(Self::new(digits, scale as u32))
+        } else {
+            Ok(Self::new(digits * pow10(-scale as u32), 0))
+        }
+    }
+}
+
+impl fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scale == 0 {
+            return fmt::Display::fmt(&self.digits, f);
+        }
+
+        if self.digits.is_negative() {
+            f.write_str("-")?;
+        }
+
+        let scale = self.scale as usize;
+        let digits = self.digits.abs().to_string();
+        let digits = if digits.len() > scale {
+            digits
+        } else {
+            format!("{}{}", "0".repeat(scale + 1 - digits.len()), digits)
+        };
+
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}.{}", int, frac)
+    }
+}
+
+/// Cast a [`Number`] into a [`BigInt`], if it's an integer.
+pub fn bigint_from_number(number: Number) -> Option<BigInt> {
+    match number {
+        Number::Int(i) => Some(BigInt::from(i64::cast_from(i))),
+        Number::UInt(u) => Some(BigInt::from(u64::cast_from(u))),
+        other => Decimal::opt_cast_from(other)
+            .filter(Decimal::is_integer)
+            .map(|decimal| decimal.digits),
+    }
+}
+
+/// Cast a [`BigInt`] into a [`Number`], which may lose precision if it's larger than 64 bits.
+pub fn bigint_into_number(i: &BigInt) -> Number {
+    if let Some(i) = i.to_i64() {
+        Number::from(i)
+    } else if let Some(u) = i.to_u64() {
+        Number::from(u)
+    } else {
+        Number::cast_from(Decimal::from(i.clone()))
+    }
+}
+
+fn pow10(exponent: u32) -> BigInt {
+    Pow::pow(&BigInt::from(10u8), exponent)
+}
+
+// divide `numerator` by `denominator`, rounding half to even
+fn div_round(numerator: BigInt, denominator: &BigInt) -> BigInt {
+    let (quotient, remainder) = numerator.div_rem(denominator);
+    let negative = remainder.is_negative() != denominator.is_negative();
+
+    match (remainder.abs() * 2u8).cmp(&denominator.abs()) {
+        Ordering::Less => quotient,
+        Ordering::Equal if quotient.is_even() => quotient,
+        _ if negative => quotient - 1,
+        _ => quotient + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for (s, expected) in &[
+            ("0", "0"),
+            ("12.340", "12.34"),
+            ("-0.05", "-0.05"),
+            ("+.5", "0.5"),
+            ("1.5e3", "1500"),
+            ("125e-5", "0.00125"),
+        ] {
+            assert_eq!(&Decimal::from_str(s).unwrap().to_string(), expected);
+        }
+
+        for s in &["", ".", "-", "1.2.3", "1e", "abc", "1e99999999"] {
+            assert!(Decimal::from_str(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let d = |s: &str| Decimal::from_str(s).unwrap();
+
+        assert_eq!(d("0.1") + d("0.2"), d("0.3"));
+        assert_eq!(d("0.1") - d("0.3"), d("-0.2"));
+        assert_eq!(d("1.5") * d("-0.2"), d("-0.3"));
+        assert_eq!(d("1").checked_div(&d("4")).unwrap(), d("0.25"));
+        assert_eq!(d("1").checked_div(&d("3")).unwrap().scale(), DIVISION_SCALE);
+        assert!(d("1").checked_div(&d("0")).is_err());
+
+        assert!(d("0.1") < d("0.10001"));
+        assert!(d("-2") < d("-1.99"));
+
+        assert_eq!(d("2.5").round(0), d("2"));
+        assert_eq!(d("3.5").round(0), d("4"));
+        assert_eq!(d("-2.5").round(0), d("-2"));
+        assert_eq!(d("1.005").round(2), d("1"));
+        assert_eq!(d("-1.987").trunc(), BigInt::from(-1));
+    }
+
+    #[test]
+    fn test_cast() {
+        let d = |s: &str| Decimal::from_str(s).unwrap();
+
+        assert_eq!(Decimal::opt_cast_from(Number::from(0.1f32)), Some(d("0.1")));
+        assert_eq!(Decimal::opt_cast_from(Number::from(-3i64)), Some(d("-3")));
+        assert_eq!(Decimal::opt_cast_from(Number::from(f64::NAN)), None);
+
+        assert_eq!(
+            bigint_from_number(Number::from(2.0f64)),
+            Some(BigInt::from(2))
+        );
+        assert_eq!(bigint_from_number(Number::from(2.5f64)), None);
+
+        let big = BigInt::from(u64::MAX) * 2u8;
+        assert_eq!(bigint_into_number(&BigInt::from(-5)), Number::from(-5i64));
+        assert_eq!(bigint_into_number(&big), Number::from(u64::MAX as f64 * 2.));
+    }
+}
@@ -10,13 +10,20 @@ use number_general::NumberCollator;
 
 use tcgeneric::Instance;
 
+pub use datetime::*;
+pub use decimal::*;
 pub use link::*;
+pub use num_bigint::BigInt;
+pub use number::*;
 pub use slice::*;
 pub use string::*;
 pub use value::*;
 pub use version::*;
 
+mod datetime;
+mod decimal;
 mod link;
+mod number;
 mod slice;
 mod string;
 mod value;
@@ -25,7 +32,10 @@ mod version;
 /// [`Collate`] support for [`Value`]
 #[derive(Default, Clone)]
 pub struct ValueCollator {
+    bigint: Collator<BigInt>,
     bytes: Collator<Bytes>,
+    datetime: Collator<DateTime>,
+    decimal: Collator<Decimal>,
     link: Collator<Link>,
     number: NumberCollator,
     string: StringCollator,
@@ -37,7 +47,10 @@ impl Collate for ValueCollator {
 
     fn compare(&self, left: &Self::Value, right: &Self::Value) -> Ordering {
         match (left, right) {
+            (Value::BigInt(l), Value::BigInt(r)) => self.bigint.compare(l, r),
             (Value::Bytes(l), Value::Bytes(r)) => self.bytes.compare(l, r),
+            (Value::DateTime(l), Value::DateTime(r)) => self.datetime.compare(l, r),
+            (Value::Decimal(l), Value::Decimal(r)) => self.decimal.compare(l, r),
             (Value::Link(l), Value::Link(r)) => self.link.compare(l, r),
             (Value::Number(l), Value::Number(r)) => self.number.compare(l, r),
             (Value::String(l), Value::String(r)) => self.string.compare(l, r),
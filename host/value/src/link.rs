@@ -373,7 +373,10 @@ impl From<(LinkHost, TCPathBuf)> for Link {
 impl TryCastFrom<Value> for Link {
     fn can_cast_from(value: &Value) -> bool {
         match value {
+            Value::BigInt(_) => false,
             Value::Bytes(_) => false,
+            Value::DateTime(_) => false,
+            Value::Decimal(_) => false,
             Value::Id(_) => true,
             Value::Link(_) => true,
             Value::None => true,
@@ -389,7 +392,10 @@ impl TryCastFrom<Value> for Link {
 
     fn opt_cast_from(value: Value) -> Option<Self> {
         match value {
+            Value::BigInt(_) => None,
             Value::Bytes(_) => None,
+            Value::DateTime(_) => None,
+            Value::Decimal(_) => None,
             Value::Id(id) => Some(TCPathBuf::from(id).into()),
             Value::Link(l) => Some(l),
             Value::None => Some(TCPathBuf::default().into()),
@@ -0,0 +1,529 @@
+//! Type promotion rules and checked arithmetic for mixed-type [`Number`]s
+
+use std::ops::{Add, Div, Mul, Rem, Sub};
+
+use number_general::{
+    Complex, ComplexType, Float, FloatInstance, FloatType, Int, IntType, Number, NumberClass,
+    NumberInstance, NumberType, UInt, UIntType,
+};
+use safecast::CastInto;
+
+use tc_error::*;
+
+/// The promotion of two [`NumberType`]s to a common type, for mixed-type arithmetic.
+pub trait Promote {
+    /// Return the narrowest type which can represent every value of both `self` and `other`,
+    /// or the nearest approximation thereof.
+    ///
+    /// Types are promoted along the lattice `Bool < UInt < Int < Float < Complex`, widening the
+    /// result as needed: e.g. `U8` and `I8` promote to `I16`, and `I64` and `F32` promote to `F64`.
+    fn promote(self, other: Self) -> Self;
+}
+
+impl Promote for NumberType {
+    fn promote(self, other: Self) -> Self {
+        use NumberType::*;
+
+        match (self, other) {
+            (Number, _) | (_, Number) => Number,
+            (Bool, that) => that,
+            (this, Bool) => this,
+
+            (UInt(l), UInt(r)) => UInt(Ord::max(l, r)),
+            (Int(l), Int(r)) => Int(Ord::max(l, r)),
+            (Float(l), Float(r)) => Float(Ord::max(l, r)),
+            (Complex(l), Complex(r)) => Complex(Ord::max(l, r)),
+
+            (UInt(u), Int(i)) | (Int(i), UInt(u)) => {
+                let bits = Ord::max(int_bits(i), Ord::min(uint_bits(u) * 2, 64));
+                Int(int_type(bits))
+            }
+
+            (UInt(u), Float(f)) | (Float(f), UInt(u)) => {
+                let bits = Ord::max(float_bits(f), float_bits_for(uint_bits(u)));
+                Float(float_type(bits))
+            }
+
+            (Int(i), Float(f)) | (Float(f), Int(i)) => {
+                let bits = Ord::max(float_bits(f), float_bits_for(int_bits(i)));
+                Float(float_type(bits))
+            }
+
+            (UInt(u), Complex(c)) | (Complex(c), UInt(u)) => {
+                let bits = Ord::max(complex_bits(c), float_bits_for(uint_bits(u)));
+                Complex(complex_type(bits))
+            }
+
+            (Int(i), Complex(c)) | (Complex(c), Int(i)) => {
+                let bits = Ord::max(complex_bits(c), float_bits_for(int_bits(i)));
+                Complex(complex_type(bits))
+            }
+
+            (Float(f), Complex(c)) | (Complex(c), Float(f)) => {
+                let bits = Ord::max(complex_bits(c), float_bits(f));
+                Complex(complex_type(bits))
+            }
+        }
+    }
+}
+
+/// Arithmetic on [`Number`]s which reports overflow as an error instead of panicking or wrapping.
+///
+/// Both operands are first promoted to a common type according to [`Promote`].
+pub trait CheckedArithmetic: Sized {
+    /// Add `other` to `self`, or return an error in case of overflow.
+    fn checked_add(self, other: Self) -> TCResult<Self>;
+
+    /// Subtract `other` from `self`, or return an error in case of overflow.
+    fn checked_sub(self, other: Self) -> TCResult<Self>;
+
+    /// Multiply `self` by `other`, or return an error in case of overflow.
+    fn checked_mul(self, other: Self) -> TCResult<Self>;
+
+    /// Divide `self` by `other`, or return an error if `other` is zero or in case of overflow.
+    fn checked_div(self, other: Self) -> TCResult<Self>;
+
+    /// Return the remainder of `self` divided by `other`, or an error if `other` is zero.
+    fn checked_rem(self, other: Self) -> TCResult<Self>;
+
+    /// Raise `self` to the power of `exp`, or return an error in case of overflow.
+    fn checked_pow(self, exp: Self) -> TCResult<Self>;
+
+    /// Add `other` to `self`, saturating at the bounds of the promoted integer type.
+    fn saturating_add(self, other: Self) -> Self;
+
+    /// Subtract `other` from `self`, saturating at the bounds of the promoted integer type.
+    fn saturating_sub(self, other: Self) -> Self;
+
+    /// Multiply `self` by `other`, saturating at the bounds of the promoted integer type.
+    fn saturating_mul(self, other: Self) -> Self;
+}
+
+// apply a checked integer operation to two integers of the same type,
+// or a floating-point operation to any other pair of numbers
+macro_rules! checked_op {
+    ($l:expr, $r:expr, $op:ident, $default:expr) => {
+        match ($l, $r) {
+            (Number::UInt(UInt::U8(l)), Number::UInt(UInt::U8(r))) => l.$op(r).map(Number::from),
+            (Number::UInt(UInt::U16(l)), Number::UInt(UInt::U16(r))) => l.$op(r).map(Number::from),
+            (Number::UInt(UInt::U32(l)), Number::UInt(UInt::U32(r))) => l.$op(r).map(Number::from),
+            (Number::UInt(UInt::U64(l)), Number::UInt(UInt::U64(r))) => l.$op(r).map(Number::from),
+            (Number::Int(Int::I8(l)), Number::Int(Int::I8(r))) => l.$op(r).map(Number::from),
+            (Number::Int(Int::I16(l)), Number::Int(Int::I16(r))) => l.$op(r).map(Number::from),
+            (Number::Int(Int::I32(l)), Number::Int(Int::I32(r))) => l.$op(r).map(Number::from),
+            (Number::Int(Int::I64(l)), Number::Int(Int::I64(r))) => l.$op(r).map(Number::from),
+            (l, r) => finite($default(l, r)),
+        }
+    };
+}
+
+// apply a saturating integer operation to two integers of the same type,
+// or a floating-point operation to any other pair of numbers
+macro_rules! saturating_op {
+    ($l:expr, $r:expr, $op:ident, $default:expr) => {
+        match ($l, $r) {
+            (Number::UInt(UInt::U8(l)), Number::UInt(UInt::U8(r))) => l.$op(r).into(),
+            (Number::UInt(UInt::U16(l)), Number::UInt(UInt::U16(r))) => l.$op(r).into(),
+            (Number::UInt(UInt::U32(l)), Number::UInt(UInt::U32(r))) => l.$op(r).into(),
+            (Number::UInt(UInt::U64(l)), Number::UInt(UInt::U64(r))) => l.$op(r).into(),
+            (Number::Int(Int::I8(l)), Number::Int(Int::I8(r))) => l.$op(r).into(),
+            (Number::Int(Int::I16(l)), Number::Int(Int::I16(r))) => l.$op(r).into(),
+            (Number::Int(Int::I32(l)), Number::Int(Int::I32(r))) => l.$op(r).into(),
+            (Number::Int(Int::I64(l)), Number::Int(Int::I64(r))) => l.$op(r).into(),
+            (l, r) => $default(l, r),
+        }
+    };
+}
+
+impl CheckedArithmetic for Number {
+    fn checked_add(self, other: Self) -> TCResult<Self> {
+        let (l, r) = promote(self, other);
+        checked_op!(l, r, checked_add, Add::add).ok_or_else(|| overflow(self, "+", other))
+    }
+
+    fn checked_sub(self, other: Self) -> TCResult<Self> {
+        let (l, r) = promote(self, other);
+        checked_op!(l, r, checked_sub, Sub::sub).ok_or_else(|| overflow(self, "-", other))
+    }
+
+    fn checked_mul(self, other: Self) -> TCResult<Self> {
+        let (l, r) = promote(self, other);
+        checked_op!(l, r, checked_mul, Mul::mul).ok_or_else(|| overflow(self, "*", other))
+    }
+
+    fn checked_div(self, other: Self) -> TCResult<Self> {
+        if other == other.class().zero() {
+            return Err(TCError::bad_request("cannot divide by zero", self));
+        }
+
+        let (l, r) = promote(self, other);
+        checked_op!(l, r, checked_div, Div::div).ok_or_else(|| overflow(self, "/", other))
+    }
+
+    fn checked_rem(self, other: Self) -> TCResult<Self> {
+        if other == other.class().zero() {
+            return Err(TCError::bad_request("cannot divide by zero", self));
+        }
+
+        let (l, r) = promote(self, other);
+        checked_op!(l, r, checked_rem, Rem::rem).ok_or_else(|| overflow(self, "%", other))
+    }
+
+    fn checked_pow(self, exp: Self) -> TCResult<Self> {
+        if !exp.is_real() {
+            return Err(TCError::unsupported(format!(
+                "cannot raise {} to a complex power {}",
+                self, exp
+            )));
+        }
+
+        let dtype = self.class().promote(exp.class());
+        let base = dtype.cast(self);
+
+        let result = match base {
+            Number::Int(_) | Number::UInt(_) if exp < exp.class().zero() => {
+                // the reciprocal of an integer truncates to zero, unless the integer is +/-1
+                let exp: i64 = exp.cast_into();
+                let negative_one = dtype.cast(Number::from(-1i64));
+
+                if base == dtype.zero() {
+                    return Err(TCError::bad_request(
+                        "cannot raise zero to a negative power",
+                        exp,
+                    ));
+                } else if base == dtype.one() {
+                    Some(base)
+                } else if matches!(base, Number::Int(_)) && base == negative_one {
+                    Some(if exp % 2 == 0 { dtype.one() } else { base })
+                } else {
+                    Some(dtype.zero())
+                }
+            }
+            Number::Int(_) | Number::UInt(_) => {
+                // an exponent too large for a u32 overflows unless the base is 0 or +/-1,
+                // in which case only its parity matters
+                let exp: u64 = exp.cast_into();
+                let exp = if exp > u32::MAX as u64 {
+                    u32::MAX - (1 - (exp % 2) as u32)
+                } else {
+                    exp as u32
+                };
+
+                match base {
+                    Number::UInt(UInt::U8(base)) => base.checked_pow(exp).map(Number::from),
+                    Number::UInt(UInt::U16(base)) => base.checked_pow(exp).map(Number::from),
+                    Number::UInt(UInt::U32(base)) => base.checked_pow(exp).map(Number::from),
+                    Number::UInt(UInt::U64(base)) => base.checked_pow(exp).map(Number::from),
+                    Number::Int(Int::I8(base)) => base.checked_pow(exp).map(Number::from),
+                    Number::Int(Int::I16(base)) => base.checked_pow(exp).map(Number::from),
+                    Number::Int(Int::I32(base)) => base.checked_pow(exp).map(Number::from),
+                    Number::Int(Int::I64(base)) => base.checked_pow(exp).map(Number::from),
+                    _ => unreachable!("integer power of {}", base),
+                }
+            }
+            base => finite(base.pow(exp)),
+        };
+
+        result.ok_or_else(|| overflow(self, "**", exp))
+    }
+
+    fn saturating_add(self, other: Self) -> Self {
+        let (l, r) = promote(self, other);
+        saturating_op!(l, r, saturating_add, Add::add)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        let (l, r) = promote(self, other);
+        saturating_op!(l, r, saturating_sub, Sub::sub)
+    }
+
+    fn saturating_mul(self, other: Self) -> Self {
+        let (l, r) = promote(self, other);
+        saturating_op!(l, r, saturating_mul, Mul::mul)
+    }
+}
+
+/// Accessors for the components of a (possibly real) complex [`Number`].
+pub trait ComplexInstance {
+    /// Return the real component of this number.
+    fn re(self) -> Self;
+
+    /// Return the imaginary component of this number, or zero if it is real.
+    fn im(self) -> Self;
+
+    /// Return the complex conjugate of this number.
+    fn conj(self) -> Self;
+
+    /// Return the phase angle (in radians) of this number.
+    fn arg(self) -> Self;
+}
+
+impl ComplexInstance for Number {
+    fn re(self) -> Self {
+        match self {
+            Self::Complex(Complex::C32(c)) => c.re.into(),
+            Self::Complex(Complex::C64(c)) => c.re.into(),
+            real => real,
+        }
+    }
+
+    fn im(self) -> Self {
+        match self {
+            Self::Complex(Complex::C32(c)) => c.im.into(),
+            Self::Complex(Complex::C64(c)) => c.im.into(),
+            real => real.class().zero(),
+        }
+    }
+
+    fn conj(self) -> Self {
+        match self {
+            Self::Complex(Complex::C32(c)) => c.conj().into(),
+            Self::Complex(Complex::C64(c)) => c.conj().into(),
+            real => real,
+        }
+    }
+
+    fn arg(self) -> Self {
+        match self {
+            Self::Complex(Complex::C32(c)) => c.arg().into(),
+            Self::Complex(Complex::C64(c)) => c.arg().into(),
+            real => {
+                let dtype = NumberType::Float(FloatType::F32).promote(real.class());
+                let arg = if real < real.class().zero() {
+                    Float::F64(std::f64::consts::PI)
+                } else {
+                    Float::F64(0.)
+                };
+
+                dtype.cast(Number::Float(arg))
+            }
+        }
+    }
+}
+
+#[inline]
+fn promote(left: Number, right: Number) -> (Number, Number) {
+    let dtype = left.class().promote(right.class());
+    (dtype.cast(left), dtype.cast(right))
+}
+
+#[inline]
+fn finite(n: Number) -> Option<Number> {
+    if n.is_infinite() || n.is_nan() {
+        None
+    } else {
+        Some(n)
+    }
+}
+
+#[inline]
+fn overflow(left: Number, op: &str, right: Number) -> TCError {
+    TCError::bad_request("arithmetic overflow", format!("{} {} {}", left, op, right))
+}
+
+// the width of a float which can represent every integer of the given width (approximately,
+// in the case of 64-bit integers)
+#[inline]
+fn float_bits_for(int_bits: u8) -> u8 {
+    if int_bits <= 16 {
+        32
+    } else {
+        64
+    }
+}
+
+#[inline]
+fn uint_bits(dtype: UIntType) -> u8 {
+    match dtype {
+        UIntType::U8 => 8,
+        UIntType::U16 => 16,
+        UIntType::U32 => 32,
+        UIntType::U64 | UIntType::UInt => 64,
+    }
+}
+
+#[inline]
+fn int_bits(dtype: IntType) -> u8 {
+    match dtype {
+        IntType::I8 => 8,
+        IntType::I16 => 16,
+        IntType::I32 => 32,
+        IntType::I64 | IntType::Int => 64,
+    }
+}
+
+#[inline]
+fn int_type(bits: u8) -> IntType {
+    match bits {
+        8 => IntType::I8,
+        16 => IntType::I16,
+        32 => IntType::I32,
+        _ => IntType::I64,
+    }
+}
+
+#[inline]
+fn float_bits(dtype: FloatType) -> u8 {
+    match dtype {
+        FloatType::F32 => 32,
+        FloatType::F64 | FloatType::Float => 64,
+    }
+}
+
+#[inline]
+fn float_type(bits: u8) -> FloatType {
+    if bits <= 32 {
+        FloatType::F32
+    } else {
+        FloatType::F64
+    }
+}
+
+// the width of each component of a complex number
+#[inline]
+fn complex_bits(dtype: ComplexType) -> u8 {
+    match dtype {
+        ComplexType::C32 => 32,
+        ComplexType::C64 | ComplexType::Complex => 64,
+    }
+}
+
+#[inline]
+fn complex_type(bits: u8) -> ComplexType {
+    if bits <= 32 {
+        ComplexType::C32
+    } else {
+        ComplexType::C64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES: [NumberType; 14] = [
+        NumberType::Bool,
+        NumberType::UInt(UIntType::U8),
+        NumberType::UInt(UIntType::U16),
+        NumberType::UInt(UIntType::U32),
+        NumberType::UInt(UIntType::U64),
+        NumberType::Int(IntType::I8),
+        NumberType::Int(IntType::I16),
+        NumberType::Int(IntType::I32),
+        NumberType::Int(IntType::I64),
+        NumberType::Float(FloatType::F32),
+        NumberType::Float(FloatType::F64),
+        NumberType::Complex(ComplexType::C32),
+        NumberType::Complex(ComplexType::C64),
+        NumberType::Number,
+    ];
+
+    #[test]
+    fn test_promote_matrix() {
+        for l in TYPES.iter().copied() {
+            assert_eq!(l.promote(l), l);
+            assert_eq!(l.promote(NumberType::Bool), l);
+
+            for r in TYPES.iter().copied() {
+                let promoted = l.promote(r);
+                assert_eq!(promoted, r.promote(l), "{} and {}", l, r);
+                assert!(promoted >= l && promoted >= r, "{} and {}", l, r);
+                assert_eq!(promoted.promote(l), promoted, "{} and {}", l, r);
+                assert_eq!(promoted.promote(r), promoted, "{} and {}", l, r);
+            }
+        }
+    }
+
+    #[test]
+    fn test_promote_widths() {
+        use NumberType::*;
+        use {ComplexType as CT, FloatType as FT, IntType as IT, UIntType as UT};
+
+        let cases = [
+            (UInt(UT::U8), Int(IT::I8), Int(IT::I16)),
+            (UInt(UT::U16), Int(IT::I8), Int(IT::I32)),
+            (UInt(UT::U32), Int(IT::I64), Int(IT::I64)),
+            (UInt(UT::U64), Int(IT::I8), Int(IT::I64)),
+            (UInt(UT::U8), Float(FT::F32), Float(FT::F32)),
+            (UInt(UT::U32), Float(FT::F32), Float(FT::F64)),
+            (Int(IT::I16), Float(FT::F32), Float(FT::F32)),
+            (Int(IT::I64), Float(FT::F32), Float(FT::F64)),
+            (Int(IT::I16), Complex(CT::C32), Complex(CT::C32)),
+            (Int(IT::I32), Complex(CT::C32), Complex(CT::C64)),
+            (Float(FT::F32), Complex(CT::C32), Complex(CT::C32)),
+            (Float(FT::F64), Complex(CT::C32), Complex(CT::C64)),
+        ];
+
+        for (l, r, expected) in cases.iter().copied() {
+            assert_eq!(l.promote(r), expected, "{} and {}", l, r);
+        }
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let max = Number::from(i64::MAX);
+        let one = Number::from(1i64);
+
+        assert!(max.checked_add(one).is_err());
+        assert!(Number::from(i64::MIN).checked_sub(one).is_err());
+        assert!(max.checked_mul(Number::from(2i64)).is_err());
+        assert!(one.checked_div(Number::from(0i64)).is_err());
+        assert!(one.checked_rem(Number::from(0u8)).is_err());
+        assert!(Number::from(2u8).checked_pow(Number::from(8u8)).is_err());
+        assert!(Number::from(f32::MAX)
+            .checked_mul(Number::from(2f32))
+            .is_err());
+
+        assert_eq!(max.saturating_add(one), max);
+        assert_eq!(
+            Number::from(0u8).saturating_sub(Number::from(1u8)),
+            Number::from(0u8)
+        );
+
+        let sum = Number::from(u8::MAX)
+            .checked_add(Number::from(1i8))
+            .unwrap();
+        assert_eq!(sum, Number::from(256i16));
+        assert_eq!(sum.class(), NumberType::Int(IntType::I16));
+
+        let quotient = Number::from(1i64).checked_div(Number::from(2f32)).unwrap();
+        assert_eq!(quotient, Number::from(0.5f64));
+
+        let power = Number::from(-1i32)
+            .checked_pow(Number::from(u64::MAX))
+            .unwrap();
+        assert_eq!(power, Number::from(-1i32));
+    }
+
+    #[test]
+    fn test_checked_pow_negative_exponent() {
+        let pow = |base: i32, exp: i32| Number::from(base).checked_pow(Number::from(exp));
+
+        assert_eq!(pow(1, -3).unwrap(), Number::from(1i32));
+        assert_eq!(pow(-1, -3).unwrap(), Number::from(-1i32));
+        assert_eq!(pow(-1, -2).unwrap(), Number::from(1i32));
+        assert_eq!(pow(2, -1).unwrap(), Number::from(0i32));
+        assert_eq!(pow(-2, -1).unwrap(), Number::from(0i32));
+        assert!(pow(0, -1).is_err());
+
+        let power = Number::from(1u8).checked_pow(Number::from(-1i8)).unwrap();
+        assert_eq!(power, Number::from(1i16));
+    }
+
+    #[test]
+    fn test_complex_components() {
+        use safecast::CastFrom;
+
+        let n = Number::Complex(Complex::cast_from([3f64, -4f64]));
+        assert_eq!(n.re(), Number::from(3f64));
+        assert_eq!(n.im(), Number::from(-4f64));
+        assert_eq!(n.conj(), Number::Complex(Complex::cast_from([3f64, 4f64])));
+        assert_eq!(n.arg(), Number::from((-4f64).atan2(3.)));
+
+        let n = Number::from(-2i16);
+        assert_eq!(n.re(), n);
+        assert_eq!(n.im(), Number::from(0i16));
+        assert_eq!(n.conj(), n);
+        assert_eq!(n.arg(), Number::from(std::f32::consts::PI));
+    }
+}
@@ -43,6 +43,64 @@ impl TCString {
             .map(Self)
             .map_err(|e| TCError::bad_request("error rendering template", e))
     }
+
+    /// Replace each `{}` placeholder in this string with the next of the given `args`,
+    /// and each `{n}` placeholder with the `n`th of the given `args`.
+    ///
+    /// Use `{{` and `}}` to write a literal brace.
+    ///
+    /// Example:
+    /// ```
+    /// # use tc_value::TCString;
+    /// let template = TCString::from("{} is {{{1}}} ({0})".to_string());
+    /// assert_eq!(template.format(&["x", "y"]).unwrap().as_str(), "x is {y} (x)");
+    /// ```
+    pub fn format<T: fmt::Display>(&self, args: &[T]) -> TCResult<TCString> {
+        let invalid = || TCError::bad_request("invalid format string", &self.0);
+
+        let mut formatted = String::with_capacity(self.0.len());
+        let mut next = 0;
+        let mut chars = self.0.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    formatted.push('{');
+                }
+                '{' => {
+                    let mut position = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) if c.is_ascii_digit() => position.push(c),
+                            _ => return Err(invalid()),
+                        }
+                    }
+
+                    let i = if position.is_empty() {
+                        next += 1;
+                        next - 1
+                    } else {
+                        position.parse().map_err(|_| invalid())?
+                    };
+
+                    let arg = args.get(i).ok_or_else(|| {
+                        TCError::bad_request("missing argument for placeholder", i)
+                    })?;
+
+                    formatted.push_str(&arg.to_string());
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    formatted.push('}');
+                }
+                '}' => return Err(invalid()),
+                c => formatted.push(c),
+            }
+        }
+
+        Ok(Self(formatted))
+    }
 }
 
 impl Deref for TCString {
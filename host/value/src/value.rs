@@ -21,7 +21,9 @@ use uuid::Uuid;
 use tc_error::*;
 use tcgeneric::*;
 
-use super::{Link, TCString, Version};
+use super::{
+    bigint_from_number, bigint_into_number, BigInt, DateTime, Decimal, Link, TCString, Version,
+};
 
 pub use number_general::*;
 
@@ -32,7 +34,10 @@ const PREFIX: PathLabel = path_label(&["state", "scalar", "value"]);
 /// The class of a [`Value`].
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ValueType {
+    BigInt,
     Bytes,
+    DateTime,
+    Decimal,
     Id,
     Link,
     None,
@@ -46,6 +51,7 @@ pub enum ValueType {
 impl ValueType {
     pub fn size(&self) -> Option<usize> {
         match self {
+            Self::DateTime => Some(8),
             Self::Number(nt) => Some(nt.size()),
             _ => None,
         }
@@ -59,6 +65,13 @@ impl ValueType {
         let value = Value::from(value);
 
         match self {
+            Self::BigInt => match value {
+                Value::Decimal(decimal) => Ok(Value::BigInt(decimal.trunc())),
+                Value::Number(Number::Float(f)) => Decimal::opt_cast_from(Number::Float(f))
+                    .map(|decimal| Value::BigInt(decimal.trunc()))
+                    .ok_or_else(|| on_err(&Value::Number(Number::Float(f)))),
+                other => other.try_cast_into(on_err).map(Value::BigInt),
+            },
             Self::Bytes => match value {
                 Value::Bytes(bytes) => Ok(Value::Bytes(bytes)),
                 Value::Number(_) => Err(TCError::not_implemented("cast into Bytes from Number")),
@@ -67,6 +80,8 @@ impl ValueType {
                     .map(Value::Bytes),
                 other => Err(TCError::bad_request("cannot cast into Bytes from", other)),
             },
+            Self::DateTime => value.try_cast_into(on_err).map(Value::DateTime),
+            Self::Decimal => value.try_cast_into(on_err).map(Value::Decimal),
             Self::Id => value.try_cast_into(on_err).map(Value::Id),
             Self::Link => value.try_cast_into(on_err).map(Value::String),
             Self::None => Ok(Value::None),
@@ -88,6 +103,41 @@ impl ValueType {
             },
         }
     }
+
+    /// Cast the given `value` into this type, like [`ValueType::try_cast`], but return an error
+    /// instead of losing any information, e.g. when casting `3.5` into an integer, `-1` into an
+    /// unsigned integer, or `"abc"` into a number.
+    pub fn try_cast_strict<V>(&self, value: V) -> TCResult<Value>
+    where
+        Value: From<V>,
+    {
+        let value = Value::from(value);
+        let cast = self.try_cast(value.clone())?;
+
+        let lossless = if let Value::Number(n) = &cast {
+            match Self::Number(NumberType::Number).try_cast(value.clone()) {
+                Ok(Value::Number(source)) => {
+                    let negative = !matches!(source, Number::Complex(_)) && source < 0i64.into();
+                    &source == n && !(negative && matches!(n, Number::UInt(_)))
+                }
+                _ => false,
+            }
+        } else {
+            match value.class().try_cast(cast.clone()) {
+                Ok(round_trip) => round_trip == value,
+                Err(_) => false,
+            }
+        };
+
+        if lossless {
+            Ok(cast)
+        } else {
+            Err(TCError::bad_request(
+                format!("casting into {} would lose the precision of", self),
+                value,
+            ))
+        }
+    }
 }
 
 impl Default for ValueType {
@@ -114,6 +164,7 @@ impl NativeClass for ValueType {
             } else if path.len() == 4 {
                 match path[3].as_str() {
                     "bytes" => Some(Self::Bytes),
+                    "datetime" => Some(Self::DateTime),
                     "id" => Some(Self::Id),
                     "link" => Some(Self::Link),
                     "number" => Some(Self::Number(NT::Number)),
@@ -125,8 +176,10 @@ impl NativeClass for ValueType {
                 }
             } else if path.len() == 5 && &path[3] == "number" {
                 match path[4].as_str() {
+                    "bigint" => Some(Self::BigInt),
                     "bool" => Some(Self::Number(NT::Bool)),
                     "complex" => Some(Self::Number(NT::Complex(CT::Complex))),
+                    "decimal" => Some(Self::Decimal),
                     "float" => Some(Self::Number(NT::Float(FT::Float))),
                     "int" => Some(Self::Number(NT::Int(IT::Int))),
                     "uint" => Some(Self::Number(NT::UInt(UT::UInt))),
@@ -171,7 +224,10 @@ impl NativeClass for ValueType {
         let prefix = TCPathBuf::from(PREFIX);
 
         match self {
+            Self::BigInt => prefix.append(label("number")).append(label("bigint")),
             Self::Bytes => prefix.append(label("bytes")),
+            Self::DateTime => prefix.append(label("datetime")),
+            Self::Decimal => prefix.append(label("number")).append(label("decimal")),
             Self::Id => prefix.append(label("id")),
             Self::Link => prefix.append(label("link")),
             Self::None => prefix.append(label("none")),
@@ -262,6 +318,15 @@ impl Ord for ValueType {
             (Self::Bytes, _) => Greater,
             (_, Self::Bytes) => Less,
 
+            (Self::Decimal, _) => Greater,
+            (_, Self::Decimal) => Less,
+
+            (Self::BigInt, _) => Greater,
+            (_, Self::BigInt) => Less,
+
+            (Self::DateTime, _) => Greater,
+            (_, Self::DateTime) => Less,
+
             (Self::Number(_), _) => Greater,
             (_, Self::Number(_)) => Less,
 
@@ -342,7 +407,10 @@ impl<'en> en::IntoStream<'en> for ValueType {
 impl fmt::Display for ValueType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Self::BigInt => f.write_str("type BigInt"),
             Self::Bytes => f.write_str("type Bytes"),
+            Self::DateTime => f.write_str("type DateTime"),
+            Self::Decimal => f.write_str("type Decimal"),
             Self::Id => f.write_str("type Id"),
             Self::Link => f.write_str("type Link"),
             Self::None => f.write_str("type None"),
@@ -403,7 +471,10 @@ impl DestreamVisitor for ValueTypeVisitor {
 /// A generic value enum
 #[derive(Clone, Eq, PartialEq)]
 pub enum Value {
+    BigInt(BigInt),
     Bytes(Bytes),
+    DateTime(DateTime),
+    Decimal(Decimal),
     Link(Link),
     Id(Id),
     None,
@@ -455,7 +526,10 @@ impl Value {
         use ValueType as VT;
 
         match class {
+            VT::BigInt => self.opt_cast_into().map(Self::BigInt),
             VT::Bytes => self.opt_cast_into().map(Self::Bytes),
+            VT::DateTime => self.opt_cast_into().map(Self::DateTime),
+            VT::Decimal => self.opt_cast_into().map(Self::Decimal),
             VT::Id => self.opt_cast_into().map(Self::Id),
             VT::Link => self.opt_cast_into().map(Self::Link),
             VT::None => Some(Self::None),
@@ -485,7 +559,10 @@ impl Instance for Value {
     fn class(&self) -> ValueType {
         use ValueType as VT;
         match self {
+            Self::BigInt(_) => VT::BigInt,
             Self::Bytes(_) => VT::Bytes,
+            Self::DateTime(_) => VT::DateTime,
+            Self::Decimal(_) => VT::Decimal,
             Self::Id(_) => VT::Id,
             Self::Link(_) => VT::Link,
             Self::None => VT::None,
@@ -506,11 +583,26 @@ impl<'de> Deserialize<'de> for Value {
 impl Serialize for Value {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         match self {
+            Self::BigInt(i) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&self.class().path().to_string(), &i.to_string())?;
+                map.end()
+            }
             Self::Bytes(bytes) => {
                 let mut map = serializer.serialize_map(Some(1))?;
                 map.serialize_entry(&self.class().path().to_string(), &base64::encode(&bytes))?;
                 map.end()
             }
+            Self::DateTime(datetime) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&self.class().path().to_string(), &datetime.to_string())?;
+                map.end()
+            }
+            Self::Decimal(decimal) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&self.class().path().to_string(), &decimal.to_string())?;
+                map.end()
+            }
             Self::Id(id) => {
                 let mut map = serializer.serialize_map(Some(1))?;
                 map.serialize_entry(id.as_str(), &EMPTY_SEQ)?;
@@ -561,7 +653,22 @@ impl<'en> en::ToStream<'en> for Value {
         use en::EncodeMap;
 
         match self {
+            Self::BigInt(i) => {
+                let mut map = encoder.encode_map(Some(1))?;
+                map.encode_entry(self.class().path().to_string(), i.to_string())?;
+                map.end()
+            }
             Self::Bytes(bytes) => encoder.encode_bytes(bytes),
+            Self::DateTime(datetime) => {
+                let mut map = encoder.encode_map(Some(1))?;
+                map.encode_entry(self.class().path().to_string(), datetime.to_string())?;
+                map.end()
+            }
+            Self::Decimal(decimal) => {
+                let mut map = encoder.encode_map(Some(1))?;
+                map.encode_entry(self.class().path().to_string(), decimal.to_string())?;
+                map.end()
+            }
             Self::Id(id) => {
                 let mut map = encoder.encode_map(Some(1))?;
                 map.encode_entry(id, &EMPTY_SEQ)?;
@@ -593,7 +700,22 @@ impl<'en> en::IntoStream<'en> for Value {
         use en::EncodeMap;
 
         match self {
+            Self::BigInt(i) => {
+                let mut map = encoder.encode_map(Some(1))?;
+                map.encode_entry(ValueType::BigInt.path().to_string(), i.to_string())?;
+                map.end()
+            }
             Self::Bytes(bytes) => encoder.encode_bytes(&bytes),
+            Self::DateTime(datetime) => {
+                let mut map = encoder.encode_map(Some(1))?;
+                map.encode_entry(ValueType::DateTime.path().to_string(), datetime.to_string())?;
+                map.end()
+            }
+            Self::Decimal(decimal) => {
+                let mut map = encoder.encode_map(Some(1))?;
+                map.encode_entry(ValueType::Decimal.path().to_string(), decimal.to_string())?;
+                map.end()
+            }
             Self::Id(id) => {
                 let mut map = encoder.encode_map(Some(1))?;
                 map.encode_entry(id, &EMPTY_SEQ)?;
@@ -620,6 +742,12 @@ impl<'en> en::IntoStream<'en> for Value {
     }
 }
 
+impl From<BigInt> for Value {
+    fn from(i: BigInt) -> Self {
+        Self::BigInt(i)
+    }
+}
+
 impl From<bool> for Value {
     fn from(b: bool) -> Self {
         Self::Number(Number::from(b))
@@ -632,6 +760,18 @@ impl From<Bytes> for Value {
     }
 }
 
+impl From<DateTime> for Value {
+    fn from(datetime: DateTime) -> Self {
+        Self::DateTime(datetime)
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(decimal: Decimal) -> Self {
+        Self::Decimal(decimal)
+    }
+}
+
 impl From<Id> for Value {
     fn from(id: Id) -> Self {
         Self::Id(id)
@@ -717,6 +857,30 @@ impl TryFrom<Value> for Tuple<Value> {
     }
 }
 
+impl TryCastFrom<Value> for BigInt {
+    fn can_cast_from(value: &Value) -> bool {
+        match value {
+            Value::BigInt(_) => true,
+            Value::Decimal(decimal) => decimal.is_integer(),
+            Value::Number(n) => bigint_from_number(*n).is_some(),
+            Value::String(s) => BigInt::from_str(s).is_ok(),
+            Value::Tuple(t) if t.len() == 1 => Self::can_cast_from(&t[0]),
+            _ => false,
+        }
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        match value {
+            Value::BigInt(i) => Some(i),
+            Value::Decimal(decimal) if decimal.is_integer() => Some(decimal.trunc()),
+            Value::Number(n) => bigint_from_number(n),
+            Value::String(s) => BigInt::from_str(&s).ok(),
+            Value::Tuple(mut t) if t.len() == 1 => Self::opt_cast_from(t.pop().unwrap()),
+            _ => None,
+        }
+    }
+}
+
 impl TryCastFrom<Value> for Bytes {
     fn can_cast_from(value: &Value) -> bool {
         match value {
@@ -739,6 +903,56 @@ impl TryCastFrom<Value> for Bytes {
     }
 }
 
+impl TryCastFrom<Value> for DateTime {
+    fn can_cast_from(value: &Value) -> bool {
+        match value {
+            Value::DateTime(_) => true,
+            Value::Number(Number::Int(_)) => true,
+            Value::Number(Number::UInt(u)) => i64::try_from(u64::cast_from(*u)).is_ok(),
+            Value::String(s) => DateTime::from_str(s).is_ok(),
+            Value::Tuple(t) if t.len() == 1 => Self::can_cast_from(&t[0]),
+            _ => false,
+        }
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        match value {
+            Value::DateTime(datetime) => Some(datetime),
+            Value::Number(Number::Int(i)) => Some(DateTime::from_nanos(i64::cast_from(i))),
+            Value::Number(Number::UInt(u)) => i64::try_from(u64::cast_from(u))
+                .ok()
+                .map(DateTime::from_nanos),
+            Value::String(s) => DateTime::from_str(&s).ok(),
+            Value::Tuple(mut t) if t.len() == 1 => Self::opt_cast_from(t.pop().unwrap()),
+            _ => None,
+        }
+    }
+}
+
+impl TryCastFrom<Value> for Decimal {
+    fn can_cast_from(value: &Value) -> bool {
+        match value {
+            Value::BigInt(_) => true,
+            Value::Decimal(_) => true,
+            Value::Number(n) => Self::can_cast_from(n),
+            Value::String(s) => Decimal::from_str(s).is_ok(),
+            Value::Tuple(t) if t.len() == 1 => Self::can_cast_from(&t[0]),
+            _ => false,
+        }
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        match value {
+            Value::BigInt(i) => Some(Decimal::from(i)),
+            Value::Decimal(decimal) => Some(decimal),
+            Value::Number(n) => Self::opt_cast_from(n),
+            Value::String(s) => Decimal::from_str(&s).ok(),
+            Value::Tuple(mut t) if t.len() == 1 => Self::opt_cast_from(t.pop().unwrap()),
+            _ => None,
+        }
+    }
+}
+
 impl TryCastFrom<Value> for Id {
     fn can_cast_from(value: &Value) -> bool {
         match value {
@@ -760,7 +974,10 @@ impl TryCastFrom<Value> for Id {
 impl TryCastFrom<Value> for Number {
     fn can_cast_from(value: &Value) -> bool {
         match value {
+            Value::BigInt(_) => true,
             Value::Bytes(_) => false,
+            Value::DateTime(_) => true,
+            Value::Decimal(_) => true,
             Value::Id(id) => f64::from_str(id.as_str()).is_ok(),
             Value::Link(_) => false,
             Value::None => true,
@@ -777,7 +994,10 @@ impl TryCastFrom<Value> for Number {
 
     fn opt_cast_from(value: Value) -> Option<Self> {
         match value {
+            Value::BigInt(i) => Some(bigint_into_number(&i)),
             Value::Bytes(_) => None,
+            Value::DateTime(datetime) => Some(datetime.as_nanos().into()),
+            Value::Decimal(decimal) => Some(Self::cast_from(decimal)),
             Value::Id(id) => f64::from_str(id.as_str())
                 .map(Float::F64)
                 .map(Self::Float)
@@ -807,6 +1027,9 @@ impl TryCastFrom<Value> for Number {
 impl TryCastFrom<Value> for TCString {
     fn can_cast_from(value: &Value) -> bool {
         match value {
+            Value::BigInt(_) => true,
+            Value::DateTime(_) => true,
+            Value::Decimal(_) => true,
             Value::Link(_) => true,
             Value::Id(_) => true,
             Value::Number(_) => true,
@@ -817,6 +1040,9 @@ impl TryCastFrom<Value> for TCString {
 
     fn opt_cast_from(value: Value) -> Option<Self> {
         match value {
+            Value::BigInt(i) => Some(i.to_string().into()),
+            Value::DateTime(datetime) => Some(datetime.to_string().into()),
+            Value::Decimal(decimal) => Some(decimal.to_string().into()),
             Value::Link(link) => Self::opt_cast_from(link),
             Value::Id(id) => Self::opt_cast_from(id),
             Value::Number(n) => Self::opt_cast_from(n),
@@ -1004,6 +1230,28 @@ impl<T1: TryCastFrom<Value>, T2: TryCastFrom<Value>, T3: TryCastFrom<Value>> Try
     }
 }
 
+impl<
+        T1: TryCastFrom<Value>,
+        T2: TryCastFrom<Value>,
+        T3: TryCastFrom<Value>,
+        T4: TryCastFrom<Value>,
+    > TryCastFrom<Value> for (T1, T2, T3, T4)
+{
+    fn can_cast_from(value: &Value) -> bool {
+        match value {
+            Value::Tuple(tuple) => Self::can_cast_from(tuple),
+            _ => false,
+        }
+    }
+
+    fn opt_cast_from(value: Value) -> Option<Self> {
+        match value {
+            Value::Tuple(tuple) => Self::opt_cast_from(tuple),
+            _ => None,
+        }
+    }
+}
+
 impl<T: Clone + TryCastFrom<Value>> TryCastFrom<Value> for Map<T> {
     fn can_cast_from(value: &Value) -> bool {
         Vec::<(Id, T)>::can_cast_from(value)
@@ -1043,7 +1291,10 @@ impl<T: Clone + TryCastFrom<Value>> TryCastFrom<Value> for Tuple<T> {
 impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Self::BigInt(i) => write!(f, "{}: {}", ValueType::BigInt, i),
             Self::Bytes(bytes) => write!(f, "({} bytes)", bytes.len()),
+            Self::DateTime(datetime) => write!(f, "{}: {}", ValueType::DateTime, datetime),
+            Self::Decimal(decimal) => write!(f, "{}: {}", ValueType::Decimal, decimal),
             Self::Id(id) => write!(f, "{}: {:?}", ValueType::Id, id.as_str()),
             Self::Link(link) => write!(f, "{}: {:?}", ValueType::Link, link),
             Self::None => f.write_str("None"),
@@ -1066,7 +1317,10 @@ impl fmt::Debug for Value {
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Self::BigInt(i) => fmt::Display::fmt(i, f),
             Self::Bytes(bytes) => write!(f, "({} bytes)", bytes.len()),
+            Self::DateTime(datetime) => fmt::Display::fmt(datetime, f),
+            Self::Decimal(decimal) => fmt::Display::fmt(decimal, f),
             Self::Id(id) => f.write_str(id.as_str()),
             Self::Link(link) => fmt::Display::fmt(link, f),
             Self::None => f.write_str("None"),
@@ -1105,6 +1359,12 @@ impl ValueVisitor {
         use ValueType as VT;
 
         return match class {
+            VT::BigInt => {
+                let value = map.next_value::<Value>()?;
+                BigInt::opt_cast_from(value)
+                    .map(Value::BigInt)
+                    .ok_or_else(|| A::Error::custom("invalid BigInt"))
+            }
             VT::Bytes => {
                 let encoded = map.next_value::<&str>()?;
                 base64::decode(encoded)
@@ -1112,6 +1372,18 @@ impl ValueVisitor {
                     .map(Value::Bytes)
                     .map_err(serde::de::Error::custom)
             }
+            VT::DateTime => {
+                let value = map.next_value::<Value>()?;
+                DateTime::opt_cast_from(value)
+                    .map(Value::DateTime)
+                    .ok_or_else(|| A::Error::custom("invalid DateTime"))
+            }
+            VT::Decimal => {
+                let value = map.next_value::<Value>()?;
+                Decimal::opt_cast_from(value)
+                    .map(Value::Decimal)
+                    .ok_or_else(|| A::Error::custom("invalid Decimal"))
+            }
             VT::Id => {
                 let id: &str = map.next_value()?;
                 Id::from_str(id).map(Value::Id).map_err(A::Error::custom)
@@ -1162,10 +1434,28 @@ impl ValueVisitor {
         }
 
         return match class {
+            VT::BigInt => {
+                let value = map.next_value::<Value>(()).await?;
+                BigInt::opt_cast_from(value)
+                    .map(Value::BigInt)
+                    .ok_or_else(|| de::Error::custom("invalid BigInt"))
+            }
             VT::Bytes => {
                 let bytes = map.next_value(()).await?;
                 Ok(Value::Bytes(bytes))
             }
+            VT::DateTime => {
+                let value = map.next_value::<Value>(()).await?;
+                DateTime::opt_cast_from(value)
+                    .map(Value::DateTime)
+                    .ok_or_else(|| de::Error::custom("invalid DateTime"))
+            }
+            VT::Decimal => {
+                let value = map.next_value::<Value>(()).await?;
+                Decimal::opt_cast_from(value)
+                    .map(Value::Decimal)
+                    .ok_or_else(|| de::Error::custom("invalid Decimal"))
+            }
             VT::Id => {
                 let id = map.next_value(()).await?;
                 Ok(Value::Id(id))
@@ -1436,3 +1726,48 @@ impl destream::de::Visitor for ValueVisitor {
         Ok(Value::Tuple(value.into()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast_strict() {
+        let int = ValueType::Number(NumberType::Int(IntType::I64));
+        let uint = ValueType::Number(NumberType::UInt(UIntType::U64));
+
+        let fraction = Value::from(Number::from(3.5f64));
+        assert_eq!(
+            int.try_cast(fraction.clone()).expect("lossy cast"),
+            Value::from(Number::from(3i64))
+        );
+        assert!(int.try_cast_strict(fraction).is_err());
+
+        let negative = Value::from(Number::from(-1i64));
+        assert!(uint.try_cast(negative.clone()).is_ok());
+        assert!(uint.try_cast_strict(negative).is_err());
+
+        let whole = Value::from(Number::from(3i64));
+        assert_eq!(
+            uint.try_cast_strict(whole).expect("strict cast"),
+            Value::from(3u64)
+        );
+
+        let numeric = Value::String(TCString::from("12".to_string()));
+        assert_eq!(
+            int.try_cast_strict(numeric).expect("strict cast"),
+            Value::from(Number::from(12i64))
+        );
+
+        let text = Value::String(TCString::from("abc".to_string()));
+        assert!(int.try_cast_strict(text).is_err());
+
+        let number = Value::from(Number::from(5i64));
+        assert_eq!(
+            ValueType::String
+                .try_cast_strict(number)
+                .expect("strict cast"),
+            Value::String(TCString::from("5".to_string()))
+        );
+    }
+}
@@ -6,13 +6,234 @@ from testutils import ClientTest
 ENDPOINT = "/transact/hypothetical"
 
 
+class BytesTests(ClientTest):
+    def testEncoding(self):
+        cxt = tc.Context()
+        cxt.bytes = tc.Bytes.from_hex("deadbeef")
+        cxt.result = (cxt.bytes.len(), cxt.bytes.to_base64(), tc.Bytes.from_base64("3q2+7w==").to_hex())
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, [4, "3q2+7w==", "deadbeef"])
+
+    def testHash(self):
+        cxt = tc.Context()
+        cxt.result = tc.Bytes.from_hex("deadbeef").sha256().to_base64()
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, "X3jDMnTkP6neVlkmXB2RfiXANyLcsLjSfbjV/qqBOVM=")
+
+
+class ClosureTests(ClientTest):
+    def testBind(self):
+        @tc.post_op
+        def scale(x: tc.Number, factor: tc.Number) -> tc.Number:
+            return x * factor
+
+        cxt = tc.Context()
+        cxt.triple = tc.Closure({}, scale).bind(factor=3)
+        cxt.result = cxt.triple.post(tc.Number, x=2)
+
+        self.assertEqual(self.host.post(ENDPOINT, cxt), 6)
+
+
+class OpTests(ClientTest):
+    def testTypedParams(self):
+        @tc.post_op(typed=True)
+        def scale(x: tc.Int, factor: tc.Int = 2) -> tc.Int:
+            return x * factor
+
+        cxt = tc.Context()
+        cxt.scale = scale
+        cxt.result = (cxt.scale(x=3), cxt.scale(x=3, factor=3))
+        self.assertEqual(self.host.post(ENDPOINT, cxt), [6, 9])
+
+        cxt = tc.Context()
+        cxt.scale = scale
+        cxt.result = cxt.scale(x=3.5)
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
+
+        cxt = tc.Context()
+        cxt.scale = scale
+        cxt.result = cxt.scale(factor=3)
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
+
+
+class MapTests(ClientTest):
+    def testMapAndFilter(self):
+        @tc.get_op
+        def double(x: tc.Int) -> tc.Int:
+            return x * 2
+
+        @tc.get_op
+        def is_even(x: tc.Int) -> tc.Bool:
+            return (x % 2) == 0
+
+        cxt = tc.Context()
+        cxt.map = tc.Map(a=1, b=2, c=3)
+        cxt.result = (cxt.map.map(double), cxt.map.filter(is_even), cxt.map.contains("c"))
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, [{"a": 2, "b": 4, "c": 6}, {"b": 2}, True])
+
+    def testGetPath(self):
+        cxt = tc.Context()
+        cxt.map = tc.Map(a=tc.Map(b=tc.Tuple([1, 2, 3])))
+        cxt.result = cxt.map.get_path("a", "b", 2)
+
+        self.assertEqual(self.host.post(ENDPOINT, cxt), 3)
+
+    def testGetPathInvalidIndex(self):
+        def get_path(i):
+            cxt = tc.Context()
+            cxt.map = tc.Map(a=tc.Tuple([1, 2, 3]))
+            cxt.result = cxt.map.get_path("a", i)
+            return self.host.post(ENDPOINT, cxt)
+
+        self.assertRaises(tc.error.NotFound, lambda: get_path(3))
+        self.assertRaises(tc.error.BadRequest, lambda: get_path(-1))
+        self.assertRaises(tc.error.BadRequest, lambda: get_path(1.5))
+
+
 class NumberTests(ClientTest):
+    def testBigInt(self):
+        cxt = tc.Context()
+        cxt.result = tc.BigInt(2**64) * tc.BigInt(2**64)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, {str(tc.uri(tc.BigInt)): str(2**128)})
+
+    def testDecimal(self):
+        cxt = tc.Context()
+        cxt.sum = tc.Decimal("0.1") + tc.Decimal("0.2")
+        cxt.result = (cxt.sum == tc.Decimal("0.3"), (cxt.sum / 3).round(2))
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, [True, {str(tc.uri(tc.Decimal)): "0.1"}])
+
+    def testDateTime(self):
+        cxt = tc.Context()
+        cxt.start = tc.DateTime("2021-06-30T23:00:00-01:30")
+        cxt.end = cxt.start.add_duration(3600.5)
+        cxt.result = (cxt.end, cxt.end - cxt.start, cxt.start < cxt.end, tc.DateTime.now() > cxt.end)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, [{str(tc.uri(tc.DateTime)): "2021-07-01T01:30:00.5Z"}, 3600.5, True, True])
+
+    def testCast(self):
+        cxt = tc.Context()
+        cxt.result = (tc.Float(3.5).cast(tc.Int), tc.Int(3).cast(tc.UInt, lossy=False))
+
+        self.assertEqual(self.host.post(ENDPOINT, cxt), [3, 3])
+
+    def testCastStrict(self):
+        def cast(value, dtype):
+            cxt = tc.Context()
+            cxt.result = value.cast(dtype, lossy=False)
+            return self.host.post(ENDPOINT, cxt)
+
+        self.assertRaises(tc.error.BadRequest, lambda: cast(tc.Float(3.5), tc.Int))
+        self.assertRaises(tc.error.BadRequest, lambda: cast(tc.Int(-1), tc.UInt))
+        self.assertRaises(tc.error.BadRequest, lambda: cast(tc.String("abc"), tc.Int))
+
     def testDivideByZero(self):
         cxt = tc.Context()
         cxt.result = tc.F32(3.14) / tc.F32(0.)
 
         self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
 
+    def testOverflow(self):
+        cxt = tc.Context()
+        cxt.result = tc.I64(2**62) * tc.I64(4)
+
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
+
+    def testRem(self):
+        cxt = tc.Context()
+        cxt.result = tc.I64(7) % tc.I64(3)
+
+        self.assertEqual(self.host.post(ENDPOINT, cxt), 1)
+
+
+class StringTests(ClientTest):
+    def testManipulation(self):
+        cxt = tc.Context()
+        cxt.words = tc.String("  Hello, World  ").strip().split(", ")
+        cxt.result = (
+            tc.String("-").join(cxt.words),
+            tc.String("{1} {}").format(tc.String(cxt.words[0]).upper(), tc.String(cxt.words[1]).lower()),
+            tc.String("Hello").starts_with("He"),
+            tc.String("Hello").ends_with("He"),
+        )
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, ["Hello-World", "world HELLO", True, False])
+
+    def testRegex(self):
+        cxt = tc.Context()
+        cxt.email = tc.String("user@example.com")
+        cxt.result = (
+            cxt.email.matches(r"^[^@]+@example\.com$"),
+            cxt.email.extract(r"@(?P<domain>.+)$", "domain"),
+            cxt.email.extract(r"^\d+"),
+            cxt.email.replace(r"^([^@]+)@(.+)$", "$2/$1"),
+        )
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, [True, "example.com", None, "example.com/user"])
+
+
+class TupleTests(ClientTest):
+    def testFunctionalOps(self):
+        @tc.get_op
+        def is_even(x: tc.Int) -> tc.Bool:
+            return (x % 2) == 0
+
+        @tc.post_op
+        def add(item: tc.Int, state: tc.Int) -> tc.Int:
+            return item + state
+
+        cxt = tc.Context()
+        cxt.tuple = tc.Tuple([1, 2, 3, 4])
+        cxt.result = (
+            cxt.tuple.filter(is_even),
+            cxt.tuple.reduce(add),
+            cxt.tuple.slice(1, -1),
+            cxt.tuple.contains(3),
+            cxt.tuple.contains(5),
+        )
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, [[2, 4], 10, [2, 3], True, False])
+
+    def testGetInvalidIndex(self):
+        def get(i):
+            cxt = tc.Context()
+            cxt.tuple = tc.Tuple([1, 2, 3])
+            cxt.result = cxt.tuple[i]
+            return self.host.post(ENDPOINT, cxt)
+
+        self.assertRaises(tc.error.NotFound, lambda: get(3))
+        self.assertRaises(tc.error.BadRequest, lambda: get(-1))
+
+
+class WhileTests(ClientTest):
+    def testIterationLimit(self):
+        @tc.post_op
+        def cond(i: tc.Int):
+            return i < 10
+
+        @tc.post_op
+        def step(i: tc.Int) -> tc.Int:
+            return tc.Map(i=i + 1)
+
+        cxt = tc.Context()
+        cxt.result = tc.While(cond, step, tc.Map(i=0), 10)
+        self.assertEqual(self.host.post(ENDPOINT, cxt), {"i": 10})
+
+        cxt = tc.Context()
+        cxt.result = tc.While(cond, step, tc.Map(i=0), 5)
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
+
 
 if __name__ == "__main__":
     unittest.main()
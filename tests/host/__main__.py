@@ -1,9 +1,16 @@
+from test_admin import *
 from test_btree import *
 from test_client_docs import *
+from test_dashboard import *
 from test_einsum import *
+from test_executor import *
+from test_finalize import *
 from test_graph import *
+from test_openapi import *
+from test_savepoint import *
 from test_table import *
 from test_table_demo import *
+from test_tenant import *
 from test_tensor import *
 
 
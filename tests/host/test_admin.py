@@ -0,0 +1,144 @@
+import os
+import shutil
+import tinychain as tc
+import unittest
+
+from testutils import start_host
+
+BACKUP_DIR = "/tmp/tc/backup/test_admin"
+CONFIG_PATH = "/tmp/tc/config/test_admin.toml"
+SCOPE_ADMIN = "/host/admin"
+
+
+class Hello(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/hello")
+
+    @tc.get_method
+    def greet(self) -> tc.String:
+        return tc.String("hello")
+
+
+class Greeter(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/greeter/v1")
+
+    @tc.get_method
+    def greet(self) -> tc.String:
+        return tc.String("hi")
+
+
+class Admin(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/admin")
+
+    @tc.post_method
+    def deploy(self, txn):
+        @tc.post_op
+        def install():
+            return tc.ref.Put(tc.URI("/host/cluster"), tc.uri(Hello), tc.form_of(Hello))
+
+        return self.grant(SCOPE_ADMIN, install)
+
+    @tc.post_method
+    def backup(self, txn):
+        @tc.post_op
+        def take_backup():
+            return tc.ref.Put(tc.URI("/backup/nightly"), None, None)
+
+        return self.grant(SCOPE_ADMIN, take_backup)
+
+    @tc.post_method
+    def reload(self, txn):
+        @tc.post_op
+        def reload_config():
+            return tc.ref.Put(tc.URI("/config"), None, None)
+
+        return self.grant(SCOPE_ADMIN, reload_config)
+
+    @tc.post_method
+    def deprecate(self, txn):
+        @tc.post_op
+        def set_version():
+            return tc.ref.Put(tc.URI("/host/version"), tc.uri(Greeter), {"deprecated": True})
+
+        return self.grant(SCOPE_ADMIN, set_version)
+
+    @tc.post_method
+    def limit(self, txn, disk: tc.UInt):
+        @tc.post_op
+        def set_quota():
+            return tc.ref.Put(tc.URI("/host/quota"), tc.uri(Admin), {"disk": disk})
+
+        return self.grant(SCOPE_ADMIN, set_quota)
+
+
+class AdminTest(unittest.TestCase):
+    def setUp(self):
+        if os.path.exists(BACKUP_DIR):
+            shutil.rmtree(BACKUP_DIR)
+
+        os.makedirs(os.path.dirname(CONFIG_PATH), exist_ok=True)
+        with open(CONFIG_PATH, "w") as config:
+            config.write('cache_limit = "10K"\n')
+
+        flags = [f"--host_admin={tc.uri(Admin)}", f"--backup_dir={BACKUP_DIR}", f"--config={CONFIG_PATH}"]
+        self.host = start_host("test_admin", [Admin, Greeter], flags=flags)
+
+    def testInstall(self):
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.install(Hello))
+        self.assertRaises(tc.error.NotFound, lambda: self.host.get("/test/hello/greet"))
+
+        self.host.post("/test/admin/deploy")
+        self.assertEqual(self.host.get("/test/hello/greet"), "hello")
+
+    def testBackup(self):
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.put("/backup/nightly"))
+        self.assertEqual(self.host.get("/backup"), [])
+
+        self.host.post("/test/admin/backup")
+        self.assertEqual(self.host.get("/backup"), ["nightly"])
+
+    def testReloadConfig(self):
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.put("/config"))
+        self.host.post("/test/admin/reload")
+
+        # the cache limit can't be less than the cache size
+        with open(CONFIG_PATH, "w") as config:
+            config.write('cache_limit = "1K"\n')
+
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post("/test/admin/reload"))
+
+    def testDeprecateVersion(self):
+        self.assertRaises(
+            tc.error.Unauthorized,
+            lambda: self.host.put("/host/version", tc.uri(Greeter), {"deprecated": True}))
+
+        self.host.post("/test/admin/deprecate")
+
+        versions = self.host.get("/host/version", tc.URI("/test/greeter"))["versions"]
+        self.assertEqual(len(versions), 1)
+        self.assertTrue(versions[0]["deprecated"])
+
+    def testSetQuota(self):
+        self.assertRaises(
+            tc.error.Unauthorized,
+            lambda: self.host.put("/host/quota", tc.uri(Admin), {"disk": 0}))
+
+        self.host.post("/test/admin/limit", {"disk": 1024 ** 3})
+        quota = self.host.get("/host/quota", tc.uri(Admin))["quota"]
+        self.assertEqual(quota["disk"], 1024 ** 3)
+
+    def testSchedule(self):
+        job = tc.ref.Get(tc.URI("/test/greeter/v1/greet"))
+
+        self.assertRaises(
+            tc.error.Unauthorized,
+            lambda: self.host.put("/schedule/greet", "@hourly", job))
+
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.delete("/schedule/greet"))
+        self.assertEqual(self.host.get("/schedule"), [])
+
+    def tearDown(self):
+        self.host.stop()
+
+
+if __name__ == "__main__":
+    unittest.main()
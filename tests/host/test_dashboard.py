@@ -0,0 +1,55 @@
+import requests
+import tinychain as tc
+import unittest
+
+from testutils import start_host
+
+SCHEMA = tc.table.Schema([tc.Column("name", tc.String, 512)], [tc.Column("views", tc.UInt)])
+
+
+class Dashboard(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/dashboard")
+
+    def _configure(self):
+        self.table = tc.chain.Block(tc.table.Table(SCHEMA))
+
+
+class DashboardTest(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.host = start_host("test_dashboard", [Dashboard], flags=["--admin"])
+
+        for i, name in enumerate(["one", "two", "three"]):
+            cls.host.put("/test/dashboard/table", [name], [i])
+
+    def url(self, path):
+        return str(tc.uri(self.host)) + path
+
+    def testAssets(self):
+        response = requests.get(self.url("/admin"))
+        self.assertEqual(response.status_code, 200)
+        self.assertTrue(response.headers["content-type"].startswith("text/html"))
+
+        response = requests.get(self.url("/admin/missing.js"))
+        self.assertEqual(response.status_code, 404)
+
+    def testUnauthorized(self):
+        # only a host administrator can read the overview of this host or browse its data
+        response = requests.get(self.url("/admin/api/overview"))
+        self.assertEqual(response.status_code, 401)
+
+        params = {"path": "/test/dashboard/table", "limit": 2}
+        response = requests.get(self.url("/admin/api/browse"), params=params)
+        self.assertEqual(response.status_code, 401)
+
+    def testReadOnly(self):
+        response = requests.put(self.url("/admin/api/overview"))
+        self.assertEqual(response.status_code, 405)
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.host.stop()
+
+
+if __name__ == "__main__":
+    unittest.main()
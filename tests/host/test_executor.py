@@ -0,0 +1,53 @@
+import functools
+import operator
+import tinychain as tc
+import unittest
+
+from testutils import start_host
+
+ENDPOINT = "/transact/hypothetical"
+WIDTH = 20
+
+
+class ExecutorTest(object):
+    def testDependencyOrder(self):
+        cxt = tc.Context()
+        cxt.a = tc.Int(1)
+        cxt.b = cxt.a + 2
+        cxt.c = cxt.a * 3
+        cxt.d = cxt.b * cxt.c
+        cxt.result = cxt.d - cxt.a
+
+        self.assertEqual(self.host.post(ENDPOINT, cxt), 8)
+
+    def testIndependentProviders(self):
+        cxt = tc.Context()
+        cxt.x = tc.Int(1)
+
+        for i in range(WIDTH):
+            setattr(cxt, f"x{i}", cxt.x + i)
+
+        cxt.result = functools.reduce(operator.add, (getattr(cxt, f"x{i}") for i in range(WIDTH)))
+
+        expected = sum(1 + i for i in range(WIDTH))
+        self.assertEqual(self.host.post(ENDPOINT, cxt), expected)
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.host.stop()
+
+
+class ConcurrentExecutorTests(ExecutorTest, unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.host = start_host("test_executor_concurrent", flags=["--op_parallelism=4"])
+
+
+class SerialExecutorTests(ExecutorTest, unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.host = start_host("test_executor_serial", flags=["--op_parallelism=1"])
+
+
+if __name__ == "__main__":
+    unittest.main()
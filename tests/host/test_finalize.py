@@ -0,0 +1,45 @@
+import time
+import tinychain as tc
+import unittest
+
+from num2words import num2words
+from testutils import start_host
+
+REQUEST_TTL = 2
+HORIZON = 1
+SCHEMA = tc.table.Schema([tc.Column("name", tc.String, 512)], [tc.Column("views", tc.UInt)])
+
+
+class Persistent(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/finalize")
+
+    def _configure(self):
+        self.table = tc.chain.Block(tc.table.Table(SCHEMA))
+
+
+class FinalizeTest(unittest.TestCase):
+    def setUp(self):
+        flags = [f"--txn_horizon={HORIZON}", "--txn_gc_interval=50"]
+        self.host = start_host("test_finalize", [Persistent], timeout=REQUEST_TTL, flags=flags)
+
+    def testReclaimVersions(self):
+        for i in range(5):
+            self.host.put("/test/finalize/table", [num2words(i)], [i])
+
+        before = self.host.get("/transact/metrics")
+        self.assertGreater(before["pending_versions"], 0)
+
+        # wait for every transaction above to expire, plus the configured horizon
+        time.sleep(REQUEST_TTL + HORIZON + 1)
+
+        after = self.host.get("/transact/metrics")
+        self.assertGreaterEqual(after["finalized"], before["finalized"] + 5)
+        self.assertLess(after["pending_versions"], before["pending_versions"])
+        self.assertEqual(self.host.get("/test/finalize/table/count"), 5)
+
+    def tearDown(self):
+        self.host.stop()
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,57 @@
+import tinychain as tc
+import unittest
+
+from testutils import start_host
+
+SCHEMA = tc.table.Schema([tc.Column("name", tc.String, 512)], [tc.Column("views", tc.UInt)])
+
+
+class Service(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/openapi")
+
+    def _configure(self):
+        self.table = tc.chain.Block(tc.table.Table(SCHEMA))
+
+    @tc.get_method
+    def greet(self) -> tc.String:
+        return tc.String("hello")
+
+    @tc.post_method
+    def add(self, txn, a: tc.Int, b: tc.Int) -> tc.Int:
+        return a + b
+
+
+class OpenAPITest(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.host = start_host("test_openapi", [Service])
+
+    def testCluster(self):
+        document = self.host.get("/test/openapi/-/openapi.json")
+        self.assertEqual(document["openapi"], "3.0.3")
+        self.assertEqual(document["info"]["title"], "/test/openapi")
+
+        paths = document["paths"]
+        self.assertIn("get", paths["/test/openapi/greet"])
+        self.assertIn("requestBody", paths["/test/openapi/add"]["post"])
+
+        table = paths["/test/openapi/table"]
+        self.assertEqual(set(["get", "put", "delete"]), set(k for k in table if not k.startswith("x-")))
+
+        schema = table["x-tc-schema"]["$ref"].split("/")[-1]
+        self.assertIn(schema, document["components"]["schemas"])
+
+    def testHost(self):
+        document = self.host.get("/-/openapi.json")
+        self.assertIn("/test/openapi/greet", document["paths"])
+
+    def testNotFound(self):
+        self.assertRaises(tc.error.NotFound, lambda: self.host.get("/test/missing/-/openapi.json"))
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.host.stop()
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,51 @@
+import tinychain as tc
+import unittest
+
+from testutils import start_host
+
+ENDPOINT = "/transact/hypothetical"
+SCHEMA = tc.btree.Schema((tc.Column("number", tc.Int), tc.Column("word", tc.String, 100)))
+
+
+class SavepointTests(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        cls.host = start_host("test_savepoint")
+
+    def testRollback(self):
+        cxt = tc.Context()
+        cxt.tree = tc.btree.BTree(SCHEMA)
+        cxt.first = cxt.tree.insert((1, "one"))
+        cxt.save = tc.After(cxt.first, tc.txn.savepoint("inserted"))
+        cxt.second = tc.After(cxt.save, cxt.tree.insert((2, "two")))
+        cxt.rollback = tc.After(cxt.second, tc.txn.rollback("inserted"))
+        cxt.result = tc.After(cxt.rollback, cxt.tree.count())
+
+        count = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(count, 1)
+
+    def testContinueAfterRollback(self):
+        cxt = tc.Context()
+        cxt.tree = tc.btree.BTree(SCHEMA)
+        cxt.save = tc.After(cxt.tree, tc.txn.savepoint("empty"))
+        cxt.first = tc.After(cxt.save, cxt.tree.insert((1, "one")))
+        cxt.rollback = tc.After(cxt.first, tc.txn.rollback("empty"))
+        cxt.second = tc.After(cxt.rollback, cxt.tree.insert((2, "two")))
+        cxt.result = tc.After(cxt.second, cxt.tree[(2,)].count())
+
+        count = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(count, 1)
+
+    def testUnknownSavepoint(self):
+        cxt = tc.Context()
+        cxt.result = tc.txn.rollback("missing")
+
+        self.assertRaises(tc.error.NotFound, lambda: self.host.post(ENDPOINT, cxt))
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.host.stop()
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -15,6 +15,53 @@ class TableTests(unittest.TestCase):
     def setUpClass(cls):
         cls.host = start_host("test_table")
 
+    def testAlter(self):
+        count = 10
+        values = [(v,) for v in range(count)]
+        keys = [(num2words(i),) for i in range(count)]
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(SCHEMA)
+        cxt.inserts = [cxt.table.insert(k, v) for k, v in zip(keys, values)]
+        cxt.added = tc.table.Table(tc.After(cxt.inserts, cxt.table.add_column(tc.Column("score", tc.UInt), 0)))
+        cxt.renamed = cxt.added.rename_column("score", "rank")
+        cxt.result = cxt.renamed.drop_column("rank").count()
+
+        result = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(result, count)
+
+    def testAsTensor(self):
+        count = 10
+        values = [(v,) for v in range(count)]
+        keys = [(num2words(i),) for i in range(count)]
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(SCHEMA)
+        cxt.inserts = [cxt.table.insert(k, v) for k, v in zip(keys, values)]
+        cxt.tensor = tc.After(cxt.inserts, cxt.table.as_tensor(["views"]))
+        cxt.result = (tc.tensor.Dense(cxt.tensor).shape, tc.tensor.Dense(cxt.tensor).sum())
+
+        shape, total = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(shape, [count, 1])
+        self.assertEqual(total, sum(range(count)))
+
+    def testCountDistinct(self):
+        count = 100
+        values = [(v % 7,) for v in range(count)]
+        keys = [(num2words(i),) for i in range(count)]
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(SCHEMA)
+        cxt.inserts = [cxt.table.insert(k, v) for k, v in zip(keys, values)]
+        cxt.result = tc.After(cxt.inserts, (
+            cxt.table.count_distinct("name"),
+            cxt.table.count_distinct("views"),
+            cxt.table.count_distinct("name", {"views": 3}),
+        ))
+
+        result = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(result, [count, 7, len(range(3, count, 7))])
+
     def testCreate(self):
         cxt = tc.Context()
         cxt.table = tc.table.Table(SCHEMA)
@@ -23,6 +70,47 @@ class TableTests(unittest.TestCase):
         count = self.host.post(ENDPOINT, cxt)
         self.assertEqual(count, 1)
 
+    def testDefaults(self):
+        schema = tc.table.Schema(
+            [tc.Column("name", tc.String, 512)], [tc.Column("label", tc.String, 512), tc.Column("views", tc.UInt)])
+
+        schema.set_default("views", 0).not_null("label")
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(schema)
+        cxt.result = tc.After(cxt.table.insert(["one"], ["first"]), cxt.table)
+
+        result = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(result, expected(schema, [["one", "first", 0]]))
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(schema)
+        cxt.result = cxt.table.insert(["one"], [None, 1])
+
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
+
+    def testSchema(self):
+        schema = tc.table.Schema(
+            [tc.Column("name", tc.String, 512)], [tc.Column("label", tc.String, 512), tc.Column("views", tc.UInt)])
+
+        schema.create_index("label", ["label"], unique=True)
+        schema.set_default("views", 0).not_null("label")
+        schema.validate(tc.table.Rule.range("views", 0, 100))
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(schema)
+        cxt.result = tc.After(cxt.table.insert(["one"], ["first"]), cxt.table)
+
+        result = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(result, expected(schema, [["one", "first", 0]]))
+
+        # a Table decoded from its own encoding keeps every constraint of its schema
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(result)
+        cxt.result = cxt.table.insert(["two"], ["first"])
+
+        self.assertRaises(tc.error.Conflict, lambda: self.host.post(ENDPOINT, cxt))
+
     def testDelete(self):
         count = 2
         values = [(v,) for v in range(count)]
@@ -37,6 +125,31 @@ class TableTests(unittest.TestCase):
         result = self.host.post(ENDPOINT, cxt)
         self.assertEqual(result, expected(SCHEMA, []))
 
+    def testInfer(self):
+        csv = "name,views\none,1\ntwo,2\nthree,3\n"
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table.infer(csv, key=["name"])
+        cxt.result = cxt.table.count()
+
+        count = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(count, 3)
+
+    def testInferWithValidation(self):
+        csv = "name,views,likes\none,1,0\ntwo,-2,1\nthree,3,4\nfour,4,2\n"
+        rules = [tc.table.Rule.range("views", 0, 100), tc.table.Rule.compare("likes", "le", "views")]
+
+        cxt = tc.Context()
+        cxt.load = tc.table.Table.infer(csv, key=["name"], rules=rules)
+        cxt.result = tc.Map({
+            "loaded": cxt.load["loaded"],
+            "quarantined": cxt.load["quarantined"],
+            "count": tc.table.Table(cxt.load["table"]).count(),
+        })
+
+        result = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(result, {"loaded": 2, "quarantined": 2, "count": 2})
+
     def testInsert(self):
         for x in range(0, 100, 10):
             keys = list(range(x))
@@ -67,6 +180,96 @@ class TableTests(unittest.TestCase):
         first_row = sorted(list(k + v) for k, v in zip(keys, values))[0]
         self.assertEqual(result, expected(SCHEMA, [first_row]))
 
+    def testLoadNdjson(self):
+        ndjson = "\n".join([
+            '{"name": "one", "views": 1}',
+            '["two", 2]',
+            '{"name": "three", "views": "many"}',
+            '{"name": "four"',
+            '',
+            '{"name": "five", "views": 5}',
+        ])
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(SCHEMA)
+        cxt.load = cxt.table.load_ndjson(ndjson, batch_size=2)
+        cxt.result = tc.After(cxt.load, tc.Map({
+            "loaded": cxt.load["loaded"],
+            "errors": cxt.load["errors"],
+            "count": cxt.table.count(),
+        }))
+
+        result = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(result["loaded"], 3)
+        self.assertEqual(result["count"], 3)
+        self.assertEqual([line for line, _reason in result["errors"]], [3, 4])
+
+    def testPage(self):
+        count = 50
+        values = [(v,) for v in range(count)]
+        keys = [(num2words(i),) for i in range(count)]
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(SCHEMA)
+        cxt.inserts = [cxt.table.insert(k, v) for k, v in zip(keys, values)]
+        cxt.result = tc.After(cxt.inserts, cxt.table.order_by(["views"], True).limit(5, 10))
+
+        result = self.host.post(ENDPOINT, cxt)
+        rows = sorted((list(k + v) for k, v in zip(keys, values)), key=lambda row: row[1], reverse=True)
+        self.assertEqual(result, expected(SCHEMA, rows[10:15]))
+
+    def testPaginate(self):
+        count = 25
+        values = [(v,) for v in range(count)]
+        keys = [(num2words(i),) for i in range(count)]
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(SCHEMA)
+        cxt.inserts = [cxt.table.insert(k, v) for k, v in zip(keys, values)]
+        cxt.first = tc.Map(tc.After(cxt.inserts, cxt.table.page(10)))
+        cxt.second = cxt.table.page(10, cxt.first["cursor"])
+        cxt.third = cxt.table.page(10, cxt.second["cursor"])
+        cxt.result = (cxt.first["rows"], cxt.second["rows"], cxt.third)
+
+        first, second, third = self.host.post(ENDPOINT, cxt)
+        rows = sorted(list(k + v) for k, v in zip(keys, values))
+        self.assertEqual(first + second + third["rows"], rows)
+        self.assertIsNone(third["cursor"])
+
+    def testQuery(self):
+        count = 20
+        values = [(v,) for v in range(count)]
+        keys = [(num2words(i),) for i in range(count)]
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(SCHEMA)
+        cxt.inserts = [cxt.table.insert(k, v) for k, v in zip(keys, values)]
+        cxt.result = tc.After(cxt.inserts, cxt.table.query("SELECT name WHERE views >= 15 LIMIT 3").count())
+
+        result = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(result, 3)
+
+    def testSample(self):
+        count = 50
+        values = [(v,) for v in range(count)]
+        keys = [(num2words(i),) for i in range(count)]
+
+        cxt = tc.Context()
+        cxt.table = tc.table.Table(SCHEMA)
+        cxt.inserts = [cxt.table.insert(k, v) for k, v in zip(keys, values)]
+        cxt.result = tc.After(cxt.inserts, (
+            cxt.table.sample(10, 42),
+            cxt.table.sample(10, 42),
+            cxt.table.sample(count * 2),
+        ))
+
+        first, second, everything = self.host.post(ENDPOINT, cxt)
+        rows = sorted(list(k + v) for k, v in zip(keys, values))
+        self.assertEqual(len(first), 10)
+        self.assertEqual(first, second)
+        self.assertEqual(first, [row for row in rows if row in first])
+        self.assertEqual(everything, rows)
+
     def testSelect(self):
         count = 5
         values = [[v] for v in range(count)]
@@ -146,7 +349,7 @@ class ChainTests(PersistenceTest, unittest.TestCase):
             __uri__ = tc.URI(f"http://127.0.0.1:{DEFAULT_PORT}/test/table")
 
             def _configure(self):
-                self.table = tc.chain.Block(tc.table.Table(SCHEMA))
+                self.table = chain_type(tc.table.Table(SCHEMA))
 
             @tc.delete_method
             def truncate(self):
@@ -214,6 +417,11 @@ class ChainTests(PersistenceTest, unittest.TestCase):
                 count = hosts[i].get("/test/table/table/count")
                 self.assertEqual(n, count, f"host {i}")
 
+        for i in range(len(hosts)):
+            report = hosts[i].post("/test/table/verify")
+            self.assertTrue(report["consistent"], f"host {i}: {report}")
+            self.assertEqual(len(report["replicas"]), self.NUM_HOSTS - 1)
+
 
 class ErrorTest(unittest.TestCase):
     def setUp(self):
@@ -230,6 +438,302 @@ class ErrorTest(unittest.TestCase):
             tc.error.BadRequest,
             lambda: self.host.put("/test/table/table", "one", [1]))
 
+    def testErrorReport(self):
+        with self.assertRaises(tc.error.BadRequest) as context:
+            self.host.put("/test/table/table", "one", [1])
+
+        error = context.exception
+        self.assertEqual(error.code, "bad_request")
+        self.assertEqual(error.path, "/test/table/table")
+        self.assertTrue(error.txn_id)
+        self.assertTrue(error.message)
+        self.assertEqual(str(error), error.message)
+
+    def tearDown(self):
+        self.host.stop()
+
+
+class ConstraintTest(unittest.TestCase):
+    def setUp(self):
+        users = tc.table.Schema([tc.Column("name", tc.String, 512)], [tc.Column("email", tc.String, 512)])
+        users.create_index("email", ["email"], unique=True)
+
+        posts = tc.table.Schema([tc.Column("id", tc.UInt)], [tc.Column("author", tc.String, 512)])
+        posts.create_index("author", ["author"], references="/test/table/users")
+
+        class Persistent(tc.Cluster, metaclass=tc.Meta):
+            __uri__ = tc.URI(f"/test/table")
+
+            def _configure(self):
+                self.users = tc.chain.Block(tc.table.Table(users))
+                self.posts = tc.chain.Block(tc.table.Table(posts))
+
+        self.host = start_host("table_constraint", [Persistent])
+
+    def testUnique(self):
+        self.host.put("/test/table/users", "one", ["one@example.com"])
+        self.host.put("/test/table/users", "one", ["one@example.com"])
+
+        self.assertRaises(
+            tc.error.Conflict,
+            lambda: self.host.put("/test/table/users", "two", ["one@example.com"]))
+
+        self.host.put("/test/table/users", "two", ["two@example.com"])
+        self.assertEqual(self.host.get("/test/table/users/count"), 2)
+
+    def testReferences(self):
+        self.host.put("/test/table/users", "one", ["one@example.com"])
+        self.host.put("/test/table/posts", 1, ["one"])
+
+        self.assertRaises(
+            tc.error.Conflict,
+            lambda: self.host.put("/test/table/posts", 2, ["two"]))
+
+        self.assertEqual(self.host.get("/test/table/posts/count"), 1)
+
+    def tearDown(self):
+        self.host.stop()
+
+
+class CopyTest(unittest.TestCase):
+    def setUp(self):
+        class Persistent(tc.Cluster, metaclass=tc.Meta):
+            __uri__ = tc.URI(f"/test/table")
+
+            def _configure(self):
+                self.source = tc.chain.Block(tc.table.Table(SCHEMA))
+                self.backup = tc.chain.Block(tc.table.Table(SCHEMA))
+
+        self.host = start_host("table_copy", [Persistent])
+
+    def testCopyInto(self):
+        count = 10
+        for n in range(count):
+            self.host.put("/test/table/source", [num2words(n)], [n])
+
+        params = {"source": tc.URI("/test/table/source"), "target": tc.URI("/test/table/backup")}
+        self.assertIsNone(self.host.post("/state/collection/copy_from", params))
+        self.assertEqual(self.host.get("/test/table/backup/count"), count)
+        self.assertEqual(self.host.get("/test/table/backup", [num2words(1)]), [num2words(1), 1])
+
+    def tearDown(self):
+        self.host.stop()
+
+
+class HistoryTest(unittest.TestCase):
+    def setUp(self):
+        class Persistent(tc.Cluster, metaclass=tc.Meta):
+            __uri__ = tc.URI(f"/test/table")
+
+            def _configure(self):
+                self.table = tc.chain.Block(tc.table.Table(SCHEMA))
+
+        self.host = start_host("table_history", [Persistent])
+
+    def testRevert(self):
+        for n in range(3):
+            self.host.put("/test/table/table", [num2words(n)], [n])
+
+        history = self.host.get("/test/table/table/chain/history")
+        self.assertEqual(len(history), 3)
+
+        past = self.host.get("/test/table/table/chain/history", history[0])
+        self.assertEqual(past, expected(SCHEMA, [[num2words(0), 0]]))
+
+        self.host.put("/test/table/table/chain/history", None, history[0])
+        self.assertEqual(self.host.get("/test/table/table/count"), 1)
+        self.assertEqual(len(self.host.get("/test/table/table/chain/history")), 4)
+
+    def testChanges(self):
+        self.host.put("/test/table/table", ["one"], [1])
+        self.host.put("/test/table/table", ["two"], [2])
+        self.host.put("/test/table/table", ["one"], [3])
+        self.host.delete("/test/table/table", ["two"])
+
+        history = self.host.get("/test/table/table/chain/history")
+        changes = self.host.get("/test/table/table/chain/changes")
+        self.assertEqual(changes, [
+            [history[0], "insert", ["one"], [1]],
+            [history[1], "insert", ["two"], [2]],
+            [history[2], "update", ["one"], [3]],
+            [history[3], "delete", ["two"], None],
+        ])
+
+        changes = self.host.get("/test/table/table/chain/changes", history[1])
+        self.assertEqual([kind for (_, kind, _, _) in changes], ["update", "delete"])
+
+    def tearDown(self):
+        self.host.stop()
+
+
+class ViewTest(unittest.TestCase):
+    def setUp(self):
+        @tc.post_op
+        def by_views(name: tc.String, views: tc.UInt):
+            return tc.Map(views=views, count=1)
+
+        @tc.post_op
+        def popular(name: tc.String, views: tc.UInt):
+            return tc.If(views >= 2, tc.Map(name=name, views=views), None)
+
+        counts = tc.table.Schema([tc.Column("views", tc.UInt)], [tc.Column("count", tc.UInt)])
+
+        class Persistent(tc.Cluster, metaclass=tc.Meta):
+            __uri__ = tc.URI(f"/test/table")
+
+            def _configure(self):
+                self.table = tc.chain.Block(tc.table.Table(SCHEMA))
+                self.by_views = tc.table.View.define("table", counts, by_views, aggregate=True)
+                self.popular = tc.table.View.define("table", SCHEMA, popular)
+
+        self.host = start_host("table_view", [Persistent])
+
+    def testRefresh(self):
+        for name, views in [("one", 1), ("two", 2), ("three", 2)]:
+            self.host.put("/test/table/table", name, [views])
+
+        self.assertEqual(self.host.get("/test/table/by_views", 2), [2, 2])
+        self.assertEqual(self.host.get("/test/table/popular/count"), 2)
+
+        self.host.put("/test/table/table", "two", [1])
+        self.assertEqual(self.host.get("/test/table/by_views", 1), [1, 2])
+        self.assertEqual(self.host.get("/test/table/by_views", 2), [2, 1])
+        self.assertEqual(self.host.get("/test/table/popular/rows"), [["three", 2]])
+
+        self.host.delete("/test/table/table", "three")
+        self.assertEqual(self.host.get("/test/table/by_views/count"), 1)
+        self.assertEqual(self.host.get("/test/table/popular/count"), 0)
+
+        self.assertRaises(
+            tc.error.MethodNotAllowed,
+            lambda: self.host.put("/test/table/popular", "one", [5]))
+
+    def tearDown(self):
+        self.host.stop()
+
+
+class ColumnAccessTest(unittest.TestCase):
+    def setUp(self):
+        schema = tc.table.Schema(
+            [tc.Column("name", tc.String, 512)],
+            [tc.Column("views", tc.UInt), tc.Column("email", tc.String, 512)])
+
+        schema.restrict("views", "/test/table/views").restrict("email", "/test/table/pii", omit=True)
+
+        class Persistent(tc.Cluster, metaclass=tc.Meta):
+            __uri__ = tc.URI(f"/test/table")
+
+            def _configure(self):
+                self.table = tc.chain.Block(tc.table.Table(schema))
+
+        self.host = start_host("table_column_access", [Persistent])
+
+    def testMaskedRead(self):
+        self.host.put("/test/table/table", "one", [1, "one@example.com"])
+        self.host.put("/test/table/table", "two", [2, "two@example.com"])
+
+        self.assertEqual(self.host.get("/test/table/table/rows"), [["one", None], ["two", None]])
+        self.assertEqual(self.host.get("/test/table/table", "one"), ["one", None])
+
+        self.assertRaises(
+            tc.error.Unauthorized,
+            lambda: self.host.get("/test/table/table/order", ["views"]))
+
+        self.assertRaises(
+            tc.error.Unauthorized,
+            lambda: self.host.post("/test/table/table/rows", {"views": 1}))
+
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.get("/test/table", "table"))
+
+    def testReplicate(self):
+        self.host.put("/test/table/table", "one", [1, "one@example.com"])
+
+        # only a replica of the cluster can read its unmasked state
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.get("/test/table/replicate"))
+
+    def tearDown(self):
+        self.host.stop()
+
+
+class PrivacyTest(unittest.TestCase):
+    def setUp(self):
+        schema = tc.table.Schema([tc.Column("name", tc.String, 512)], [tc.Column("views", tc.UInt)])
+        schema.private("/test/table/rows", 1.0, 3, {"views": (0, 100)})
+
+        class Persistent(tc.Cluster, metaclass=tc.Meta):
+            __uri__ = tc.URI(f"/test/table")
+
+            def _configure(self):
+                self.table = tc.chain.Block(tc.table.Table(schema))
+
+        self.host = start_host("table_privacy", [Persistent])
+
+    def testAggregate(self):
+        for i in range(10):
+            self.host.put("/test/table/table", f"row{i}", [i * 10])
+
+        count = self.host.get("/test/table/table/count")
+        self.assertTrue(0 <= count < 40)
+
+        total = self.host.get("/test/table/table/sum", "views")
+        self.assertIsInstance(total, float)
+
+        mean = self.host.get("/test/table/table/mean", "views")
+        self.assertTrue(0 <= mean <= 100)
+
+        self.assertRaises(
+            tc.error.Unauthorized,
+            lambda: self.host.get("/test/table/table/count", {"name": "row1"}))
+
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.get("/test/table/table/rows"))
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.get("/test/table/table/sample", 5))
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.get("/test/table/table", "row1"))
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.get("/test/table", "table"))
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.get("/test/table/replicate"))
+
+    def tearDown(self):
+        self.host.stop()
+
+
+class RegistryAdmin(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/admin")
+
+    @tc.post_method
+    def publish(self, txn, compatibility: tc.String, schema: tc.Map):
+        @tc.post_op
+        def put(compatibility: tc.String, schema: tc.Map):
+            return tc.ref.Put(tc.URI("/schema/views"), compatibility, schema)
+
+        return self.grant("/host/admin", put, {"compatibility": compatibility, "schema": schema})
+
+
+class RegistryTest(unittest.TestCase):
+    def setUp(self):
+        flags = [f"--host_admin={tc.uri(RegistryAdmin)}"]
+        self.host = start_host("table_registry", [RegistryAdmin], flags=flags)
+
+    def testPublish(self):
+        v1 = tc.table.Schema([tc.Column("name", tc.String, 512)], [tc.Column("views", tc.UInt)])
+        v2 = tc.table.Schema(
+            [tc.Column("name", tc.String, 512)],
+            [tc.Column("views", tc.UInt), tc.Column("likes", tc.UInt)])
+
+        publish = lambda compatibility, schema: self.host.post(
+            "/test/admin/publish", {"compatibility": compatibility, "schema": schema})
+
+        # only a host admin can publish a schema
+        self.assertRaises(tc.error.Unauthorized, lambda: self.host.put("/schema/views", None, v1))
+
+        publish(None, v1)
+
+        # adding a column is forward- but not backward-compatible
+        self.assertRaises(tc.error.BadRequest, lambda: publish(None, v2))
+        publish("forward", v2)
+
+        self.assertEqual(self.host.get("/schema"), ["views"])
+        self.assertEqual(self.host.get("/schema/views/1"), tc.to_json(v1))
+        self.assertEqual(self.host.get("/schema/views"), tc.to_json(v2))
+
     def tearDown(self):
         self.host.stop()
 
@@ -0,0 +1,65 @@
+import requests
+import tinychain as tc
+import unittest
+
+from testutils import start_host
+
+ISSUER = "http://auth.example.com/team_a"
+SCHEMA = tc.table.Schema([tc.Column("name", tc.String, 512)], [tc.Column("views", tc.UInt)])
+
+
+class Private(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/team_a/app")
+
+    def _configure(self):
+        self.table = tc.chain.Block(tc.table.Table(SCHEMA))
+
+    @tc.get_method
+    def greet(self) -> tc.String:
+        return tc.String("hello")
+
+
+class Shared(tc.Cluster, metaclass=tc.Meta):
+    __uri__ = tc.URI("/test/shared")
+
+    def _configure(self):
+        self.table = tc.chain.Block(tc.table.Table(SCHEMA))
+
+
+class TenantTest(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        flags = [f"--tenant=/test/team_a={ISSUER}", "--graphql"]
+        cls.host = start_host("test_tenant", [Shared, Private], flags=flags)
+
+    def url(self, path):
+        return str(tc.uri(self.host)) + path
+
+    def testRoute(self):
+        self.assertEqual(self.host.get("/test/shared/table/count"), 0)
+
+        # a cluster in a tenant namespace does not exist to a request without the tenant's token
+        self.assertRaises(tc.error.NotFound, lambda: self.host.get("/test/team_a/app/greet"))
+        self.assertRaises(tc.error.NotFound, lambda: self.host.get("/test/team_a/app/table/count"))
+
+    def testOpenAPI(self):
+        document = self.host.get("/-/openapi.json")
+        self.assertNotIn("/test/team_a/app/greet", document["paths"])
+
+    def testGraphQL(self):
+        sdl = requests.get(self.url("/graphql")).text
+        self.assertIn("test_shared_table", sdl)
+        self.assertNotIn("test_team_a", sdl)
+
+        query = {"query": "{ test_team_a_app_table { name } }"}
+        response = requests.post(self.url("/graphql"), json=query).json()
+        self.assertIsNone(response["data"])
+        self.assertTrue(response["errors"])
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.host.stop()
+
+
+if __name__ == "__main__":
+    unittest.main()
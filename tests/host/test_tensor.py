@@ -37,6 +37,16 @@ class DenseTests(unittest.TestCase):
         expected = expect_dense(tc.I64, [2], np.arange(1, 11).reshape([2, 5])[1, 2:-1])
         self.assertEqual(actual, expected)
 
+    def testMaterialize(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([2, 5], 1, 11)
+        cxt.copy = cxt.tensor[1].materialize()
+        cxt.result = tc.After(cxt.copy[0].write(0), cxt.copy)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = expect_dense(tc.I64, [5], [0, 7, 8, 9, 10])
+        self.assertEqual(actual, expected)
+
     def testAssignSlice(self):
         cxt = tc.Context()
         cxt.big = tc.tensor.Dense.zeros([2, 2, 5])
@@ -51,6 +61,60 @@ class DenseTests(unittest.TestCase):
 
         self.assertEqual(actual, expected)
 
+    def testMaskSelect(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([2, 5], -5, 5)
+        cxt.result = cxt.tensor.mask_select(cxt.tensor > 0)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = expect_dense(tc.I64, [4], [1, 2, 3, 4])
+        self.assertEqual(actual, expected)
+
+    def testMaskAssign(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([2, 5], -5, 5)
+        cxt.result = tc.After(cxt.tensor.mask_assign(cxt.tensor < 0, 0), cxt.tensor)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = expect_dense(tc.I64, [2, 5], [0] * 5 + list(range(5)))
+        self.assertEqual(actual, expected)
+
+    def testAssignOps(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([2, 3], 1., 7.)
+        cxt.row = tc.tensor.Dense.load([3], tc.F64, [1., 2., 3.])
+        cxt.result = tc.After([
+            cxt.tensor.add_assign(cxt.row),
+            cxt.tensor.mul_assign(2),
+            cxt.tensor.sub_assign(tc.tensor.Dense.constant([2, 3], 2)),
+            cxt.tensor.div_assign(cxt.row),
+        ], cxt.tensor)
+
+        actual = self.host.post(ENDPOINT, cxt)
+
+        tensor = np.arange(1., 7.).reshape([2, 3])
+        row = np.array([1., 2., 3.])
+        tensor = (((tensor + row) * 2) - 2) / row
+        expected = expect_dense(tc.F64, [2, 3], tensor.flatten())
+        self.assertEqual(actual, expected)
+
+    def testAssignDivideByZero(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([3], 1., 4.)
+        cxt.result = tc.After(cxt.tensor.div_assign(tc.tensor.Dense.zeros([3])), cxt.tensor)
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
+
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([3], 1., 4.)
+        cxt.result = tc.After(cxt.tensor.div_assign(0), cxt.tensor)
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
+
+    def testAssignView(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([2, 3], 1., 7.)
+        cxt.result = tc.After(cxt.tensor[0].add_assign(1), cxt.tensor)
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.post(ENDPOINT, cxt))
+
     def testAdd(self):
         cxt = tc.Context()
         cxt.left = tc.tensor.Dense.arange([5, 2, 2], 1., 21.)
@@ -65,6 +129,16 @@ class DenseTests(unittest.TestCase):
 
         self.assertEqual(actual, expected)
 
+    def testAddMixedTypes(self):
+        cxt = tc.Context()
+        cxt.left = tc.tensor.Dense.load([3], tc.I64, [1, 2, 3])
+        cxt.right = tc.tensor.Dense.ones([3], tc.F32)
+        cxt.result = cxt.left + cxt.right
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = expect_dense(tc.F64, [3], [2., 3., 4.])
+        self.assertEqual(actual, expected)
+
     def testDiv(self):
         shape = [3]
 
@@ -139,6 +213,207 @@ class DenseTests(unittest.TestCase):
         actual = self.host.post(ENDPOINT, cxt)
         self.assertEqual(actual, [False, True, True, False, True])
 
+    def testCumsum(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([3, 4], 1, 13)
+        cxt.result = [cxt.tensor.cumsum(0), cxt.tensor.cumsum(1)]
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = np.arange(1, 13).reshape([3, 4])
+        self.assertEqual(actual, [
+            expect_dense(tc.I64, [3, 4], np.cumsum(expected, 0).flatten()),
+            expect_dense(tc.I64, [3, 4], np.cumsum(expected, 1).flatten()),
+        ])
+
+    def testCumprod(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([2, 3], 1, 7)
+        cxt.result = cxt.tensor.cumprod(1)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = np.cumprod(np.arange(1, 7).reshape([2, 3]), 1)
+        self.assertEqual(actual, expect_dense(tc.I64, [2, 3], expected.flatten()))
+
+    def testSort(self):
+        data = [3, 1, 2, 6, 5, 4]
+
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.load([2, 3], tc.I32, data)
+        cxt.result = [cxt.tensor.sort(), cxt.tensor.sort(0, False)]
+
+        actual = self.host.post(ENDPOINT, cxt)
+        matrix = np.array(data).reshape([2, 3])
+        self.assertEqual(actual, [
+            [
+                expect_dense(tc.I32, [2, 3], np.sort(matrix, 1).flatten()),
+                expect_dense(tc.U64, [2, 3], np.argsort(matrix, 1).flatten()),
+            ],
+            [
+                expect_dense(tc.I32, [2, 3], [6, 5, 4, 3, 1, 2]),
+                expect_dense(tc.U64, [2, 3], [1, 1, 1, 0, 0, 0]),
+            ],
+        ])
+
+    def testTopK(self):
+        data = [3, 1, 2, 6, 5, 4]
+
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.load([2, 3], tc.I32, data)
+        cxt.result = cxt.tensor.topk(2)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, [
+            expect_dense(tc.I32, [2, 2], [3, 2, 6, 5]),
+            expect_dense(tc.U64, [2, 2], [0, 2, 0, 1]),
+        ])
+
+    def testIm2Col1D(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([2, 7, 3], 0, 42)
+        cxt.result = cxt.tensor.im2col([3], 2)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        x = np.arange(42).reshape([2, 7, 3])
+        expected = np.stack([x[:, p:p + 3] for p in range(0, 5, 2)], 1)
+        self.assertEqual(actual, expect_dense(tc.I64, [2, 3, 3, 3], expected.flatten()))
+
+    def testIm2Col2D(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.arange([1, 4, 5, 2], 0, 40)
+        cxt.result = cxt.tensor.im2col([2, 3])
+
+        actual = self.host.post(ENDPOINT, cxt)
+        x = np.arange(40).reshape([1, 4, 5, 2])
+        expected = np.stack([
+            np.stack([x[:, i:i + 2, j:j + 3] for j in range(3)], 1)
+            for i in range(3)], 1)
+
+        self.assertEqual(actual, expect_dense(tc.I64, [1, 3, 3, 2, 3, 2], expected.flatten()))
+
+    def testSoftmax(self):
+        x = np.array([[1., 2., 3.], [-1., 0., 4.]])
+
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.load([2, 3], tc.F64, x.flatten().tolist())
+        cxt.result = [cxt.tensor.softmax(), cxt.tensor.softmax(0)]
+
+        actual = self.host.post(ENDPOINT, cxt)
+
+        for axis, result in zip([1, 0], actual):
+            exps = np.exp(x - x.max(axis, keepdims=True))
+            expected = exps / exps.sum(axis, keepdims=True)
+            self.assertEqual(list(expected.shape), result[tc.uri(tc.tensor.Dense)][0][0])
+            self.assertTrue(np.allclose(expected.flatten(), result[tc.uri(tc.tensor.Dense)][1]))
+
+    def testBatchNorm(self):
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.load([2, 2], tc.F32, [1, 2, 3, 4])
+        cxt.mean = tc.tensor.Dense.load([2], tc.F32, [2, 3])
+        cxt.var = tc.tensor.Dense.load([2], tc.F32, [1, 1])
+        cxt.gamma = tc.tensor.Dense.load([2], tc.F32, [2, 1])
+        cxt.beta = tc.tensor.Dense.load([2], tc.F32, [0, 1])
+        cxt.result = cxt.tensor.batch_norm(cxt.mean, cxt.var, cxt.gamma, cxt.beta, epsilon=0)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, expect_dense(tc.F32, [2, 2], [-2, 0, 2, 2]))
+
+    def testInverse(self):
+        x = np.array([[4., 7.], [2., 6.]])
+
+        cxt = tc.Context()
+        cxt.matrix = tc.tensor.Dense.load([2, 2], tc.F64, x.flatten().tolist())
+        cxt.result = [cxt.matrix.inv(), cxt.matrix.det()]
+
+        inverse, det = self.host.post(ENDPOINT, cxt)
+        self.assertEqual([2, 2], inverse[tc.uri(tc.tensor.Dense)][0][0])
+        self.assertTrue(np.allclose(np.linalg.inv(x).flatten(), inverse[tc.uri(tc.tensor.Dense)][1]))
+        self.assertAlmostEqual(np.linalg.det(x), det)
+
+    def testSolve(self):
+        a = np.array([[3., 1.], [1., 2.]])
+        b = np.array([9., 8.])
+
+        cxt = tc.Context()
+        cxt.a = tc.tensor.Dense.load([2, 2], tc.F64, a.flatten().tolist())
+        cxt.b = tc.tensor.Dense.load([2], tc.F64, b.tolist())
+        cxt.result = tc.tensor.solve(cxt.a, cxt.b)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual([2], actual[tc.uri(tc.tensor.Dense)][0][0])
+        self.assertTrue(np.allclose(np.linalg.solve(a, b), actual[tc.uri(tc.tensor.Dense)][1]))
+
+    def testCholesky(self):
+        x = np.array([[4., 12., -16.], [12., 37., -43.], [-16., -43., 98.]])
+
+        cxt = tc.Context()
+        cxt.matrix = tc.tensor.Dense.load([3, 3], tc.F64, x.flatten().tolist())
+        cxt.result = cxt.matrix.cholesky()
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual([3, 3], actual[tc.uri(tc.tensor.Dense)][0][0])
+        self.assertTrue(np.allclose(np.linalg.cholesky(x).flatten(), actual[tc.uri(tc.tensor.Dense)][1]))
+
+    def testQR(self):
+        x = np.array([[1., 2.], [3., 4.], [5., 6.]])
+
+        cxt = tc.Context()
+        cxt.matrix = tc.tensor.Dense.load([3, 2], tc.F64, x.flatten().tolist())
+        cxt.result = cxt.matrix.qr()
+
+        q, r = (load_dense(t) for t in self.host.post(ENDPOINT, cxt))
+        self.assertEqual(q.shape, (3, 3))
+        self.assertEqual(r.shape, (3, 2))
+        self.assertTrue(np.allclose(q @ r, x))
+        self.assertTrue(np.allclose(q.T @ q, np.eye(3)))
+        self.assertTrue(np.allclose(np.tril(r, -1), 0))
+
+    def testSVD(self):
+        x = np.array([[1., 2.], [3., 4.], [5., 6.]])
+
+        cxt = tc.Context()
+        cxt.matrix = tc.tensor.Dense.load([3, 2], tc.F64, x.flatten().tolist())
+        cxt.result = cxt.matrix.svd()
+
+        u, s, vt = (load_dense(t) for t in self.host.post(ENDPOINT, cxt))
+        self.assertEqual(u.shape, (3, 3))
+        self.assertEqual(vt.shape, (2, 2))
+        self.assertTrue(np.allclose(s, np.linalg.svd(x, compute_uv=False)))
+        self.assertTrue(np.allclose((u[:, :2] * s) @ vt, x))
+
+    def testNorm(self):
+        x = np.array([[3., -4.], [-1., 2.], [0., 5.]])
+
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Dense.load([3, 2], tc.F64, x.flatten().tolist())
+        cxt.result = [
+            cxt.tensor.norm(),
+            cxt.tensor.norm(1, 0),
+            cxt.tensor.norm(2, 1),
+            cxt.tensor.norm("inf", 1),
+        ]
+
+        l2_all, l1, l2, inf = self.host.post(ENDPOINT, cxt)
+        self.assertAlmostEqual(l2_all, np.linalg.norm(x))
+        self.assertTrue(np.allclose(load_dense(l1), np.abs(x).sum(0)))
+        self.assertTrue(np.allclose(load_dense(l2), np.linalg.norm(x, axis=1)))
+        self.assertTrue(np.allclose(load_dense(inf), np.abs(x).max(1)))
+
+    def testCDist(self):
+        a = np.array([[0., 0.], [1., 1.], [2., 0.]])
+        b = np.array([[0., 1.], [3., 4.]])
+
+        cxt = tc.Context()
+        cxt.a = tc.tensor.Dense.load([3, 2], tc.F64, a.flatten().tolist())
+        cxt.b = tc.tensor.Dense.load([2, 2], tc.F64, b.flatten().tolist())
+        cxt.result = [tc.tensor.cdist(cxt.a, cxt.b, metric) for metric in ["euclidean", "cityblock", "chebyshev"]]
+
+        actual = [load_dense(result) for result in self.host.post(ENDPOINT, cxt)]
+        delta = a[:, np.newaxis, :] - b[np.newaxis, :, :]
+        expected = [np.sqrt((delta ** 2).sum(2)), np.abs(delta).sum(2), np.abs(delta).max(2)]
+        for actual, expected in zip(actual, expected):
+            self.assertEqual(actual.shape, (3, 2))
+            self.assertTrue(np.allclose(actual, expected))
+
     def testProduct(self):
         shape = [2, 3, 4]
         axis = 1
@@ -259,6 +534,17 @@ class SparseTests(unittest.TestCase):
         expected = expect_sparse(tc.F32, shape, [[[0, 2], 1], [[0, 3], 1], [[1, 2], 1], [[1, 3], 1]])
         self.assertEqual(actual, expected)
 
+    def testDensity(self):
+        shape = [2, 5]
+
+        cxt = tc.Context()
+        cxt.tensor = tc.tensor.Sparse.zeros(shape)
+        cxt.result = tc.After(cxt.tensor[:, 2:-1].write(1), [
+            cxt.tensor.filled_count(), cxt.tensor.density(), cxt.tensor.filled_count(0)])
+
+        actual = self.host.post(ENDPOINT, cxt)
+        self.assertEqual(actual, [4, 0.4, 2])
+
     def testAdd(self):
         shape = [5, 2, 3]
 
@@ -414,6 +700,37 @@ class TensorTests(unittest.TestCase):
     def setUpClass(cls):
         cls.host = start_host("test_tensor", cache_size="1G")
 
+    def testHistogram(self):
+        x = np.arange(10)
+
+        cxt = tc.Context()
+        cxt.dense = tc.tensor.Dense.load([10], tc.I32, x.tolist())
+        cxt.sparse = tc.tensor.Sparse.zeros([2, 5], tc.F32)
+        cxt.result = tc.After(cxt.sparse[1, 4].write(8), [cxt.dense.histogram(5), cxt.sparse.histogram(4)])
+
+        dense, sparse = self.host.post(ENDPOINT, cxt)
+
+        counts, edges = np.histogram(x, 5)
+        self.assertEqual(dense, [counts.tolist(), edges.tolist()])
+        self.assertEqual(sparse, [[9, 0, 0, 1], [0, 2, 4, 6, 8]])
+
+    def testQuantile(self):
+        x = np.random.random(1000)
+
+        cxt = tc.Context()
+        cxt.dense = tc.tensor.Dense.load([1000], tc.F64, x.tolist())
+        cxt.sparse = tc.tensor.Sparse.zeros([10, 10], tc.F32)
+        cxt.result = tc.After(cxt.sparse[0].write(1), [cxt.dense.quantile([0.1, 0.5, 0.9]), cxt.sparse.quantile(0.5)])
+
+        dense, sparse = self.host.post(ENDPOINT, cxt)
+        self.assertTrue(np.allclose(dense, np.quantile(x, [0.1, 0.5, 0.9]), atol=0.02))
+        self.assertEqual(sparse, 0)
+
+    def testBackend(self):
+        backend = self.host.get("/host/backend")
+        self.assertIn(backend["backend"], backend["available"])
+        self.assertEqual(backend["device"], 0)
+
     def testAdd(self):
         cxt = tc.Context()
         cxt.dense = tc.tensor.Dense.arange([3, 5, 2], 0, 30)
@@ -489,6 +806,95 @@ class TensorTests(unittest.TestCase):
         expected = expect_sparse(tc.I32, [3, 3], matrix)
         self.assertEqual(actual, expected)
 
+    def testWhere(self):
+        cxt = tc.Context()
+        cxt.dense = tc.tensor.Dense.arange([2, 3], 0, 6)
+        cxt.sparse = tc.tensor.Sparse.zeros([3], tc.I64)
+        cxt.result = tc.After(
+            cxt.sparse[1].write(-1),
+            tc.tensor.where(cxt.dense > 2, cxt.dense, cxt.sparse))
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = np.where(np.arange(0, 6).reshape([2, 3]) > 2, np.arange(0, 6).reshape([2, 3]), [0, -1, 0])
+        self.assertEqual(actual, expect_dense(tc.I64, [2, 3], expected.flatten()))
+
+    def testWhereSparse(self):
+        cxt = tc.Context()
+        cxt.cond = tc.tensor.Sparse.zeros([2, 3], tc.Bool)
+        cxt.then = tc.tensor.Sparse.zeros([2, 3], tc.I64)
+        cxt.or_else = tc.tensor.Sparse.zeros([2, 3], tc.I64)
+        cxt.result = tc.After(
+            [cxt.cond[0].write(True), cxt.then[:, 0].write(2), cxt.or_else[:, 1].write(3)],
+            tc.tensor.where(cxt.cond, cxt.then, cxt.or_else))
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = np.zeros([2, 3], np.int64)
+        expected[0, 0] = 2
+        expected[1, 1] = 3
+        self.assertEqual(actual, expect_sparse(tc.I64, [2, 3], expected))
+
+    def testBackward(self):
+        x = np.array([[1, 2], [3, 4]])
+        w = np.array([0.5, -1])
+
+        cxt = tc.Context()
+        cxt.x = tc.tensor.Dense.load([2, 2], tc.F32, x.flatten().tolist())
+        cxt.w = tc.tensor.watch(tc.tensor.Dense.load([2], tc.F32, w.tolist()))
+        cxt.y = cxt.x * cxt.w
+        cxt.z = cxt.y * cxt.y
+        cxt.loss = cxt.z.sum()
+        cxt.result = tc.tensor.backward(cxt.loss)
+
+        actual = self.host.post(ENDPOINT, cxt)
+        expected = 2 * w * (x * x).sum(0)
+        self.assertEqual(actual, {"w": expect_dense(tc.F32, [2], expected.tolist())})
+
+    @classmethod
+    def tearDownClass(cls):
+        cls.host.stop()
+
+
+class NumpyEncodingTests(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        class Persistent(tc.Cluster, metaclass=tc.Meta):
+            __uri__ = tc.URI("/test/tensor")
+
+            def _configure(self):
+                schema = tc.tensor.Schema([2, 3], tc.F32)
+                self.dense = tc.chain.Sync(tc.tensor.Dense(schema))
+                self.sparse = tc.chain.Sync(tc.tensor.Sparse(schema))
+
+        cls.host = start_host("test_tensor_numpy", [Persistent])
+
+    def testDense(self):
+        expected = np.arange(6, dtype=np.float32).reshape([2, 3])
+        self.host.put_array("/test/tensor/dense", None, expected)
+
+        actual = self.host.get_array("/test/tensor/dense")
+        self.assertEqual(actual.dtype, np.float32)
+        self.assertTrue(np.array_equal(actual, expected))
+
+        actual = self.host.get("/test/tensor/dense")
+        self.assertEqual(actual, expect_dense(tc.F32, [2, 3], list(range(6))))
+
+    def testSparse(self):
+        coords = np.array([[0, 1], [2, 0]])
+        data = np.array([1., 2.], np.float32)
+        self.host.put_array("/test/tensor/sparse", None, {"coords": coords, "data": data, "shape": np.array([2, 3])})
+
+        actual = self.host.get_array("/test/tensor/sparse", sparse=True)
+        self.assertTrue(np.array_equal(actual["coords"], coords))
+        self.assertTrue(np.array_equal(actual["data"], data))
+        self.assertEqual(list(actual["shape"]), [2, 3])
+        self.assertEqual(actual["fill_value"], 0)
+
+        actual = self.host.get("/test/tensor/sparse")
+        self.assertEqual(actual, expect_sparse(tc.F32, [2, 3], [[[0, 2], 1], [[1, 0], 2]]))
+
+    def testNotATensor(self):
+        self.assertRaises(tc.error.BadRequest, lambda: self.host.get_array("/test/tensor/dense/shape"))
+
     @classmethod
     def tearDownClass(cls):
         cls.host.stop()
@@ -557,6 +963,18 @@ class ChainTests(PersistenceTest, unittest.TestCase):
             actual = host.get("/test/tensor/eq")
             self.assertEqual(actual, eq)
 
+        # a restarted host should load its dense tensor from disk
+        hosts[3].stop()
+        hosts[3].start()
+
+        actual = hosts[3].get("/test/tensor/dense")
+        self.assertEqual(actual, dense)
+
+
+def load_dense(as_json):
+    (shape, _dtype), data = as_json[str(tc.uri(tc.tensor.Dense))]
+    return np.array(data).reshape(shape)
+
 
 def expect_dense(dtype, shape, flat):
     return {
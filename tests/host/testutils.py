@@ -17,7 +17,8 @@ class Process(tc.host.Local.Process):
     ADDRESS = "127.0.0.1"
 
     def __init__(self, path, workspace, force_create=False,
-                 data_dir=None, clusters=[], port=DEFAULT_PORT, log_level="warn", cache_size="1G", request_ttl="30"):
+                 data_dir=None, clusters=[], port=DEFAULT_PORT, log_level="warn", cache_size="1G", request_ttl="30",
+                 flags=[]):
 
         print(f"start host process on port {port}")
 
@@ -47,6 +48,7 @@ class Process(tc.host.Local.Process):
             args.append(f"--data_dir={data_dir}")
 
         args.extend([f"--cluster={cluster}" for cluster in clusters])
+        args.extend(flags)
 
         self._args = args
 
@@ -85,7 +87,7 @@ class Process(tc.host.Local.Process):
             self.stop()
 
 
-def start_host(name, clusters=[], overwrite=True, host_uri=None, cache_size="5K", wait_time=1, timeout=30):
+def start_host(name, clusters=[], overwrite=True, host_uri=None, cache_size="5K", wait_time=1, timeout=30, flags=[]):
     if not os.path.isfile(TC_PATH):
         raise RuntimeError(f"invalid executable path: {TC_PATH}")
 
@@ -116,7 +118,8 @@ def start_host(name, clusters=[], overwrite=True, host_uri=None, cache_size="5K"
         log_level="debug",
         cache_size=cache_size,
         force_create=True,
-        request_ttl=timeout)
+        request_ttl=timeout,
+        flags=flags)
 
     process.start(wait_time)
     return tc.host.Local(process, f"http://{process.ADDRESS}:{port}")